@@ -0,0 +1,91 @@
+//! Benchmark for many small `parse_lines` calls
+//!
+//! Log sources such as CloudWatch deliver a few lines at a time, so callers
+//! parse many short batches. Parsers share regexes compiled once per process,
+//! which makes creating a parser per batch cheap; reusing one parser also
+//! keeps its normalizer. For comparison the benchmark compiles the nine
+//! patterns each `TextLogParser` used to compile when it was created.
+//!
+//! Run with `cargo bench --bench parse_lines`. Set `PG_LOGSTATS_BENCH_CALLS`
+//! to change the number of calls.
+
+use pg_logstats::TextLogParser;
+use regex::Regex;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CALLS: usize = 20_000;
+
+/// A statement and its duration, the shape of a typical small batch
+fn batch(call: usize) -> Vec<String> {
+    vec![
+        format!(
+            "2024-08-15 10:30:15.123 UTC [{}] app@orders api: LOG:  statement: SELECT * FROM orders WHERE id = {}",
+            10_000 + call % 100,
+            call
+        ),
+        format!(
+            "2024-08-15 10:30:15.200 UTC [{}] app@orders api: LOG:  duration: 2.500 ms",
+            10_000 + call % 100
+        ),
+    ]
+}
+
+/// Compile patterns of the size and number a text parser needs
+fn compile_parser_patterns() -> Vec<Regex> {
+    [
+        r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?) ([A-Za-z0-9_+\-:/]+) \[(\d+)\] ([^@]+)@([^ ]+) ([^:]+): (\w+):\s*(.+)$",
+        r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?)(?: ([^:]+))?:(.*):([^@]+)@([^:]+):\[(\d+)\]:(\w+):\s*(.+)$",
+        r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?) ([A-Za-z0-9_+\-:/]+) \[(\d+)\]:? \[(\d+)(?:-(\d+))?\] ?(.*)$",
+        r"^(?:(.*?)\s+)?(DEBUG[1-5]?|INFO|NOTICE|WARNING|ERROR|LOG|FATAL|PANIC|STATEMENT|DETAIL|HINT|CONTEXT|LOCATION):\s+(.*)$",
+        r"duration:\s*([\d.]+)\s*ms",
+        r"^duration:\s*([\d.]+\s*(?:ms|s|min))\s+(?:statement|execute\s+[^:]+):\s*(.+)$",
+        r"^duration:\s*[\d.]+\s*(?:ms|s|min)\s+(?:parse|bind)\s+[^:]+:",
+        r"^execute\s+[^:]+:\s*(.+)$",
+        r"\$(\d+)",
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+}
+
+fn time(calls: usize, mut call: impl FnMut(usize)) -> Duration {
+    let start = Instant::now();
+    for index in 0..calls {
+        call(index);
+    }
+    start.elapsed()
+}
+
+fn report(label: &str, calls: usize, elapsed: Duration) {
+    println!(
+        "{label:<36} {:>10.2} ms total {:>10.2} us/call",
+        elapsed.as_secs_f64() * 1000.0,
+        elapsed.as_secs_f64() * 1_000_000.0 / calls as f64
+    );
+}
+
+fn main() {
+    let calls = std::env::var("PG_LOGSTATS_BENCH_CALLS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_CALLS);
+    let batches: Vec<_> = (0..calls).map(batch).collect();
+
+    println!("Parsing {calls} batches of 2 lines...");
+    let mut parser = TextLogParser::new();
+    let reused = time(calls, |index| {
+        black_box(parser.parse_lines(&batches[index]).unwrap());
+    });
+    let fresh = time(calls, |index| {
+        black_box(TextLogParser::new().parse_lines(&batches[index]).unwrap());
+    });
+    let compiled = time(calls, |index| {
+        black_box(compile_parser_patterns());
+        black_box(TextLogParser::new().parse_lines(&batches[index]).unwrap());
+    });
+
+    report("reused parser", calls, reused);
+    report("new parser per call", calls, fresh);
+    report("new parser compiling its patterns", calls, compiled);
+}
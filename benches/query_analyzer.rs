@@ -0,0 +1,202 @@
+//! Allocation benchmark for `QueryAnalyzer::analyze_events`
+//!
+//! Runs the analyzer over a synthetic dataset of 1M statements (500 distinct
+//! query shapes) and reports allocation counts, bytes allocated, and peak
+//! live heap measured with a counting global allocator. For comparison it also
+//! runs the previous tracking strategy, which cloned the normalized SQL into
+//! the frequency map and an unbounded slow-query list for every execution.
+//! Both figures include correlation, which is reported on its own as well and
+//! dominates peak heap.
+//!
+//! Run with `cargo bench --bench query_analyzer`. Set `PG_LOGSTATS_BENCH_ENTRIES`
+//! to change the dataset size.
+
+use chrono::{Duration, TimeZone, Utc};
+use pg_logstats::{
+    normalize_log_entries, Correlator, EventSourceKind, LogEntry, LogLevel, NormalizedEvent,
+    ProcessOrderCorrelator, Query, QueryAnalyzer,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const DEFAULT_ENTRIES: usize = 1_000_000;
+const QUERY_SHAPES: usize = 500;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+struct Measurement {
+    allocations: usize,
+    allocated_bytes: usize,
+    peak_extra_bytes: usize,
+    elapsed_ms: f64,
+}
+
+fn measure<T>(f: impl FnOnce() -> T) -> (T, Measurement) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let live_before = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_LIVE_BYTES.store(live_before, Ordering::Relaxed);
+
+    let start = Instant::now();
+    let value = f();
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let measurement = Measurement {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+        peak_extra_bytes: PEAK_LIVE_BYTES.load(Ordering::Relaxed) - live_before,
+        elapsed_ms,
+    };
+    (value, measurement)
+}
+
+fn report(label: &str, measurement: &Measurement) {
+    println!(
+        "{:<28} {:>12} allocs {:>10.1} MiB allocated {:>10.1} MiB peak {:>9.1} ms",
+        label,
+        measurement.allocations,
+        measurement.allocated_bytes as f64 / (1024.0 * 1024.0),
+        measurement.peak_extra_bytes as f64 / (1024.0 * 1024.0),
+        measurement.elapsed_ms,
+    );
+}
+
+fn synthetic_events(count: usize) -> Vec<NormalizedEvent> {
+    let shapes: Vec<(String, Option<Vec<Query>>)> = (0..QUERY_SHAPES)
+        .map(|shape| {
+            let sql = format!(
+                "SELECT o.id, o.total, c.name FROM orders_{shape} o JOIN customers c ON c.id = o.customer_id WHERE o.status = 'open' AND o.customer_id = {shape}"
+            );
+            let queries = Query::from_sql(&sql).ok();
+            (sql, queries)
+        })
+        .collect();
+
+    let start = Utc.with_ymd_and_hms(2024, 8, 15, 0, 0, 0).unwrap();
+    let entries: Vec<LogEntry> = (0..count)
+        .map(|i| {
+            let (sql, queries) = &shapes[(i * 7919) % QUERY_SHAPES];
+            LogEntry::builder(
+                start + Duration::milliseconds(i as i64),
+                (10_000 + i % 64).to_string(),
+                LogLevel::Statement,
+                format!("statement: {sql}"),
+            )
+            .with_queries(queries.clone())
+            .with_duration(Some(((i * 31) % 5_000) as f64 / 2.0))
+            .build()
+            .unwrap()
+        })
+        .collect();
+
+    normalize_log_entries(&entries, EventSourceKind::Stderr)
+}
+
+/// Slowest and most frequent queries
+type TrackedQueries = (Vec<(String, f64)>, Vec<(String, u64)>);
+
+/// The tracking strategy `analyze_events` used before query interning.
+fn cloned_string_tracking(
+    events: &[NormalizedEvent],
+    threshold: f64,
+    max_slow: usize,
+    max_frequent: usize,
+) -> TrackedQueries {
+    let mut query_counts: HashMap<String, u64> = HashMap::new();
+    let mut slow_queries = Vec::new();
+
+    for execution in &ProcessOrderCorrelator.correlate(events) {
+        let duration = execution.duration_ms.unwrap_or(0.0);
+        let normalized_concat = execution.query_family.normalized_sql.clone();
+        for query in &execution.queries {
+            *query_counts
+                .entry(query.normalized_query.clone())
+                .or_insert(0) += 1;
+        }
+        if duration > threshold {
+            slow_queries.push((normalized_concat.clone(), duration));
+        }
+    }
+
+    slow_queries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    slow_queries.truncate(max_slow);
+    let mut frequent: Vec<_> = query_counts.into_iter().collect();
+    frequent.sort_by_key(|query| Reverse(query.1));
+    frequent.truncate(max_frequent);
+    (slow_queries, frequent)
+}
+
+fn main() {
+    let count = std::env::var("PG_LOGSTATS_BENCH_ENTRIES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_ENTRIES);
+
+    println!("Building {count} synthetic statements ({QUERY_SHAPES} query shapes)...");
+    let events = synthetic_events(count);
+    let analyzer = QueryAnalyzer::new();
+
+    let (correlated, correlate_only) = measure(|| ProcessOrderCorrelator.correlate(&events));
+    drop(correlated);
+    let (result, interned) = measure(|| analyzer.analyze_events(&events).unwrap());
+    let (_, cloned) = measure(|| {
+        cloned_string_tracking(
+            &events,
+            analyzer.slow_query_threshold(),
+            analyzer.max_slow_queries(),
+            analyzer.max_frequent_queries(),
+        )
+    });
+
+    report("correlation only", &correlate_only);
+    report("analyze_events (interned)", &interned);
+    report("cloned-string tracking", &cloned);
+    println!(
+        "analyzed {} queries; {} slowest, {} most frequent",
+        result.total_queries,
+        result.slowest_queries.len(),
+        result.most_frequent_queries.len()
+    );
+}
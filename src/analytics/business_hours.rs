@@ -0,0 +1,526 @@
+//! Business hours versus off-hours breakdown
+//!
+//! A [`BusinessHours`] window such as `Mon-Fri 09:00-18:00` splits activity
+//! into business hours and off-hours, judged in a [`ReportTimezone`], since
+//! log timestamps are kept in UTC. An IANA zone such as `Europe/Berlin` gets
+//! its daylight saving offset per entry. The same timezone decides the
+//! weekday of each entry for the per-weekday table.
+
+use crate::timezone::{parse_timezone, Tz};
+use crate::{PgLogstatsError, Result};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Offset, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Three-letter weekday names, Monday first
+pub const WEEKDAY_ABBREVIATIONS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Working days and daily working hours, e.g. `Mon-Fri 09:00-18:00`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusinessHours {
+    /// Working days, Monday first
+    pub days: [bool; 7],
+    /// Start of the working day, inclusive
+    pub start: NaiveTime,
+    /// End of the working day, exclusive
+    pub end: NaiveTime,
+}
+
+impl Default for BusinessHours {
+    /// Monday to Friday, 09:00 to 18:00
+    fn default() -> Self {
+        Self {
+            days: [true, true, true, true, true, false, false],
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+        }
+    }
+}
+
+impl BusinessHours {
+    /// Whether `timestamp`, seen in the timezone `offset`, falls inside the
+    /// window
+    pub fn contains(&self, timestamp: DateTime<Utc>, offset: FixedOffset) -> bool {
+        let local = timestamp.with_timezone(&offset);
+        let time = local.time();
+        self.days[local.weekday().num_days_from_monday() as usize]
+            && time >= self.start
+            && time < self.end
+    }
+}
+
+fn business_hours_error(spec: &str, problem: &str) -> PgLogstatsError {
+    PgLogstatsError::Configuration {
+        message: format!(
+            "Invalid business hours '{}': {}; expected e.g. 'Mon-Fri 09:00-18:00'",
+            spec, problem
+        ),
+        field: Some("business_hours".to_string()),
+    }
+}
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Monday-first index of a weekday name such as `Mon` or `monday`
+fn weekday_index(name: &str) -> Option<usize> {
+    let name = name.to_ascii_lowercase();
+    (0..7).find(|&day| {
+        name == WEEKDAY_NAMES[day] || name.eq_ignore_ascii_case(WEEKDAY_ABBREVIATIONS[day])
+    })
+}
+
+impl std::str::FromStr for BusinessHours {
+    type Err = PgLogstatsError;
+
+    /// Parse `DAYS HH:MM-HH:MM`, where `DAYS` lists weekdays and ranges of
+    /// them separated by commas, e.g. `Mon-Fri` or `Mon-Wed,Sat`
+    fn from_str(s: &str) -> Result<Self> {
+        let spec = s.trim();
+        let (days_spec, hours_spec) = spec
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| business_hours_error(spec, "missing the days or the hours"))?;
+
+        let mut days = [false; 7];
+        for part in days_spec.split(',') {
+            let (first, last) = part.split_once('-').unwrap_or((part, part));
+            let unknown =
+                |name: &str| business_hours_error(spec, &format!("unknown day '{}'", name));
+            let first = weekday_index(first.trim()).ok_or_else(|| unknown(first))?;
+            let last = weekday_index(last.trim()).ok_or_else(|| unknown(last))?;
+            if last < first {
+                return Err(business_hours_error(
+                    spec,
+                    &format!("day range '{}' runs backwards", part),
+                ));
+            }
+            days[first..=last].iter_mut().for_each(|day| *day = true);
+        }
+
+        let (start, end) = hours_spec
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| business_hours_error(spec, "hours must be a range HH:MM-HH:MM"))?;
+        let parse_time = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), TIME_FORMAT)
+                .map_err(|_| business_hours_error(spec, &format!("invalid time '{}'", time)))
+        };
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if end <= start {
+            return Err(business_hours_error(
+                spec,
+                "the hours must end after they start",
+            ));
+        }
+
+        Ok(Self { days, start, end })
+    }
+}
+
+impl fmt::Display for BusinessHours {
+    /// The window in the form [`FromStr`](std::str::FromStr) accepts, with
+    /// consecutive days joined into ranges
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ranges = Vec::new();
+        let mut day = 0;
+        while day < 7 {
+            if !self.days[day] {
+                day += 1;
+                continue;
+            }
+            let first = day;
+            while day + 1 < 7 && self.days[day + 1] {
+                day += 1;
+            }
+            ranges.push(if first == day {
+                WEEKDAY_ABBREVIATIONS[first].to_string()
+            } else {
+                format!(
+                    "{}-{}",
+                    WEEKDAY_ABBREVIATIONS[first], WEEKDAY_ABBREVIATIONS[day]
+                )
+            });
+            day += 1;
+        }
+        write!(
+            f,
+            "{} {}-{}",
+            ranges.join(","),
+            self.start.format(TIME_FORMAT),
+            self.end.format(TIME_FORMAT)
+        )
+    }
+}
+
+/// Timezone business hours are judged in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportTimezone {
+    /// An IANA zone, whose offset follows daylight saving time
+    Named(Tz),
+    /// A fixed UTC offset
+    Fixed(FixedOffset),
+}
+
+impl Default for ReportTimezone {
+    /// UTC
+    fn default() -> Self {
+        ReportTimezone::Fixed(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+impl ReportTimezone {
+    /// UTC offset of the timezone at `timestamp`
+    pub fn offset_at(&self, timestamp: DateTime<Utc>) -> FixedOffset {
+        match self {
+            ReportTimezone::Named(timezone) => timezone
+                .offset_from_utc_datetime(&timestamp.naive_utc())
+                .fix(),
+            ReportTimezone::Fixed(offset) => *offset,
+        }
+    }
+}
+
+impl fmt::Display for ReportTimezone {
+    /// The IANA name, or the offset such as `+02:00`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportTimezone::Named(timezone) => f.write_str(timezone.name()),
+            ReportTimezone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl std::str::FromStr for ReportTimezone {
+    type Err = PgLogstatsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_report_timezone(s)
+    }
+}
+
+/// Parse a reporting timezone: `UTC`, a fixed offset as [`parse_utc_offset`]
+/// takes it, or an IANA name such as `Europe/Berlin`
+pub fn parse_report_timezone(s: &str) -> Result<ReportTimezone> {
+    parse_utc_offset(s)
+        .map(ReportTimezone::Fixed)
+        .or_else(|_| parse_timezone(s).map(ReportTimezone::Named))
+        .map_err(|_| PgLogstatsError::Configuration {
+            message: format!(
+                "Invalid report timezone '{}': expected UTC, an IANA name such as \
+                 Europe/Berlin, or an offset such as +02:00",
+                s.trim()
+            ),
+            field: Some("report_timezone".to_string()),
+        })
+}
+
+/// Parse a fixed reporting timezone: `UTC`, or an offset such as `+02:00`,
+/// `-0530`, or `+9`
+pub fn parse_utc_offset(s: &str) -> Result<FixedOffset> {
+    let value = s.trim();
+    let invalid = || PgLogstatsError::Configuration {
+        message: format!(
+            "Invalid report timezone '{}': expected UTC or an offset such as +02:00",
+            value
+        ),
+        field: Some("report_timezone".to_string()),
+    };
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+
+    let (sign, rest) = match value.as_bytes().first() {
+        Some(b'+') => (1, &value[1..]),
+        Some(b'-') => (-1, &value[1..]),
+        _ => return Err(invalid()),
+    };
+    if !rest
+        .bytes()
+        .all(|byte| byte.is_ascii_digit() || byte == b':')
+    {
+        return Err(invalid());
+    }
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// Query count, p95 duration, and error count of one period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeriodStats {
+    pub query_count: u64,
+    /// 95th percentile query duration in milliseconds; `None`, written as
+    /// `null`, when no query of the period had a duration
+    pub p95_duration_ms: Option<f64>,
+    pub error_count: u64,
+}
+
+/// [`PeriodStats`] of one weekday
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeekdayStats {
+    /// Monday = 0
+    pub day: u32,
+    #[serde(flatten)]
+    pub stats: PeriodStats,
+}
+
+/// Activity inside and outside business hours, and per weekday
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BusinessHoursAnalysis {
+    /// The window, e.g. `Mon-Fri 09:00-18:00`
+    pub window: String,
+    /// Reporting timezone, an IANA name or a UTC offset such as `+02:00`
+    pub timezone: String,
+    pub business_hours: PeriodStats,
+    pub off_hours: PeriodStats,
+    /// Weekdays with activity, Monday first
+    pub weekdays: Vec<WeekdayStats>,
+}
+
+impl BusinessHoursAnalysis {
+    /// Whether no query or error was counted
+    pub fn is_empty(&self) -> bool {
+        self.weekdays.is_empty()
+    }
+}
+
+/// Durations and errors of one period, as collected
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PeriodState {
+    durations: Vec<f64>,
+    /// Queries logged without a duration
+    #[serde(default)]
+    untimed_count: u64,
+    error_count: u64,
+}
+
+impl PeriodState {
+    fn merge(&mut self, other: Self) {
+        self.durations.extend(other.durations);
+        self.untimed_count += other.untimed_count;
+        self.error_count += other.error_count;
+    }
+
+    fn into_stats(mut self) -> PeriodStats {
+        self.durations.sort_by(f64::total_cmp);
+        let p95_duration_ms = match self.durations.len() {
+            0 => None,
+            len => Some(self.durations[((len as f64 * 0.95) as usize).min(len - 1)]),
+        };
+        PeriodStats {
+            query_count: self.durations.len() as u64 + self.untimed_count,
+            p95_duration_ms,
+            error_count: self.error_count,
+        }
+    }
+}
+
+/// Business-hours breakdown in progress, kept by the timing analyzer state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BusinessHoursState {
+    business_hours: PeriodState,
+    off_hours: PeriodState,
+    weekdays: BTreeMap<u32, PeriodState>,
+}
+
+impl BusinessHoursState {
+    /// Count a query logged at `timestamp`, with its duration if it has one
+    pub fn observe_query(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        duration: Option<f64>,
+        hours: &BusinessHours,
+        timezone: ReportTimezone,
+    ) {
+        for state in self.periods(timestamp, hours, timezone) {
+            match duration {
+                Some(duration) => state.durations.push(duration),
+                None => state.untimed_count += 1,
+            }
+        }
+    }
+
+    /// Give a query counted at `timestamp` without a duration the one logged
+    /// after it
+    pub fn time_query(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        duration: f64,
+        hours: &BusinessHours,
+        timezone: ReportTimezone,
+    ) {
+        for state in self.periods(timestamp, hours, timezone) {
+            state.untimed_count = state.untimed_count.saturating_sub(1);
+            state.durations.push(duration);
+        }
+    }
+
+    /// Count an error logged at `timestamp`
+    pub fn observe_error(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        hours: &BusinessHours,
+        timezone: ReportTimezone,
+    ) {
+        for state in self.periods(timestamp, hours, timezone) {
+            state.error_count += 1;
+        }
+    }
+
+    /// The period `timestamp` falls in and its weekday
+    fn periods(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        hours: &BusinessHours,
+        timezone: ReportTimezone,
+    ) -> [&mut PeriodState; 2] {
+        let offset = timezone.offset_at(timestamp);
+        let day = timestamp
+            .with_timezone(&offset)
+            .weekday()
+            .num_days_from_monday();
+        let period = if hours.contains(timestamp, offset) {
+            &mut self.business_hours
+        } else {
+            &mut self.off_hours
+        };
+        [period, self.weekdays.entry(day).or_default()]
+    }
+
+    /// Add the counts of `other`
+    pub fn merge(&mut self, other: Self) {
+        self.business_hours.merge(other.business_hours);
+        self.off_hours.merge(other.off_hours);
+        for (day, state) in other.weekdays {
+            self.weekdays.entry(day).or_default().merge(state);
+        }
+    }
+
+    /// The breakdown for `hours` in `timezone`
+    pub fn into_analysis(
+        self,
+        hours: &BusinessHours,
+        timezone: ReportTimezone,
+    ) -> BusinessHoursAnalysis {
+        BusinessHoursAnalysis {
+            window: hours.to_string(),
+            timezone: timezone.to_string(),
+            business_hours: self.business_hours.into_stats(),
+            off_hours: self.off_hours.into_stats(),
+            weekdays: self
+                .weekdays
+                .into_iter()
+                .map(|(day, state)| WeekdayStats {
+                    day,
+                    stats: state.into_stats(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_and_displays_windows() {
+        let hours: BusinessHours = "Mon-Fri 09:00-18:00".parse().unwrap();
+        assert_eq!(hours, BusinessHours::default());
+        assert_eq!(hours.to_string(), "Mon-Fri 09:00-18:00");
+
+        let hours: BusinessHours = "monday-wednesday,Sat  07:30-12:00".parse().unwrap();
+        assert_eq!(hours.days, [true, true, true, false, false, true, false]);
+        assert_eq!(hours.to_string(), "Mon-Wed,Sat 07:30-12:00");
+    }
+
+    #[test]
+    fn rejects_nonsense_windows() {
+        for spec in [
+            "",
+            "Mon-Fri",
+            "09:00-18:00",
+            "Mon-Fry 09:00-18:00",
+            "Fri-Mon 09:00-18:00",
+            "Mon-Fri 9-18",
+            "Mon-Fri 18:00-09:00",
+            "Mon-Fri 09:00-25:00",
+        ] {
+            let error = spec.parse::<BusinessHours>().unwrap_err();
+            assert!(
+                matches!(error, PgLogstatsError::Configuration { ref field, .. } if field.as_deref() == Some("business_hours")),
+                "{}",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn classifies_in_the_reporting_timezone() {
+        let hours = BusinessHours::default();
+        // Friday 17:30 UTC is Friday 19:30 at +02:00, after hours
+        let friday = Utc.with_ymd_and_hms(2024, 8, 16, 17, 30, 0).unwrap();
+        assert!(hours.contains(friday, parse_utc_offset("UTC").unwrap()));
+        assert!(!hours.contains(friday, parse_utc_offset("+02:00").unwrap()));
+        // Sunday 23:00 UTC is Monday 09:00 at +10:00
+        let sunday = Utc.with_ymd_and_hms(2024, 8, 18, 23, 0, 0).unwrap();
+        assert!(hours.contains(sunday, parse_utc_offset("+10").unwrap()));
+
+        assert_eq!(
+            parse_utc_offset("-0530").unwrap(),
+            FixedOffset::west_opt(5 * 3600 + 30 * 60).unwrap()
+        );
+        assert!(parse_utc_offset("Europe/Berlin").is_err());
+        assert!(parse_utc_offset("+15:00").is_err());
+        assert!(parse_utc_offset("+1\u{e9}1").is_err());
+        assert!(parse_utc_offset("+\u{e9}").is_err());
+    }
+
+    #[test]
+    fn named_timezones_follow_daylight_saving_time() {
+        let hours: BusinessHours = "Mon-Fri 09:00-17:00".parse().unwrap();
+        let berlin = parse_report_timezone("Europe/Berlin").unwrap();
+        assert_eq!(berlin.to_string(), "Europe/Berlin");
+        // 08:30 UTC is 09:30 in Berlin in winter and 10:30 in summer
+        let winter = Utc.with_ymd_and_hms(2024, 1, 15, 8, 30, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2024, 7, 15, 8, 30, 0).unwrap();
+        assert_eq!(berlin.offset_at(winter).local_minus_utc(), 3600);
+        assert_eq!(berlin.offset_at(summer).local_minus_utc(), 7200);
+        // 15:30 UTC is 16:30 in winter, inside, and 17:30 in summer, outside
+        let winter = Utc.with_ymd_and_hms(2024, 1, 15, 15, 30, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2024, 7, 15, 15, 30, 0).unwrap();
+        assert!(hours.contains(winter, berlin.offset_at(winter)));
+        assert!(!hours.contains(summer, berlin.offset_at(summer)));
+
+        assert_eq!(
+            parse_report_timezone("+02:00").unwrap(),
+            ReportTimezone::Fixed(FixedOffset::east_opt(7200).unwrap())
+        );
+        let error = parse_report_timezone("+1\u{e9}1").unwrap_err();
+        assert!(
+            matches!(error, PgLogstatsError::Configuration { ref field, .. } if field.as_deref() == Some("report_timezone")),
+            "{}",
+            error
+        );
+        assert!(parse_report_timezone("Berlin").is_err());
+    }
+}
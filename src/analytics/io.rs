@@ -0,0 +1,176 @@
+//! Rough I/O volume from temp-file and checkpoint messages
+//!
+//! With `log_temp_files` set, the server logs `temporary file: path
+//! "base/pgsql_tmp/pgsql_tmp1234.0", size 104857600` as each temp file is
+//! removed, and every checkpoint completion says how many buffers it wrote.
+//! Their totals over the span of the log give the temp bytes and checkpoint
+//! megabytes written per hour. Buffers are converted with the default 8 kB
+//! page size, so the figures are estimates for servers built with another.
+
+use serde::{Deserialize, Serialize};
+
+/// Bytes in a page of the default build
+pub const PAGE_SIZE_BYTES: u64 = 8_192;
+
+const BYTES_PER_MB: f64 = 1_048_576.0;
+const SECONDS_PER_HOUR: f64 = 3_600.0;
+
+/// Size in bytes of the temp file a `temporary file: path "...", size N`
+/// message reports
+pub fn temp_file_size(message: &str) -> Option<u64> {
+    let (_, size) = message
+        .strip_prefix("temporary file: path ")?
+        .rsplit_once(", size ")?;
+    size.trim_end().parse().ok()
+}
+
+/// Buffers written by the checkpoint or restart point a `... complete: wrote
+/// N buffers (25.0%); ...` message reports
+pub fn checkpoint_buffers(message: &str) -> Option<u64> {
+    let rest = message
+        .strip_prefix("checkpoint complete: wrote ")
+        .or_else(|| message.strip_prefix("restartpoint complete: wrote "))?;
+    let (buffers, _) = rest.split_once(" buffers")?;
+    buffers.parse().ok()
+}
+
+/// Bytes in `buffers` pages of [`PAGE_SIZE_BYTES`]
+pub fn buffers_to_bytes(buffers: u64) -> u64 {
+    buffers.saturating_mul(PAGE_SIZE_BYTES)
+}
+
+/// `bytes` in megabytes of 1,048,576 bytes
+pub fn bytes_to_mb(bytes: u64) -> f64 {
+    bytes as f64 / BYTES_PER_MB
+}
+
+/// `total` spread over `span_seconds`, per hour; `None` for an empty span
+pub fn per_hour(total: f64, span_seconds: f64) -> Option<f64> {
+    (span_seconds > 0.0).then(|| total * SECONDS_PER_HOUR / span_seconds)
+}
+
+/// Temp files and checkpoint writes of a log
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IoVolume {
+    pub temp_files: u64,
+    pub temp_bytes: u64,
+    /// Checkpoints and restart points whose buffer count was read
+    pub checkpoints: u64,
+    pub checkpoint_buffers: u64,
+}
+
+impl IoVolume {
+    /// Add the temp file or checkpoint writes `message` reports, if any
+    pub fn record(&mut self, message: &str) {
+        if let Some(size) = temp_file_size(message) {
+            self.temp_files += 1;
+            self.temp_bytes += size;
+        } else if let Some(buffers) = checkpoint_buffers(message) {
+            self.checkpoints += 1;
+            self.checkpoint_buffers += buffers;
+        }
+    }
+
+    /// Add the volume of `other`
+    pub fn merge(&mut self, other: Self) {
+        self.temp_files += other.temp_files;
+        self.temp_bytes += other.temp_bytes;
+        self.checkpoints += other.checkpoints;
+        self.checkpoint_buffers += other.checkpoint_buffers;
+    }
+
+    /// Megabytes written by checkpoints
+    pub fn checkpoint_mb(&self) -> f64 {
+        bytes_to_mb(buffers_to_bytes(self.checkpoint_buffers))
+    }
+
+    /// Temp bytes written per hour of a log spanning `span_seconds`
+    pub fn temp_bytes_per_hour(&self, span_seconds: f64) -> Option<f64> {
+        per_hour(self.temp_bytes as f64, span_seconds)
+    }
+
+    /// Checkpoint megabytes written per hour of a log spanning `span_seconds`
+    pub fn checkpoint_mb_per_hour(&self, span_seconds: f64) -> Option<f64> {
+        per_hour(self.checkpoint_mb(), span_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_file_size() {
+        assert_eq!(
+            temp_file_size(
+                r#"temporary file: path "base/pgsql_tmp/pgsql_tmp1234.0", size 104857600"#
+            ),
+            Some(104_857_600)
+        );
+        assert_eq!(
+            temp_file_size(r#"temporary file: path "base/pgsql_tmp/a, size 9.0", size 0"#),
+            Some(0)
+        );
+        assert_eq!(
+            temp_file_size(r#"temporary file: path "base/pgsql_tmp/pgsql_tmp1234.0", size "#),
+            None
+        );
+        assert_eq!(
+            temp_file_size(r#"temporary file: path "base/pgsql_tmp/pgsql_tmp1234.0", size -1"#),
+            None
+        );
+        assert_eq!(temp_file_size("temporary file size 1024"), None);
+        assert_eq!(
+            temp_file_size(&format!(r#"temporary file: path "t", size {}0"#, u64::MAX)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_buffers() {
+        assert_eq!(
+            checkpoint_buffers(
+                "checkpoint complete: wrote 4096 buffers (25.0%); 0 WAL file(s) added"
+            ),
+            Some(4096)
+        );
+        assert_eq!(
+            checkpoint_buffers("restartpoint complete: wrote 0 buffers (0.0%); ..."),
+            Some(0)
+        );
+        assert_eq!(checkpoint_buffers("checkpoint complete"), None);
+        assert_eq!(checkpoint_buffers("checkpoint starting: time"), None);
+        assert_eq!(
+            checkpoint_buffers("checkpoint complete: wrote many buffers (0.0%)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unit_conversions() {
+        assert_eq!(buffers_to_bytes(128), 1_048_576);
+        assert_eq!(buffers_to_bytes(u64::MAX), u64::MAX);
+        assert_eq!(bytes_to_mb(104_857_600), 100.0);
+        assert_eq!(per_hour(10.0, 1_800.0), Some(20.0));
+        assert_eq!(per_hour(10.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_volume_per_hour() {
+        let mut volume = IoVolume::default();
+        volume.record(r#"temporary file: path "base/pgsql_tmp/pgsql_tmp1.0", size 1048576"#);
+        volume.record(r#"temporary file: path "base/pgsql_tmp/pgsql_tmp1.1", size 3145728"#);
+        let mut other = IoVolume::default();
+        other.record("checkpoint complete: wrote 4096 buffers (25.0%); 0 WAL file(s) added");
+        other.record("checkpoint starting: time");
+        volume.merge(other);
+
+        assert_eq!(volume.temp_files, 2);
+        assert_eq!(volume.temp_bytes, 4_194_304);
+        assert_eq!(volume.checkpoints, 1);
+        assert_eq!(volume.checkpoint_mb(), 32.0);
+        assert_eq!(volume.temp_bytes_per_hour(7_200.0), Some(2_097_152.0));
+        assert_eq!(volume.checkpoint_mb_per_hour(900.0), Some(128.0));
+        assert_eq!(volume.checkpoint_mb_per_hour(0.0), None);
+    }
+}
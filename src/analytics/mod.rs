@@ -1,10 +1,26 @@
 //! Data analysis modules for PostgreSQL log data
 
+pub mod business_hours;
+pub mod io;
+pub mod pooler;
 pub mod queries;
+pub mod stats;
 pub mod timing;
+mod topk;
+pub mod traffic;
 
-pub use queries::{HourlyStats, QueryAnalyzer, QueryMetrics};
+pub use business_hours::{
+    parse_report_timezone, parse_utc_offset, BusinessHours, BusinessHoursAnalysis,
+    BusinessHoursState, PeriodStats, ReportTimezone, WeekdayStats,
+};
+pub use io::IoVolume;
+pub use pooler::{PoolConnections, PoolerAnalyzer, PoolerSummary};
+pub use queries::{HourlyStats, QueryAnalyzer, QueryAnalyzerState, QueryMetrics};
 pub use timing::{
-    ConnectionAnalysis, HourlyMetrics, PeakUsageAnalysis, TimingAnalysis, TimingAnalyzer,
-    TimingAnalyzerConfig,
+    ConnectionAnalysis, HourStats, HourlyMetrics, PeakUsageAnalysis, TimingAnalysis,
+    TimingAnalyzer, TimingAnalyzerConfig, TimingAnalyzerState,
+};
+pub use traffic::{
+    format_bucket_width, traffic_bucket_minutes, TrafficBucket, TrafficCounter, TrafficGranularity,
+    TrafficHistogram, MAX_TRAFFIC_ROWS,
 };
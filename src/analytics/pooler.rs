@@ -0,0 +1,164 @@
+//! Connection pooler analysis for pgbouncer logs
+
+use crate::parsers::{PgbouncerEvent, PoolerStats};
+use crate::{LogEntry, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Connection event counts for one database/user pool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolConnections {
+    pub database: String,
+    pub user: String,
+    /// Client login attempts
+    pub logins: u64,
+    /// Client and server connection closes
+    pub closes: u64,
+    /// Pooler errors reported to clients
+    pub pooler_errors: u64,
+}
+
+/// Summary of pgbouncer activity over the analyzed logs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoolerSummary {
+    /// Pools ordered by login count, busiest first
+    pub pools: Vec<PoolConnections>,
+    pub total_logins: u64,
+    pub total_closes: u64,
+    pub total_pooler_errors: u64,
+    /// Number of `stats:` samples seen
+    pub stats_samples: u64,
+    /// Mean transactions per second across stats samples
+    pub avg_xacts_per_sec: f64,
+    /// Mean queries per second across stats samples
+    pub avg_queries_per_sec: f64,
+    /// Mean client wait time in microseconds across stats samples
+    pub avg_wait_time_us: f64,
+}
+
+/// Analyzer for pgbouncer connection events and stats samples
+pub struct PoolerAnalyzer;
+
+impl PoolerAnalyzer {
+    /// Create a new pooler analyzer
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Summarize connection events per pool and average throughput
+    pub fn analyze(&self, entries: &[LogEntry], stats: &[PoolerStats]) -> Result<PoolerSummary> {
+        let mut summary = PoolerSummary::default();
+        let mut pools: HashMap<(String, String), PoolConnections> = HashMap::new();
+
+        for entry in entries {
+            let Some(event) = PgbouncerEvent::classify(&entry.message) else {
+                continue;
+            };
+
+            let database = entry
+                .database
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string());
+            let user = entry.user.clone().unwrap_or_else(|| "unknown".to_string());
+            let pool = pools
+                .entry((database.clone(), user.clone()))
+                .or_insert_with(|| PoolConnections {
+                    database,
+                    user,
+                    ..Default::default()
+                });
+
+            match event {
+                PgbouncerEvent::Login => {
+                    pool.logins += 1;
+                    summary.total_logins += 1;
+                }
+                PgbouncerEvent::Close => {
+                    pool.closes += 1;
+                    summary.total_closes += 1;
+                }
+                PgbouncerEvent::PoolerError => {
+                    pool.pooler_errors += 1;
+                    summary.total_pooler_errors += 1;
+                }
+            }
+        }
+
+        summary.pools = pools.into_values().collect();
+        summary.pools.sort_by(|a, b| {
+            b.logins
+                .cmp(&a.logins)
+                .then_with(|| a.database.cmp(&b.database))
+                .then_with(|| a.user.cmp(&b.user))
+        });
+
+        if !stats.is_empty() {
+            let samples = stats.len() as f64;
+            summary.stats_samples = stats.len() as u64;
+            summary.avg_xacts_per_sec =
+                stats.iter().map(|s| s.xacts_per_sec).sum::<f64>() / samples;
+            summary.avg_queries_per_sec =
+                stats.iter().map(|s| s.queries_per_sec).sum::<f64>() / samples;
+            summary.avg_wait_time_us = stats.iter().map(|s| s.wait_time_us).sum::<f64>() / samples;
+        }
+
+        Ok(summary)
+    }
+}
+
+impl Default for PoolerAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PgbouncerParser;
+
+    fn parse(lines: &[&str]) -> (Vec<LogEntry>, Vec<PoolerStats>) {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        let (entries, stats, _report) = PgbouncerParser::new().parse_lines_with_report(&lines);
+        (entries, stats)
+    }
+
+    #[test]
+    fn counts_connection_events_per_pool() {
+        let (entries, stats) = parse(&[
+            "2024-08-15 10:30:15.000 UTC [1] LOG C-0x1: app/web@10.0.0.5:1000 login attempt: db=app user=web tls=no",
+            "2024-08-15 10:30:16.000 UTC [1] LOG C-0x2: app/web@10.0.0.6:1000 login attempt: db=app user=web tls=no",
+            "2024-08-15 10:30:17.000 UTC [1] LOG C-0x3: billing/batch@10.0.0.7:1000 login attempt: db=billing user=batch tls=no",
+            "2024-08-15 10:30:18.000 UTC [1] LOG C-0x1: app/web@10.0.0.5:1000 closing because: client close request (age=3s)",
+            "2024-08-15 10:30:19.000 UTC [1] WARNING C-0x4: billing/batch@10.0.0.7:1001 pooler error: query_wait_timeout",
+        ]);
+
+        let summary = PoolerAnalyzer::new().analyze(&entries, &stats).unwrap();
+
+        assert_eq!(summary.total_logins, 3);
+        assert_eq!(summary.total_closes, 1);
+        assert_eq!(summary.total_pooler_errors, 1);
+        assert_eq!(summary.pools.len(), 2);
+        assert_eq!(summary.pools[0].database, "app");
+        assert_eq!(summary.pools[0].logins, 2);
+        assert_eq!(summary.pools[0].closes, 1);
+        assert_eq!(summary.pools[1].database, "billing");
+        assert_eq!(summary.pools[1].pooler_errors, 1);
+        assert_eq!(summary.stats_samples, 0);
+    }
+
+    #[test]
+    fn averages_stats_samples() {
+        let (entries, stats) = parse(&[
+            "2024-08-15 10:31:00.000 UTC [1] LOG stats: 100 xacts/s, 300 queries/s, in 0 B/s, out 0 B/s, xact 0 us, query 0 us, wait 10 us",
+            "2024-08-15 10:32:00.000 UTC [1] LOG stats: 140 xacts/s, 380 queries/s, in 0 B/s, out 0 B/s, xact 0 us, query 0 us, wait 30 us",
+        ]);
+
+        let summary = PoolerAnalyzer::new().analyze(&entries, &stats).unwrap();
+
+        assert_eq!(summary.stats_samples, 2);
+        assert_eq!(summary.avg_xacts_per_sec, 120.0);
+        assert_eq!(summary.avg_queries_per_sec, 340.0);
+        assert_eq!(summary.avg_wait_time_us, 20.0);
+    }
+}
@@ -1,14 +1,29 @@
 //! Query analysis functionality for PostgreSQL logs
 
+use super::topk::TopKCounter;
+use super::traffic::{TrafficCounter, TrafficGranularity, MAX_TRAFFIC_ROWS};
 use crate::{
-    normalize_log_entries, AnalysisResult, Correlator, EventSourceKind, LogEntry, NormalizedEvent,
-    ProcessOrderCorrelator, QueryType, Result,
+    cancellation::{CancelReason, CanceledQuery, CancellationStats},
+    copy::{CopyDirection, CopyStatement, CopyStats, CopyTableStats},
+    log_events::{LogEventTracker, DEFAULT_MAX_LOG_EVENTS},
+    normalize_log_entries,
+    timezone::{hour_in, Tz},
+    AnalysisResult, ApplicationStats, BatchDuration, ClientHostStats, ConnectionEventKind,
+    CorrelationConfidence, Correlator, EventKind, EventSourceKind, FrequencyEstimate, GroupBy,
+    LogEntry, NormalizedEvent, PgVersion, PoolerNoise, PreparedStatementTracker,
+    ProcessOrderCorrelator, Query, QueryExample, QueryExecution, QueryGroup, QueryLinter,
+    QuerySort, QueryStats, QueryType, QueryTypeStats, QueryWarning, ReplicationEventKind,
+    ReplicationStats, Result, SessionIdentity, StreamingCorrelator, TimeGap, TimeRange,
+    NO_APPLICATION_NAME, STATEMENT_NOT_LOGGED, UNKNOWN_BREAKDOWN_KEY,
 };
 use chrono::{DateTime, Timelike, Utc};
-use regex::Regex;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::cmp::Reverse;
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{btree_map, hash_map, BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::AddAssign;
+use std::sync::Arc;
 
 /// Query performance metrics
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,35 +71,1061 @@ pub struct HourlyStats {
     pub total_duration: f64,
     /// Average duration in milliseconds
     pub average_duration: f64,
+    /// 95th percentile duration in milliseconds
+    #[serde(default)]
+    pub p95_duration: f64,
+    /// Number of errors in this hour
+    #[serde(default)]
+    pub error_count: u64,
 }
 
-/// Analyzer for SQL queries found in PostgreSQL logs
-pub struct QueryAnalyzer {
+impl HourlyStats {
+    /// Stats of an hour without queries or errors yet
+    fn empty(hour: u32) -> Self {
+        Self {
+            hour,
+            query_count: 0,
+            queries_per_second: 0.0,
+            total_duration: 0.0,
+            average_duration: 0.0,
+            p95_duration: 0.0,
+            error_count: 0,
+        }
+    }
+}
+
+/// Duration statistics of a query counted `count` times
+fn query_stats(count: u64, durations: &[f64]) -> QueryStats {
+    if durations.is_empty() {
+        return QueryStats {
+            count,
+            ..QueryStats::default()
+        };
+    }
+    let mut sorted = durations.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let total_duration = sorted.iter().sum::<f64>();
+    let p95_index = (sorted.len() as f64 * 0.95) as usize;
+    QueryStats {
+        count,
+        total_duration,
+        average_duration: total_duration / sorted.len() as f64,
+        p95_duration: sorted[p95_index.min(sorted.len() - 1)],
+        examples: Vec::new(),
+    }
+}
+
+/// Durations and example executions of one query family
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FamilyExecutions {
+    durations: Vec<f64>,
+    /// The slowest executions seen, at most the analyzer's example limit
+    #[serde(default)]
+    examples: Vec<QueryExample>,
+}
+
+impl FamilyExecutions {
+    /// Keep the example `make` builds when fewer than `limit` are kept, or in
+    /// place of the fastest one kept when `duration` is slower. Earlier
+    /// executions win ties.
+    fn offer(&mut self, duration: f64, limit: usize, make: impl FnOnce() -> QueryExample) {
+        if self.examples.len() < limit {
+            self.examples.push(make());
+            return;
+        }
+        let fastest = self
+            .examples
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.duration.total_cmp(&b.1.duration))
+            .filter(|(_, fastest)| duration > fastest.duration)
+            .map(|(i, _)| i);
+        if let Some(i) = fastest {
+            self.examples[i] = make();
+        }
+    }
+
+    /// Add the executions of `other`, which were seen after these
+    fn merge(&mut self, other: Self, limit: usize) {
+        self.durations.extend(other.durations);
+        for example in other.examples {
+            self.offer(example.duration, limit, || example);
+        }
+    }
+
+    /// The kept examples, slowest and then earliest first
+    fn examples(&self) -> Vec<QueryExample> {
+        let mut examples = self.examples.clone();
+        examples.sort_by(|a, b| {
+            b.duration
+                .total_cmp(&a.duration)
+                .then_with(|| a.timestamp.cmp(&b.timestamp))
+        });
+        examples
+    }
+}
+
+/// Normalized SQL strings seen during analysis, stored once each, with their
+/// execution counts and the durations and example executions of their family.
+///
+/// Slow-query and frequency tracking hold shared handles to the SQL instead of
+/// cloning it for every execution. Queries keep first-seen order, which also
+/// keeps tie-breaking in the results stable. Past `exact_limit` distinct
+/// queries, only the `capacity` most frequent are kept and counts become
+/// estimates; see [`TopKCounter`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueryInterner {
+    queries: TopKCounter<FamilyExecutions>,
+    exact_limit: usize,
+    capacity: usize,
+    /// Example executions kept per query family
+    #[serde(default)]
+    max_examples: usize,
+}
+
+impl QueryInterner {
+    fn new(exact_limit: usize, capacity: usize, max_examples: usize) -> Self {
+        let mut queries = TopKCounter::new();
+        if exact_limit == 0 {
+            queries.bound(capacity);
+        }
+        Self {
+            queries,
+            exact_limit,
+            capacity,
+            max_examples,
+        }
+    }
+
+    /// Count one execution of `sql`
+    fn count(&mut self, sql: &str) -> Arc<str> {
+        let handle = self.queries.increment(sql).0.clone();
+        self.bound_past_limit();
+        handle
+    }
+
+    /// Record `execution` of the query family `sql` taking `duration`, keeping
+    /// it as an example when it has a statement; dropped when the family is
+    /// not among the kept queries
+    fn add_execution(&mut self, sql: &str, execution: &QueryExecution, duration: f64) -> Arc<str> {
+        let handle = match self.queries.track(sql) {
+            Some((handle, family)) => {
+                family.durations.push(duration);
+                if execution.confidence != CorrelationConfidence::DurationOnly {
+                    family.offer(duration, self.max_examples, || QueryExample {
+                        sql: execution.statement.clone(),
+                        timestamp: execution.timestamp,
+                        duration,
+                        user: execution.session.user.clone(),
+                        database: execution.session.database.clone(),
+                        parameters: execution.parameters.clone(),
+                    });
+                }
+                handle.clone()
+            }
+            None => Arc::from(sql),
+        };
+        self.bound_past_limit();
+        handle
+    }
+
+    /// Add the queries of `other`, which were seen after these
+    fn merge(&mut self, other: Self) {
+        let max_examples = self.max_examples;
+        self.queries.merge(other.queries, |family, theirs| {
+            family.merge(theirs, max_examples)
+        });
+        self.bound_past_limit();
+    }
+
+    fn bound_past_limit(&mut self) {
+        if self.queries.len() > self.exact_limit {
+            self.bound();
+        }
+    }
+
+    /// Keep only the `capacity` most frequent queries from now on
+    fn bound(&mut self) {
+        if self.queries.capacity().is_none() {
+            self.queries.bound(self.capacity);
+        }
+    }
+
+    fn stats(&self, sql: &str) -> Option<QueryStats> {
+        self.queries.get(sql).map(|(count, family)| QueryStats {
+            examples: family.examples(),
+            // A multi-statement batch is counted through its statements
+            ..query_stats(count.max(family.durations.len() as u64), &family.durations)
+        })
+    }
+
+    /// Stats without examples of every counted query
+    fn all_stats(&self) -> HashMap<String, QueryStats> {
+        self.queries
+            .iter()
+            .filter(|(_, count, _)| *count > 0)
+            .map(|(sql, count, family)| {
+                let stats =
+                    query_stats(count.max(family.durations.len() as u64), &family.durations);
+                (sql.to_string(), stats)
+            })
+            .collect()
+    }
+
+    /// The `limit` queries ranking highest by `sort`, with their counts
+    fn top_by(&self, sort: QuerySort, limit: usize) -> Vec<(String, u64)> {
+        if sort == QuerySort::Count {
+            return self.most_frequent(limit);
+        }
+        let mut ranked: Vec<_> = self
+            .queries
+            .iter()
+            .filter(|(_, count, _)| *count > 0)
+            .map(|(sql, count, family)| {
+                let key = sort.key(&query_stats(count, &family.durations));
+                (sql, count, key)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.2.total_cmp(&a.2));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(sql, count, _)| (sql.to_string(), count))
+            .collect()
+    }
+
+    /// The `limit` most frequent queries
+    fn most_frequent(&self, limit: usize) -> Vec<(String, u64)> {
+        top_counts(
+            self.queries
+                .iter()
+                .map(|(sql, count, _)| (sql.as_ref(), count)),
+            limit,
+        )
+    }
+
+    /// Whether only the most frequent queries are kept
+    fn is_approximate(&self) -> bool {
+        self.queries.capacity().is_some()
+    }
+
+    /// How counts were estimated; `None` when they are exact
+    fn estimate(&self) -> Option<FrequencyEstimate> {
+        self.queries.capacity().map(|capacity| FrequencyEstimate {
+            capacity,
+            max_overcount: self.queries.max_overcount(),
+        })
+    }
+}
+
+/// A slow execution held in the bounded slow-query heap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SlowQuery {
+    duration: f64,
+    /// Arrival order; earlier executions win ties
+    sequence: usize,
+    query: Arc<str>,
+}
+
+impl SlowQuery {
+    /// Rank by duration, then by arrival so the earliest of equal durations
+    /// ranks highest
+    fn rank(&self, other: &Self) -> Ordering {
+        self.duration
+            .total_cmp(&other.duration)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialEq for SlowQuery {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SlowQuery {}
+
+impl PartialOrd for SlowQuery {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SlowQuery {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank(other)
+    }
+}
+
+/// Keeps the `capacity` slowest executions in a min-heap so memory stays
+/// bounded no matter how many executions cross the threshold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SlowQueryHeap {
+    capacity: usize,
+    heap: BinaryHeap<Reverse<SlowQuery>>,
+    next_sequence: usize,
+}
+
+impl SlowQueryHeap {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            heap: BinaryHeap::with_capacity(capacity + 1),
+            next_sequence: 0,
+        }
+    }
+
+    fn push(&mut self, query: Arc<str>, duration: f64) {
+        let candidate = SlowQuery {
+            duration,
+            sequence: self.next_sequence,
+            query,
+        };
+        self.next_sequence += 1;
+        self.insert(candidate);
+    }
+
+    /// Add the executions of `other`, which arrived after these
+    fn merge(&mut self, other: Self) {
+        let offset = self.next_sequence;
+        for Reverse(slow) in other.heap {
+            self.insert(SlowQuery {
+                sequence: offset + slow.sequence,
+                ..slow
+            });
+        }
+        self.next_sequence += other.next_sequence;
+    }
+
+    fn insert(&mut self, candidate: SlowQuery) {
+        if self.heap.len() < self.capacity {
+            self.heap.push(Reverse(candidate));
+        } else if let Some(mut fastest) = self.heap.peek_mut() {
+            if candidate > fastest.0 {
+                *fastest = Reverse(candidate);
+            }
+        }
+    }
+
+    /// Slowest first
+    fn into_sorted_vec(self) -> Vec<(String, f64)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(slow)| (slow.query.to_string(), slow.duration))
+            .collect()
+    }
+}
+
+/// Counters for one `group_by` value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GroupTracker {
+    total_queries: u64,
+    total_duration: f64,
+    query_counts: TopKCounter<()>,
+    slow_queries: SlowQueryHeap,
+}
+
+impl GroupTracker {
+    /// Query counts are bounded to `capacity` when given, as in low-memory mode
+    fn new(max_slow_queries: usize, capacity: Option<usize>) -> Self {
+        let mut query_counts = TopKCounter::new();
+        if let Some(capacity) = capacity {
+            query_counts.bound(capacity);
+        }
+        Self {
+            total_queries: 0,
+            total_duration: 0.0,
+            query_counts,
+            slow_queries: SlowQueryHeap::new(max_slow_queries),
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.total_queries += other.total_queries;
+        self.total_duration += other.total_duration;
+        self.query_counts.merge(other.query_counts, |_, _| {});
+        self.slow_queries.merge(other.slow_queries);
+    }
+
+    fn into_group(self, max_frequent: usize) -> QueryGroup {
+        QueryGroup {
+            total_queries: self.total_queries,
+            total_duration: self.total_duration,
+            slowest_queries: self.slow_queries.into_sorted_vec(),
+            most_frequent_queries: top_counts(
+                self.query_counts
+                    .iter()
+                    .map(|(sql, count, _)| (sql.as_ref(), count)),
+                max_frequent,
+            ),
+        }
+    }
+}
+
+/// Type an execution counts under: that of its statement, or for a batch
+/// that of its first statement other than transaction control, such as the
+/// UPDATE of `BEGIN; UPDATE ...; COMMIT`
+fn execution_query_type(queries: &[Query]) -> Option<QueryType> {
+    queries
+        .iter()
+        .find(|query| query.query_type != QueryType::Transaction)
+        .or(queries.first())
+        .map(|query| query.query_type.clone())
+}
+
+/// The session's application name, or `(none)`
+fn application_key(session: &SessionIdentity) -> &str {
+    session
+        .application_name
+        .as_deref()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(NO_APPLICATION_NAME)
+}
+
+/// Counters for one `application_name`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ApplicationTracker {
+    durations: Vec<f64>,
+    error_count: u64,
+    /// Longest duration seen per query
+    slowest: IndexMap<Arc<str>, f64>,
+}
+
+impl ApplicationTracker {
+    /// Drop all but the `limit` slowest queries once twice that many are
+    /// held. A dropped query can only come back with a longer execution, so
+    /// the slowest list is unchanged.
+    fn keep_slowest(&mut self, limit: usize) {
+        if self.slowest.len() <= limit.max(1) * 2 {
+            return;
+        }
+        let mut durations: Vec<f64> = self.slowest.values().copied().collect();
+        durations.sort_by(|a, b| b.total_cmp(a));
+        let cutoff = durations[limit.min(durations.len()).saturating_sub(1)];
+        self.slowest
+            .retain(|_, duration| limit > 0 && *duration >= cutoff);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.durations.extend(other.durations);
+        self.error_count += other.error_count;
+        for (sql, duration) in other.slowest {
+            let slowest = self.slowest.entry(sql).or_insert(duration);
+            *slowest = slowest.max(duration);
+        }
+    }
+
+    fn into_stats(self, application_name: &str, max_queries: usize) -> ApplicationStats {
+        let metrics = calculate_metrics(&self.durations);
+        let mut slowest: Vec<_> = self.slowest.into_iter().collect();
+        slowest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ApplicationStats {
+            application_name: application_name.to_string(),
+            query_count: self.durations.len() as u64,
+            total_duration: metrics.total_duration,
+            p95_duration: metrics.p95_duration,
+            error_count: self.error_count,
+            slowest_queries: slowest
+                .into_iter()
+                .take(max_queries)
+                .map(|(sql, duration)| (sql.to_string(), duration))
+                .collect(),
+        }
+    }
+}
+
+/// The session's client host, as `host(port)` when `keep_port` is set;
+/// `None` when the log does not name the client
+fn client_key(session: &SessionIdentity, keep_port: bool) -> Option<String> {
+    let host = session.client_host.as_deref()?;
+    Some(match session.client_port {
+        Some(port) if keep_port => format!("{}({})", host, port),
+        _ => host.to_string(),
+    })
+}
+
+/// Counters for one client host
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ClientTracker {
+    connection_count: u64,
+    query_count: u64,
+    total_duration: f64,
+    error_count: u64,
+}
+
+impl ClientTracker {
+    fn merge(&mut self, other: Self) {
+        self.connection_count += other.connection_count;
+        self.query_count += other.query_count;
+        self.total_duration += other.total_duration;
+        self.error_count += other.error_count;
+    }
+
+    fn into_stats(self, client_host: String) -> ClientHostStats {
+        let statements = self.query_count + self.error_count;
+        ClientHostStats {
+            client_host,
+            connection_count: self.connection_count,
+            query_count: self.query_count,
+            total_duration: self.total_duration,
+            error_count: self.error_count,
+            error_rate: if statements > 0 {
+                self.error_count as f64 / statements as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Counters for canceled statements and terminated connections
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CancellationTracker {
+    total: u64,
+    by_reason: BTreeMap<CancelReason, u64>,
+    queries: IndexMap<String, BTreeMap<CancelReason, u64>>,
+    hourly: BTreeMap<u32, u64>,
+}
+
+impl CancellationTracker {
+    fn record(&mut self, reason: CancelReason, statement: Option<&str>, hour: u32) {
+        self.total += 1;
+        *self.by_reason.entry(reason).or_insert(0) += 1;
+        *self.hourly.entry(hour).or_insert(0) += 1;
+        if let Some(statement) = statement {
+            *self
+                .queries
+                .entry(statement.to_string())
+                .or_default()
+                .entry(reason)
+                .or_insert(0) += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.total += other.total;
+        add_counts(&mut self.by_reason, other.by_reason);
+        for (query, by_reason) in other.queries {
+            add_counts(self.queries.entry(query).or_default(), by_reason);
+        }
+        add_counts(&mut self.hourly, other.hourly);
+    }
+
+    /// Stats listing the `max_queries` most canceled statements; the stable
+    /// sort keeps first-seen order among equal counts
+    fn into_stats(self, max_queries: usize) -> CancellationStats {
+        let reason_counts = |counts: BTreeMap<CancelReason, u64>| {
+            counts
+                .into_iter()
+                .map(|(reason, count)| (reason.to_string(), count))
+                .collect()
+        };
+        let mut top_queries: Vec<_> = self
+            .queries
+            .into_iter()
+            .map(|(query, by_reason)| CanceledQuery {
+                query,
+                count: by_reason.values().sum(),
+                by_reason: reason_counts(by_reason),
+            })
+            .collect();
+        top_queries.sort_by_key(|query| Reverse(query.count));
+        top_queries.truncate(max_queries);
+        CancellationStats {
+            total: self.total,
+            by_reason: reason_counts(self.by_reason),
+            top_queries,
+            hourly: self.hourly,
+        }
+    }
+}
+
+/// Counters for `COPY` statements per table and direction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CopyTracker {
+    tables: Vec<CopyTableStats>,
+}
+
+impl CopyTracker {
+    /// Stats of one table and direction; logs copy few distinct tables, so a
+    /// linear search is enough
+    fn table(&mut self, table: &str, direction: CopyDirection) -> &mut CopyTableStats {
+        let index = match self
+            .tables
+            .iter()
+            .position(|stats| stats.table == table && stats.direction == direction)
+        {
+            Some(index) => index,
+            None => {
+                self.tables.push(CopyTableStats::new(table, direction));
+                self.tables.len() - 1
+            }
+        };
+        &mut self.tables[index]
+    }
+
+    fn record(&mut self, statement: &CopyStatement, duration: f64) {
+        let stats = self.table(&statement.table, statement.direction);
+        stats.operations += 1;
+        stats.total_duration += duration;
+        stats.max_duration = stats.max_duration.max(duration);
+    }
+
+    fn record_error(&mut self, table: &str) {
+        self.table(table, CopyDirection::From).error_count += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        for theirs in other.tables {
+            let stats = self.table(&theirs.table, theirs.direction);
+            stats.operations += theirs.operations;
+            stats.total_duration += theirs.total_duration;
+            stats.max_duration = stats.max_duration.max(theirs.max_duration);
+            stats.error_count += theirs.error_count;
+        }
+    }
+
+    fn into_stats(mut self) -> CopyStats {
+        self.tables.sort_by(|a, b| {
+            b.total_duration
+                .total_cmp(&a.total_duration)
+                .then_with(|| a.table.cmp(&b.table))
+                .then_with(|| a.direction.cmp(&b.direction))
+        });
+        CopyStats {
+            operations: self.tables.iter().map(|stats| stats.operations).sum(),
+            // An empty f64 sum is -0.0, which would be written as such
+            total_duration: self
+                .tables
+                .iter()
+                .map(|stats| stats.total_duration)
+                .fold(0.0, |total, duration| total + duration),
+            max_duration: self
+                .tables
+                .iter()
+                .map(|stats| stats.max_duration)
+                .fold(0.0, f64::max),
+            error_count: self.tables.iter().map(|stats| stats.error_count).sum(),
+            tables: self.tables,
+        }
+    }
+}
+
+/// A query that matched a lint rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LintMatch {
+    query: Arc<str>,
+    rule: String,
+    description: String,
+}
+
+/// Warnings for the lint matches, most executed first
+fn query_warnings(matches: Vec<LintMatch>, interner: &QueryInterner) -> Vec<QueryWarning> {
+    let mut warnings: Vec<_> = matches
+        .into_iter()
+        .map(|lint| {
+            let stats = interner.stats(&lint.query).unwrap_or_default();
+            QueryWarning {
+                rule: lint.rule,
+                description: lint.description,
+                query: lint.query.to_string(),
+                count: stats.count,
+                p95_duration: stats.p95_duration,
+            }
+        })
+        .collect();
+    warnings.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.p95_duration.total_cmp(&a.p95_duration))
+            .then_with(|| a.rule.cmp(&b.rule))
+            .then_with(|| a.query.cmp(&b.query))
+    });
+    warnings
+}
+
+/// Add each count of `other` to the same key of `counts`
+fn add_counts<K: Ord, V: AddAssign + Default>(counts: &mut BTreeMap<K, V>, other: BTreeMap<K, V>) {
+    for (key, count) in other {
+        *counts.entry(key).or_default() += count;
+    }
+}
+
+/// [`add_counts`] for hash maps
+fn add_hashed_counts<K: Eq + Hash, V: AddAssign + Default>(
+    counts: &mut HashMap<K, V>,
+    other: HashMap<K, V>,
+) {
+    for (key, count) in other {
+        *counts.entry(key).or_default() += count;
+    }
+}
+
+/// Default number of client hosts listed
+pub const DEFAULT_MAX_CLIENT_HOSTS: usize = 10;
+
+/// Default number of most canceled statements listed
+pub const DEFAULT_MAX_CANCELED_QUERIES: usize = 10;
+
+/// Default number of distinct queries kept once query counts are estimated
+pub const DEFAULT_TOP_K_CAPACITY: usize = 10_000;
+
+/// Default number of distinct queries counted exactly before switching to
+/// estimated counts
+pub const DEFAULT_EXACT_QUERY_LIMIT: usize = 100_000;
+
+/// Default minutes without entries that count as a gap in the time range
+pub const DEFAULT_GAP_THRESHOLD_MINUTES: f64 = 60.0;
+
+/// Default characters kept of each unparsed statement sample
+pub const DEFAULT_UNPARSED_SAMPLE_LENGTH: usize = 200;
+
+/// Default number of example executions kept per normalized query
+pub const DEFAULT_MAX_QUERY_EXAMPLES: usize = 3;
+
+/// Default histogram bucket upper bounds in milliseconds
+pub const DEFAULT_HISTOGRAM_BOUNDS: [f64; 5] = [1.0, 10.0, 100.0, 1_000.0, 10_000.0];
+
+/// `45ms`, `1s`, or `2.5s`
+fn format_bound(ms: f64) -> String {
+    if ms >= 1_000.0 {
+        format!("{}s", ms / 1_000.0)
+    } else {
+        format!("{}ms", ms)
+    }
+}
+
+/// Label of the bucket `[lower, upper)`; an open end is written as `<` or `>=`
+fn bucket_label(lower: Option<f64>, upper: Option<f64>) -> String {
+    match (lower, upper) {
+        (None, Some(upper)) => format!("<{}", format_bound(upper)),
+        (Some(lower), None) => format!(">={}", format_bound(lower)),
+        (Some(lower), Some(upper)) if (lower >= 1_000.0) == (upper >= 1_000.0) => {
+            let scale = if lower >= 1_000.0 { 1_000.0 } else { 1.0 };
+            format!("{}-{}", lower / scale, format_bound(upper))
+        }
+        (Some(lower), Some(upper)) => format!("{}-{}", format_bound(lower), format_bound(upper)),
+        (None, None) => "all".to_string(),
+    }
+}
+
+/// The `limit` highest counts; the stable sort keeps first-seen order among
+/// equal counts
+fn top_counts<'a>(
+    counts: impl Iterator<Item = (&'a str, u64)>,
+    limit: usize,
+) -> Vec<(String, u64)> {
+    let mut counts: Vec<_> = counts.filter(|(_, count)| *count > 0).collect();
+    counts.sort_by_key(|(_, count)| Reverse(*count));
+    counts
+        .into_iter()
+        .take(limit)
+        .map(|(sql, count)| (sql.to_string(), count))
+        .collect()
+}
+
+/// Count, mean, spread, and extremes of durations, updated one duration at a
+/// time with Welford's algorithm so no list of durations is needed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct DurationMoments {
+    count: u64,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl DurationMoments {
+    fn add(&mut self, duration: f64) {
+        if self.count == 0 {
+            self.min = duration;
+            self.max = duration;
+        } else {
+            self.min = self.min.min(duration);
+            self.max = self.max.max(duration);
+        }
+        self.count += 1;
+        let delta = duration - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (duration - self.mean);
+    }
+
+    /// Combine with the moments of other durations (Chan et al.)
+    fn merge(&mut self, other: Self) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * (self.count * other.count) as f64 / count as f64;
+        self.count = count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Population standard deviation, as in `pg_stat_statements`
+    fn stddev(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        (self.m2 / self.count as f64).sqrt()
+    }
+}
+
+/// Performance metrics of `durations`
+fn calculate_metrics(durations: &[f64]) -> QueryMetrics {
+    if durations.is_empty() {
+        return QueryMetrics::default();
+    }
+
+    let total_queries = durations.len() as u64;
+    let total_duration = durations.iter().sum::<f64>();
+    let average_duration = total_duration / total_queries as f64;
+
+    let min_duration = durations.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+    let max_duration = durations.iter().fold(0.0_f64, |a, &b| a.max(b));
+
+    // Calculate percentiles
+    let mut sorted_durations = durations.to_vec();
+    sorted_durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let p95_index = (sorted_durations.len() as f64 * 0.95) as usize;
+    let p99_index = (sorted_durations.len() as f64 * 0.99) as usize;
+
+    let p95_duration = sorted_durations[p95_index.min(sorted_durations.len() - 1)];
+    let p99_duration = sorted_durations[p99_index.min(sorted_durations.len() - 1)];
+
+    QueryMetrics {
+        min_duration,
+        max_duration,
+        average_duration,
+        p95_duration,
+        p99_duration,
+        total_queries,
+        total_duration,
+    }
+}
+
+/// Calculate queries per second for hourly buckets from the first and
+/// last statement seen in each hour
+fn calculate_queries_per_second(
+    hourly_stats: &mut HashMap<u32, HourlyStats>,
+    hourly_spans: &HashMap<u32, (DateTime<Utc>, DateTime<Utc>)>,
+) {
+    for (hour, (min_time, max_time)) in hourly_spans {
+        if let Some(stats) = hourly_stats.get_mut(hour) {
+            let duration_seconds = (*max_time - *min_time).num_seconds() as f64;
+            if duration_seconds > 0.0 {
+                stats.queries_per_second = stats.query_count as f64 / duration_seconds;
+            }
+
+            if stats.query_count > 0 {
+                stats.average_duration = stats.total_duration / stats.query_count as f64;
+            }
+        }
+    }
+}
+
+/// Settings a [`QueryAnalyzer`] hands to each analysis it starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueryAnalyzerSettings {
     /// Threshold for considering a query "slow" (milliseconds)
     slow_query_threshold: f64,
     /// Maximum number of slow queries to track
     max_slow_queries: usize,
     /// Maximum number of frequent queries to track
     max_frequent_queries: usize,
-    /// Regex for normalizing SQL queries
-    literal_regex: Regex,
-    /// Regex for extracting numeric literals
-    numeric_regex: Regex,
-    /// Regex for extracting string literals
-    string_regex: Regex,
+    /// Dimension to group the slowest and most frequent queries by
+    group_by: Option<GroupBy>,
+    /// Maximum number of slowest queries listed per application
+    max_application_queries: usize,
+    /// Maximum number of client hosts listed
+    max_client_hosts: usize,
+    /// Keep the client port in client host keys instead of aggregating by host
+    keep_client_ports: bool,
+    /// Maximum number of most canceled statements listed
+    max_canceled_queries: usize,
+    /// Maximum number of LOG, WARNING, and NOTICE message templates listed
+    #[serde(default = "default_max_log_events")]
+    max_log_events: usize,
+    /// Ascending upper bounds of the duration histogram buckets in milliseconds
+    histogram_bounds: Vec<f64>,
+    /// Minutes without entries reported as a gap in the time range
+    gap_threshold_minutes: f64,
+    /// Metric the most frequent queries are selected and ordered by
+    query_sort: QuerySort,
+    /// Estimate query counts from the start instead of past the exact limit
+    low_memory: bool,
+    /// Distinct queries kept once query counts are estimated
+    top_k_capacity: usize,
+    /// Distinct queries counted exactly before switching to estimated counts
+    exact_query_limit: usize,
+    /// Characters kept of each unparsed statement sample
+    #[serde(default = "default_unparsed_sample_length")]
+    unparsed_sample_length: usize,
+    /// Timezone the hourly stats bucket entries in; UTC when `None`
+    #[serde(default)]
+    bucket_timezone: Option<Tz>,
+    /// Example executions kept per normalized query
+    #[serde(default = "default_max_query_examples")]
+    max_query_examples: usize,
+    /// Bucket width of the traffic histogram; no histogram when `None`
+    #[serde(default)]
+    traffic_histogram: Option<TrafficGranularity>,
+    /// Matcher of pooler maintenance statements
+    #[serde(default)]
+    pooler_noise: PoolerNoise,
+    /// Leave pooler maintenance executions out of the analysis, counting
+    /// them only in `pooler_maintenance_count`
+    #[serde(default)]
+    ignore_pooler_noise: bool,
+    /// PostgreSQL major version to parse messages as, instead of the one
+    /// detected from the log
+    #[serde(default)]
+    assume_pg_version: Option<u32>,
+    /// How the duration of a multi-statement line is attributed
+    #[serde(default)]
+    batch_duration: BatchDuration,
+}
+
+fn default_max_query_examples() -> usize {
+    DEFAULT_MAX_QUERY_EXAMPLES
+}
+
+fn default_max_log_events() -> usize {
+    DEFAULT_MAX_LOG_EVENTS
+}
+
+fn default_unparsed_sample_length() -> usize {
+    DEFAULT_UNPARSED_SAMPLE_LENGTH
+}
+
+impl QueryAnalyzerSettings {
+    fn new(
+        slow_query_threshold: f64,
+        max_slow_queries: usize,
+        max_frequent_queries: usize,
+    ) -> Self {
+        Self {
+            slow_query_threshold,
+            max_slow_queries,
+            max_frequent_queries,
+            group_by: None,
+            max_application_queries: 3,
+            max_client_hosts: DEFAULT_MAX_CLIENT_HOSTS,
+            keep_client_ports: false,
+            max_canceled_queries: DEFAULT_MAX_CANCELED_QUERIES,
+            max_log_events: DEFAULT_MAX_LOG_EVENTS,
+            histogram_bounds: DEFAULT_HISTOGRAM_BOUNDS.to_vec(),
+            gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+            query_sort: QuerySort::default(),
+            low_memory: false,
+            top_k_capacity: DEFAULT_TOP_K_CAPACITY,
+            exact_query_limit: DEFAULT_EXACT_QUERY_LIMIT,
+            unparsed_sample_length: DEFAULT_UNPARSED_SAMPLE_LENGTH,
+            bucket_timezone: None,
+            max_query_examples: DEFAULT_MAX_QUERY_EXAMPLES,
+            traffic_histogram: None,
+            pooler_noise: PoolerNoise::new(),
+            ignore_pooler_noise: false,
+            assume_pg_version: None,
+            batch_duration: BatchDuration::default(),
+        }
+    }
+
+    /// Distinct queries counted exactly; zero in low-memory mode
+    fn exact_query_limit(&self) -> usize {
+        if self.low_memory {
+            0
+        } else {
+            self.exact_query_limit
+        }
+    }
+
+    fn time_range_of(
+        &self,
+        mut timestamps: Vec<DateTime<Utc>>,
+        total_queries: u64,
+    ) -> Option<TimeRange> {
+        timestamps.sort_unstable();
+        let (&start, &end) = (timestamps.first()?, timestamps.last()?);
+
+        let span_seconds = (end - start).num_milliseconds() as f64 / 1_000.0;
+        let queries_per_second = if span_seconds > 0.0 {
+            total_queries as f64 / span_seconds
+        } else {
+            0.0
+        };
+        let active_days = timestamps
+            .iter()
+            .map(|timestamp| timestamp.date_naive())
+            .collect::<HashSet<_>>()
+            .len() as u64;
+        let active_hours = timestamps
+            .iter()
+            .map(|timestamp| (timestamp.date_naive(), timestamp.hour()))
+            .collect::<HashSet<_>>()
+            .len() as u64;
+        let gaps = timestamps
+            .windows(2)
+            .filter_map(|pair| {
+                let minutes = (pair[1] - pair[0]).num_milliseconds() as f64 / 60_000.0;
+                (minutes > self.gap_threshold_minutes).then_some(TimeGap {
+                    start: pair[0],
+                    end: pair[1],
+                    minutes,
+                })
+            })
+            .collect();
+
+        Some(TimeRange {
+            start,
+            end,
+            span_seconds,
+            queries_per_second,
+            active_days,
+            active_hours,
+            gaps,
+        })
+    }
+
+    /// Count durations per histogram bucket, fastest bucket first
+    pub fn duration_histogram(&self, durations: &[f64]) -> Vec<(String, u64)> {
+        let bounds = &self.histogram_bounds;
+        let mut counts = vec![0; bounds.len() + 1];
+        for duration in durations {
+            counts[bounds.partition_point(|bound| bound <= duration)] += 1;
+        }
+
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let lower = i.checked_sub(1).map(|lower| bounds[lower]);
+                (bucket_label(lower, bounds.get(i).copied()), count)
+            })
+            .collect()
+    }
+}
+
+/// Analyzer for SQL queries found in PostgreSQL logs
+pub struct QueryAnalyzer {
+    settings: QueryAnalyzerSettings,
+    /// Rules run over each distinct query when linting is on
+    linter: Option<Arc<QueryLinter>>,
 }
 
 impl QueryAnalyzer {
     /// Create a new query analyzer with default settings
     pub fn new() -> Self {
-        Self {
-            slow_query_threshold: 1000.0, // 1 second default
-            max_slow_queries: 10,
-            max_frequent_queries: 20,
-            literal_regex: Regex::new(r"\$(\d+)").unwrap(),
-            numeric_regex: Regex::new(r"\b\d+(?:\.\d+)?\b").unwrap(),
-            string_regex: Regex::new(r"'[^']*'").unwrap(),
-        }
+        Self::with_settings(1000.0, 10, 20) // 1 second default
     }
 
     /// Create a new query analyzer with custom settings
@@ -94,244 +1135,327 @@ impl QueryAnalyzer {
         max_frequent_queries: usize,
     ) -> Self {
         Self {
-            slow_query_threshold,
-            max_slow_queries,
-            max_frequent_queries,
-            literal_regex: Regex::new(r"\$(\d+)").unwrap(),
-            numeric_regex: Regex::new(r"\b\d+(?:\.\d+)?\b").unwrap(),
-            string_regex: Regex::new(r"'[^']*'").unwrap(),
+            settings: QueryAnalyzerSettings::new(
+                slow_query_threshold,
+                max_slow_queries,
+                max_frequent_queries,
+            ),
+            linter: None,
         }
     }
 
-    /// Get the slow query threshold (public for testing)
-    pub fn slow_query_threshold(&self) -> f64 {
-        self.slow_query_threshold
+    /// Estimate query counts with a bounded top-k counter from the start.
+    ///
+    /// Only the `top_k_capacity` most frequent distinct queries are kept, so
+    /// memory stays bounded however many distinct queries the log has. The
+    /// counts of listed queries may be overestimated; the result's
+    /// `frequency_estimate` says by how much.
+    pub fn with_low_memory(mut self, low_memory: bool) -> Self {
+        self.settings.low_memory = low_memory;
+        self
     }
 
-    /// Get the maximum number of slow queries to track (public for testing)
-    pub fn max_slow_queries(&self) -> usize {
-        self.max_slow_queries
+    /// Get whether query counts are estimated from the start
+    pub fn is_low_memory(&self) -> bool {
+        self.settings.low_memory
     }
 
-    /// Get the maximum number of frequent queries to track (public for testing)
-    pub fn max_frequent_queries(&self) -> usize {
-        self.max_frequent_queries
+    /// Keep this many distinct queries once query counts are estimated
+    pub fn with_top_k_capacity(mut self, capacity: usize) -> Self {
+        self.settings.top_k_capacity = capacity.max(1);
+        self
     }
 
-    /// Analyze queries from log entries
-    pub fn analyze(&self, entries: &[LogEntry]) -> Result<AnalysisResult> {
-        let events = normalize_log_entries(entries, EventSourceKind::Stderr);
-        self.analyze_events(&events)
+    /// Get the number of distinct queries kept once counts are estimated
+    pub fn top_k_capacity(&self) -> usize {
+        self.settings.top_k_capacity
     }
 
-    /// Analyze queries from normalized events.
-    pub fn analyze_events(&self, events: &[NormalizedEvent]) -> Result<AnalysisResult> {
-        if events.is_empty() {
-            return Ok(AnalysisResult::new());
-        }
+    /// Count up to this many distinct queries exactly, then switch to
+    /// estimated counts as with [`with_low_memory`](Self::with_low_memory)
+    pub fn with_exact_query_limit(mut self, limit: usize) -> Self {
+        self.settings.exact_query_limit = limit;
+        self
+    }
 
-        let mut result = AnalysisResult::new();
-        let mut query_durations = Vec::new();
-        let mut query_counts = HashMap::new();
-        let mut query_type_counts = HashMap::new();
-        let mut hourly_stats = HashMap::new();
-        let mut slow_queries = Vec::new();
-        let mut error_count = 0;
-        let mut connection_count = 0;
-
-        let executions = ProcessOrderCorrelator.correlate(events);
-        for execution in &executions {
-            let duration = execution.duration_ms.unwrap_or(0.0);
-            let normalized_concat = Some(execution.query_family.normalized_sql.clone());
-            for query in &execution.queries {
-                let normalized_query = query.normalized_query.clone();
-                let query_type = &query.query_type;
-
-                // Update query counts
-                *query_counts.entry(normalized_query).or_insert(0) += 1;
-                *query_type_counts.entry(query_type).or_insert(0) += 1;
-            }
+    /// Get the number of distinct queries counted exactly; zero in low-memory
+    /// mode
+    pub fn exact_query_limit(&self) -> usize {
+        self.settings.exact_query_limit()
+    }
 
-            // Track slow queries
-            if let Some(ref n) = normalized_concat {
-                if duration > self.slow_query_threshold {
-                    slow_queries.push((n.clone(), duration));
-                }
-            }
+    /// Run `linter` over the first logged SQL of each distinct query and list
+    /// the matches in the result's `query_warnings`
+    pub fn with_linter(mut self, linter: Option<QueryLinter>) -> Self {
+        self.linter = linter.map(Arc::new);
+        self
+    }
 
-            // Update hourly statistics
-            let hour = execution.timestamp.hour();
-            let hourly = hourly_stats.entry(hour).or_insert_with(|| HourlyStats {
-                hour,
-                query_count: 0,
-                queries_per_second: 0.0,
-                total_duration: 0.0,
-                average_duration: 0.0,
-            });
-            hourly.query_count += 1;
-            hourly.total_duration += duration;
-            result.total_queries += 1;
-            query_durations.push(duration);
-            result.total_duration += duration;
-        }
+    /// Get the query linter, when linting is on
+    pub fn linter(&self) -> Option<&QueryLinter> {
+        self.linter.as_deref()
+    }
 
-        for event in events {
-            if event.is_error() {
-                error_count += 1;
-            } else if event.message().to_lowercase().contains("connection") {
-                connection_count += 1;
-            }
+    /// Use custom duration histogram bucket bounds in milliseconds.
+    ///
+    /// Each bound starts a new bucket, so a duration equal to a bound is counted
+    /// in the bucket above it. Bounds are sorted; duplicates and values that
+    /// are not positive and finite are dropped.
+    pub fn with_histogram_buckets(mut self, bounds: impl IntoIterator<Item = f64>) -> Self {
+        let mut bounds: Vec<f64> = bounds
+            .into_iter()
+            .filter(|bound| bound.is_finite() && *bound > 0.0)
+            .collect();
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        self.settings.histogram_bounds = bounds;
+        self
+    }
+
+    /// Get the duration histogram bucket bounds in milliseconds
+    pub fn histogram_bounds(&self) -> &[f64] {
+        &self.settings.histogram_bounds
+    }
+
+    /// Report stretches without entries longer than `minutes` as gaps in the
+    /// time range. Non-positive or non-finite values are ignored.
+    pub fn with_gap_threshold_minutes(mut self, minutes: f64) -> Self {
+        if minutes.is_finite() && minutes > 0.0 {
+            self.settings.gap_threshold_minutes = minutes;
         }
+        self
+    }
 
-        // Calculate performance metrics
-        let metrics = self.calculate_metrics(&query_durations);
-        result.average_duration = metrics.average_duration;
-        result.p95_duration = metrics.p95_duration;
-        result.p99_duration = metrics.p99_duration;
+    /// Get the gap threshold in minutes
+    pub fn gap_threshold_minutes(&self) -> f64 {
+        self.settings.gap_threshold_minutes
+    }
 
-        // Update error and connection counts
-        result.error_count = error_count;
-        result.connection_count = connection_count;
+    /// Time window of `events`, the query rate over it, and the gaps longer
+    /// than the gap threshold. `None` when there are no events.
+    pub fn time_range(&self, events: &[NormalizedEvent], total_queries: u64) -> Option<TimeRange> {
+        self.settings.time_range_of(
+            events.iter().map(|event| event.timestamp).collect(),
+            total_queries,
+        )
+    }
 
-        // Find top slowest queries
-        slow_queries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        result.slowest_queries = slow_queries
-            .into_iter()
-            .take(self.max_slow_queries)
-            .collect();
+    /// Count durations per histogram bucket, fastest bucket first
+    pub fn duration_histogram(&self, durations: &[f64]) -> Vec<(String, u64)> {
+        self.settings.duration_histogram(durations)
+    }
 
-        // Find top most frequent queries
-        let mut frequent_queries: Vec<_> = query_counts.into_iter().collect();
-        frequent_queries.sort_by_key(|query| Reverse(query.1));
-        result.most_frequent_queries = frequent_queries
-            .into_iter()
-            .take(self.max_frequent_queries)
-            .collect();
+    /// Set how many of the slowest queries are listed per application
+    pub fn with_max_application_queries(mut self, max_application_queries: usize) -> Self {
+        self.settings.max_application_queries = max_application_queries;
+        self
+    }
 
-        // Update query type distribution
-        result.query_types = query_type_counts
-            .into_iter()
-            .map(|(query_type, count)| (query_type.to_string(), count))
-            .collect();
+    /// Get the maximum number of slowest queries listed per application
+    pub fn max_application_queries(&self) -> usize {
+        self.settings.max_application_queries
+    }
 
-        // Calculate queries per second for hourly buckets
-        self.calculate_queries_per_second(&mut hourly_stats, events);
+    /// Set how many client hosts are listed
+    pub fn with_max_client_hosts(mut self, max_client_hosts: usize) -> Self {
+        self.settings.max_client_hosts = max_client_hosts;
+        self
+    }
 
-        Ok(result)
+    /// Get the maximum number of client hosts listed
+    pub fn max_client_hosts(&self) -> usize {
+        self.settings.max_client_hosts
     }
 
-    /// Normalize SQL query by replacing literals with placeholders
-    pub fn normalize_query(&self, sql: &str) -> String {
-        let mut normalized = sql.trim().to_string();
+    /// Keep up to `max_query_examples` of the slowest executions of each
+    /// normalized query as examples, with their literals; 0 keeps none
+    pub fn with_max_query_examples(mut self, max_query_examples: usize) -> Self {
+        self.settings.max_query_examples = max_query_examples;
+        self
+    }
 
-        // Replace parameter placeholders ($1, $2, etc.)
-        normalized = self.literal_regex.replace_all(&normalized, "?").to_string();
+    /// Get the number of example executions kept per normalized query
+    pub fn max_query_examples(&self) -> usize {
+        self.settings.max_query_examples
+    }
 
-        // Replace numeric literals
-        normalized = self.numeric_regex.replace_all(&normalized, "N").to_string();
+    /// Set how many characters of each unparsed statement are kept as a sample
+    pub fn with_unparsed_sample_length(mut self, unparsed_sample_length: usize) -> Self {
+        self.settings.unparsed_sample_length = unparsed_sample_length;
+        self
+    }
 
-        // Replace string literals
-        normalized = self.string_regex.replace_all(&normalized, "S").to_string();
+    /// Bucket the hourly stats by the hour of day in `timezone` instead of UTC
+    pub fn with_bucket_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.settings.bucket_timezone = timezone;
+        self
+    }
 
-        // Normalize whitespace
-        normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+    /// Get the timezone the hourly stats are bucketed in; `None` for UTC
+    pub fn bucket_timezone(&self) -> Option<Tz> {
+        self.settings.bucket_timezone
     }
 
-    /// Classify query type based on SQL content
-    pub fn classify_query(&self, sql: &str) -> QueryType {
-        let sql_upper = sql.trim().to_uppercase();
+    /// Count queries and errors over the time range in buckets of
+    /// `granularity`, widened to fit [`MAX_TRAFFIC_ROWS`]; `None` turns the
+    /// traffic histogram off
+    pub fn with_traffic_histogram(mut self, granularity: Option<TrafficGranularity>) -> Self {
+        self.settings.traffic_histogram = granularity;
+        self
+    }
 
-        if sql_upper.starts_with("SELECT") {
-            QueryType::Select
-        } else if sql_upper.starts_with("INSERT") {
-            QueryType::Insert
-        } else if sql_upper.starts_with("UPDATE") {
-            QueryType::Update
-        } else if sql_upper.starts_with("DELETE") {
-            QueryType::Delete
-        } else if sql_upper.starts_with("CREATE")
-            || sql_upper.starts_with("DROP")
-            || sql_upper.starts_with("ALTER")
-            || sql_upper.starts_with("TRUNCATE")
-            || sql_upper.starts_with("GRANT")
-            || sql_upper.starts_with("REVOKE")
-        {
-            QueryType::DDL
-        } else {
-            QueryType::Other
-        }
+    /// Get the bucket width of the traffic histogram, if one is counted
+    pub fn traffic_histogram(&self) -> Option<TrafficGranularity> {
+        self.settings.traffic_histogram
     }
 
-    /// Calculate performance metrics from durations (public for testing)
-    pub fn calculate_metrics(&self, durations: &[f64]) -> QueryMetrics {
-        if durations.is_empty() {
-            return QueryMetrics::default();
-        }
+    /// Set the matcher of pooler maintenance statements, counted in
+    /// `pooler_maintenance_count`
+    pub fn with_pooler_noise(mut self, pooler_noise: PoolerNoise) -> Self {
+        self.settings.pooler_noise = pooler_noise;
+        self
+    }
 
-        let total_queries = durations.len() as u64;
-        let total_duration = durations.iter().sum::<f64>();
-        let average_duration = total_duration / total_queries as f64;
+    /// Get the matcher of pooler maintenance statements
+    pub fn pooler_noise(&self) -> &PoolerNoise {
+        &self.settings.pooler_noise
+    }
 
-        let min_duration = durations.iter().fold(f64::INFINITY, |a, &b| a.min(b));
-        let max_duration = durations.iter().fold(0.0_f64, |a, &b| a.max(b));
+    /// Leave executions made only of pooler maintenance statements out of
+    /// the query counts, lists, and durations
+    pub fn with_ignore_pooler_noise(mut self, ignore: bool) -> Self {
+        self.settings.ignore_pooler_noise = ignore;
+        self
+    }
 
-        // Calculate percentiles
-        let mut sorted_durations = durations.to_vec();
-        sorted_durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    /// Get whether pooler maintenance executions are left out
+    pub fn ignores_pooler_noise(&self) -> bool {
+        self.settings.ignore_pooler_noise
+    }
 
-        let p95_index = (sorted_durations.len() as f64 * 0.95) as usize;
-        let p99_index = (sorted_durations.len() as f64 * 0.99) as usize;
+    /// Parse checkpoint and autovacuum messages as PostgreSQL `major` writes
+    /// them, whatever version the log names; `None` detects it
+    pub fn with_assume_pg_version(mut self, major: Option<u32>) -> Self {
+        self.settings.assume_pg_version = major;
+        self
+    }
 
-        let p95_duration = sorted_durations[p95_index.min(sorted_durations.len() - 1)];
-        let p99_duration = sorted_durations[p99_index.min(sorted_durations.len() - 1)];
+    /// Get the PostgreSQL major version assumed
+    pub fn assumed_pg_version(&self) -> Option<u32> {
+        self.settings.assume_pg_version
+    }
 
-        QueryMetrics {
-            min_duration,
-            max_duration,
-            average_duration,
-            p95_duration,
-            p99_duration,
-            total_queries,
-            total_duration,
-        }
+    /// Set how many of the most canceled statements are listed
+    pub fn with_max_canceled_queries(mut self, max_canceled_queries: usize) -> Self {
+        self.settings.max_canceled_queries = max_canceled_queries;
+        self
     }
 
-    /// Calculate queries per second for hourly buckets
-    fn calculate_queries_per_second(
-        &self,
-        hourly_stats: &mut HashMap<u32, HourlyStats>,
-        events: &[NormalizedEvent],
-    ) {
-        // Group entries by hour to calculate time spans
-        let mut hourly_entries: HashMap<u32, Vec<DateTime<Utc>>> = HashMap::new();
+    /// Get the maximum number of most canceled statements listed
+    pub fn max_canceled_queries(&self) -> usize {
+        self.settings.max_canceled_queries
+    }
+
+    /// Set how many LOG, WARNING, and NOTICE message templates are listed
+    pub fn with_max_log_events(mut self, max_log_events: usize) -> Self {
+        self.settings.max_log_events = max_log_events;
+        self
+    }
+
+    /// Get the maximum number of message templates listed
+    pub fn max_log_events(&self) -> usize {
+        self.settings.max_log_events
+    }
+
+    /// Count each client port separately instead of aggregating a host's
+    /// ephemeral ports
+    pub fn with_client_ports(mut self, keep_client_ports: bool) -> Self {
+        self.settings.keep_client_ports = keep_client_ports;
+        self
+    }
+
+    /// Whether client ports are kept in client host keys
+    pub fn keeps_client_ports(&self) -> bool {
+        self.settings.keep_client_ports
+    }
+
+    /// Also track the slowest and most frequent queries per value of `group_by`
+    pub fn with_group_by(mut self, group_by: Option<GroupBy>) -> Self {
+        self.settings.group_by = group_by;
+        self
+    }
+
+    /// Get the grouping dimension, if any
+    pub fn group_by(&self) -> Option<GroupBy> {
+        self.settings.group_by
+    }
+
+    /// Get the slow query threshold (public for testing)
+    pub fn slow_query_threshold(&self) -> f64 {
+        self.settings.slow_query_threshold
+    }
+
+    /// List the `max_frequent_queries` queries ranking highest by `sort`
+    /// instead of the most executed ones
+    pub fn with_query_sort(mut self, sort: QuerySort) -> Self {
+        self.settings.query_sort = sort;
+        self
+    }
+
+    /// Get the metric the most frequent queries are ranked by
+    pub fn query_sort(&self) -> QuerySort {
+        self.settings.query_sort
+    }
+
+    /// Attribute the duration of a multi-statement line as `batch_duration`
+    /// says
+    pub fn with_batch_duration(mut self, batch_duration: BatchDuration) -> Self {
+        self.settings.batch_duration = batch_duration;
+        self
+    }
+
+    /// Get how the duration of a multi-statement line is attributed
+    pub fn batch_duration(&self) -> BatchDuration {
+        self.settings.batch_duration
+    }
+
+    /// Get the maximum number of slow queries to track (public for testing)
+    pub fn max_slow_queries(&self) -> usize {
+        self.settings.max_slow_queries
+    }
+
+    /// Get the maximum number of frequent queries to track (public for testing)
+    pub fn max_frequent_queries(&self) -> usize {
+        self.settings.max_frequent_queries
+    }
+
+    /// Analyze queries from log entries
+    pub fn analyze(&self, entries: &[LogEntry]) -> Result<AnalysisResult> {
+        let events = normalize_log_entries(entries, EventSourceKind::Stderr);
+        self.analyze_events(&events)
+    }
 
+    /// Analyze queries from normalized events.
+    pub fn analyze_events(&self, events: &[NormalizedEvent]) -> Result<AnalysisResult> {
+        let mut state = self.state();
+        for execution in &ProcessOrderCorrelator.correlate(events) {
+            state.record_execution(execution);
+        }
         for event in events {
-            if event.is_query() {
-                let hour = event.timestamp.hour();
-                hourly_entries
-                    .entry(hour)
-                    .or_default()
-                    .push(event.timestamp);
-            }
+            state.record_event(event);
         }
+        Ok(state.into_result())
+    }
 
-        for (hour, timestamps) in hourly_entries {
-            if let Some(stats) = hourly_stats.get_mut(&hour) {
-                if timestamps.len() > 1 {
-                    let min_time = timestamps.iter().min().unwrap();
-                    let max_time = timestamps.iter().max().unwrap();
-                    let duration_seconds = (*max_time - *min_time).num_seconds() as f64;
-
-                    if duration_seconds > 0.0 {
-                        stats.queries_per_second = stats.query_count as f64 / duration_seconds;
-                    }
-                }
+    /// Start an incremental analysis that is fed one entry at a time with
+    /// [`QueryAnalyzerState::observe`]
+    pub fn state(&self) -> QueryAnalyzerState {
+        QueryAnalyzerState::new(self)
+    }
 
-                if stats.query_count > 0 {
-                    stats.average_duration = stats.total_duration / stats.query_count as f64;
-                }
-            }
-        }
+    /// Calculate performance metrics from durations (public for testing)
+    pub fn calculate_metrics(&self, durations: &[f64]) -> QueryMetrics {
+        calculate_metrics(durations)
     }
 
     /// Find slow queries above a threshold
@@ -397,10 +1521,661 @@ impl Default for QueryAnalyzer {
     }
 }
 
+/// Query analysis in progress, fed one entry at a time.
+///
+/// Created with [`QueryAnalyzer::state`]. Statements are paired with their
+/// durations by a [`StreamingCorrelator`], so for chronologically ordered
+/// entries [`finish`](Self::finish) returns the same result as
+/// [`QueryAnalyzer::analyze`] on the whole list. States of consecutive parts
+/// of a log can be combined with [`merge`](Self::merge), and a state can be
+/// serialized to checkpoint an analysis. Lint rules are not serialized: a
+/// deserialized state keeps the warnings found so far but lints no new
+/// queries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryAnalyzerState {
+    settings: QueryAnalyzerSettings,
+    #[serde(skip)]
+    linter: Option<Arc<QueryLinter>>,
+    /// Whether the result lists query warnings
+    linting: bool,
+    correlator: StreamingCorrelator,
+    /// Counts that need no post-processing, in their final place
+    result: AnalysisResult,
+    event_count: usize,
+    timestamps: Vec<DateTime<Utc>>,
+    query_durations: Vec<f64>,
+    #[serde(default)]
+    duration_moments: DurationMoments,
+    interner: QueryInterner,
+    /// Duration of each execution by type
+    #[serde(default)]
+    query_type_durations: HashMap<QueryType, Vec<f64>>,
+    hourly_stats: HashMap<u32, HourlyStats>,
+    hourly_durations: HashMap<u32, Vec<f64>>,
+    /// First and last statement timestamp per hour
+    hourly_spans: HashMap<u32, (DateTime<Utc>, DateTime<Utc>)>,
+    slow_queries: SlowQueryHeap,
+    connection_count: u64,
+    groups: HashMap<String, GroupTracker>,
+    applications: HashMap<String, ApplicationTracker>,
+    clients: HashMap<String, ClientTracker>,
+    cancellations: CancellationTracker,
+    copies: CopyTracker,
+    /// Replication and recovery events, sorted when the result is built
+    #[serde(default)]
+    replication_events: Vec<(DateTime<Utc>, ReplicationEventKind)>,
+    /// LOG, WARNING, and NOTICE messages by template
+    #[serde(default)]
+    log_events: LogEventTracker,
+    /// Queries and errors per minute, when a traffic histogram is requested
+    #[serde(default)]
+    traffic: TrafficCounter,
+    /// SQL of each prepared statement, per session
+    #[serde(default)]
+    prepared: PreparedStatementTracker,
+    linted: HashSet<Arc<str>>,
+    lint_matches: Vec<LintMatch>,
+}
+
+impl QueryAnalyzerState {
+    fn new(analyzer: &QueryAnalyzer) -> Self {
+        let settings = analyzer.settings.clone();
+        let mut result = AnalysisResult::new();
+        result.pg_version = settings.assume_pg_version.map(PgVersion::assumed);
+        Self {
+            linter: analyzer.linter.clone(),
+            linting: analyzer.linter.is_some(),
+            correlator: StreamingCorrelator::new(),
+            result,
+            event_count: 0,
+            timestamps: Vec::new(),
+            query_durations: Vec::new(),
+            duration_moments: DurationMoments::default(),
+            interner: QueryInterner::new(
+                settings.exact_query_limit(),
+                settings.top_k_capacity,
+                settings.max_query_examples,
+            ),
+            query_type_durations: HashMap::new(),
+            hourly_stats: HashMap::new(),
+            hourly_durations: HashMap::new(),
+            hourly_spans: HashMap::new(),
+            slow_queries: SlowQueryHeap::new(settings.max_slow_queries),
+            connection_count: 0,
+            groups: HashMap::new(),
+            applications: HashMap::new(),
+            clients: HashMap::new(),
+            cancellations: CancellationTracker::default(),
+            copies: CopyTracker::default(),
+            replication_events: Vec::new(),
+            log_events: LogEventTracker::default(),
+            traffic: TrafficCounter::default(),
+            prepared: PreparedStatementTracker::new(),
+            linted: HashSet::new(),
+            lint_matches: Vec::new(),
+            settings,
+        }
+    }
+
+    /// Add one log entry to the analysis
+    pub fn observe(&mut self, entry: &LogEntry) {
+        let event =
+            NormalizedEvent::from_log_entry(entry, EventSourceKind::Stderr, self.event_count);
+        self.observe_event(&event);
+    }
+
+    /// Add one normalized event to the analysis
+    pub fn observe_event(&mut self, event: &NormalizedEvent) {
+        self.record_event(event);
+        for execution in self.correlator.push(event) {
+            self.record_execution(&execution);
+        }
+    }
+
+    /// Add the analysis of `other`, which was fed the entries following the
+    /// ones fed to this state.
+    ///
+    /// A statement still waiting for a duration in `other` replaces one of the
+    /// same process waiting here, which is counted without a duration.
+    pub fn merge(&mut self, other: Self) {
+        self.correlator.merge(other.correlator);
+        self.prepared.merge(other.prepared);
+        merge_counts(&mut self.result, other.result);
+        self.event_count += other.event_count;
+        self.timestamps.extend(other.timestamps);
+        self.query_durations.extend(other.query_durations);
+        self.duration_moments.merge(other.duration_moments);
+        self.interner.merge(other.interner);
+        for (query_type, durations) in other.query_type_durations {
+            self.query_type_durations
+                .entry(query_type)
+                .or_default()
+                .extend(durations);
+        }
+        for (hour, theirs) in other.hourly_stats {
+            let hourly = self
+                .hourly_stats
+                .entry(hour)
+                .or_insert_with(|| HourlyStats::empty(hour));
+            hourly.query_count += theirs.query_count;
+            hourly.total_duration += theirs.total_duration;
+            hourly.error_count += theirs.error_count;
+        }
+        for (hour, durations) in other.hourly_durations {
+            self.hourly_durations
+                .entry(hour)
+                .or_default()
+                .extend(durations);
+        }
+        for (hour, (first, last)) in other.hourly_spans {
+            self.hourly_spans
+                .entry(hour)
+                .and_modify(|span| {
+                    span.0 = span.0.min(first);
+                    span.1 = span.1.max(last);
+                })
+                .or_insert((first, last));
+        }
+        self.slow_queries.merge(other.slow_queries);
+        self.connection_count += other.connection_count;
+        for (key, theirs) in other.groups {
+            match self.groups.entry(key) {
+                hash_map::Entry::Occupied(mut group) => group.get_mut().merge(theirs),
+                hash_map::Entry::Vacant(group) => {
+                    group.insert(theirs);
+                }
+            }
+        }
+        for (name, theirs) in other.applications {
+            let application = self.applications.entry(name).or_default();
+            application.merge(theirs);
+            if self.interner.is_approximate() {
+                application.keep_slowest(self.settings.max_application_queries);
+            }
+        }
+        for (key, theirs) in other.clients {
+            self.clients.entry(key).or_default().merge(theirs);
+        }
+        self.cancellations.merge(other.cancellations);
+        self.copies.merge(other.copies);
+        self.replication_events.extend(other.replication_events);
+        self.log_events.merge(other.log_events);
+        self.traffic.merge(other.traffic);
+
+        self.linting |= other.linting;
+        if self.linter.is_none() {
+            self.linter = other.linter;
+        }
+        let linted = &self.linted;
+        self.lint_matches.extend(
+            other
+                .lint_matches
+                .into_iter()
+                .filter(|lint| !linted.contains(&lint.query)),
+        );
+        self.linted.extend(other.linted);
+    }
+
+    /// Keep only the `top_k_capacity` most frequent queries from now on, as in
+    /// low-memory mode, so query counts become estimates
+    pub fn estimate_query_counts(&mut self) {
+        let settings = &mut self.settings;
+        settings.low_memory = true;
+        self.interner.bound();
+        for group in self.groups.values_mut() {
+            if group.query_counts.capacity().is_none() {
+                group.query_counts.bound(settings.top_k_capacity);
+            }
+        }
+        for application in self.applications.values_mut() {
+            application.keep_slowest(settings.max_application_queries);
+        }
+    }
+
+    /// The result of the analysis so far, with statements still waiting for a
+    /// duration counted without one
+    pub fn finish(&self) -> AnalysisResult {
+        self.clone().into_result()
+    }
+
+    /// [`finish`](Self::finish) without copying the state
+    pub fn into_result(mut self) -> AnalysisResult {
+        for execution in std::mem::take(&mut self.correlator).finish() {
+            self.record_execution(&execution);
+        }
+        self.build()
+    }
+
+    /// Count a correlated execution
+    fn record_execution(&mut self, execution: &QueryExecution) {
+        let batch = execution.queries.len() > 1;
+        if batch && self.settings.batch_duration == BatchDuration::Split {
+            for part in execution.split_batch() {
+                self.record_execution(&part);
+            }
+            return;
+        }
+        let settings = &self.settings;
+        let pooler_maintenance = !execution.queries.is_empty()
+            && execution
+                .queries
+                .iter()
+                .all(|query| settings.pooler_noise.is_match(&query.normalized_query));
+        if pooler_maintenance {
+            self.result.pooler_maintenance_count += 1;
+            if settings.ignore_pooler_noise {
+                return;
+            }
+        }
+        let duration = execution.duration_ms.unwrap_or(0.0);
+        if execution.duration_ms.is_none() {
+            self.result.queries_without_duration += 1;
+        }
+        let mut group = settings.group_by.map(|group_by| {
+            self.groups
+                .entry(group_by.key(&execution.session).to_string())
+                .or_insert_with(|| {
+                    GroupTracker::new(
+                        settings.max_slow_queries,
+                        settings.low_memory.then_some(settings.top_k_capacity),
+                    )
+                })
+        });
+        // Statements the parser rejected are classified, but kept out of the
+        // per-query lists since their text is not normalized
+        let unparsed = execution.queries.iter().any(|query| query.unparsed);
+        if unparsed {
+            self.result.unparsed_statements.record(
+                &execution.statement,
+                duration,
+                settings.unparsed_sample_length,
+            );
+        }
+        // Split batches were recorded statement by statement above, so a
+        // batch left here is one query of its joined statements
+        if let Some(query_type) = execution_query_type(&execution.queries) {
+            self.query_type_durations
+                .entry(query_type)
+                .or_default()
+                .push(duration);
+        }
+        if !unparsed && !execution.queries.is_empty() {
+            let sql = self.interner.count(&execution.query_family.normalized_sql);
+            if let Some(linter) = &self.linter {
+                if self.linted.insert(sql.clone()) {
+                    for query in &execution.queries {
+                        self.lint_matches
+                            .extend(linter.lint(&query.sql).into_iter().map(|rule| LintMatch {
+                                query: sql.clone(),
+                                rule: rule.name().to_string(),
+                                description: rule.description(),
+                            }));
+                    }
+                }
+            }
+            if let Some(group) = group.as_mut() {
+                group.query_counts.increment(sql);
+            }
+        }
+        if execution.confidence == CorrelationConfidence::DurationOnly {
+            let sql = self.interner.count(STATEMENT_NOT_LOGGED);
+            if let Some(group) = group.as_mut() {
+                group.query_counts.increment(sql);
+            }
+            self.result.durations_without_statement += 1;
+            // No statement event marks this query in the hourly span
+            track_hourly_span(
+                &mut self.hourly_spans,
+                execution.timestamp,
+                self.settings.bucket_timezone,
+            );
+        }
+
+        let family = (!unparsed).then(|| {
+            self.interner
+                .add_execution(&execution.query_family.normalized_sql, execution, duration)
+        });
+
+        // Track slow queries
+        if let Some(family) = family
+            .as_ref()
+            .filter(|_| duration > settings.slow_query_threshold)
+        {
+            self.slow_queries.push(family.clone(), duration);
+            if let Some(group) = group.as_mut() {
+                group.slow_queries.push(family.clone(), duration);
+            }
+        }
+        if let Some(group) = group {
+            group.total_queries += 1;
+            group.total_duration += duration;
+        }
+
+        if let Some(copy) = execution
+            .queries
+            .iter()
+            .filter(|query| query.query_type == QueryType::Copy)
+            .find_map(|query| CopyStatement::parse(&query.sql))
+        {
+            self.copies.record(&copy, duration);
+        }
+
+        let application = self
+            .applications
+            .entry(application_key(&execution.session).to_string())
+            .or_default();
+        application.durations.push(duration);
+        if let Some(family) = family {
+            let slowest = application.slowest.entry(family).or_insert(duration);
+            *slowest = slowest.max(duration);
+        }
+        if self.interner.is_approximate() {
+            application.keep_slowest(settings.max_application_queries);
+        }
+
+        if let Some(key) = client_key(&execution.session, settings.keep_client_ports) {
+            let client = self.clients.entry(key).or_default();
+            client.query_count += 1;
+            client.total_duration += duration;
+        }
+
+        // Update per-user and per-database breakdowns
+        let result = &mut self.result;
+        let session = &execution.session;
+        let user = session.user.as_deref().unwrap_or(UNKNOWN_BREAKDOWN_KEY);
+        *result.queries_by_user.entry(user.to_string()).or_insert(0) += 1;
+        *result
+            .duration_by_user
+            .entry(user.to_string())
+            .or_insert(0.0) += duration;
+        let database = session.database.as_deref().unwrap_or(UNKNOWN_BREAKDOWN_KEY);
+        *result
+            .queries_by_database
+            .entry(database.to_string())
+            .or_insert(0) += 1;
+
+        // Update hourly statistics
+        let hour = hour_in(execution.timestamp, self.settings.bucket_timezone);
+        let hourly = self
+            .hourly_stats
+            .entry(hour)
+            .or_insert_with(|| HourlyStats::empty(hour));
+        hourly.query_count += 1;
+        hourly.total_duration += duration;
+        self.hourly_durations
+            .entry(hour)
+            .or_default()
+            .push(duration);
+        result.total_queries += 1;
+        if self.settings.traffic_histogram.is_some() {
+            self.traffic.record_query(execution.timestamp);
+        }
+        self.query_durations.push(duration);
+        self.duration_moments.add(duration);
+        result.total_duration += duration;
+    }
+
+    /// Count the parts of an event that do not depend on correlation
+    fn record_event(&mut self, event: &NormalizedEvent) {
+        let keep_client_ports = self.settings.keep_client_ports;
+        self.event_count += 1;
+        self.timestamps.push(event.timestamp);
+
+        if event.is_query() {
+            track_hourly_span(
+                &mut self.hourly_spans,
+                event.timestamp,
+                self.settings.bucket_timezone,
+            );
+        }
+        if let Some(kind) = ReplicationEventKind::from_message(event.message()) {
+            self.replication_events.push((event.timestamp, kind));
+        }
+        self.record_maintenance(event.message());
+        self.result.io.record(event.message());
+        self.log_events.record(event);
+        self.prepared
+            .record(event, &mut self.result.prepared_statements);
+
+        if let EventKind::Error(error) = &event.kind {
+            let result = &mut self.result;
+            result.add_error_with_statement(error.sqlstate.as_deref(), error.statement.as_deref());
+            *result
+                .errors_by_severity
+                .entry(error.severity.to_string())
+                .or_insert(0) += 1;
+            let database = event
+                .session
+                .database
+                .as_deref()
+                .unwrap_or(UNKNOWN_BREAKDOWN_KEY);
+            *result
+                .errors_by_database
+                .entry(database.to_string())
+                .or_insert(0) += 1;
+            self.applications
+                .entry(application_key(&event.session).to_string())
+                .or_default()
+                .error_count += 1;
+            if let Some(key) = client_key(&event.session, keep_client_ports) {
+                self.clients.entry(key).or_default().error_count += 1;
+            }
+            let hour = hour_in(event.timestamp, self.settings.bucket_timezone);
+            self.hourly_stats
+                .entry(hour)
+                .or_insert_with(|| HourlyStats::empty(hour))
+                .error_count += 1;
+            if self.settings.traffic_histogram.is_some() {
+                self.traffic.record_error(event.timestamp);
+            }
+            if let Some(reason) = CancelReason::from_message(&error.message) {
+                self.cancellations
+                    .record(reason, error.statement.as_deref(), hour);
+            }
+            if let Some(table) = &error.copy_table {
+                self.copies.record_error(table);
+            }
+        } else if let Some(kind) = event.connection_event() {
+            self.connection_count += 1;
+            if kind == ConnectionEventKind::Received {
+                if let Some(key) = client_key(&event.session, keep_client_ports) {
+                    self.clients.entry(key).or_default().connection_count += 1;
+                }
+            }
+        }
+    }
+
+    /// Note the PostgreSQL version of a startup banner, and count checkpoint
+    /// and autovacuum messages with the patterns of the version known so far
+    fn record_maintenance(&mut self, message: &str) {
+        let result = &mut self.result;
+        if let Some(version) = PgVersion::from_banner(message) {
+            PgVersion::update(&mut result.pg_version, version);
+        } else if result.pg_version.is_none() {
+            result.pg_version = PgVersion::from_message_shape(message);
+        }
+        result.maintenance.record(message, result.pg_version);
+    }
+
+    /// Build the result from the recorded events and executions
+    fn build(self) -> AnalysisResult {
+        if self.event_count == 0 {
+            return AnalysisResult::new();
+        }
+
+        let settings = self.settings;
+        let mut result = self.result;
+        let interner = self.interner;
+
+        // Calculate performance metrics
+        result.duration_histogram = settings.duration_histogram(&self.query_durations);
+        let metrics = calculate_metrics(&self.query_durations);
+        result.average_duration = metrics.average_duration;
+        result.stddev_duration = self.duration_moments.stddev();
+        result.min_duration = self.duration_moments.min;
+        result.p95_duration = metrics.p95_duration;
+        result.p99_duration = metrics.p99_duration;
+        result.max_duration = self.duration_moments.max;
+        result.slow_query_count = self
+            .query_durations
+            .iter()
+            .filter(|duration| **duration > settings.slow_query_threshold)
+            .count() as u64;
+
+        // Update connection count
+        result.connection_count = self.connection_count;
+        result.time_range = settings.time_range_of(self.timestamps, result.total_queries);
+
+        // Find top slowest queries
+        result.slowest_queries = self.slow_queries.into_sorted_vec();
+
+        // Find top most frequent queries
+        result.most_frequent_queries =
+            interner.top_by(settings.query_sort, settings.max_frequent_queries);
+        result.frequency_estimate = interner.estimate();
+        result
+            .prepared_statements
+            .keep_most_executed(settings.max_frequent_queries);
+        if self.linting {
+            result.query_warnings = Some(query_warnings(self.lint_matches, &interner));
+        }
+        result.query_stats = result
+            .slowest_queries
+            .iter()
+            .map(|(sql, _)| sql)
+            .chain(result.most_frequent_queries.iter().map(|(sql, _)| sql))
+            .filter_map(|sql| Some((sql.clone(), interner.stats(sql)?)))
+            .collect();
+        result.all_query_stats = interner.all_stats();
+
+        let mut applications: Vec<_> = self
+            .applications
+            .into_iter()
+            .map(|(name, tracker)| tracker.into_stats(&name, settings.max_application_queries))
+            .collect();
+        applications.sort_by(|a, b| {
+            b.total_duration
+                .total_cmp(&a.total_duration)
+                .then_with(|| a.application_name.cmp(&b.application_name))
+        });
+        result.applications = applications;
+
+        let mut client_hosts: Vec<_> = self
+            .clients
+            .into_iter()
+            .map(|(host, tracker)| tracker.into_stats(host))
+            .collect();
+        client_hosts.sort_by(|a, b| {
+            b.total_duration
+                .total_cmp(&a.total_duration)
+                .then_with(|| b.connection_count.cmp(&a.connection_count))
+                .then_with(|| a.client_host.cmp(&b.client_host))
+        });
+        client_hosts.truncate(settings.max_client_hosts);
+        result.client_hosts = client_hosts;
+        result.cancellations = self.cancellations.into_stats(settings.max_canceled_queries);
+        result.copy_stats = self.copies.into_stats();
+        result.replication = ReplicationStats::from_events(self.replication_events);
+        result.log_events = self.log_events.into_stats(settings.max_log_events);
+        result.traffic_histogram = settings
+            .traffic_histogram
+            .and_then(|granularity| self.traffic.histogram(granularity, MAX_TRAFFIC_ROWS));
+
+        result.group_by = settings.group_by;
+        result.query_groups = self
+            .groups
+            .into_iter()
+            .map(|(key, group)| (key, group.into_group(settings.max_frequent_queries)))
+            .collect();
+
+        // Update query type distribution
+        result.query_types = self
+            .query_type_durations
+            .into_iter()
+            .map(|(query_type, durations)| {
+                let metrics = calculate_metrics(&durations);
+                let stats = QueryTypeStats {
+                    count: metrics.total_queries,
+                    total_duration: metrics.total_duration,
+                    average_duration: metrics.average_duration,
+                    p95_duration: metrics.p95_duration,
+                    max_duration: metrics.max_duration,
+                };
+                (query_type.to_string(), stats)
+            })
+            .collect();
+
+        // Calculate queries per second for hourly buckets
+        let mut hourly_stats = self.hourly_stats;
+        calculate_queries_per_second(&mut hourly_stats, &self.hourly_spans);
+        for (hour, durations) in &self.hourly_durations {
+            if let Some(stats) = hourly_stats.get_mut(hour) {
+                stats.p95_duration = calculate_metrics(durations).p95_duration;
+            }
+        }
+        let mut hourly_stats: Vec<_> = hourly_stats.into_values().collect();
+        hourly_stats.sort_by_key(|stats| stats.hour);
+        result.hourly_stats = hourly_stats;
+
+        result
+    }
+}
+
+/// Widen the span of the hour of `timestamp` to include it
+fn track_hourly_span(
+    spans: &mut HashMap<u32, (DateTime<Utc>, DateTime<Utc>)>,
+    timestamp: DateTime<Utc>,
+    timezone: Option<Tz>,
+) {
+    spans
+        .entry(hour_in(timestamp, timezone))
+        .and_modify(|(first, last)| {
+            *first = (*first).min(timestamp);
+            *last = (*last).max(timestamp);
+        })
+        .or_insert((timestamp, timestamp));
+}
+
+/// Add the counts `AnalysisResult` collects while entries are observed
+fn merge_counts(result: &mut AnalysisResult, other: AnalysisResult) {
+    result.total_queries += other.total_queries;
+    result.total_duration += other.total_duration;
+    result.durations_without_statement += other.durations_without_statement;
+    result.queries_without_duration += other.queries_without_duration;
+    result.pooler_maintenance_count += other.pooler_maintenance_count;
+    if let Some(version) = other.pg_version {
+        PgVersion::update(&mut result.pg_version, version);
+    }
+    result.maintenance.merge(other.maintenance);
+    result.io.merge(other.io);
+    result.prepared_statements.merge(other.prepared_statements);
+    result.unparsed_statements.merge(other.unparsed_statements);
+    result.error_count += other.error_count;
+    for (class, theirs) in other.errors_by_sqlstate {
+        match result.errors_by_sqlstate.entry(class) {
+            btree_map::Entry::Occupied(mut ours) => {
+                let ours = ours.get_mut();
+                ours.count += theirs.count;
+                add_counts(&mut ours.codes, theirs.codes);
+                add_counts(&mut ours.statements, theirs.statements);
+            }
+            btree_map::Entry::Vacant(ours) => {
+                ours.insert(theirs);
+            }
+        }
+    }
+    add_counts(&mut result.errors_by_severity, other.errors_by_severity);
+    add_hashed_counts(&mut result.queries_by_user, other.queries_by_user);
+    add_hashed_counts(&mut result.duration_by_user, other.duration_by_user);
+    add_hashed_counts(&mut result.queries_by_database, other.queries_by_database);
+    add_hashed_counts(&mut result.errors_by_database, other.errors_by_database);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::LogLevel;
+    use crate::{LogLevel, Query};
+    use std::collections::BTreeMap;
 
     fn create_test_entry(
         timestamp: DateTime<Utc>,
@@ -408,80 +2183,71 @@ mod tests {
         query: Option<String>,
         duration: Option<f64>,
     ) -> LogEntry {
-        LogEntry {
-            timestamp,
-            process_id: "12345".to_string(),
-            user: Some("test_user".to_string()),
-            database: Some("testdb".to_string()),
-            client_host: None,
-            application_name: Some("psql".to_string()),
-            message_type,
-            message: query
-                .as_ref()
-                .map_or("test message".to_string(), |q| format!("statement: {}", q)),
-            queries: crate::Query::from_sql(query.as_deref().unwrap_or("")).ok(),
-            duration,
-        }
+        let message = query
+            .as_ref()
+            .map_or("test message".to_string(), |q| format!("statement: {}", q));
+        LogEntry::builder(timestamp, "12345", message_type, message)
+            .with_user(Some("test_user"))
+            .with_database(Some("testdb"))
+            .with_application_name(Some("psql"))
+            .with_queries(crate::Query::from_sql(query.as_deref().unwrap_or("")).ok())
+            .with_duration(duration)
+            .build()
+            .unwrap()
     }
 
     #[test]
     fn test_normalize_query() {
-        let analyzer = QueryAnalyzer::new();
-
         // Test parameter replacement
         let query = "SELECT * FROM users WHERE id = $1 AND name = $2";
-        let normalized = analyzer.normalize_query(query);
+        let normalized = crate::normalize(query).unwrap();
         assert_eq!(normalized, "SELECT * FROM users WHERE id = ? AND name = ?");
 
         // Test numeric literal replacement
         let query = "SELECT * FROM users WHERE age > 25 AND score < 100.5";
-        let normalized = analyzer.normalize_query(query);
+        let normalized = crate::normalize(query).unwrap();
         assert_eq!(
             normalized,
-            "SELECT * FROM users WHERE age > N AND score < N"
+            "SELECT * FROM users WHERE age > ? AND score < ?"
         );
 
         // Test string literal replacement
         let query = "SELECT * FROM users WHERE name = 'John' AND city = 'New York'";
-        let normalized = analyzer.normalize_query(query);
+        let normalized = crate::normalize(query).unwrap();
         assert_eq!(
             normalized,
-            "SELECT * FROM users WHERE name = S AND city = S"
+            "SELECT * FROM users WHERE name = ? AND city = ?"
         );
 
         // Test whitespace normalization
         let query = "SELECT   *   FROM    users   WHERE   id=1";
-        let normalized = analyzer.normalize_query(query);
-        assert_eq!(normalized, "SELECT * FROM users WHERE id=N");
+        let normalized = crate::normalize(query).unwrap();
+        assert_eq!(normalized, "SELECT * FROM users WHERE id = ?");
     }
 
     #[test]
     fn test_classify_query() {
-        let analyzer = QueryAnalyzer::new();
-
+        assert_eq!(crate::classify("SELECT * FROM users"), QueryType::Select);
         assert_eq!(
-            analyzer.classify_query("SELECT * FROM users"),
-            QueryType::Select
-        );
-        assert_eq!(
-            analyzer.classify_query("INSERT INTO users VALUES (1, 'John')"),
+            crate::classify("INSERT INTO users VALUES (1, 'John')"),
             QueryType::Insert
         );
         assert_eq!(
-            analyzer.classify_query("UPDATE users SET name = 'Jane'"),
+            crate::classify("UPDATE users SET name = 'Jane'"),
             QueryType::Update
         );
         assert_eq!(
-            analyzer.classify_query("DELETE FROM users WHERE id = 1"),
+            crate::classify("DELETE FROM users WHERE id = 1"),
             QueryType::Delete
         );
         assert_eq!(
-            analyzer.classify_query("CREATE TABLE users (id INT)"),
+            crate::classify("CREATE TABLE users (id INT)"),
             QueryType::DDL
         );
-        assert_eq!(analyzer.classify_query("DROP TABLE users"), QueryType::DDL);
-        assert_eq!(analyzer.classify_query("BEGIN"), QueryType::Other);
-        assert_eq!(analyzer.classify_query("COMMIT"), QueryType::Other);
+        assert_eq!(crate::classify("DROP TABLE users"), QueryType::DDL);
+        assert_eq!(crate::classify("BEGIN"), QueryType::Transaction);
+        assert_eq!(crate::classify("COMMIT"), QueryType::Transaction);
+        assert_eq!(crate::classify("LISTEN jobs"), QueryType::Other);
     }
 
     #[test]
@@ -495,6 +2261,29 @@ mod tests {
         assert_eq!(result.connection_count, 0);
     }
 
+    #[test]
+    fn test_duration_moments_are_stable_and_merge() {
+        // A large offset loses the spread in a naive sum of squares
+        let durations = [1e9 + 4.0, 1e9 + 7.0, 1e9 + 13.0, 1e9 + 16.0];
+        let mut whole = DurationMoments::default();
+        durations.iter().for_each(|duration| whole.add(*duration));
+        assert!((whole.stddev() - 22.5_f64.sqrt()).abs() < 1e-6);
+        assert_eq!((whole.min, whole.max), (1e9 + 4.0, 1e9 + 16.0));
+
+        let (mut first, mut second) = (DurationMoments::default(), DurationMoments::default());
+        durations[..1]
+            .iter()
+            .for_each(|duration| first.add(*duration));
+        durations[1..]
+            .iter()
+            .for_each(|duration| second.add(*duration));
+        first.merge(second);
+        assert_eq!(first.count, 4);
+        assert!((first.mean - whole.mean).abs() < 1e-6);
+        assert!((first.stddev() - whole.stddev()).abs() < 1e-6);
+        assert_eq!((first.min, first.max), (whole.min, whole.max));
+    }
+
     #[test]
     fn test_analyze_with_queries() {
         let analyzer = QueryAnalyzer::new();
@@ -527,12 +2316,106 @@ mod tests {
         assert_eq!(result.total_queries, 3);
         assert_eq!(result.total_duration, 350.0);
         assert_eq!(result.average_duration, 116.66666666666667);
+        assert_eq!((result.min_duration, result.max_duration), (50.0, 200.0));
+        assert!((result.stddev_duration - (35000.0_f64 / 9.0).sqrt()).abs() < 1e-9);
         assert_eq!(result.error_count, 1);
         assert_eq!(result.connection_count, 0);
 
         // Check query type distribution
-        assert_eq!(result.query_types.get("SELECT"), Some(&2));
-        assert_eq!(result.query_types.get("INSERT"), Some(&1));
+        assert_eq!(
+            result.query_types.get("SELECT").map(|stats| stats.count),
+            Some(2)
+        );
+        assert_eq!(
+            result.query_types.get("INSERT").map(|stats| stats.count),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_analyze_groups_errors_by_sqlstate_class() {
+        let analyzer = QueryAnalyzer::new();
+        let now = Utc::now();
+
+        let mut entries: Vec<LogEntry> = ["23505", "23503", "40P01"]
+            .iter()
+            .map(|code| {
+                let mut entry = create_test_entry(now, LogLevel::Error, None, None);
+                entry.sqlstate = Some(code.to_string());
+                entry
+            })
+            .collect();
+        entries.push(create_test_entry(now, LogLevel::Error, None, None));
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        assert_eq!(result.error_count, 4);
+        let constraints = &result.errors_by_sqlstate["23"];
+        assert_eq!(constraints.class_name, "Integrity Constraint Violation");
+        assert_eq!(constraints.count, 2);
+        assert_eq!(constraints.codes.get("23505"), Some(&1));
+        assert_eq!(result.errors_by_sqlstate["40"].count, 1);
+        assert_eq!(result.errors_by_sqlstate["unknown"].count, 1);
+    }
+
+    #[test]
+    fn test_analyze_counts_failing_statements_per_sqlstate_class() {
+        let analyzer = QueryAnalyzer::new();
+        let now = Utc::now();
+
+        let entries: Vec<LogEntry> = [
+            ("23505", Some("INSERT INTO t VALUES (1)")),
+            ("23505", Some("INSERT INTO t VALUES (2)")),
+            ("23503", None),
+            ("42601", Some("SELECT 1 FORM dual")),
+        ]
+        .iter()
+        .map(|(code, statement)| {
+            let mut entry = create_test_entry(now, LogLevel::Error, None, None);
+            entry.sqlstate = Some(code.to_string());
+            entry.statement = statement.map(str::to_string);
+            entry.queries = statement.and_then(|sql| Query::from_sql(sql).ok());
+            entry
+        })
+        .collect();
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        assert_eq!(
+            result.errors_by_sqlstate["23"].statements,
+            BTreeMap::from([("INSERT INTO t VALUES (?)".to_string(), 2)])
+        );
+        assert_eq!(
+            result.errors_by_sqlstate["42"].statements,
+            BTreeMap::from([("SELECT 1 FORM dual".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_analyze_counts_fatal_and_panic_as_errors_by_severity() {
+        let analyzer = QueryAnalyzer::new();
+        let now = Utc::now();
+
+        let entries = vec![
+            create_test_entry(now, LogLevel::Error, None, None),
+            create_test_entry(now, LogLevel::Fatal, None, None),
+            create_test_entry(now, LogLevel::Fatal, None, None),
+            create_test_entry(now, LogLevel::Panic, None, None),
+            create_test_entry(now, LogLevel::Detail, None, None),
+            create_test_entry(now, LogLevel::Hint, None, None),
+        ];
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        assert_eq!(result.error_count, 4);
+        assert_eq!(
+            result.errors_by_severity,
+            BTreeMap::from([
+                ("ERROR".to_string(), 1),
+                ("FATAL".to_string(), 2),
+                ("PANIC".to_string(), 1),
+            ])
+        );
     }
 
     #[test]
@@ -571,7 +2454,7 @@ mod tests {
     #[test]
     fn test_analyze_events_uses_correlated_statement_duration_pairs() {
         let analyzer = QueryAnalyzer::with_settings(100.0, 5, 5);
-        let parser = crate::TextLogParser::new();
+        let mut parser = crate::TextLogParser::new();
         let lines = vec![
             "2024-08-15 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  statement: SELECT * FROM users WHERE id = 1".to_string(),
             "2024-08-15 10:30:15.456 UTC [12345] postgres@testdb psql: LOG:  duration: 150.000 ms".to_string(),
@@ -591,10 +2474,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_unparsed_statements_stay_out_of_the_query_lists() {
+        let analyzer = QueryAnalyzer::with_settings(100.0, 5, 5).with_unparsed_sample_length(24);
+        let mut parser = crate::TextLogParser::new();
+        let mut lines = Vec::new();
+        for (pid, sql, duration) in [
+            ("1", "DO $$ BEGIN PERFORM pg_sleep(0.2); END $$", 200.0),
+            ("2", "DO $$ BEGIN PERFORM pg_sleep(0.3); END $$", 300.0),
+            ("3", "SELEC broken FROM", 1.0),
+            ("4", "SELECT * FROM users WHERE id = 1", 150.0),
+        ] {
+            lines.push(format!(
+                "2024-08-15 10:30:15.000 UTC [{pid}] postgres@testdb psql: LOG:  statement: {sql}"
+            ));
+            lines.push(format!(
+                "2024-08-15 10:30:15.500 UTC [{pid}] postgres@testdb psql: LOG:  duration: {duration:.3} ms"
+            ));
+        }
+        let entries = parser.parse_lines(&lines).unwrap();
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        assert_eq!(result.total_queries, 4);
+        assert_eq!(
+            result.query_types.get("OTHER").map(|stats| stats.count),
+            Some(3)
+        );
+        let unparsed = &result.unparsed_statements;
+        assert_eq!((unparsed.count, unparsed.total_duration), (3, 501.0));
+        assert_eq!(
+            unparsed.samples,
+            ["DO $$ BEGIN PERFORM pg_…", "SELEC broken FROM"]
+        );
+        assert_eq!(
+            result.most_frequent_queries,
+            [("SELECT * FROM users WHERE id = ?".to_string(), 1)]
+        );
+        assert_eq!(
+            result.slowest_queries,
+            [("SELECT * FROM users WHERE id = ?".to_string(), 150.0)]
+        );
+    }
+
+    #[test]
+    fn test_pooler_noise_is_counted_and_optionally_left_out() {
+        let mut parser = crate::TextLogParser::new();
+        let mut lines = Vec::new();
+        for (pid, sql) in [
+            ("1", "DISCARD ALL"),
+            ("2", "SELECT * FROM users WHERE id = 1"),
+            ("3", "DISCARD ALL"),
+            ("4", "SET SESSION AUTHORIZATION DEFAULT"),
+        ] {
+            lines.push(format!(
+                "2024-08-15 10:30:15.000 UTC [{pid}] postgres@testdb psql: LOG:  statement: {sql}"
+            ));
+            lines.push(format!(
+                "2024-08-15 10:30:15.500 UTC [{pid}] postgres@testdb psql: LOG:  duration: 2.000 ms"
+            ));
+        }
+        let entries = parser.parse_lines(&lines).unwrap();
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+        assert_eq!(
+            (result.total_queries, result.pooler_maintenance_count),
+            (4, 3)
+        );
+
+        let analyzer = QueryAnalyzer::new().with_ignore_pooler_noise(true);
+        let result = analyzer.analyze(&entries).unwrap();
+        assert_eq!(
+            (result.total_queries, result.pooler_maintenance_count),
+            (1, 3)
+        );
+        assert_eq!(result.total_duration, 2.0);
+        assert_eq!(
+            result.most_frequent_queries,
+            [("SELECT * FROM users WHERE id = ?".to_string(), 1)]
+        );
+
+        // Split analyses add up, and a checkpointed state keeps the setting
+        let (first, second) = entries.split_at(4);
+        let mut state = analyzer.state();
+        first.iter().for_each(|entry| state.observe(entry));
+        let json = serde_json::to_string(&state).unwrap();
+        let mut state: QueryAnalyzerState = serde_json::from_str(&json).unwrap();
+        let mut rest = analyzer.state();
+        second.iter().for_each(|entry| rest.observe(entry));
+        state.merge(rest);
+        let merged = state.into_result();
+        assert_eq!(
+            (merged.total_queries, merged.pooler_maintenance_count),
+            (1, 3)
+        );
+    }
+
     #[test]
     fn test_analyze_events_correlates_interleaved_processes() {
         let analyzer = QueryAnalyzer::with_settings(100.0, 5, 5);
-        let parser = crate::TextLogParser::new();
+        let mut parser = crate::TextLogParser::new();
         let lines = vec![
             "2024-08-15 10:30:15.000 UTC [11111] postgres@testdb psql: LOG:  statement: SELECT * FROM users WHERE id = 1".to_string(),
             "2024-08-15 10:30:15.001 UTC [22222] postgres@testdb psql: LOG:  statement: SELECT * FROM orders WHERE id = 2".to_string(),
@@ -682,6 +2661,62 @@ mod tests {
         assert_eq!(result.slowest_queries[1].1, 150.0);
     }
 
+    #[test]
+    fn test_slow_queries_keep_only_the_slowest() {
+        let analyzer = QueryAnalyzer::with_settings(10.0, 3, 5);
+        let now = Utc::now();
+
+        let entries: Vec<LogEntry> = [40.0, 90.0, 20.0, 90.0, 60.0, 5.0, 70.0]
+            .iter()
+            .enumerate()
+            .map(|(i, duration)| {
+                create_test_entry(
+                    now,
+                    LogLevel::Statement,
+                    Some(format!("SELECT * FROM t{}", i)),
+                    Some(*duration),
+                )
+            })
+            .collect();
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        let durations: Vec<f64> = result.slowest_queries.iter().map(|q| q.1).collect();
+        assert_eq!(durations, vec![90.0, 90.0, 70.0]);
+        // Equal durations keep log order
+        assert_eq!(result.slowest_queries[0].0, "SELECT * FROM t1");
+        assert_eq!(result.slowest_queries[1].0, "SELECT * FROM t3");
+    }
+
+    #[test]
+    fn test_most_frequent_queries_share_interned_sql() {
+        let analyzer = QueryAnalyzer::with_settings(0.0, 5, 2);
+        let now = Utc::now();
+
+        let entries: Vec<LogEntry> = ["b", "a", "b", "c", "a", "b"]
+            .iter()
+            .map(|table| {
+                create_test_entry(
+                    now,
+                    LogLevel::Statement,
+                    Some(format!("SELECT * FROM {}", table)),
+                    Some(1.0),
+                )
+            })
+            .collect();
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        assert_eq!(
+            result.most_frequent_queries,
+            vec![
+                ("SELECT * FROM b".to_string(), 3),
+                ("SELECT * FROM a".to_string(), 2),
+            ]
+        );
+        assert_eq!(result.slowest_queries.len(), 5);
+    }
+
     #[test]
     fn test_error_rate_calculation() {
         let analyzer = QueryAnalyzer::new();
@@ -0,0 +1,85 @@
+//! Truncation of timestamps to the start of their minute, hour, or day
+//!
+//! Buckets keyed by a truncated timestamp keep the date, so the same hour of
+//! two different days stays apart.
+
+use chrono::{DateTime, Utc};
+
+const SECONDS_PER_MINUTE: i64 = 60;
+const SECONDS_PER_HOUR: i64 = 3_600;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Start of the UTC minute holding `timestamp`
+pub fn truncate_to_minute(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    truncate(timestamp, SECONDS_PER_MINUTE)
+}
+
+/// Start of the UTC hour holding `timestamp`
+pub fn truncate_to_hour(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    truncate(timestamp, SECONDS_PER_HOUR)
+}
+
+/// Midnight UTC of the day holding `timestamp`
+pub fn truncate_to_day(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    truncate(timestamp, SECONDS_PER_DAY)
+}
+
+/// Minutes since the Unix epoch at the start of the minute holding
+/// `timestamp`
+pub fn epoch_minute(timestamp: DateTime<Utc>) -> i64 {
+    timestamp.timestamp().div_euclid(SECONDS_PER_MINUTE)
+}
+
+/// Start of the minute `minute` minutes after the Unix epoch
+pub fn minute_timestamp(minute: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(minute * SECONDS_PER_MINUTE, 0).unwrap_or_default()
+}
+
+fn truncate(timestamp: DateTime<Utc>, width_seconds: i64) -> DateTime<Utc> {
+    let seconds = timestamp.timestamp();
+    DateTime::from_timestamp(seconds - seconds.rem_euclid(width_seconds), 0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_truncation_keeps_the_date() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 8, 19, 10, 42, 17).unwrap()
+            + chrono::Duration::milliseconds(250);
+
+        assert_eq!(
+            truncate_to_minute(timestamp),
+            Utc.with_ymd_and_hms(2024, 8, 19, 10, 42, 0).unwrap()
+        );
+        assert_eq!(
+            truncate_to_hour(timestamp),
+            Utc.with_ymd_and_hms(2024, 8, 19, 10, 0, 0).unwrap()
+        );
+        assert_eq!(
+            truncate_to_day(timestamp),
+            Utc.with_ymd_and_hms(2024, 8, 19, 0, 0, 0).unwrap()
+        );
+        assert_ne!(
+            truncate_to_hour(timestamp),
+            truncate_to_hour(timestamp + chrono::Duration::days(7))
+        );
+    }
+
+    #[test]
+    fn test_truncation_before_the_epoch() {
+        let timestamp = Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 30).unwrap();
+
+        assert_eq!(
+            truncate_to_hour(timestamp),
+            Utc.with_ymd_and_hms(1969, 12, 31, 23, 0, 0).unwrap()
+        );
+        assert_eq!(epoch_minute(timestamp), -1);
+        assert_eq!(
+            minute_timestamp(epoch_minute(timestamp)),
+            truncate_to_minute(timestamp)
+        );
+    }
+}
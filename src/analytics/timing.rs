@@ -1,14 +1,20 @@
 //! Performance timing analysis for PostgreSQL logs
 
+use super::business_hours::{
+    BusinessHours, BusinessHoursAnalysis, BusinessHoursState, ReportTimezone,
+};
+use super::stats::truncate_to_hour;
+use crate::timezone::{hour_in, weekday_in, Tz};
 use crate::{
-    analytics_error, normalize_log_entries, EventSourceKind, LogEntry, NormalizedEvent, Result,
+    analytics_error, normalize_log_entries, BatchDuration, ConnectionEventKind, EventKind,
+    EventSourceKind, LogEntry, NormalizedEvent, Result,
 };
-use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Timing analyzer configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimingAnalyzerConfig {
     /// Time bucket size in minutes for aggregation
     pub time_bucket_size: u32,
@@ -16,6 +22,24 @@ pub struct TimingAnalyzerConfig {
     pub include_connections: bool,
     /// Whether to include peak usage analysis
     pub include_peak_analysis: bool,
+    /// Window of the business hours breakdown
+    #[serde(default)]
+    pub business_hours: BusinessHours,
+    /// UTC offset in seconds of the timezone the business hours are in,
+    /// unless `report_timezone` names one
+    #[serde(default)]
+    pub utc_offset_seconds: i32,
+    /// IANA timezone the business hours are in
+    #[serde(default)]
+    pub report_timezone: Option<Tz>,
+    /// Timezone the hourly and daily patterns bucket entries in; UTC when
+    /// `None`
+    #[serde(default)]
+    pub bucket_timezone: Option<Tz>,
+    /// How the duration of a multi-statement line is attributed, as by the
+    /// query analyzer
+    #[serde(default)]
+    pub batch_duration: BatchDuration,
 }
 
 impl Default for TimingAnalyzerConfig {
@@ -24,8 +48,82 @@ impl Default for TimingAnalyzerConfig {
             time_bucket_size: 60, // 1 hour default
             include_connections: true,
             include_peak_analysis: true,
+            business_hours: BusinessHours::default(),
+            utc_offset_seconds: 0,
+            report_timezone: None,
+            bucket_timezone: None,
+            batch_duration: BatchDuration::default(),
+        }
+    }
+}
+
+impl TimingAnalyzerConfig {
+    /// Use `business_hours` for the business hours breakdown
+    pub fn with_business_hours(mut self, business_hours: BusinessHours) -> Self {
+        self.business_hours = business_hours;
+        self
+    }
+
+    /// Judge business hours in the timezone `offset` instead of UTC
+    pub fn with_utc_offset(mut self, offset: FixedOffset) -> Self {
+        self.utc_offset_seconds = offset.local_minus_utc();
+        self.report_timezone = None;
+        self
+    }
+
+    /// Judge business hours in `timezone` instead of UTC
+    pub fn with_report_timezone(self, timezone: ReportTimezone) -> Self {
+        match timezone {
+            ReportTimezone::Named(timezone) => Self {
+                report_timezone: Some(timezone),
+                utc_offset_seconds: 0,
+                ..self
+            },
+            ReportTimezone::Fixed(offset) => self.with_utc_offset(offset),
+        }
+    }
+
+    /// The fixed reporting offset, used when no IANA timezone is set
+    pub fn utc_offset(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.utc_offset_seconds).unwrap_or(FixedOffset::east_opt(0).unwrap())
+    }
+
+    /// The reporting timezone
+    pub fn report_timezone(&self) -> ReportTimezone {
+        match self.report_timezone {
+            Some(timezone) => ReportTimezone::Named(timezone),
+            None => ReportTimezone::Fixed(self.utc_offset()),
+        }
+    }
+
+    /// Bucket the hourly and daily patterns by the hour and weekday in
+    /// `timezone` instead of UTC
+    pub fn with_bucket_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.bucket_timezone = timezone;
+        self
+    }
+
+    /// Attribute the duration of a multi-statement line as `batch_duration`
+    pub fn with_batch_duration(mut self, batch_duration: BatchDuration) -> Self {
+        self.batch_duration = batch_duration;
+        self
+    }
+
+    /// Queries a statement of `statements` statements counts as
+    fn queries_per_statement(&self, statements: usize) -> usize {
+        match self.batch_duration {
+            BatchDuration::Batch => 1,
+            BatchDuration::Split => statements.max(1),
         }
     }
+
+    fn hour(&self, timestamp: DateTime<Utc>) -> u32 {
+        hour_in(timestamp, self.bucket_timezone)
+    }
+
+    fn weekday(&self, timestamp: DateTime<Utc>) -> u32 {
+        weekday_in(timestamp, self.bucket_timezone)
+    }
 }
 
 /// Analyzer for timing and performance metrics
@@ -47,6 +145,11 @@ impl TimingAnalyzer {
         Self { config }
     }
 
+    /// Get the configuration
+    pub fn config(&self) -> &TimingAnalyzerConfig {
+        &self.config
+    }
+
     /// Create a new timing analyzer with custom bucket size
     pub fn with_bucket_size(time_bucket_size: u32) -> Self {
         Self {
@@ -65,81 +168,30 @@ impl TimingAnalyzer {
 
     /// Analyze timing patterns in normalized events.
     pub fn analyze_timing_events(&self, events: &[NormalizedEvent]) -> Result<TimingAnalysis> {
-        if events.is_empty() {
-            return Ok(TimingAnalysis::default());
-        }
-
-        let mut hourly_patterns = HashMap::new();
-        let mut daily_patterns = HashMap::new();
-        let mut response_times = Vec::new();
-        let mut connection_patterns = HashMap::new();
-        let mut peak_hours = Vec::new();
-
-        // Process each entry
+        let mut timing = self.state();
         for event in events {
-            if let Some(duration) = event.duration_ms() {
-                response_times.push(duration);
-
-                // Group by hour
-                let hour = event.timestamp.hour();
-                let current_duration = hourly_patterns.entry(hour).or_insert(0.0);
-                *current_duration += duration;
-
-                // Group by day of week
-                let day = event.timestamp.weekday().num_days_from_monday();
-                let current_day_duration = daily_patterns.entry(day).or_insert(0.0);
-                *current_day_duration += duration;
-            }
-
-            // Analyze connection patterns if enabled
-            if self.config.include_connections
-                && event.message().to_lowercase().contains("connection")
-            {
-                let hour = event.timestamp.hour();
-                *connection_patterns.entry(hour).or_insert(0) += 1;
-            }
+            timing.observe_event(event);
         }
+        Ok(timing.into_analysis())
+    }
 
-        // Calculate basic statistics
-        let avg_response_time = if !response_times.is_empty() {
-            response_times.iter().sum::<f64>() / response_times.len() as f64
-        } else {
-            0.0
-        };
-
-        let mut sorted_times = response_times.clone();
-        sorted_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-        let p95_response_time = if !sorted_times.is_empty() {
-            let p95_index = (sorted_times.len() as f64 * 0.95) as usize;
-            sorted_times[p95_index.min(sorted_times.len() - 1)]
-        } else {
-            0.0
-        };
-
-        let p99_response_time = if !sorted_times.is_empty() {
-            let p99_index = (sorted_times.len() as f64 * 0.99) as usize;
-            sorted_times[p99_index.min(sorted_times.len() - 1)]
-        } else {
-            0.0
-        };
-
-        // Identify peak usage hours if enabled
-        if self.config.include_peak_analysis {
-            peak_hours = self.identify_peak_hours(&hourly_patterns);
+    /// Start an incremental analysis that is fed one entry at a time with
+    /// [`TimingAnalyzerState::observe`]
+    pub fn state(&self) -> TimingAnalyzerState {
+        TimingAnalyzerState {
+            config: self.config.clone(),
+            event_count: 0,
+            hourly_patterns: HashMap::new(),
+            hourly_query_counts: HashMap::new(),
+            daily_patterns: HashMap::new(),
+            daily_query_counts: HashMap::new(),
+            by_hour: BTreeMap::new(),
+            response_times: Vec::new(),
+            connection_patterns: HashMap::new(),
+            business_hours: BusinessHoursState::default(),
+            query_count: 0,
+            untimed_statements: HashMap::new(),
         }
-
-        Ok(TimingAnalysis {
-            average_response_time: Duration::milliseconds(avg_response_time as i64),
-            p95_response_time: Duration::milliseconds(p95_response_time as i64),
-            p99_response_time: Duration::milliseconds(p99_response_time as i64),
-            hourly_patterns,
-            daily_patterns,
-            connection_patterns,
-            peak_hours,
-            total_queries: response_times.len() as u64,
-            total_duration: response_times.iter().sum(),
-        })
     }
 
     /// Calculate response time percentiles
@@ -184,7 +236,7 @@ impl TimingAnalyzer {
 
         for entry in entries {
             if entry.is_query() {
-                let hour = entry.timestamp.hour();
+                let hour = self.config.hour(entry.timestamp);
                 let metrics = hourly_metrics.entry(hour).or_insert_with(|| HourlyMetrics {
                     hour,
                     query_count: 0,
@@ -227,16 +279,16 @@ impl TimingAnalyzer {
         let mut connection_errors = 0;
 
         for entry in entries {
-            if entry.message.to_lowercase().contains("connection") {
+            if let Some(kind) = entry.connection_event {
                 total_connections += 1;
 
-                let hour = entry.timestamp.hour();
+                let hour = self.config.hour(entry.timestamp);
                 *hourly_connections.entry(hour).or_insert(0) += 1;
 
-                let day = entry.timestamp.weekday().num_days_from_monday();
+                let day = self.config.weekday(entry.timestamp);
                 *daily_connections.entry(day).or_insert(0) += 1;
 
-                if entry.is_error() {
+                if kind == ConnectionEventKind::Reset {
                     connection_errors += 1;
                 }
             }
@@ -255,25 +307,6 @@ impl TimingAnalyzer {
         })
     }
 
-    /// Identify peak usage hours
-    fn identify_peak_hours(&self, hourly_patterns: &HashMap<u32, f64>) -> Vec<u32> {
-        if hourly_patterns.is_empty() {
-            return Vec::new();
-        }
-
-        let avg_duration = hourly_patterns.values().sum::<f64>() / hourly_patterns.len() as f64;
-        let threshold = avg_duration * 1.5; // 50% above average
-
-        let mut peak_hours: Vec<_> = hourly_patterns
-            .iter()
-            .filter(|(_, &duration)| duration > threshold)
-            .map(|(&hour, _)| hour)
-            .collect();
-
-        peak_hours.sort();
-        peak_hours
-    }
-
     /// Calculate queries per second for hourly buckets
     fn calculate_queries_per_second(
         &self,
@@ -285,7 +318,7 @@ impl TimingAnalyzer {
 
         for entry in entries {
             if entry.is_query() {
-                let hour = entry.timestamp.hour();
+                let hour = self.config.hour(entry.timestamp);
                 hourly_entries
                     .entry(hour)
                     .or_default()
@@ -326,7 +359,7 @@ impl TimingAnalyzer {
             .map(|m| m.total_duration)
             .fold(0.0_f64, f64::max);
 
-        let peak_hours: Vec<_> = hourly_distribution
+        let mut peak_hours: Vec<_> = hourly_distribution
             .iter()
             .filter(|(_, metrics)| {
                 metrics.query_count as f64 >= max_queries as f64 * 0.8 || // 80% of max queries
@@ -334,10 +367,16 @@ impl TimingAnalyzer {
             })
             .map(|(&hour, _)| hour)
             .collect();
+        peak_hours.sort_unstable();
 
+        // Ties go to the earliest hour
         let busiest_hour = hourly_distribution
             .iter()
-            .max_by(|(_, a), (_, b)| a.query_count.cmp(&b.query_count))
+            .max_by(|(a_hour, a), (b_hour, b)| {
+                a.query_count
+                    .cmp(&b.query_count)
+                    .then_with(|| b_hour.cmp(a_hour))
+            })
             .map(|(&hour, _)| hour);
 
         Ok(PeakUsageAnalysis {
@@ -360,34 +399,487 @@ impl Default for TimingAnalyzer {
     }
 }
 
-/// Results of timing analysis
+/// Identify peak usage hours
+fn identify_peak_hours(hourly_patterns: &HashMap<u32, f64>) -> Vec<u32> {
+    if hourly_patterns.is_empty() {
+        return Vec::new();
+    }
+
+    let avg_duration = hourly_patterns.values().sum::<f64>() / hourly_patterns.len() as f64;
+    let threshold = avg_duration * 1.5; // 50% above average
+
+    let mut peak_hours: Vec<_> = hourly_patterns
+        .iter()
+        .filter(|(_, &duration)| duration > threshold)
+        .map(|(&hour, _)| hour)
+        .collect();
+
+    peak_hours.sort();
+    peak_hours
+}
+
+/// Timing analysis in progress, fed one entry at a time.
+///
+/// Created with [`TimingAnalyzer::state`]; [`finish`](Self::finish) returns
+/// the same result as [`TimingAnalyzer::analyze_timing`] on the same entries.
+/// States of separate parts of a log can be combined with
+/// [`merge`](Self::merge) and serialized to checkpoint an analysis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimingAnalyzerState {
+    config: TimingAnalyzerConfig,
+    event_count: usize,
+    hourly_patterns: HashMap<u32, f64>,
+    #[serde(default)]
+    hourly_query_counts: HashMap<u32, u64>,
+    daily_patterns: HashMap<u32, f64>,
+    #[serde(default)]
+    daily_query_counts: HashMap<u32, u64>,
+    #[serde(default, with = "hour_series")]
+    by_hour: BTreeMap<DateTime<Utc>, HourStats>,
+    response_times: Vec<f64>,
+    connection_patterns: HashMap<u32, u64>,
+    #[serde(default)]
+    business_hours: BusinessHoursState,
+    /// Queries counted, with a duration or without
+    #[serde(default)]
+    query_count: u64,
+    /// When the statement each session logged without a duration was
+    /// logged and the queries it counts as, until a duration line follows it
+    #[serde(default)]
+    untimed_statements: HashMap<String, (DateTime<Utc>, usize)>,
+}
+
+impl TimingAnalyzerState {
+    /// Add one log entry to the analysis
+    pub fn observe(&mut self, entry: &LogEntry) {
+        let event =
+            NormalizedEvent::from_log_entry(entry, EventSourceKind::Stderr, self.event_count);
+        self.observe_event(&event);
+    }
+
+    /// Add one normalized event to the analysis
+    pub fn observe_event(&mut self, event: &NormalizedEvent) {
+        self.event_count += 1;
+        let session = event.session.key();
+        match &event.kind {
+            EventKind::Statement(statement) => {
+                self.untimed_statements.remove(session);
+                let queries = self.config.queries_per_statement(statement.queries.len());
+                self.count_queries(event.timestamp, queries, statement.duration_ms);
+                if statement.duration_ms.is_none() {
+                    self.untimed_statements
+                        .insert(session.to_string(), (event.timestamp, queries));
+                }
+            }
+            // A duration logged after its statement times the queries already
+            // counted, as the query analyzer pairs them
+            EventKind::Duration(duration) => match self.untimed_statements.remove(session) {
+                Some((logged_at, queries)) if logged_at <= event.timestamp => {
+                    self.time_queries(logged_at, queries, duration.duration_ms)
+                }
+                _ => self.count_queries(event.timestamp, 1, Some(duration.duration_ms)),
+            },
+            EventKind::Error(_) => self.business_hours.observe_error(
+                event.timestamp,
+                &self.config.business_hours,
+                self.config.report_timezone(),
+            ),
+            EventKind::Log { .. } => {}
+        }
+
+        // Analyze connection patterns if enabled
+        if self.config.include_connections && event.connection_event().is_some() {
+            let hour = self.config.hour(event.timestamp);
+            *self.connection_patterns.entry(hour).or_insert(0) += 1;
+            self.by_hour
+                .entry(truncate_to_hour(event.timestamp))
+                .or_default()
+                .connection_count += 1;
+        }
+    }
+
+    /// Count `queries` queries logged at `timestamp`, sharing its duration if
+    /// it has one
+    fn count_queries(&mut self, timestamp: DateTime<Utc>, queries: usize, duration: Option<f64>) {
+        let share = duration.map(|duration| duration / queries as f64);
+        for _ in 0..queries {
+            self.query_count += 1;
+            *self
+                .hourly_query_counts
+                .entry(self.config.hour(timestamp))
+                .or_insert(0) += 1;
+            *self
+                .daily_query_counts
+                .entry(self.config.weekday(timestamp))
+                .or_insert(0) += 1;
+            self.by_hour
+                .entry(truncate_to_hour(timestamp))
+                .or_default()
+                .query_count += 1;
+            self.business_hours.observe_query(
+                timestamp,
+                share,
+                &self.config.business_hours,
+                self.config.report_timezone(),
+            );
+            if let Some(share) = share {
+                self.add_duration(timestamp, share);
+            }
+        }
+    }
+
+    /// Give the `queries` queries counted at `timestamp` without a duration
+    /// shares of the one logged after them
+    fn time_queries(&mut self, timestamp: DateTime<Utc>, queries: usize, duration: f64) {
+        let share = duration / queries as f64;
+        for _ in 0..queries {
+            self.business_hours.time_query(
+                timestamp,
+                share,
+                &self.config.business_hours,
+                self.config.report_timezone(),
+            );
+            self.add_duration(timestamp, share);
+        }
+    }
+
+    fn add_duration(&mut self, timestamp: DateTime<Utc>, duration: f64) {
+        self.response_times.push(duration);
+        *self
+            .hourly_patterns
+            .entry(self.config.hour(timestamp))
+            .or_insert(0.0) += duration;
+        *self
+            .daily_patterns
+            .entry(self.config.weekday(timestamp))
+            .or_insert(0.0) += duration;
+        self.by_hour
+            .entry(truncate_to_hour(timestamp))
+            .or_default()
+            .total_duration += duration;
+    }
+
+    /// Add the analysis of `other`
+    pub fn merge(&mut self, other: Self) {
+        self.event_count += other.event_count;
+        for (hour, duration) in other.hourly_patterns {
+            *self.hourly_patterns.entry(hour).or_insert(0.0) += duration;
+        }
+        for (hour, count) in other.hourly_query_counts {
+            *self.hourly_query_counts.entry(hour).or_insert(0) += count;
+        }
+        for (day, duration) in other.daily_patterns {
+            *self.daily_patterns.entry(day).or_insert(0.0) += duration;
+        }
+        for (day, count) in other.daily_query_counts {
+            *self.daily_query_counts.entry(day).or_insert(0) += count;
+        }
+        for (hour, stats) in other.by_hour {
+            self.by_hour.entry(hour).or_default().merge(&stats);
+        }
+        self.response_times.extend(other.response_times);
+        for (hour, count) in other.connection_patterns {
+            *self.connection_patterns.entry(hour).or_insert(0) += count;
+        }
+        self.business_hours.merge(other.business_hours);
+        self.query_count += other.query_count;
+        self.untimed_statements.extend(other.untimed_statements);
+    }
+
+    /// The result of the analysis so far
+    pub fn finish(&self) -> TimingAnalysis {
+        self.clone().into_analysis()
+    }
+
+    /// [`finish`](Self::finish) without copying the state
+    pub fn into_analysis(self) -> TimingAnalysis {
+        if self.event_count == 0 {
+            return TimingAnalysis::default();
+        }
+
+        let response_times = self.response_times;
+
+        // Calculate basic statistics
+        let avg_response_time = if !response_times.is_empty() {
+            response_times.iter().sum::<f64>() / response_times.len() as f64
+        } else {
+            0.0
+        };
+
+        let mut sorted_times = response_times.clone();
+        sorted_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let p95_response_time = if !sorted_times.is_empty() {
+            let p95_index = (sorted_times.len() as f64 * 0.95) as usize;
+            sorted_times[p95_index.min(sorted_times.len() - 1)]
+        } else {
+            0.0
+        };
+
+        let p99_response_time = if !sorted_times.is_empty() {
+            let p99_index = (sorted_times.len() as f64 * 0.99) as usize;
+            sorted_times[p99_index.min(sorted_times.len() - 1)]
+        } else {
+            0.0
+        };
+
+        // Identify peak usage hours if enabled
+        let peak_hours = if self.config.include_peak_analysis {
+            identify_peak_hours(&self.hourly_patterns)
+        } else {
+            Vec::new()
+        };
+
+        let business_hours = self
+            .business_hours
+            .into_analysis(&self.config.business_hours, self.config.report_timezone());
+
+        TimingAnalysis {
+            average_response_time_ms: avg_response_time,
+            p95_response_time_ms: p95_response_time,
+            p99_response_time_ms: p99_response_time,
+            hourly_patterns: self.hourly_patterns,
+            hourly_query_counts: self.hourly_query_counts,
+            daily_patterns: self.daily_patterns,
+            daily_query_counts: self.daily_query_counts,
+            by_hour: self.by_hour,
+            connection_patterns: self.connection_patterns,
+            peak_hours,
+            total_queries: self.query_count,
+            timed_queries: response_times.len() as u64,
+            total_duration: response_times.iter().sum(),
+            business_hours,
+        }
+    }
+}
+
+/// Results of timing analysis.
+///
+/// Serializes to plain JSON: response times are milliseconds, and the maps
+/// keyed by hour of day or weekday are arrays of `{"hour": 10, "value": ...}`
+/// or `{"day": 0, "value": ...}` objects in key order.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimingAnalysis {
-    pub average_response_time: Duration,
-    pub p95_response_time: Duration,
-    pub p99_response_time: Duration,
+    /// Mean query duration in milliseconds
+    pub average_response_time_ms: f64,
+    pub p95_response_time_ms: f64,
+    pub p99_response_time_ms: f64,
+    /// Total query duration in milliseconds by hour of day
+    #[serde(default, with = "hour_of_day_series")]
     pub hourly_patterns: HashMap<u32, f64>,
+    /// Queries by hour of day, with a duration or without
+    #[serde(default, with = "hour_of_day_series")]
+    pub hourly_query_counts: HashMap<u32, u64>,
+    /// Total query duration in milliseconds by day of week, Monday = 0
+    #[serde(default, with = "weekday_series")]
     pub daily_patterns: HashMap<u32, f64>,
+    /// Queries by day of week, Monday = 0, with a duration or without
+    #[serde(default, with = "weekday_series")]
+    pub daily_query_counts: HashMap<u32, u64>,
+    /// Queries, duration, and connections of each UTC hour of the log, keyed
+    /// by the start of the hour so the same hour of different days stays
+    /// apart; serialized as an array of objects with an RFC 3339 `hour`
+    #[serde(default, with = "hour_series")]
+    pub by_hour: BTreeMap<DateTime<Utc>, HourStats>,
+    /// Connection messages by hour of day
+    #[serde(default, with = "hour_of_day_series")]
     pub connection_patterns: HashMap<u32, u64>,
+    /// Hours whose total duration is more than 50% above the hourly average
     pub peak_hours: Vec<u32>,
+    /// Queries, with a duration or without
     pub total_queries: u64,
+    /// Queries that had a duration
+    #[serde(default)]
+    pub timed_queries: u64,
+    /// Total duration in milliseconds of the queries that had one
     pub total_duration: f64,
+    /// Queries and errors inside and outside business hours, and per weekday
+    #[serde(default)]
+    pub business_hours: BusinessHoursAnalysis,
+}
+
+impl TimingAnalysis {
+    /// Mean query duration, to the microsecond
+    pub fn average_response_time(&self) -> Duration {
+        milliseconds_duration(self.average_response_time_ms)
+    }
+
+    /// 95th percentile query duration, to the microsecond
+    pub fn p95_response_time(&self) -> Duration {
+        milliseconds_duration(self.p95_response_time_ms)
+    }
+
+    /// 99th percentile query duration, to the microsecond
+    pub fn p99_response_time(&self) -> Duration {
+        milliseconds_duration(self.p99_response_time_ms)
+    }
+
+    /// Hour of day with the most queries, the earliest on a tie
+    pub fn busiest_hour(&self) -> Option<u32> {
+        self.hourly_query_counts
+            .iter()
+            .max_by(|(hour_a, count_a), (hour_b, count_b)| {
+                count_a.cmp(count_b).then(hour_b.cmp(hour_a))
+            })
+            .map(|(&hour, _)| hour)
+    }
 }
 
 impl Default for TimingAnalysis {
     fn default() -> Self {
         Self {
-            average_response_time: Duration::zero(),
-            p95_response_time: Duration::zero(),
-            p99_response_time: Duration::zero(),
+            average_response_time_ms: 0.0,
+            p95_response_time_ms: 0.0,
+            p99_response_time_ms: 0.0,
             hourly_patterns: HashMap::new(),
+            hourly_query_counts: HashMap::new(),
             daily_patterns: HashMap::new(),
+            daily_query_counts: HashMap::new(),
+            by_hour: BTreeMap::new(),
             connection_patterns: HashMap::new(),
             peak_hours: Vec::new(),
             total_queries: 0,
+            timed_queries: 0,
             total_duration: 0.0,
+            business_hours: BusinessHoursAnalysis::default(),
+        }
+    }
+}
+
+fn milliseconds_duration(milliseconds: f64) -> Duration {
+    Duration::microseconds((milliseconds * 1000.0) as i64)
+}
+
+/// Activity of one hour of [`TimingAnalysis::by_hour`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HourStats {
+    /// Queries, with a duration or without
+    pub query_count: u64,
+    /// Total query duration in milliseconds
+    pub total_duration: f64,
+    /// Connection messages
+    pub connection_count: u64,
+}
+
+impl HourStats {
+    /// Average query duration in milliseconds, 0 without queries
+    pub fn average_duration(&self) -> f64 {
+        if self.query_count == 0 {
+            0.0
+        } else {
+            self.total_duration / self.query_count as f64
         }
     }
+
+    /// Add the activity of `other`
+    pub fn merge(&mut self, other: &Self) {
+        self.query_count += other.query_count;
+        self.total_duration += other.total_duration;
+        self.connection_count += other.connection_count;
+    }
+}
+
+/// (De)serializes hour-keyed stats as an array of objects, since timestamps
+/// make awkward JSON object keys
+mod hour_series {
+    use super::HourStats;
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct HourEntry {
+        hour: DateTime<Utc>,
+        #[serde(flatten)]
+        stats: HourStats,
+    }
+
+    pub fn serialize<S: Serializer>(
+        by_hour: &BTreeMap<DateTime<Utc>, HourStats>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(by_hour.iter().map(|(&hour, stats)| HourEntry {
+            hour,
+            stats: stats.clone(),
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<DateTime<Utc>, HourStats>, D::Error> {
+        Ok(Vec::<HourEntry>::deserialize(deserializer)?
+            .into_iter()
+            .map(|entry| (entry.hour, entry.stats))
+            .collect())
+    }
+}
+
+/// (De)serializes maps keyed by hour of day as arrays of `{hour, value}`
+/// objects in hour order, since JSON object keys can only be strings
+mod hour_of_day_series {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry<V> {
+        hour: u32,
+        value: V,
+    }
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        by_hour: &HashMap<u32, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = by_hour
+            .iter()
+            .map(|(&hour, value)| Entry { hour, value })
+            .collect();
+        entries.sort_by_key(|entry| entry.hour);
+        serializer.collect_seq(entries)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<u32, V>, D::Error> {
+        Ok(Vec::<Entry<V>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|entry| (entry.hour, entry.value))
+            .collect())
+    }
+}
+
+/// (De)serializes maps keyed by weekday, Monday = 0, as arrays of
+/// `{day, value}` objects in weekday order
+mod weekday_series {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry<V> {
+        day: u32,
+        value: V,
+    }
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        by_day: &HashMap<u32, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = by_day
+            .iter()
+            .map(|(&day, value)| Entry { day, value })
+            .collect();
+        entries.sort_by_key(|entry| entry.day);
+        serializer.collect_seq(entries)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<u32, V>, D::Error> {
+        Ok(Vec::<Entry<V>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|entry| (entry.day, entry.value))
+            .collect())
+    }
 }
 
 /// Hourly metrics for detailed analysis
@@ -405,9 +897,15 @@ pub struct HourlyMetrics {
 /// Connection pattern analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionAnalysis {
+    /// Connection, disconnection, and reset lines
     pub total_connections: u64,
+    /// Connections reset by the client
     pub connection_errors: u64,
+    /// Serialized as an array of `{hour, value}` objects
+    #[serde(with = "hour_of_day_series")]
     pub hourly_connections: HashMap<u32, u64>,
+    /// Monday = 0; serialized as an array of `{day, value}` objects
+    #[serde(with = "weekday_series")]
     pub daily_connections: HashMap<u32, u64>,
     pub error_rate: f64,
 }
@@ -437,7 +935,8 @@ impl Default for PeakUsageAnalysis {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::LogLevel;
+    use crate::{ConnectionEventKind, LogLevel};
+    use chrono::{Datelike, Timelike};
 
     fn create_test_entry(
         timestamp: DateTime<Utc>,
@@ -445,18 +944,14 @@ mod tests {
         duration: Option<f64>,
         message: &str,
     ) -> LogEntry {
-        LogEntry {
-            timestamp,
-            process_id: "12345".to_string(),
-            user: Some("test_user".to_string()),
-            database: Some("testdb".to_string()),
-            client_host: None,
-            application_name: Some("psql".to_string()),
-            message_type,
-            message: message.to_string(),
-            queries: None,
-            duration,
-        }
+        LogEntry::builder(timestamp, "12345", message_type, message)
+            .with_user(Some("test_user"))
+            .with_database(Some("testdb"))
+            .with_application_name(Some("psql"))
+            .with_duration(duration)
+            .with_connection_event(ConnectionEventKind::from_message(message))
+            .build()
+            .unwrap()
     }
 
     #[test]
@@ -484,7 +979,225 @@ mod tests {
 
         assert_eq!(result.total_queries, 3);
         assert_eq!(result.total_duration, 600.0);
-        assert_eq!(result.average_response_time.num_milliseconds(), 200);
+        assert_eq!(result.average_response_time().num_milliseconds(), 200);
+        assert_eq!(result.hourly_query_counts, HashMap::from([(now.hour(), 3)]));
+        assert_eq!(
+            result.daily_query_counts,
+            HashMap::from([(now.weekday().num_days_from_monday(), 3)])
+        );
+        assert_eq!(result.busiest_hour(), Some(now.hour()));
+    }
+
+    #[test]
+    fn test_timing_analysis_serializes_milliseconds_and_keyed_arrays() {
+        use chrono::TimeZone;
+        // A Wednesday
+        let morning = Utc.with_ymd_and_hms(2024, 8, 21, 9, 15, 0).unwrap();
+        let entries = vec![
+            create_test_entry(morning, LogLevel::Log, None, "connection received"),
+            create_test_entry(
+                morning,
+                LogLevel::Statement,
+                Some(12.5),
+                "statement: SELECT 1",
+            ),
+            create_test_entry(
+                morning + Duration::hours(2),
+                LogLevel::Statement,
+                Some(450.25),
+                "statement: SELECT 2",
+            ),
+        ];
+        let result = TimingAnalyzer::new().analyze_timing(&entries).unwrap();
+        assert_eq!(result.average_response_time_ms, 231.375);
+        assert_eq!(
+            result.average_response_time().num_microseconds(),
+            Some(231_375)
+        );
+        assert_eq!(result.average_response_time().num_milliseconds(), 231);
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["average_response_time_ms"], 231.375);
+        assert_eq!(json["p99_response_time_ms"], 450.25);
+        assert_eq!(
+            json["hourly_query_counts"],
+            serde_json::json!([{"hour": 9, "value": 1}, {"hour": 11, "value": 1}])
+        );
+        assert_eq!(
+            json["daily_patterns"],
+            serde_json::json!([{"day": 2, "value": 462.75}])
+        );
+        assert_eq!(
+            json["connection_patterns"],
+            serde_json::json!([{"hour": 9, "value": 1}])
+        );
+
+        let round_trip: TimingAnalysis = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(round_trip.hourly_patterns, result.hourly_patterns);
+        assert_eq!(round_trip.daily_query_counts, result.daily_query_counts);
+        assert_eq!(round_trip.p95_response_time(), result.p95_response_time());
+        assert_eq!(serde_json::to_value(&round_trip).unwrap(), json);
+    }
+
+    #[test]
+    fn test_connection_analysis_round_trips_through_json() {
+        use chrono::TimeZone;
+        let monday = Utc.with_ymd_and_hms(2024, 8, 19, 23, 0, 0).unwrap();
+        let entries = vec![
+            create_test_entry(monday, LogLevel::Log, None, "connection received"),
+            create_test_entry(
+                monday + Duration::hours(1),
+                LogLevel::Log,
+                None,
+                "connection received",
+            ),
+        ];
+        let result = TimingAnalyzer::new()
+            .analyze_connection_patterns(&entries)
+            .unwrap();
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json["hourly_connections"],
+            serde_json::json!([{"hour": 0, "value": 1}, {"hour": 23, "value": 1}])
+        );
+        assert_eq!(
+            json["daily_connections"],
+            serde_json::json!([{"day": 0, "value": 1}, {"day": 1, "value": 1}])
+        );
+        let round_trip: ConnectionAnalysis = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip.hourly_connections, result.hourly_connections);
+        assert_eq!(round_trip.daily_connections, result.daily_connections);
+    }
+
+    #[test]
+    fn test_by_hour_keeps_the_same_hour_of_two_mondays_apart() {
+        use chrono::TimeZone;
+        let first_monday = Utc.with_ymd_and_hms(2024, 8, 19, 10, 15, 0).unwrap();
+        let second_monday = first_monday + Duration::days(7);
+        let entries = vec![
+            create_test_entry(
+                first_monday,
+                LogLevel::Statement,
+                Some(100.0),
+                "statement: SELECT 1",
+            ),
+            create_test_entry(
+                first_monday + Duration::minutes(30),
+                LogLevel::Statement,
+                Some(50.0),
+                "statement: SELECT 1",
+            ),
+            create_test_entry(
+                second_monday,
+                LogLevel::Statement,
+                Some(300.0),
+                "statement: SELECT 1",
+            ),
+            create_test_entry(second_monday, LogLevel::Log, None, "connection received"),
+        ];
+
+        let result = TimingAnalyzer::new().analyze_timing(&entries).unwrap();
+
+        // The weekday and hour-of-day aggregates merge the two Mondays
+        assert_eq!(result.daily_query_counts, HashMap::from([(0, 3)]));
+        assert_eq!(result.hourly_query_counts, HashMap::from([(10, 3)]));
+        let hours: Vec<_> = result.by_hour.keys().copied().collect();
+        assert_eq!(
+            hours,
+            vec![
+                Utc.with_ymd_and_hms(2024, 8, 19, 10, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 8, 26, 10, 0, 0).unwrap(),
+            ]
+        );
+        let first = &result.by_hour[&hours[0]];
+        assert_eq!((first.query_count, first.total_duration), (2, 150.0));
+        assert_eq!(first.average_duration(), 75.0);
+        let second = &result.by_hour[&hours[1]];
+        assert_eq!((second.query_count, second.connection_count), (1, 1));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["by_hour"][1]["hour"], "2024-08-26T10:00:00Z");
+        assert_eq!(json["by_hour"][1]["total_duration"], 300.0);
+        let round_trip: TimingAnalysis = serde_json::from_value(json).unwrap();
+        assert_eq!(round_trip.by_hour, result.by_hour);
+    }
+
+    #[test]
+    fn test_merged_states_keep_hours_of_different_days_apart() {
+        use chrono::TimeZone;
+        let monday = Utc.with_ymd_and_hms(2024, 8, 19, 10, 0, 0).unwrap();
+        let analyzer = TimingAnalyzer::new();
+        let mut first = analyzer.state();
+        first.observe(&create_test_entry(
+            monday,
+            LogLevel::Statement,
+            Some(10.0),
+            "statement: SELECT 1",
+        ));
+        let mut second = analyzer.state();
+        for timestamp in [monday, monday + Duration::days(7)] {
+            second.observe(&create_test_entry(
+                timestamp,
+                LogLevel::Statement,
+                Some(20.0),
+                "statement: SELECT 1",
+            ));
+        }
+
+        first.merge(second);
+        let result = first.finish();
+
+        assert_eq!(result.by_hour.len(), 2);
+        assert_eq!(result.by_hour[&monday].query_count, 2);
+        assert_eq!(result.by_hour[&monday].total_duration, 30.0);
+    }
+
+    #[test]
+    fn test_busiest_hour_prefers_the_earliest_on_a_tie() {
+        let analysis = TimingAnalysis {
+            hourly_query_counts: HashMap::from([(14, 5), (9, 5), (11, 2)]),
+            ..TimingAnalysis::default()
+        };
+
+        assert_eq!(analysis.busiest_hour(), Some(9));
+        assert_eq!(TimingAnalysis::default().busiest_hour(), None);
+    }
+
+    #[test]
+    fn test_statements_without_durations_are_counted_once() {
+        use chrono::TimeZone;
+        let analyzer = TimingAnalyzer::new();
+        // Monday
+        let at = |hour, minute| Utc.with_ymd_and_hms(2024, 3, 4, hour, minute, 0).unwrap();
+        let entries = vec![
+            create_test_entry(at(9, 0), LogLevel::Statement, None, "statement: SELECT 1"),
+            create_test_entry(
+                at(9, 1),
+                LogLevel::Duration,
+                Some(40.0),
+                "duration: 40.000 ms",
+            ),
+            create_test_entry(at(9, 2), LogLevel::Statement, None, "statement: SELECT 2"),
+            create_test_entry(at(10, 0), LogLevel::Statement, None, "statement: SELECT 3"),
+        ];
+
+        let result = analyzer.analyze_timing(&entries).unwrap();
+
+        assert_eq!((result.total_queries, result.timed_queries), (3, 1));
+        assert_eq!(result.total_duration, 40.0);
+        assert_eq!(result.hourly_query_counts, HashMap::from([(9, 2), (10, 1)]));
+        assert_eq!(result.hourly_patterns, HashMap::from([(9, 40.0)]));
+        assert_eq!(result.daily_query_counts, HashMap::from([(0, 3)]));
+        let counts: Vec<_> = result
+            .by_hour
+            .values()
+            .map(|stats| stats.query_count)
+            .collect();
+        assert_eq!(counts, [2, 1]);
+        let business_hours = &result.business_hours.business_hours;
+        assert_eq!(business_hours.query_count, 3);
+        assert_eq!(business_hours.p95_duration_ms, Some(40.0));
     }
 
     #[test]
@@ -515,8 +1228,8 @@ mod tests {
         assert_eq!(event_result.total_queries, entry_result.total_queries);
         assert_eq!(event_result.total_duration, entry_result.total_duration);
         assert_eq!(
-            event_result.average_response_time,
-            entry_result.average_response_time
+            event_result.average_response_time_ms,
+            entry_result.average_response_time_ms
         );
         assert_eq!(event_result.hourly_patterns, entry_result.hourly_patterns);
         assert_eq!(
@@ -548,8 +1261,25 @@ mod tests {
 
         let entries = vec![
             create_test_entry(now, LogLevel::Log, None, "connection received"),
-            create_test_entry(now, LogLevel::Log, None, "connection established"),
-            create_test_entry(now, LogLevel::Error, None, "connection failed"),
+            create_test_entry(now, LogLevel::Log, None, "connection authorized"),
+            create_test_entry(
+                now,
+                LogLevel::Log,
+                None,
+                "could not receive data from client: Connection reset by peer",
+            ),
+            create_test_entry(
+                now,
+                LogLevel::Statement,
+                None,
+                "statement: SELECT * FROM connection_log",
+            ),
+            create_test_entry(
+                now,
+                LogLevel::Error,
+                None,
+                "could not serialize access due to concurrent update on connection slot",
+            ),
         ];
 
         let result = analyzer.analyze_connection_patterns(&entries).unwrap();
@@ -0,0 +1,312 @@
+//! Memory-bounded counting of the most frequent queries
+//!
+//! [`TopKCounter`] counts every key exactly until it is bounded. From then on
+//! it keeps at most `capacity` keys with the Space-Saving algorithm: a new key
+//! takes the slot of the least counted key and inherits its count. Counts are
+//! then overestimated by at most the count a key inherited, and every key seen
+//! more than `total / capacity` times is guaranteed to be kept.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Count of one key and the value kept with it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Counter<V> {
+    count: u64,
+    /// Count inherited from the key this one replaced
+    overcount: u64,
+    value: V,
+}
+
+/// Slot counts of a bounded counter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bound {
+    capacity: usize,
+    /// `(count, slot)` of every slot, least counted first
+    by_count: BTreeSet<(u64, usize)>,
+}
+
+/// Counts keys exactly until bounded, then with Space-Saving.
+///
+/// Slots keep first-seen order while the counter is exact; a replaced key's
+/// successor takes its slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TopKCounter<V> {
+    counters: IndexMap<Arc<str>, Counter<V>>,
+    bound: Option<Bound>,
+}
+
+impl<V: Default> TopKCounter<V> {
+    /// An exact counter
+    pub(crate) fn new() -> Self {
+        Self {
+            counters: IndexMap::new(),
+            bound: None,
+        }
+    }
+
+    /// Number of keys counted
+    pub(crate) fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Keys kept once bounded; `None` while counting exactly
+    pub(crate) fn capacity(&self) -> Option<usize> {
+        self.bound.as_ref().map(|bound| bound.capacity)
+    }
+
+    /// Keep at most `capacity` keys from now on, dropping the least counted.
+    ///
+    /// The most counted keys keep their slot order. `capacity` must be at
+    /// least 1; [`QueryAnalyzer`](crate::QueryAnalyzer) and the CLI ensure it.
+    pub(crate) fn bound(&mut self, capacity: usize) {
+        if self.counters.len() > capacity {
+            let mut counts: Vec<u64> = self.counters.values().map(|c| c.count).collect();
+            counts.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = counts[capacity - 1];
+            // Keep every key above the cutoff, then ties in slot order
+            let mut ties = capacity - counts.iter().filter(|count| **count > cutoff).count();
+            self.counters.retain(|_, counter| {
+                if counter.count > cutoff {
+                    true
+                } else if counter.count == cutoff && ties > 0 {
+                    ties -= 1;
+                    true
+                } else {
+                    false
+                }
+            });
+        }
+        let by_count = self
+            .counters
+            .values()
+            .enumerate()
+            .map(|(slot, counter)| (counter.count, slot))
+            .collect();
+        self.bound = Some(Bound { capacity, by_count });
+    }
+
+    /// Count one occurrence of `key`, returning its handle and value
+    pub(crate) fn increment<K>(&mut self, key: K) -> (&Arc<str>, &mut V)
+    where
+        K: AsRef<str> + Into<Arc<str>>,
+    {
+        let slot = match self.counters.get_index_of(key.as_ref()) {
+            Some(slot) => slot,
+            None => self.insert(key),
+        };
+        if let Some(bound) = self.bound.as_mut() {
+            let count = self.counters[slot].count;
+            bound.by_count.remove(&(count, slot));
+            bound.by_count.insert((count + 1, slot));
+        }
+        let (key, counter) = self
+            .counters
+            .get_index_mut(slot)
+            .expect("slot was just found or inserted");
+        counter.count += 1;
+        (key, &mut counter.value)
+    }
+
+    /// Handle and value of `key`, adding it uncounted unless the counter is
+    /// bounded
+    pub(crate) fn track(&mut self, key: &str) -> Option<(&Arc<str>, &mut V)> {
+        let slot = match self.counters.get_index_of(key) {
+            Some(slot) => slot,
+            None if self.bound.is_none() => {
+                self.counters
+                    .insert_full(Arc::from(key), Counter::default())
+                    .0
+            }
+            None => return None,
+        };
+        self.counters
+            .get_index_mut(slot)
+            .map(|(key, counter)| (key, &mut counter.value))
+    }
+
+    /// Count and value of `key`
+    pub(crate) fn get(&self, key: &str) -> Option<(u64, &V)> {
+        self.counters
+            .get(key)
+            .map(|counter| (counter.count, &counter.value))
+    }
+
+    /// Keys with their counts and values, in slot order
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Arc<str>, u64, &V)> {
+        self.counters
+            .iter()
+            .map(|(key, counter)| (key, counter.count, &counter.value))
+    }
+
+    /// Most any kept count exceeds the key's true count by
+    pub(crate) fn max_overcount(&self) -> u64 {
+        self.counters
+            .values()
+            .map(|counter| counter.overcount)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Add the counts of `other`, combining the values of keys counted by
+    /// both with `combine`.
+    ///
+    /// Keys new to this counter follow its own in `other`'s slot order. When
+    /// either counter is bounded the result is bounded to the same capacity,
+    /// keeping the most counted keys, and inherited counts add up.
+    pub(crate) fn merge(&mut self, other: Self, mut combine: impl FnMut(&mut V, V)) {
+        let capacity = self.capacity().or(other.capacity());
+        for (key, theirs) in other.counters {
+            match self.counters.get_mut(&key) {
+                Some(ours) => {
+                    ours.count += theirs.count;
+                    ours.overcount += theirs.overcount;
+                    combine(&mut ours.value, theirs.value);
+                }
+                None => {
+                    self.counters.insert(key, theirs);
+                }
+            }
+        }
+        self.bound = None;
+        if let Some(capacity) = capacity {
+            self.bound(capacity);
+        }
+    }
+
+    /// Slot for a new `key`, replacing the least counted key when full
+    fn insert(&mut self, key: impl Into<Arc<str>>) -> usize {
+        let replaced = self.bound.as_ref().and_then(|bound| {
+            if self.counters.len() < bound.capacity {
+                None
+            } else {
+                bound.by_count.first().copied()
+            }
+        });
+        match replaced {
+            Some((count, slot)) => {
+                let _ = self.counters.replace_index(slot, key.into());
+                self.counters[slot] = Counter {
+                    count,
+                    overcount: count,
+                    value: V::default(),
+                };
+                slot
+            }
+            None => {
+                let slot = self.counters.len();
+                self.counters.insert(key.into(), Counter::default());
+                if let Some(bound) = self.bound.as_mut() {
+                    bound.by_count.insert((0, slot));
+                }
+                slot
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(counter: &TopKCounter<()>) -> Vec<(String, u64)> {
+        counter
+            .iter()
+            .map(|(key, count, _)| (key.to_string(), count))
+            .collect()
+    }
+
+    #[test]
+    fn counts_exactly_until_bounded() {
+        let mut counter = TopKCounter::<()>::new();
+        for key in ["a", "b", "a", "c", "a", "b"] {
+            counter.increment(key);
+        }
+
+        assert_eq!(counter.capacity(), None);
+        assert_eq!(
+            counts(&counter),
+            vec![
+                ("a".to_string(), 3),
+                ("b".to_string(), 2),
+                ("c".to_string(), 1)
+            ]
+        );
+        assert_eq!(counter.max_overcount(), 0);
+    }
+
+    #[test]
+    fn bounding_keeps_the_most_counted_keys_in_order() {
+        let mut counter = TopKCounter::<()>::new();
+        for key in ["a", "b", "c", "b", "c", "d"] {
+            counter.increment(key);
+        }
+
+        counter.bound(2);
+
+        assert_eq!(counter.capacity(), Some(2));
+        assert_eq!(
+            counts(&counter),
+            vec![("b".to_string(), 2), ("c".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn new_keys_replace_the_least_counted_key() {
+        let mut counter = TopKCounter::<Vec<u32>>::new();
+        counter.bound(2);
+        counter.increment("a").1.push(1);
+        counter.increment("a");
+        counter.increment("b").1.push(2);
+
+        let (key, value) = counter.increment("c");
+        assert_eq!(key.as_ref(), "c");
+        assert!(value.is_empty());
+
+        assert_eq!(counter.get("b"), None);
+        assert_eq!(counter.get("c"), Some((2, &Vec::new())));
+        assert_eq!(counter.get("a"), Some((2, &vec![1])));
+        assert_eq!(counter.max_overcount(), 1);
+        assert!(counter.track("d").is_none());
+    }
+
+    #[test]
+    fn merging_adds_counts_in_slot_order() {
+        let mut left = TopKCounter::<Vec<u32>>::new();
+        left.increment("a").1.push(1);
+        left.increment("b");
+        let mut right = TopKCounter::<Vec<u32>>::new();
+        right.increment("c");
+        right.increment("a").1.push(2);
+
+        left.merge(right, |ours, theirs| ours.extend(theirs));
+
+        assert_eq!(left.capacity(), None);
+        assert_eq!(left.get("a"), Some((2, &vec![1, 2])));
+        let keys: Vec<_> = left.iter().map(|(key, _, _)| key.to_string()).collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn merging_a_bounded_counter_keeps_the_capacity() {
+        let mut left = TopKCounter::<()>::new();
+        for key in ["a", "b", "b"] {
+            left.increment(key);
+        }
+        let mut right = TopKCounter::<()>::new();
+        right.bound(2);
+        for key in ["c", "c", "c", "a"] {
+            right.increment(key);
+        }
+
+        left.merge(right, |_, _| {});
+
+        assert_eq!(left.capacity(), Some(2));
+        assert_eq!(
+            counts(&left),
+            vec![("a".to_string(), 2), ("c".to_string(), 3)]
+        );
+    }
+}
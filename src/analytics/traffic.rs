@@ -0,0 +1,315 @@
+//! Query and error counts per minute or hour of the analyzed time range
+//!
+//! Counts are kept per minute while entries are analyzed. The histogram is
+//! built from them in buckets of the requested width, widened to a coarser
+//! one when the time range would need more than [`MAX_TRAFFIC_ROWS`] rows.
+
+use super::stats::{epoch_minute, minute_timestamp};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Most buckets a traffic histogram has before its buckets are widened
+pub const MAX_TRAFFIC_ROWS: usize = 200;
+
+/// Bucket widths in minutes tried, narrowest first, when widening; past the
+/// last one, whole days are used
+const BUCKET_MINUTES: [u64; 12] = [1, 2, 5, 10, 15, 30, 60, 120, 180, 360, 720, 1440];
+
+const MINUTES_PER_DAY: u64 = 1440;
+
+/// Bucket width asked for with `--histogram`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrafficGranularity {
+    Minute,
+    Hour,
+}
+
+impl TrafficGranularity {
+    /// Lowercase name used on the command line and in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+        }
+    }
+
+    /// Width of one bucket in minutes
+    pub fn minutes(self) -> u64 {
+        match self {
+            Self::Minute => 1,
+            Self::Hour => 60,
+        }
+    }
+}
+
+impl std::fmt::Display for TrafficGranularity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Narrowest bucket width, in minutes and at least `granularity`, that
+/// covers `first` to `last` in at most `max_rows` buckets aligned to whole
+/// multiples of the width since the Unix epoch
+pub fn traffic_bucket_minutes(
+    granularity: TrafficGranularity,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+    max_rows: usize,
+) -> u64 {
+    let (first, last) = (epoch_minute(first), epoch_minute(last.max(first)));
+    let rows =
+        |width: u64| bucket_start(last, width).abs_diff(bucket_start(first, width)) / width + 1;
+    let fits = |width: &u64| rows(*width) <= max_rows.max(1) as u64;
+
+    BUCKET_MINUTES
+        .into_iter()
+        .filter(|width| *width >= granularity.minutes())
+        .find(fits)
+        .unwrap_or_else(|| {
+            (2..)
+                .map(|days| days * MINUTES_PER_DAY)
+                .find(fits)
+                .unwrap_or(MINUTES_PER_DAY)
+        })
+}
+
+/// Human-readable bucket width, e.g. `10 minutes` or `1 hour`
+pub fn format_bucket_width(minutes: u64) -> String {
+    let (count, unit) = if minutes.is_multiple_of(MINUTES_PER_DAY) {
+        (minutes / MINUTES_PER_DAY, "day")
+    } else if minutes.is_multiple_of(60) {
+        (minutes / 60, "hour")
+    } else {
+        (minutes, "minute")
+    };
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+/// One bucket of a [`TrafficHistogram`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficBucket {
+    pub start: DateTime<Utc>,
+    pub query_count: u64,
+    pub error_count: u64,
+}
+
+/// Query and error counts per bucket, one bucket for every step of the time
+/// range, empty ones included, earliest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficHistogram {
+    /// Width asked for
+    pub granularity: TrafficGranularity,
+    /// Width used, wider than `granularity` when the range needed more than
+    /// [`MAX_TRAFFIC_ROWS`] buckets
+    pub bucket_minutes: u64,
+    pub buckets: Vec<TrafficBucket>,
+}
+
+impl TrafficHistogram {
+    /// Whether the buckets were widened past the requested width
+    pub fn is_widened(&self) -> bool {
+        self.bucket_minutes > self.granularity.minutes()
+    }
+
+    /// End of `bucket`, exclusive
+    pub fn bucket_end(&self, bucket: &TrafficBucket) -> DateTime<Utc> {
+        bucket.start + Duration::minutes(self.bucket_minutes as i64)
+    }
+
+    /// Most queries in one bucket
+    pub fn max_query_count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.query_count)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Note naming the bucket width used, when it was widened
+    pub fn widened_note(&self) -> Option<String> {
+        self.is_widened().then(|| {
+            format!(
+                "traffic histogram buckets widened from 1 {} to {} to keep the chart short",
+                self.granularity,
+                format_bucket_width(self.bucket_minutes)
+            )
+        })
+    }
+}
+
+/// Query and error counts per minute, fed while entries are analyzed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrafficCounter {
+    /// `(queries, errors)` keyed by minutes since the Unix epoch
+    minutes: BTreeMap<i64, (u64, u64)>,
+}
+
+impl TrafficCounter {
+    /// Count a query at `timestamp`
+    pub fn record_query(&mut self, timestamp: DateTime<Utc>) {
+        self.minutes.entry(epoch_minute(timestamp)).or_default().0 += 1;
+    }
+
+    /// Count an error at `timestamp`
+    pub fn record_error(&mut self, timestamp: DateTime<Utc>) {
+        self.minutes.entry(epoch_minute(timestamp)).or_default().1 += 1;
+    }
+
+    /// Add the counts of `other`
+    pub fn merge(&mut self, other: Self) {
+        for (minute, (queries, errors)) in other.minutes {
+            let counts = self.minutes.entry(minute).or_default();
+            counts.0 += queries;
+            counts.1 += errors;
+        }
+    }
+
+    /// Histogram of the counts in buckets of `granularity`, widened to fit
+    /// `max_rows`; `None` when nothing was counted
+    pub fn histogram(
+        &self,
+        granularity: TrafficGranularity,
+        max_rows: usize,
+    ) -> Option<TrafficHistogram> {
+        let (&first, _) = self.minutes.first_key_value()?;
+        let (&last, _) = self.minutes.last_key_value()?;
+        let bucket_minutes = traffic_bucket_minutes(
+            granularity,
+            minute_timestamp(first),
+            minute_timestamp(last),
+            max_rows,
+        );
+
+        let width = bucket_minutes as i64;
+        let first = bucket_start(first, bucket_minutes);
+        let rows = (bucket_start(last, bucket_minutes) - first) / width + 1;
+        let mut buckets: Vec<_> = (0..rows)
+            .map(|row| TrafficBucket {
+                start: minute_timestamp(first + row * width),
+                query_count: 0,
+                error_count: 0,
+            })
+            .collect();
+        for (minute, (queries, errors)) in &self.minutes {
+            let bucket = &mut buckets[((minute - first) / width) as usize];
+            bucket.query_count += queries;
+            bucket.error_count += errors;
+        }
+
+        Some(TrafficHistogram {
+            granularity,
+            bucket_minutes,
+            buckets,
+        })
+    }
+}
+
+/// Start, in epoch minutes, of the bucket of `width` minutes holding `minute`
+fn bucket_start(minute: i64, width: u64) -> i64 {
+    minute - minute.rem_euclid(width as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 8, 15, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn widens_buckets_to_the_narrowest_width_that_fits() {
+        let minute = TrafficGranularity::Minute;
+        let hour = TrafficGranularity::Hour;
+
+        // 10:00 to 13:19 is 200 one-minute buckets
+        assert_eq!(
+            traffic_bucket_minutes(minute, at(10, 0), at(13, 19), 200),
+            1
+        );
+        // One more minute needs 2-minute buckets: 10:00, 10:02, ... 13:20
+        assert_eq!(
+            traffic_bucket_minutes(minute, at(10, 0), at(13, 20), 200),
+            2
+        );
+        // A whole day does not fit 5-minute buckets (288) but fits 10 (144)
+        let next_day = at(0, 0) + Duration::days(1);
+        assert_eq!(
+            traffic_bucket_minutes(minute, at(0, 0), next_day - Duration::minutes(1), 200),
+            10
+        );
+        // Buckets are aligned, so 10:59 to 11:00 spans two hours
+        assert_eq!(traffic_bucket_minutes(hour, at(10, 59), at(11, 0), 2), 60);
+        assert_eq!(traffic_bucket_minutes(hour, at(10, 59), at(11, 0), 1), 120);
+        // Thirty days of hours (720) widen to 6 hours (120 rows)
+        let month_later = at(0, 0) + Duration::days(30);
+        assert_eq!(
+            traffic_bucket_minutes(hour, at(0, 0), month_later, 200),
+            360
+        );
+        // Past a day, whole days are used
+        let years_later = at(0, 0) + Duration::days(999);
+        assert_eq!(
+            traffic_bucket_minutes(hour, at(0, 0), years_later, 200),
+            5 * MINUTES_PER_DAY
+        );
+
+        assert_eq!(format_bucket_width(1), "1 minute");
+        assert_eq!(format_bucket_width(10), "10 minutes");
+        assert_eq!(format_bucket_width(120), "2 hours");
+        assert_eq!(format_bucket_width(5 * MINUTES_PER_DAY), "5 days");
+    }
+
+    #[test]
+    fn histogram_has_a_row_for_every_bucket_of_the_range() {
+        let mut counter = TrafficCounter::default();
+        for timestamp in [at(10, 0), at(10, 0), at(10, 3), at(10, 4)] {
+            counter.record_query(timestamp);
+        }
+        let mut later = TrafficCounter::default();
+        later.record_error(at(10, 4));
+        later.record_query(at(10, 5));
+        counter.merge(later);
+
+        let histogram = counter.histogram(TrafficGranularity::Minute, 200).unwrap();
+        assert!(!histogram.is_widened());
+        assert_eq!(histogram.widened_note(), None);
+        let counts: Vec<_> = histogram
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.query_count, bucket.error_count))
+            .collect();
+        assert_eq!(counts, [(2, 0), (0, 0), (0, 0), (1, 0), (1, 1), (1, 0)]);
+        assert_eq!(histogram.buckets[3].start, at(10, 3));
+        assert_eq!(histogram.max_query_count(), 2);
+
+        let widened = counter.histogram(TrafficGranularity::Minute, 3).unwrap();
+        assert_eq!(widened.bucket_minutes, 2);
+        assert_eq!(widened.bucket_end(&widened.buckets[0]), at(10, 2));
+        let counts: Vec<_> = widened
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.query_count, bucket.error_count))
+            .collect();
+        assert_eq!(counts, [(2, 0), (1, 0), (2, 1)]);
+        assert_eq!(
+            widened.widened_note().as_deref(),
+            Some(
+                "traffic histogram buckets widened from 1 minute to 2 minutes to keep the chart short"
+            )
+        );
+
+        assert_eq!(
+            TrafficCounter::default().histogram(TrafficGranularity::Hour, 200),
+            None
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Reasons PostgreSQL canceled a statement or terminated a connection
+//!
+//! Timeouts, administrator commands, and recovery conflicts are logged as
+//! errors with fixed messages, such as `canceling statement due to statement
+//! timeout`. [`CancelReason::from_message`] recognizes them so the analyzer
+//! can count cancellations by reason, by failing statement, and by hour.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Why a statement was canceled or a connection terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    /// `statement_timeout` expired
+    StatementTimeout,
+    /// `lock_timeout` expired while waiting for a lock
+    LockTimeout,
+    /// `idle_in_transaction_session_timeout` expired
+    IdleInTransactionTimeout,
+    /// `pg_terminate_backend` or a fast shutdown
+    AdministratorCommand,
+    /// A standby canceled the query to replay WAL
+    RecoveryConflict,
+    /// The client or `pg_cancel_backend` canceled the statement
+    UserRequest,
+}
+
+/// Message prefixes of each reason, after `canceling statement due to ` or
+/// `terminating connection due to `
+const REASON_MESSAGES: &[(&str, CancelReason)] = &[
+    ("statement timeout", CancelReason::StatementTimeout),
+    ("lock timeout", CancelReason::LockTimeout),
+    (
+        "idle-in-transaction timeout",
+        CancelReason::IdleInTransactionTimeout,
+    ),
+    ("administrator command", CancelReason::AdministratorCommand),
+    ("conflict with recovery", CancelReason::RecoveryConflict),
+    ("user request", CancelReason::UserRequest),
+];
+
+impl CancelReason {
+    /// Every reason, in report order
+    pub const ALL: [CancelReason; 6] = [
+        CancelReason::StatementTimeout,
+        CancelReason::LockTimeout,
+        CancelReason::IdleInTransactionTimeout,
+        CancelReason::AdministratorCommand,
+        CancelReason::RecoveryConflict,
+        CancelReason::UserRequest,
+    ];
+
+    /// Reason of a `canceling statement due to ...` or `terminating
+    /// connection due to ...` error message
+    pub fn from_message(message: &str) -> Option<Self> {
+        let message = message.trim_start();
+        let reason = message
+            .strip_prefix("canceling statement due to ")
+            .or_else(|| message.strip_prefix("terminating connection due to "))?;
+        REASON_MESSAGES
+            .iter()
+            .find(|(prefix, _)| reason.starts_with(prefix))
+            .map(|(_, reason)| *reason)
+    }
+
+    /// Snake-case name used as the key in reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelReason::StatementTimeout => "statement_timeout",
+            CancelReason::LockTimeout => "lock_timeout",
+            CancelReason::IdleInTransactionTimeout => "idle_in_transaction_timeout",
+            CancelReason::AdministratorCommand => "administrator_command",
+            CancelReason::RecoveryConflict => "recovery_conflict",
+            CancelReason::UserRequest => "user_request",
+        }
+    }
+}
+
+impl std::fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Canceled statements and terminated connections of a log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CancellationStats {
+    /// Number of cancellations and terminations
+    pub total: u64,
+    /// Counts per reason name, such as `statement_timeout`
+    pub by_reason: BTreeMap<String, u64>,
+    /// The most often canceled statements, most canceled first
+    pub top_queries: Vec<CanceledQuery>,
+    /// Counts per hour of day (0-23)
+    pub hourly: BTreeMap<u32, u64>,
+}
+
+/// A normalized statement and how often it was canceled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CanceledQuery {
+    pub query: String,
+    pub count: u64,
+    /// Counts per reason name
+    pub by_reason: BTreeMap<String, u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_each_reason() {
+        let cases = [
+            (
+                "canceling statement due to statement timeout",
+                CancelReason::StatementTimeout,
+            ),
+            (
+                "canceling statement due to lock timeout",
+                CancelReason::LockTimeout,
+            ),
+            (
+                "terminating connection due to idle-in-transaction timeout",
+                CancelReason::IdleInTransactionTimeout,
+            ),
+            (
+                "terminating connection due to administrator command",
+                CancelReason::AdministratorCommand,
+            ),
+            (
+                "canceling statement due to conflict with recovery",
+                CancelReason::RecoveryConflict,
+            ),
+            (
+                "terminating connection due to conflict with recovery",
+                CancelReason::RecoveryConflict,
+            ),
+            (
+                "canceling statement due to user request",
+                CancelReason::UserRequest,
+            ),
+        ];
+        for (message, reason) in cases {
+            assert_eq!(
+                CancelReason::from_message(message),
+                Some(reason),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_other_errors() {
+        assert_eq!(
+            CancelReason::from_message("duplicate key value violates unique constraint \"x\""),
+            None
+        );
+        assert_eq!(
+            CancelReason::from_message("canceling autovacuum task"),
+            None
+        );
+    }
+}
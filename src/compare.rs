@@ -0,0 +1,464 @@
+//! Comparison of two JSON summary reports
+//!
+//! Queries are matched by `query_id`, or by normalized SQL for reports
+//! without ids, across the `most_frequent` lists of both reports. Only listed
+//! queries can be compared: a query that fell out of the top list in one
+//! report shows up as new or disappeared, so compare reports written with the
+//! same `--max-frequent-queries`.
+//!
+//! The `slowest_queries` lists are compared by rank: each query of the
+//! current list gets how many places it moved and how much its p95 changed.
+
+use crate::output::schema::{FrequentQueryEntry, Report};
+use crate::query_id;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default change, in percent, above which a query is reported
+pub const DEFAULT_CHANGE_THRESHOLD_PERCENT: f64 = 20.0;
+
+/// How a query changed between the baseline and current reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Changed beyond the threshold and spends more total time
+    Regressed,
+    /// Changed beyond the threshold and spends less total time
+    Improved,
+    /// Only in the current report
+    New,
+    /// Only in the baseline report
+    Disappeared,
+}
+
+/// Metrics of one query in one report
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuerySnapshot {
+    pub count: u64,
+    pub avg_duration_ms: f64,
+    /// `None` for reports that only had the overall average
+    pub p95_duration_ms: Option<f64>,
+    pub total_duration_ms: f64,
+}
+
+impl From<&FrequentQueryEntry> for QuerySnapshot {
+    fn from(entry: &FrequentQueryEntry) -> Self {
+        // Durations count as zero for reports without logged durations
+        let avg_duration_ms = entry.avg_duration_ms.unwrap_or(0.0);
+        Self {
+            count: entry.count,
+            avg_duration_ms,
+            p95_duration_ms: entry.p95_duration_ms,
+            total_duration_ms: entry
+                .total_duration_ms
+                .unwrap_or(avg_duration_ms * entry.count as f64),
+        }
+    }
+}
+
+/// A query whose metrics changed, appeared, or disappeared
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryChange {
+    pub kind: ChangeKind,
+    pub query: String,
+    pub query_id: String,
+    pub baseline: Option<QuerySnapshot>,
+    pub current: Option<QuerySnapshot>,
+    /// Percent changes; `None` when the baseline value is zero or unknown
+    pub count_change_percent: Option<f64>,
+    pub avg_change_percent: Option<f64>,
+    pub p95_change_percent: Option<f64>,
+    /// Change in total time spent in the query, in milliseconds
+    pub time_delta_ms: f64,
+}
+
+/// A query of the current slowest list against the baseline's
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowestRankChange {
+    pub query_id: String,
+    /// Position in the current slowest list, from 1
+    pub rank: usize,
+    /// Places moved up since the baseline, negative when it moved down;
+    /// `None` when the baseline did not list the query
+    pub rank_delta: Option<i64>,
+    /// Change of the p95 duration in milliseconds; `None` when either report
+    /// lacks it
+    pub p95_delta_ms: Option<f64>,
+}
+
+/// Changed queries between two reports, largest absolute time delta first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportComparison {
+    pub threshold_percent: f64,
+    pub changes: Vec<QueryChange>,
+    /// Rank changes of the current slowest queries, in current rank order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slowest: Vec<SlowestRankChange>,
+}
+
+impl ReportComparison {
+    /// Changes of one kind, in impact order
+    pub fn of_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &QueryChange> {
+        self.changes
+            .iter()
+            .filter(move |change| change.kind == kind)
+    }
+
+    /// Rank change of the row of the current slowest list at `rank`, when it
+    /// lists `query_id`
+    pub fn slowest_change(&self, rank: usize, query_id: &str) -> Option<&SlowestRankChange> {
+        slowest_change(&self.slowest, rank, query_id)
+    }
+}
+
+/// Compare the most frequent queries of two reports, keeping queries whose
+/// count, average, or p95 duration changed by more than `threshold_percent`,
+/// and queries found in only one report
+pub fn compare_reports(
+    baseline: &Report,
+    current: &Report,
+    threshold_percent: f64,
+) -> ReportComparison {
+    let baseline_queries = queries_by_id(baseline);
+    let mut current_queries = queries_by_id(current);
+    let mut changes = Vec::new();
+
+    for (id, before) in baseline_queries {
+        let change = match current_queries.shift_remove(&id) {
+            Some(after) => changed_query(id, before, after, threshold_percent),
+            None => Some(QueryChange {
+                kind: ChangeKind::Disappeared,
+                query: before.query.clone(),
+                query_id: id,
+                baseline: Some(before.into()),
+                current: None,
+                count_change_percent: None,
+                avg_change_percent: None,
+                p95_change_percent: None,
+                time_delta_ms: -QuerySnapshot::from(before).total_duration_ms,
+            }),
+        };
+        changes.extend(change);
+    }
+    changes.extend(current_queries.into_iter().map(|(id, after)| QueryChange {
+        kind: ChangeKind::New,
+        query: after.query.clone(),
+        query_id: id,
+        baseline: None,
+        current: Some(after.into()),
+        count_change_percent: None,
+        avg_change_percent: None,
+        p95_change_percent: None,
+        time_delta_ms: QuerySnapshot::from(after).total_duration_ms,
+    }));
+
+    changes.sort_by(|a, b| {
+        b.time_delta_ms
+            .abs()
+            .total_cmp(&a.time_delta_ms.abs())
+            .then_with(|| a.query_id.cmp(&b.query_id))
+    });
+
+    ReportComparison {
+        threshold_percent,
+        changes,
+        slowest: slowest_rank_changes(baseline, current),
+    }
+}
+
+/// Rank and p95 changes of each row of the current slowest list; a query
+/// listed more than once, for several slow executions, matches its rows in
+/// the baseline in order
+fn slowest_rank_changes(baseline: &Report, current: &Report) -> Vec<SlowestRankChange> {
+    let baseline_ranks: HashMap<(String, usize), usize> = slowest_ids(baseline)
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| (key, i + 1))
+        .collect();
+    let (baseline_p95, current_p95) = (p95_by_id(baseline), p95_by_id(current));
+
+    slowest_ids(current)
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let rank = i + 1;
+            let before = baseline_ranks.get(&key);
+            let id = key.0;
+            SlowestRankChange {
+                rank,
+                rank_delta: before.map(|before| *before as i64 - rank as i64),
+                p95_delta_ms: baseline_p95
+                    .get(&id)
+                    .zip(current_p95.get(&id))
+                    .map(|(before, after)| after - before),
+                query_id: id,
+            }
+        })
+        .collect()
+}
+
+/// The change of `changes` for the slowest row at `rank`, when it lists
+/// `query_id`
+pub(crate) fn slowest_change<'a>(
+    changes: &'a [SlowestRankChange],
+    rank: usize,
+    query_id: &str,
+) -> Option<&'a SlowestRankChange> {
+    changes
+        .get(rank.wrapping_sub(1))
+        .filter(|change| change.rank == rank && change.query_id == query_id)
+}
+
+/// `id`, or the id of `query` for reports written before query ids existed
+fn entry_id(id: &str, query: &str) -> String {
+    if id.is_empty() {
+        query_id(query)
+    } else {
+        id.to_string()
+    }
+}
+
+/// Ids of the slowest list of a report, slowest first, each with how many
+/// earlier rows had the same id
+fn slowest_ids(report: &Report) -> Vec<(String, usize)> {
+    let mut seen = HashMap::new();
+    report
+        .query_analysis
+        .iter()
+        .flat_map(|section| section.slowest_queries.iter().flatten())
+        .map(|entry| {
+            let id = entry_id(&entry.query_id, &entry.query);
+            let occurrence = seen.entry(id.clone()).or_insert(0);
+            *occurrence += 1;
+            (id, *occurrence - 1)
+        })
+        .collect()
+}
+
+/// p95 durations of the listed queries of a report, from either list
+fn p95_by_id(report: &Report) -> HashMap<String, f64> {
+    let Some(section) = &report.query_analysis else {
+        return HashMap::new();
+    };
+    let frequent = section
+        .most_frequent
+        .iter()
+        .flatten()
+        .map(|entry| (&entry.query_id, &entry.query, entry.p95_duration_ms));
+    let slowest = section
+        .slowest_queries
+        .iter()
+        .flatten()
+        .map(|entry| (&entry.query_id, &entry.query, entry.p95_duration_ms));
+    frequent
+        .chain(slowest)
+        .filter_map(|(id, query, p95)| Some((entry_id(id, query), p95?)))
+        .collect()
+}
+
+/// Listed queries of a report keyed by `query_id`, in report order
+fn queries_by_id(report: &Report) -> IndexMap<String, &FrequentQueryEntry> {
+    report
+        .query_analysis
+        .iter()
+        .flat_map(|section| section.most_frequent.iter().flatten())
+        .map(|entry| (entry_id(&entry.query_id, &entry.query), entry))
+        .collect()
+}
+
+fn changed_query(
+    query_id: String,
+    before: &FrequentQueryEntry,
+    after: &FrequentQueryEntry,
+    threshold_percent: f64,
+) -> Option<QueryChange> {
+    let query = after.query.clone();
+    let (before, after) = (QuerySnapshot::from(before), QuerySnapshot::from(after));
+    let p95 = before.p95_duration_ms.zip(after.p95_duration_ms);
+    let exceeded = exceeds(before.count as f64, after.count as f64, threshold_percent)
+        || exceeds(
+            before.avg_duration_ms,
+            after.avg_duration_ms,
+            threshold_percent,
+        )
+        || p95.is_some_and(|(before, after)| exceeds(before, after, threshold_percent));
+    if !exceeded {
+        return None;
+    }
+
+    let time_delta_ms = after.total_duration_ms - before.total_duration_ms;
+    Some(QueryChange {
+        kind: if time_delta_ms > 0.0 {
+            ChangeKind::Regressed
+        } else {
+            ChangeKind::Improved
+        },
+        query,
+        query_id,
+        baseline: Some(before),
+        current: Some(after),
+        count_change_percent: percent_change(before.count as f64, after.count as f64),
+        avg_change_percent: percent_change(before.avg_duration_ms, after.avg_duration_ms),
+        p95_change_percent: p95.and_then(|(before, after)| percent_change(before, after)),
+        time_delta_ms,
+    })
+}
+
+fn percent_change(before: f64, after: f64) -> Option<f64> {
+    (before != 0.0).then(|| (after - before) / before * 100.0)
+}
+
+/// Whether the change is beyond the threshold; any change from zero is
+fn exceeds(before: f64, after: f64, threshold_percent: f64) -> bool {
+    match percent_change(before, after) {
+        Some(percent) => percent.abs() > threshold_percent,
+        None => after != 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::schema::{QueryAnalysisSection, SlowQueryEntry};
+
+    fn entry(query: &str, count: u64, avg: f64, p95: f64) -> FrequentQueryEntry {
+        FrequentQueryEntry {
+            query: query.to_string(),
+            query_id: query_id(query),
+            count,
+            avg_duration_ms: Some(avg),
+            p95_duration_ms: Some(p95),
+            total_duration_ms: Some(avg * count as f64),
+            percent_of_total_duration: None,
+            examples: Vec::new(),
+        }
+    }
+
+    fn report(most_frequent: Vec<FrequentQueryEntry>) -> Report {
+        Report {
+            query_analysis: Some(QueryAnalysisSection {
+                most_frequent: Some(most_frequent),
+                ..QueryAnalysisSection::default()
+            }),
+            ..Report::default()
+        }
+    }
+
+    #[test]
+    fn reports_changes_beyond_the_threshold_by_time_impact() {
+        let baseline = report(vec![
+            entry("SELECT a", 100, 10.0, 20.0),
+            entry("SELECT steady", 100, 10.0, 20.0),
+            entry("SELECT gone", 5, 4.0, 4.0),
+            entry("SELECT faster", 10, 100.0, 150.0),
+        ]);
+        let current = report(vec![
+            entry("SELECT a", 100, 50.0, 90.0),
+            entry("SELECT steady", 110, 10.5, 21.0),
+            entry("SELECT faster", 10, 20.0, 30.0),
+            entry("SELECT new", 3, 1000.0, 1000.0),
+        ]);
+
+        let comparison = compare_reports(&baseline, &current, 20.0);
+        let summary: Vec<_> = comparison
+            .changes
+            .iter()
+            .map(|change| (change.query.as_str(), change.kind, change.time_delta_ms))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("SELECT a", ChangeKind::Regressed, 4000.0),
+                ("SELECT new", ChangeKind::New, 3000.0),
+                ("SELECT faster", ChangeKind::Improved, -800.0),
+                ("SELECT gone", ChangeKind::Disappeared, -20.0),
+            ]
+        );
+        let regressed = &comparison.changes[0];
+        assert_eq!(regressed.count_change_percent, Some(0.0));
+        assert_eq!(regressed.avg_change_percent, Some(400.0));
+        assert_eq!(regressed.p95_change_percent, Some(350.0));
+    }
+
+    fn slow(query: &str, p95: Option<f64>) -> SlowQueryEntry {
+        SlowQueryEntry {
+            query: query.to_string(),
+            query_id: query_id(query),
+            duration_ms: p95.unwrap_or(1.0),
+            count: 1,
+            p95_duration_ms: p95,
+            total_duration_ms: None,
+            pct_of_total_time: None,
+            rank_delta: None,
+            p95_delta_ms: None,
+            examples: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn ranks_the_current_slowest_queries_against_the_baseline() {
+        let mut baseline = report(vec![entry("SELECT c", 10, 5.0, 8.0)]);
+        baseline.query_analysis.as_mut().unwrap().slowest_queries = Some(vec![
+            slow("SELECT a", Some(900.0)),
+            slow("SELECT b", Some(500.0)),
+            slow("SELECT c", None),
+        ]);
+        let mut current = report(Vec::new());
+        current.query_analysis.as_mut().unwrap().slowest_queries = Some(vec![
+            slow("SELECT c", Some(1200.0)),
+            slow("SELECT a", Some(800.0)),
+            slow("SELECT new", Some(100.0)),
+            slow("SELECT b", None),
+            slow("SELECT a", Some(800.0)),
+        ]);
+
+        let comparison = compare_reports(&baseline, &current, 20.0);
+        let slowest: Vec<_> = comparison
+            .slowest
+            .iter()
+            .map(|change| (change.rank, change.rank_delta, change.p95_delta_ms))
+            .collect();
+
+        assert_eq!(
+            slowest,
+            vec![
+                // The baseline p95 of c comes from its most frequent row
+                (1, Some(2), Some(1192.0)),
+                (2, Some(-1), Some(-100.0)),
+                (3, None, None),
+                (4, Some(-2), None),
+                // The baseline listed a only once
+                (5, None, Some(-100.0)),
+            ]
+        );
+        assert!(comparison
+            .slowest_change(1, &query_id("SELECT c"))
+            .is_some());
+        assert!(comparison
+            .slowest_change(2, &query_id("SELECT c"))
+            .is_none());
+    }
+
+    #[test]
+    fn matches_queries_without_ids_by_normalized_sql() {
+        let mut before = entry("SELECT a", 10, 1.0, 1.0);
+        before.query_id.clear();
+        before.p95_duration_ms = None;
+        before.total_duration_ms = None;
+
+        let comparison = compare_reports(
+            &report(vec![before]),
+            &report(vec![entry("SELECT a", 30, 1.0, 1.0)]),
+            50.0,
+        );
+
+        assert_eq!(comparison.changes.len(), 1);
+        let change = &comparison.changes[0];
+        assert_eq!(change.kind, ChangeKind::Regressed);
+        assert_eq!(change.count_change_percent, Some(200.0));
+        assert_eq!(change.p95_change_percent, None);
+        assert_eq!(change.time_delta_ms, 20.0);
+    }
+}
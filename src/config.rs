@@ -0,0 +1,263 @@
+//! Analyzer settings from defaults, a TOML config file, and CLI flags
+//!
+//! Settings are resolved in three layers: built-in defaults, then the values
+//! set in a config file such as `pg-logstats.toml`, then CLI flags. Each layer
+//! only overrides the fields it sets.
+//!
+//! ```toml
+//! slow_query_threshold_ms = 250.0
+//! max_slow_queries = 20
+//! max_frequent_queries = 50
+//! ```
+
+use crate::{PgLogstatsError, QueryAnalyzer, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Default threshold above which a query counts as slow, in milliseconds
+pub const DEFAULT_SLOW_QUERY_THRESHOLD_MS: f64 = 1000.0;
+/// Default number of slowest queries listed
+pub const DEFAULT_MAX_SLOW_QUERIES: usize = 10;
+/// Default number of most frequent queries listed
+pub const DEFAULT_MAX_FREQUENT_QUERIES: usize = 20;
+
+/// Resolved analyzer settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Queries slower than this many milliseconds count as slow
+    pub slow_query_threshold_ms: f64,
+    /// Number of slowest queries listed
+    pub max_slow_queries: usize,
+    /// Number of most frequent queries listed
+    pub max_frequent_queries: usize,
+}
+
+/// One layer of settings; unset fields keep the value of the layer below
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigOverrides {
+    pub slow_query_threshold_ms: Option<f64>,
+    pub max_slow_queries: Option<usize>,
+    pub max_frequent_queries: Option<usize>,
+}
+
+impl Config {
+    /// Resolve settings from the defaults, an optional config file, and CLI
+    /// flags, in increasing precedence, and validate the result
+    pub fn resolve(config_file: Option<&Path>, cli: ConfigOverrides) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(path) = config_file {
+            config = config.with_overrides(ConfigOverrides::from_file(path)?);
+        }
+        let config = config.with_overrides(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Copy of these settings with the fields set in `overrides` replaced
+    pub fn with_overrides(self, overrides: ConfigOverrides) -> Self {
+        Self {
+            slow_query_threshold_ms: overrides
+                .slow_query_threshold_ms
+                .unwrap_or(self.slow_query_threshold_ms),
+            max_slow_queries: overrides.max_slow_queries.unwrap_or(self.max_slow_queries),
+            max_frequent_queries: overrides
+                .max_frequent_queries
+                .unwrap_or(self.max_frequent_queries),
+        }
+    }
+
+    /// Check that every setting is usable, naming the first one that is not
+    pub fn validate(&self) -> Result<()> {
+        if !(self.slow_query_threshold_ms.is_finite() && self.slow_query_threshold_ms >= 0.0) {
+            return Err(invalid_field(
+                "slow_query_threshold_ms",
+                "Slow query threshold must be a non-negative number of milliseconds",
+            ));
+        }
+        if self.max_slow_queries == 0 {
+            return Err(invalid_field(
+                "max_slow_queries",
+                "Max slow queries must be greater than 0",
+            ));
+        }
+        if self.max_frequent_queries == 0 {
+            return Err(invalid_field(
+                "max_frequent_queries",
+                "Max frequent queries must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Query analyzer using these settings
+    pub fn query_analyzer(&self) -> QueryAnalyzer {
+        QueryAnalyzer::with_settings(
+            self.slow_query_threshold_ms,
+            self.max_slow_queries,
+            self.max_frequent_queries,
+        )
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            slow_query_threshold_ms: DEFAULT_SLOW_QUERY_THRESHOLD_MS,
+            max_slow_queries: DEFAULT_MAX_SLOW_QUERIES,
+            max_frequent_queries: DEFAULT_MAX_FREQUENT_QUERIES,
+        }
+    }
+}
+
+impl ConfigOverrides {
+    /// Read settings from a TOML config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| PgLogstatsError::Configuration {
+            message: format!("Cannot read config file {}: {}", path.display(), e),
+            field: Some("config".to_string()),
+        })?;
+        Self::from_toml(&text).map_err(|e| match e {
+            PgLogstatsError::Configuration { message, field } => PgLogstatsError::Configuration {
+                message: format!("{}: {}", path.display(), message),
+                field,
+            },
+            other => other,
+        })
+    }
+
+    /// Parse settings from TOML text.
+    ///
+    /// Syntax errors, unknown keys, and values of the wrong type are reported
+    /// with their line number and name the key on that line as the field.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| {
+            let line = e.span().map(|span| {
+                let start = text[..span.start].rfind('\n').map_or(0, |i| i + 1);
+                let end = text[span.start..]
+                    .find('\n')
+                    .map_or(text.len(), |i| span.start + i);
+                (
+                    text[..span.start].matches('\n').count() + 1,
+                    &text[start..end],
+                )
+            });
+            let location = line.map_or_else(
+                || "invalid config".to_string(),
+                |(number, _)| format!("invalid config at line {}", number),
+            );
+            let field = line
+                .and_then(|(_, line)| line_key(line))
+                .unwrap_or("config");
+            PgLogstatsError::Configuration {
+                message: format!("{}: {}", location, e.message().trim_end()),
+                field: Some(field.to_string()),
+            }
+        })
+    }
+}
+
+/// Bare key set on a `key = value` line, if any
+fn line_key(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once('=')?;
+    let key = key.trim();
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    bare.then_some(key)
+}
+
+fn invalid_field(field: &str, message: &str) -> PgLogstatsError {
+    PgLogstatsError::Configuration {
+        message: message.to_string(),
+        field: Some(field.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_of(error: PgLogstatsError) -> Option<String> {
+        match error {
+            PgLogstatsError::Configuration { field, .. } => field,
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn later_layers_override_only_the_fields_they_set() {
+        let file =
+            ConfigOverrides::from_toml("slow_query_threshold_ms = 250.0\nmax_slow_queries = 5\n")
+                .unwrap();
+        let cli = ConfigOverrides {
+            max_slow_queries: Some(3),
+            ..ConfigOverrides::default()
+        };
+
+        let config = Config::default().with_overrides(file).with_overrides(cli);
+
+        assert_eq!(
+            config,
+            Config {
+                slow_query_threshold_ms: 250.0,
+                max_slow_queries: 3,
+                max_frequent_queries: DEFAULT_MAX_FREQUENT_QUERIES,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_invalid_toml_with_its_line() {
+        let error = ConfigOverrides::from_toml(
+            "max_slow_queries = 5\nslow_query_threshold_ms = \"fast\"\n",
+        )
+        .unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("line 2"), "{message}");
+        assert!(message.contains("invalid type"), "{message}");
+        assert_eq!(field_of(error).as_deref(), Some("slow_query_threshold_ms"));
+
+        let error = ConfigOverrides::from_toml("max_slow_queries = [").unwrap_err();
+        assert!(error.to_string().contains("line 1"), "{error}");
+
+        let error = ConfigOverrides::from_toml("slow_threshold = 5").unwrap_err();
+        assert!(error.to_string().contains("unknown field"), "{error}");
+        assert_eq!(field_of(error).as_deref(), Some("slow_threshold"));
+
+        let error = ConfigOverrides::from_toml("max_frequent_queries = -3").unwrap_err();
+        assert_eq!(field_of(error).as_deref(), Some("max_frequent_queries"));
+    }
+
+    #[test]
+    fn validation_names_the_offending_field() {
+        for (overrides, field) in [
+            (
+                ConfigOverrides {
+                    slow_query_threshold_ms: Some(-1.0),
+                    ..ConfigOverrides::default()
+                },
+                "slow_query_threshold_ms",
+            ),
+            (
+                ConfigOverrides {
+                    max_slow_queries: Some(0),
+                    ..ConfigOverrides::default()
+                },
+                "max_slow_queries",
+            ),
+            (
+                ConfigOverrides {
+                    max_frequent_queries: Some(0),
+                    ..ConfigOverrides::default()
+                },
+                "max_frequent_queries",
+            ),
+        ] {
+            let error = Config::resolve(None, overrides).unwrap_err();
+            assert_eq!(field_of(error).as_deref(), Some(field));
+        }
+    }
+}
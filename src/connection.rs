@@ -0,0 +1,104 @@
+//! Connection lifecycle messages
+//!
+//! `log_connections` and `log_disconnections` write one line per step of a
+//! session. The parsers recognize them by the start of the message, ignoring
+//! case, so statements and errors that merely mention a connection are not
+//! counted as one.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Step of a connection's life logged by PostgreSQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+    /// `connection received: host=... port=...`
+    Received,
+    /// `connection authorized: user=... database=...`
+    Authorized,
+    /// `disconnection: session time: ...`
+    Disconnection,
+    /// `connection reset by peer`, or `could not receive data from client:
+    /// Connection reset by peer`
+    Reset,
+}
+
+/// Message starts of each kind, lowercase
+const PREFIXES: [(&str, ConnectionEventKind); 5] = [
+    ("connection received", ConnectionEventKind::Received),
+    ("connection authorized", ConnectionEventKind::Authorized),
+    ("disconnection:", ConnectionEventKind::Disconnection),
+    ("connection reset", ConnectionEventKind::Reset),
+    (
+        "could not receive data from client: connection reset",
+        ConnectionEventKind::Reset,
+    ),
+];
+
+impl ConnectionEventKind {
+    /// Kind of a message that starts with one of the connection lines
+    pub fn from_message(message: &str) -> Option<Self> {
+        PREFIXES
+            .iter()
+            .find(|(prefix, _)| starts_with_ignore_case(message, prefix))
+            .map(|(_, kind)| *kind)
+    }
+
+    /// Lowercase name as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Received => "received",
+            Self::Authorized => "authorized",
+            Self::Disconnection => "disconnection",
+            Self::Reset => "reset",
+        }
+    }
+}
+
+impl fmt::Display for ConnectionEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+fn starts_with_ignore_case(message: &str, prefix: &str) -> bool {
+    message
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_message_starts_only() {
+        assert_eq!(
+            ConnectionEventKind::from_message("connection received: host=10.0.0.5 port=5432"),
+            Some(ConnectionEventKind::Received)
+        );
+        assert_eq!(
+            ConnectionEventKind::from_message("Connection authorized: user=app database=shop"),
+            Some(ConnectionEventKind::Authorized)
+        );
+        assert_eq!(
+            ConnectionEventKind::from_message("disconnection: session time: 0:00:01.000"),
+            Some(ConnectionEventKind::Disconnection)
+        );
+        assert_eq!(
+            ConnectionEventKind::from_message(
+                "could not receive data from client: Connection reset by peer"
+            ),
+            Some(ConnectionEventKind::Reset)
+        );
+        assert_eq!(
+            ConnectionEventKind::from_message("statement: SELECT * FROM connection_log"),
+            None
+        );
+        assert_eq!(
+            ConnectionEventKind::from_message("terminating connection due to idle timeout"),
+            None
+        );
+        assert_eq!(ConnectionEventKind::from_message("connectio"), None);
+    }
+}
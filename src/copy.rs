@@ -0,0 +1,186 @@
+//! Bulk loads and exports with `COPY`
+//!
+//! sqlparser handles only part of PostgreSQL's `COPY` syntax, so
+//! [`CopyStatement::parse`] reads the target table and direction with a regex.
+//! Errors raised while loading rows name the table in their `CONTEXT:` line,
+//! e.g. `COPY orders, line 123, column id: "abc"`, which
+//! [`copy_context_table`] recognizes so they can be attributed to the load.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Table name used for `COPY (query) TO ...`
+pub const COPY_QUERY_TABLE: &str = "(query)";
+
+/// Whether `COPY` loaded into a table or exported from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CopyDirection {
+    /// `COPY table FROM ...`, a load
+    From,
+    /// `COPY table TO ...`, an export
+    To,
+}
+
+impl CopyDirection {
+    /// Keyword as written in SQL
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CopyDirection::From => "FROM",
+            CopyDirection::To => "TO",
+        }
+    }
+}
+
+impl std::fmt::Display for CopyDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Target of a `COPY` statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyStatement {
+    /// Table as written, with its schema if given, or `(query)` for
+    /// `COPY (SELECT ...) TO`
+    pub table: String,
+    pub direction: CopyDirection,
+}
+
+fn copy_regex() -> &'static Regex {
+    static COPY_REGEX: OnceLock<Regex> = OnceLock::new();
+    COPY_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"(?is)^\s*COPY\s+(?:BINARY\s+)?(?:(\(.*\))|((?:"[^"]*"|[^\s(".;]+)(?:\.(?:"[^"]*"|[^\s(".;]+))*)(?:\s*\([^)]*\))?)\s+(FROM|TO)\b"#,
+        )
+        .unwrap()
+    })
+}
+
+impl CopyStatement {
+    /// Table and direction of a `COPY` statement; `None` for other SQL
+    pub fn parse(sql: &str) -> Option<Self> {
+        let captures = copy_regex().captures(sql)?;
+        let table = match captures.get(2) {
+            Some(table) => table.as_str().to_string(),
+            None => COPY_QUERY_TABLE.to_string(),
+        };
+        let direction = if captures[3].eq_ignore_ascii_case("FROM") {
+            CopyDirection::From
+        } else {
+            CopyDirection::To
+        };
+        Some(Self { table, direction })
+    }
+}
+
+/// Table named by a `COPY orders, line 123...` line of an error's context,
+/// which may hold several lines
+pub fn copy_context_table(context: &str) -> Option<&str> {
+    context.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("COPY ")?;
+        let (table, _) = rest.split_once(", line ")?;
+        Some(table)
+    })
+}
+
+/// `COPY` operations of a log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CopyStats {
+    /// Number of `COPY` statements run
+    pub operations: u64,
+    /// Total duration of all `COPY` statements in milliseconds
+    pub total_duration: f64,
+    /// Longest `COPY` statement in milliseconds
+    pub max_duration: f64,
+    /// Errors raised while copying rows
+    pub error_count: u64,
+    /// Per table and direction, highest total duration first
+    pub tables: Vec<CopyTableStats>,
+}
+
+/// `COPY` operations on one table in one direction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopyTableStats {
+    pub table: String,
+    pub direction: CopyDirection,
+    pub operations: u64,
+    /// Total duration in milliseconds
+    pub total_duration: f64,
+    /// Longest operation in milliseconds
+    pub max_duration: f64,
+    /// Errors whose context names the table; counted against loads, since
+    /// exports rarely fail row by row
+    pub error_count: u64,
+}
+
+impl CopyTableStats {
+    /// Stats with nothing counted yet
+    pub fn new(table: impl Into<String>, direction: CopyDirection) -> Self {
+        Self {
+            table: table.into(),
+            direction,
+            operations: 0,
+            total_duration: 0.0,
+            max_duration: 0.0,
+            error_count: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_table_and_direction() {
+        let cases = [
+            ("COPY orders FROM STDIN", "orders", CopyDirection::From),
+            (
+                "copy public.orders (id, total) from '/tmp/orders.csv' WITH (FORMAT csv)",
+                "public.orders",
+                CopyDirection::From,
+            ),
+            (
+                "COPY \"Order Items\" TO STDOUT",
+                "\"Order Items\"",
+                CopyDirection::To,
+            ),
+            ("COPY BINARY events TO STDOUT", "events", CopyDirection::To),
+            (
+                "COPY (SELECT * FROM orders WHERE id > 5) TO STDOUT",
+                COPY_QUERY_TABLE,
+                CopyDirection::To,
+            ),
+        ];
+        for (sql, table, direction) in cases {
+            assert_eq!(
+                CopyStatement::parse(sql),
+                Some(CopyStatement {
+                    table: table.to_string(),
+                    direction,
+                }),
+                "{}",
+                sql
+            );
+        }
+        assert_eq!(CopyStatement::parse("SELECT * FROM copy_jobs"), None);
+    }
+
+    #[test]
+    fn finds_the_table_in_error_context() {
+        assert_eq!(
+            copy_context_table("COPY orders, line 123, column id: \"abc\""),
+            Some("orders")
+        );
+        assert_eq!(
+            copy_context_table("SQL function \"load\"\nCOPY public.orders, line 2: \"x,y\""),
+            Some("public.orders")
+        );
+        assert_eq!(
+            copy_context_table("PL/pgSQL function f() line 3 at RAISE"),
+            None
+        );
+    }
+}
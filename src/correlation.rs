@@ -5,10 +5,15 @@
 //! strategy so structured log implementations can later use stronger keys such
 //! as session ID and per-session line number.
 
+use crate::sql::join_normalized_queries;
 use crate::{EventKind, NormalizedEvent, Query, SessionIdentity, SourceReference, StatementEvent};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Statement of an execution known only from a duration line, as logged with
+/// `log_duration = on` when the statement itself is not logged
+pub const STATEMENT_NOT_LOGGED: &str = "(statement not logged)";
 
 /// A deterministic grouping key for related executions of the same query shape.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -48,6 +53,8 @@ impl QueryFamilyIdentity {
 pub enum CorrelationConfidence {
     Exact,
     StatementOnly,
+    /// A duration without a statement of its process to pair with
+    DurationOnly,
 }
 
 /// A correlated query execution suitable for analytics and findings.
@@ -62,9 +69,12 @@ pub struct QueryExecution {
     pub duration_ms: Option<f64>,
     pub evidence: Vec<SourceReference>,
     pub confidence: CorrelationConfidence,
+    /// Placeholder values logged with the statement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct PendingStatement {
     event_id: String,
     timestamp: DateTime<Utc>,
@@ -101,12 +111,37 @@ pub fn correlate_query_executions(events: &[NormalizedEvent]) -> Vec<QueryExecut
 
 fn correlate_by_process_order(events: &[NormalizedEvent]) -> Vec<QueryExecution> {
     let mut executions = Vec::new();
-    let mut pending_by_process: HashMap<String, PendingStatement> = HashMap::new();
-
+    let mut pairing = ProcessOrderPairing::default();
     for event in events {
+        pairing.push(event, &mut executions);
+    }
+    executions.extend(pairing.finish());
+
+    executions.sort_by_key(|execution| {
+        (
+            execution.timestamp,
+            execution.evidence.first().map(|source| source.record_index),
+        )
+    });
+    executions
+}
+
+/// Statements waiting for a duration, keyed by session id, or by process ID
+/// when the prefix has no `%c`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProcessOrderPairing {
+    pending_by_process: HashMap<String, PendingStatement>,
+    /// Number of pending statements per timestamp, so the oldest is cheap to find
+    pending_timestamps: BTreeMap<DateTime<Utc>, usize>,
+}
+
+impl ProcessOrderPairing {
+    /// Pair `event` with the pending statement of its process, appending any
+    /// finished execution to `executions`
+    fn push(&mut self, event: &NormalizedEvent, executions: &mut Vec<QueryExecution>) {
         match &event.kind {
             EventKind::Statement(statement) => {
-                if let Some(previous) = pending_by_process.remove(&event.session.process_id) {
+                if let Some(previous) = self.take_pending(event.session.key()) {
                     executions.push(execution_from_pending(
                         previous,
                         None,
@@ -124,21 +159,18 @@ fn correlate_by_process_order(events: &[NormalizedEvent]) -> Vec<QueryExecution>
                         CorrelationConfidence::Exact,
                     ));
                 } else {
-                    pending_by_process.insert(
-                        event.session.process_id.clone(),
-                        PendingStatement {
-                            event_id: event.event_id.clone(),
-                            timestamp: event.timestamp,
-                            source: event.source.clone(),
-                            session: event.session.clone(),
-                            queryid: event.queryid.clone(),
-                            statement: statement.clone(),
-                        },
-                    );
+                    self.put_pending(PendingStatement {
+                        event_id: event.event_id.clone(),
+                        timestamp: event.timestamp,
+                        source: event.source.clone(),
+                        session: event.session.clone(),
+                        queryid: event.queryid.clone(),
+                        statement: statement.clone(),
+                    });
                 }
             }
             EventKind::Duration(duration) => {
-                if let Some(pending) = pending_by_process.remove(&event.session.process_id) {
+                if let Some(pending) = self.take_pending(event.session.key()) {
                     if event.timestamp >= pending.timestamp {
                         executions.push(execution_from_pending(
                             pending,
@@ -146,28 +178,146 @@ fn correlate_by_process_order(events: &[NormalizedEvent]) -> Vec<QueryExecution>
                             Some(event.source.clone()),
                             CorrelationConfidence::Exact,
                         ));
-                    } else {
-                        pending_by_process.insert(event.session.process_id.clone(), pending);
+                        return;
                     }
+                    self.put_pending(pending);
                 }
+                executions.push(execution_from_duration_event(event, duration.duration_ms));
             }
             _ => {}
         }
     }
 
-    let mut remaining: Vec<_> = pending_by_process.into_values().collect();
-    remaining.sort_by_key(|pending| pending.timestamp);
-    for pending in remaining {
-        executions.push(execution_from_pending(
-            pending,
-            None,
-            None,
-            CorrelationConfidence::StatementOnly,
-        ));
+    fn take_pending(&mut self, session_key: &str) -> Option<PendingStatement> {
+        let pending = self.pending_by_process.remove(session_key)?;
+        if let Some(count) = self.pending_timestamps.get_mut(&pending.timestamp) {
+            *count -= 1;
+            if *count == 0 {
+                self.pending_timestamps.remove(&pending.timestamp);
+            }
+        }
+        Some(pending)
     }
 
-    executions.sort_by_key(|execution| execution.timestamp);
-    executions
+    fn put_pending(&mut self, pending: PendingStatement) {
+        *self
+            .pending_timestamps
+            .entry(pending.timestamp)
+            .or_insert(0) += 1;
+        self.pending_by_process
+            .insert(pending.session.key().to_string(), pending);
+    }
+
+    /// Take over the pending statements of `other`. A statement of the same
+    /// process pending here is superseded, as by a new statement, and
+    /// appended to `executions`.
+    fn merge(&mut self, other: Self, executions: &mut Vec<QueryExecution>) {
+        for pending in other.pending_by_process.into_values() {
+            if let Some(previous) = self.take_pending(pending.session.key()) {
+                executions.push(execution_from_pending(
+                    previous,
+                    None,
+                    None,
+                    CorrelationConfidence::StatementOnly,
+                ));
+            }
+            self.put_pending(pending);
+        }
+    }
+
+    /// Timestamp of the oldest statement still waiting for a duration
+    fn oldest_pending(&self) -> Option<DateTime<Utc>> {
+        self.pending_timestamps.keys().next().copied()
+    }
+
+    /// Statements that never got a duration, oldest first
+    fn finish(self) -> Vec<QueryExecution> {
+        let mut remaining: Vec<_> = self.pending_by_process.into_values().collect();
+        remaining.sort_by_key(|pending| (pending.timestamp, pending.source.record_index));
+        remaining
+            .into_iter()
+            .map(|pending| {
+                execution_from_pending(pending, None, None, CorrelationConfidence::StatementOnly)
+            })
+            .collect()
+    }
+}
+
+/// Incremental form of [`ProcessOrderCorrelator`] for events that arrive one
+/// at a time.
+///
+/// Executions are held back until no pending statement or later event can sort
+/// before them, then released in timestamp order. For chronologically ordered
+/// input the released executions match [`Correlator::correlate`] on the whole
+/// event list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamingCorrelator {
+    pairing: ProcessOrderPairing,
+    /// Finished executions by timestamp, in arrival order
+    ready: BTreeMap<DateTime<Utc>, Vec<QueryExecution>>,
+    /// Latest event timestamp seen
+    watermark: Option<DateTime<Utc>>,
+}
+
+impl StreamingCorrelator {
+    /// Create a correlator with no pending statements
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one event and return the executions that can no longer be
+    /// preceded by a later one
+    pub fn push(&mut self, event: &NormalizedEvent) -> Vec<QueryExecution> {
+        let mut finished = Vec::new();
+        self.pairing.push(event, &mut finished);
+        self.hold(finished);
+        let watermark = self
+            .watermark
+            .map_or(event.timestamp, |w| w.max(event.timestamp));
+        self.watermark = Some(watermark);
+
+        let limit = self
+            .pairing
+            .oldest_pending()
+            .map_or(watermark, |oldest| oldest.min(watermark));
+        let mut released = Vec::new();
+        while let Some(entry) = self.ready.first_entry() {
+            if *entry.key() > limit {
+                break;
+            }
+            released.extend(entry.remove());
+        }
+        released
+    }
+
+    /// Take over the pending statements and held executions of `other`,
+    /// which saw events after this correlator's
+    pub fn merge(&mut self, other: Self) {
+        let mut superseded = Vec::new();
+        self.pairing.merge(other.pairing, &mut superseded);
+        self.hold(superseded);
+        for (timestamp, executions) in other.ready {
+            self.ready.entry(timestamp).or_default().extend(executions);
+        }
+        self.watermark = self.watermark.max(other.watermark);
+    }
+
+    /// Flush statements still waiting for a duration and return every
+    /// execution not released yet, in timestamp order
+    pub fn finish(mut self) -> Vec<QueryExecution> {
+        let remaining = std::mem::take(&mut self.pairing).finish();
+        self.hold(remaining);
+        self.ready.into_values().flatten().collect()
+    }
+
+    fn hold(&mut self, executions: Vec<QueryExecution>) {
+        for execution in executions {
+            self.ready
+                .entry(execution.timestamp)
+                .or_default()
+                .push(execution);
+        }
+    }
 }
 
 fn execution_from_pending(
@@ -194,6 +344,7 @@ fn execution_from_pending(
         duration_ms,
         evidence,
         confidence,
+        parameters: pending.statement.parameters,
     }
 }
 
@@ -218,6 +369,58 @@ fn execution_from_statement_event(
         duration_ms,
         evidence,
         confidence,
+        parameters: statement.parameters.clone(),
+    }
+}
+
+fn execution_from_duration_event(event: &NormalizedEvent, duration_ms: f64) -> QueryExecution {
+    let query_family = QueryFamilyIdentity::new(
+        STATEMENT_NOT_LOGGED.to_string(),
+        &event.session,
+        event.queryid.clone(),
+    );
+
+    QueryExecution {
+        execution_id: event.event_id.clone(),
+        timestamp: event.timestamp,
+        session: event.session.clone(),
+        statement: STATEMENT_NOT_LOGGED.to_string(),
+        queries: Vec::new(),
+        query_family,
+        duration_ms: Some(duration_ms),
+        evidence: vec![event.source.clone()],
+        confidence: CorrelationConfidence::DurationOnly,
+        parameters: None,
+    }
+}
+
+impl QueryExecution {
+    /// Each statement of a multi-statement execution as an execution of its
+    /// own, taking an equal share of the duration
+    pub fn split_batch(&self) -> Vec<QueryExecution> {
+        let share = self
+            .duration_ms
+            .map(|duration| duration / self.queries.len().max(1) as f64);
+        self.queries
+            .iter()
+            .enumerate()
+            .map(|(i, query)| QueryExecution {
+                execution_id: format!("{}.{}", self.execution_id, i + 1),
+                timestamp: self.timestamp,
+                session: self.session.clone(),
+                statement: query.sql.clone(),
+                queries: vec![query.clone()],
+                query_family: QueryFamilyIdentity::new(
+                    query.normalized_query.clone(),
+                    &self.session,
+                    self.query_family.queryid.clone(),
+                ),
+                duration_ms: share,
+                evidence: self.evidence.clone(),
+                confidence: self.confidence,
+                parameters: self.parameters.clone(),
+            })
+            .collect()
     }
 }
 
@@ -225,12 +428,7 @@ fn normalized_sql(statement: &StatementEvent) -> String {
     if statement.queries.is_empty() {
         statement.statement.clone()
     } else {
-        statement
-            .queries
-            .iter()
-            .map(|query| query.normalized_query.clone())
-            .collect::<Vec<_>>()
-            .join(";")
+        join_normalized_queries(&statement.queries)
     }
 }
 
@@ -248,7 +446,9 @@ mod tests {
             user: Some("postgres".to_string()),
             database: Some(database.to_string()),
             client_host: None,
+            client_port: None,
             application_name: Some("psql".to_string()),
+            session_id: None,
         }
     }
 
@@ -267,6 +467,8 @@ mod tests {
                 statement: sql.to_string(),
                 queries: Query::from_sql(sql).unwrap(),
                 duration_ms: None,
+                prepared_statement: None,
+                parameters: None,
             }),
         }
     }
@@ -327,13 +529,43 @@ mod tests {
 
         let executions = correlate_query_executions(&events);
 
-        assert_eq!(executions.len(), 1);
+        assert_eq!(executions.len(), 2);
         assert_eq!(executions[0].duration_ms, None);
         assert_eq!(
             executions[0].confidence,
             CorrelationConfidence::StatementOnly
         );
         assert_eq!(executions[0].evidence.len(), 1);
+        assert_eq!(
+            executions[1].confidence,
+            CorrelationConfidence::DurationOnly
+        );
+    }
+
+    #[test]
+    fn counts_duration_without_statement_as_unlogged_execution() {
+        let events = vec![
+            duration_event(0, "12345", 42.5),
+            statement_event(1, "12345", "SELECT * FROM users WHERE id = 1"),
+            duration_event(2, "12345", 12.0),
+        ];
+
+        let executions = correlate_query_executions(&events);
+
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].statement, STATEMENT_NOT_LOGGED);
+        assert_eq!(
+            executions[0].query_family.normalized_sql,
+            STATEMENT_NOT_LOGGED
+        );
+        assert!(executions[0].queries.is_empty());
+        assert_eq!(executions[0].duration_ms, Some(42.5));
+        assert_eq!(
+            executions[0].confidence,
+            CorrelationConfidence::DurationOnly
+        );
+        assert_eq!(executions[1].duration_ms, Some(12.0));
+        assert_eq!(executions[1].confidence, CorrelationConfidence::Exact);
     }
 
     #[test]
@@ -356,6 +588,40 @@ mod tests {
         assert_eq!(executions[1].confidence, CorrelationConfidence::Exact);
     }
 
+    #[test]
+    fn streaming_correlator_releases_executions_in_batch_order() {
+        let events = vec![
+            statement_event(0, "11111", "SELECT * FROM users WHERE id = 1"),
+            statement_event(1, "22222", "SELECT * FROM posts WHERE id = 2"),
+            duration_event(2, "22222", 5.0),
+            statement_event(3, "22222", "SELECT * FROM tags WHERE id = 3"),
+            duration_event(4, "11111", 40.0),
+            duration_event(5, "22222", 7.0),
+            statement_event(6, "33333", "SELECT * FROM users WHERE id = 4"),
+        ];
+
+        let mut correlator = StreamingCorrelator::new();
+        let mut released_before_finish = 0;
+        let mut streamed = Vec::new();
+        for event in &events {
+            let released = correlator.push(event);
+            released_before_finish += released.len();
+            streamed.extend(released);
+        }
+        streamed.extend(correlator.finish());
+
+        // The pid 22222 execution waits for the older pid 11111 statement
+        assert_eq!(released_before_finish, 3);
+        let batch = correlate_query_executions(&events);
+        let ids = |executions: &[QueryExecution]| {
+            executions
+                .iter()
+                .map(|execution| (execution.execution_id.clone(), execution.duration_ms))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&streamed), ids(&batch));
+    }
+
     #[test]
     fn query_family_identity_includes_normalized_sql_and_metadata() {
         let mut event = statement_event(0, "12345", "SELECT * FROM users WHERE id = 1");
@@ -3,7 +3,9 @@
 //! This layer sits above raw parser output so workflows and analytics do not
 //! depend directly on the legacy `LogEntry` structure.
 
-use crate::{LogEntry, LogLevel, Query};
+use crate::parsers::PARAMETERS_PREFIX;
+use crate::sql::join_normalized_queries;
+use crate::{copy::copy_context_table, ConnectionEventKind, LogEntry, LogLevel, Query};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +16,8 @@ pub enum EventSourceKind {
     AwsRds,
     Csvlog,
     Jsonlog,
+    Pgbouncer,
+    Syslog,
 }
 
 /// Stable pointer back to the raw source record that produced an event.
@@ -30,7 +34,20 @@ pub struct SessionIdentity {
     pub user: Option<String>,
     pub database: Option<String>,
     pub client_host: Option<String>,
+    #[serde(default)]
+    pub client_port: Option<u16>,
     pub application_name: Option<String>,
+    /// Session id of the `%c` prefix escape
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl SessionIdentity {
+    /// Session id when it was logged, else the backend PID, which a new
+    /// session may reuse
+    pub fn key(&self) -> &str {
+        self.session_id.as_deref().unwrap_or(&self.process_id)
+    }
 }
 
 /// Structured statement payload.
@@ -39,6 +56,13 @@ pub struct StatementEvent {
     pub statement: String,
     pub queries: Vec<Query>,
     pub duration_ms: Option<f64>,
+    /// Prepared statement name of an extended protocol `execute`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepared_statement: Option<String>,
+    /// Placeholder values of the `DETAIL:  parameters:` line, such as
+    /// `$1 = '42'`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<String>,
 }
 
 /// Structured duration payload.
@@ -52,6 +76,29 @@ pub struct DurationEvent {
 pub struct ErrorEvent {
     pub message: String,
     pub sqlstate: Option<String>,
+    /// `Error`, `Fatal`, or `Panic`
+    #[serde(default = "default_error_severity")]
+    pub severity: LogLevel,
+    /// Normalized statement that failed, from the error's `STATEMENT:` line
+    #[serde(default)]
+    pub statement: Option<String>,
+    /// Table named by a `COPY table, line N` context line, for errors raised
+    /// while copying rows
+    #[serde(default)]
+    pub copy_table: Option<String>,
+}
+
+fn default_error_severity() -> LogLevel {
+    LogLevel::Error
+}
+
+/// Normalized form of an error's failing statement, or its text when it does
+/// not parse
+fn failing_statement(entry: &LogEntry) -> Option<String> {
+    match &entry.queries {
+        Some(queries) if !queries.is_empty() => Some(join_normalized_queries(queries)),
+        _ => entry.statement.clone(),
+    }
 }
 
 /// Normalized event kinds for investigation workflows.
@@ -60,7 +107,13 @@ pub enum EventKind {
     Statement(StatementEvent),
     Duration(DurationEvent),
     Error(ErrorEvent),
-    Log { level: LogLevel, message: String },
+    Log {
+        level: LogLevel,
+        message: String,
+        /// Set for connection, disconnection, and reset lines
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connection: Option<ConnectionEventKind>,
+    },
 }
 
 /// Normalized PostgreSQL event.
@@ -90,7 +143,9 @@ impl NormalizedEvent {
             user: entry.user.clone(),
             database: entry.database.clone(),
             client_host: entry.client_host.clone(),
+            client_port: entry.client_port,
             application_name: entry.application_name.clone(),
+            session_id: entry.session_id.clone(),
         };
 
         let kind = if entry.is_query() {
@@ -102,6 +157,12 @@ impl NormalizedEvent {
                     .to_string(),
                 queries: entry.queries.clone().unwrap_or_default(),
                 duration_ms: entry.duration,
+                prepared_statement: entry.prepared_statement.clone(),
+                parameters: entry
+                    .detail
+                    .as_deref()
+                    .and_then(|detail| detail.strip_prefix(PARAMETERS_PREFIX))
+                    .map(str::to_string),
             })
         } else if entry.is_duration() {
             EventKind::Duration(DurationEvent {
@@ -110,12 +171,20 @@ impl NormalizedEvent {
         } else if entry.is_error() {
             EventKind::Error(ErrorEvent {
                 message: entry.message.clone(),
-                sqlstate: None,
+                sqlstate: entry.sqlstate.clone(),
+                severity: entry.message_type.clone(),
+                statement: failing_statement(entry),
+                copy_table: entry
+                    .context
+                    .as_deref()
+                    .and_then(copy_context_table)
+                    .map(str::to_string),
             })
         } else {
             EventKind::Log {
                 level: entry.message_type.clone(),
                 message: entry.message.clone(),
+                connection: entry.connection_event,
             }
         };
 
@@ -127,6 +196,8 @@ impl NormalizedEvent {
                     EventSourceKind::AwsRds => "aws-rds",
                     EventSourceKind::Csvlog => "csvlog",
                     EventSourceKind::Jsonlog => "jsonlog",
+                    EventSourceKind::Pgbouncer => "pgbouncer",
+                    EventSourceKind::Syslog => "syslog",
                 },
                 record_index
             ),
@@ -167,13 +238,7 @@ impl NormalizedEvent {
             return None;
         }
 
-        Some(
-            queries
-                .iter()
-                .map(|query| query.normalized_query.clone())
-                .collect::<Vec<_>>()
-                .join(";"),
-        )
+        Some(join_normalized_queries(queries))
     }
 
     pub fn message(&self) -> &str {
@@ -184,6 +249,14 @@ impl NormalizedEvent {
             EventKind::Log { message, .. } => message,
         }
     }
+
+    /// Connection step of a connection, disconnection, or reset line
+    pub fn connection_event(&self) -> Option<ConnectionEventKind> {
+        match &self.kind {
+            EventKind::Log { connection, .. } => *connection,
+            _ => None,
+        }
+    }
 }
 
 pub fn normalize_log_entries(
@@ -211,18 +284,20 @@ mod tests {
         duration: Option<f64>,
         queries: Option<Vec<Query>>,
     ) -> LogEntry {
-        LogEntry {
-            timestamp: Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap(),
-            process_id: "12345".to_string(),
-            user: Some("postgres".to_string()),
-            database: Some("testdb".to_string()),
-            client_host: Some("10.0.0.10".to_string()),
-            application_name: Some("psql".to_string()),
+        LogEntry::builder(
+            Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap(),
+            "12345",
             message_type,
-            message: message.to_string(),
-            queries,
-            duration,
-        }
+            message,
+        )
+        .with_user(Some("postgres"))
+        .with_database(Some("testdb"))
+        .with_client_host(Some("10.0.0.10"))
+        .with_application_name(Some("psql"))
+        .with_queries(queries)
+        .with_duration(duration)
+        .build()
+        .unwrap()
     }
 
     #[test]
@@ -312,7 +387,7 @@ mod tests {
         assert!(!event.is_error());
         assert_eq!(event.message(), "there is no transaction in progress");
         match event.kind {
-            EventKind::Log { level, message } => {
+            EventKind::Log { level, message, .. } => {
                 assert_eq!(level, LogLevel::Warning);
                 assert_eq!(message, "there is no transaction in progress");
             }
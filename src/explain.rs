@@ -0,0 +1,288 @@
+//! EXPLAIN-ready statements of the slowest queries
+//!
+//! The next step after finding a slow query is usually its plan. For each of
+//! the slowest normalized queries, [`explain_script`] writes the slowest kept
+//! example, with its literals, behind `EXPLAIN (ANALYZE, BUFFERS)`.
+//! `EXPLAIN ANALYZE` runs the statement, so statements that can change data,
+//! data-modifying CTEs and `MERGE` included, are wrapped in `BEGIN` and
+//! `ROLLBACK`. A query whose examples only ever had
+//! `$n` placeholders is written as a `PREPARE` and `EXECUTE` pair, with the
+//! values of the logged `parameters:` line when there was one.
+
+use crate::{classify, AnalysisResult, QueryExample, QueryType};
+use regex::Regex;
+use sqlparser::ast::{visit_statements, Statement};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::OnceLock;
+
+/// Number of the slowest queries written by default
+pub const DEFAULT_EXPLAIN_CANDIDATES: usize = 10;
+
+/// A slow query with the example to explain it with
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainCandidate {
+    /// Normalized query
+    pub query: String,
+    pub count: u64,
+    /// 95th percentile duration in milliseconds
+    pub p95_ms: f64,
+    pub example: QueryExample,
+    pub query_type: QueryType,
+}
+
+impl ExplainCandidate {
+    /// Whether `EXPLAIN ANALYZE` of the statement can change data
+    pub fn modifies_data(&self) -> bool {
+        changes_data(statement_text(&self.example.sql))
+    }
+
+    /// Statements that explain the example; `rank` names its prepared
+    /// statement
+    pub fn to_sql(&self, rank: usize) -> String {
+        let sql = statement_text(&self.example.sql);
+        let mut out = String::new();
+        let Some(placeholders) = max_placeholder(sql) else {
+            self.write_explain(&mut out, sql);
+            return out;
+        };
+
+        let name = format!("explain_candidate_{}", rank);
+        let values = self
+            .example
+            .parameters
+            .as_deref()
+            .map(parse_parameters)
+            .unwrap_or_default();
+        if values.is_empty() {
+            out.push_str("-- Parameters were not logged; replace the NULLs with real values\n");
+        }
+        let arguments: Vec<&str> = (1..=placeholders)
+            .map(|n| values.get(&n).map_or("NULL", String::as_str))
+            .collect();
+        out.push_str(&format!("PREPARE {} AS {};\n", name, sql));
+        self.write_explain(
+            &mut out,
+            &format!("EXECUTE {}({})", name, arguments.join(", ")),
+        );
+        out.push_str(&format!("DEALLOCATE {};\n", name));
+        out
+    }
+
+    fn write_explain(&self, out: &mut String, statement: &str) {
+        if self.modifies_data() {
+            out.push_str("BEGIN;\n");
+        }
+        out.push_str(&format!("EXPLAIN (ANALYZE, BUFFERS) {};\n", statement));
+        if self.modifies_data() {
+            out.push_str("ROLLBACK;\n");
+        }
+    }
+}
+
+/// Up to `limit` of the slowest queries of `analysis` that have an example
+/// and can be explained, slowest first
+pub fn explain_candidates(analysis: &AnalysisResult, limit: usize) -> Vec<ExplainCandidate> {
+    let mut seen = HashSet::new();
+    analysis
+        .slowest_queries
+        .iter()
+        .filter(|(query, _)| seen.insert(query.as_str()))
+        .filter_map(|(query, _)| {
+            let stats = analysis.query_stats.get(query)?;
+            let example = preferred_example(&stats.examples)?;
+            let query_type = classify(&example.sql);
+            let explainable = matches!(
+                query_type,
+                QueryType::Select | QueryType::Insert | QueryType::Update | QueryType::Delete
+            ) || is_merge(&example.sql);
+            explainable.then(|| ExplainCandidate {
+                query: query.clone(),
+                count: stats.count,
+                p95_ms: stats.p95_duration,
+                example: example.clone(),
+                query_type,
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+/// SQL script with the statements of the `limit` slowest queries
+pub fn explain_script(analysis: &AnalysisResult, limit: usize) -> String {
+    let candidates = explain_candidates(analysis, limit);
+    let mut out = String::new();
+    out.push_str("-- EXPLAIN candidates: the slowest queries of the log, slowest first\n");
+    out.push_str("-- EXPLAIN ANALYZE runs each statement; data-modifying ones are rolled back\n");
+    if candidates.is_empty() {
+        out.push_str(
+            "-- No slow query had an example to explain; examples need --max-examples above 0\n",
+        );
+    }
+    for (index, candidate) in candidates.iter().enumerate() {
+        let rank = index + 1;
+        let example = &candidate.example;
+        out.push_str(&format!("\n-- {}. {}\n", rank, one_line(&candidate.query)));
+        out.push_str(&format!(
+            "-- Executions: {}, p95: {:.3} ms; slowest example: {:.3} ms at {}",
+            candidate.count,
+            candidate.p95_ms,
+            example.duration,
+            example.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+        ));
+        match (&example.user, &example.database) {
+            (Some(user), Some(database)) => {
+                out.push_str(&format!(" ({}@{})", one_line(user), one_line(database)))
+            }
+            (Some(name), None) | (None, Some(name)) => {
+                out.push_str(&format!(" ({})", one_line(name)))
+            }
+            (None, None) => {}
+        }
+        out.push('\n');
+        out.push_str(&candidate.to_sql(rank));
+    }
+    out
+}
+
+/// Values of a `parameters:` list such as `$1 = '42', $2 = NULL`, by
+/// placeholder number; values keep their quotes
+pub fn parse_parameters(list: &str) -> BTreeMap<usize, String> {
+    let mut values = BTreeMap::new();
+    let mut rest = list.trim();
+    while let Some(after_dollar) = rest.strip_prefix('$') {
+        let digits = after_dollar
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after_dollar.len());
+        let Ok(number) = after_dollar[..digits].parse() else {
+            break;
+        };
+        let Some(value) = after_dollar[digits..].trim_start().strip_prefix('=') else {
+            break;
+        };
+        let value = value.trim_start();
+        let end = if value.starts_with('\'') {
+            quoted_literal_end(value)
+        } else {
+            value.find(", $").unwrap_or(value.len())
+        };
+        values.insert(number, value[..end].trim().to_string());
+        rest = value[end..].trim_start_matches([',', ' ']);
+    }
+    values
+}
+
+/// Slowest example with literals, or else the slowest with logged parameters
+fn preferred_example(examples: &[QueryExample]) -> Option<&QueryExample> {
+    examples
+        .iter()
+        .find(|example| max_placeholder(&example.sql).is_none())
+        .or_else(|| examples.iter().find(|example| example.parameters.is_some()))
+        .or_else(|| examples.first())
+}
+
+/// Whether running `sql` can change data: it has an `INSERT`, `UPDATE`,
+/// `DELETE`, or `MERGE` anywhere, CTEs included. SQL the parser rejects, such
+/// as a `DELETE` in a CTE, is assumed to change data.
+fn changes_data(sql: &str) -> bool {
+    let Ok(statements) = Parser::parse_sql(&PostgreSqlDialect {}, sql) else {
+        return true;
+    };
+    let modifying = visit_statements(&statements, |statement| match statement {
+        Statement::Insert(_)
+        | Statement::Update { .. }
+        | Statement::Delete(_)
+        | Statement::Merge { .. } => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    });
+    modifying.is_break()
+}
+
+/// Whether `sql` is a `MERGE`, which has no query type of its own
+fn is_merge(sql: &str) -> bool {
+    sql.split_whitespace()
+        .next()
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("MERGE"))
+}
+
+/// Highest `$n` placeholder of `sql`
+fn max_placeholder(sql: &str) -> Option<usize> {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = REGEX.get_or_init(|| Regex::new(r"\$([0-9]+)\b").unwrap());
+    regex
+        .captures_iter(sql)
+        .filter_map(|captures| captures[1].parse().ok())
+        .max()
+}
+
+/// `sql` without surrounding whitespace and trailing semicolons
+fn statement_text(sql: &str) -> &str {
+    sql.trim().trim_end_matches(';').trim_end()
+}
+
+/// Length of the `'...'` literal at the start of `value`, with `''` escapes
+fn quoted_literal_end(value: &str) -> usize {
+    let bytes = value.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i] == b'\'' {
+            if bytes.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    value.len()
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_null_and_escaped_values() {
+        let values = parse_parameters("$1 = '42', $2 = NULL, $3 = 'it''s, $4 = x'");
+        assert_eq!(values.get(&1).map(String::as_str), Some("'42'"));
+        assert_eq!(values.get(&2).map(String::as_str), Some("NULL"));
+        assert_eq!(values.get(&3).map(String::as_str), Some("'it''s, $4 = x'"));
+        assert_eq!(values.len(), 3);
+        assert!(parse_parameters("").is_empty());
+    }
+
+    #[test]
+    fn finds_the_highest_placeholder() {
+        assert_eq!(max_placeholder("SELECT $1, $12, $3"), Some(12));
+        assert_eq!(max_placeholder("SELECT 1"), None);
+    }
+
+    #[test]
+    fn finds_data_changes_in_ctes_and_merge() {
+        assert!(!changes_data("SELECT * FROM t WHERE id = 1"));
+        assert!(!changes_data("WITH r AS (SELECT 1) SELECT * FROM r"));
+        assert!(changes_data("DELETE FROM t WHERE id = $1"));
+        assert!(changes_data(
+            "WITH d AS (DELETE FROM t WHERE id < 5 RETURNING *) SELECT * FROM d"
+        ));
+        assert!(changes_data(
+            "WITH u AS (UPDATE t SET a = 1 RETURNING *) SELECT * FROM u"
+        ));
+        assert!(changes_data(
+            "WITH i AS (INSERT INTO t VALUES (1) RETURNING *) SELECT * FROM i"
+        ));
+        assert!(changes_data(
+            "MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN UPDATE SET a = s.a"
+        ));
+        assert!(is_merge(
+            "merge INTO t USING s ON t.id = s.id WHEN MATCHED THEN DELETE"
+        ));
+        assert!(!is_merge("SELECT merge FROM t"));
+    }
+}
@@ -186,7 +186,9 @@ impl QueryFamilyAccumulator {
 
         match execution.confidence {
             CorrelationConfidence::Exact => self.correlated_execution_count += 1,
-            CorrelationConfidence::StatementOnly => self.uncorrelated_execution_count += 1,
+            CorrelationConfidence::StatementOnly | CorrelationConfidence::DurationOnly => {
+                self.uncorrelated_execution_count += 1
+            }
         }
 
         for source in &execution.evidence {
@@ -324,7 +326,9 @@ impl DiffAccumulator {
 
         match execution.confidence {
             CorrelationConfidence::Exact => self.correlated_execution_count += 1,
-            CorrelationConfidence::StatementOnly => self.uncorrelated_execution_count += 1,
+            CorrelationConfidence::StatementOnly | CorrelationConfidence::DurationOnly => {
+                self.uncorrelated_execution_count += 1
+            }
         }
 
         for source in &execution.evidence {
@@ -606,7 +610,9 @@ mod tests {
             user: Some("app".to_string()),
             database: Some("appdb".to_string()),
             client_host: None,
+            client_port: None,
             application_name: Some("api".to_string()),
+            session_id: None,
         };
         let queries = Query::from_sql(sql).unwrap();
         let normalized_sql = queries[0].normalized_query.clone();
@@ -629,6 +635,7 @@ mod tests {
             } else {
                 CorrelationConfidence::StatementOnly
             },
+            parameters: None,
         }
     }
 
@@ -766,7 +773,9 @@ mod tests {
             user: Some("app'user".to_string()),
             database: Some("app_db".to_string()),
             client_host: None,
+            client_port: None,
             application_name: Some("api%worker".to_string()),
+            session_id: None,
         };
         let identity = QueryFamilyIdentity::new(
             "select * from orders where note = 'abc_%'".to_string(),
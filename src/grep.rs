@@ -0,0 +1,217 @@
+//! Grep-like search of parsed entries
+//!
+//! A pattern is matched against the message of each entry and against the
+//! normalized form of its SQL, so `WHERE id = \?` finds every execution of a
+//! query shape whatever literals it ran with, and a literal finds the
+//! executions that used it.
+//!
+//! Statements logged without a duration take the duration of the next
+//! `duration:` line of their session, as in [`session_timeline`], so matches
+//! in logs that write the two separately still show how long they ran.
+//!
+//! [`session_timeline`]: crate::session_timeline
+
+use crate::{LogEntry, PgLogstatsError, Result};
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Characters of SQL kept in each match
+pub const GREP_SQL_LENGTH: usize = 200;
+
+/// Regular expression matched against entries
+#[derive(Debug, Clone)]
+pub struct EntryMatcher {
+    regex: Regex,
+}
+
+impl EntryMatcher {
+    /// Match `pattern`, ignoring case unless `case_sensitive`
+    pub fn new(pattern: &str, case_sensitive: bool) -> Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|e| PgLogstatsError::Configuration {
+                message: format!("Invalid --grep pattern '{}': {}", pattern, e),
+                field: Some("grep".to_string()),
+            })?;
+        Ok(Self { regex })
+    }
+
+    /// Whether the message of `entry` or one of its normalized queries
+    /// matches
+    pub fn is_match(&self, entry: &LogEntry) -> bool {
+        self.regex.is_match(&entry.message)
+            || entry
+                .queries
+                .iter()
+                .flatten()
+                .any(|query| self.regex.is_match(&query.normalized_query))
+    }
+}
+
+/// Entries of `entries` that `matcher` matches, in order, with the duration of
+/// each matching statement logged without one taken from the next `duration:`
+/// entry of its session
+pub fn grep_matches(entries: &[LogEntry], matcher: &EntryMatcher) -> Vec<GrepMatch> {
+    let mut matches: Vec<GrepMatch> = Vec::new();
+    // Index into `matches` of the statement each session still waits on
+    let mut pending: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        if entry.is_query() {
+            pending.remove(entry.session_key());
+        } else if entry.is_duration() {
+            if let Some(index) = pending.remove(entry.session_key()) {
+                matches[index].duration_ms = entry.duration;
+            }
+        }
+        if !matcher.is_match(entry) {
+            continue;
+        }
+        if entry.is_query() && entry.duration.is_none() {
+            pending.insert(entry.session_key(), matches.len());
+        }
+        matches.push(GrepMatch::from_entry(entry));
+    }
+    matches
+}
+
+/// An entry found by [`EntryMatcher`], as written to the output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GrepMatch {
+    pub timestamp: DateTime<Utc>,
+    /// Read order of the entry within its file; see [`LogEntry::seq`]
+    pub seq: u64,
+    pub process_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+    /// Statement of a statement entry, or else the message, on one line and
+    /// cut to [`GREP_SQL_LENGTH`] characters
+    pub sql: String,
+}
+
+impl GrepMatch {
+    pub fn from_entry(entry: &LogEntry) -> Self {
+        let text = entry
+            .message
+            .strip_prefix("statement: ")
+            .unwrap_or(&entry.message);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        Self {
+            timestamp: entry.timestamp,
+            seq: entry.seq,
+            process_id: entry.process_id.clone(),
+            user: entry.user.clone(),
+            database: entry.database.clone(),
+            duration_ms: entry.duration,
+            sql: text.chars().take(GREP_SQL_LENGTH).collect(),
+        }
+    }
+
+    /// Compact line such as
+    /// `2024-01-15 10:00:00.000 [4000] app@appdb 12.500 ms SELECT ...`
+    pub fn to_line(&self) -> String {
+        let session = match (&self.user, &self.database) {
+            (Some(user), Some(database)) => format!("{}@{}", user, database),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => "-".to_string(),
+        };
+        let duration = match self.duration_ms {
+            Some(duration_ms) => format!("{:.3} ms", duration_ms),
+            None => "-".to_string(),
+        };
+        format!(
+            "{} [{}] {} {} {}",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.process_id,
+            session,
+            duration,
+            self.sql
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogLevel, Query};
+    use chrono::TimeZone;
+
+    #[test]
+    fn cuts_sql_to_one_short_line() {
+        let entry = LogEntry::builder(
+            Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap(),
+            "4000",
+            LogLevel::Statement,
+            format!("statement: SELECT 1,\n       {}", "x".repeat(300)),
+        )
+        .with_user(Some("app"))
+        .with_database(Some("appdb"))
+        .with_duration(Some(12.5))
+        .with_queries(Query::from_sql("SELECT 1").ok())
+        .build()
+        .unwrap();
+
+        let found = GrepMatch::from_entry(&entry);
+        assert_eq!(found.sql.chars().count(), GREP_SQL_LENGTH);
+        assert!(found
+            .to_line()
+            .starts_with("2024-01-15 10:00:00.000 [4000] app@appdb 12.500 ms SELECT 1, xxx"));
+    }
+
+    #[test]
+    fn pairs_statements_with_the_next_duration_of_their_process() {
+        let at = |second| Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, second).unwrap();
+        let statement = |second, pid: &str, sql: &str| {
+            LogEntry::builder(
+                at(second),
+                pid,
+                LogLevel::Statement,
+                format!("statement: {sql}"),
+            )
+            .with_queries(Query::from_sql(sql).ok())
+            .build()
+            .unwrap()
+        };
+        let duration = |second, pid: &str, duration_ms: f64| {
+            LogEntry::builder(
+                at(second),
+                pid,
+                LogLevel::Duration,
+                format!("duration: {duration_ms:.3} ms"),
+            )
+            .with_duration(Some(duration_ms))
+            .build()
+            .unwrap()
+        };
+        let entries = [
+            statement(0, "7", "SELECT * FROM orders"),
+            statement(0, "8", "SELECT * FROM orders"),
+            duration(1, "8", 4.0),
+            duration(1, "7", 2.5),
+            statement(2, "7", "SELECT * FROM orders"),
+            statement(3, "7", "SELECT 1"),
+            duration(3, "7", 9.0),
+        ];
+        let matcher = EntryMatcher::new("orders", false).unwrap();
+
+        let found: Vec<_> = grep_matches(&entries, &matcher)
+            .into_iter()
+            .map(|found| (found.process_id, found.duration_ms))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("7".to_string(), Some(2.5)),
+                ("8".to_string(), Some(4.0)),
+                // The next statement of the process came before any duration
+                ("7".to_string(), None),
+            ]
+        );
+    }
+}
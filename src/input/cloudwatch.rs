@@ -1,4 +1,4 @@
-use crate::{LogEntry, PgLogstatsError, Result, TextLogParser};
+use crate::{LogEntry, ParseReport, PgLogstatsError, Result, TextLogParser};
 use chrono::Utc;
 use std::fs;
 use std::str::FromStr;
@@ -91,8 +91,21 @@ pub fn validate_cloudwatch_input_args(input: &CloudWatchInput) -> Result<()> {
 
 pub fn process_cloudwatch_input(
     input: &CloudWatchInput,
-    parser: &TextLogParser,
+    parser: &mut TextLogParser,
 ) -> Result<Vec<LogEntry>> {
+    process_cloudwatch_input_with_report(input, parser).map(|(entries, _)| entries)
+}
+
+pub fn process_cloudwatch_input_with_report(
+    input: &CloudWatchInput,
+    parser: &mut TextLogParser,
+) -> Result<(Vec<LogEntry>, ParseReport)> {
+    let lines = read_cloudwatch_lines(input)?;
+    Ok(parser.parse_lines_with_report(&lines))
+}
+
+/// Fetch CloudWatch log events and split them into log lines.
+pub fn read_cloudwatch_lines(input: &CloudWatchInput) -> Result<Vec<String>> {
     let log_group = input
         .log_group_name()
         .expect("validated CloudWatch input should have a log group");
@@ -107,7 +120,7 @@ pub fn process_cloudwatch_input(
         lines.truncate(sample_size);
     }
 
-    parser.parse_lines(&lines)
+    Ok(lines)
 }
 
 #[derive(Debug, serde::Deserialize)]
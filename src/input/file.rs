@@ -1,97 +1,516 @@
-use crate::{LogEntry, PgLogstatsError, Result, TextLogParser};
+use crate::{
+    LineContext, LogEntry, LogParser, ParseReport, PgLogstatsError, Result, TextLogParser,
+};
 use log::{info, warn};
+use std::borrow::Cow;
+use std::ffi::OsStr;
 use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Character encoding used to decode log file bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    /// UTF-8; invalid bytes become U+FFFD replacement characters
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where every byte maps to one character
+    Latin1,
+}
+
+impl Charset {
+    /// Decode one line, reporting whether any bytes had to be replaced
+    pub fn decode<'a>(self, bytes: &'a [u8]) -> (Cow<'a, str>, bool) {
+        match self {
+            Self::Utf8 => {
+                let text = String::from_utf8_lossy(bytes);
+                let replaced = matches!(text, Cow::Owned(_));
+                (text, replaced)
+            }
+            Self::Latin1 => (
+                Cow::Owned(bytes.iter().map(|&byte| char::from(byte)).collect()),
+                false,
+            ),
+        }
+    }
+}
+
+/// Lines read from a log file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodedLines {
+    pub lines: Vec<String>,
+    /// Lines that contained bytes invalid in the charset
+    pub replaced_lines: usize,
+    /// The file did not end with a newline, so its last line may still be
+    /// being written
+    pub unterminated: bool,
+}
 
 #[derive(Debug, Clone)]
 pub struct LocalLogInput {
-    pub log_dir: Option<PathBuf>,
+    /// Directories to read log files from
+    pub log_dirs: Vec<PathBuf>,
     pub sample_size: Option<usize>,
+    /// File listing log paths or glob patterns, one per line, or `-` for stdin
     pub logfile_list: Option<String>,
-    pub log_files: Vec<String>,
+    /// Log files, and directories read like `log_dirs`
+    pub log_files: Vec<PathBuf>,
+    /// Names of the files in the directories to read; empty uses
+    /// [`is_default_log_file`]
+    pub file_patterns: Vec<FilePattern>,
+    /// Also look in the subdirectories of the directories
+    pub recursive: bool,
+    /// Fail instead of warning when a `logfile_list` entry matches no file
+    pub strict_filelist: bool,
+    /// Fail instead of warning when a directory cannot be read
+    pub strict_inputs: bool,
 }
 
-pub fn validate_file_input_args(input: &LocalLogInput) -> Result<()> {
-    if let Some(log_dir) = &input.log_dir {
-        if !log_dir.exists() {
-            return Err(PgLogstatsError::Configuration {
-                message: format!("Log directory does not exist: {}", log_dir.display()),
-                field: Some("log_dir".to_string()),
-            });
-        }
+/// Shell-style pattern matched against file names, where `*` matches any
+/// run of characters and `?` one character.
+///
+/// Matching compares the bytes of the name, ignoring ASCII case, so names
+/// that are not valid UTF-8 still match, and `*.LOG` finds `server.log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePattern(String);
+
+impl FilePattern {
+    /// Whether `file_name` matches the whole pattern
+    pub fn matches(&self, file_name: &OsStr) -> bool {
+        wildcard_match(self.0.as_bytes(), file_name.as_encoded_bytes())
+    }
 
-        if !log_dir.is_dir() {
+    /// The pattern as written
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for FilePattern {
+    type Err = PgLogstatsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() || s.contains(['/', '\\']) {
             return Err(PgLogstatsError::Configuration {
                 message: format!(
-                    "Log directory path is not a directory: {}",
-                    log_dir.display()
+                    "Invalid file pattern '{}': expected a file name pattern such as '*.csv'",
+                    s
                 ),
-                field: Some("log_dir".to_string()),
+                field: Some("file_pattern".to_string()),
             });
         }
+        Ok(Self(s.to_string()))
+    }
+}
+
+/// Match `name` against `pattern`, backtracking to the last `*` on a mismatch
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                last_star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c.eq_ignore_ascii_case(&name[n]) => {
+                p += 1;
+                n += 1;
+            }
+            _ => match last_star {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    last_star = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Whether a file in a log directory looks like a PostgreSQL log when no
+/// `--file-pattern` is given: a `.log` or `.txt` extension, or no extension
+/// and `postgres` or `pg` in the name
+pub fn is_default_log_file(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => {
+            let extension = extension.as_encoded_bytes();
+            extension.eq_ignore_ascii_case(b"log") || extension.eq_ignore_ascii_case(b"txt")
+        }
+        None => path.file_name().is_some_and(|name| {
+            let name = name.as_encoded_bytes().to_ascii_lowercase();
+            [&b"postgres"[..], b"pg"]
+                .iter()
+                .any(|needle| name.windows(needle.len()).any(|window| window == *needle))
+        }),
+    }
+}
+
+/// Check the `log_dirs` of `input`.
+///
+/// A directory that cannot be read is only an error with `strict_inputs`, or
+/// when nothing else is left to read; otherwise discovery warns and skips it.
+pub fn validate_file_input_args(input: &LocalLogInput) -> Result<()> {
+    let checks: Vec<_> = input
+        .log_dirs
+        .iter()
+        .map(|dir| check_log_dir(dir))
+        .collect();
+    let nothing_else = input.log_files.is_empty()
+        && input.logfile_list.is_none()
+        && checks.iter().all(Result::is_err);
+    if input.strict_inputs || nothing_else {
+        checks.into_iter().collect::<Result<()>>()?;
+    }
+
+    Ok(())
+}
+
+fn check_log_dir(log_dir: &Path) -> Result<()> {
+    if !log_dir.exists() {
+        return Err(PgLogstatsError::Configuration {
+            message: format!("Log directory does not exist: {}", log_dir.display()),
+            field: Some("log_dir".to_string()),
+        });
+    }
 
-        fs::read_dir(log_dir).map_err(|err| PgLogstatsError::Configuration {
-            message: format!("Cannot read log directory {}: {}", log_dir.display(), err),
+    if !log_dir.is_dir() {
+        return Err(PgLogstatsError::Configuration {
+            message: format!(
+                "Log directory path is not a directory: {}",
+                log_dir.display()
+            ),
             field: Some("log_dir".to_string()),
-        })?;
+        });
     }
 
+    fs::read_dir(log_dir).map_err(|err| PgLogstatsError::Configuration {
+        message: format!("Cannot read log directory {}: {}", log_dir.display(), err),
+        field: Some("log_dir".to_string()),
+    })?;
+
     Ok(())
 }
 
-pub fn discover_log_files(input: &LocalLogInput) -> Result<Vec<PathBuf>> {
-    let mut log_files = Vec::new();
+/// Log files found for a [`LocalLogInput`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveredLogFiles {
+    /// Non-empty files to read, sorted by path
+    pub files: Vec<PathBuf>,
+    /// Paths and glob patterns of the `--logfile-list` that matched no file
+    pub missing: Vec<String>,
+    /// Why each directory that could not be read was skipped
+    pub unreadable_dirs: Vec<String>,
+    /// Directories among the `log_files`, read like `log_dirs`
+    pub dirs_from_files: Vec<PathBuf>,
+    /// Inputs that gave no log file, and files left out, with the reason
+    pub rejected: Vec<RejectedInput>,
+}
+
+/// Why an input gave no log file to read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    NotFound,
+    /// An empty file, or a directory without files
+    Empty,
+    /// The path could not be read; the error
+    Unreadable(String),
+    /// A directory whose files have none of the `expected` names
+    WrongExtension {
+        files: usize,
+        expected: String,
+    },
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "not found"),
+            Self::Empty => write!(f, "empty"),
+            Self::Unreadable(err) => write!(f, "unreadable: {}", err),
+            Self::WrongExtension { files, expected } => write!(
+                f,
+                "wrong extension: none of its {} files has {}",
+                files, expected
+            ),
+        }
+    }
+}
+
+/// An input path or `--logfile-list` entry, or a file found through one,
+/// that gave no log file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedInput {
+    pub input: String,
+    pub reason: RejectReason,
+}
 
-    if let Some(log_dir) = &input.log_dir {
-        discover_files_in_directory(log_dir, &mut log_files)?;
+impl RejectedInput {
+    fn new(input: impl std::fmt::Display, reason: RejectReason) -> Self {
+        Self {
+            input: input.to_string(),
+            reason,
+        }
     }
+}
 
-    for file_pattern in &input.log_files {
-        if let Ok(path) = PathBuf::from(file_pattern).canonicalize() {
-            if path.is_file() {
-                log_files.push(path);
+impl std::fmt::Display for RejectedInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.input, self.reason)
+    }
+}
+
+/// Files named by a [`LocalLogInput`], logging a warning for each
+/// `--logfile-list` entry that matches no file and each directory that
+/// cannot be read.
+///
+/// With `strict_filelist` or `strict_inputs`, those are a configuration error
+/// instead. A directory among the `log_files` is read like the `log_dirs`.
+/// Files named more than once, such as through a directory and by path, are
+/// read once; the files are sorted by canonical path. Each input that gave no
+/// file, and each empty or unreadable file, is listed in `rejected`.
+pub fn discover_local_log_files(input: &LocalLogInput) -> Result<DiscoveredLogFiles> {
+    let mut log_files = Vec::new();
+    let mut missing = Vec::new();
+    let mut unreadable_dirs = Vec::new();
+    let mut rejected = Vec::new();
+
+    let dirs_from_files: Vec<PathBuf> = input
+        .log_files
+        .iter()
+        .filter(|path| path.is_dir())
+        .cloned()
+        .collect();
+    for dir in &dirs_from_files {
+        info!("Reading directory {} as --log-dir", dir.display());
+    }
+    for dir in input.log_dirs.iter().chain(&dirs_from_files) {
+        match discover_files_in_directory(dir, &input.file_patterns, input.recursive) {
+            Ok(files) if files.is_empty() => {
+                rejected.push(RejectedInput::new(
+                    dir.display(),
+                    empty_directory_reason(dir, &input.file_patterns, input.recursive),
+                ));
             }
-        } else {
-            let path = Path::new(file_pattern);
-            if path.exists() && path.is_file() {
-                log_files.push(path.to_path_buf());
+            Ok(files) => log_files.extend(files),
+            Err(err) => {
+                let message = format!("Cannot read log directory {}: {}", dir.display(), err);
+                if input.strict_inputs {
+                    return Err(PgLogstatsError::Configuration {
+                        message,
+                        field: Some("log_dir".to_string()),
+                    });
+                }
+                warn!("{}", message);
+                unreadable_dirs.push(message);
+                let reason = if dir.exists() {
+                    RejectReason::Unreadable(err.to_string())
+                } else {
+                    RejectReason::NotFound
+                };
+                rejected.push(RejectedInput::new(dir.display(), reason));
             }
         }
     }
 
+    for path in &input.log_files {
+        if path.is_file() {
+            log_files.push(path.clone());
+        } else if !path.exists() {
+            rejected.push(RejectedInput::new(path.display(), RejectReason::NotFound));
+        } else if !path.is_dir() {
+            rejected.push(RejectedInput::new(
+                path.display(),
+                RejectReason::Unreadable("not a regular file".to_string()),
+            ));
+        }
+    }
+
     if let Some(logfile_list) = &input.logfile_list {
-        let list_content = fs::read_to_string(logfile_list).map_err(PgLogstatsError::Io)?;
-
-        for line in list_content.lines() {
-            let line = line.trim();
-            if !line.is_empty() && !line.starts_with('#') {
-                let path = Path::new(line);
-                if path.exists() && path.is_file() {
-                    log_files.push(path.to_path_buf());
-                }
+        for entry in logfile_list_entries(&read_logfile_list(logfile_list)?) {
+            let found = expand_list_entry(entry);
+            if found.is_empty() {
+                warn!("Listed log file not found: {}", entry);
+                missing.push(entry.to_string());
+                rejected.push(RejectedInput::new(entry, RejectReason::NotFound));
             }
+            log_files.extend(found);
         }
     }
+    if input.strict_filelist && !missing.is_empty() {
+        return Err(PgLogstatsError::Configuration {
+            message: format!(
+                "{} listed log files were not found: {}",
+                missing.len(),
+                missing.join(", ")
+            ),
+            field: Some("logfile_list".to_string()),
+        });
+    }
 
+    for path in &mut log_files {
+        if let Ok(canonical) = path.canonicalize() {
+            *path = canonical;
+        }
+    }
     log_files.sort();
     log_files.dedup();
 
-    log_files.retain(|path| match fs::metadata(path) {
-        Ok(metadata) => {
-            if metadata.len() == 0 {
-                warn!("Skipping empty log file: {}", path.display());
+    log_files.retain(|path| {
+        let check = fs::metadata(path).and_then(|metadata| {
+            fs::File::open(path)?;
+            Ok(metadata)
+        });
+        match check {
+            Ok(metadata) => {
+                if metadata.len() == 0 {
+                    warn!("Skipping empty log file: {}", path.display());
+                    rejected.push(RejectedInput::new(path.display(), RejectReason::Empty));
+                    false
+                } else {
+                    true
+                }
+            }
+            Err(err) => {
+                warn!("Cannot read {}: {}", path.display(), err);
+                rejected.push(RejectedInput::new(
+                    path.display(),
+                    RejectReason::Unreadable(err.to_string()),
+                ));
                 false
-            } else {
-                true
             }
         }
-        Err(err) => {
-            warn!("Cannot read metadata for {}: {}", path.display(), err);
-            false
-        }
     });
 
-    Ok(log_files)
+    Ok(DiscoveredLogFiles {
+        files: log_files,
+        missing,
+        unreadable_dirs,
+        dirs_from_files,
+        rejected,
+    })
+}
+
+/// Why the readable `dir` gave no log file: it has no files, or none with a
+/// name `patterns` or [`is_default_log_file`] accept
+fn empty_directory_reason(dir: &Path, patterns: &[FilePattern], recursive: bool) -> RejectReason {
+    let any_name = [FilePattern("*".to_string())];
+    let files =
+        discover_files_in_directory(dir, &any_name, recursive).map_or(0, |files| files.len());
+    if files == 0 {
+        return RejectReason::Empty;
+    }
+    let expected = if patterns.is_empty() {
+        "a .log or .txt extension, or postgres or pg in an extensionless name".to_string()
+    } else {
+        let patterns: Vec<_> = patterns.iter().map(FilePattern::as_str).collect();
+        format!("a name matching --file-pattern {}", patterns.join(", "))
+    };
+    RejectReason::WrongExtension { files, expected }
+}
+
+/// Files named by a [`LocalLogInput`]; see [`discover_local_log_files`]
+pub fn discover_log_files(input: &LocalLogInput) -> Result<Vec<PathBuf>> {
+    discover_local_log_files(input).map(|discovered| discovered.files)
+}
+
+/// Contents of a `--logfile-list` file, or of stdin for `-`.
+///
+/// Stdin can only be read once, so its contents are kept for later calls.
+fn read_logfile_list(logfile_list: &str) -> Result<String> {
+    static STDIN_LIST: OnceLock<String> = OnceLock::new();
+    if logfile_list != "-" {
+        return fs::read_to_string(logfile_list).map_err(PgLogstatsError::Io);
+    }
+    if let Some(list) = STDIN_LIST.get() {
+        return Ok(list.clone());
+    }
+    let mut list = String::new();
+    io::stdin().read_to_string(&mut list)?;
+    Ok(STDIN_LIST.get_or_init(|| list).clone())
+}
+
+/// Paths and patterns of a file list, one per line, without blank lines and
+/// `#` comments, which may also follow an entry
+pub fn logfile_list_entries(list: &str) -> Vec<&str> {
+    list.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Files a file-list entry names: the entry itself, or the files matching it
+/// when a component holds `*` or `?`; a leading `~` is the home directory
+pub fn expand_list_entry(entry: &str) -> Vec<PathBuf> {
+    let path = expand_home(entry);
+    if !path
+        .components()
+        .any(|component| has_wildcard(component.as_os_str()))
+    {
+        return if path.is_file() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let mut candidates = vec![PathBuf::new()];
+    for component in path.components() {
+        let name = component.as_os_str();
+        let pattern = has_wildcard(name).then(|| {
+            name.to_str()
+                .and_then(|name| name.parse::<FilePattern>().ok())
+        });
+        candidates = match pattern {
+            None => candidates
+                .into_iter()
+                .map(|candidate| candidate.join(name))
+                .collect(),
+            Some(None) => return Vec::new(),
+            Some(Some(pattern)) => candidates
+                .iter()
+                .flat_map(|candidate| {
+                    let dir = if candidate.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        candidate.as_path()
+                    };
+                    fs::read_dir(dir)
+                        .into_iter()
+                        .flatten()
+                        .flatten()
+                        .filter(|entry| pattern.matches(&entry.file_name()))
+                        .map(|entry| candidate.join(entry.file_name()))
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        };
+    }
+
+    candidates.retain(|path| path.is_file());
+    candidates.sort();
+    candidates
+}
+
+fn has_wildcard(name: &OsStr) -> bool {
+    name.as_encoded_bytes()
+        .iter()
+        .any(|&byte| byte == b'*' || byte == b'?')
+}
+
+/// `entry` with a leading `~` replaced by the home directory, when known
+fn expand_home(entry: &str) -> PathBuf {
+    let rest = match entry.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return PathBuf::from(entry),
+    };
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(entry),
+    }
 }
 
 pub fn discover_log_files_for_path(path: &Path) -> Result<Vec<PathBuf>> {
@@ -106,7 +525,7 @@ pub fn discover_log_files_for_path(path: &Path) -> Result<Vec<PathBuf>> {
     if path.is_file() {
         log_files.push(path.to_path_buf());
     } else if path.is_dir() {
-        discover_files_in_directory(path, &mut log_files)?;
+        log_files = discover_files_in_directory(path, &[], false)?;
     } else {
         return Err(PgLogstatsError::Configuration {
             message: format!("Log path is neither file nor directory: {}", path.display()),
@@ -119,62 +538,145 @@ pub fn discover_log_files_for_path(path: &Path) -> Result<Vec<PathBuf>> {
     Ok(log_files)
 }
 
-fn discover_files_in_directory(dir: &Path, log_files: &mut Vec<PathBuf>) -> Result<()> {
-    let entries = fs::read_dir(dir)?;
-
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+/// Files in `dir` whose name matches one of `patterns`, or that
+/// [`is_default_log_file`] accepts when there are none, in path order.
+///
+/// With `recursive`, subdirectories are searched too; symlinked directories
+/// are not followed, so links back up the tree cannot loop.
+pub fn discover_files_in_directory(
+    dir: &Path,
+    patterns: &[FilePattern],
+    recursive: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut log_files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
 
-        if path.is_file() {
-            if let Some(extension) = path.extension() {
-                let ext_str = extension.to_string_lossy().to_lowercase();
-                if ext_str == "log" || ext_str == "txt" {
-                    log_files.push(path);
+            if entry.file_type()?.is_dir() {
+                if recursive {
+                    pending.push(path);
                 }
-            } else if let Some(filename) = path.file_name() {
-                let filename_str = filename.to_string_lossy().to_lowercase();
-                if filename_str.contains("postgres") || filename_str.contains("pg") {
+            } else if path.is_file() {
+                let matches = if patterns.is_empty() {
+                    is_default_log_file(&path)
+                } else {
+                    patterns
+                        .iter()
+                        .any(|pattern| pattern.matches(&entry.file_name()))
+                };
+                if matches {
                     log_files.push(path);
                 }
             }
         }
     }
 
-    Ok(())
+    log_files.sort();
+    Ok(log_files)
 }
 
+/// Parse a log file; only I/O errors fail, while lines that cannot be
+/// parsed are skipped
 pub fn process_log_file(
     log_file: &Path,
-    parser: &TextLogParser,
+    parser: &mut TextLogParser,
     sample_size: Option<usize>,
 ) -> Result<Vec<LogEntry>> {
-    let content = fs::read_to_string(log_file)?;
-    let lines: Vec<String> = content.lines().map(str::to_string).collect();
-
-    let lines_to_process = if let Some(sample_size) = sample_size {
-        if lines.len() > sample_size {
-            info!(
-                "Limiting analysis to first {} lines of {}",
-                sample_size,
-                log_file.display()
-            );
-            &lines[..sample_size]
-        } else {
-            &lines
+    process_log_file_with_report(log_file, parser, sample_size).map(|(entries, _)| entries)
+}
+
+/// Parse a log file and report the lines that were skipped and why
+pub fn process_log_file_with_report(
+    log_file: &Path,
+    parser: &mut TextLogParser,
+    sample_size: Option<usize>,
+) -> Result<(Vec<LogEntry>, ParseReport)> {
+    let lines = read_log_lines(log_file, sample_size)?;
+    let mut parsed = parser
+        .parse_log_lines_with_context(&lines, &LineContext::file(log_file.display().to_string()));
+    parsed.set_source_file(log_file);
+    Ok((parsed.entries, parsed.parse_report))
+}
+
+/// Read a log file into lines, keeping only the first `sample_size` lines
+/// when set. Invalid UTF-8 is replaced rather than rejected.
+pub fn read_log_lines(log_file: &Path, sample_size: Option<usize>) -> Result<Vec<String>> {
+    read_log_lines_with_charset(log_file, sample_size, Charset::Utf8).map(|decoded| decoded.lines)
+}
+
+/// Stream a log file line by line, decoding each line with `charset`, so a
+/// bad byte only affects the line that contains it.
+pub fn read_log_lines_with_charset(
+    log_file: &Path,
+    sample_size: Option<usize>,
+    charset: Charset,
+) -> Result<DecodedLines> {
+    let (decoded, limited) = read_decoded_lines(log_file, sample_size, charset)?;
+    if let (Some(sample_size), true) = (sample_size, limited) {
+        info!(
+            "Limiting analysis to first {} lines of {}",
+            sample_size,
+            log_file.display()
+        );
+    }
+    Ok(decoded)
+}
+
+/// Read the first `max_lines` lines of a log file, e.g. to find its first
+/// entry without reading the whole file
+pub fn peek_log_lines(log_file: &Path, max_lines: usize, charset: Charset) -> Result<Vec<String>> {
+    read_decoded_lines(log_file, Some(max_lines), charset).map(|(decoded, _)| decoded.lines)
+}
+
+/// Decoded lines, up to `limit`, and whether the file had more
+fn read_decoded_lines(
+    log_file: &Path,
+    limit: Option<usize>,
+    charset: Charset,
+) -> Result<(DecodedLines, bool)> {
+    let mut reader = BufReader::new(fs::File::open(log_file)?);
+    let mut decoded = DecodedLines::default();
+    let mut buffer = Vec::new();
+
+    loop {
+        if limit.is_some_and(|limit| decoded.lines.len() >= limit) {
+            let limited = !reader.fill_buf()?.is_empty();
+            return Ok((decoded, limited));
         }
-    } else {
-        &lines
-    };
 
-    parser.parse_lines(lines_to_process)
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+        decoded.unterminated = !buffer.ends_with(b"\n");
+        let line = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let (text, replaced) = charset.decode(line);
+        if replaced {
+            decoded.replaced_lines += 1;
+        }
+        decoded.lines.push(text.into_owned());
+    }
+
+    Ok((decoded, false))
 }
 
 pub fn process_log_paths(
     path: &Path,
-    parser: &TextLogParser,
+    parser: &mut TextLogParser,
     sample_size: Option<usize>,
 ) -> Result<Vec<LogEntry>> {
+    process_log_paths_with_report(path, parser, sample_size).map(|(entries, _)| entries)
+}
+
+pub fn process_log_paths_with_report(
+    path: &Path,
+    parser: &mut TextLogParser,
+    sample_size: Option<usize>,
+) -> Result<(Vec<LogEntry>, ParseReport)> {
     let log_files = discover_log_files_for_path(path)?;
     if log_files.is_empty() {
         return Err(PgLogstatsError::Configuration {
@@ -184,10 +686,155 @@ pub fn process_log_paths(
     }
 
     let mut all_entries = Vec::new();
+    let mut report = ParseReport::new();
     for log_file in log_files {
-        let mut entries = process_log_file(&log_file, parser, sample_size)?;
+        let (mut entries, file_report) =
+            process_log_file_with_report(&log_file, parser, sample_size)?;
         all_entries.append(&mut entries);
+        report.merge(file_report);
+    }
+
+    Ok((all_entries, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            path,
+            "2024-08-15 10:00:00 UTC [1] LOG:  checkpoint starting: time\n",
+        )
+        .unwrap();
     }
 
-    Ok(all_entries)
+    fn names(dir: &Path, files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .map(|path| {
+                path.strip_prefix(dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<FilePattern> {
+        patterns.iter().map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn discovers_files_by_pattern_and_recursion() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in [
+            "postgresql.log",
+            "notes.md",
+            "pg_stderr",
+            "db01-2024-08-15.csv",
+            "server.jsonlog",
+            "archive/postgresql-2024-08-14.LOG",
+            "archive/deep/db02-2024-08-13.csv",
+            "weird name [1].log",
+        ] {
+            touch(&dir.path().join(name));
+        }
+
+        let defaults = discover_files_in_directory(dir.path(), &[], false).unwrap();
+        assert_eq!(
+            names(dir.path(), &defaults),
+            ["pg_stderr", "postgresql.log", "weird name [1].log"]
+        );
+
+        let recursive = discover_files_in_directory(dir.path(), &[], true).unwrap();
+        assert_eq!(
+            names(dir.path(), &recursive),
+            [
+                "archive/postgresql-2024-08-14.LOG",
+                "pg_stderr",
+                "postgresql.log",
+                "weird name [1].log"
+            ]
+        );
+
+        let custom = patterns(&["db??-*.csv", "*.jsonlog"]);
+        assert_eq!(
+            names(
+                dir.path(),
+                &discover_files_in_directory(dir.path(), &custom, false).unwrap()
+            ),
+            ["db01-2024-08-15.csv", "server.jsonlog"]
+        );
+        assert_eq!(
+            names(
+                dir.path(),
+                &discover_files_in_directory(dir.path(), &custom, true).unwrap()
+            ),
+            [
+                "archive/deep/db02-2024-08-13.csv",
+                "db01-2024-08-15.csv",
+                "server.jsonlog"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discovers_files_whose_names_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let name = OsStr::from_bytes(b"caf\xe9.log");
+        touch(&dir.path().join(name));
+
+        let found = discover_files_in_directory(dir.path(), &[], false).unwrap();
+        assert_eq!(found, [dir.path().join(name)]);
+        let found = discover_files_in_directory(dir.path(), &patterns(&["caf?.*"]), false).unwrap();
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn reads_list_entries_and_expands_globs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in [
+            "a/postgresql-1.log",
+            "a/postgresql-2.log",
+            "b/postgresql-3.log",
+        ] {
+            touch(&dir.path().join(name));
+        }
+        let list = format!(
+            "# rotated logs\n{0}/?/postgresql-*.log # all hosts\n\n  {0}/missing.log\n",
+            dir.path().display()
+        );
+
+        let entries = logfile_list_entries(&list);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            names(dir.path(), &expand_list_entry(entries[0])),
+            [
+                "a/postgresql-1.log",
+                "a/postgresql-2.log",
+                "b/postgresql-3.log"
+            ]
+        );
+        assert!(expand_list_entry(entries[1]).is_empty());
+    }
+
+    #[test]
+    fn file_patterns_match_whole_names_ignoring_case() {
+        let pattern: FilePattern = "*.log".parse().unwrap();
+        assert!(pattern.matches(OsStr::new("postgresql.LOG")));
+        assert!(pattern.matches(OsStr::new(".log")));
+        assert!(!pattern.matches(OsStr::new("postgresql.log.1")));
+
+        let pattern: FilePattern = "postgresql-*-*.log".parse().unwrap();
+        assert!(pattern.matches(OsStr::new("postgresql-2024-08-15.log")));
+        assert!(!pattern.matches(OsStr::new("postgresql-20240815.log")));
+
+        assert!("logs/*.log".parse::<FilePattern>().is_err());
+        assert!("".parse::<FilePattern>().is_err());
+    }
 }
@@ -3,8 +3,15 @@
 pub mod cloudwatch;
 pub mod file;
 
-pub use cloudwatch::{process_cloudwatch_input, CloudWatchInput, CloudWatchSince, CloudWatchUntil};
+pub use cloudwatch::{
+    process_cloudwatch_input, process_cloudwatch_input_with_report, read_cloudwatch_lines,
+    CloudWatchInput, CloudWatchSince, CloudWatchUntil,
+};
 pub use file::{
-    discover_log_files, discover_log_files_for_path, process_log_file, process_log_paths,
-    validate_file_input_args, LocalLogInput,
+    discover_files_in_directory, discover_local_log_files, discover_log_files,
+    discover_log_files_for_path, expand_list_entry, is_default_log_file, logfile_list_entries,
+    peek_log_lines, process_log_file, process_log_file_with_report, process_log_paths,
+    process_log_paths_with_report, read_log_lines, read_log_lines_with_charset,
+    validate_file_input_args, Charset, DecodedLines, DiscoveredLogFiles, FilePattern,
+    LocalLogInput, RejectReason, RejectedInput,
 };
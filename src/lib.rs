@@ -6,23 +6,60 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
 pub mod analytics;
+pub mod cancellation;
+pub mod compare;
+pub mod config;
+pub mod connection;
+pub mod copy;
 pub mod correlation;
 pub mod events;
+pub mod explain;
 pub mod findings;
+pub mod grep;
 pub mod input;
+pub mod log_events;
+pub mod maintenance;
 pub mod output;
 pub mod parsers;
+pub mod pg_stat_statements;
+pub mod pg_version;
+pub mod pipeline;
+pub mod prepared;
+pub mod replication;
+pub mod run_stats;
+pub mod sampling;
+pub mod session;
+pub mod snapshot;
 pub mod sql;
+pub mod sqlstate;
+pub mod thresholds;
+pub mod timezone;
+pub mod tui;
 
 // Re-export commonly used items
-pub use analytics::{QueryAnalyzer, TimingAnalysis, TimingAnalyzer};
+pub use analytics::{
+    BusinessHours, BusinessHoursAnalysis, HourStats, HourlyStats, IoVolume, PeriodStats,
+    PoolerAnalyzer, PoolerSummary, QueryAnalyzer, QueryAnalyzerState, TimingAnalysis,
+    TimingAnalyzer, TimingAnalyzerState, TrafficBucket, TrafficGranularity, TrafficHistogram,
+    MAX_TRAFFIC_ROWS,
+};
+pub use cancellation::{CancelReason, CanceledQuery, CancellationStats};
+pub use compare::{
+    compare_reports, ChangeKind, QueryChange, QuerySnapshot, ReportComparison, SlowestRankChange,
+    DEFAULT_CHANGE_THRESHOLD_PERCENT,
+};
+pub use config::{Config, ConfigOverrides};
+pub use connection::ConnectionEventKind;
+pub use copy::{CopyDirection, CopyStatement, CopyStats, CopyTableStats};
 pub use correlation::{
     correlate_query_executions, CorrelationConfidence, Correlator, ProcessOrderCorrelator,
-    QueryExecution, QueryFamilyIdentity,
+    QueryExecution, QueryFamilyIdentity, StreamingCorrelator, STATEMENT_NOT_LOGGED,
 };
 pub use events::{
     normalize_log_entries, DurationEvent, ErrorEvent, EventKind, EventSourceKind, NormalizedEvent,
@@ -33,9 +70,47 @@ pub use findings::{
     FindingConfidence, FindingKind, FindingMetrics, FindingSet, QueryFamilyFinding, ReasonCode,
     SlowQueryDiffOptions, FINDING_SCHEMA_VERSION,
 };
-pub use output::{JsonFormatter, TextFormatter};
-pub use parsers::{TextLogFormat, TextLogParser};
-pub use sql::{Query, QueryType};
+pub use log_events::{message_template, LogEventStats, LogEventTracker, DEFAULT_MAX_LOG_EVENTS};
+pub use maintenance::{
+    CheckpointStats, HintRule, MaintenanceStats, TuningHint, VacuumStats, WarningStats, HINT_RULES,
+};
+pub use output::{
+    EntryLevelFilter, EntryLimit, JsonFormatter, Redact, Redactor, ReportSections, TextFormatter,
+    REDACTED,
+};
+#[cfg(feature = "async")]
+pub use parsers::TextEntryStream;
+pub use parsers::{
+    FileLines, FileOverlap, LogParser, MemoryLimit, ParseReport, PgbouncerParser, PoolerStats,
+    SkipReason, SyslogParser, TextLogFormat, TextLogParser, Truncation, UnterminatedLine,
+    BACKWARDS_JUMP_THRESHOLD_MS,
+};
+pub use pg_stat_statements::{PgStatStatement, PgStatStatementsComparison, QueryCrossReference};
+pub use pg_version::{LogPatterns, PgVersion, PgVersionSource};
+pub use pipeline::{
+    Analyzer, LogFilter, ParsedLog, ParserKind, Pipeline, Report, RunProgress, TimePattern,
+    TimeWindow,
+};
+pub use prepared::{
+    PhaseMessage, PhaseStats, PreparePhase, PreparedStatementStats, PreparedStatementTracker,
+    UNNAMED_STATEMENT,
+};
+pub use replication::{
+    ReplicationEventKind, ReplicationGap, ReplicationStats, ReplicationTransition,
+};
+pub use run_stats::{FileRunStats, RunStats};
+pub use sampling::{SampleEstimate, StatementSampler, StatementSampling};
+pub use session::{is_session_event, session_timeline, SessionEvent, SessionEventKind};
+pub use snapshot::{QueryDelta, Snapshot, SnapshotWindow, SummaryDelta};
+pub use sql::{
+    classify, classify_sql, fingerprint, fingerprint_sql, normalize, normalize_many, query_id,
+    PoolerNoise, Query, QueryLintRule, QueryLinter, QueryType, QueryWarning, TextNormalizer,
+    POOLER_MAINTENANCE,
+};
+pub use sqlstate::{sqlstate_class_name, UNKNOWN_SQLSTATE};
+pub use thresholds::{breach_summary, ThresholdBreach, Thresholds, THRESHOLD_EXIT_CODE};
+pub use timezone::{parse_timezone, Tz};
+pub use tui::{QueryRow, QueryTable, SummaryHeader};
 
 /// Main error type for pg-logstats operations
 #[derive(Error, Debug)]
@@ -45,18 +120,21 @@ pub enum PgLogstatsError {
     Io(#[from] std::io::Error),
 
     /// Errors parsing log files or individual log lines
-    #[error("Parse error: {message}")]
+    #[error("{}Parse error: {message}", .context.prefix())]
     Parse {
         message: String,
-        line_number: Option<usize>,
+        /// Where in the input the line is, as far as known
+        context: LineContext,
         line_content: Option<String>,
     },
 
     /// Errors parsing timestamps in log entries
-    #[error("Timestamp parse error: {message}")]
+    #[error("{}Timestamp parse error: {message}", .context.prefix())]
     TimestampParse {
         message: String,
         timestamp_string: String,
+        /// Where in the input the line is, as far as known
+        context: LineContext,
     },
 
     /// Configuration errors from CLI arguments or settings
@@ -82,8 +160,100 @@ pub enum PgLogstatsError {
     },
 }
 
+impl PgLogstatsError {
+    /// Locate a parse or timestamp parse error at `context`, keeping any
+    /// location it already has; other errors are returned unchanged
+    pub fn with_context(mut self, context: &LineContext) -> Self {
+        if let Self::Parse { context: own, .. } | Self::TimestampParse { context: own, .. } =
+            &mut self
+        {
+            own.fill_from(context);
+        }
+        self
+    }
+
+    /// Where in the input a parse or timestamp parse error happened
+    pub fn context(&self) -> Option<&LineContext> {
+        match self {
+            Self::Parse { context, .. } | Self::TimestampParse { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+}
+
+/// Where a line is in the parsed input: its file, 1-based line number, and
+/// the byte offset of its start, each when known.
+///
+/// Parsers thread one through their line loop so the errors of a line can
+/// be located; it displays as `file.log:12345`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_number: Option<usize>,
+    /// Offset of the start of the line, counting one byte per line end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<u64>,
+}
+
+impl LineContext {
+    /// Context of the lines of `file`
+    pub fn file(file: impl Into<String>) -> Self {
+        Self {
+            file: Some(file.into()),
+            ..Self::default()
+        }
+    }
+
+    /// This context at `line_number`, whose line starts at `byte_offset`
+    pub fn at(&self, line_number: usize, byte_offset: u64) -> Self {
+        Self {
+            file: self.file.clone(),
+            line_number: Some(line_number),
+            byte_offset: Some(byte_offset),
+        }
+    }
+
+    /// Whether nothing about the location is known
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fill in what is unknown here from `other`
+    fn fill_from(&mut self, other: &Self) {
+        self.file = self.file.take().or_else(|| other.file.clone());
+        self.line_number = self.line_number.or(other.line_number);
+        self.byte_offset = self.byte_offset.or(other.byte_offset);
+    }
+
+    /// `file.log:12: ` for the start of a message, or nothing when the
+    /// location is unknown
+    fn prefix(&self) -> String {
+        if self.is_empty() {
+            String::new()
+        } else {
+            format!("{}: ", self)
+        }
+    }
+}
+
+impl std::fmt::Display for LineContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.line_number, self.byte_offset) {
+            (Some(file), Some(line_number), _) => write!(f, "{}:{}", file, line_number),
+            (Some(file), None, Some(byte_offset)) => write!(f, "{}, byte {}", file, byte_offset),
+            (Some(file), None, None) => write!(f, "{}", file),
+            (None, Some(line_number), _) => write!(f, "line {}", line_number),
+            (None, None, Some(byte_offset)) => write!(f, "byte {}", byte_offset),
+            (None, None, None) => Ok(()),
+        }
+    }
+}
+
 /// Log level enumeration for PostgreSQL log entries
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "SerializedLogLevel")]
 pub enum LogLevel {
     /// Error messages
     Error,
@@ -105,10 +275,59 @@ pub enum LogLevel {
     Fatal,
     /// Panic messages
     Panic,
+    /// Detail lines following an error or log message
+    Detail,
+    /// Hint lines following an error
+    Hint,
+    /// Context lines following an error
+    Context,
     /// Unknown or unrecognized log level
     Unknown(String),
 }
 
+/// Deserialized form of [`LogLevel`].
+///
+/// Entries serialized before a level had its own variant store it as
+/// `{"Unknown": "FATAL"}`; those are mapped to the matching variant.
+#[derive(Deserialize)]
+enum SerializedLogLevel {
+    Error,
+    Warning,
+    Info,
+    Debug,
+    Notice,
+    Log,
+    Statement,
+    Duration,
+    Fatal,
+    Panic,
+    Detail,
+    Hint,
+    Context,
+    Unknown(String),
+}
+
+impl From<SerializedLogLevel> for LogLevel {
+    fn from(level: SerializedLogLevel) -> Self {
+        match level {
+            SerializedLogLevel::Error => LogLevel::Error,
+            SerializedLogLevel::Warning => LogLevel::Warning,
+            SerializedLogLevel::Info => LogLevel::Info,
+            SerializedLogLevel::Debug => LogLevel::Debug,
+            SerializedLogLevel::Notice => LogLevel::Notice,
+            SerializedLogLevel::Log => LogLevel::Log,
+            SerializedLogLevel::Statement => LogLevel::Statement,
+            SerializedLogLevel::Duration => LogLevel::Duration,
+            SerializedLogLevel::Fatal => LogLevel::Fatal,
+            SerializedLogLevel::Panic => LogLevel::Panic,
+            SerializedLogLevel::Detail => LogLevel::Detail,
+            SerializedLogLevel::Hint => LogLevel::Hint,
+            SerializedLogLevel::Context => LogLevel::Context,
+            SerializedLogLevel::Unknown(level) => LogLevel::from(level.as_str()),
+        }
+    }
+}
+
 impl std::fmt::Display for LogLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -122,6 +341,9 @@ impl std::fmt::Display for LogLevel {
             LogLevel::Duration => write!(f, "DURATION"),
             LogLevel::Fatal => write!(f, "FATAL"),
             LogLevel::Panic => write!(f, "PANIC"),
+            LogLevel::Detail => write!(f, "DETAIL"),
+            LogLevel::Hint => write!(f, "HINT"),
+            LogLevel::Context => write!(f, "CONTEXT"),
             LogLevel::Unknown(s) => write!(f, "{}", s.to_uppercase()),
         }
     }
@@ -140,13 +362,19 @@ impl From<&str> for LogLevel {
             "DURATION" => LogLevel::Duration,
             "FATAL" => LogLevel::Fatal,
             "PANIC" => LogLevel::Panic,
+            "DETAIL" => LogLevel::Detail,
+            "HINT" => LogLevel::Hint,
+            "CONTEXT" => LogLevel::Context,
             _ => LogLevel::Unknown(s.to_string()),
         }
     }
 }
 
 /// Represents a single parsed PostgreSQL log entry
+///
+/// Outside this crate, build one with [`LogEntry::builder`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct LogEntry {
     /// Timestamp when the log entry was generated
     pub timestamp: DateTime<Utc>,
@@ -158,6 +386,9 @@ pub struct LogEntry {
     pub database: Option<String>,
     /// Client host address (if available)
     pub client_host: Option<String>,
+    /// Client port of the connection (if available)
+    #[serde(default)]
+    pub client_port: Option<u16>,
     /// Application name (if available)
     pub application_name: Option<String>,
     /// Type/level of the log message
@@ -168,9 +399,72 @@ pub struct LogEntry {
     pub queries: Option<Vec<Query>>,
     /// Query duration in milliseconds (if available)
     pub duration: Option<f64>,
+    /// SQLSTATE error code, e.g. `23505` (if available)
+    #[serde(default)]
+    pub sqlstate: Option<String>,
+    /// `DETAIL:` line that followed this error, or the `parameters: $1 =
+    /// '...'` line that followed this statement
+    #[serde(default)]
+    pub detail: Option<String>,
+    /// `HINT:` line that followed this error
+    #[serde(default)]
+    pub hint: Option<String>,
+    /// `CONTEXT:` line that followed this error
+    #[serde(default)]
+    pub context: Option<String>,
+    /// Failing SQL from the `STATEMENT:` line that followed this error; its
+    /// parsed form is in `queries`
+    #[serde(default)]
+    pub statement: Option<String>,
+    /// Per-session line number `n` of a `[n-m]` prefix (`%l`, or syslog
+    /// sequence numbers), if present
+    #[serde(default)]
+    pub session_line: Option<u32>,
+    /// Chunk `m` of a `[n-m]` prefix; for a message reassembled from several
+    /// records, the last chunk folded in
+    #[serde(default)]
+    pub chunk: Option<u32>,
+    /// Prepared statement name of an extended protocol `execute` line, such
+    /// as `S_1` or `<unnamed>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepared_statement: Option<String>,
+    /// Connection, disconnection, or reset line this entry is, recognized
+    /// when it was parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_event: Option<ConnectionEventKind>,
+    /// Session id of a `%c` prefix, such as `65a5b1c0.fa0`; unlike the PID,
+    /// it is never reused by a later session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Virtual transaction id of a `%v` prefix, such as `3/42`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub virtual_txid: Option<String>,
+    /// Transaction id of a `%x` prefix; `0`, logged before an id is assigned,
+    /// is left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txid: Option<u64>,
+    /// Log file the entry was read from, when parsed from a file; shared by
+    /// every entry of the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<Arc<PathBuf>>,
+    /// Position of the entry among those parsed from its input, counting
+    /// from 0 in read order; orders entries logged within the same
+    /// millisecond
+    #[serde(default)]
+    pub seq: u64,
 }
 
 impl LogEntry {
+    /// Start building an entry from its required fields
+    pub fn builder(
+        timestamp: DateTime<Utc>,
+        process_id: impl Into<String>,
+        message_type: LogLevel,
+        message: impl Into<String>,
+    ) -> LogEntryBuilder {
+        LogEntryBuilder::new(timestamp, process_id, message_type, message)
+    }
+
     /// Create a new LogEntry with required fields
     pub fn new(
         timestamp: DateTime<Utc>,
@@ -184,14 +478,77 @@ impl LogEntry {
             user: None,
             database: None,
             client_host: None,
+            client_port: None,
             application_name: None,
             message_type,
             message,
             queries: None,
             duration: None,
+            sqlstate: None,
+            detail: None,
+            hint: None,
+            context: None,
+            statement: None,
+            session_line: None,
+            chunk: None,
+            prepared_statement: None,
+            connection_event: None,
+            session_id: None,
+            virtual_txid: None,
+            txid: None,
+            source_file: None,
+            seq: 0,
         }
     }
 
+    /// Key that orders entries by timestamp, then source file, then read
+    /// order, so entries sharing a timestamp keep the order they were logged
+    /// in
+    pub fn log_order(&self) -> (DateTime<Utc>, Option<&Path>, u64) {
+        (
+            self.timestamp,
+            self.source_file.as_deref().map(PathBuf::as_path),
+            self.seq,
+        )
+    }
+
+    /// Approximate memory held by the entry in bytes, counting its strings'
+    /// capacity; used to enforce memory limits
+    pub fn approximate_size(&self) -> usize {
+        let strings = [
+            Some(&self.process_id),
+            self.user.as_ref(),
+            self.database.as_ref(),
+            self.client_host.as_ref(),
+            self.application_name.as_ref(),
+            Some(&self.message),
+            self.sqlstate.as_ref(),
+            self.detail.as_ref(),
+            self.hint.as_ref(),
+            self.context.as_ref(),
+            self.statement.as_ref(),
+            self.prepared_statement.as_ref(),
+            self.session_id.as_ref(),
+            self.virtual_txid.as_ref(),
+        ];
+        let queries = self.queries.iter().flatten().map(|query| {
+            std::mem::size_of::<Query>() + query.sql.capacity() + query.normalized_query.capacity()
+        });
+        std::mem::size_of::<Self>()
+            + strings
+                .into_iter()
+                .flatten()
+                .map(String::capacity)
+                .sum::<usize>()
+            + queries.sum::<usize>()
+    }
+
+    /// Session the entry belongs to: the `%c` session id when logged, else
+    /// the process id
+    pub fn session_key(&self) -> &str {
+        self.session_id.as_deref().unwrap_or(&self.process_id)
+    }
+
     /// Check if this log entry represents a query statement
     pub fn is_query(&self) -> bool {
         matches!(self.message_type, LogLevel::Statement)
@@ -202,25 +559,216 @@ impl LogEntry {
         matches!(self.message_type, LogLevel::Duration)
     }
 
-    /// Check if this log entry represents an error
+    /// Check if this log entry represents an error: `ERROR`, `FATAL`, or `PANIC`
     pub fn is_error(&self) -> bool {
-        matches!(self.message_type, LogLevel::Error)
+        matches!(
+            self.message_type,
+            LogLevel::Error | LogLevel::Fatal | LogLevel::Panic
+        )
     }
 
     /// Get the normalized query (for deduplication)
     pub fn normalized_query(&self) -> Option<String> {
-        let mut normalized_query: Option<String> = None;
-        if self.is_query() {
-            if let Some(queries) = &self.queries {
-                for query in queries {
-                    normalized_query = match normalized_query {
-                        Some(ref mut s) => Some(format!("{};{}", s, query.normalized_query)),
-                        None => Some(query.normalized_query.clone()),
-                    };
-                }
+        match &self.queries {
+            Some(queries) if self.is_query() && !queries.is_empty() => {
+                Some(sql::join_normalized_queries(queries))
             }
+            _ => None,
+        }
+    }
+}
+
+/// Builds a [`LogEntry`] from its required fields and any optional ones,
+/// so construction keeps compiling as fields are added
+///
+/// ```
+/// use chrono::Utc;
+/// use pg_logstats::{LogEntry, LogLevel};
+///
+/// let entry = LogEntry::builder(Utc::now(), "4000", LogLevel::Log, "checkpoint starting: time")
+///     .with_user(Some("postgres"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(entry.user.as_deref(), Some("postgres"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogEntryBuilder {
+    entry: LogEntry,
+}
+
+impl LogEntryBuilder {
+    /// Start an entry with the fields every entry has
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        process_id: impl Into<String>,
+        message_type: LogLevel,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            entry: LogEntry::new(timestamp, process_id.into(), message_type, message.into()),
+        }
+    }
+
+    /// `statement: <sql>` entry with the parsed form of `sql`, or none when
+    /// it does not parse
+    pub fn statement(timestamp: DateTime<Utc>, process_id: impl Into<String>, sql: &str) -> Self {
+        Self::new(
+            timestamp,
+            process_id,
+            LogLevel::Statement,
+            format!("statement: {}", sql),
+        )
+        .with_queries(Query::from_sql(sql).ok())
+    }
+
+    /// `duration: <n> ms` entry
+    pub fn duration(
+        timestamp: DateTime<Utc>,
+        process_id: impl Into<String>,
+        duration_ms: f64,
+    ) -> Self {
+        Self::new(
+            timestamp,
+            process_id,
+            LogLevel::Duration,
+            format!("duration: {:.3} ms", duration_ms),
+        )
+        .with_duration(Some(duration_ms))
+    }
+
+    /// Database user
+    pub fn with_user(mut self, user: Option<impl Into<String>>) -> Self {
+        self.entry.user = user.map(Into::into);
+        self
+    }
+
+    /// Database name
+    pub fn with_database(mut self, database: Option<impl Into<String>>) -> Self {
+        self.entry.database = database.map(Into::into);
+        self
+    }
+
+    /// Client host address
+    pub fn with_client_host(mut self, client_host: Option<impl Into<String>>) -> Self {
+        self.entry.client_host = client_host.map(Into::into);
+        self
+    }
+
+    /// Client port of the connection
+    pub fn with_client_port(mut self, client_port: Option<u16>) -> Self {
+        self.entry.client_port = client_port;
+        self
+    }
+
+    /// Application name
+    pub fn with_application_name(mut self, application_name: Option<impl Into<String>>) -> Self {
+        self.entry.application_name = application_name.map(Into::into);
+        self
+    }
+
+    /// Parsed SQL of a statement
+    pub fn with_queries(mut self, queries: Option<Vec<Query>>) -> Self {
+        self.entry.queries = queries;
+        self
+    }
+
+    /// Duration in milliseconds
+    pub fn with_duration(mut self, duration: Option<f64>) -> Self {
+        self.entry.duration = duration;
+        self
+    }
+
+    /// `[n-m]` session line number and chunk
+    pub fn with_session_line(mut self, session_line: Option<u32>, chunk: Option<u32>) -> Self {
+        self.entry.session_line = session_line;
+        self.entry.chunk = chunk;
+        self
+    }
+
+    /// SQLSTATE error code
+    pub fn with_sqlstate(mut self, sqlstate: Option<impl Into<String>>) -> Self {
+        self.entry.sqlstate = sqlstate.map(Into::into);
+        self
+    }
+
+    /// `DETAIL:` or `parameters:` line
+    pub fn with_detail(mut self, detail: Option<impl Into<String>>) -> Self {
+        self.entry.detail = detail.map(Into::into);
+        self
+    }
+
+    /// `HINT:` line
+    pub fn with_hint(mut self, hint: Option<impl Into<String>>) -> Self {
+        self.entry.hint = hint.map(Into::into);
+        self
+    }
+
+    /// `CONTEXT:` line
+    pub fn with_context(mut self, context: Option<impl Into<String>>) -> Self {
+        self.entry.context = context.map(Into::into);
+        self
+    }
+
+    /// Failing SQL of an error
+    pub fn with_statement(mut self, statement: Option<impl Into<String>>) -> Self {
+        self.entry.statement = statement.map(Into::into);
+        self
+    }
+
+    /// Prepared statement name of an `execute` line
+    pub fn with_prepared_statement(
+        mut self,
+        prepared_statement: Option<impl Into<String>>,
+    ) -> Self {
+        self.entry.prepared_statement = prepared_statement.map(Into::into);
+        self
+    }
+
+    /// Connection event the entry is
+    pub fn with_connection_event(mut self, connection_event: Option<ConnectionEventKind>) -> Self {
+        self.entry.connection_event = connection_event;
+        self
+    }
+
+    /// `%c` session id
+    pub fn with_session_id(mut self, session_id: Option<impl Into<String>>) -> Self {
+        self.entry.session_id = session_id.map(Into::into);
+        self
+    }
+
+    /// `%v` virtual transaction id
+    pub fn with_virtual_txid(mut self, virtual_txid: Option<impl Into<String>>) -> Self {
+        self.entry.virtual_txid = virtual_txid.map(Into::into);
+        self
+    }
+
+    /// `%x` transaction id
+    pub fn with_txid(mut self, txid: Option<u64>) -> Self {
+        self.entry.txid = txid;
+        self
+    }
+
+    /// Log file the entry was read from
+    pub fn with_source_file(mut self, source_file: Option<Arc<PathBuf>>) -> Self {
+        self.entry.source_file = source_file;
+        self
+    }
+
+    /// Position of the entry in read order within its input
+    pub fn with_seq(mut self, seq: u64) -> Self {
+        self.entry.seq = seq;
+        self
+    }
+
+    /// The entry; fails when the process id is empty
+    pub fn build(self) -> Result<LogEntry> {
+        if self.entry.process_id.is_empty() {
+            return Err(config_error(
+                "Log entry has an empty process id",
+                Some("process_id"),
+            ));
         }
-        normalized_query
+        Ok(self.entry)
     }
 }
 
@@ -231,22 +779,143 @@ pub struct AnalysisResult {
     pub total_queries: u64,
     /// Total duration of all queries in milliseconds
     pub total_duration: f64,
-    /// Count of queries by type (SELECT, INSERT, UPDATE, DELETE, etc.)
-    pub query_types: HashMap<String, u64>,
+    /// Count and durations of queries by type (SELECT, INSERT, UPDATE,
+    /// DELETE, etc.)
+    pub query_types: HashMap<String, QueryTypeStats>,
     /// Slowest queries with their durations
     pub slowest_queries: Vec<(String, f64)>,
     /// Most frequent queries with their counts
     pub most_frequent_queries: Vec<(String, u64)>,
     /// Total number of error messages
     pub error_count: u64,
+    /// Error counts keyed by SQLSTATE class, e.g. `23`, or `unknown`
+    #[serde(default)]
+    pub errors_by_sqlstate: BTreeMap<String, SqlstateClassCount>,
+    /// Error counts keyed by severity: `ERROR`, `FATAL`, or `PANIC`
+    #[serde(default)]
+    pub errors_by_severity: BTreeMap<String, u64>,
     /// Total number of connection events
     pub connection_count: u64,
     /// Average query duration in milliseconds
     pub average_duration: f64,
+    /// Population standard deviation of query durations in milliseconds
+    #[serde(default)]
+    pub stddev_duration: f64,
+    /// Fastest query duration in milliseconds
+    #[serde(default)]
+    pub min_duration: f64,
     /// 95th percentile query duration in milliseconds
     pub p95_duration: f64,
     /// 99th percentile query duration in milliseconds
     pub p99_duration: f64,
+    /// Slowest query duration in milliseconds
+    #[serde(default)]
+    pub max_duration: f64,
+    /// Queries slower than the slow-query threshold
+    #[serde(default)]
+    pub slow_query_count: u64,
+    /// Query counts keyed by user; entries without one count as `unknown`
+    #[serde(default)]
+    pub queries_by_user: HashMap<String, u64>,
+    /// Total query duration in milliseconds keyed by user
+    #[serde(default)]
+    pub duration_by_user: HashMap<String, f64>,
+    /// Query counts keyed by database
+    #[serde(default)]
+    pub queries_by_database: HashMap<String, u64>,
+    /// Error counts keyed by database
+    #[serde(default)]
+    pub errors_by_database: HashMap<String, u64>,
+    /// Dimension `query_groups` is keyed by, when grouping was requested
+    #[serde(default)]
+    pub group_by: Option<GroupBy>,
+    /// Slowest and most frequent queries per `group_by` value
+    #[serde(default)]
+    pub query_groups: BTreeMap<String, QueryGroup>,
+    /// Per-application statistics, highest total duration first
+    #[serde(default)]
+    pub applications: Vec<ApplicationStats>,
+    /// Busiest client hosts, highest total duration first; empty when the log
+    /// names no clients
+    #[serde(default)]
+    pub client_hosts: Vec<ClientHostStats>,
+    /// Query counts per duration bucket, e.g. `("10-100ms", 42)`, fastest first
+    #[serde(default)]
+    pub duration_histogram: Vec<(String, u64)>,
+    /// Time window covered by the analyzed entries; `None` without entries
+    #[serde(default)]
+    pub time_range: Option<TimeRange>,
+    /// Duration statistics keyed by normalized SQL, for each query in
+    /// `slowest_queries` and `most_frequent_queries`
+    #[serde(default)]
+    pub query_stats: HashMap<String, QueryStats>,
+    /// Duration statistics keyed by normalized SQL, without examples, for
+    /// every query counted; only the most frequent ones once counts are
+    /// estimates, see `frequency_estimate`
+    #[serde(default)]
+    pub all_query_stats: HashMap<String, QueryStats>,
+    /// Set when `most_frequent_queries` counts are estimates from a bounded
+    /// top-k counter; `None` when they are exact
+    #[serde(default)]
+    pub frequency_estimate: Option<FrequencyEstimate>,
+    /// Set when query counts and totals were scaled up from a statement
+    /// sample; `None` when every statement was analyzed
+    #[serde(default)]
+    pub sample_estimate: Option<SampleEstimate>,
+    /// Queries matching a lint rule, most executed first; `None` when linting
+    /// was off
+    #[serde(default)]
+    pub query_warnings: Option<Vec<QueryWarning>>,
+    /// Canceled statements and terminated connections by reason, statement,
+    /// and hour
+    #[serde(default)]
+    pub cancellations: CancellationStats,
+    /// `COPY` loads and exports per table
+    #[serde(default)]
+    pub copy_stats: CopyStats,
+    /// Streaming replication and recovery events of a standby
+    #[serde(default)]
+    pub replication: ReplicationStats,
+    /// Query counts and durations per hour of day, earliest hour first
+    #[serde(default)]
+    pub hourly_stats: Vec<HourlyStats>,
+    /// Queries known only from a duration line, counted under
+    /// [`STATEMENT_NOT_LOGGED`]
+    #[serde(default)]
+    pub durations_without_statement: u64,
+    /// Queries whose statement was logged without a duration, as with
+    /// `log_statement = all` and `log_duration = off`; they count with a
+    /// duration of zero
+    #[serde(default)]
+    pub queries_without_duration: u64,
+    /// Statements the SQL parser rejected; they are counted by type but left
+    /// out of the slowest and most frequent queries
+    #[serde(default)]
+    pub unparsed_statements: UnparsedStatements,
+    /// Query and error counts over the time range; `None` unless requested
+    /// with [`QueryAnalyzer::with_traffic_histogram`]
+    #[serde(default)]
+    pub traffic_histogram: Option<TrafficHistogram>,
+    /// Executions made only of pooler maintenance statements, such as
+    /// `DISCARD ALL`; counted as queries too unless the analyzer ignores them
+    #[serde(default)]
+    pub pooler_maintenance_count: u64,
+    /// PostgreSQL major version that wrote the log, when known
+    #[serde(default)]
+    pub pg_version: Option<PgVersion>,
+    /// Checkpoints and autovacuums
+    #[serde(default)]
+    pub maintenance: MaintenanceStats,
+    /// Temp files and checkpoint writes
+    #[serde(default)]
+    pub io: IoVolume,
+    /// Parse, bind, and execute phases of prepared statements
+    #[serde(default)]
+    pub prepared_statements: PreparedStatementStats,
+    /// Most frequent LOG, WARNING, and NOTICE message templates, most
+    /// frequent first
+    #[serde(default)]
+    pub log_events: Vec<LogEventStats>,
 }
 
 impl AnalysisResult {
@@ -259,11 +928,93 @@ impl AnalysisResult {
             slowest_queries: Vec::new(),
             most_frequent_queries: Vec::new(),
             error_count: 0,
+            errors_by_sqlstate: BTreeMap::new(),
+            errors_by_severity: BTreeMap::new(),
             connection_count: 0,
             average_duration: 0.0,
+            stddev_duration: 0.0,
+            min_duration: 0.0,
             p95_duration: 0.0,
             p99_duration: 0.0,
+            max_duration: 0.0,
+            slow_query_count: 0,
+            queries_by_user: HashMap::new(),
+            duration_by_user: HashMap::new(),
+            queries_by_database: HashMap::new(),
+            errors_by_database: HashMap::new(),
+            group_by: None,
+            query_groups: BTreeMap::new(),
+            applications: Vec::new(),
+            client_hosts: Vec::new(),
+            duration_histogram: Vec::new(),
+            time_range: None,
+            query_stats: HashMap::new(),
+            all_query_stats: HashMap::new(),
+            frequency_estimate: None,
+            sample_estimate: None,
+            query_warnings: None,
+            cancellations: CancellationStats::default(),
+            copy_stats: CopyStats::default(),
+            replication: ReplicationStats::default(),
+            hourly_stats: Vec::new(),
+            durations_without_statement: 0,
+            queries_without_duration: 0,
+            unparsed_statements: UnparsedStatements::default(),
+            traffic_histogram: None,
+            pooler_maintenance_count: 0,
+            pg_version: None,
+            maintenance: MaintenanceStats::default(),
+            io: IoVolume::default(),
+            prepared_statements: PreparedStatementStats::default(),
+            log_events: Vec::new(),
+        }
+    }
+
+    /// One-line note suggesting `log_min_duration_statement` when more than
+    /// [`UNLOGGED_STATEMENT_NOTE_PERCENT`] of the queries had no statement
+    pub fn unlogged_statement_note(&self) -> Option<String> {
+        if self.total_queries == 0 {
+            return None;
         }
+        let percent = self.durations_without_statement as f64 * 100.0 / self.total_queries as f64;
+        (percent > UNLOGGED_STATEMENT_NOTE_PERCENT).then(|| {
+            format!(
+                "{} of {} durations ({:.0}%) had no logged statement and are counted as {}; \
+                 set log_min_duration_statement to log statements with their durations",
+                self.durations_without_statement, self.total_queries, percent, STATEMENT_NOT_LOGGED
+            )
+        })
+    }
+
+    /// Whether any query had a duration; `false` when statements were logged
+    /// but none of them with a duration, so every duration statistic is a
+    /// meaningless zero
+    pub fn durations_logged(&self) -> bool {
+        self.total_queries == 0 || self.queries_without_duration < self.total_queries
+    }
+
+    /// One-line note suggesting `log_min_duration_statement` when no query
+    /// had a duration
+    pub fn unlogged_duration_note(&self) -> Option<String> {
+        (!self.durations_logged()).then(|| {
+            format!(
+                "none of the {} statements had a logged duration, so durations are not reported; \
+                 set log_min_duration_statement (0 logs every statement with its duration) \
+                 or log_duration = on",
+                self.total_queries
+            )
+        })
+    }
+
+    /// Groups in `query_groups`, busiest first by total duration, then by name
+    pub fn query_groups_by_duration(&self) -> Vec<(&str, &QueryGroup)> {
+        let mut groups: Vec<_> = self
+            .query_groups
+            .iter()
+            .map(|(key, group)| (key.as_str(), group))
+            .collect();
+        groups.sort_by(|a, b| b.1.total_duration.total_cmp(&a.1.total_duration));
+        groups
     }
 
     /// Add a query to the analysis
@@ -271,9 +1022,16 @@ impl AnalysisResult {
         self.total_queries += 1;
         self.total_duration += duration;
 
-        // Update query type count
-        let query_type = self.extract_query_type(query);
-        *self.query_types.entry(query_type).or_insert(0) += 1;
+        // Update query type stats; percentiles need every duration, so the
+        // p95 is left as it was
+        let stats = self
+            .query_types
+            .entry(classify(query).to_string())
+            .or_default();
+        stats.count += 1;
+        stats.total_duration += duration;
+        stats.average_duration = stats.total_duration / stats.count as f64;
+        stats.max_duration = stats.max_duration.max(duration);
 
         // Update average duration
         self.average_duration = self.total_duration / self.total_queries as f64;
@@ -284,38 +1042,44 @@ impl AnalysisResult {
         self.error_count += 1;
     }
 
-    /// Add a connection event to the count
-    pub fn add_connection(&mut self) {
-        self.connection_count += 1;
+    /// Add an error to the count and to its SQLSTATE class
+    pub fn add_error_with_sqlstate(&mut self, sqlstate: Option<&str>) {
+        self.add_error_with_statement(sqlstate, None);
     }
 
-    /// Extract the query type from a SQL query
-    fn extract_query_type(&self, query: &str) -> String {
-        let query_upper = query.trim().to_uppercase();
-        if query_upper.starts_with("SELECT") {
-            "SELECT".to_string()
-        } else if query_upper.starts_with("INSERT") {
-            "INSERT".to_string()
-        } else if query_upper.starts_with("UPDATE") {
-            "UPDATE".to_string()
-        } else if query_upper.starts_with("DELETE") {
-            "DELETE".to_string()
-        } else if query_upper.starts_with("CREATE") {
-            "CREATE".to_string()
-        } else if query_upper.starts_with("DROP") {
-            "DROP".to_string()
-        } else if query_upper.starts_with("ALTER") {
-            "ALTER".to_string()
-        } else if query_upper.starts_with("BEGIN")
-            || query_upper.starts_with("COMMIT")
-            || query_upper.starts_with("ROLLBACK")
-        {
-            "TRANSACTION".to_string()
-        } else {
-            "OTHER".to_string()
+    /// Add an error to the count and to its SQLSTATE class, recording the
+    /// normalized statement that failed
+    pub fn add_error_with_statement(&mut self, sqlstate: Option<&str>, statement: Option<&str>) {
+        self.add_error();
+
+        let (class, code) = match sqlstate {
+            Some(code) if sqlstate::is_sqlstate(code) => (&code[..2], code),
+            _ => (UNKNOWN_SQLSTATE, UNKNOWN_SQLSTATE),
+        };
+        let class_count = self
+            .errors_by_sqlstate
+            .entry(class.to_string())
+            .or_insert_with(|| SqlstateClassCount {
+                class_name: sqlstate_class_name(class).to_string(),
+                count: 0,
+                codes: BTreeMap::new(),
+                statements: BTreeMap::new(),
+            });
+        class_count.count += 1;
+        *class_count.codes.entry(code.to_string()).or_insert(0) += 1;
+        if let Some(statement) = statement {
+            *class_count
+                .statements
+                .entry(statement.to_string())
+                .or_insert(0) += 1;
         }
     }
 
+    /// Add a connection event to the count
+    pub fn add_connection(&mut self) {
+        self.connection_count += 1;
+    }
+
     /// Calculate percentiles from a list of durations
     pub fn calculate_percentiles(&mut self, durations: &[f64]) {
         if durations.is_empty() {
@@ -334,6 +1098,328 @@ impl AnalysisResult {
     }
 }
 
+/// Error counts for one SQLSTATE class
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SqlstateClassCount {
+    /// Human-readable class name, e.g. "Integrity Constraint Violation"
+    pub class_name: String,
+    /// Total errors in this class
+    pub count: u64,
+    /// Error counts per full SQLSTATE code
+    pub codes: BTreeMap<String, u64>,
+    /// Error counts per normalized failing statement
+    #[serde(default)]
+    pub statements: BTreeMap<String, u64>,
+}
+
+/// Key used in breakdowns for entries that do not carry the dimension
+pub const UNKNOWN_BREAKDOWN_KEY: &str = "unknown";
+
+/// Share of queries, in percent, known only from a duration line above which
+/// the report suggests logging statements
+pub const UNLOGGED_STATEMENT_NOTE_PERCENT: f64 = 10.0;
+
+/// Session dimension the slowest and most frequent queries can be grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// Database user
+    User,
+    /// Database name
+    Database,
+    /// `application_name` of the client
+    Application,
+}
+
+impl GroupBy {
+    /// Lowercase name used in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Database => "database",
+            Self::Application => "application",
+        }
+    }
+
+    /// The session's value for this dimension, or `unknown`
+    pub fn key(self, session: &SessionIdentity) -> &str {
+        let value = match self {
+            Self::User => session.user.as_deref(),
+            Self::Database => session.database.as_deref(),
+            Self::Application => session.application_name.as_deref(),
+        };
+        value.unwrap_or(UNKNOWN_BREAKDOWN_KEY)
+    }
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Statements the SQL parser rejected, such as `DO` blocks, classified only
+/// by their leading keyword
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UnparsedStatements {
+    pub count: u64,
+    /// Total duration in milliseconds
+    pub total_duration: f64,
+    /// The first distinct statements, truncated
+    pub samples: Vec<String>,
+}
+
+impl UnparsedStatements {
+    /// Number of samples kept
+    pub const MAX_SAMPLES: usize = 5;
+
+    /// Count one statement, keeping it shortened to `sample_length`
+    /// characters as a sample while there is room
+    pub fn record(&mut self, statement: &str, duration: f64, sample_length: usize) {
+        self.count += 1;
+        self.total_duration += duration;
+        if self.samples.len() >= Self::MAX_SAMPLES {
+            return;
+        }
+        let statement = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+        let sample = output::text::truncate_query(&statement, sample_length).into_owned();
+        if !self.samples.contains(&sample) {
+            self.samples.push(sample);
+        }
+    }
+
+    /// Add the statements of `other`
+    pub fn merge(&mut self, other: Self) {
+        self.count += other.count;
+        self.total_duration += other.total_duration;
+        for sample in other.samples {
+            if self.samples.len() < Self::MAX_SAMPLES && !self.samples.contains(&sample) {
+                self.samples.push(sample);
+            }
+        }
+    }
+
+    /// Whether every statement was parsed
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+/// Execution count and duration statistics of one normalized query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryStats {
+    /// Number of executions
+    pub count: u64,
+    /// Total duration in milliseconds
+    pub total_duration: f64,
+    /// Average duration in milliseconds
+    pub average_duration: f64,
+    /// 95th percentile duration in milliseconds
+    pub p95_duration: f64,
+    /// Slowest executions kept as examples, slowest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<QueryExample>,
+}
+
+/// Count and duration statistics of the queries of one [`QueryType`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryTypeStats {
+    /// Number of statements of the type
+    pub count: u64,
+    /// Total duration in milliseconds
+    pub total_duration: f64,
+    /// Average duration in milliseconds
+    pub average_duration: f64,
+    /// 95th percentile duration in milliseconds
+    pub p95_duration: f64,
+    /// Maximum duration in milliseconds
+    pub max_duration: f64,
+}
+
+/// One execution of a normalized query, kept with its literals so it can be
+/// rerun or explained
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryExample {
+    /// Statement as logged, before normalization
+    pub sql: String,
+    pub timestamp: DateTime<Utc>,
+    /// Duration in milliseconds
+    pub duration: f64,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    /// Values of the `$n` placeholders, as logged after `parameters:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<String>,
+}
+
+/// Metric the most frequent queries are ranked by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuerySort {
+    /// Number of executions
+    #[default]
+    Count,
+    /// Total duration, what pgBadger calls time consuming queries
+    TotalTime,
+    /// Average duration
+    AvgTime,
+    /// 95th percentile duration
+    P95,
+}
+
+impl QuerySort {
+    /// Name used on the command line and in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::TotalTime => "total_time",
+            Self::AvgTime => "avg_time",
+            Self::P95 => "p95",
+        }
+    }
+
+    /// The ranked metric of a query with `stats`
+    pub fn key(self, stats: &QueryStats) -> f64 {
+        match self {
+            Self::Count => stats.count as f64,
+            Self::TotalTime => stats.total_duration,
+            Self::AvgTime => stats.average_duration,
+            Self::P95 => stats.p95_duration,
+        }
+    }
+
+    /// Order `(query, count)` rows by this metric, largest first.
+    ///
+    /// Durations come from `query_stats`; rows without stats go last. Ties
+    /// keep their order.
+    pub fn sort_rows(self, rows: &mut [(String, u64)], query_stats: &HashMap<String, QueryStats>) {
+        if self == Self::Count {
+            rows.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            return;
+        }
+        let key = |query: &str| {
+            query_stats
+                .get(query)
+                .map_or(f64::NEG_INFINITY, |stats| self.key(stats))
+        };
+        rows.sort_by(|(a, _), (b, _)| key(b).total_cmp(&key(a)));
+    }
+}
+
+impl std::fmt::Display for QuerySort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How the one duration logged for a multi-statement line, such as
+/// `BEGIN; UPDATE ...; COMMIT`, is attributed to its statements.
+///
+/// Either way, every count, total, and percentile uses the same queries, so
+/// per-query and per-type totals add up to the overall total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchDuration {
+    /// The batch is one query taking the duration, listed under its
+    /// statements joined by `; ` and counted under the type of its first
+    /// statement other than transaction control
+    #[default]
+    Batch,
+    /// Each statement is a query of its own taking an equal share of the
+    /// duration
+    Split,
+}
+
+/// Query statistics for one value of the `group_by` dimension
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryGroup {
+    /// Number of queries in this group
+    pub total_queries: u64,
+    /// Total duration of this group's queries in milliseconds
+    pub total_duration: f64,
+    /// Slowest queries in this group with their durations
+    pub slowest_queries: Vec<(String, f64)>,
+    /// Most frequent queries in this group with their counts
+    pub most_frequent_queries: Vec<(String, u64)>,
+}
+
+/// Application name used for entries that do not carry one
+pub const NO_APPLICATION_NAME: &str = "(none)";
+
+/// Query statistics for one `application_name`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationStats {
+    /// Application name, or `(none)` when the client did not set one
+    pub application_name: String,
+    /// Number of queries run by this application
+    pub query_count: u64,
+    /// Total duration of this application's queries in milliseconds
+    pub total_duration: f64,
+    /// 95th percentile duration of this application's queries in milliseconds
+    pub p95_duration: f64,
+    /// Number of errors logged by this application's sessions
+    pub error_count: u64,
+    /// Slowest normalized queries of this application with their longest duration
+    pub slowest_queries: Vec<(String, f64)>,
+}
+
+/// Connection, query, and error counts of one client host
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientHostStats {
+    /// Client address, with its port as `host(port)` when ports are kept
+    pub client_host: String,
+    /// Number of `connection received` lines from this host
+    pub connection_count: u64,
+    /// Number of queries run by this host's sessions
+    pub query_count: u64,
+    /// Total duration of this host's queries in milliseconds
+    pub total_duration: f64,
+    /// Number of errors logged by this host's sessions
+    pub error_count: u64,
+    /// Share of this host's statements that failed, from 0 to 1
+    pub error_rate: f64,
+}
+
+/// How the most frequent query counts were estimated, when a log had too
+/// many distinct queries to count them all
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrequencyEstimate {
+    /// Number of distinct queries kept
+    pub capacity: usize,
+    /// Most any listed count may exceed the query's true count by
+    pub max_overcount: u64,
+}
+
+/// Time window covered by a log and how much of it had activity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeRange {
+    /// Timestamp of the earliest entry
+    pub start: DateTime<Utc>,
+    /// Timestamp of the latest entry
+    pub end: DateTime<Utc>,
+    /// Wall-clock seconds from `start` to `end`
+    pub span_seconds: f64,
+    /// Queries per second over the whole span; 0 when the span is empty
+    pub queries_per_second: f64,
+    /// Distinct calendar days (UTC) with at least one entry
+    pub active_days: u64,
+    /// Distinct clock hours (UTC) with at least one entry
+    pub active_hours: u64,
+    /// Stretches without entries longer than the gap threshold, oldest first
+    pub gaps: Vec<TimeGap>,
+}
+
+/// Stretch of time without any log entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeGap {
+    /// Timestamp of the last entry before the gap
+    pub start: DateTime<Utc>,
+    /// Timestamp of the first entry after the gap
+    pub end: DateTime<Utc>,
+    /// Length of the gap in minutes
+    pub minutes: f64,
+}
+
 impl Default for AnalysisResult {
     fn default() -> Self {
         Self::new()
@@ -346,21 +1432,23 @@ pub type Result<T> = std::result::Result<T, PgLogstatsError>;
 /// Helper function to create parse errors with context
 pub fn parse_error(
     message: &str,
-    line_number: Option<usize>,
+    context: LineContext,
     line_content: Option<&str>,
 ) -> PgLogstatsError {
     PgLogstatsError::Parse {
         message: message.to_string(),
-        line_number,
+        context,
         line_content: line_content.map(|s| s.to_string()),
     }
 }
 
-/// Helper function to create timestamp parse errors
+/// Helper function to create timestamp parse errors; the parser's line loop
+/// locates them with [`PgLogstatsError::with_context`]
 pub fn timestamp_error(message: &str, timestamp_string: &str) -> PgLogstatsError {
     PgLogstatsError::TimestampParse {
         message: message.to_string(),
         timestamp_string: timestamp_string.to_string(),
+        context: LineContext::default(),
     }
 }
 
@@ -0,0 +1,182 @@
+//! Frequency of LOG, WARNING, and NOTICE messages
+//!
+//! Besides errors, a log carries messages such as `checkpoints are occurring
+//! too frequently (24 seconds apart)` or `parameter "work_mem" changed to
+//! "64MB"` that are worth counting. [`message_template`] replaces the numbers
+//! and quoted names of a message with `?`, so repeats of a message with other
+//! values count together, and [`LogEventTracker`] counts each template by
+//! severity with the first and last time it was seen.
+//!
+//! Statements, durations, and connection lines have sections of their own and
+//! are not counted, nor are the parse and bind times of prepared statements.
+
+use crate::events::{EventKind, NormalizedEvent};
+use crate::prepared::PhaseMessage;
+use crate::LogLevel;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Message templates listed by default
+pub const DEFAULT_MAX_LOG_EVENTS: usize = 20;
+
+/// A message template with how often and when it was logged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEventStats {
+    /// `LOG`, `WARNING`, `NOTICE`, `INFO`, or `DEBUG`
+    pub severity: String,
+    /// Message with numbers and quoted names replaced by `?`
+    pub template: String,
+    pub count: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl LogEventStats {
+    fn merge(&mut self, other: Self) {
+        self.count += other.count;
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+    }
+}
+
+/// Counts of the message templates of a log, per severity
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogEventTracker {
+    events: HashMap<(String, String), LogEventStats>,
+}
+
+impl LogEventTracker {
+    /// Count `event` when it is a LOG, WARNING, NOTICE, INFO, or DEBUG
+    /// message of its own
+    pub fn record(&mut self, event: &NormalizedEvent) {
+        let EventKind::Log {
+            level,
+            message,
+            connection: None,
+        } = &event.kind
+        else {
+            return;
+        };
+        if !matches!(
+            level,
+            LogLevel::Log | LogLevel::Warning | LogLevel::Notice | LogLevel::Info | LogLevel::Debug
+        ) || message.starts_with("duration: ")
+            || PhaseMessage::parse(message).is_some()
+        {
+            return;
+        }
+
+        let severity = level.to_string();
+        let template = message_template(message);
+        self.events
+            .entry((severity.clone(), template.clone()))
+            .and_modify(|stats| {
+                stats.count += 1;
+                stats.first_seen = stats.first_seen.min(event.timestamp);
+                stats.last_seen = stats.last_seen.max(event.timestamp);
+            })
+            .or_insert(LogEventStats {
+                severity,
+                template,
+                count: 1,
+                first_seen: event.timestamp,
+                last_seen: event.timestamp,
+            });
+    }
+
+    /// Add the counts of `other`
+    pub fn merge(&mut self, other: Self) {
+        for (key, theirs) in other.events {
+            match self.events.get_mut(&key) {
+                Some(stats) => stats.merge(theirs),
+                None => {
+                    self.events.insert(key, theirs);
+                }
+            }
+        }
+    }
+
+    /// The `limit` most frequent templates, most frequent first
+    pub fn into_stats(self, limit: usize) -> Vec<LogEventStats> {
+        let mut events: Vec<_> = self.events.into_values().collect();
+        events.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.severity.cmp(&b.severity))
+                .then_with(|| a.template.cmp(&b.template))
+        });
+        events.truncate(limit);
+        events
+    }
+}
+
+/// `message` with its quoted strings and names and its numbers replaced by
+/// `?`, and its whitespace collapsed.
+///
+/// A number is a token starting with a digit, such as `24`, `1.5`,
+/// `10.0.0.5`, `64MB`, or the `16B3748` of an LSN; digits inside a word, as
+/// in `pg_stat_2`, are kept.
+pub fn message_template(message: &str) -> String {
+    static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+    let (double_quoted, single_quoted, number) = PATTERNS.get_or_init(|| {
+        (
+            Regex::new(r#""(?:[^"]|"")*""#).unwrap(),
+            // Not an apostrophe inside a word
+            Regex::new(r"(^|[^\w'])'(?:[^']|'')*'").unwrap(),
+            Regex::new(r"\b\d[0-9A-Za-z_]*(?:\.[0-9A-Za-z_]+)*").unwrap(),
+        )
+    });
+    let text = double_quoted.replace_all(message, "\"?\"");
+    let text = single_quoted.replace_all(&text, "$1'?'");
+    let text = number.replace_all(&text, "?");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_numbers_and_quoted_names() {
+        let cases = [
+            (
+                "checkpoints are occurring too frequently (24 seconds apart)",
+                "checkpoints are occurring too frequently (? seconds apart)",
+            ),
+            (
+                r#"parameter "work_mem" changed to "64MB""#,
+                r#"parameter "?" changed to "?""#,
+            ),
+            (
+                r#"relation "my ""odd"" table" does not exist"#,
+                r#"relation "?" does not exist"#,
+            ),
+            (
+                "invalid input syntax for type integer: 'a''b'",
+                "invalid input syntax for type integer: '?'",
+            ),
+            (
+                "redo starts at 0/16B3748 after 1.5 s",
+                "redo starts at ?/? after ? s",
+            ),
+            (
+                "archive command failed with exit code 1 for pg_wal_2 from 10.0.0.5:5432",
+                "archive command failed with exit code ? for pg_wal_2 from ?:?",
+            ),
+            (
+                "database system was shut down at 2024-01-15 10:00:00 UTC",
+                "database system was shut down at ?-?-? ?:?:? UTC",
+            ),
+            (
+                "could not connect to the primary server:  it's down",
+                "could not connect to the primary server: it's down",
+            ),
+        ];
+        for (message, template) in cases {
+            assert_eq!(message_template(message), template, "{}", message);
+        }
+    }
+}
@@ -1,20 +1,53 @@
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
-use log::{debug, error, info, warn};
+use log::{debug, info, warn, Level};
 use pg_logstats::{
+    analytics::queries::{
+        DEFAULT_GAP_THRESHOLD_MINUTES, DEFAULT_MAX_CLIENT_HOSTS, DEFAULT_MAX_QUERY_EXAMPLES,
+        DEFAULT_TOP_K_CAPACITY, DEFAULT_UNPARSED_SAMPLE_LENGTH,
+    },
+    analytics::{BusinessHours, ReportTimezone, TimingAnalyzerConfig},
+    breach_summary, compare_reports,
+    explain::{explain_script, DEFAULT_EXPLAIN_CANDIDATES},
+    grep::{grep_matches, EntryMatcher},
     input::{
-        discover_log_files, process_cloudwatch_input, process_log_file, process_log_paths,
-        validate_file_input_args, CloudWatchInput, CloudWatchSince, CloudWatchUntil, LocalLogInput,
+        discover_local_log_files, read_cloudwatch_lines, validate_file_input_args, Charset,
+        CloudWatchInput, CloudWatchSince, CloudWatchUntil, DiscoveredLogFiles, FilePattern,
+        LocalLogInput,
     },
-    normalize_log_entries, query_family_findings, slow_query_diff_findings, Correlator,
-    EventSourceKind, Finding, FindingSet, JsonFormatter, PgLogstatsError, ProcessOrderCorrelator,
-    Result, SlowQueryDiffOptions, TextFormatter, TextLogFormat, TextLogParser,
+    output::{schema, SqliteExporter},
+    parse_timezone,
+    pg_stat_statements::{cross_reference, read_pg_stat_statements, DEFAULT_MISSING_FROM_LOGS},
+    run_stats::elapsed_ms,
+    sql::lint::{DEFAULT_MAX_IN_LIST, DEFAULT_MAX_JOINS},
+    tui, Analyzer, BatchDuration, Config, ConfigOverrides, EntryLevelFilter, EntryLimit,
+    EventSourceKind, Finding, FindingSet, GroupBy, JsonFormatter, LogEntry, LogFilter, ParseReport,
+    ParsedLog, ParserKind, PgLogstatsError, Pipeline, PoolerNoise, QueryAnalyzer, QueryLinter,
+    QuerySort, Redact, Redactor, Report, ReportSections, Result, RunProgress, RunStats,
+    SlowQueryDiffOptions, Snapshot, StatementSampling, TextFormatter, Thresholds, TimePattern,
+    TimeWindow, TimingAnalyzer, TrafficGranularity, Tz, DEFAULT_CHANGE_THRESHOLD_PERCENT,
+    DEFAULT_MAX_LOG_EVENTS, THRESHOLD_EXIT_CODE,
 };
 use serde_json::json;
 use std::fs;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Width of the rank, duration, and ID columns in front of the query column
+const QUERY_COLUMN_OFFSET: usize = 32;
+/// Narrowest query column used when sizing to the terminal
+const MIN_QUERY_WIDTH: usize = 40;
+/// Width of the bucket and count columns in front of the histogram bars
+const CHART_COLUMN_OFFSET: usize = 24;
+/// Narrowest histogram bar used when sizing to the terminal
+const MIN_CHART_WIDTH: usize = 10;
+/// Total size of local log files above which analysis streams on its own
+const STREAM_THRESHOLD_BYTES: u64 = 1 << 30;
+/// Shortest `--interval`, so follow mode never re-analyzes the log back to back
+const MIN_FOLLOW_INTERVAL_SECONDS: f64 = 1.0;
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -26,32 +59,169 @@ struct Arguments {
     #[clap(subcommand)]
     command: Command,
 
-    /// Output format for results
-    #[clap(long, global = true, value_enum, default_value = "text")]
-    output_format: OutputFormat,
+    /// Output format for results. Repeat to write several formats in one run,
+    /// which needs `--outdir`. sqlite appends a run to the output database and
+    /// needs the `sqlite` feature. ndjson appends one snapshot record per
+    /// analysis, see `summary --follow`.
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        default_value = "text",
+        action = ArgAction::Append
+    )]
+    output_format: Vec<OutputFormat>,
 
-    /// Input log format. auto supports local PostgreSQL stderr and AWS RDS logs.
-    #[clap(long, global = true, value_enum, default_value = "auto")]
+    /// Input log format. auto supports local PostgreSQL stderr and AWS RDS logs;
+    /// pgbouncer and syslog logs need `pgbouncer` or `syslog`. `--format` is an alias.
+    #[clap(
+        long,
+        visible_alias = "format",
+        global = true,
+        value_enum,
+        default_value = "auto"
+    )]
     input_format: InputFormat,
 
     /// Write results to a file. Use `-` to force stdout.
     #[clap(short = 'o', long, global = true, value_name = "PATH")]
     outfile: Option<String>,
 
-    /// Directory to prepend to `--outfile`
+    /// Directory to prepend to `--outfile`. Without `--outfile`, each format is
+    /// written to `out.txt`, `out.json`, or `out.db` in it. Created if missing.
     #[clap(short = 'O', long, global = true, value_name = "DIR")]
     outdir: Option<String>,
 
     /// Suppress progress output and the completion footer
     #[clap(short = 'q', long, global = true)]
     quiet: bool,
+
+    /// Format of the tool's own diagnostics, which always go to stderr.
+    /// json writes one object per line and turns off the progress bar.
+    #[clap(long, global = true, value_enum, default_value = "plain")]
+    log_format: LogFormat,
+
+    /// Only analyze executions of the query with this `query_id`. A prefix,
+    /// such as the ID column of the text report, also works.
+    #[clap(long, global = true, value_name = "ID")]
+    filter_query_id: Option<String>,
+
+    /// Only analyze entries of this database. Repeat to select several
+    /// databases.
+    #[clap(long, global = true, value_name = "DATABASE")]
+    filter_database: Vec<String>,
+
+    /// Only analyze entries of this user. Repeat to select several users.
+    #[clap(long, global = true, value_name = "USER")]
+    filter_user: Vec<String>,
+
+    /// Only analyze entries of the backend process with this PID. Repeat to
+    /// select several processes.
+    #[clap(long = "pid", global = true, value_name = "PID")]
+    pids: Vec<String>,
+
+    /// Only analyze entries of the session with this id, as logged by `%c`
+    /// in log_line_prefix. Repeat to select several sessions.
+    #[clap(long = "session", global = true, value_name = "SESSION_ID")]
+    sessions: Vec<String>,
+
+    /// Only analyze entries whose timestamp, formatted as
+    /// `YYYY-MM-DD HH:MM:SS` in UTC, matches this regex. Repeat to allow
+    /// several patterns.
+    #[clap(long, global = true, value_name = "REGEX", value_parser = parse_time_pattern)]
+    include_time: Vec<TimePattern>,
+
+    /// Skip entries whose formatted timestamp matches this regex, such as
+    /// '2024-04-12 .*'. Exclusions win over inclusions.
+    #[clap(long, global = true, value_name = "REGEX", value_parser = parse_time_pattern)]
+    exclude_time: Vec<TimePattern>,
+
+    /// Only analyze entries inside this daily UTC window, such as
+    /// '09:00-17:00'. Windows may span midnight; repeat to allow several.
+    #[clap(long, global = true, value_name = "HH:MM-HH:MM", value_parser = parse_time_window)]
+    include_window: Vec<TimeWindow>,
+
+    /// Skip entries inside this daily UTC window, such as '02:00-03:00'.
+    /// Exclusions win over inclusions.
+    #[clap(long, global = true, value_name = "HH:MM-HH:MM", value_parser = parse_time_window)]
+    exclude_window: Vec<TimeWindow>,
+
+    /// Drop continuation lines that could belong to more than one backend
+    /// instead of attaching them to the most recent statement
+    #[clap(long, global = true)]
+    strict_multiline: bool,
+
+    /// Keep every item of IN lists and multi-row VALUES when normalizing
+    /// queries, instead of collapsing lists of like literals to one
+    #[clap(long, global = true)]
+    no_collapse_lists: bool,
+
+    /// Print text report timestamps in this IANA timezone, such as
+    /// 'Europe/Berlin', instead of UTC. JSON keeps UTC timestamps.
+    #[clap(long, global = true, value_name = "IANA_NAME", value_parser = parse_display_timezone)]
+    display_timezone: Option<Tz>,
+
+    /// Bucket the hour-of-day and weekday stats in --display-timezone instead
+    /// of UTC, following its daylight saving time
+    #[clap(long, global = true, requires = "display_timezone")]
+    bucket_in_display_tz: bool,
+
+    /// List the example executions kept of each slowest and most frequent
+    /// query under it in text reports
+    #[clap(long, global = true)]
+    show_examples: bool,
+
+    /// Sort each file's entries by timestamp before analysis, for logs
+    /// gathered from hosts with skewed clocks. Costs a scratch buffer of one
+    /// entry per entry of the largest file.
+    #[clap(long, global = true)]
+    sort_entries: bool,
+
+    /// Character encoding of log files. Invalid UTF-8 bytes are replaced and
+    /// counted instead of aborting the run.
+    #[clap(long, global = true, value_enum, default_value = "utf-8")]
+    charset: CharsetArg,
+
+    /// Color text output. auto colors only when writing to a terminal and
+    /// NO_COLOR is unset.
+    #[clap(long, global = true, value_enum, default_value = "auto")]
+    color: ColorChoice,
+
+    /// Show queries in full in text reports instead of truncating them
+    #[clap(long, global = true, conflicts_with = "max_query_length")]
+    wide: bool,
+
+    /// Print counts without thousands separators and durations in
+    /// milliseconds in text reports, for scripts that parse them
+    #[clap(long, global = true)]
+    raw_numbers: bool,
+
+    /// Write every query type in the JSON `by_type` objects, with zeros for
+    /// types not seen, and nest any other category under `other_detail`, so
+    /// the keys of the report stay the same from log to log
+    #[clap(long, global = true)]
+    stable_schema: bool,
+
+    /// Replace email addresses, 13 to 19 digit numbers, and bearer tokens
+    /// with [REDACTED] in every output format
+    #[clap(long, global = true)]
+    redact_sensitive: bool,
+
+    /// Also replace matches of REGEX with [REDACTED]; repeatable
+    #[clap(long, global = true, value_name = "REGEX", value_parser = parse_redact_pattern)]
+    redact: Vec<String>,
+
+    /// Truncate queries in text reports to N characters. Defaults to the
+    /// terminal width, or 120 when not writing to a terminal.
+    #[clap(long, global = true, value_name = "N")]
+    max_query_length: Option<usize>,
 }
 
 #[derive(Debug, Args)]
 struct LogInputArgs {
-    /// Directory containing PostgreSQL log files
+    /// Directory containing PostgreSQL log files. Repeat to read several.
     #[clap(long, value_name = "DIR")]
-    log_dir: Option<PathBuf>,
+    log_dir: Vec<PathBuf>,
 
     /// CloudWatch Logs group to read PostgreSQL log events from
     #[clap(long, value_name = "LOG_GROUP", conflicts_with = "rds_instance")]
@@ -93,13 +263,64 @@ struct LogInputArgs {
     #[clap(long, value_name = "N")]
     sample_size: Option<usize>,
 
-    /// file containing a list of log file to parse.
+    /// Analyze a random share of the statements, such as 0.01 for 1%, with
+    /// their durations; errors and connections are all kept. Query counts and
+    /// totals are scaled up by 1/RATE and marked as estimated.
+    #[clap(long, value_name = "RATE", value_parser = parse_sample_rate)]
+    sample_rate: Option<f64>,
+
+    /// Seed of --sample-rate, to pick the same statements again; a seed from
+    /// the clock is used and reported otherwise
+    #[clap(long, value_name = "SEED", requires = "sample_rate")]
+    sample_seed: Option<u64>,
+
+    /// Analyze entries while later files are still being parsed instead of
+    /// loading every file first. Used automatically for local inputs over
+    /// 1 GiB.
+    #[clap(long)]
+    stream: bool,
+
+    /// Stop parsing after N entries across all files; the report is marked
+    /// as truncated and names the file and line reached
+    #[clap(long, value_name = "N")]
+    max_entries: Option<usize>,
+
+    /// Once parsed entries hold more than MB megabytes, also in the middle
+    /// of a file, analyze them and the rest as they are parsed, with
+    /// estimated query counts
+    #[clap(long, value_name = "MB")]
+    max_memory_mb: Option<usize>,
+
+    /// Only read files in directories whose name matches this pattern, where *
+    /// matches any characters and ? one character, such as '*.csv'. Repeat
+    /// to allow several. Defaults to .log and .txt files and files without
+    /// an extension named like postgres or pg.
+    #[clap(long, value_name = "GLOB", value_parser = parse_file_pattern)]
+    file_pattern: Vec<FilePattern>,
+
+    /// Also look for log files in the subdirectories of the directories
+    #[clap(long)]
+    recursive: bool,
+
+    /// File listing log files to parse, one per line, or - to read the list
+    /// from stdin. Entries may use * and ? globs and a leading ~, and # starts
+    /// a comment.
     #[clap(short = 'L', long, value_name = "logfile-list")]
     logfile_list: Option<String>,
 
-    /// Log files to analyze
+    /// Fail when an entry of --logfile-list matches no file instead of
+    /// warning and skipping it
+    #[clap(long, requires = "logfile_list")]
+    strict_filelist: bool,
+
+    /// Fail when a directory cannot be read instead of warning and reading
+    /// the other inputs
+    #[clap(long)]
+    strict_inputs: bool,
+
+    /// Log files to analyze; directories are read like --log-dir
     #[clap(value_name = "LOG_FILES")]
-    log_files: Vec<String>,
+    log_files: Vec<PathBuf>,
 }
 
 impl LogInputArgs {
@@ -121,13 +342,484 @@ impl LogInputArgs {
         }
     }
 
+    /// Statement sampling of `--sample-rate`
+    fn sampling(&self) -> Option<StatementSampling> {
+        let rate = self.sample_rate?;
+        let seed = self.sample_seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        });
+        StatementSampling::new(rate, seed).ok()
+    }
+
     fn local_log_input(&self) -> LocalLogInput {
         LocalLogInput {
-            log_dir: self.log_dir.clone(),
+            log_dirs: self.log_dir.clone(),
             sample_size: self.sample_size,
             logfile_list: self.logfile_list.clone(),
             log_files: self.log_files.clone(),
+            file_patterns: self.file_pattern.clone(),
+            recursive: self.recursive,
+            strict_filelist: self.strict_filelist,
+            strict_inputs: self.strict_inputs,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct SectionArgs {
+    /// Render only these report sections, comma-separated: summary, query_types,
+    /// slowest, most_frequent, errors, connections, hourly, breakdowns, applications,
+    /// clients, copy, replication, maintenance, events, prepared_statements, histogram
+    #[clap(long, value_name = "SECTION,...", value_parser = parse_report_sections)]
+    only: Option<ReportSections>,
+
+    /// Omit query totals and latency percentiles
+    #[clap(long)]
+    no_summary: bool,
+
+    /// Omit query counts by statement type
+    #[clap(long)]
+    no_query_types: bool,
+
+    /// Omit the slowest queries
+    #[clap(long)]
+    no_slowest: bool,
+
+    /// Omit the most frequent queries
+    #[clap(long)]
+    no_most_frequent: bool,
+
+    /// Omit the error count and errors by SQLSTATE
+    #[clap(long)]
+    no_errors: bool,
+
+    /// Omit the connection count
+    #[clap(long)]
+    no_connections: bool,
+
+    /// Omit hourly activity
+    #[clap(long)]
+    no_hourly: bool,
+
+    /// Omit the per-user and per-database tables
+    #[clap(long)]
+    no_breakdowns: bool,
+
+    /// Omit the per-application table
+    #[clap(long)]
+    no_applications: bool,
+
+    /// Omit the per-client-host table
+    #[clap(long)]
+    no_clients: bool,
+
+    /// Omit the COPY operations table
+    #[clap(long)]
+    no_copy: bool,
+
+    /// Omit replication and recovery events
+    #[clap(long)]
+    no_replication: bool,
+
+    /// Omit checkpoint and autovacuum activity
+    #[clap(long)]
+    no_maintenance: bool,
+
+    /// Omit the most frequent LOG, WARNING, and NOTICE messages
+    #[clap(long)]
+    no_events: bool,
+
+    /// Omit parse, bind, and execute times of prepared statements
+    #[clap(long)]
+    no_prepared_statements: bool,
+
+    /// Omit the query duration histogram
+    #[clap(long)]
+    no_histogram: bool,
+}
+
+impl SectionArgs {
+    fn report_sections(&self) -> ReportSections {
+        let mut sections = self.only.unwrap_or_default();
+        for (disabled, section) in [
+            (self.no_summary, ReportSections::SUMMARY),
+            (self.no_query_types, ReportSections::QUERY_TYPES),
+            (self.no_slowest, ReportSections::SLOWEST),
+            (self.no_most_frequent, ReportSections::MOST_FREQUENT),
+            (self.no_errors, ReportSections::ERRORS),
+            (self.no_connections, ReportSections::CONNECTIONS),
+            (self.no_hourly, ReportSections::HOURLY),
+            (self.no_breakdowns, ReportSections::BREAKDOWNS),
+            (self.no_applications, ReportSections::APPLICATIONS),
+            (self.no_clients, ReportSections::CLIENTS),
+            (self.no_copy, ReportSections::COPY),
+            (self.no_replication, ReportSections::REPLICATION),
+            (self.no_maintenance, ReportSections::MAINTENANCE),
+            (self.no_events, ReportSections::EVENTS),
+            (
+                self.no_prepared_statements,
+                ReportSections::PREPARED_STATEMENTS,
+            ),
+            (self.no_histogram, ReportSections::HISTOGRAM),
+        ] {
+            if disabled {
+                sections.remove(section);
+            }
+        }
+        sections
+    }
+}
+
+/// Message of a rejected flag value; clap's error already reads as a
+/// configuration error, so the `Configuration error:` prefix is left off
+fn value_error(e: PgLogstatsError) -> String {
+    match e {
+        PgLogstatsError::Configuration { message, .. } => message,
+        e => e.to_string(),
+    }
+}
+
+fn parse_report_sections(value: &str) -> std::result::Result<ReportSections, String> {
+    value.parse()
+}
+
+fn parse_time_pattern(value: &str) -> std::result::Result<TimePattern, String> {
+    value.parse().map_err(value_error)
+}
+
+fn parse_pooler_noise_pattern(value: &str) -> std::result::Result<String, String> {
+    PoolerNoise::new()
+        .with_pattern(value)
+        .map(|_| value.to_string())
+        .map_err(value_error)
+}
+
+fn parse_redact_pattern(value: &str) -> std::result::Result<String, String> {
+    Redactor::new()
+        .with_pattern(value)
+        .map(|_| value.to_string())
+        .map_err(value_error)
+}
+
+fn parse_grep_pattern(value: &str) -> std::result::Result<String, String> {
+    EntryMatcher::new(value, true)
+        .map(|_| value.to_string())
+        .map_err(value_error)
+}
+
+fn parse_sample_rate(value: &str) -> std::result::Result<f64, String> {
+    let rate = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    StatementSampling::new(rate, 0)
+        .map(|sampling| sampling.rate)
+        .map_err(value_error)
+}
+
+fn parse_file_pattern(value: &str) -> std::result::Result<FilePattern, String> {
+    value.parse().map_err(value_error)
+}
+
+fn parse_business_hours(value: &str) -> std::result::Result<BusinessHours, String> {
+    value.parse().map_err(value_error)
+}
+
+fn parse_report_timezone(value: &str) -> std::result::Result<ReportTimezone, String> {
+    value.parse().map_err(value_error)
+}
+
+fn parse_display_timezone(value: &str) -> std::result::Result<Tz, String> {
+    parse_timezone(value).map_err(value_error)
+}
+
+fn parse_time_window(value: &str) -> std::result::Result<TimeWindow, String> {
+    value.parse().map_err(value_error)
+}
+
+/// Analyzer settings; flags override the values in `--config`
+#[derive(Debug, Args)]
+struct AnalyzerArgs {
+    /// TOML file with analyzer settings. Flags override its values.
+    #[clap(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Queries slower than this many milliseconds count as slow [default: 1000]
+    #[clap(long, value_name = "MS")]
+    slow_query_threshold_ms: Option<f64>,
+
+    /// Number of slowest queries listed [default: 10]
+    #[clap(long, value_name = "N")]
+    max_slow_queries: Option<usize>,
+
+    /// Number of most frequent queries listed [default: 20]
+    #[clap(long, value_name = "N")]
+    max_frequent_queries: Option<usize>,
+}
+
+impl AnalyzerArgs {
+    /// Settings from the defaults, `--config`, and these flags
+    fn config(&self) -> Result<Config> {
+        Config::resolve(
+            self.config.as_deref(),
+            ConfigOverrides {
+                slow_query_threshold_ms: self.slow_query_threshold_ms,
+                max_slow_queries: self.max_slow_queries,
+                max_frequent_queries: self.max_frequent_queries,
+            },
+        )
+    }
+}
+
+/// Query lint settings
+#[derive(Debug, Args)]
+struct LintArgs {
+    /// List queries with suspicious patterns: SELECT * without LIMIT, LIKE
+    /// '%...', UPDATE or DELETE without WHERE, long IN lists, and many joins
+    #[clap(long)]
+    lint_queries: bool,
+
+    /// Flag IN lists with more than this many items
+    #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_IN_LIST)]
+    lint_max_in_list: usize,
+
+    /// Flag queries with more than this many joins
+    #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_JOINS)]
+    lint_max_joins: usize,
+}
+
+impl LintArgs {
+    /// The linter to run, when `--lint-queries` is given
+    fn linter(&self) -> Option<QueryLinter> {
+        self.lint_queries
+            .then(|| QueryLinter::with_limits(self.lint_max_in_list, self.lint_max_joins))
+    }
+}
+
+/// Connection pooler maintenance statements
+#[derive(Debug, Args)]
+struct PoolerNoiseArgs {
+    /// Leave statements connection poolers send between clients, such as
+    /// DISCARD ALL and RESET ALL, out of the query counts and lists; they are
+    /// still counted as pooler maintenance
+    #[clap(long)]
+    ignore_pooler_noise: bool,
+
+    /// Also treat normalized statements matching this regex as pooler
+    /// maintenance. Repeat to add several patterns.
+    #[clap(long, value_name = "REGEX", value_parser = parse_pooler_noise_pattern)]
+    pooler_noise_pattern: Vec<String>,
+}
+
+impl PoolerNoiseArgs {
+    /// The built-in pooler maintenance patterns and the added ones
+    fn pooler_noise(&self) -> Result<PoolerNoise> {
+        self.pooler_noise_pattern
+            .iter()
+            .try_fold(PoolerNoise::new(), |noise, pattern| {
+                noise.with_pattern(pattern)
+            })
+    }
+}
+
+/// Business hours breakdown of the timing analysis
+#[derive(Debug, Args)]
+struct BusinessHoursArgs {
+    /// Working days and hours that split activity into business hours and
+    /// off-hours, e.g. 'Mon-Fri 09:00-18:00' or 'Mon-Wed,Sat 07:30-12:00'
+    #[clap(
+        long,
+        value_name = "WINDOW",
+        default_value = "Mon-Fri 09:00-18:00",
+        value_parser = parse_business_hours
+    )]
+    business_hours: BusinessHours,
+
+    /// Timezone the business hours and weekdays are judged in: UTC, an IANA
+    /// name such as Europe/Berlin, or a fixed offset such as +02:00
+    #[clap(
+        long,
+        value_name = "TIMEZONE",
+        default_value = "UTC",
+        allow_hyphen_values = true,
+        value_parser = parse_report_timezone
+    )]
+    report_timezone: ReportTimezone,
+}
+
+/// Grep-like listing of the entries that match a pattern
+#[derive(Debug, Args)]
+struct GrepArgs {
+    /// Instead of the summary, print each entry whose message or normalized
+    /// query matches REGEX, ignoring case, as it is parsed: timestamp, PID,
+    /// user@database, duration, and the first 200 characters of its SQL.
+    /// `--output-format ndjson` writes one JSON object per entry, with the
+    /// entry's read order within its file as `seq`.
+    #[clap(long, value_name = "REGEX", value_parser = parse_grep_pattern)]
+    grep: Option<String>,
+
+    /// Match --grep with case
+    #[clap(long, requires = "grep")]
+    case_sensitive: bool,
+}
+
+/// Listing of the parsed entries
+#[derive(Debug, Args)]
+struct EntriesArgs {
+    /// Instead of the summary, print the first N parsed entries (20 without
+    /// N) with their timestamp, level, and message, to check the log prefix
+    /// was parsed as expected. A last line says how many entries were left
+    /// out. Give N as `--show-entries=N`
+    #[clap(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "20"
+    )]
+    show_entries: Option<usize>,
+
+    /// Print the last --show-entries entries instead of the first
+    #[clap(long, requires = "show_entries")]
+    entries_tail: bool,
+
+    /// Print only the --show-entries entries of this level
+    #[clap(long, value_enum, value_name = "LEVEL", requires = "show_entries")]
+    entries_level: Option<EntriesLevel>,
+}
+
+impl EntriesArgs {
+    fn limit(&self) -> Option<EntryLimit> {
+        self.show_entries.map(|count| {
+            if self.entries_tail {
+                EntryLimit::Tail(count)
+            } else {
+                EntryLimit::Head(count)
+            }
+        })
+    }
+}
+
+/// Periodic snapshots of a growing log
+#[derive(Debug, Args)]
+struct FollowArgs {
+    /// Analyze the log again every --interval seconds and append the changes
+    /// since the previous analysis as one record; needs `--output-format ndjson`
+    #[clap(long)]
+    follow: bool,
+
+    /// Seconds between --follow snapshots, at least 1
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 60.0,
+        requires = "follow"
+    )]
+    interval: f64,
+
+    /// Stop --follow after N snapshots instead of running until interrupted
+    #[clap(long, value_name = "N", requires = "follow")]
+    max_snapshots: Option<usize>,
+}
+
+impl BusinessHoursArgs {
+    fn timing_analyzer(
+        &self,
+        bucket_timezone: Option<Tz>,
+        batch_duration: BatchDuration,
+    ) -> TimingAnalyzer {
+        TimingAnalyzer::with_config(
+            TimingAnalyzerConfig::default()
+                .with_business_hours(self.business_hours)
+                .with_report_timezone(self.report_timezone)
+                .with_bucket_timezone(bucket_timezone)
+                .with_batch_duration(batch_duration),
+        )
+    }
+}
+
+/// Conditions that fail a CI run with exit code 3
+#[derive(Debug, Args)]
+struct FailOnArgs {
+    /// Exit with code 3 when there are more than N error entries
+    #[clap(long, value_name = "N")]
+    fail_on_errors: Option<u64>,
+
+    /// Exit with code 3 when more than N queries are slower than
+    /// --slow-query-threshold-ms
+    #[clap(long, value_name = "N")]
+    fail_on_slow_queries: Option<u64>,
+
+    /// Exit with code 3 when the p95 query duration is above this many
+    /// milliseconds
+    #[clap(long, value_name = "MS")]
+    fail_on_p95_ms: Option<f64>,
+}
+
+impl FailOnArgs {
+    fn thresholds(&self) -> Thresholds {
+        Thresholds::new()
+            .with_max_errors(self.fail_on_errors)
+            .with_max_slow_queries(self.fail_on_slow_queries)
+            .with_max_p95_ms(self.fail_on_p95_ms)
+    }
+}
+
+/// Baselines to compare a summary against
+#[derive(Debug, Args)]
+struct BaselineArgs {
+    /// JSON summary report saved from an earlier run. Adds a section listing
+    /// queries that changed since then.
+    #[clap(long, value_name = "PATH")]
+    compare_with: Option<PathBuf>,
+
+    /// Report queries whose count, average, or p95 duration changed by more
+    /// than this percent
+    #[clap(long, value_name = "PERCENT", default_value_t = DEFAULT_CHANGE_THRESHOLD_PERCENT)]
+    threshold_percent: f64,
+
+    /// CSV or JSON export of pg_stat_statements with queryid, query, calls,
+    /// total_exec_time, and mean_exec_time. Adds a section matching the top
+    /// queries with its rows and listing queries missing from either side.
+    #[clap(long, value_name = "FILE")]
+    pg_stat_statements: Option<PathBuf>,
+}
+
+/// EXPLAIN statements for the slowest queries
+#[derive(Debug, Args)]
+struct ExplainArgs {
+    /// Write EXPLAIN (ANALYZE, BUFFERS) of the slowest example of each of the
+    /// slowest queries to this SQL file. Data-modifying statements, CTEs
+    /// and MERGE included, are wrapped in BEGIN and ROLLBACK.
+    #[clap(long, value_name = "PATH")]
+    explain_out: Option<PathBuf>,
+
+    /// Number of the slowest queries written to --explain-out; at most the
+    /// slowest queries the analysis keeps
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_EXPLAIN_CANDIDATES,
+        requires = "explain_out"
+    )]
+    explain_candidates: usize,
+}
+
+impl ExplainArgs {
+    /// Write the EXPLAIN script of `report` to `--explain-out`, if given
+    fn write(&self, report: &Report, args: &Arguments) -> Result<()> {
+        let Some(path) = &self.explain_out else {
+            return Ok(());
+        };
+        let analysis = report.analysis.clone().unwrap_or_default();
+        let mut script = explain_script(&analysis, self.explain_candidates);
+        if let Some(redactor) = redactor(args) {
+            script = redactor.redact(&script).into_owned();
         }
+        fs::write(path, script)?;
+        info!("EXPLAIN candidates written to {}", path.display());
+        Ok(())
     }
 }
 
@@ -138,6 +830,142 @@ enum Command {
         #[clap(subcommand)]
         command: TopCommand,
     },
+    /// Summarize query volume, latency, errors, and hourly activity for one log window
+    Summary {
+        #[clap(flatten)]
+        sections: SectionArgs,
+
+        #[clap(flatten)]
+        analyzer: AnalyzerArgs,
+
+        /// List the slowest and most frequent queries per user, database, or application
+        #[clap(long, value_enum, value_name = "DIMENSION")]
+        group_by: Option<GroupDimension>,
+
+        /// Number of slowest queries listed per application
+        #[clap(long, value_name = "N", default_value_t = 3)]
+        application_queries: usize,
+
+        /// Number of client hosts listed, busiest first
+        #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_CLIENT_HOSTS)]
+        client_hosts: usize,
+
+        /// Number of LOG, WARNING, and NOTICE message templates listed, most
+        /// frequent first
+        #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_LOG_EVENTS)]
+        event_templates: usize,
+
+        /// List each client host and port separately instead of aggregating a
+        /// host's ephemeral ports
+        #[clap(long)]
+        keep_client_port: bool,
+
+        /// Warn about stretches longer than this many minutes without log entries
+        #[clap(long, value_name = "MINUTES", default_value_t = DEFAULT_GAP_THRESHOLD_MINUTES)]
+        gap_minutes: f64,
+
+        /// Estimate query counts with a bounded top-k counter from the start.
+        /// Logs with more distinct queries than the exact limit switch to it
+        /// on their own.
+        #[clap(long)]
+        low_memory: bool,
+
+        /// Number of distinct queries kept when query counts are estimated
+        #[clap(long, value_name = "N", default_value_t = DEFAULT_TOP_K_CAPACITY)]
+        top_k_capacity: usize,
+
+        /// Rank the most frequent queries by execution count, total time,
+        /// average time, or p95 time
+        #[clap(long, value_enum, value_name = "KEY", default_value = "total_time")]
+        sort_queries: QueryOrder,
+
+        /// How a multi-statement line is counted: as one query of the batch
+        /// taking its duration, or as one query per statement, each taking an
+        /// even share
+        #[clap(long, value_enum, value_name = "MODE", default_value = "batch")]
+        batch_duration: BatchAttribution,
+
+        /// Characters kept of each sample of the statements the SQL parser
+        /// rejected
+        #[clap(long, value_name = "N", default_value_t = DEFAULT_UNPARSED_SAMPLE_LENGTH)]
+        unparsed_sample_length: usize,
+
+        /// Number of the slowest executions kept per normalized query as
+        /// examples, with their literals and session
+        #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_QUERY_EXAMPLES)]
+        max_examples: usize,
+
+        /// Chart queries per minute or hour over the time range, with errors;
+        /// long ranges use wider buckets to stay within 200 rows
+        #[clap(long, value_enum, value_name = "WIDTH")]
+        histogram: Option<HistogramWidth>,
+
+        #[clap(flatten)]
+        business_hours: Box<BusinessHoursArgs>,
+
+        #[clap(flatten)]
+        follow: Box<FollowArgs>,
+
+        /// Browse the summary in an interactive terminal view instead of
+        /// writing it: queries sorted by count (c), total (t), average (a), or
+        /// p95 (p) time, with the examples of the selected query. With
+        /// --follow the view refreshes every --interval seconds. Needs a
+        /// build with the `tui` feature and a terminal on stdout
+        #[clap(long)]
+        tui: bool,
+
+        /// Instead of the summary, print the timeline of each --pid or
+        /// --session session:
+        /// connections, statements with their durations, errors, and
+        /// disconnections
+        #[clap(long)]
+        session_report: bool,
+
+        #[clap(flatten)]
+        grep: GrepArgs,
+
+        #[clap(flatten)]
+        entries: EntriesArgs,
+
+        #[clap(flatten)]
+        lint: LintArgs,
+
+        #[clap(flatten)]
+        pooler_noise: PoolerNoiseArgs,
+
+        /// Parse checkpoint and autovacuum messages as this PostgreSQL major
+        /// version writes them, instead of the version the startup banner or
+        /// the messages themselves point to
+        #[clap(long, value_name = "MAJOR", value_parser = clap::value_parser!(u32).range(9..))]
+        assume_pg_version: Option<u32>,
+
+        #[clap(flatten)]
+        baseline: BaselineArgs,
+
+        #[clap(flatten)]
+        explain: ExplainArgs,
+
+        #[clap(flatten)]
+        fail_on: Box<FailOnArgs>,
+
+        #[clap(flatten)]
+        input: Box<LogInputArgs>,
+    },
+    /// Compare two JSON summary reports: regressed, improved, new, and disappeared queries
+    Compare {
+        /// JSON summary report from before the change
+        #[clap(value_name = "BASELINE")]
+        baseline: PathBuf,
+
+        /// JSON summary report from after the change
+        #[clap(value_name = "CURRENT")]
+        current: PathBuf,
+
+        /// Report queries whose count, average, or p95 duration changed by more
+        /// than this percent
+        #[clap(long, value_name = "PERCENT", default_value_t = DEFAULT_CHANGE_THRESHOLD_PERCENT)]
+        threshold_percent: f64,
+    },
     /// Slow-query investigation workflows
     SlowQueries {
         #[clap(subcommand)]
@@ -206,10 +1034,58 @@ enum SlowQueriesCommand {
     },
 }
 
-#[derive(Debug, ValueEnum, Clone, Copy)]
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
 enum OutputFormat {
     Text,
     Json,
+    Sqlite,
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Extension of the file written in `--outdir` when there is no `--outfile`
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Sqlite => "db",
+            OutputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    /// Human-readable lines.
+    Plain,
+    /// One JSON object per line with timestamp, level, target, and message.
+    Json,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum CharsetArg {
+    /// UTF-8, replacing invalid bytes with U+FFFD.
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    /// ISO-8859-1; every byte is a character.
+    #[value(name = "latin-1", alias = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+impl CharsetArg {
+    fn charset(self) -> Charset {
+        match self {
+            Self::Utf8 => Charset::Utf8,
+            Self::Latin1 => Charset::Latin1,
+        }
+    }
 }
 
 #[derive(Debug, ValueEnum, Clone, Copy)]
@@ -217,56 +1093,313 @@ enum InputFormat {
     /// Auto-detect among supported text formats.
     Auto,
     /// Local logs using the pg-logstats supported default text prefix.
+    #[value(alias = "stderr")]
     Default,
     /// Amazon RDS logs using `%t:%r:%u@%d:[%p]:`.
     Rds,
+    /// pgbouncer logs; adds a pooler summary to the output.
+    Pgbouncer,
+    /// PostgreSQL logs delivered through syslog, with `[seq-chunk]` reassembly.
+    Syslog,
 }
 
-impl InputFormat {
-    fn text_log_format(self) -> TextLogFormat {
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum GroupDimension {
+    /// Database user.
+    User,
+    /// Database name.
+    Database,
+    /// Client `application_name`.
+    Application,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum HistogramWidth {
+    /// One row per minute.
+    Minute,
+    /// One row per hour.
+    Hour,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum QueryOrder {
+    /// Number of executions.
+    Count,
+    /// Total duration.
+    #[value(name = "total_time")]
+    TotalTime,
+    /// Average duration.
+    #[value(name = "avg_time")]
+    AvgTime,
+    /// 95th percentile duration.
+    P95,
+}
+
+impl QueryOrder {
+    fn query_sort(self) -> QuerySort {
         match self {
-            Self::Auto => TextLogFormat::Auto,
-            Self::Default => TextLogFormat::Default,
-            Self::Rds => TextLogFormat::AwsRds,
+            Self::Count => QuerySort::Count,
+            Self::TotalTime => QuerySort::TotalTime,
+            Self::AvgTime => QuerySort::AvgTime,
+            Self::P95 => QuerySort::P95,
         }
     }
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum EntriesLevel {
+    /// `ERROR`, `FATAL`, and `PANIC` entries.
+    Error,
+    /// `WARNING` entries.
+    Warning,
+    /// Statements.
+    Statement,
+}
 
-    fn event_source_kind(self) -> EventSourceKind {
+impl EntriesLevel {
+    fn level_filter(self) -> EntryLevelFilter {
         match self {
-            Self::Rds => EventSourceKind::AwsRds,
-            Self::Auto | Self::Default => EventSourceKind::Stderr,
+            Self::Error => EntryLevelFilter::Error,
+            Self::Warning => EntryLevelFilter::Warning,
+            Self::Statement => EntryLevelFilter::Statement,
         }
     }
 }
 
-fn main() -> Result<()> {
-    // Initialize logging
-    env_logger::init();
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum BatchAttribution {
+    /// The batch is one query taking the duration.
+    Batch,
+    /// Each statement takes an equal share of the duration.
+    Split,
+}
+
+impl BatchAttribution {
+    fn batch_duration(self) -> BatchDuration {
+        match self {
+            Self::Batch => BatchDuration::Batch,
+            Self::Split => BatchDuration::Split,
+        }
+    }
+}
 
-    let args = Arguments::parse();
-    let start_time = Instant::now();
+impl HistogramWidth {
+    fn granularity(self) -> TrafficGranularity {
+        match self {
+            Self::Minute => TrafficGranularity::Minute,
+            Self::Hour => TrafficGranularity::Hour,
+        }
+    }
+}
+
+impl GroupDimension {
+    fn group_by(self) -> GroupBy {
+        match self {
+            Self::User => GroupBy::User,
+            Self::Database => GroupBy::Database,
+            Self::Application => GroupBy::Application,
+        }
+    }
+}
 
-    // Validate CLI arguments
-    validate_arguments(&args)?;
+impl InputFormat {
+    fn parser_kind(self) -> ParserKind {
+        match self {
+            Self::Auto => ParserKind::Auto,
+            Self::Default => ParserKind::Stderr,
+            Self::Rds => ParserKind::AwsRds,
+            Self::Pgbouncer => ParserKind::Pgbouncer,
+            Self::Syslog => ParserKind::Syslog,
+        }
+    }
+}
 
-    // Initialize parser based on format
-    let parser = initialize_parser(&args)?;
+fn main() {
+    let args = Arguments::try_parse().unwrap_or_else(|e| exit_on_parse_error(e));
+    init_logging(args.log_format);
+    let start_time = Instant::now();
 
-    run_command(&args, &parser)?;
+    // Validate CLI arguments, then run
+    if let Err(e) = validate_arguments(&args).and_then(|()| run_command(&args)) {
+        print_diagnostic(&args, Level::Error, &error_message(&e));
+        process::exit(1);
+    }
 
     let elapsed = start_time.elapsed();
     if !args.quiet {
-        println!("Analysis completed in {:.2}s", elapsed.as_secs_f64());
+        print_diagnostic(
+            &args,
+            Level::Info,
+            &format!("Analysis completed in {:.2}s", elapsed.as_secs_f64()),
+        );
+    }
+}
+
+/// Text of `e` for the user; configuration errors also name the setting at
+/// fault
+fn error_message(e: &PgLogstatsError) -> String {
+    match e {
+        PgLogstatsError::Configuration {
+            field: Some(field), ..
+        } => format!("{} (setting: {})", e, field),
+        _ => e.to_string(),
     }
+}
 
-    Ok(())
+/// Print a command line parse error as a configuration error, like the
+/// errors of [`validate_arguments`], and exit with clap's usage error code.
+/// Help and version requests print as usual.
+fn exit_on_parse_error(e: clap::Error) -> ! {
+    use clap::error::ErrorKind;
+    if matches!(
+        e.kind(),
+        ErrorKind::DisplayHelp
+            | ErrorKind::DisplayVersion
+            | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+    ) {
+        e.exit();
+    }
+    let rendered = e.render().to_string();
+    let error = PgLogstatsError::Configuration {
+        message: rendered.trim().trim_start_matches("error: ").to_string(),
+        field: None,
+    };
+    eprintln!("Error: {}", error);
+    process::exit(e.exit_code());
+}
+
+/// Send `log` records to stderr in the `--log-format`
+fn init_logging(log_format: LogFormat) {
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.target(env_logger::Target::Stderr);
+    if log_format == LogFormat::Json {
+        builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{}",
+                diagnostic_json(record.level(), record.target(), &record.args().to_string())
+            )
+        });
+    }
+    builder.init();
+}
+
+fn diagnostic_json(level: Level, target: &str, message: &str) -> serde_json::Value {
+    json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "level": level.as_str(),
+        "target": target,
+        "message": message,
+    })
+}
+
+/// Print a message meant for the user on stderr, so stdout only ever holds
+/// results
+fn print_diagnostic(args: &Arguments, level: Level, message: &str) {
+    match args.log_format {
+        LogFormat::Plain => match level {
+            Level::Error => eprintln!("Error: {}", message),
+            Level::Warn => eprintln!("Warning: {}", message),
+            _ => eprintln!("{}", message),
+        },
+        LogFormat::Json => eprintln!(
+            "{}",
+            diagnostic_json(level, env!("CARGO_CRATE_NAME"), message)
+        ),
+    }
 }
 
-fn run_command(args: &Arguments, parser: &TextLogParser) -> Result<()> {
+fn run_command(args: &Arguments) -> Result<()> {
     match &args.command {
         Command::Top {
             command: TopCommand::QueryFamilies { limit, input },
-        } => run_top_query_families_command(args, parser, input, *limit),
+        } => run_top_query_families_command(args, input, *limit),
+        Command::Summary {
+            sections,
+            analyzer,
+            group_by,
+            application_queries,
+            client_hosts,
+            event_templates,
+            keep_client_port,
+            gap_minutes,
+            low_memory,
+            top_k_capacity,
+            sort_queries,
+            batch_duration,
+            unparsed_sample_length,
+            max_examples,
+            histogram,
+            business_hours,
+            follow,
+            tui,
+            session_report,
+            grep,
+            entries,
+            lint,
+            pooler_noise,
+            assume_pg_version,
+            baseline,
+            explain,
+            fail_on,
+            input,
+        } => {
+            if *session_report {
+                return run_session_report_command(args, input);
+            }
+            if let Some(pattern) = &grep.grep {
+                let matcher = EntryMatcher::new(pattern, grep.case_sensitive)?;
+                return run_grep_command(args, input, &matcher);
+            }
+            if entries.show_entries.is_some() {
+                return run_show_entries_command(args, input, entries);
+            }
+            let pipeline = summary_pipeline(
+                args,
+                input,
+                analyzer
+                    .config()?
+                    .query_analyzer()
+                    .with_group_by(group_by.map(GroupDimension::group_by))
+                    .with_max_application_queries(*application_queries)
+                    .with_max_client_hosts(*client_hosts)
+                    .with_max_log_events(*event_templates)
+                    .with_client_ports(*keep_client_port)
+                    .with_gap_threshold_minutes(*gap_minutes)
+                    .with_low_memory(*low_memory)
+                    .with_top_k_capacity(*top_k_capacity)
+                    .with_query_sort(sort_queries.query_sort())
+                    .with_batch_duration(batch_duration.batch_duration())
+                    .with_unparsed_sample_length(*unparsed_sample_length)
+                    .with_max_query_examples(*max_examples)
+                    .with_traffic_histogram(histogram.map(HistogramWidth::granularity))
+                    .with_bucket_timezone(bucket_timezone(args))
+                    .with_linter(lint.linter())
+                    .with_pooler_noise(pooler_noise.pooler_noise()?)
+                    .with_ignore_pooler_noise(pooler_noise.ignore_pooler_noise)
+                    .with_assume_pg_version(*assume_pg_version),
+                business_hours
+                    .timing_analyzer(bucket_timezone(args), batch_duration.batch_duration()),
+            );
+            if *tui {
+                return run_tui_command(args, input, pipeline, sort_queries.query_sort(), follow);
+            }
+            if follow.follow {
+                // A half-written last line is read whole on the next poll
+                let pipeline = pipeline.with_hold_back_unterminated(true);
+                run_follow_command(args, input, &pipeline, follow)
+            } else {
+                run_summary_command(
+                    args,
+                    input,
+                    baseline,
+                    explain,
+                    fail_on.thresholds(),
+                    sections.report_sections(),
+                    &pipeline,
+                )
+            }
+        }
         Command::SlowQueries {
             command:
                 SlowQueriesCommand::Diff {
@@ -280,7 +1413,6 @@ fn run_command(args: &Arguments, parser: &TextLogParser) -> Result<()> {
                 },
         } => run_slow_queries_diff_command(
             args,
-            parser,
             baseline,
             target,
             *sample_size,
@@ -291,6 +1423,11 @@ fn run_command(args: &Arguments, parser: &TextLogParser) -> Result<()> {
                 min_p95_delta_ms: *min_p95_delta_ms,
             },
         ),
+        Command::Compare {
+            baseline,
+            current,
+            threshold_percent,
+        } => run_compare_command(args, baseline, current, *threshold_percent),
         Command::SuggestSql {
             findings_file,
             finding_id,
@@ -299,116 +1436,616 @@ fn run_command(args: &Arguments, parser: &TextLogParser) -> Result<()> {
     }
 }
 
-fn load_default_log_entries(
+/// Parse and analyze the command's input, streaming local files when
+/// `--stream` is given or they exceed `STREAM_THRESHOLD_BYTES`; the time of
+/// each stage and the input counts go to `run_stats`
+fn analyze_default_input(
     args: &Arguments,
     input: &LogInputArgs,
-    parser: &TextLogParser,
-) -> Result<Vec<pg_logstats::LogEntry>> {
+    pipeline: &Pipeline,
+    run_stats: &mut RunStats,
+) -> Result<Report> {
     if input.uses_cloudwatch() {
-        let entries = process_cloudwatch_input(&input.cloudwatch_input(), parser)?;
-        if entries.is_empty() {
-            warn!("No CloudWatch log events were successfully parsed");
-            process::exit(1);
-        }
-
-        info!("Total CloudWatch entries parsed: {}", entries.len());
-        return Ok(entries);
+        let started = Instant::now();
+        let parsed = load_cloudwatch_entries(args, input, pipeline, run_stats)?;
+        run_stats.parse_ms += elapsed_ms(started);
+        info!("Analyzing {} parsed entries", parsed.entries.len());
+        let started = Instant::now();
+        let report = pipeline.run_parsed(parsed)?;
+        run_stats.analysis_ms += elapsed_ms(started);
+        run_stats.record_parse_report(&report.parse_report);
+        return Ok(report);
     }
 
-    // Initialize progress bar if not in quiet mode
-    let progress_bar = if !args.quiet {
-        Some(create_progress_bar())
+    let discovered = discover_input_files(args, input)?;
+    let log_files = pipeline.order_log_files(&discovered.files);
+
+    let total_bytes: u64 = log_files
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let mut report = if input.stream || total_bytes > STREAM_THRESHOLD_BYTES {
+        stream_log_files(args, &log_files, pipeline, run_stats)?
     } else {
-        None
+        analyze_log_files(args, &log_files, pipeline, run_stats)?
     };
+    report.parse_report.missing_files = discovered.missing;
+    run_stats.record_parse_report(&report.parse_report);
+    Ok(report)
+}
 
-    // Discover log files
-    let local_input = input.local_log_input();
-    let log_files = discover_log_files(&local_input)?;
+/// Analyze local log files while they are parsed
+fn stream_log_files(
+    args: &Arguments,
+    log_files: &[PathBuf],
+    pipeline: &Pipeline,
+    run_stats: &mut RunStats,
+) -> Result<Report> {
+    info!("Streaming {} log files", log_files.len());
+    let started = Instant::now();
+    let report = pipeline.run_files_streaming(log_files)?;
+    run_stats.parse_ms += elapsed_ms(started);
+    run_stats.streamed = true;
+    check_parse_report(args, &report);
+    Ok(report)
+}
 
-    if log_files.is_empty() {
-        error!("No log files found to process");
+/// Warn about the parse report of a run and exit when nothing parsed, unless
+/// the lines read are only waiting for the next poll
+fn check_parse_report(args: &Arguments, report: &Report) {
+    warn_on_skipped_lines(args, &report.parse_report);
+    let has_pooler_stats = report
+        .pooler
+        .as_ref()
+        .is_some_and(|pooler| pooler.stats_samples > 0);
+    if report.parse_report.parsed_entries == 0
+        && !has_pooler_stats
+        && !report.parse_report.has_held_back_lines()
+    {
+        warn!("No log entries were successfully parsed");
         process::exit(1);
     }
+}
 
-    info!("Found {} log files to process", log_files.len());
+fn load_cloudwatch_entries(
+    args: &Arguments,
+    input: &LogInputArgs,
+    pipeline: &Pipeline,
+    run_stats: &mut RunStats,
+) -> Result<ParsedLog> {
+    let lines = read_cloudwatch_lines(&input.cloudwatch_input())?;
+    run_stats.input_bytes = lines.iter().map(|line| line.len() as u64 + 1).sum();
+    let parsed = pipeline.parse_lines(&lines);
+    warn_on_skipped_lines(args, &parsed.parse_report);
+    if is_empty_input(&parsed) {
+        warn!("No CloudWatch log events were successfully parsed");
+        process::exit(1);
+    }
 
-    // Process log files with progress indication
-    let mut all_entries = Vec::new();
+    info!("Total CloudWatch entries parsed: {}", parsed.entries.len());
+    Ok(parsed)
+}
 
-    for (index, log_file) in log_files.iter().enumerate() {
-        if let Some(pb) = &progress_bar {
-            pb.set_message(format!("Processing {}", log_file.display()));
-            pb.set_position(index as u64);
-        }
+/// Parse every log file before analyzing them, analyzing entries as they are
+/// parsed once they pass `--max-memory-mb`
+fn analyze_log_files(
+    args: &Arguments,
+    log_files: &[PathBuf],
+    pipeline: &Pipeline,
+    run_stats: &mut RunStats,
+) -> Result<Report> {
+    // Initialize progress bar if not in quiet mode and diagnostics are plain
+    let progress_bar = if !args.quiet && args.log_format == LogFormat::Plain {
+        Some(create_progress_bar())
+    } else {
+        None
+    };
 
-        match process_log_file(log_file, parser, input.sample_size) {
-            Ok(mut entries) => {
-                info!(
-                    "Processed {} entries from {}",
-                    entries.len(),
-                    log_file.display()
-                );
-                all_entries.append(&mut entries);
+    info!("Found {} log files to process", log_files.len());
+
+    let started = Instant::now();
+    let mut analysis_started = None;
+    let report = pipeline.run_files_with_progress(log_files, |progress| match progress {
+        RunProgress::Parsing { index, path } => {
+            if let Some(pb) = &progress_bar {
+                pb.set_message(format!("Processing {}", path.display()));
+                pb.set_position(index as u64);
             }
-            Err(e) => {
-                warn!("Failed to process {}: {}", log_file.display(), e);
-                continue;
+        }
+        RunProgress::Streaming => run_stats.streamed = true,
+        RunProgress::Analyzing => {
+            if let Some(pb) = &progress_bar {
+                pb.finish_with_message("File processing complete");
             }
+            run_stats.parse_ms += elapsed_ms(started);
+            analysis_started = Some(Instant::now());
         }
+    });
+    // A streamed or failed run ends without the analysis stage
+    if let Some(pb) = progress_bar.filter(|pb| !pb.is_finished()) {
+        pb.finish_and_clear();
     }
+    let report = report?;
 
-    if let Some(pb) = &progress_bar {
-        pb.finish_with_message("File processing complete");
+    match analysis_started {
+        Some(analysis_started) => run_stats.analysis_ms += elapsed_ms(analysis_started),
+        None => run_stats.parse_ms += elapsed_ms(started),
     }
+    check_parse_report(args, &report);
+    Ok(report)
+}
 
-    if all_entries.is_empty() {
-        warn!("No log entries were successfully parsed");
-        process::exit(1);
+fn is_empty_input(parsed: &ParsedLog) -> bool {
+    parsed.entries.is_empty() && parsed.pooler_stats.is_empty()
+}
+
+fn warn_on_skipped_lines(args: &Arguments, report: &ParseReport) {
+    if let Some(summary) = report.warning_summary() {
+        warn!("{}", summary);
+        print_diagnostic(args, Level::Warn, &summary);
     }
+    for note in [
+        report.encoding_warning(),
+        report.truncation_warning(),
+        report.memory_limit_warning(),
+        report.overlap_warning(),
+        report.clock_skew_warning(),
+        report.unterminated_line_warning(),
+        report
+            .first_error()
+            .map(|error| format!("first parse error: {}", error)),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        warn!("{}", note);
+        print_diagnostic(args, Level::Warn, &note);
+    }
+}
 
-    info!("Total entries parsed: {}", all_entries.len());
-    Ok(all_entries)
+/// Log files of the local `input`, warning of the inputs that could not be
+/// read; exits naming each rejected input and why when no file is left
+fn discover_input_files(args: &Arguments, input: &LogInputArgs) -> Result<DiscoveredLogFiles> {
+    let discovered = discover_local_log_files(&input.local_log_input())?;
+    if !args.quiet {
+        for dir in &discovered.dirs_from_files {
+            print_diagnostic(
+                args,
+                Level::Info,
+                &format!("Reading directory {} as --log-dir", dir.display()),
+            );
+        }
+    }
+    for missing in &discovered.missing {
+        print_diagnostic(
+            args,
+            Level::Warn,
+            &format!("Listed log file not found: {}", missing),
+        );
+    }
+    for unreadable in &discovered.unreadable_dirs {
+        print_diagnostic(args, Level::Warn, unreadable);
+    }
+    if discovered.files.is_empty() {
+        let mut message = "No log files found to process".to_string();
+        for rejected in &discovered.rejected {
+            message.push_str(&format!("\n  {}", rejected));
+        }
+        print_diagnostic(args, Level::Error, &message);
+        process::exit(1);
+    }
+    Ok(discovered)
 }
 
 fn run_top_query_families_command(
     args: &Arguments,
-    parser: &TextLogParser,
     input: &LogInputArgs,
     limit: usize,
 ) -> Result<()> {
-    let all_entries = load_default_log_entries(args, input, parser)?;
-    let findings = run_top_query_families(&all_entries, limit, source_kind_for_input(args, input))?;
-    output_findings(&findings, args, &all_entries)
+    let pipeline = initialize_pipeline(args)
+        .with_source_kind(source_kind_for_input(args, input))
+        .with_sample_size(input.sample_size)
+        .with_max_entries(input.max_entries)
+        .with_max_memory_mb(input.max_memory_mb)
+        .with_analyzers([Analyzer::QueryFamilies { limit }]);
+
+    let report = analyze_default_input(args, input, &pipeline, &mut RunStats::new())?;
+    output_report(&report, args, ReportSections::all())
+}
+
+/// Print the timeline of the sessions selected with `--pid` or `--session`
+fn run_session_report_command(args: &Arguments, input: &LogInputArgs) -> Result<()> {
+    let pipeline = initialize_pipeline(args)
+        .with_source_kind(source_kind_for_input(args, input))
+        .with_sample_size(input.sample_size)
+        .with_max_entries(input.max_entries)
+        .with_max_memory_mb(input.max_memory_mb)
+        .with_analyzers([Analyzer::SessionTimeline]);
+
+    let report = analyze_default_input(args, input, &pipeline, &mut RunStats::new())?;
+    output_report(&report, args, ReportSections::all())
+}
+
+/// Print the entries matching `--grep`, file by file as they are parsed
+fn run_grep_command(args: &Arguments, input: &LogInputArgs, matcher: &EntryMatcher) -> Result<()> {
+    let pipeline = initialize_pipeline(args)
+        .with_source_kind(source_kind_for_input(args, input))
+        .with_sample_size(input.sample_size)
+        .with_max_entries(input.max_entries);
+    let format = output_formats(args)[0];
+    let mut out: Box<dyn Write> = match output_file(args, format) {
+        Some(path) => {
+            create_outdir(args)?;
+            Box::new(std::io::BufWriter::new(fs::File::create(path)?))
+        }
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let redactor = redactor(args);
+    let mut write_matches = |entries: Vec<LogEntry>| -> Result<()> {
+        for found in grep_matches(&pipeline.filter().apply(entries), matcher) {
+            let line = match format {
+                OutputFormat::Ndjson => serde_json::to_string(&found)?,
+                _ => found.to_line(),
+            };
+            let line = match &redactor {
+                Some(redactor) => redactor.redact(&line).into_owned(),
+                None => line,
+            };
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()?;
+        Ok(())
+    };
+
+    if input.uses_cloudwatch() {
+        let parsed = load_cloudwatch_entries(args, input, &pipeline, &mut RunStats::new())?;
+        return write_matches(parsed.entries);
+    }
+    let discovered = discover_input_files(args, input)?;
+    // Entries are written and dropped after each file; the parse report
+    // keeps counting them for --max-entries
+    let mut parsed = ParsedLog::default();
+    for path in pipeline.order_log_files(&discovered.files) {
+        pipeline.parse_file_into(&path, &mut parsed)?;
+        write_matches(std::mem::take(&mut parsed.entries))?;
+        if parsed.parse_report.truncation.is_some() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Print the first or last `--show-entries` parsed entries of the input
+fn run_show_entries_command(
+    args: &Arguments,
+    input: &LogInputArgs,
+    entries: &EntriesArgs,
+) -> Result<()> {
+    let pipeline = initialize_pipeline(args)
+        .with_source_kind(source_kind_for_input(args, input))
+        .with_sample_size(input.sample_size)
+        .with_max_entries(input.max_entries);
+    let parsed = if input.uses_cloudwatch() {
+        load_cloudwatch_entries(args, input, &pipeline, &mut RunStats::new())?
+    } else {
+        let discovered = discover_input_files(args, input)?;
+        let mut parsed = ParsedLog::default();
+        for path in pipeline.order_log_files(&discovered.files) {
+            pipeline.parse_file_into(&path, &mut parsed)?;
+            if parsed.parse_report.truncation.is_some() {
+                break;
+            }
+        }
+        warn_on_skipped_lines(args, &parsed.parse_report);
+        parsed
+    };
+
+    let formatter = text_formatter(TextFormatter::new(), args)
+        .with_limit(entries.limit())
+        .with_level_filter(entries.entries_level.map(EntriesLevel::level_filter));
+    let output = formatter.format_log_entries(&pipeline.filter().apply(parsed.entries))?;
+    write_or_print_output(output, args, OutputFormat::Text)
+}
+
+/// Pipeline of the summary command and its `--follow` snapshots
+fn summary_pipeline(
+    args: &Arguments,
+    input: &LogInputArgs,
+    query_analyzer: QueryAnalyzer,
+    timing_analyzer: TimingAnalyzer,
+) -> Pipeline {
+    initialize_pipeline(args)
+        .with_source_kind(source_kind_for_input(args, input))
+        .with_sample_size(input.sample_size)
+        .with_sampling(input.sampling())
+        .with_max_entries(input.max_entries)
+        .with_max_memory_mb(input.max_memory_mb)
+        .with_analyzers([Analyzer::Queries, Analyzer::Timing])
+        .with_query_analyzer(query_analyzer)
+        .with_timing_analyzer(timing_analyzer)
+}
+
+fn run_summary_command(
+    args: &Arguments,
+    input: &LogInputArgs,
+    baseline: &BaselineArgs,
+    explain: &ExplainArgs,
+    thresholds: Thresholds,
+    sections: ReportSections,
+    pipeline: &Pipeline,
+) -> Result<()> {
+    let mut run_stats = RunStats::new();
+    let mut report = analyze_default_input(args, input, pipeline, &mut run_stats)?;
+    let output_started = Instant::now();
+    if let Some(path) = &baseline.compare_with {
+        let current = schema::Report::from_json(&report.to_json()?)?;
+        report.comparison = Some(compare_reports(
+            &load_report_file(path)?,
+            &current,
+            baseline.threshold_percent,
+        ));
+    }
+    if let (Some(path), Some(analysis)) = (&baseline.pg_stat_statements, &report.analysis) {
+        let statements = read_pg_stat_statements(path)?;
+        report.pg_stat_statements = Some(cross_reference(
+            analysis,
+            &statements,
+            DEFAULT_MISSING_FROM_LOGS,
+        ));
+    }
+    for format in output_formats(args) {
+        match format {
+            OutputFormat::Sqlite => export_sqlite(&report, args)?,
+            OutputFormat::Ndjson => write_snapshot(
+                &Snapshot::between(
+                    None,
+                    &report.analysis.clone().unwrap_or_default(),
+                    chrono::Utc::now(),
+                ),
+                args,
+            )?,
+            format => {
+                run_stats.output_ms = elapsed_ms(output_started);
+                run_stats.sample_peak_rss();
+                write_report(&report, args, sections, format, Some(&run_stats))?
+            }
+        }
+    }
+    explain.write(&report, args)?;
+
+    // Reports are written first so CI can keep them when the run fails
+    if let Some(analysis) = &report.analysis {
+        let breaches = thresholds.check(analysis);
+        if !breaches.is_empty() {
+            print_diagnostic(args, Level::Error, &breach_summary(&breaches));
+            process::exit(THRESHOLD_EXIT_CODE);
+        }
+    }
+    Ok(())
+}
+
+/// Analyze the input every `--interval` seconds and write the changes since
+/// the previous analysis as one NDJSON record each time
+fn run_follow_command(
+    args: &Arguments,
+    input: &LogInputArgs,
+    pipeline: &Pipeline,
+    follow: &FollowArgs,
+) -> Result<()> {
+    let mut previous = None;
+    let mut snapshots = 0;
+    loop {
+        let current = analyze_default_input(args, input, pipeline, &mut RunStats::new())?
+            .analysis
+            .unwrap_or_default();
+        write_snapshot(
+            &Snapshot::between(previous.as_ref(), &current, chrono::Utc::now()),
+            args,
+        )?;
+        previous = Some(current);
+        snapshots += 1;
+        if follow.max_snapshots.is_some_and(|max| snapshots >= max) {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs_f64(follow.interval));
+    }
+}
+
+/// Browse the analysis in the terminal, analyzing the input again every
+/// `--interval` seconds with `--follow`
+fn run_tui_command(
+    args: &Arguments,
+    input: &LogInputArgs,
+    pipeline: Pipeline,
+    sort: QuerySort,
+    follow: &FollowArgs,
+) -> Result<()> {
+    let pipeline = pipeline.with_hold_back_unterminated(follow.follow);
+    let refresh = follow
+        .follow
+        .then(|| Duration::from_secs_f64(follow.interval));
+    tui::run(sort, redactor(args), refresh, || {
+        Ok(
+            analyze_default_input(args, input, &pipeline, &mut RunStats::new())?
+                .analysis
+                .unwrap_or_default(),
+        )
+    })
+}
+
+/// Append `snapshot` to the `--outfile`, or print it
+fn write_snapshot(snapshot: &Snapshot, args: &Arguments) -> Result<()> {
+    match output_file(args, OutputFormat::Ndjson) {
+        Some(path) => {
+            create_outdir(args)?;
+            snapshot.append_to_with(&path, redactor(args).as_deref())
+        }
+        None => {
+            let line = snapshot.to_ndjson_line_with(redactor(args).as_deref())?;
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(line.as_bytes())?;
+            stdout.flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// Append the report's analysis as a new run of the `--outfile` database,
+/// recording the files the run parsed
+fn export_sqlite(report: &Report, args: &Arguments) -> Result<()> {
+    let files = report
+        .parse_report
+        .files
+        .iter()
+        .map(|file| file.file.clone())
+        .collect();
+    let path = sqlite_path(args)?;
+    create_outdir(args)?;
+    let run_id = SqliteExporter::new()
+        .with_redactor(redactor(args))
+        .with_metadata(files, report.total_entries)
+        .export(&path, &report.analysis.clone().unwrap_or_default())?;
+    info!("Run {} appended to {}", run_id, path.display());
+    Ok(())
+}
+
+fn run_compare_command(
+    args: &Arguments,
+    baseline: &Path,
+    current: &Path,
+    threshold_percent: f64,
+) -> Result<()> {
+    let comparison = compare_reports(
+        &load_report_file(baseline)?,
+        &load_report_file(current)?,
+        threshold_percent,
+    );
+
+    for format in output_formats(args) {
+        let output = match format {
+            OutputFormat::Json => JsonFormatter::new()
+                .with_pretty(true)
+                .with_redactor(redactor(args))
+                .with_metadata(
+                    env!("CARGO_PKG_VERSION"),
+                    vec![
+                        baseline.display().to_string(),
+                        current.display().to_string(),
+                    ],
+                    0,
+                )
+                .format_comparison(&comparison)?,
+            OutputFormat::Text => {
+                text_formatter(TextFormatter::new(), args).format_comparison(&comparison)?
+            }
+            OutputFormat::Sqlite | OutputFormat::Ndjson => return Err(summary_only(format)),
+        };
+        write_or_print_output(output, args, format)?;
+    }
+    Ok(())
+}
+
+/// Read a JSON summary report written with `--output-format json`
+fn load_report_file(path: &Path) -> Result<schema::Report> {
+    let content = fs::read_to_string(path)?;
+    let report =
+        schema::Report::from_json(&content).map_err(|e| PgLogstatsError::Configuration {
+            message: format!("{} is not a JSON summary report: {}", path.display(), e),
+            field: Some("report".to_string()),
+        })?;
+    if report.query_analysis.is_none() {
+        return Err(PgLogstatsError::Configuration {
+            message: format!(
+                "{} has no query_analysis; save it with `summary --output-format json`",
+                path.display()
+            ),
+            field: Some("report".to_string()),
+        });
+    }
+    Ok(report)
 }
 
 fn run_slow_queries_diff_command(
     args: &Arguments,
-    parser: &TextLogParser,
     baseline: &Path,
     target: &Path,
     sample_size: Option<usize>,
     options: SlowQueryDiffOptions,
 ) -> Result<()> {
-    let (findings, total_entries) = run_slow_queries_diff(
-        baseline,
-        target,
-        parser,
-        sample_size,
-        options,
-        args.input_format.event_source_kind(),
-    )?;
-    output_findings_with_entry_count(&findings, args, total_entries)
+    let report = initialize_pipeline(args)
+        .with_sample_size(sample_size)
+        .run_diff(baseline, target, options)?;
+    warn_on_skipped_lines(args, &report.parse_report);
+    output_report(&report, args, ReportSections::all())
 }
 
 fn validate_arguments(args: &Arguments) -> Result<()> {
+    validate_flag_conflicts(args)?;
     match &args.command {
         Command::Top {
             command: TopCommand::QueryFamilies { input, .. },
         } => validate_log_input_args(input)?,
+        Command::Summary {
+            input,
+            gap_minutes,
+            top_k_capacity,
+            baseline,
+            fail_on,
+            follow,
+            tui,
+            ..
+        } => {
+            validate_log_input_args(input)?;
+            if !(gap_minutes.is_finite() && *gap_minutes > 0.0) {
+                return Err(PgLogstatsError::Configuration {
+                    message: "Gap minutes must be greater than 0".to_string(),
+                    field: Some("gap_minutes".to_string()),
+                });
+            }
+            if *top_k_capacity == 0 {
+                return Err(PgLogstatsError::Configuration {
+                    message: "Top-k capacity must be greater than 0".to_string(),
+                    field: Some("top_k_capacity".to_string()),
+                });
+            }
+            if let Some(path) = &baseline.compare_with {
+                if !path.is_file() {
+                    return Err(PgLogstatsError::Configuration {
+                        message: format!("Baseline report does not exist: {}", path.display()),
+                        field: Some("compare_with".to_string()),
+                    });
+                }
+            }
+            validate_threshold_percent(baseline.threshold_percent)?;
+            if let Some(p95_ms) = fail_on.fail_on_p95_ms {
+                if !(p95_ms.is_finite() && p95_ms >= 0.0) {
+                    return Err(PgLogstatsError::Configuration {
+                        message: "--fail-on-p95-ms must be 0 or greater".to_string(),
+                        field: Some("fail_on_p95_ms".to_string()),
+                    });
+                }
+            }
+            validate_follow_args(args, follow, *tui)?;
+        }
         Command::SlowQueries {
             command: SlowQueriesCommand::Diff { sample_size, .. },
         } => validate_sample_size(*sample_size)?,
+        Command::Compare {
+            baseline,
+            current,
+            threshold_percent,
+        } => {
+            for path in [baseline, current] {
+                if !path.is_file() {
+                    return Err(PgLogstatsError::Configuration {
+                        message: format!("Report file does not exist: {}", path.display()),
+                        field: Some("report".to_string()),
+                    });
+                }
+            }
+            validate_threshold_percent(*threshold_percent)?;
+        }
         Command::SuggestSql {
             findings_file,
             finding_id,
@@ -416,6 +2053,96 @@ fn validate_arguments(args: &Arguments) -> Result<()> {
         } => validate_suggest_sql_args(findings_file, finding_id.as_deref(), *rank)?,
     }
 
+    let formats = output_formats(args);
+    if formats.len() > 1 {
+        if args.outfile.is_some() {
+            return Err(PgLogstatsError::Configuration {
+                message:
+                    "--outfile names a single file; use --outdir to write several output formats"
+                        .to_string(),
+                field: Some("outfile".to_string()),
+            });
+        }
+        if args.outdir.is_none() {
+            return Err(PgLogstatsError::Configuration {
+                message: "Writing several output formats needs --outdir".to_string(),
+                field: Some("outdir".to_string()),
+            });
+        }
+    }
+    if formats.contains(&OutputFormat::Sqlite) {
+        if !matches!(args.command, Command::Summary { .. }) {
+            return Err(summary_only(OutputFormat::Sqlite));
+        }
+        sqlite_path(args)?;
+    }
+    if formats.contains(&OutputFormat::Ndjson) && !matches!(args.command, Command::Summary { .. }) {
+        return Err(summary_only(OutputFormat::Ndjson));
+    }
+
+    if let Some(query_id) = &args.filter_query_id {
+        validate_query_id(query_id)?;
+    }
+    if let Command::Summary {
+        session_report: true,
+        ..
+    } = &args.command
+    {
+        if args.pids.is_empty() && args.sessions.is_empty() {
+            return Err(PgLogstatsError::Configuration {
+                message: "--session-report needs at least one --pid or --session".to_string(),
+                field: Some("pid".to_string()),
+            });
+        }
+        if formats.contains(&OutputFormat::Sqlite) || formats.contains(&OutputFormat::Ndjson) {
+            return Err(PgLogstatsError::Configuration {
+                message: "--session-report supports only text and json output".to_string(),
+                field: Some("output_format".to_string()),
+            });
+        }
+    }
+    if let Command::Summary {
+        grep: GrepArgs { grep: Some(_), .. },
+        ..
+    } = &args.command
+    {
+        if !matches!(formats[..], [OutputFormat::Text] | [OutputFormat::Ndjson]) {
+            return Err(PgLogstatsError::Configuration {
+                message: "--grep writes one text or ndjson output".to_string(),
+                field: Some("output_format".to_string()),
+            });
+        }
+    }
+    if let Command::Summary {
+        entries:
+            EntriesArgs {
+                show_entries: Some(show_entries),
+                ..
+            },
+        ..
+    } = &args.command
+    {
+        if *show_entries == 0 {
+            return Err(PgLogstatsError::Configuration {
+                message: "Show entries must be greater than 0".to_string(),
+                field: Some("show_entries".to_string()),
+            });
+        }
+        if formats != [OutputFormat::Text] {
+            return Err(PgLogstatsError::Configuration {
+                message: "--show-entries writes only text output".to_string(),
+                field: Some("output_format".to_string()),
+            });
+        }
+    }
+
+    if args.max_query_length == Some(0) {
+        return Err(PgLogstatsError::Configuration {
+            message: "Max query length must be greater than 0".to_string(),
+            field: Some("max_query_length".to_string()),
+        });
+    }
+
     // Validate output directory if specified
     if let Some(outdir) = &args.outdir {
         let outdir_path = Path::new(outdir);
@@ -433,18 +2160,202 @@ fn validate_arguments(args: &Arguments) -> Result<()> {
     Ok(())
 }
 
+/// Reject pairs of flags clap accepts together although one of them would be
+/// ignored, naming both flags and how to resolve the conflict
+fn validate_flag_conflicts(args: &Arguments) -> Result<()> {
+    let outfile = args.outfile.as_deref();
+    let mut conflicts = vec![
+        (
+            args.outdir.is_some() && outfile == Some("-"),
+            "--outfile -",
+            "--outdir",
+            "standard output has no directory; drop --outdir",
+        ),
+        (
+            args.outdir.is_some() && outfile.is_some_and(|path| Path::new(path).is_absolute()),
+            "--outfile",
+            "--outdir",
+            "--outdir is prepended to --outfile, which is an absolute path; give a relative \
+             --outfile or drop --outdir",
+        ),
+    ];
+    if let Command::Summary {
+        follow,
+        tui,
+        session_report,
+        grep,
+        entries,
+        baseline,
+        explain,
+        fail_on,
+        input,
+        ..
+    } = &args.command
+    {
+        let follow = follow.follow;
+        let grep = grep.grep.is_some();
+        let show_entries = entries.show_entries.is_some();
+        conflicts.extend([
+            (
+                grep && show_entries,
+                "--grep",
+                "--show-entries",
+                "both replace the summary; run them separately",
+            ),
+            (
+                show_entries && *session_report,
+                "--show-entries",
+                "--session-report",
+                "both replace the summary; run them separately",
+            ),
+            (
+                show_entries && *tui,
+                "--show-entries",
+                "--tui",
+                "both replace the summary; run them separately",
+            ),
+            (
+                show_entries && follow,
+                "--show-entries",
+                "--follow",
+                "--show-entries lists the entries once; drop --follow",
+            ),
+            (
+                show_entries && baseline.compare_with.is_some(),
+                "--show-entries",
+                "--compare-with",
+                "--show-entries writes no report to compare; drop --compare-with",
+            ),
+            (
+                grep && *session_report,
+                "--grep",
+                "--session-report",
+                "both replace the summary; run them separately",
+            ),
+            (
+                grep && follow,
+                "--grep",
+                "--follow",
+                "--grep lists the entries once; drop --follow",
+            ),
+            (
+                grep && *tui,
+                "--grep",
+                "--tui",
+                "both replace the summary; run them separately",
+            ),
+            (
+                *session_report && *tui,
+                "--session-report",
+                "--tui",
+                "both replace the summary; run them separately",
+            ),
+            (
+                *tui && baseline.compare_with.is_some(),
+                "--compare-with",
+                "--tui",
+                "the view shows no comparison; write a report to compare instead",
+            ),
+            (
+                grep && baseline.compare_with.is_some(),
+                "--grep",
+                "--compare-with",
+                "--grep writes no report to compare; drop --compare-with",
+            ),
+            (
+                grep && explain.explain_out.is_some(),
+                "--grep",
+                "--explain-out",
+                "--grep does not analyze the queries; drop --explain-out",
+            ),
+            (
+                grep && input.sample_rate.is_some(),
+                "--sample-rate",
+                "--grep",
+                "--grep lists every matching entry; drop --sample-rate",
+            ),
+            (
+                *session_report && input.sample_rate.is_some(),
+                "--sample-rate",
+                "--session-report",
+                "session timelines show every statement of the session; drop --sample-rate",
+            ),
+            (
+                follow && input.sample_size.is_some(),
+                "--sample-size",
+                "--follow",
+                "--follow analyzes every line appended to the log; drop --sample-size",
+            ),
+            (
+                follow && *session_report,
+                "--session-report",
+                "--follow",
+                "session timelines are printed once; drop --follow",
+            ),
+            (
+                follow && baseline.compare_with.is_some(),
+                "--compare-with",
+                "--follow",
+                "snapshots are not compared with a baseline; run pg-logstats compare on saved \
+                 reports instead",
+            ),
+        ]);
+        for (set, flag) in [
+            (fail_on.fail_on_errors.is_some(), "--fail-on-errors"),
+            (
+                fail_on.fail_on_slow_queries.is_some(),
+                "--fail-on-slow-queries",
+            ),
+            (fail_on.fail_on_p95_ms.is_some(), "--fail-on-p95-ms"),
+        ] {
+            conflicts.push((
+                follow && set,
+                flag,
+                "--follow",
+                "thresholds are checked only by a single summary run; drop --follow",
+            ));
+        }
+    }
+
+    match conflicts.into_iter().find(|(conflicting, ..)| *conflicting) {
+        Some((_, first, second, hint)) => Err(PgLogstatsError::Configuration {
+            message: format!("{} cannot be used with {}: {}", first, second, hint),
+            field: Some(
+                first
+                    .trim_start_matches("--")
+                    .trim_end_matches(" -")
+                    .replace('-', "_"),
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
 fn validate_log_input_args(input: &LogInputArgs) -> Result<()> {
     if input.uses_cloudwatch() {
         validate_cloudwatch_input_args(input)?;
-        return validate_sample_size(input.sample_size);
+    } else {
+        validate_file_input_args(&input.local_log_input())?;
     }
 
-    validate_file_input_args(&input.local_log_input())?;
-    validate_sample_size(input.sample_size)
+    validate_sample_size(input.sample_size)?;
+    for (value, field, name) in [
+        (input.max_entries, "max_entries", "Max entries"),
+        (input.max_memory_mb, "max_memory_mb", "Max memory"),
+    ] {
+        if value == Some(0) {
+            return Err(PgLogstatsError::Configuration {
+                message: format!("{} must be greater than 0", name),
+                field: Some(field.to_string()),
+            });
+        }
+    }
+
+    Ok(())
 }
 
 fn validate_cloudwatch_input_args(input: &LogInputArgs) -> Result<()> {
-    if input.log_dir.is_some() || input.logfile_list.is_some() || !input.log_files.is_empty() {
+    if !input.log_dir.is_empty() || input.logfile_list.is_some() || !input.log_files.is_empty() {
         return Err(PgLogstatsError::Configuration {
             message: "CloudWatch input cannot be combined with local log files".to_string(),
             field: Some("cloudwatch_input".to_string()),
@@ -454,6 +2365,59 @@ fn validate_cloudwatch_input_args(input: &LogInputArgs) -> Result<()> {
     pg_logstats::input::cloudwatch::validate_cloudwatch_input_args(&input.cloudwatch_input())
 }
 
+fn validate_query_id(query_id: &str) -> Result<()> {
+    if query_id.is_empty()
+        || query_id.len() > 16
+        || !query_id.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Err(PgLogstatsError::Configuration {
+            message: format!(
+                "Invalid query id '{}': expected up to 16 hex digits as shown in query_id",
+                query_id
+            ),
+            field: Some("filter_query_id".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_follow_args(args: &Arguments, follow: &FollowArgs, tui: bool) -> Result<()> {
+    if !follow.follow {
+        return Ok(());
+    }
+    if !tui && output_formats(args) != [OutputFormat::Ndjson] {
+        return Err(PgLogstatsError::Configuration {
+            message: "--follow needs --output-format ndjson".to_string(),
+            field: Some("follow".to_string()),
+        });
+    }
+    if !(follow.interval.is_finite() && follow.interval >= MIN_FOLLOW_INTERVAL_SECONDS) {
+        return Err(PgLogstatsError::Configuration {
+            message: format!("--interval must be at least {MIN_FOLLOW_INTERVAL_SECONDS} second"),
+            field: Some("interval".to_string()),
+        });
+    }
+    if follow.max_snapshots == Some(0) {
+        return Err(PgLogstatsError::Configuration {
+            message: "--max-snapshots must be greater than 0".to_string(),
+            field: Some("max_snapshots".to_string()),
+        });
+    }
+    Ok(())
+}
+
+fn validate_threshold_percent(threshold_percent: f64) -> Result<()> {
+    if !(threshold_percent.is_finite() && threshold_percent >= 0.0) {
+        return Err(PgLogstatsError::Configuration {
+            message: "Threshold percent must be a non-negative number".to_string(),
+            field: Some("threshold_percent".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
 fn validate_sample_size(sample_size: Option<usize>) -> Result<()> {
     if let Some(sample_size) = sample_size {
         if sample_size == 0 {
@@ -503,11 +2467,47 @@ fn validate_suggest_sql_args(
     Ok(())
 }
 
-fn initialize_parser(args: &Arguments) -> Result<TextLogParser> {
-    debug!("Initializing text log parser for {:?}", args.input_format);
-    Ok(TextLogParser::with_format(
-        args.input_format.text_log_format(),
-    ))
+fn initialize_pipeline(args: &Arguments) -> Pipeline {
+    debug!(
+        "Initializing text log parser for {:?}",
+        args.input_format.parser_kind()
+    );
+    let mut filter = LogFilter::new();
+    if let Some(query_id) = &args.filter_query_id {
+        filter = filter.with_query_id(query_id);
+    }
+    for database in &args.filter_database {
+        filter = filter.with_database(database);
+    }
+    for user in &args.filter_user {
+        filter = filter.with_user(user);
+    }
+    for pid in &args.pids {
+        filter = filter.with_process_id(pid);
+    }
+    for session in &args.sessions {
+        filter = filter.with_session_id(session);
+    }
+    for pattern in &args.include_time {
+        filter = filter.with_include_time(pattern.clone());
+    }
+    for pattern in &args.exclude_time {
+        filter = filter.with_exclude_time(pattern.clone());
+    }
+    for window in &args.include_window {
+        filter = filter.with_include_window(*window);
+    }
+    for window in &args.exclude_window {
+        filter = filter.with_exclude_window(*window);
+    }
+
+    Pipeline::new()
+        .with_parser(args.input_format.parser_kind())
+        .with_filters(filter)
+        .with_strict_multiline(args.strict_multiline)
+        .with_collapse_lists(!args.no_collapse_lists)
+        .with_charset(args.charset.charset())
+        .with_sort_entries(args.sort_entries)
 }
 
 fn source_kind_for_input(args: &Arguments, input: &LogInputArgs) -> EventSourceKind {
@@ -515,50 +2515,7 @@ fn source_kind_for_input(args: &Arguments, input: &LogInputArgs) -> EventSourceK
         return EventSourceKind::AwsRds;
     }
 
-    args.input_format.event_source_kind()
-}
-
-fn run_top_query_families(
-    entries: &[pg_logstats::LogEntry],
-    limit: usize,
-    source_kind: EventSourceKind,
-) -> Result<pg_logstats::FindingSet> {
-    info!(
-        "Building top query-family findings from {} entries",
-        entries.len()
-    );
-    let events = normalize_log_entries(entries, source_kind);
-    let executions = ProcessOrderCorrelator.correlate(&events);
-
-    Ok(query_family_findings(&executions, limit))
-}
-
-fn run_slow_queries_diff(
-    baseline: &Path,
-    target: &Path,
-    parser: &TextLogParser,
-    sample_size: Option<usize>,
-    options: SlowQueryDiffOptions,
-    source_kind: EventSourceKind,
-) -> Result<(pg_logstats::FindingSet, usize)> {
-    info!(
-        "Building slow-query diff findings from baseline {} and target {}",
-        baseline.display(),
-        target.display()
-    );
-
-    let baseline_entries = process_log_paths(baseline, parser, sample_size)?;
-    let target_entries = process_log_paths(target, parser, sample_size)?;
-
-    let baseline_events = normalize_log_entries(&baseline_entries, source_kind);
-    let target_events = normalize_log_entries(&target_entries, source_kind);
-    let baseline_executions = ProcessOrderCorrelator.correlate(&baseline_events);
-    let target_executions = ProcessOrderCorrelator.correlate(&target_events);
-
-    let findings = slow_query_diff_findings(&baseline_executions, &target_executions, options);
-    let total_entries = baseline_entries.len() + target_entries.len();
-
-    Ok((findings, total_entries))
+    args.input_format.parser_kind().event_source_kind()
 }
 
 fn run_suggest_sql_command(
@@ -619,17 +2576,26 @@ fn select_finding<'a>(
 }
 
 fn output_suggested_sql(args: &Arguments, finding: &Finding) -> Result<()> {
-    match args.output_format {
+    for format in output_formats(args) {
+        write_suggested_sql(args, finding, format)?;
+    }
+    Ok(())
+}
+
+fn write_suggested_sql(args: &Arguments, finding: &Finding, format: OutputFormat) -> Result<()> {
+    match format {
         OutputFormat::Json => {
-            let output = serde_json::to_string_pretty(&json!({
-                "finding_id": finding.finding_id,
-                "rank": finding.rank,
-                "kind": finding.kind,
-                "title": finding.title,
-                "next_sql": finding.next_sql,
-            }))
-            .map_err(PgLogstatsError::Serialization)?;
-            write_or_print_output(output, args)
+            let output = JsonFormatter::new()
+                .with_pretty(true)
+                .with_redactor(redactor(args))
+                .render(&json!({
+                    "finding_id": finding.finding_id,
+                    "rank": finding.rank,
+                    "kind": finding.kind,
+                    "title": finding.title,
+                    "next_sql": finding.next_sql,
+                }))?;
+            write_or_print_output(output, args, format)
         }
         OutputFormat::Text => {
             let mut output = String::new();
@@ -641,60 +2607,199 @@ fn output_suggested_sql(args: &Arguments, finding: &Finding) -> Result<()> {
                 output.push_str(statement);
                 output.push('\n');
             }
-            write_or_print_output(output, args)
+            if let Some(redactor) = redactor(args) {
+                output = redactor.redact(&output).into_owned();
+            }
+            write_or_print_output(output, args, format)
         }
+        OutputFormat::Sqlite | OutputFormat::Ndjson => Err(summary_only(format)),
     }
 }
 
-fn output_findings(
-    findings: &pg_logstats::FindingSet,
-    args: &Arguments,
-    entries: &[pg_logstats::LogEntry],
-) -> Result<()> {
-    output_findings_with_entry_count(findings, args, entries.len())
+fn output_report(report: &Report, args: &Arguments, sections: ReportSections) -> Result<()> {
+    for format in output_formats(args) {
+        write_report(report, args, sections, format, None)?;
+    }
+    Ok(())
 }
 
-fn output_findings_with_entry_count(
-    findings: &pg_logstats::FindingSet,
+/// Write `report` in one output format, ending it with `run_stats` if given
+fn write_report(
+    report: &Report,
     args: &Arguments,
-    total_log_entries: usize,
+    sections: ReportSections,
+    format: OutputFormat,
+    run_stats: Option<&RunStats>,
 ) -> Result<()> {
-    match args.output_format {
+    let output = match format {
         OutputFormat::Json => {
-            let formatter = JsonFormatter::new().with_pretty(true).with_metadata(
-                env!("CARGO_PKG_VERSION"),
-                vec![],
-                total_log_entries,
-            );
-
-            let output = formatter.format_findings(findings)?;
-            write_or_print_output(output, args)?;
+            let mut formatter = report
+                .json_formatter()
+                .with_sections(sections)
+                .with_redactor(redactor(args))
+                .with_stable_schema(args.stable_schema);
+            if let Some(run_stats) = run_stats {
+                formatter = formatter.with_run_stats(run_stats.clone());
+            }
+            report.format_json(&formatter)?
         }
         OutputFormat::Text => {
-            let formatter = TextFormatter::new();
-            let output = formatter.format_findings(findings)?;
-            write_or_print_output(output, args)?;
+            let mut formatter =
+                text_formatter(report.text_formatter(), args).with_sections(sections);
+            if let Some(run_stats) = run_stats {
+                formatter = formatter.with_run_stats(run_stats.clone());
+            }
+            report.format_text(&formatter)?
         }
+        OutputFormat::Sqlite | OutputFormat::Ndjson => return Err(summary_only(format)),
+    };
+
+    write_or_print_output(output, args, format)
+}
+
+/// The redactor asked for with --redact-sensitive and --redact, if any
+fn redactor(args: &Arguments) -> Option<Arc<dyn Redact>> {
+    let base = if args.redact_sensitive {
+        Redactor::sensitive()
+    } else {
+        Redactor::new()
+    };
+    let redactor = args
+        .redact
+        .iter()
+        .try_fold(base, |redactor, pattern| redactor.with_pattern(pattern))
+        .expect("--redact patterns are validated when parsed");
+    (!redactor.is_empty()).then(|| Arc::new(redactor) as Arc<dyn Redact>)
+}
+
+/// The requested output formats, each once, in the order given
+fn output_formats(args: &Arguments) -> Vec<OutputFormat> {
+    let mut formats = Vec::new();
+    for format in &args.output_format {
+        if !formats.contains(format) {
+            formats.push(*format);
+        }
+    }
+    formats
+}
+
+/// `formatter` with the color and widths the output target calls for
+fn text_formatter(formatter: TextFormatter, args: &Arguments) -> TextFormatter {
+    let mut formatter = formatter
+        .with_color(color_enabled(args))
+        .with_human_units(!args.raw_numbers)
+        .with_display_timezone(args.display_timezone)
+        .with_show_examples(args.show_examples)
+        .with_redactor(redactor(args));
+    let columns = terminal_columns(args);
+    if let Some(columns) = columns {
+        formatter = formatter.with_chart_width(
+            columns
+                .saturating_sub(CHART_COLUMN_OFFSET)
+                .max(MIN_CHART_WIDTH),
+        );
+    }
+    if args.wide {
+        return formatter.with_wide(true);
+    }
+
+    let terminal_query_width = columns.map(|columns| {
+        columns
+            .saturating_sub(QUERY_COLUMN_OFFSET)
+            .max(MIN_QUERY_WIDTH)
+    });
+    match args.max_query_length.or(terminal_query_width) {
+        Some(max_query_length) => formatter.with_max_query_length(max_query_length),
+        None => formatter,
+    }
+}
+
+/// Error for commands other than `summary` run with `--output-format sqlite`
+/// or `ndjson`
+fn summary_only(format: OutputFormat) -> PgLogstatsError {
+    let name = match format {
+        OutputFormat::Ndjson => "NDJSON",
+        _ => "SQLite",
+    };
+    PgLogstatsError::Configuration {
+        message: format!("{} output is only supported by the summary command", name),
+        field: Some("output_format".to_string()),
+    }
+}
+
+/// Database file of `--output-format sqlite`
+fn sqlite_path(args: &Arguments) -> Result<PathBuf> {
+    output_file(args, OutputFormat::Sqlite).ok_or_else(|| PgLogstatsError::Configuration {
+        message: "SQLite output needs a database path in --outfile or --outdir".to_string(),
+        field: Some("outfile".to_string()),
+    })
+}
+
+/// Whether results go to stdout rather than a file
+fn writes_to_stdout(args: &Arguments) -> bool {
+    match args.outfile.as_deref() {
+        Some(outfile) => outfile == "-",
+        None => args.outdir.is_none(),
     }
+}
+
+/// Timezone of the hour-of-day buckets; `None` for UTC
+fn bucket_timezone(args: &Arguments) -> Option<Tz> {
+    args.display_timezone.filter(|_| args.bucket_in_display_tz)
+}
+
+fn color_enabled(args: &Arguments) -> bool {
+    match args.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            !no_color && writes_to_stdout(args) && std::io::stdout().is_terminal()
+        }
+    }
+}
+
+/// Width of the terminal, when writing to one
+fn terminal_columns(args: &Arguments) -> Option<usize> {
+    if !writes_to_stdout(args) {
+        return None;
+    }
+    let (_, columns) = console::Term::stdout().size_checked()?;
+    Some(columns as usize)
+}
 
+/// File that `format` output is written to; `None` for stdout.
+///
+/// `--outfile` is taken inside `--outdir` when both are given. With only
+/// `--outdir`, each format gets its own `out.<extension>` file.
+fn output_file(args: &Arguments, format: OutputFormat) -> Option<PathBuf> {
+    let outdir = args.outdir.as_deref().map(Path::new);
+    match args.outfile.as_deref() {
+        Some("-") => None,
+        Some(outfile) => Some(match outdir {
+            Some(outdir) => outdir.join(outfile),
+            None => PathBuf::from(outfile),
+        }),
+        None => outdir.map(|outdir| outdir.join(format!("out.{}", format.extension()))),
+    }
+}
+
+/// Create `--outdir` when it does not exist yet
+fn create_outdir(args: &Arguments) -> Result<()> {
+    if let Some(outdir) = &args.outdir {
+        fs::create_dir_all(outdir)?;
+    }
     Ok(())
 }
 
-fn write_or_print_output(output: String, args: &Arguments) -> Result<()> {
-    if let Some(outfile) = &args.outfile {
-        if outfile == "-" {
-            println!("{}", output);
-        } else {
-            let output_path = if let Some(outdir) = &args.outdir {
-                Path::new(outdir).join(outfile)
-            } else {
-                PathBuf::from(outfile)
-            };
+fn write_or_print_output(output: String, args: &Arguments, format: OutputFormat) -> Result<()> {
+    match output_file(args, format) {
+        Some(output_path) => {
+            create_outdir(args)?;
             fs::write(&output_path, output)?;
             info!("Results written to {}", output_path.display());
         }
-    } else {
-        println!("{}", output);
+        None => println!("{}", output),
     }
 
     Ok(())
@@ -0,0 +1,360 @@
+//! Checkpoint and autovacuum activity
+//!
+//! Every checkpoint completion and autovacuum message is counted. Their
+//! figures are added up only when the message has the shape of the log's
+//! PostgreSQL version (see [`crate::pg_version`]), so a log of an unknown
+//! version, or with the detail lines of autovacuum dropped, still gets counts.
+//!
+//! Messages warning of a misconfiguration, such as `checkpoints are occurring
+//! too frequently (24 seconds apart)`, are counted by the [`HINT_RULES`] they
+//! match. A rule whose messages were logged more often than its threshold
+//! gives a [`TuningHint`].
+
+use crate::pg_version::{is_autovacuum_message, is_checkpoint_message, PgVersion};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Messages that hint at a setting to tune, and the advice to give once they
+/// were logged often enough
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HintRule {
+    /// Key of the rule's counts in [`MaintenanceStats::warnings`]
+    pub id: &'static str,
+    /// What the matched messages report, e.g. `checkpoints too frequent`
+    pub label: &'static str,
+    /// Pattern of the messages; its `value` group is the figure, in seconds,
+    /// whose minimum and average are reported
+    pub pattern: &'static str,
+    /// Messages whose value is smaller are not counted
+    pub min_value: Option<f64>,
+    /// The hint is given once more messages than this were counted
+    pub threshold: u64,
+    pub message: &'static str,
+}
+
+/// Rules checked against every message; add one here to give a new hint
+pub const HINT_RULES: [HintRule; 2] = [
+    HintRule {
+        id: "checkpoints_too_frequent",
+        label: "checkpoints too frequent",
+        pattern: r"^checkpoints are occurring too frequently \((?P<value>\d+) seconds? apart\)",
+        min_value: None,
+        threshold: 3,
+        message: "consider raising max_wal_size",
+    },
+    HintRule {
+        id: "slow_checkpoint_sync",
+        label: "slow checkpoint syncs",
+        pattern: r"^(?:checkpoint|restartpoint) complete: .*sync files=\d+, longest=(?P<value>[\d.]+) s",
+        min_value: Some(10.0),
+        threshold: 3,
+        message: "consider checking storage latency and raising checkpoint_completion_target",
+    },
+];
+
+/// Messages a [`HintRule`] matched, with the range of their values
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarningStats {
+    pub count: u64,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub total_value: f64,
+}
+
+impl WarningStats {
+    fn record(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min_value = value;
+            self.max_value = value;
+        } else {
+            self.min_value = self.min_value.min(value);
+            self.max_value = self.max_value.max(value);
+        }
+        self.count += 1;
+        self.total_value += value;
+    }
+
+    fn merge(&mut self, other: Self) {
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+        if other.count == 0 {
+            return;
+        }
+        self.count += other.count;
+        self.min_value = self.min_value.min(other.min_value);
+        self.max_value = self.max_value.max(other.max_value);
+        self.total_value += other.total_value;
+    }
+
+    /// Mean of the values, or 0 without messages
+    pub fn average_value(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_value / self.count as f64
+        }
+    }
+}
+
+/// Advice of a [`HintRule`] whose threshold was exceeded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuningHint {
+    /// [`HintRule::id`]
+    pub rule: String,
+    pub label: String,
+    pub occurrences: u64,
+    pub threshold: u64,
+    pub min_value: f64,
+    pub avg_value: f64,
+    /// Average WAL written between the checkpoints whose figures were read,
+    /// in kB, when there were any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_checkpoint_distance_kb: Option<f64>,
+    pub message: String,
+}
+
+/// Checkpoints and restart points of a log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointStats {
+    pub count: u64,
+    /// Checkpoints whose figures were read; the rest are only counted
+    pub with_details: u64,
+    pub buffers_written: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    /// WAL written between checkpoints, in kB
+    pub distance_kb: u64,
+}
+
+/// Automatic vacuums of a log
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VacuumStats {
+    pub count: u64,
+    /// Vacuums whose figures were read; the rest are only counted
+    pub with_details: u64,
+    pub pages_removed: u64,
+    pub tuples_removed: u64,
+}
+
+/// Checkpoint and autovacuum activity of a log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceStats {
+    pub checkpoints: CheckpointStats,
+    pub autovacuums: VacuumStats,
+    /// Messages matched by each of the [`HINT_RULES`], by rule id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub warnings: BTreeMap<String, WarningStats>,
+}
+
+impl MaintenanceStats {
+    /// Count `message` when it reports a checkpoint or an autovacuum, adding
+    /// its figures when it has the shape of `version`
+    pub fn record(&mut self, message: &str, version: Option<PgVersion>) {
+        self.record_warning(message);
+        let patterns = version.map(|version| version.patterns());
+        if is_checkpoint_message(message) {
+            let checkpoints = &mut self.checkpoints;
+            checkpoints.count += 1;
+            if let Some(details) = patterns.and_then(|p| p.parse_checkpoint(message)) {
+                checkpoints.with_details += 1;
+                checkpoints.buffers_written += details.buffers_written;
+                checkpoints.total_ms += details.total_ms;
+                checkpoints.max_ms = checkpoints.max_ms.max(details.total_ms);
+                checkpoints.distance_kb += details.distance_kb;
+            }
+        } else if is_autovacuum_message(message) {
+            let autovacuums = &mut self.autovacuums;
+            autovacuums.count += 1;
+            if let Some(details) = patterns.and_then(|p| p.parse_vacuum(message)) {
+                autovacuums.with_details += 1;
+                autovacuums.pages_removed += details.pages_removed;
+                autovacuums.tuples_removed += details.tuples_removed;
+            }
+        }
+    }
+
+    /// Count `message` for the first of the [`HINT_RULES`] it matches
+    fn record_warning(&mut self, message: &str) {
+        static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+        let patterns = PATTERNS.get_or_init(|| {
+            HINT_RULES
+                .iter()
+                .map(|rule| Regex::new(rule.pattern).expect("valid hint rule pattern"))
+                .collect()
+        });
+        for (rule, pattern) in HINT_RULES.iter().zip(patterns) {
+            let Some(value) = pattern
+                .captures(message)
+                .and_then(|captures| captures["value"].parse::<f64>().ok())
+            else {
+                continue;
+            };
+            if rule.min_value.is_none_or(|min| value >= min) {
+                self.warnings
+                    .entry(rule.id.to_string())
+                    .or_default()
+                    .record(value);
+            }
+            return;
+        }
+    }
+
+    /// Hints of the [`HINT_RULES`] that matched more messages than their
+    /// threshold, in rule order
+    pub fn hints(&self) -> Vec<TuningHint> {
+        let checkpoints = &self.checkpoints;
+        let avg_checkpoint_distance_kb = (checkpoints.with_details > 0)
+            .then(|| checkpoints.distance_kb as f64 / checkpoints.with_details as f64);
+        HINT_RULES
+            .iter()
+            .filter_map(|rule| {
+                let stats = self.warnings.get(rule.id)?;
+                (stats.count > rule.threshold).then(|| TuningHint {
+                    rule: rule.id.to_string(),
+                    label: rule.label.to_string(),
+                    occurrences: stats.count,
+                    threshold: rule.threshold,
+                    min_value: stats.min_value,
+                    avg_value: stats.average_value(),
+                    avg_checkpoint_distance_kb,
+                    message: rule.message.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Add the activity of `other`
+    pub fn merge(&mut self, other: Self) {
+        let (ours, theirs) = (&mut self.checkpoints, other.checkpoints);
+        ours.count += theirs.count;
+        ours.with_details += theirs.with_details;
+        ours.buffers_written += theirs.buffers_written;
+        ours.total_ms += theirs.total_ms;
+        ours.max_ms = ours.max_ms.max(theirs.max_ms);
+        ours.distance_kb += theirs.distance_kb;
+
+        let (ours, theirs) = (&mut self.autovacuums, other.autovacuums);
+        ours.count += theirs.count;
+        ours.with_details += theirs.with_details;
+        ours.pages_removed += theirs.pages_removed;
+        ours.tuples_removed += theirs.tuples_removed;
+
+        for (rule, theirs) in other.warnings {
+            self.warnings.entry(rule).or_default().merge(theirs);
+        }
+    }
+
+    /// Whether the log had no checkpoint or autovacuum messages
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.count == 0 && self.autovacuums.count == 0 && self.warnings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn too_frequent(seconds: u64) -> String {
+        format!(
+            "checkpoints are occurring too frequently ({} seconds apart)",
+            seconds
+        )
+    }
+
+    fn checkpoint(longest_sync_s: f64) -> String {
+        format!(
+            "checkpoint complete: wrote 1024 buffers (6.3%); 0 WAL file(s) added, 0 removed, \
+             4 recycled; write=26.821 s, sync={0:.3} s, total=40.012 s; sync files=12, \
+             longest={0:.3} s, average=1.010 s; distance=65536 kB, estimate=65536 kB",
+            longest_sync_s
+        )
+    }
+
+    #[test]
+    fn test_frequent_checkpoint_warnings_give_a_hint_past_the_threshold() {
+        let mut stats = MaintenanceStats::default();
+        for seconds in [12, 30, 18] {
+            stats.record(&too_frequent(seconds), None);
+        }
+        let warnings = &stats.warnings["checkpoints_too_frequent"];
+        assert_eq!(warnings.count, 3);
+        assert_eq!(warnings.min_value, 12.0);
+        assert_eq!(warnings.average_value(), 20.0);
+        // Three warnings are not more than the threshold
+        assert!(stats.hints().is_empty());
+
+        stats.record(&too_frequent(20), None);
+        let hints = stats.hints();
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].rule, "checkpoints_too_frequent");
+        assert_eq!(hints[0].occurrences, 4);
+        assert_eq!(hints[0].min_value, 12.0);
+        assert_eq!(hints[0].avg_value, 20.0);
+        assert_eq!(hints[0].message, "consider raising max_wal_size");
+        assert_eq!(hints[0].avg_checkpoint_distance_kb, None);
+    }
+
+    #[test]
+    fn test_hints_carry_the_checkpoint_distance() {
+        let version = Some(PgVersion::assumed(14));
+        let mut stats = MaintenanceStats::default();
+        for seconds in [10, 11, 12, 13] {
+            stats.record(&too_frequent(seconds), version);
+        }
+        stats.record(&checkpoint(0.5), version);
+
+        assert_eq!(stats.checkpoints.with_details, 1);
+        let hints = stats.hints();
+        assert_eq!(hints[0].avg_checkpoint_distance_kb, Some(65536.0));
+    }
+
+    #[test]
+    fn test_only_slow_checkpoint_syncs_count() {
+        let version = Some(PgVersion::assumed(14));
+        let mut stats = MaintenanceStats::default();
+        for longest in [0.5, 2.0, 9.9, 0.1, 1.0] {
+            stats.record(&checkpoint(longest), version);
+        }
+        assert_eq!(stats.checkpoints.count, 5);
+        assert!(!stats.warnings.contains_key("slow_checkpoint_sync"));
+
+        for longest in [12.0, 15.5, 30.0, 10.0] {
+            stats.record(&checkpoint(longest), version);
+        }
+        let hints = stats.hints();
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].rule, "slow_checkpoint_sync");
+        assert_eq!(hints[0].occurrences, 4);
+        assert_eq!(hints[0].min_value, 10.0);
+    }
+
+    #[test]
+    fn test_merge_adds_warnings() {
+        let mut first = MaintenanceStats::default();
+        first.record(&too_frequent(40), None);
+        first.record(&too_frequent(20), None);
+        let mut second = MaintenanceStats::default();
+        second.record(&too_frequent(8), None);
+        second.record(&too_frequent(12), None);
+
+        first.merge(second);
+        let warnings = &first.warnings["checkpoints_too_frequent"];
+        assert_eq!(warnings.count, 4);
+        assert_eq!(warnings.min_value, 8.0);
+        assert_eq!(warnings.max_value, 40.0);
+        assert_eq!(first.hints().len(), 1);
+        assert!(!first.is_empty());
+    }
+
+    #[test]
+    fn test_hint_rule_patterns_compile() {
+        for rule in HINT_RULES {
+            let pattern = Regex::new(rule.pattern).unwrap();
+            assert!(pattern.capture_names().any(|name| name == Some("value")));
+        }
+    }
+}
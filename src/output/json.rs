@@ -1,9 +1,205 @@
 //! JSON output formatter for pg-logstats results
 
-use crate::{AnalysisResult, FindingSet, PgLogstatsError, Result, TimingAnalysis};
-use chrono::Utc;
+use super::schema::{
+    ApplicationEntry, ApplicationQueryEntry, Breakdowns, BusinessHoursSection, CanceledQueryEntry,
+    CancellationHourEntry, CancellationsSection, ClientEntry, CopySection, CopyTableEntry,
+    DailyEntry, FrequentQueryEntry, HistogramBucket, HourSeriesEntry, HourlyEntry,
+    QueryAnalysisSection, QueryExampleEntry, QueryGroupEntry, QueryTypeEntry, QueryWarningEntry,
+    ReplicationSection, Report, ReportMetadata, SlowQueryEntry, Summary, TemporalSection,
+    TrafficBucketEntry, UnparsedStatementsEntry, WeekdayEntry, REPORT_SCHEMA_VERSION,
+};
+use super::{Redact, ReportSections};
+use crate::compare::slowest_change;
+use crate::{
+    query_id, AnalysisResult, FindingSet, ParseReport, PgLogstatsError, QuerySort, QueryStats,
+    QueryType, QueryTypeStats, ReportComparison, Result, RunStats, SessionEvent, SlowestRankChange,
+    TimingAnalysis,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Example rows of a query with `stats`, without their durations unless
+/// `durations_logged`
+fn example_entries(stats: Option<&QueryStats>, durations_logged: bool) -> Vec<QueryExampleEntry> {
+    stats
+        .into_iter()
+        .flat_map(|stats| &stats.examples)
+        .map(|example| QueryExampleEntry {
+            sql: example.sql.clone(),
+            timestamp: example.timestamp,
+            duration_ms: durations_logged.then_some(example.duration),
+            user: example.user.clone(),
+            database: example.database.clone(),
+            parameters: example.parameters.clone(),
+        })
+        .collect()
+}
+
+/// Share of `total` taken by `part`, in percent with two decimals
+fn percent_of(part: f64, total: f64) -> Option<f64> {
+    (total > 0.0).then(|| (part / total * 10_000.0).round() / 100.0)
+}
+
+/// Slowest-query rows, with counts, durations, their share of
+/// `total_duration`, and examples from `query_stats` when it has the query,
+/// else counts from the matching frequency list; `changes` against a
+/// baseline fill in the rank and p95 deltas
+fn slowest_query_entries(
+    slowest: &[(String, f64)],
+    most_frequent: &[(String, u64)],
+    query_stats: &HashMap<String, QueryStats>,
+    total_duration: f64,
+    changes: &[SlowestRankChange],
+) -> Vec<SlowQueryEntry> {
+    let freq_map: HashMap<&str, u64> = most_frequent
+        .iter()
+        .map(|(q, c)| (q.as_str(), *c))
+        .collect();
+    slowest
+        .iter()
+        .enumerate()
+        .map(|(i, (q, d))| {
+            let stats = query_stats.get(q);
+            let id = query_id(q);
+            let change = slowest_change(changes, i + 1, &id);
+            SlowQueryEntry {
+                query: q.clone(),
+                query_id: id,
+                duration_ms: *d,
+                count: stats.map_or_else(
+                    || freq_map.get(q.as_str()).cloned().unwrap_or(1),
+                    |stats| stats.count,
+                ),
+                p95_duration_ms: stats.map(|stats| stats.p95_duration),
+                total_duration_ms: stats.map(|stats| stats.total_duration),
+                pct_of_total_time: stats
+                    .and_then(|stats| percent_of(stats.total_duration, total_duration)),
+                rank_delta: change.and_then(|change| change.rank_delta),
+                p95_delta_ms: change.and_then(|change| change.p95_delta_ms),
+                examples: example_entries(stats, true),
+            }
+        })
+        .collect()
+}
+
+/// Most-frequent-query rows ordered by `sort`, with durations, their share
+/// of `total_duration`, and examples from `query_stats` when it has the query.
+/// `average_duration` is `None` when statements were logged without
+/// durations, which leaves out every duration.
+fn most_frequent_entries(
+    most_frequent: &[(String, u64)],
+    query_stats: &HashMap<String, QueryStats>,
+    average_duration: Option<f64>,
+    total_duration: f64,
+    sort: QuerySort,
+) -> Vec<FrequentQueryEntry> {
+    let mut rows = most_frequent.to_vec();
+    sort.sort_rows(&mut rows, query_stats);
+    rows.into_iter()
+        .map(|(q, c)| {
+            let stats = query_stats.get(&q);
+            let timed = stats.filter(|_| average_duration.is_some());
+            FrequentQueryEntry {
+                query_id: query_id(&q),
+                query: q,
+                count: c,
+                // Without per-query duration distribution, fall back to the average
+                avg_duration_ms: average_duration
+                    .map(|average| timed.map_or(average, |stats| stats.average_duration)),
+                p95_duration_ms: timed.map(|stats| stats.p95_duration),
+                total_duration_ms: timed.map(|stats| stats.total_duration),
+                percent_of_total_duration: timed
+                    .and_then(|stats| percent_of(stats.total_duration, total_duration)),
+                examples: example_entries(stats, average_duration.is_some()),
+            }
+        })
+        .collect()
+}
+
+/// Copy of a map with its keys in order
+fn sorted<V: Clone>(map: &HashMap<String, V>) -> BTreeMap<String, V> {
+    map.iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// `by_type` value of a query type; without `durations_logged`, only the
+/// count
+fn query_type_entry(stats: &QueryTypeStats, durations_logged: bool) -> QueryTypeEntry {
+    let timed = |duration: f64| durations_logged.then_some(duration);
+    QueryTypeEntry {
+        count: stats.count,
+        total_ms: timed(stats.total_duration),
+        avg_ms: timed(stats.average_duration),
+        p95_ms: timed(stats.p95_duration),
+        max_ms: timed(stats.max_duration),
+    }
+}
+
+/// `by_type` with a key for every [`QueryType`], and the keys of
+/// `query_types` that are none of them. Those are added to `OTHER`, whose
+/// p95 becomes the highest of the p95s added.
+fn stable_query_type_entries(
+    query_types: &HashMap<String, QueryTypeStats>,
+    durations_logged: bool,
+) -> (
+    BTreeMap<String, QueryTypeEntry>,
+    BTreeMap<String, QueryTypeEntry>,
+) {
+    let mut by_type: BTreeMap<String, QueryTypeEntry> = QueryType::ALL
+        .iter()
+        .map(|query_type| {
+            let stats = query_types.get(&query_type.to_string());
+            let entry = query_type_entry(
+                stats.unwrap_or(&QueryTypeStats::default()),
+                durations_logged,
+            );
+            (query_type.to_string(), entry)
+        })
+        .collect();
+    let other_detail: BTreeMap<String, QueryTypeEntry> = query_types
+        .iter()
+        .filter(|(key, _)| !by_type.contains_key(*key))
+        .map(|(key, stats)| (key.clone(), query_type_entry(stats, durations_logged)))
+        .collect();
+
+    let other = by_type
+        .get_mut(&QueryType::Other.to_string())
+        .expect("every query type has a key");
+    for entry in other_detail.values() {
+        other.count += entry.count;
+        other.total_ms = other.total_ms.zip(entry.total_ms).map(|(a, b)| a + b);
+        other.p95_ms = other.p95_ms.zip(entry.p95_ms).map(|(a, b)| a.max(b));
+        other.max_ms = other.max_ms.zip(entry.max_ms).map(|(a, b)| a.max(b));
+    }
+    if other.count > 0 {
+        other.avg_ms = other.total_ms.map(|total| total / other.count as f64);
+    }
+    (by_type, other_detail)
+}
+
+/// Weekday names by days from Monday
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Mean of `total` over `count`, 0 when there is nothing to average
+fn average(total: Option<f64>, count: Option<u64>) -> f64 {
+    match (total, count) {
+        (Some(total), Some(count)) if count > 0 => total / count as f64,
+        _ => 0.0,
+    }
+}
 
 /// JSON formatter for analysis results
 pub struct JsonFormatter {
@@ -12,6 +208,15 @@ pub struct JsonFormatter {
     tool_version: String,
     log_files_processed: Vec<String>,
     total_log_entries: usize,
+    parse_report: Option<ParseReport>,
+    sections: ReportSections,
+    analysis_timestamp: Option<DateTime<Utc>>,
+    query_sort: QuerySort,
+    hour_timezone: Option<Tz>,
+    redactor: Option<Arc<dyn Redact>>,
+    run_stats: Option<RunStats>,
+    stable_schema: bool,
+    slowest_changes: Vec<SlowestRankChange>,
 }
 
 impl JsonFormatter {
@@ -22,6 +227,15 @@ impl JsonFormatter {
             tool_version: env!("CARGO_PKG_VERSION").to_string(),
             log_files_processed: Vec::new(),
             total_log_entries: 0,
+            parse_report: None,
+            sections: ReportSections::all(),
+            analysis_timestamp: None,
+            query_sort: QuerySort::default(),
+            hour_timezone: None,
+            redactor: None,
+            run_stats: None,
+            stable_schema: false,
+            slowest_changes: Vec::new(),
         }
     }
 
@@ -44,6 +258,85 @@ impl JsonFormatter {
         self
     }
 
+    /// Attach parse diagnostics to the output metadata
+    pub fn with_parse_report(mut self, parse_report: ParseReport) -> Self {
+        self.parse_report = Some(parse_report);
+        self
+    }
+
+    /// Record `timezone` as the one the hour-of-day stats were bucketed in;
+    /// `None` for UTC
+    pub fn with_hour_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.hour_timezone = timezone;
+        self
+    }
+
+    /// Emit only the given report sections; disabled sections' keys are omitted
+    pub fn with_sections(mut self, sections: ReportSections) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Stamp reports with `timestamp` instead of the current time
+    pub fn with_analysis_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.analysis_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Order the most frequent queries by `sort`
+    pub fn with_query_sort(mut self, sort: QuerySort) -> Self {
+        self.query_sort = sort;
+        self
+    }
+
+    /// Redact every string of the output with `redactor`; `None` writes
+    /// values as they are
+    pub fn with_redactor(mut self, redactor: Option<Arc<dyn Redact>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Get the redactor applied to the output
+    pub fn redactor(&self) -> Option<&dyn Redact> {
+        self.redactor.as_deref()
+    }
+
+    /// Record the stage times and input counts of the run in the metadata
+    pub fn with_run_stats(mut self, run_stats: RunStats) -> Self {
+        self.run_stats = Some(run_stats);
+        self
+    }
+
+    /// Get the run stats written to the metadata
+    pub fn run_stats(&self) -> Option<&RunStats> {
+        self.run_stats.as_ref()
+    }
+
+    /// Write every query type in `by_type`, with zeros for the ones not
+    /// seen, and move keys that are not a query type to `other_detail`, so
+    /// the keys of the report do not depend on the log
+    pub fn with_stable_schema(mut self, stable_schema: bool) -> Self {
+        self.stable_schema = stable_schema;
+        self
+    }
+
+    /// Annotate the slowest queries with their rank and p95 changes against
+    /// a baseline report
+    pub fn with_slowest_changes(mut self, changes: Vec<SlowestRankChange>) -> Self {
+        self.slowest_changes = changes;
+        self
+    }
+
+    /// Get whether the query type keys are fixed
+    pub fn is_stable_schema(&self) -> bool {
+        self.stable_schema
+    }
+
+    /// Get the report sections that will be emitted
+    pub fn sections(&self) -> ReportSections {
+        self.sections
+    }
+
     /// Get whether pretty printing is enabled
     pub fn is_pretty(&self) -> bool {
         self.pretty
@@ -64,75 +357,489 @@ impl JsonFormatter {
         self.total_log_entries
     }
 
+    /// Get the attached parse diagnostics
+    pub fn parse_report(&self) -> Option<&ParseReport> {
+        self.parse_report.as_ref()
+    }
+
     /// Get metadata object (made public for testing)
     pub fn metadata_object(&self) -> serde_json::Value {
-        json!({
-            "analysis_timestamp": Utc::now().to_rfc3339(),
-            "tool_version": self.tool_version,
-            "log_files_processed": self.log_files_processed,
-            "total_log_entries": self.total_log_entries,
-        })
+        json!(self.metadata())
     }
 
-    /// Format a single AnalysisResult as structured JSON
-    pub fn format(&self, analysis: &AnalysisResult) -> Result<String> {
-        let summary = json!({
-            "total_queries": analysis.total_queries,
-            "total_duration_ms": analysis.total_duration,
-            "avg_duration_ms": analysis.average_duration,
-            "error_count": analysis.error_count,
-            "connection_count": analysis.connection_count,
-        });
+    /// Metadata of a report written now
+    pub fn metadata(&self) -> ReportMetadata {
+        ReportMetadata {
+            schema_version: REPORT_SCHEMA_VERSION,
+            analysis_timestamp: self
+                .analysis_timestamp
+                .unwrap_or_else(Utc::now)
+                .to_rfc3339(),
+            tool_version: self.tool_version.clone(),
+            log_files_processed: self.log_files_processed.clone(),
+            total_log_entries: self.total_log_entries,
+            parse_report: self.parse_report.clone(),
+            run_stats: self.run_stats.clone(),
+            approximate_query_counts: None,
+            estimated_from_sample: None,
+            pg_version: None,
+            hour_timezone: self
+                .hour_timezone
+                .map(|timezone| timezone.name().to_string()),
+            truncated: self
+                .parse_report
+                .as_ref()
+                .is_some_and(|report| report.truncation.is_some()),
+            notes: self
+                .parse_report
+                .as_ref()
+                .map(|report| {
+                    [
+                        report.clock_skew_warning(),
+                        report.unterminated_line_warning(),
+                    ]
+                })
+                .into_iter()
+                .flatten()
+                .flatten()
+                .collect(),
+        }
+    }
 
-        let by_type =
-            serde_json::to_value(&analysis.query_types).map_err(PgLogstatsError::Serialization)?;
+    /// Build the report for `analysis`, and `timing` when given, with the
+    /// enabled sections
+    pub fn report(&self, analysis: &AnalysisResult, timing: Option<&TimingAnalysis>) -> Report {
+        let durations_logged = analysis.durations_logged();
+        let timed = |duration: f64| Some(durations_logged.then_some(duration));
+        let mut summary = Summary::default();
+        if self.sections.contains(ReportSections::SUMMARY) {
+            summary.total_queries = Some(analysis.total_queries);
+            summary.pooler_maintenance_count = Some(analysis.pooler_maintenance_count);
+            summary.total_duration_ms = timed(analysis.total_duration);
+            summary.avg_duration_ms = timed(analysis.average_duration);
+            summary.stddev_duration_ms = timed(analysis.stddev_duration);
+            summary.min_duration_ms = timed(analysis.min_duration);
+            summary.p95_duration_ms = timed(analysis.p95_duration);
+            summary.p99_duration_ms = timed(analysis.p99_duration);
+            summary.max_duration_ms = timed(analysis.max_duration);
+            summary.time_range = Some(analysis.time_range.clone());
+            let io = &analysis.io;
+            let span_seconds = analysis
+                .time_range
+                .as_ref()
+                .map_or(0.0, |range| range.span_seconds);
+            if io.temp_files > 0 {
+                summary.temp_files = Some(io.temp_files);
+                summary.temp_bytes = Some(io.temp_bytes);
+                summary.temp_bytes_per_hour = io.temp_bytes_per_hour(span_seconds);
+            }
+            if io.checkpoints > 0 {
+                summary.checkpoint_buffers_written = Some(io.checkpoint_buffers);
+                summary.checkpoint_mb_written = Some(io.checkpoint_mb());
+                summary.checkpoint_mb_per_hour = io.checkpoint_mb_per_hour(span_seconds);
+            }
+        }
+        if self.sections.contains(ReportSections::ERRORS) {
+            summary.error_count = Some(analysis.error_count);
+        }
+        if self.sections.contains(ReportSections::CONNECTIONS) {
+            summary.connection_count = Some(analysis.connection_count);
+        }
 
-        // Build a map from query -> count to enrich slowest queries
-        let mut freq_map: HashMap<String, u64> = HashMap::new();
-        for (q, c) in &analysis.most_frequent_queries {
-            freq_map.insert(q.clone(), *c);
+        let mut query_analysis = QueryAnalysisSection::default();
+        if self.sections.contains(ReportSections::QUERY_TYPES) {
+            let by_type = if self.stable_schema {
+                let (by_type, other_detail) =
+                    stable_query_type_entries(&analysis.query_types, durations_logged);
+                query_analysis.other_detail = Some(other_detail);
+                by_type
+            } else {
+                analysis
+                    .query_types
+                    .iter()
+                    .map(|(query_type, stats)| {
+                        (
+                            query_type.clone(),
+                            query_type_entry(stats, durations_logged),
+                        )
+                    })
+                    .collect()
+            };
+            query_analysis.by_type_counts = Some(
+                by_type
+                    .iter()
+                    .map(|(query_type, entry)| (query_type.clone(), entry.count))
+                    .collect(),
+            );
+            query_analysis.by_type = Some(by_type);
+        }
+        if self.sections.contains(ReportSections::SLOWEST) {
+            query_analysis.slowest_queries = Some(slowest_query_entries(
+                &analysis.slowest_queries,
+                &analysis.most_frequent_queries,
+                &analysis.query_stats,
+                analysis.total_duration,
+                &self.slowest_changes,
+            ));
+        }
+        if self.sections.contains(ReportSections::MOST_FREQUENT) {
+            query_analysis.most_frequent = Some(most_frequent_entries(
+                &analysis.most_frequent_queries,
+                &analysis.query_stats,
+                durations_logged.then_some(analysis.average_duration),
+                analysis.total_duration,
+                self.query_sort,
+            ));
+        }
+        if self.sections.contains(ReportSections::MOST_FREQUENT)
+            && !analysis.unparsed_statements.is_empty()
+        {
+            let unparsed = &analysis.unparsed_statements;
+            query_analysis.unparsed_statements = Some(UnparsedStatementsEntry {
+                count: unparsed.count,
+                total_duration_ms: unparsed.total_duration,
+                samples: unparsed.samples.clone(),
+            });
+        }
+        if self.sections.contains(ReportSections::HISTOGRAM) && durations_logged {
+            query_analysis.duration_histogram = Some(
+                analysis
+                    .duration_histogram
+                    .iter()
+                    .map(|(bucket, count)| HistogramBucket {
+                        bucket: bucket.clone(),
+                        count: *count,
+                    })
+                    .collect(),
+            );
+        }
+        if let Some(group_by) = analysis.group_by {
+            let show_slowest = self.sections.contains(ReportSections::SLOWEST);
+            let show_frequent = self.sections.contains(ReportSections::MOST_FREQUENT);
+            if show_slowest || show_frequent {
+                let groups = analysis
+                    .query_groups_by_duration()
+                    .into_iter()
+                    .map(|(key, group)| {
+                        let average = if group.total_queries > 0 {
+                            group.total_duration / group.total_queries as f64
+                        } else {
+                            0.0
+                        };
+                        QueryGroupEntry {
+                            key: key.to_string(),
+                            total_queries: group.total_queries,
+                            total_duration_ms: group.total_duration,
+                            slowest_queries: show_slowest.then(|| {
+                                slowest_query_entries(
+                                    &group.slowest_queries,
+                                    &group.most_frequent_queries,
+                                    &HashMap::new(),
+                                    group.total_duration,
+                                    &[],
+                                )
+                            }),
+                            most_frequent: show_frequent.then(|| {
+                                most_frequent_entries(
+                                    &group.most_frequent_queries,
+                                    &HashMap::new(),
+                                    durations_logged.then_some(average),
+                                    group.total_duration,
+                                    QuerySort::Count,
+                                )
+                            }),
+                        }
+                    })
+                    .collect();
+                query_analysis.group_by = Some(group_by);
+                query_analysis.groups = Some(groups);
+            }
         }
 
-        let slowest_queries = analysis
-            .slowest_queries
-            .iter()
-            .map(|(q, d)| {
-                json!({
-                    "query": q,
-                    "duration_ms": d,
-                    "count": freq_map.get(q).cloned().unwrap_or(1),
+        let mut metadata = self.metadata();
+        metadata.approximate_query_counts = analysis.frequency_estimate;
+        metadata.estimated_from_sample = analysis.sample_estimate;
+        metadata
+            .notes
+            .extend(analysis.sample_estimate.map(|sample| sample.note()));
+        metadata.pg_version = analysis.pg_version;
+        metadata.notes.extend(analysis.unlogged_statement_note());
+        metadata.notes.extend(analysis.unlogged_duration_note());
+        if let Some(traffic) = &analysis.traffic_histogram {
+            metadata.notes.extend(traffic.widened_note());
+        }
+        let mut report = Report {
+            metadata,
+            summary: (!summary.is_empty()).then_some(summary),
+            query_analysis: (!query_analysis.is_empty()).then_some(query_analysis),
+            ..Report::default()
+        };
+        if self.sections.contains(ReportSections::ERRORS) {
+            report.errors_by_sqlstate = Some(analysis.errors_by_sqlstate.clone());
+            report.errors_by_severity = Some(analysis.errors_by_severity.clone());
+            let cancellations = &analysis.cancellations;
+            report.cancellations = Some(CancellationsSection {
+                total: cancellations.total,
+                by_reason: cancellations.by_reason.clone(),
+                top_queries: cancellations
+                    .top_queries
+                    .iter()
+                    .map(|query| CanceledQueryEntry {
+                        query: query.query.clone(),
+                        query_id: query_id(&query.query),
+                        count: query.count,
+                        by_reason: query.by_reason.clone(),
+                    })
+                    .collect(),
+                hourly: cancellations
+                    .hourly
+                    .iter()
+                    .map(|(hour, count)| CancellationHourEntry {
+                        hour: *hour,
+                        count: *count,
+                    })
+                    .collect(),
+            });
+        }
+        if self.sections.contains(ReportSections::APPLICATIONS) {
+            report.applications = Some(
+                analysis
+                    .applications
+                    .iter()
+                    .map(|application| ApplicationEntry {
+                        application_name: application.application_name.clone(),
+                        query_count: application.query_count,
+                        total_duration_ms: durations_logged.then_some(application.total_duration),
+                        p95_duration_ms: durations_logged.then_some(application.p95_duration),
+                        error_count: application.error_count,
+                        slowest_queries: application
+                            .slowest_queries
+                            .iter()
+                            .filter(|_| durations_logged)
+                            .map(|(q, d)| ApplicationQueryEntry {
+                                query: q.clone(),
+                                query_id: query_id(q),
+                                duration_ms: *d,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            );
+        }
+        if self.sections.contains(ReportSections::CLIENTS) {
+            report.clients = Some(
+                analysis
+                    .client_hosts
+                    .iter()
+                    .map(|client| ClientEntry {
+                        client_host: client.client_host.clone(),
+                        connection_count: client.connection_count,
+                        query_count: client.query_count,
+                        total_duration_ms: client.total_duration,
+                        error_count: client.error_count,
+                        error_rate: client.error_rate,
+                    })
+                    .collect(),
+            );
+        }
+        if self.sections.contains(ReportSections::COPY) && !analysis.copy_stats.tables.is_empty() {
+            let copy = &analysis.copy_stats;
+            report.copy = Some(CopySection {
+                operations: copy.operations,
+                total_duration_ms: copy.total_duration,
+                max_duration_ms: copy.max_duration,
+                error_count: copy.error_count,
+                tables: copy
+                    .tables
+                    .iter()
+                    .map(|table| CopyTableEntry {
+                        table: table.table.clone(),
+                        direction: table.direction,
+                        operations: table.operations,
+                        total_duration_ms: table.total_duration,
+                        max_duration_ms: table.max_duration,
+                        error_count: table.error_count,
+                    })
+                    .collect(),
+            });
+        }
+        let replication = &analysis.replication;
+        if self.sections.contains(ReportSections::REPLICATION) && !replication.is_empty() {
+            report.replication = Some(ReplicationSection {
+                total: replication.total(),
+                counts: replication
+                    .counts
+                    .iter()
+                    .map(|(kind, count)| (kind.to_string(), *count))
+                    .collect(),
+                transitions: replication.transitions.clone(),
+                longest_gap: replication.longest_gap.clone(),
+            });
+        }
+        if self.sections.contains(ReportSections::MAINTENANCE) && !analysis.maintenance.is_empty() {
+            report.maintenance = Some(analysis.maintenance.clone());
+            let hints = analysis.maintenance.hints();
+            report.tuning_hints = (!hints.is_empty()).then_some(hints);
+        }
+        if self.sections.contains(ReportSections::EVENTS) && !analysis.log_events.is_empty() {
+            report.events = Some(analysis.log_events.clone());
+        }
+        if self.sections.contains(ReportSections::PREPARED_STATEMENTS)
+            && !analysis.prepared_statements.is_empty()
+        {
+            report.prepared_statements = Some(analysis.prepared_statements.clone());
+        }
+        report.query_warnings = analysis.query_warnings.as_ref().map(|warnings| {
+            warnings
+                .iter()
+                .map(|warning| QueryWarningEntry {
+                    rule: warning.rule.clone(),
+                    description: warning.description.clone(),
+                    query: warning.query.clone(),
+                    query_id: query_id(&warning.query),
+                    count: warning.count,
+                    p95_duration_ms: warning.p95_duration,
                 })
-            })
-            .collect::<Vec<_>>();
-
-        let most_frequent = analysis
-            .most_frequent_queries
-            .iter()
-            .map(|(q, c)| {
-                json!({
-                    "query": q,
-                    "count": c,
-                    // Without per-query duration distribution, fall back to overall average
-                    "avg_duration_ms": analysis.average_duration,
+                .collect()
+        });
+        if self.sections.contains(ReportSections::BREAKDOWNS) {
+            report.breakdowns = Some(Breakdowns {
+                queries_by_user: sorted(&analysis.queries_by_user),
+                duration_by_user: if durations_logged {
+                    sorted(&analysis.duration_by_user)
+                } else {
+                    BTreeMap::new()
+                },
+                queries_by_database: sorted(&analysis.queries_by_database),
+                errors_by_database: sorted(&analysis.errors_by_database),
+            });
+        }
+        if let Some(timing) = timing {
+            let temporal = self.temporal_section(timing, analysis);
+            report.temporal_analysis = (!temporal.is_empty()).then_some(temporal);
+        }
+        report.traffic_histogram = analysis.traffic_histogram.as_ref().map(|traffic| {
+            traffic
+                .buckets
+                .iter()
+                .map(|bucket| TrafficBucketEntry {
+                    start: bucket.start,
+                    end: traffic.bucket_end(bucket),
+                    query_count: bucket.query_count,
+                    error_count: bucket.error_count,
                 })
-            })
-            .collect::<Vec<_>>();
-
-        let root = json!({
-            "metadata": self.metadata_object(),
-            "summary": summary,
-            "query_analysis": {
-                "by_type": by_type,
-                "slowest_queries": slowest_queries,
-                "most_frequent": most_frequent,
-            },
+                .collect()
         });
+        report
+    }
 
-        if self.pretty {
-            serde_json::to_string_pretty(&root).map_err(PgLogstatsError::Serialization)
-        } else {
-            serde_json::to_string(&root).map_err(PgLogstatsError::Serialization)
+    /// Temporal section of `timing`; duration fields are null when
+    /// `analysis` has no logged durations
+    fn temporal_section(
+        &self,
+        timing: &TimingAnalysis,
+        analysis: &AnalysisResult,
+    ) -> TemporalSection {
+        let durations_logged = analysis.durations_logged();
+        let mut temporal = TemporalSection::default();
+        if self.sections.contains(ReportSections::HOURLY) {
+            let mut hours: Vec<u32> = timing
+                .hourly_query_counts
+                .keys()
+                .chain(timing.connection_patterns.keys())
+                .copied()
+                .collect();
+            hours.sort_unstable();
+            hours.dedup();
+            temporal.hourly_stats = Some(
+                hours
+                    .into_iter()
+                    .map(|hour| {
+                        let query_count = timing.hourly_query_counts.get(&hour).copied();
+                        let total_ms = timing.hourly_patterns.get(&hour).copied();
+                        HourlyEntry {
+                            hour,
+                            query_count: query_count.unwrap_or(0),
+                            total_duration_ms: durations_logged.then(|| total_ms.unwrap_or(0.0)),
+                            avg_duration_ms: durations_logged
+                                .then(|| average(total_ms, query_count)),
+                            connection_count: timing
+                                .connection_patterns
+                                .get(&hour)
+                                .copied()
+                                .unwrap_or(0),
+                        }
+                    })
+                    .collect(),
+            );
+
+            let mut days: Vec<_> = timing.daily_query_counts.iter().collect();
+            days.sort_by_key(|(day, _)| **day);
+            temporal.daily_patterns = Some(
+                days.into_iter()
+                    .filter_map(|(&day, &query_count)| {
+                        let name = WEEKDAYS.get(day as usize)?;
+                        let total_ms = timing.daily_patterns.get(&day).copied();
+                        Some(DailyEntry {
+                            day: name.to_string(),
+                            query_count,
+                            total_duration_ms: durations_logged.then(|| total_ms.unwrap_or(0.0)),
+                            avg_duration_ms: durations_logged
+                                .then(|| average(total_ms, Some(query_count))),
+                        })
+                    })
+                    .collect(),
+            );
+
+            temporal.by_hour = Some(
+                timing
+                    .by_hour
+                    .iter()
+                    .map(|(&hour, stats)| HourSeriesEntry {
+                        hour,
+                        query_count: stats.query_count,
+                        total_duration_ms: durations_logged.then_some(stats.total_duration),
+                        avg_duration_ms: durations_logged.then(|| stats.average_duration()),
+                        connection_count: stats.connection_count,
+                    })
+                    .collect(),
+            );
+
+            let mut peak_hours = timing.peak_hours.clone();
+            peak_hours.sort_unstable();
+            temporal.peak_hours = Some(peak_hours);
+            temporal.busiest_hour = timing.busiest_hour();
+
+            let business_hours = &timing.business_hours;
+            temporal.business_hours = (!business_hours.is_empty()).then(|| BusinessHoursSection {
+                window: business_hours.window.clone(),
+                timezone: business_hours.timezone.clone(),
+                business_hours: business_hours.business_hours.clone(),
+                off_hours: business_hours.off_hours.clone(),
+                weekdays: business_hours
+                    .weekdays
+                    .iter()
+                    .filter_map(|weekday| {
+                        Some(WeekdayEntry {
+                            day: WEEKDAYS.get(weekday.day as usize)?.to_string(),
+                            stats: weekday.stats.clone(),
+                        })
+                    })
+                    .collect(),
+            });
         }
+        if self.sections.contains(ReportSections::SUMMARY) {
+            let timed = |time_ms: f64| Some(durations_logged.then_some(time_ms));
+            temporal.average_response_time_ms = timed(timing.average_response_time_ms);
+            temporal.p95_response_time_ms = timed(timing.p95_response_time_ms);
+            temporal.p99_response_time_ms = timed(timing.p99_response_time_ms);
+        }
+        temporal
+    }
+
+    /// Format a single AnalysisResult as structured JSON
+    pub fn format(&self, analysis: &AnalysisResult) -> Result<String> {
+        self.render(&self.report(analysis, None))
     }
 
     /// Format with timing analysis included
@@ -141,37 +848,21 @@ impl JsonFormatter {
         analysis: &AnalysisResult,
         timing: &TimingAnalysis,
     ) -> Result<String> {
-        let mut base: serde_json::Value = serde_json::from_str(&self.format(analysis)?)
-            .map_err(PgLogstatsError::Serialization)?;
-
-        // Build temporal analysis section from TimingAnalysis
-        let hourly_stats = timing
-            .hourly_patterns
-            .iter()
-            .map(|(hour, total_ms)| {
-                json!({
-                    "hour": hour,
-                    "total_duration_ms": total_ms,
-                })
-            })
-            .collect::<Vec<_>>();
-
-        let temporal = json!({
-            "hourly_stats": hourly_stats,
-            "average_response_time_ms": timing.average_response_time.num_milliseconds(),
-            "p95_response_time_ms": timing.p95_response_time.num_milliseconds(),
-            "p99_response_time_ms": timing.p99_response_time.num_milliseconds(),
-        });
+        self.render(&self.report(analysis, Some(timing)))
+    }
 
-        if let Some(obj) = base.as_object_mut() {
-            obj.insert("temporal_analysis".to_string(), temporal);
-        }
+    /// Format a comparison of two reports under a top-level `comparison` key
+    pub fn format_comparison(&self, comparison: &ReportComparison) -> Result<String> {
+        let root = json!({
+            "metadata": self.metadata_object(),
+            "comparison": comparison,
+        });
+        self.render(&root)
+    }
 
-        if self.pretty {
-            serde_json::to_string_pretty(&base).map_err(PgLogstatsError::Serialization)
-        } else {
-            serde_json::to_string(&base).map_err(PgLogstatsError::Serialization)
-        }
+    /// Format a session timeline as an array of its events, without metadata
+    pub fn format_session_timeline(&self, events: &[SessionEvent]) -> Result<String> {
+        self.render(&events)
     }
 
     /// Format structured findings as compact, versioned JSON.
@@ -181,11 +872,20 @@ impl JsonFormatter {
             "schema_version": findings.schema_version,
             "findings": findings.findings,
         });
+        self.render(&root)
+    }
 
+    /// Write `value` as this formatter writes reports: redacted, with sorted
+    /// keys, and pretty-printed when enabled
+    pub fn render(&self, value: &impl Serialize) -> Result<String> {
+        let mut value = serde_json::to_value(value).map_err(PgLogstatsError::Serialization)?;
+        if let Some(redactor) = &self.redactor {
+            redactor.redact_json(&mut value);
+        }
         if self.pretty {
-            serde_json::to_string_pretty(&root).map_err(PgLogstatsError::Serialization)
+            serde_json::to_string_pretty(&value).map_err(PgLogstatsError::Serialization)
         } else {
-            serde_json::to_string(&root).map_err(PgLogstatsError::Serialization)
+            serde_json::to_string(&value).map_err(PgLogstatsError::Serialization)
         }
     }
 }
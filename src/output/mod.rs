@@ -1,7 +1,22 @@
 //! Output formatters for pg-logstats analysis results
 
 pub mod json;
+pub mod redact;
+pub mod schema;
+pub mod sections;
+pub mod sqlite;
 pub mod text;
 
 pub use json::JsonFormatter;
-pub use text::TextFormatter;
+pub use redact::{Redact, Redactor, REDACTED};
+pub use schema::{
+    ApplicationEntry, ApplicationQueryEntry, Breakdowns, BusinessHoursSection, CanceledQueryEntry,
+    CancellationHourEntry, CancellationsSection, ClientEntry, DailyEntry, FrequentQueryEntry,
+    HistogramBucket, HourSeriesEntry, HourlyEntry, QueryAnalysisSection, QueryExampleEntry,
+    QueryGroupEntry, QueryTypeEntry, QueryWarningEntry, ReplicationSection, ReportMetadata,
+    SlowQueryEntry, Summary, TemporalSection, TrafficBucketEntry, WeekdayEntry,
+    REPORT_SCHEMA_VERSION,
+};
+pub use sections::ReportSections;
+pub use sqlite::SqliteExporter;
+pub use text::{EntryLevelFilter, EntryLimit, TextFormatter};
@@ -0,0 +1,661 @@
+//! Typed schema of the JSON summary report
+//!
+//! `JsonFormatter` fills a [`Report`] and serializes it, so these structs are
+//! the report format: a saved report can be read back with
+//! [`Report::from_json`], e.g. by `pg-logstats compare`. Sections that were
+//! turned off when the report was written are `None` and their keys are
+//! omitted.
+
+use crate::{
+    CopyDirection, Finding, FrequencyEstimate, GroupBy, LogEventStats, MaintenanceStats,
+    ParseReport, PeriodStats, PgLogstatsError, PgStatStatementsComparison, PgVersion,
+    PoolerSummary, PreparedStatementStats, ReplicationGap, ReplicationTransition, ReportComparison,
+    Result, RunStats, SampleEstimate, SqlstateClassCount, TimeRange, TuningHint,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of the report format in `metadata.schema_version`, raised when
+/// existing keys change shape; reports without one are version 1
+pub const REPORT_SCHEMA_VERSION: u32 = 3;
+
+/// JSON summary report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    pub metadata: ReportMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_analysis: Option<QueryAnalysisSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors_by_sqlstate: Option<BTreeMap<String, SqlstateClassCount>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors_by_severity: Option<BTreeMap<String, u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancellations: Option<CancellationsSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applications: Option<Vec<ApplicationEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clients: Option<Vec<ClientEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copy: Option<CopySection>,
+    /// Present only when the log has replication events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication: Option<ReplicationSection>,
+    /// Present only when the log has checkpoint or autovacuum messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceStats>,
+    /// Present only when a hint rule matched more messages than its threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tuning_hints: Option<Vec<TuningHint>>,
+    /// Present only when the log has LOG, WARNING, or NOTICE messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<LogEventStats>>,
+    /// Present only when the log has parse, bind, or execute messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepared_statements: Option<PreparedStatementStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_warnings: Option<Vec<QueryWarningEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdowns: Option<Breakdowns>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temporal_analysis: Option<TemporalSection>,
+    /// Present only when a traffic histogram was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_histogram: Option<Vec<TrafficBucketEntry>>,
+    /// Findings schema version, when the report also has findings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub findings: Option<Vec<Finding>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pooler_summary: Option<PoolerSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<ReportComparison>,
+    /// Present when the report was matched with a `pg_stat_statements` export
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pg_stat_statements: Option<PgStatStatementsComparison>,
+}
+
+impl Report {
+    /// Parse a report written with `--output-format json`
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(PgLogstatsError::Serialization)
+    }
+
+    /// Serialize the report, with object keys in sorted order
+    pub fn to_json(&self, pretty: bool) -> Result<String> {
+        // Going through `Value` sorts the keys, as every report has had them
+        let value = serde_json::to_value(self).map_err(PgLogstatsError::Serialization)?;
+        if pretty {
+            serde_json::to_string_pretty(&value).map_err(PgLogstatsError::Serialization)
+        } else {
+            serde_json::to_string(&value).map_err(PgLogstatsError::Serialization)
+        }
+    }
+}
+
+/// The `metadata` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportMetadata {
+    /// [`REPORT_SCHEMA_VERSION`] of the writer; not the findings
+    /// `schema_version` at the top level
+    #[serde(default = "first_schema_version")]
+    pub schema_version: u32,
+    /// RFC 3339 time the report was written
+    pub analysis_timestamp: String,
+    pub tool_version: String,
+    pub log_files_processed: Vec<String>,
+    pub total_log_entries: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_report: Option<ParseReport>,
+    /// Stage times and input counts of the run that wrote the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_stats: Option<RunStats>,
+    /// Present when query counts are estimates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approximate_query_counts: Option<FrequencyEstimate>,
+    /// Present when query counts and totals were scaled up from a sample of
+    /// the statements
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_from_sample: Option<SampleEstimate>,
+    /// IANA timezone of the `hour` values, when not UTC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hour_timezone: Option<String>,
+    /// PostgreSQL major version that wrote the log, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pg_version: Option<PgVersion>,
+    /// Set when an entry limit stopped parsing before the end of the input;
+    /// `parse_report.truncation` says where
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Caveats about what the log could tell, one sentence each
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// The `summary` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_queries: Option<u64>,
+    /// Executions made only of pooler maintenance statements, such as
+    /// `DISCARD ALL`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pooler_maintenance_count: Option<u64>,
+    /// This and the other durations are `Some(None)`, written as `null`,
+    /// when statements were logged without durations
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub total_duration_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub avg_duration_ms: Option<Option<f64>>,
+    /// Population standard deviation of query durations
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub stddev_duration_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub min_duration_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub p95_duration_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub p99_duration_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub max_duration_ms: Option<Option<f64>>,
+    /// `Some(None)` is written as `null`, for a report without entries
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub time_range: Option<Option<TimeRange>>,
+    /// This and the other temp file fields are present only when the log
+    /// has `temporary file` messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_files: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_bytes: Option<u64>,
+    /// Also absent when the log spans no time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_bytes_per_hour: Option<f64>,
+    /// This and the other checkpoint write fields are present only when the
+    /// log has checkpoint completions, converted with 8 kB pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_buffers_written: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_mb_written: Option<f64>,
+    /// Also absent when the log spans no time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_mb_per_hour: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_count: Option<u64>,
+}
+
+impl Summary {
+    /// Whether every key is omitted
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The `query_analysis` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryAnalysisSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_type: Option<BTreeMap<String, QueryTypeEntry>>,
+    /// Counts of `by_type`, in the flat form reports had before schema
+    /// version 2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_type_counts: Option<BTreeMap<String, u64>>,
+    /// With a stable schema, the `by_type` keys that are not a known query
+    /// type; they are counted in `OTHER` instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other_detail: Option<BTreeMap<String, QueryTypeEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slowest_queries: Option<Vec<SlowQueryEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub most_frequent: Option<Vec<FrequentQueryEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_histogram: Option<Vec<HistogramBucket>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<GroupBy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<QueryGroupEntry>>,
+    /// Statements the SQL parser rejected; absent when there were none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unparsed_statements: Option<UnparsedStatementsEntry>,
+}
+
+/// One value of `query_analysis.by_type`; the durations are `None` when
+/// statements were logged without durations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ByTypeValue")]
+pub struct QueryTypeEntry {
+    pub count: u64,
+    pub total_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+}
+
+/// A `by_type` value as written by any schema version: a bare count before
+/// version 2
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ByTypeValue {
+    Count(u64),
+    Stats {
+        count: u64,
+        total_ms: Option<f64>,
+        avg_ms: Option<f64>,
+        p95_ms: Option<f64>,
+        max_ms: Option<f64>,
+    },
+}
+
+impl From<ByTypeValue> for QueryTypeEntry {
+    fn from(value: ByTypeValue) -> Self {
+        match value {
+            ByTypeValue::Count(count) => Self {
+                count,
+                total_ms: None,
+                avg_ms: None,
+                p95_ms: None,
+                max_ms: None,
+            },
+            ByTypeValue::Stats {
+                count,
+                total_ms,
+                avg_ms,
+                p95_ms,
+                max_ms,
+            } => Self {
+                count,
+                total_ms,
+                avg_ms,
+                p95_ms,
+                max_ms,
+            },
+        }
+    }
+}
+
+/// The `query_analysis.unparsed_statements` object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnparsedStatementsEntry {
+    pub count: u64,
+    pub total_duration_ms: f64,
+    pub samples: Vec<String>,
+}
+
+impl QueryAnalysisSection {
+    /// Whether every key is omitted
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One row of `query_analysis.slowest_queries`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowQueryEntry {
+    pub query: String,
+    /// Empty in reports written before query ids existed
+    #[serde(default)]
+    pub query_id: String,
+    pub duration_ms: f64,
+    pub count: u64,
+    /// Absent without per-query statistics, as in groups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p95_duration_ms: Option<f64>,
+    /// Absent without per-query statistics, as in groups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_duration_ms: Option<f64>,
+    /// Share of the total query duration, in percent with two decimals;
+    /// absent with `total_duration_ms`. The listed queries are only the
+    /// slowest, so their shares need not add up to 100.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pct_of_total_time: Option<f64>,
+    /// Places moved up the slowest list since the `--compare-with` baseline,
+    /// negative when it moved down; absent without a baseline or when the
+    /// baseline did not list the query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank_delta: Option<i64>,
+    /// Change of `p95_duration_ms` since the baseline, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p95_delta_ms: Option<f64>,
+    /// Slowest executions kept of the query, slowest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<QueryExampleEntry>,
+}
+
+/// One row of `query_analysis.most_frequent`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrequentQueryEntry {
+    pub query: String,
+    /// Empty in reports written before query ids existed
+    #[serde(default)]
+    pub query_id: String,
+    pub count: u64,
+    /// `None` when statements were logged without durations
+    pub avg_duration_ms: Option<f64>,
+    /// Absent when the report only had the overall average
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p95_duration_ms: Option<f64>,
+    /// Absent when the report only had the overall average
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_duration_ms: Option<f64>,
+    /// Share of the total query duration, in percent with two decimals;
+    /// absent with `total_duration_ms`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent_of_total_duration: Option<f64>,
+    /// Slowest executions kept of the query, slowest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<QueryExampleEntry>,
+}
+
+/// One object of the `examples` of a slowest or most frequent query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryExampleEntry {
+    /// Statement as logged, with its literals
+    pub sql: String,
+    pub timestamp: DateTime<Utc>,
+    /// `None`, written as `null`, when durations were not logged
+    pub duration_ms: Option<f64>,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    /// Values of the `$n` placeholders, when the statement had them logged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<String>,
+}
+
+/// One bucket of `query_analysis.duration_histogram`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub bucket: String,
+    pub count: u64,
+}
+
+/// One object of `query_analysis.groups`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryGroupEntry {
+    pub key: String,
+    pub total_queries: u64,
+    pub total_duration_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slowest_queries: Option<Vec<SlowQueryEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub most_frequent: Option<Vec<FrequentQueryEntry>>,
+}
+
+/// One object of `applications`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationEntry {
+    pub application_name: String,
+    pub query_count: u64,
+    /// `None` when statements were logged without durations
+    pub total_duration_ms: Option<f64>,
+    /// `None` when statements were logged without durations
+    pub p95_duration_ms: Option<f64>,
+    pub error_count: u64,
+    pub slowest_queries: Vec<ApplicationQueryEntry>,
+}
+
+/// One of an application's slowest queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationQueryEntry {
+    pub query: String,
+    #[serde(default)]
+    pub query_id: String,
+    pub duration_ms: f64,
+}
+
+/// One object of `clients`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientEntry {
+    pub client_host: String,
+    pub connection_count: u64,
+    pub query_count: u64,
+    pub total_duration_ms: f64,
+    pub error_count: u64,
+    pub error_rate: f64,
+}
+
+/// The `copy` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CopySection {
+    pub operations: u64,
+    pub total_duration_ms: f64,
+    pub max_duration_ms: f64,
+    pub error_count: u64,
+    pub tables: Vec<CopyTableEntry>,
+}
+
+/// One object of `copy.tables`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopyTableEntry {
+    pub table: String,
+    pub direction: CopyDirection,
+    pub operations: u64,
+    pub total_duration_ms: f64,
+    pub max_duration_ms: f64,
+    pub error_count: u64,
+}
+
+/// The `replication` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationSection {
+    pub total: u64,
+    /// Events per category, e.g. `streaming_started`
+    pub counts: BTreeMap<String, u64>,
+    /// Changes between streaming and not streaming, oldest first
+    pub transitions: Vec<ReplicationTransition>,
+    /// Longest stretch without streaming
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longest_gap: Option<ReplicationGap>,
+}
+
+/// The `cancellations` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CancellationsSection {
+    pub total: u64,
+    pub by_reason: BTreeMap<String, u64>,
+    pub top_queries: Vec<CanceledQueryEntry>,
+    pub hourly: Vec<CancellationHourEntry>,
+}
+
+/// One object of `cancellations.top_queries`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanceledQueryEntry {
+    pub query: String,
+    pub query_id: String,
+    pub count: u64,
+    pub by_reason: BTreeMap<String, u64>,
+}
+
+/// One object of `cancellations.hourly`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CancellationHourEntry {
+    pub hour: u32,
+    pub count: u64,
+}
+
+/// One object of `query_warnings`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryWarningEntry {
+    pub rule: String,
+    pub description: String,
+    pub query: String,
+    pub query_id: String,
+    pub count: u64,
+    pub p95_duration_ms: f64,
+}
+
+/// The `breakdowns` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Breakdowns {
+    pub queries_by_user: BTreeMap<String, u64>,
+    pub duration_by_user: BTreeMap<String, f64>,
+    pub queries_by_database: BTreeMap<String, u64>,
+    pub errors_by_database: BTreeMap<String, u64>,
+}
+
+/// The `temporal_analysis` object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemporalSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly_stats: Option<Vec<HourlyEntry>>,
+    /// By day of week, Monday first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_patterns: Option<Vec<DailyEntry>>,
+    /// Each UTC hour of the log with activity, earliest first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_hour: Option<Vec<HourSeriesEntry>>,
+    /// Hours whose total duration is more than 50% above the hourly average
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_hours: Option<Vec<u32>>,
+    /// Hour with the most queries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub busiest_hour: Option<u32>,
+    /// This and the other response times are `Some(None)`, written as
+    /// `null`, when statements were logged without durations
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub average_response_time_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub p95_response_time_ms: Option<Option<f64>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub p99_response_time_ms: Option<Option<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business_hours: Option<BusinessHoursSection>,
+}
+
+impl TemporalSection {
+    /// Whether every key is omitted
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One bucket of `traffic_histogram`, covering `start` up to `end`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficBucketEntry {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub query_count: u64,
+    pub error_count: u64,
+}
+
+/// One row of `temporal_analysis.hourly_stats`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HourlyEntry {
+    pub hour: u32,
+    #[serde(default)]
+    pub query_count: u64,
+    /// `None`, written as `null`, when durations were not logged
+    pub total_duration_ms: Option<f64>,
+    #[serde(default)]
+    pub avg_duration_ms: Option<f64>,
+    /// Connection messages
+    #[serde(default)]
+    pub connection_count: u64,
+}
+
+/// One row of `temporal_analysis.by_hour`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HourSeriesEntry {
+    /// Start of the hour
+    pub hour: DateTime<Utc>,
+    pub query_count: u64,
+    /// `None`, written as `null`, when durations were not logged
+    pub total_duration_ms: Option<f64>,
+    pub avg_duration_ms: Option<f64>,
+    /// Connection messages
+    pub connection_count: u64,
+}
+
+/// One row of `temporal_analysis.daily_patterns`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyEntry {
+    /// Weekday name, e.g. `Monday`
+    pub day: String,
+    pub query_count: u64,
+    /// `None`, written as `null`, when durations were not logged
+    pub total_duration_ms: Option<f64>,
+    pub avg_duration_ms: Option<f64>,
+}
+
+/// `temporal_analysis.business_hours`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusinessHoursSection {
+    /// The window, e.g. `Mon-Fri 09:00-18:00`
+    pub window: String,
+    /// Timezone the window is in, an IANA name or a UTC offset such as
+    /// `+02:00`
+    pub timezone: String,
+    pub business_hours: PeriodStats,
+    pub off_hours: PeriodStats,
+    /// Weekdays with activity, Monday first
+    pub weekdays: Vec<WeekdayEntry>,
+}
+
+/// One row of `temporal_analysis.business_hours.weekdays`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeekdayEntry {
+    /// Weekday name, e.g. `Monday`
+    pub day: String,
+    #[serde(flatten)]
+    pub stats: PeriodStats,
+}
+
+fn first_schema_version() -> u32 {
+    1
+}
+
+/// Read a key that is present, even as `null`, as `Some`
+fn present<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,192 @@
+//! Report section toggles shared by the text and JSON formatters
+
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+use std::str::FromStr;
+
+/// Set of report sections to render, in the style of a bitflag set.
+///
+/// Formatters skip disabled sections; JSON output omits their keys entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportSections(u16);
+
+impl ReportSections {
+    /// Query totals, average duration, and percentiles
+    pub const SUMMARY: Self = Self(1 << 0);
+    /// Query counts by statement type
+    pub const QUERY_TYPES: Self = Self(1 << 1);
+    /// Slowest queries
+    pub const SLOWEST: Self = Self(1 << 2);
+    /// Most frequent queries
+    pub const MOST_FREQUENT: Self = Self(1 << 3);
+    /// Error count and errors by SQLSTATE
+    pub const ERRORS: Self = Self(1 << 4);
+    /// Connection count
+    pub const CONNECTIONS: Self = Self(1 << 5);
+    /// Hourly activity
+    pub const HOURLY: Self = Self(1 << 6);
+    /// Per-user and per-database query and error counts
+    pub const BREAKDOWNS: Self = Self(1 << 7);
+    /// Per-application query counts, latency, errors, and slowest queries
+    pub const APPLICATIONS: Self = Self(1 << 8);
+    /// Query counts per duration bucket
+    pub const HISTOGRAM: Self = Self(1 << 9);
+    /// Per-client-host connection, query, and error counts
+    pub const CLIENTS: Self = Self(1 << 10);
+    /// `COPY` operations per table
+    pub const COPY: Self = Self(1 << 11);
+    /// Streaming replication and recovery events
+    pub const REPLICATION: Self = Self(1 << 12);
+    /// Checkpoint and autovacuum activity
+    pub const MAINTENANCE: Self = Self(1 << 13);
+    /// Parse, bind, and execute phases of prepared statements
+    pub const PREPARED_STATEMENTS: Self = Self(1 << 14);
+    /// Most frequent LOG, WARNING, and NOTICE messages
+    pub const EVENTS: Self = Self(1 << 15);
+
+    /// Section names accepted by [`FromStr`], in report order
+    pub const NAMES: [(&'static str, Self); 16] = [
+        ("summary", Self::SUMMARY),
+        ("query_types", Self::QUERY_TYPES),
+        ("slowest", Self::SLOWEST),
+        ("most_frequent", Self::MOST_FREQUENT),
+        ("errors", Self::ERRORS),
+        ("connections", Self::CONNECTIONS),
+        ("hourly", Self::HOURLY),
+        ("breakdowns", Self::BREAKDOWNS),
+        ("applications", Self::APPLICATIONS),
+        ("clients", Self::CLIENTS),
+        ("copy", Self::COPY),
+        ("replication", Self::REPLICATION),
+        ("maintenance", Self::MAINTENANCE),
+        ("events", Self::EVENTS),
+        ("prepared_statements", Self::PREPARED_STATEMENTS),
+        ("histogram", Self::HISTOGRAM),
+    ];
+
+    /// Every section
+    pub const fn all() -> Self {
+        Self(0b1111_1111_1111_1111)
+    }
+
+    /// No sections
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Whether every section in `other` is enabled
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether no section is enabled
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Enable the sections in `other`
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Disable the sections in `other`
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// Copy of this set without the sections in `other`
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Look up a single section by name, accepting `-` for `_`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase().replace('-', "_");
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, section)| *section)
+    }
+}
+
+impl Default for ReportSections {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl BitOr for ReportSections {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ReportSections {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.insert(rhs);
+    }
+}
+
+impl FromStr for ReportSections {
+    type Err = String;
+
+    /// Parse a comma-separated list such as `summary,slowest`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sections = Self::empty();
+        for name in s.split(',').filter(|name| !name.trim().is_empty()) {
+            match Self::from_name(name) {
+                Some(section) => sections.insert(section),
+                None => {
+                    let known: Vec<_> = Self::NAMES.iter().map(|(name, _)| *name).collect();
+                    return Err(format!(
+                        "unknown report section '{}'; expected one of: {}",
+                        name.trim(),
+                        known.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(sections)
+    }
+}
+
+impl fmt::Display for ReportSections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = Self::NAMES
+            .iter()
+            .filter(|(_, section)| self.contains(*section))
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_section_lists() {
+        let sections: ReportSections = "summary, slowest,most-frequent".parse().unwrap();
+
+        assert!(sections.contains(ReportSections::SUMMARY));
+        assert!(sections.contains(ReportSections::SLOWEST));
+        assert!(sections.contains(ReportSections::MOST_FREQUENT));
+        assert!(!sections.contains(ReportSections::ERRORS));
+        assert_eq!(sections.to_string(), "summary,slowest,most_frequent");
+        assert!("summary,bogus".parse::<ReportSections>().is_err());
+    }
+
+    #[test]
+    fn removes_sections_from_all() {
+        let sections =
+            ReportSections::all().without(ReportSections::HOURLY | ReportSections::ERRORS);
+
+        assert!(!sections.contains(ReportSections::HOURLY));
+        assert!(!sections.contains(ReportSections::ERRORS));
+        assert!(sections.contains(ReportSections::CONNECTIONS));
+        assert_eq!(ReportSections::default(), ReportSections::all());
+    }
+}
@@ -0,0 +1,199 @@
+//! SQLite export of analysis results
+//!
+//! Each export appends one run to the database, so a database written by
+//! daily runs keeps a history that can be queried for trends:
+//!
+//! - `runs(run_id, started_at, files, entries)`
+//! - `query_stats(run_id, fingerprint, normalized_query, query_type, count, total_ms, avg_ms, p95_ms)`,
+//!   one row per counted query rather than only the listed slowest and most
+//!   frequent ones
+//! - `errors(run_id, class, count)`
+//! - `hourly(run_id, hour, count, avg_ms)`
+//!
+//! The duration columns are `NULL` when the log has statements but no
+//! durations, as with `log_statement = all` and `log_duration = off`.
+//!
+//! Writing needs the optional `sqlite` feature.
+
+use super::Redact;
+use crate::{AnalysisResult, PgLogstatsError, Result};
+use chrono::{DateTime, Utc};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Statements creating the export tables; safe to run on an existing database
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    run_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    files TEXT NOT NULL,
+    entries INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS query_stats (
+    run_id INTEGER NOT NULL REFERENCES runs(run_id),
+    fingerprint TEXT NOT NULL,
+    normalized_query TEXT NOT NULL,
+    query_type TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    total_ms REAL,
+    avg_ms REAL,
+    p95_ms REAL
+);
+CREATE TABLE IF NOT EXISTS errors (
+    run_id INTEGER NOT NULL REFERENCES runs(run_id),
+    class TEXT NOT NULL,
+    count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS hourly (
+    run_id INTEGER NOT NULL REFERENCES runs(run_id),
+    hour INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    avg_ms REAL
+);
+";
+
+/// Appends analysis results to a SQLite database
+#[derive(Debug, Clone)]
+pub struct SqliteExporter {
+    files: Vec<String>,
+    total_entries: usize,
+    started_at: DateTime<Utc>,
+    redactor: Option<Arc<dyn Redact>>,
+}
+
+impl SqliteExporter {
+    /// Create an exporter for a run starting now
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            total_entries: 0,
+            started_at: Utc::now(),
+            redactor: None,
+        }
+    }
+
+    /// Set the log files and entry count recorded in `runs`
+    pub fn with_metadata(mut self, files: Vec<String>, total_entries: usize) -> Self {
+        self.files = files;
+        self.total_entries = total_entries;
+        self
+    }
+
+    /// Set the run's `started_at` time
+    pub fn with_started_at(mut self, started_at: DateTime<Utc>) -> Self {
+        self.started_at = started_at;
+        self
+    }
+
+    /// Redact the query text written to `query_stats` with `redactor`;
+    /// fingerprints are of the query as analyzed
+    pub fn with_redactor(mut self, redactor: Option<Arc<dyn Redact>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Get the log files recorded in `runs`
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Get the run's `started_at` time
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    /// Append `analysis` as a new run of the database at `path`, creating the
+    /// file and tables when missing, and return the run's `run_id`
+    pub fn export(&self, path: &Path, analysis: &AnalysisResult) -> Result<i64> {
+        write_run(self, path, analysis)
+    }
+}
+
+impl Default for SqliteExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn write_run(exporter: &SqliteExporter, path: &Path, analysis: &AnalysisResult) -> Result<i64> {
+    use crate::{classify, query_id};
+    use rusqlite::{params, Connection};
+
+    let sqlite_error = |e: rusqlite::Error| PgLogstatsError::Unexpected {
+        message: e.to_string(),
+        context: Some("sqlite export".to_string()),
+    };
+
+    let mut connection = Connection::open(path).map_err(sqlite_error)?;
+    connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+    let transaction = connection.transaction().map_err(sqlite_error)?;
+    transaction
+        .execute(
+            "INSERT INTO runs (started_at, files, entries) VALUES (?1, ?2, ?3)",
+            params![
+                exporter.started_at.to_rfc3339(),
+                serde_json::to_string(&exporter.files)?,
+                exporter.total_entries as i64,
+            ],
+        )
+        .map_err(sqlite_error)?;
+    let run_id = transaction.last_insert_rowid();
+    let durations_logged = analysis.durations_logged();
+
+    let mut queries: Vec<_> = analysis.all_query_stats.iter().collect();
+    queries.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+    for (sql, stats) in queries {
+        let normalized_query = match &exporter.redactor {
+            Some(redactor) => redactor.redact(sql),
+            None => sql.into(),
+        };
+        transaction
+            .execute(
+                "INSERT INTO query_stats (run_id, fingerprint, normalized_query, query_type, \
+                 count, total_ms, avg_ms, p95_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    run_id,
+                    query_id(sql),
+                    normalized_query,
+                    classify(sql).to_string(),
+                    stats.count as i64,
+                    durations_logged.then_some(stats.total_duration),
+                    durations_logged.then_some(stats.average_duration),
+                    durations_logged.then_some(stats.p95_duration),
+                ],
+            )
+            .map_err(sqlite_error)?;
+    }
+    for (class, errors) in &analysis.errors_by_sqlstate {
+        transaction
+            .execute(
+                "INSERT INTO errors (run_id, class, count) VALUES (?1, ?2, ?3)",
+                params![run_id, class, errors.count as i64],
+            )
+            .map_err(sqlite_error)?;
+    }
+    for hourly in &analysis.hourly_stats {
+        let average = match (durations_logged, hourly.query_count) {
+            (false, _) => None,
+            (true, 0) => Some(0.0),
+            (true, count) => Some(hourly.total_duration / count as f64),
+        };
+        transaction
+            .execute(
+                "INSERT INTO hourly (run_id, hour, count, avg_ms) VALUES (?1, ?2, ?3, ?4)",
+                params![run_id, hourly.hour, hourly.query_count as i64, average],
+            )
+            .map_err(sqlite_error)?;
+    }
+    transaction.commit().map_err(sqlite_error)?;
+    Ok(run_id)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn write_run(_exporter: &SqliteExporter, _path: &Path, _analysis: &AnalysisResult) -> Result<i64> {
+    Err(PgLogstatsError::Configuration {
+        message: "SQLite output requires building pg-logstats with `--features sqlite`".to_string(),
+        field: Some("output_format".to_string()),
+    })
+}
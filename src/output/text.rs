@@ -1,7 +1,127 @@
 //! Human-readable text output formatter for pg-logstats results
 
-use crate::{AnalysisResult, FindingSet, LogEntry, PgLogstatsError, Result, TimingAnalysis};
+use super::{Redact, ReportSections};
+use crate::analytics::business_hours::WEEKDAY_ABBREVIATIONS;
+use crate::analytics::io::{bytes_to_mb, per_hour};
+use crate::analytics::traffic::format_bucket_width;
+use crate::compare::slowest_change;
+use crate::timezone::hour_in;
+use crate::{
+    query_id, AnalysisResult, ApplicationStats, CancellationStats, ChangeKind, ClientHostStats,
+    CopyStats, FindingSet, FrequencyEstimate, GroupBy, LogEntry, LogEventStats, LogLevel,
+    MaintenanceStats, PgLogstatsError, PgStatStatementsComparison, PoolerSummary, PreparePhase,
+    PreparedStatementStats, QueryChange, QueryGroup, QuerySort, QueryStats, QueryType,
+    QueryWarning, ReplicationStats, ReportComparison, Result, RunStats, SessionEvent,
+    SessionEventKind, SlowestRankChange, TimeRange, TimingAnalysis, TrafficHistogram,
+    UnparsedStatements,
+};
+use crate::{BusinessHoursAnalysis, HourStats, HINT_RULES};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
+use std::sync::Arc;
+
+/// Default maximum number of characters shown for a query in text reports
+pub const DEFAULT_MAX_QUERY_LENGTH: usize = 120;
+
+/// Default width, in characters, of the longest duration histogram bar
+pub const DEFAULT_CHART_WIDTH: usize = 40;
+
+/// Failing statements listed under each SQLSTATE class
+pub const ERROR_STATEMENTS_PER_CLASS: usize = 3;
+
+/// Length of the longest query volume bar in the hourly table
+pub const HOURLY_BAR_WIDTH: usize = 20;
+
+/// Characters a traffic histogram row takes beyond a duration histogram row,
+/// for its longer bucket label and an error count after the bar
+const TRAFFIC_ROW_EXTRA: usize = 16;
+
+/// Hex digits of the `query_id` shown in the text report's ID column
+pub const SHORT_QUERY_ID_LEN: usize = 8;
+
+/// Timestamp format of the time range lines
+const TIME_RANGE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+
+/// Summary lines shown as [`NOT_LOGGED`] when no statement had a duration
+const DURATION_LABELS: [&str; 7] = [
+    "Total Duration",
+    "Average Duration",
+    "Std Dev Duration",
+    "Min Duration",
+    "P95 Duration",
+    "P99 Duration",
+    "Max Duration",
+];
+
+/// Stands in for the durations of a log that has none
+const NOT_LOGGED: &str = "n/a (durations not logged)";
+
+/// Whole-second span such as `2d 3h 4m 5s`, omitting leading zero units
+fn format_span(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (days, hours, minutes, seconds) = (
+        total / 86_400,
+        total / 3_600 % 24,
+        total / 60 % 60,
+        total % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h {}m {}s", days, hours, minutes, seconds)
+    } else if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Digits grouped in threes with `,`, e.g. `1,234,567`, whatever the locale
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Duration in the largest unit that keeps it at 1 or more, rounded to two
+/// decimals: `999.99 ms`, `1.00 s`, `1.50 min`
+pub fn format_duration(duration_ms: f64) -> String {
+    let seconds = duration_ms / 1000.0;
+    if (duration_ms.abs() * 100.0).round() < 100_000.0 {
+        format!("{:.2} ms", duration_ms)
+    } else if (seconds.abs() * 100.0).round() < 6_000.0 {
+        format!("{:.2} s", seconds)
+    } else {
+        format!("{:.2} min", seconds / 60.0)
+    }
+}
+
+/// Bar of `#` for `count`, scaled so `max_count` fills `width`; any nonzero
+/// count gets at least one mark so it stays visible
+fn bar(count: u64, max_count: u64, width: usize) -> String {
+    if count == 0 {
+        return String::new();
+    }
+    let len = (count as f64 / max_count as f64) * width as f64;
+    "#".repeat((len.round() as usize).max(1))
+}
+
+/// Signed percent such as `+40%`, or `n/a` without a baseline value
+fn format_percent(percent: Option<f64>) -> String {
+    match percent {
+        Some(percent) => format!("{:+.0}%", percent),
+        None => "n/a".to_string(),
+    }
+}
 
 /// ANSI color helpers (basic)
 pub fn bold(s: &str, color: Option<&str>, enable_color: bool) -> String {
@@ -20,10 +140,148 @@ pub fn bold(s: &str, color: Option<&str>, enable_color: bool) -> String {
     format!("{}{}\x1b[0m", code, s)
 }
 
+/// Leading digits of the query's `query_id`, enough to pass to `--filter-query-id`
+fn short_query_id(normalized_sql: &str) -> String {
+    let mut id = query_id(normalized_sql);
+    id.truncate(SHORT_QUERY_ID_LEN);
+    id
+}
+
+/// Before and after metrics of a changed query, e.g.
+/// `count 10 -> 12 (+20%), avg 1.00 -> 2.00 ms (+100%)`
+fn change_details(change: &QueryChange) -> String {
+    match (&change.baseline, &change.current) {
+        (Some(before), Some(after)) => {
+            let mut details = format!(
+                "count {} -> {} ({}), avg {:.2} -> {:.2} ms ({})",
+                before.count,
+                after.count,
+                format_percent(change.count_change_percent),
+                before.avg_duration_ms,
+                after.avg_duration_ms,
+                format_percent(change.avg_change_percent)
+            );
+            if let (Some(before_p95), Some(after_p95)) =
+                (before.p95_duration_ms, after.p95_duration_ms)
+            {
+                details.push_str(&format!(
+                    ", p95 {:.2} -> {:.2} ms ({})",
+                    before_p95,
+                    after_p95,
+                    format_percent(change.p95_change_percent)
+                ));
+            }
+            details
+        }
+        (None, Some(after)) => {
+            format!("count {}, avg {:.2} ms", after.count, after.avg_duration_ms)
+        }
+        (Some(before), None) => format!(
+            "count {}, avg {:.2} ms in baseline",
+            before.count, before.avg_duration_ms
+        ),
+        (None, None) => String::new(),
+    }
+}
+
+/// Groups of a grouped analysis that pass `keep`, busiest first
+fn grouped(
+    analysis: &AnalysisResult,
+    keep: impl Fn(&QueryGroup) -> bool,
+) -> Vec<(&str, &QueryGroup)> {
+    analysis
+        .query_groups_by_duration()
+        .into_iter()
+        .filter(|(_, group)| keep(group))
+        .collect()
+}
+
+/// Map entries ordered by count, highest first, then by key
+fn by_count_desc<'a>(
+    counts: impl IntoIterator<Item = (&'a String, &'a u64)>,
+) -> Vec<(&'a str, u64)> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(key, count)| (key.as_str(), *count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    counts
+}
+
+/// Shorten `query` to at most `max_chars` characters, ending with "…".
+///
+/// Counts characters rather than bytes, so multi-byte characters are never split.
+pub fn truncate_query(query: &str, max_chars: usize) -> Cow<'_, str> {
+    if query.char_indices().nth(max_chars).is_none() {
+        return Cow::Borrowed(query);
+    }
+    let end = query
+        .char_indices()
+        .nth(max_chars.saturating_sub(1))
+        .map(|(index, _)| index)
+        .unwrap_or(query.len());
+    Cow::Owned(format!("{}…", &query[..end]))
+}
+
+/// Which end of the log [`TextFormatter::format_log_entries`] lists, and how
+/// many entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryLimit {
+    /// The first N entries
+    Head(usize),
+    /// The last N entries
+    Tail(usize),
+}
+
+/// Kind of entry [`TextFormatter::format_log_entries`] lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryLevelFilter {
+    /// `ERROR`, `FATAL`, and `PANIC` entries
+    Error,
+    /// `WARNING` entries
+    Warning,
+    /// Statement entries
+    Statement,
+}
+
+impl EntryLevelFilter {
+    /// Whether `entry` is of this kind
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        match self {
+            EntryLevelFilter::Error => entry.is_error(),
+            EntryLevelFilter::Warning => entry.message_type == LogLevel::Warning,
+            EntryLevelFilter::Statement => entry.is_query(),
+        }
+    }
+
+    /// Name used in the entry listing's heading
+    fn name(&self) -> &'static str {
+        match self {
+            EntryLevelFilter::Error => "error",
+            EntryLevelFilter::Warning => "warning",
+            EntryLevelFilter::Statement => "statement",
+        }
+    }
+}
+
 /// Text formatter for analysis results
 pub struct TextFormatter {
     // Configuration for text formatting
     enable_color: bool,
+    sections: ReportSections,
+    max_query_length: Option<usize>,
+    slow_query_threshold: Option<f64>,
+    chart_width: usize,
+    query_sort: QuerySort,
+    human_units: bool,
+    display_timezone: Option<Tz>,
+    hour_timezone: Option<Tz>,
+    show_examples: bool,
+    redactor: Option<Arc<dyn Redact>>,
+    run_stats: Option<RunStats>,
+    slowest_changes: Vec<SlowestRankChange>,
+    entry_limit: Option<EntryLimit>,
+    entry_level: Option<EntryLevelFilter>,
 }
 
 impl TextFormatter {
@@ -31,6 +289,20 @@ impl TextFormatter {
     pub fn new() -> Self {
         Self {
             enable_color: false,
+            sections: ReportSections::all(),
+            max_query_length: Some(DEFAULT_MAX_QUERY_LENGTH),
+            slow_query_threshold: None,
+            chart_width: DEFAULT_CHART_WIDTH,
+            query_sort: QuerySort::default(),
+            human_units: true,
+            display_timezone: None,
+            hour_timezone: None,
+            show_examples: false,
+            redactor: None,
+            run_stats: None,
+            slowest_changes: Vec::new(),
+            entry_limit: None,
+            entry_level: None,
         }
     }
 
@@ -40,11 +312,193 @@ impl TextFormatter {
         self
     }
 
+    /// Render only the given report sections
+    pub fn with_sections(mut self, sections: ReportSections) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Truncate queries in the slowest and most frequent tables to `max_chars` characters
+    pub fn with_max_query_length(mut self, max_chars: usize) -> Self {
+        self.max_query_length = Some(max_chars);
+        self
+    }
+
+    /// Show queries in full instead of truncating them
+    pub fn with_wide(mut self, wide: bool) -> Self {
+        if wide {
+            self.max_query_length = None;
+        } else if self.max_query_length.is_none() {
+            self.max_query_length = Some(DEFAULT_MAX_QUERY_LENGTH);
+        }
+        self
+    }
+
+    /// Highlight durations above `threshold_ms` in red when color is enabled
+    pub fn with_slow_query_threshold(mut self, threshold_ms: f64) -> Self {
+        self.slow_query_threshold = Some(threshold_ms);
+        self
+    }
+
+    /// Order the most frequent queries by `sort`
+    pub fn with_query_sort(mut self, sort: QuerySort) -> Self {
+        self.query_sort = sort;
+        self
+    }
+
+    /// Group count digits and scale durations to s or min (the default), or
+    /// print plain counts and milliseconds for scripts that read the text
+    pub fn with_human_units(mut self, human_units: bool) -> Self {
+        self.human_units = human_units;
+        self
+    }
+
+    /// Print timestamps in `timezone` instead of UTC
+    pub fn with_display_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.display_timezone = timezone;
+        self
+    }
+
+    /// List the example executions of each slowest and most frequent query
+    /// under its row
+    pub fn with_show_examples(mut self, show_examples: bool) -> Self {
+        self.show_examples = show_examples;
+        self
+    }
+
+    /// Redact the formatted text with `redactor`; `None` writes it as it is
+    pub fn with_redactor(mut self, redactor: Option<Arc<dyn Redact>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Get the redactor applied to the formatted text
+    pub fn redactor(&self) -> Option<&dyn Redact> {
+        self.redactor.as_deref()
+    }
+
+    /// Add a column of rank and p95 changes against a baseline report to the
+    /// slowest queries
+    pub fn with_slowest_changes(mut self, changes: Vec<SlowestRankChange>) -> Self {
+        self.slowest_changes = changes;
+        self
+    }
+
+    /// End reports with a line of the run's stage times and input counts
+    pub fn with_run_stats(mut self, run_stats: RunStats) -> Self {
+        self.run_stats = Some(run_stats);
+        self
+    }
+
+    /// List only the first or last entries in `format_log_entries`; `None`
+    /// lists all of them
+    pub fn with_limit(mut self, limit: Option<EntryLimit>) -> Self {
+        self.entry_limit = limit;
+        self
+    }
+
+    /// List only the entries of one kind in `format_log_entries`; `None`
+    /// lists every kind
+    pub fn with_level_filter(mut self, level: Option<EntryLevelFilter>) -> Self {
+        self.entry_level = level;
+        self
+    }
+
+    /// Get the run stats written at the end of reports
+    pub fn run_stats(&self) -> Option<&RunStats> {
+        self.run_stats.as_ref()
+    }
+
+    /// One line of stage times and input counts
+    pub fn format_run_stats(&self, stats: &RunStats) -> Result<String> {
+        let stages = if stats.streamed {
+            format!(
+                "parse and analysis {}, output {}",
+                self.duration(stats.parse_ms + stats.analysis_ms),
+                self.duration(stats.output_ms)
+            )
+        } else {
+            format!(
+                "parse {}, analysis {}, output {}",
+                self.duration(stats.parse_ms),
+                self.duration(stats.analysis_ms),
+                self.duration(stats.output_ms)
+            )
+        };
+        let files = match stats.files.len() {
+            0 => String::new(),
+            1 => " from 1 file".to_string(),
+            count => format!(" from {} files", self.count(count as u64)),
+        };
+        let mut line = format!(
+            "Run Stats: {}; {} lines ({} bytes){}; {} entries parsed, {} lines skipped",
+            stages,
+            self.count(stats.lines_processed as u64),
+            self.count(stats.input_bytes),
+            files,
+            self.count(stats.entries_parsed as u64),
+            self.count(stats.lines_skipped as u64)
+        );
+        if let Some(peak_rss_kb) = stats.peak_rss_kb {
+            write!(line, "; peak RSS {} kB", self.count(peak_rss_kb)).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+        line.push('\n');
+        Ok(line)
+    }
+
+    /// Name `timezone` as the one the hour-of-day buckets were computed in;
+    /// `None` for UTC
+    pub fn with_hour_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.hour_timezone = timezone;
+        self
+    }
+
+    /// Scale the duration histogram so its longest bar is `width` characters
+    pub fn with_chart_width(mut self, width: usize) -> Self {
+        self.chart_width = width.max(1);
+        self
+    }
+
+    /// Get the width of the longest duration histogram bar
+    pub fn chart_width(&self) -> usize {
+        self.chart_width
+    }
+
+    /// Get the slow-query threshold used for highlighting, in milliseconds
+    pub fn slow_query_threshold(&self) -> Option<f64> {
+        self.slow_query_threshold
+    }
+
+    /// Get the maximum query length, or `None` when queries are shown in full
+    pub fn max_query_length(&self) -> Option<usize> {
+        self.max_query_length
+    }
+
+    /// Get whether counts and durations are formatted for reading
+    pub fn human_units(&self) -> bool {
+        self.human_units
+    }
+
+    /// Get the timezone timestamps are printed in; `None` for UTC
+    pub fn display_timezone(&self) -> Option<Tz> {
+        self.display_timezone
+    }
+
     /// Get whether color output is enabled
     pub fn is_color_enabled(&self) -> bool {
         self.enable_color
     }
 
+    /// Get the report sections that will be rendered
+    pub fn sections(&self) -> ReportSections {
+        self.sections
+    }
+
     /// Format query analysis results as text
     pub fn format_query_analysis(&self, analysis: &AnalysisResult) -> Result<String> {
         let mut output = String::new();
@@ -67,53 +521,101 @@ impl TextFormatter {
             message: e.to_string(),
             context: Some("text formatting".to_string()),
         })?;
-        writeln!(output, "Total Queries: {}", analysis.total_queries).map_err(|e| {
-            PgLogstatsError::Unexpected {
-                message: e.to_string(),
-                context: Some("text formatting".to_string()),
+        if self.sections.contains(ReportSections::SUMMARY) {
+            if let Some(time_range) = &analysis.time_range {
+                self.write_time_range(&mut output, time_range)?;
             }
-        })?;
-        writeln!(output, "Total Duration: {:.2} ms", analysis.total_duration).map_err(|e| {
-            PgLogstatsError::Unexpected {
-                message: e.to_string(),
-                context: Some("text formatting".to_string()),
+            if let Some(sample) = analysis.sample_estimate {
+                writeln!(output, "Estimated: {}", sample.note()).map_err(|e| {
+                    PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    }
+                })?;
             }
-        })?;
-        writeln!(
-            output,
-            "Average Duration: {:.2} ms",
-            analysis.average_duration
-        )
-        .map_err(|e| PgLogstatsError::Unexpected {
-            message: e.to_string(),
-            context: Some("text formatting".to_string()),
-        })?;
-        writeln!(output, "P95 Duration: {:.2} ms", analysis.p95_duration).map_err(|e| {
-            PgLogstatsError::Unexpected {
+            writeln!(
+                output,
+                "Total Queries: {}",
+                self.count(analysis.total_queries)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
                 message: e.to_string(),
                 context: Some("text formatting".to_string()),
+            })?;
+            if analysis.pooler_maintenance_count > 0 {
+                writeln!(
+                    output,
+                    "Pooler Maintenance: {}",
+                    self.count(analysis.pooler_maintenance_count)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
             }
-        })?;
-        writeln!(output, "P99 Duration: {:.2} ms", analysis.p99_duration).map_err(|e| {
-            PgLogstatsError::Unexpected {
-                message: e.to_string(),
-                context: Some("text formatting".to_string()),
+            self.write_io_volume(&mut output, analysis)?;
+            if let Some(version) = analysis.pg_version {
+                writeln!(output, "PostgreSQL Version: {}", version).map_err(|e| {
+                    PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    }
+                })?;
             }
-        })?;
-        writeln!(output, "Error Count: {}", analysis.error_count).map_err(|e| {
-            PgLogstatsError::Unexpected {
+            if analysis.durations_logged() {
+                self.write_summary_durations(&mut output, analysis)?;
+            } else {
+                for label in DURATION_LABELS {
+                    writeln!(output, "{}: {}", label, NOT_LOGGED).map_err(|e| {
+                        PgLogstatsError::Unexpected {
+                            message: e.to_string(),
+                            context: Some("text formatting".to_string()),
+                        }
+                    })?;
+                }
+            }
+            if let Some(note) = analysis.unlogged_statement_note() {
+                writeln!(output, "Note: {}", note).map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+            if let Some(note) = analysis.unlogged_duration_note() {
+                writeln!(output, "Note: {}", note).map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+        }
+        if self.sections.contains(ReportSections::ERRORS) {
+            let error_count = self.count(analysis.error_count);
+            writeln!(
+                output,
+                "Error Count: {}",
+                bold(
+                    &error_count,
+                    Some("red"),
+                    self.enable_color && analysis.error_count > 0
+                )
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
                 message: e.to_string(),
                 context: Some("text formatting".to_string()),
-            }
-        })?;
-        writeln!(output, "Connection Count: {}", analysis.connection_count).map_err(|e| {
-            PgLogstatsError::Unexpected {
+            })?;
+        }
+        if self.sections.contains(ReportSections::CONNECTIONS) {
+            writeln!(
+                output,
+                "Connection Count: {}",
+                self.count(analysis.connection_count)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
                 message: e.to_string(),
                 context: Some("text formatting".to_string()),
-            }
-        })?;
+            })?;
+        }
 
-        if !analysis.query_types.is_empty() {
+        if self.sections.contains(ReportSections::QUERY_TYPES) && !analysis.query_types.is_empty() {
             writeln!(
                 output,
                 "\n{}",
@@ -123,34 +625,65 @@ impl TextFormatter {
                 message: e.to_string(),
                 context: Some("text formatting".to_string()),
             })?;
-            for (query_type, count) in &analysis.query_types {
-                writeln!(output, "  {:>8}: {}", query_type, count).map_err(|e| {
-                    PgLogstatsError::Unexpected {
-                        message: e.to_string(),
-                        context: Some("text formatting".to_string()),
-                    }
+            writeln!(
+                output,
+                "  {:<12}  {:>8}  {:>14}  {:>10}  {:>10}  {:>10}",
+                "Type",
+                "Count",
+                self.duration_header("Total"),
+                self.duration_header("Avg"),
+                self.duration_header("P95"),
+                self.duration_header("Max")
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            let mut query_types: Vec<_> = analysis.query_types.iter().collect();
+            query_types.sort_by(|(a_type, _), (b_type, _)| {
+                QueryType::canonical_rank(a_type)
+                    .cmp(&QueryType::canonical_rank(b_type))
+                    .then_with(|| a_type.cmp(b_type))
+            });
+            let durations_logged = analysis.durations_logged();
+            let cell = |duration_ms: f64| match durations_logged {
+                true => self.duration_cell(duration_ms),
+                false => "n/a".to_string(),
+            };
+            for (query_type, stats) in query_types {
+                writeln!(
+                    output,
+                    "  {:<12}  {:>8}  {:>14}  {:>10}  {}  {:>10}",
+                    query_type,
+                    self.count(stats.count),
+                    cell(stats.total_duration),
+                    cell(stats.average_duration),
+                    self.highlight_duration(
+                        format!("{:>10}", cell(stats.p95_duration)),
+                        stats.p95_duration
+                    ),
+                    cell(stats.max_duration)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
                 })?;
             }
         }
 
-        if !analysis.slowest_queries.is_empty() {
+        if self.sections.contains(ReportSections::ERRORS) && !analysis.errors_by_severity.is_empty()
+        {
             writeln!(
                 output,
                 "\n{}",
-                bold("Slowest Queries:", Some("red"), self.enable_color)
+                bold("Errors by Severity:", Some("red"), self.enable_color)
             )
             .map_err(|e| PgLogstatsError::Unexpected {
                 message: e.to_string(),
                 context: Some("text formatting".to_string()),
             })?;
-            writeln!(output, "  {:>4}  {:>12}  Query", "#", "Duration (ms)").map_err(|e| {
-                PgLogstatsError::Unexpected {
-                    message: e.to_string(),
-                    context: Some("text formatting".to_string()),
-                }
-            })?;
-            for (i, (query, duration)) in analysis.slowest_queries.iter().enumerate() {
-                writeln!(output, "  {:>4}  {:>12.2}  {}", i + 1, duration, query).map_err(|e| {
+            for (severity, count) in &analysis.errors_by_severity {
+                writeln!(output, "  {:<8}  {:>8}", severity, count).map_err(|e| {
                     PgLogstatsError::Unexpected {
                         message: e.to_string(),
                         context: Some("text formatting".to_string()),
@@ -159,33 +692,204 @@ impl TextFormatter {
             }
         }
 
-        if !analysis.most_frequent_queries.is_empty() {
+        if self.sections.contains(ReportSections::ERRORS) && !analysis.errors_by_sqlstate.is_empty()
+        {
             writeln!(
                 output,
                 "\n{}",
-                bold("Most Frequent Queries:", Some("green"), self.enable_color)
+                bold("Errors by SQLSTATE:", Some("red"), self.enable_color)
             )
             .map_err(|e| PgLogstatsError::Unexpected {
                 message: e.to_string(),
                 context: Some("text formatting".to_string()),
             })?;
-            writeln!(output, "  {:>4}  {:>8}  Query", "#", "Count").map_err(|e| {
+            writeln!(output, "  {:>8}  {:>8}  Class", "SQLSTATE", "Count").map_err(|e| {
                 PgLogstatsError::Unexpected {
                     message: e.to_string(),
                     context: Some("text formatting".to_string()),
                 }
             })?;
-            for (i, (query, count)) in analysis.most_frequent_queries.iter().enumerate() {
-                writeln!(output, "  {:>4}  {:>8}  {}", i + 1, count, query).map_err(|e| {
+            for (class, errors) in &analysis.errors_by_sqlstate {
+                let codes = errors
+                    .codes
+                    .iter()
+                    .filter(|(code, _)| code.as_str() != class)
+                    .map(|(code, count)| format!("{} x{}", code, count))
+                    .collect::<Vec<_>>();
+                let name = if codes.is_empty() {
+                    errors.class_name.clone()
+                } else {
+                    format!("{} ({})", errors.class_name, codes.join(", "))
+                };
+                writeln!(output, "  {:>8}  {:>8}  {}", class, errors.count, name).map_err(|e| {
                     PgLogstatsError::Unexpected {
                         message: e.to_string(),
                         context: Some("text formatting".to_string()),
                     }
                 })?;
+                for (statement, count) in by_count_desc(&errors.statements)
+                    .into_iter()
+                    .take(ERROR_STATEMENTS_PER_CLASS)
+                {
+                    writeln!(
+                        output,
+                        "  {:>8}  {:>8}  {}",
+                        "",
+                        format!("{}x", count),
+                        self.display_query(statement)
+                    )
+                    .map_err(|e| PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    })?;
+                }
+            }
+        }
+
+        if self.sections.contains(ReportSections::ERRORS) && analysis.cancellations.total > 0 {
+            self.write_cancellations(&mut output, &analysis.cancellations)?;
+        }
+
+        if self.sections.contains(ReportSections::SLOWEST) {
+            let groups = grouped(analysis, |group| !group.slowest_queries.is_empty());
+            match analysis.group_by {
+                Some(group_by) if !groups.is_empty() => {
+                    let title = format!("Slowest Queries by {}:", group_by);
+                    writeln!(output, "\n{}", bold(&title, Some("red"), self.enable_color))
+                        .map_err(|e| PgLogstatsError::Unexpected {
+                            message: e.to_string(),
+                            context: Some("text formatting".to_string()),
+                        })?;
+                    for (key, group) in groups {
+                        self.write_group_heading(&mut output, group_by, key, group)?;
+                        self.write_slowest_rows(&mut output, &group.slowest_queries, None, &[])?;
+                    }
+                }
+                _ if !analysis.slowest_queries.is_empty() => {
+                    writeln!(
+                        output,
+                        "\n{}",
+                        bold("Slowest Queries:", Some("red"), self.enable_color)
+                    )
+                    .map_err(|e| PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    })?;
+                    self.write_slowest_rows(
+                        &mut output,
+                        &analysis.slowest_queries,
+                        Some((&analysis.query_stats, analysis.total_duration)),
+                        &self.slowest_changes,
+                    )?;
+                }
+                _ => {}
+            }
+        }
+
+        if self.sections.contains(ReportSections::MOST_FREQUENT) {
+            let groups = grouped(analysis, |group| !group.most_frequent_queries.is_empty());
+            match analysis.group_by {
+                Some(group_by) if !groups.is_empty() => {
+                    let title = format!("Most Frequent Queries by {}:", group_by);
+                    writeln!(
+                        output,
+                        "\n{}",
+                        bold(&title, Some("green"), self.enable_color)
+                    )
+                    .map_err(|e| PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    })?;
+                    self.write_frequency_estimate(&mut output, analysis.frequency_estimate)?;
+                    for (key, group) in groups {
+                        self.write_group_heading(&mut output, group_by, key, group)?;
+                        self.write_most_frequent_rows(
+                            &mut output,
+                            &group.most_frequent_queries,
+                            None,
+                        )?;
+                    }
+                }
+                _ if !analysis.most_frequent_queries.is_empty() => {
+                    writeln!(
+                        output,
+                        "\n{}",
+                        bold("Most Frequent Queries:", Some("green"), self.enable_color)
+                    )
+                    .map_err(|e| PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    })?;
+                    self.write_frequency_estimate(&mut output, analysis.frequency_estimate)?;
+                    self.write_most_frequent_rows(
+                        &mut output,
+                        &analysis.most_frequent_queries,
+                        Some((&analysis.query_stats, analysis.total_duration))
+                            .filter(|_| analysis.durations_logged()),
+                    )?;
+                }
+                _ => {}
             }
+            if !analysis.unparsed_statements.is_empty() {
+                self.write_unparsed_statements(&mut output, &analysis.unparsed_statements)?;
+            }
+        }
+
+        if self.sections.contains(ReportSections::BREAKDOWNS) {
+            self.write_breakdowns(&mut output, analysis)?;
+        }
+
+        if self.sections.contains(ReportSections::APPLICATIONS) && !analysis.applications.is_empty()
+        {
+            self.write_applications(
+                &mut output,
+                &analysis.applications,
+                analysis.durations_logged(),
+            )?;
+        }
+
+        if self.sections.contains(ReportSections::CLIENTS) && !analysis.client_hosts.is_empty() {
+            self.write_clients(&mut output, &analysis.client_hosts)?;
+        }
+
+        if self.sections.contains(ReportSections::COPY) && !analysis.copy_stats.tables.is_empty() {
+            self.write_copy(&mut output, &analysis.copy_stats)?;
+        }
+
+        if self.sections.contains(ReportSections::REPLICATION) && !analysis.replication.is_empty() {
+            self.write_replication(&mut output, &analysis.replication)?;
+        }
+
+        if self.sections.contains(ReportSections::MAINTENANCE) && !analysis.maintenance.is_empty() {
+            self.write_maintenance(&mut output, &analysis.maintenance)?;
+        }
+
+        if self.sections.contains(ReportSections::EVENTS) && !analysis.log_events.is_empty() {
+            self.write_log_events(&mut output, &analysis.log_events)?;
+        }
+
+        if self.sections.contains(ReportSections::PREPARED_STATEMENTS)
+            && !analysis.prepared_statements.is_empty()
+        {
+            self.write_prepared_statements(&mut output, &analysis.prepared_statements)?;
+        }
+
+        if self.sections.contains(ReportSections::HISTOGRAM)
+            && !analysis.duration_histogram.is_empty()
+            && analysis.durations_logged()
+        {
+            self.write_histogram(&mut output, &analysis.duration_histogram)?;
+        }
+
+        if let Some(traffic) = &analysis.traffic_histogram {
+            self.write_traffic_histogram(&mut output, traffic)?;
+        }
+
+        if let Some(warnings) = &analysis.query_warnings {
+            self.write_query_warnings(&mut output, warnings)?;
         }
 
-        Ok(output)
+        Ok(self.redacted(output))
     }
 
     /// Format timing analysis results as text
@@ -210,19 +914,281 @@ impl TextFormatter {
             message: e.to_string(),
             context: Some("text formatting".to_string()),
         })?;
-        writeln!(
+        let durations_logged = analysis.timed_queries > 0;
+        if self.sections.contains(ReportSections::SUMMARY) && !durations_logged {
+            for label in [
+                "Average Response Time",
+                "95th Percentile",
+                "99th Percentile",
+            ] {
+                writeln!(output, "{}: {}", label, NOT_LOGGED).map_err(|e| {
+                    PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    }
+                })?;
+            }
+        } else if self.sections.contains(ReportSections::SUMMARY) {
+            writeln!(
+                output,
+                "Average Response Time: {}",
+                self.duration(analysis.average_response_time_ms)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            writeln!(
+                output,
+                "95th Percentile: {}",
+                self.duration(analysis.p95_response_time_ms)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            writeln!(
+                output,
+                "99th Percentile: {}",
+                self.duration(analysis.p99_response_time_ms)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        if self.sections.contains(ReportSections::HOURLY) {
+            if let Some(busiest) = analysis.busiest_hour() {
+                self.write_peak_usage(&mut output, analysis, busiest)?;
+            }
+            self.write_daily_activity(&mut output, analysis, durations_logged)?;
+            if !analysis.business_hours.is_empty() {
+                self.write_business_hours(&mut output, &analysis.business_hours)?;
+            }
+        }
+
+        Ok(self.redacted(output))
+    }
+
+    /// Queries, total duration, and connections of each date of the log,
+    /// from the hours in `by_hour`; nothing for a log within a single date,
+    /// which the hourly table already covers. Totals are `n/a` unless
+    /// `durations_logged`
+    fn write_daily_activity(
+        &self,
+        output: &mut String,
+        analysis: &TimingAnalysis,
+        durations_logged: bool,
+    ) -> Result<()> {
+        let mut dates: BTreeMap<String, HourStats> = BTreeMap::new();
+        for (&hour, stats) in &analysis.by_hour {
+            dates
+                .entry(self.timestamp(hour, "%Y-%m-%d %a"))
+                .or_default()
+                .merge(stats);
+        }
+        if dates.len() < 2 {
+            return Ok(());
+        }
+
+        writeln!(
+            output,
+            "\n{}",
+            bold("Daily Activity:", Some("yellow"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {:<14}  {:>8}  {:>14}  {:>11}",
+            "Date",
+            "Queries",
+            self.duration_header("Total"),
+            "Connections"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for (date, stats) in &dates {
+            writeln!(
+                output,
+                "  {:<14}  {:>8}  {:>14}  {:>11}",
+                date,
+                self.count(stats.query_count),
+                match durations_logged {
+                    true => self.duration_cell(stats.total_duration),
+                    false => "n/a".to_string(),
+                },
+                self.count(stats.connection_count)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Queries, P95 duration, and errors inside and outside business hours,
+    /// then per weekday
+    fn write_business_hours(
+        &self,
+        output: &mut String,
+        analysis: &BusinessHoursAnalysis,
+    ) -> Result<()> {
+        let title = format!(
+            "Business Hours ({}, UTC{}):",
+            analysis.window, analysis.timezone
+        );
+        writeln!(
+            output,
+            "\n{}",
+            bold(&title, Some("yellow"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {:<14}  {:>8}  {:>10}  {:>6}",
+            "Period",
+            "Queries",
+            self.duration_header("P95"),
+            "Errors"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+
+        let periods = [
+            ("Business hours", &analysis.business_hours),
+            ("Off hours", &analysis.off_hours),
+        ]
+        .into_iter()
+        .chain(analysis.weekdays.iter().filter_map(|weekday| {
+            Some((
+                *WEEKDAY_ABBREVIATIONS.get(weekday.day as usize)?,
+                &weekday.stats,
+            ))
+        }));
+        for (label, stats) in periods {
+            writeln!(
+                output,
+                "  {:<14}  {:>8}  {:>10}  {:>6}",
+                label,
+                self.count(stats.query_count),
+                stats
+                    .p95_duration_ms
+                    .map_or_else(|| "n/a".to_string(), |p95| self.duration_cell(p95)),
+                self.count(stats.error_count)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn write_peak_usage(
+        &self,
+        output: &mut String,
+        analysis: &TimingAnalysis,
+        busiest: u32,
+    ) -> Result<()> {
+        let mut peak_hours = analysis.peak_hours.clone();
+        peak_hours.sort_unstable();
+        let peak_hours = if peak_hours.is_empty() {
+            "none".to_string()
+        } else {
+            peak_hours
+                .iter()
+                .map(|hour| format!("{:02}:00", hour))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let queries = analysis
+            .hourly_query_counts
+            .get(&busiest)
+            .copied()
+            .unwrap_or(0);
+
+        writeln!(
+            output,
+            "\n{}",
+            bold(
+                &self.hourly_title("Peak Usage"),
+                Some("yellow"),
+                self.enable_color
+            )
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
             output,
-            "Average Response Time: {}ms",
-            analysis.average_response_time.num_milliseconds()
+            "  Busiest Hour: {:02}:00 ({} queries)",
+            busiest,
+            self.count(queries)
         )
         .map_err(|e| PgLogstatsError::Unexpected {
             message: e.to_string(),
             context: Some("text formatting".to_string()),
         })?;
+        writeln!(output, "  Peak Hours: {}", peak_hours).map_err(|e| {
+            PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Format the hourly table: queries, average and P95 duration, errors, and
+    /// connections per hour of day, with a bar for query volume.
+    ///
+    /// Every hour of day the log's time range covers gets a row, wrapping
+    /// around midnight, so quiet hours show as zeros. Empty when the `hourly` section is off or there is
+    /// no activity.
+    pub fn format_hourly_report(
+        &self,
+        analysis: &AnalysisResult,
+        timing: &TimingAnalysis,
+    ) -> Result<String> {
+        let mut output = String::new();
+        let hours = analysis
+            .hourly_stats
+            .iter()
+            .map(|stats| stats.hour)
+            .chain(timing.connection_patterns.keys().copied());
+        let active: Vec<u32> = hours.collect();
+        let (Some(&first), Some(&last)) = (active.iter().min(), active.iter().max()) else {
+            return Ok(output);
+        };
+        let covered = analysis
+            .time_range
+            .as_ref()
+            .map(|range| self.hours_covered(range))
+            .filter(|covered| active.iter().all(|hour| covered.contains(hour)))
+            .unwrap_or_else(|| (first..=last).collect());
+        if !self.sections.contains(ReportSections::HOURLY) {
+            return Ok(output);
+        }
+
         writeln!(
             output,
-            "95th Percentile: {}ms",
-            analysis.p95_response_time.num_milliseconds()
+            "\n{}",
+            bold(
+                &self.hourly_title("Hourly Activity"),
+                Some("yellow"),
+                self.enable_color
+            )
         )
         .map_err(|e| PgLogstatsError::Unexpected {
             message: e.to_string(),
@@ -230,15 +1196,58 @@ impl TextFormatter {
         })?;
         writeln!(
             output,
-            "99th Percentile: {}ms",
-            analysis.p99_response_time.num_milliseconds()
+            "  {:>4}  {:>8}  {:>10}  {:>10}  {:>6}  {:>11}",
+            "Hour",
+            "Queries",
+            self.duration_header("Avg"),
+            self.duration_header("P95"),
+            "Errors",
+            "Connections"
         )
         .map_err(|e| PgLogstatsError::Unexpected {
             message: e.to_string(),
             context: Some("text formatting".to_string()),
         })?;
+        let max_count = analysis
+            .hourly_stats
+            .iter()
+            .map(|stats| stats.query_count)
+            .max()
+            .unwrap_or(0);
+        let bar_width = self.chart_width.min(HOURLY_BAR_WIDTH);
+        for hour in covered {
+            let stats = analysis
+                .hourly_stats
+                .iter()
+                .find(|stats| stats.hour == hour);
+            let count = stats.map_or(0, |stats| stats.query_count);
+            let average = match stats {
+                Some(stats) if stats.query_count > 0 => {
+                    stats.total_duration / stats.query_count as f64
+                }
+                _ => 0.0,
+            };
+            let cell = |duration_ms: f64| match analysis.durations_logged() {
+                true => self.duration_cell(duration_ms),
+                false => "n/a".to_string(),
+            };
+            let row = format!(
+                "  {:>4}  {:>8}  {:>10}  {:>10}  {:>6}  {:>11}  {}",
+                format!("{:02}", hour),
+                self.count(count),
+                cell(average),
+                cell(stats.map_or(0.0, |stats| stats.p95_duration)),
+                self.count(stats.map_or(0, |stats| stats.error_count)),
+                self.count(timing.connection_patterns.get(&hour).copied().unwrap_or(0)),
+                bar(count, max_count, bar_width)
+            );
+            writeln!(output, "{}", row.trim_end()).map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
 
-        Ok(output)
+        Ok(self.redacted(output))
     }
 
     /// Format structured findings as a compact human-readable view.
@@ -303,19 +1312,22 @@ impl TextFormatter {
             }
         }
 
-        Ok(output)
+        Ok(self.redacted(output))
     }
 
-    /// Format log entries as text
-    pub fn format_log_entries(&self, entries: &[LogEntry]) -> Result<String> {
+    /// Format a comparison of two reports as text
+    pub fn format_comparison(&self, comparison: &ReportComparison) -> Result<String> {
         let mut output = String::new();
 
         writeln!(
             output,
             "{}",
             bold(
-                &format!("Log Entries ({} total)", entries.len()),
-                Some("magenta"),
+                &format!(
+                    "Changes Since Baseline (over {}%)",
+                    comparison.threshold_percent
+                ),
+                Some("cyan"),
                 self.enable_color
             )
         )
@@ -325,22 +1337,210 @@ impl TextFormatter {
         })?;
         writeln!(
             output,
-            "{}",
-            bold("================", Some("magenta"), self.enable_color)
+            "Regressed: {}  Improved: {}  New: {}  Disappeared: {}",
+            comparison.of_kind(ChangeKind::Regressed).count(),
+            comparison.of_kind(ChangeKind::Improved).count(),
+            comparison.of_kind(ChangeKind::New).count(),
+            comparison.of_kind(ChangeKind::Disappeared).count()
         )
         .map_err(|e| PgLogstatsError::Unexpected {
             message: e.to_string(),
             context: Some("text formatting".to_string()),
         })?;
+        if comparison.changes.is_empty() {
+            return Ok(output);
+        }
 
-        for (i, entry) in entries.iter().enumerate() {
+        writeln!(
+            output,
+            "  {:>4}  {:<11}  {:>15}  {:<8}  Query",
+            "#", "Change", "Time Delta (ms)", "ID"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for (i, change) in comparison.changes.iter().enumerate() {
+            let (label, color) = match change.kind {
+                ChangeKind::Regressed => ("regressed", "red"),
+                ChangeKind::Improved => ("improved", "green"),
+                ChangeKind::New => ("new", "yellow"),
+                ChangeKind::Disappeared => ("disappeared", "blue"),
+            };
             writeln!(
                 output,
-                "[{}] {} {}: {}",
+                "  {:>4}  {}  {:>+15.2}  {:<8}  {}",
                 i + 1,
-                entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                entry.message_type,
-                entry.message
+                bold(&format!("{:<11}", label), Some(color), self.enable_color),
+                change.time_delta_ms,
+                &change.query_id[..change.query_id.len().min(SHORT_QUERY_ID_LEN)],
+                self.display_query(&change.query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            writeln!(output, "        {}", change_details(change)).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+
+        Ok(self.redacted(output))
+    }
+
+    /// Format the report's queries next to their `pg_stat_statements` rows
+    pub fn format_pg_stat_statements(
+        &self,
+        comparison: &PgStatStatementsComparison,
+    ) -> Result<String> {
+        let mut output = String::new();
+
+        writeln!(
+            output,
+            "{}",
+            bold(
+                "pg_stat_statements Cross-Reference",
+                Some("cyan"),
+                self.enable_color
+            )
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Export Rows: {}  Missing from pg_stat_statements: {}  Missing from Logs: {}",
+            self.count(comparison.rows as u64),
+            comparison.missing_from_pg_stat_statements().count(),
+            comparison.missing_from_logs.len()
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        if !comparison.queries.is_empty() {
+            writeln!(
+                output,
+                "  {:>9}  {:>13}  {:>9}  {:>13}  {:<8}  Query",
+                "Log Count",
+                self.duration_header("Log P95"),
+                "PSS Calls",
+                self.duration_header("PSS Mean"),
+                "ID"
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        for query in &comparison.queries {
+            let (calls, mean) = match &query.pg_stat_statements {
+                Some(row) => (
+                    self.count(row.calls),
+                    self.duration_cell(row.mean_exec_time),
+                ),
+                None => ("missing".to_string(), "-".to_string()),
+            };
+            writeln!(
+                output,
+                "  {:>9}  {:>13}  {:>9}  {:>13}  {:<8}  {}",
+                self.count(query.log_count),
+                self.duration_cell(query.log_p95_ms),
+                calls,
+                mean,
+                &query.query_id[..query.query_id.len().min(SHORT_QUERY_ID_LEN)],
+                self.display_query(&query.query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        if !comparison.missing_from_logs.is_empty() {
+            writeln!(
+                output,
+                "Busiest pg_stat_statements queries not in the log report:\n  {:>9}  {:>13}  {:>13}  Query",
+                "Calls",
+                self.duration_header("Total"),
+                self.duration_header("Mean")
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        for row in &comparison.missing_from_logs {
+            let query = row.query.split_whitespace().collect::<Vec<_>>().join(" ");
+            writeln!(
+                output,
+                "  {:>9}  {:>13}  {:>13}  {}",
+                self.count(row.calls),
+                self.duration_cell(row.total_exec_time),
+                self.duration_cell(row.mean_exec_time),
+                self.display_query(&query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+
+        Ok(self.redacted(output))
+    }
+
+    /// Format a pgbouncer pooler summary as text
+    pub fn format_pooler_summary(&self, summary: &PoolerSummary) -> Result<String> {
+        let mut output = String::new();
+
+        writeln!(
+            output,
+            "{}",
+            bold("Pooler Summary", Some("cyan"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Logins: {}  Closes: {}  Pooler Errors: {}",
+            summary.total_logins, summary.total_closes, summary.total_pooler_errors
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+
+        if summary.stats_samples > 0 {
+            writeln!(
+                output,
+                "Average Throughput: {:.1} xacts/s, {:.1} queries/s ({} samples)",
+                summary.avg_xacts_per_sec, summary.avg_queries_per_sec, summary.stats_samples
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            writeln!(
+                output,
+                "Average Client Wait: {:.0}us",
+                summary.avg_wait_time_us
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+
+        for pool in &summary.pools {
+            writeln!(
+                output,
+                "  {}/{}: {} logins, {} closes, {} pooler errors",
+                pool.database, pool.user, pool.logins, pool.closes, pool.pooler_errors
             )
             .map_err(|e| PgLogstatsError::Unexpected {
                 message: e.to_string(),
@@ -348,7 +1548,1464 @@ impl TextFormatter {
             })?;
         }
 
-        Ok(output)
+        Ok(self.redacted(output))
+    }
+
+    /// Format a session timeline as one line per step:
+    /// `2024-08-15 10:00:01.250 [4242] statement      12.50 ms  SELECT ...`,
+    /// with the session id after the PID when the prefix logged one
+    pub fn format_session_timeline(&self, events: &[SessionEvent]) -> Result<String> {
+        let mut output = String::new();
+
+        writeln!(
+            output,
+            "{}",
+            bold("Session Timeline", Some("cyan"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        if events.is_empty() {
+            writeln!(output, "No session events for the selected processes").map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+
+        for event in events {
+            let duration = match event.duration_ms {
+                Some(duration_ms) => self
+                    .highlight_duration(format!("{:>10}", self.duration(duration_ms)), duration_ms),
+                None => " ".repeat(10),
+            };
+            let message = match (event.kind, &event.sqlstate) {
+                (SessionEventKind::Statement, _) => self.display_query(&event.message),
+                (_, Some(sqlstate)) => Cow::Owned(format!("[{}] {}", sqlstate, event.message)),
+                _ => Cow::Borrowed(event.message.as_str()),
+            };
+            let session = match &event.session_id {
+                Some(session_id) => format!("{} {}", event.process_id, session_id),
+                None => event.process_id.clone(),
+            };
+            writeln!(
+                output,
+                "{} [{}] {:<13} {}  {}",
+                self.timestamp(event.timestamp, "%Y-%m-%d %H:%M:%S%.3f"),
+                session,
+                event.kind,
+                duration,
+                message
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+
+        Ok(self.redacted(output))
+    }
+
+    /// Format log entries as text, numbered by their position in `entries`.
+    ///
+    /// With a level filter only the entries of that kind are listed, and
+    /// with a limit only the first or last of them; a line says how many
+    /// were left out.
+    pub fn format_log_entries(&self, entries: &[LogEntry]) -> Result<String> {
+        let mut output = String::new();
+
+        let matching: Vec<_> = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| self.entry_level.is_none_or(|level| level.matches(entry)))
+            .collect();
+        let (shown, omitted) = match self.entry_limit {
+            Some(EntryLimit::Head(limit)) if limit < matching.len() => {
+                (&matching[..limit], matching.len() - limit)
+            }
+            Some(EntryLimit::Tail(limit)) if limit < matching.len() => {
+                (&matching[matching.len() - limit..], matching.len() - limit)
+            }
+            _ => (&matching[..], 0),
+        };
+        let mut heading = format!("Log Entries ({} total", self.count(entries.len() as u64));
+        if let Some(level) = self.entry_level {
+            heading.push_str(&format!(
+                ", {} {}{}",
+                self.count(matching.len() as u64),
+                level.name(),
+                if matching.len() == 1 { "" } else { "s" }
+            ));
+        }
+        heading.push(')');
+        let omitted_line = |position: &str| {
+            format!(
+                "  ... {} {} entries omitted",
+                self.count(omitted as u64),
+                position
+            )
+        };
+
+        writeln!(
+            output,
+            "{}",
+            bold(&heading, Some("magenta"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "{}",
+            bold("================", Some("magenta"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+
+        if omitted > 0 && matches!(self.entry_limit, Some(EntryLimit::Tail(_))) {
+            writeln!(output, "{}", omitted_line("earlier")).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+
+        for &(i, entry) in shown {
+            writeln!(
+                output,
+                "[{}] {} {}: {}",
+                i + 1,
+                self.timestamp(entry.timestamp, "%Y-%m-%d %H:%M:%S"),
+                entry.message_type,
+                entry.message
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            // A multi-statement line lists each of its statements
+            let queries = entry.queries.as_deref().unwrap_or_default();
+            if queries.len() > 1 {
+                for (j, query) in queries.iter().enumerate() {
+                    writeln!(
+                        output,
+                        "    {}/{} {}: {}",
+                        j + 1,
+                        queries.len(),
+                        query.query_type,
+                        query.sql
+                    )
+                    .map_err(|e| PgLogstatsError::Unexpected {
+                        message: e.to_string(),
+                        context: Some("text formatting".to_string()),
+                    })?;
+                }
+            }
+        }
+
+        if omitted > 0 && matches!(self.entry_limit, Some(EntryLimit::Head(_))) {
+            writeln!(output, "{}", omitted_line("later")).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+
+        Ok(self.redacted(output))
+    }
+
+    /// Write the column header and rows of a slowest-queries table, with the
+    /// examples in `query_stats` when they are shown
+    /// Slowest-query rows; with the query stats and total duration of the
+    /// analysis, also the calls and share of the total time of each query,
+    /// and with `changes`, the rank and p95 changes against a baseline
+    fn write_slowest_rows(
+        &self,
+        output: &mut String,
+        rows: &[(String, f64)],
+        totals: Option<(&HashMap<String, QueryStats>, f64)>,
+        changes: &[SlowestRankChange],
+    ) -> Result<()> {
+        let mut header = format!("  {:>4}  {:>12}", "#", self.duration_header("Duration"));
+        if totals.is_some() {
+            header.push_str(&format!("  {:>8}  {:>7}", "Calls", "% Total"));
+        }
+        if !changes.is_empty() {
+            header.push_str(&format!("  {:<18}", "vs Baseline"));
+        }
+        writeln!(output, "{}  {:<8}  Query", header, "ID").map_err(|e| {
+            PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            }
+        })?;
+        for (i, (query, duration)) in rows.iter().enumerate() {
+            let stats = totals.and_then(|(stats, _)| stats.get(query));
+            let mut row = format!(
+                "  {:>4}  {}",
+                i + 1,
+                self.highlight_duration(
+                    format!("{:>12}", self.duration_cell(*duration)),
+                    *duration
+                )
+            );
+            if let Some((_, total_duration)) = totals {
+                let (calls, percent) = match stats {
+                    Some(stats) if total_duration > 0.0 => (
+                        stats.count.to_string(),
+                        format!("{:.2}%", stats.total_duration / total_duration * 100.0),
+                    ),
+                    Some(stats) => (stats.count.to_string(), "-".to_string()),
+                    None => ("-".to_string(), "-".to_string()),
+                };
+                row.push_str(&format!("  {:>8}  {:>7}", calls, percent));
+            }
+            if !changes.is_empty() {
+                row.push_str(&format!(
+                    "  {:<18}",
+                    self.rank_change_cell(changes, i + 1, query)
+                ));
+            }
+            writeln!(
+                output,
+                "{}  {}  {}",
+                row,
+                short_query_id(query),
+                self.display_query(query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            if let Some(stats) = stats {
+                self.write_examples(output, stats)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rank move and p95 change of a slowest query against the baseline,
+    /// e.g. `↑2 p95 +1.20 s`, or `new` when the baseline did not list it
+    fn rank_change_cell(&self, changes: &[SlowestRankChange], rank: usize, query: &str) -> String {
+        let Some(change) = slowest_change(changes, rank, &query_id(query)) else {
+            return "-".to_string();
+        };
+        let mut cell = match change.rank_delta {
+            None => "new".to_string(),
+            Some(0) => "=".to_string(),
+            Some(delta) if delta > 0 => format!("\u{2191}{}", delta),
+            Some(delta) => format!("\u{2193}{}", -delta),
+        };
+        if let Some(delta) = change.p95_delta_ms {
+            let sign = if delta < 0.0 { '-' } else { '+' };
+            cell.push_str(&format!(" p95 {}{}", sign, self.duration_cell(delta.abs())));
+        }
+        cell
+    }
+
+    /// Write the example executions of a query under its row, when shown
+    fn write_examples(&self, output: &mut String, stats: &QueryStats) -> Result<()> {
+        if !self.show_examples {
+            return Ok(());
+        }
+        for example in &stats.examples {
+            let session = match (&example.user, &example.database) {
+                (Some(user), Some(database)) => format!("{}@{}", user, database),
+                (Some(user), None) => user.clone(),
+                (None, Some(database)) => format!("@{}", database),
+                (None, None) => "-".to_string(),
+            };
+            let sql = example.sql.split_whitespace().collect::<Vec<_>>().join(" ");
+            writeln!(
+                output,
+                "        e.g. {}  {}  {}  {}",
+                self.timestamp(example.timestamp, TIME_RANGE_FORMAT),
+                self.duration_cell(example.duration),
+                session,
+                self.display_query(&sql)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the column header and rows of a most-frequent-queries table.
+    ///
+    /// With per-query stats and the total duration, rows are ordered by the
+    /// configured sort and show their time and share of the total.
+    fn write_most_frequent_rows(
+        &self,
+        output: &mut String,
+        rows: &[(String, u64)],
+        durations: Option<(&HashMap<String, QueryStats>, f64)>,
+    ) -> Result<()> {
+        if let Some((query_stats, total_duration)) = durations {
+            return self.write_timed_frequent_rows(output, rows, query_stats, total_duration);
+        }
+        writeln!(output, "  {:>4}  {:>8}  {:<8}  Query", "#", "Count", "ID").map_err(|e| {
+            PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            }
+        })?;
+        for (i, (query, count)) in rows.iter().enumerate() {
+            writeln!(
+                output,
+                "  {:>4}  {:>8}  {}  {}",
+                i + 1,
+                self.count(*count),
+                short_query_id(query),
+                self.display_query(query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write most-frequent rows ordered by the configured sort, with count,
+    /// total time, share of `total_duration`, and average time
+    fn write_timed_frequent_rows(
+        &self,
+        output: &mut String,
+        rows: &[(String, u64)],
+        query_stats: &HashMap<String, QueryStats>,
+        total_duration: f64,
+    ) -> Result<()> {
+        let mut rows = rows.to_vec();
+        self.query_sort.sort_rows(&mut rows, query_stats);
+
+        writeln!(
+            output,
+            "  {:>4}  {:>8}  {:>12}  {:>7}  {:>10}  {:<8}  Query",
+            "#",
+            "Count",
+            self.duration_header("Total"),
+            "% Total",
+            self.duration_header("Avg"),
+            "ID"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for (i, (query, count)) in rows.iter().enumerate() {
+            let (total, percent, average) = match query_stats.get(query) {
+                Some(stats) => (
+                    self.duration_cell(stats.total_duration),
+                    if total_duration > 0.0 {
+                        format!("{:.2}%", stats.total_duration / total_duration * 100.0)
+                    } else {
+                        "-".to_string()
+                    },
+                    self.duration_cell(stats.average_duration),
+                ),
+                None => ("-".to_string(), "-".to_string(), "-".to_string()),
+            };
+            writeln!(
+                output,
+                "  {:>4}  {:>8}  {:>12}  {:>7}  {:>10}  {}  {}",
+                i + 1,
+                self.count(*count),
+                total,
+                percent,
+                average,
+                short_query_id(query),
+                self.display_query(query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            if let Some(stats) = query_stats.get(query) {
+                self.write_examples(output, stats)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the note that query counts are estimates, when they are
+    fn write_frequency_estimate(
+        &self,
+        output: &mut String,
+        estimate: Option<FrequencyEstimate>,
+    ) -> Result<()> {
+        let Some(estimate) = estimate else {
+            return Ok(());
+        };
+        writeln!(
+            output,
+            "  Approximate counts: the {} most frequent distinct queries were tracked; \
+             each count may be up to {} too high",
+            estimate.capacity, estimate.max_overcount
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })
+    }
+
+    /// Write the line introducing one group of a grouped query table
+    fn write_group_heading(
+        &self,
+        output: &mut String,
+        group_by: GroupBy,
+        key: &str,
+        group: &QueryGroup,
+    ) -> Result<()> {
+        writeln!(
+            output,
+            "  {} {} ({} queries, {} total)",
+            group_by,
+            bold(key, None, self.enable_color),
+            self.count(group.total_queries),
+            self.duration(group.total_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })
+    }
+
+    /// Write the duration lines of the summary
+    fn write_summary_durations(
+        &self,
+        output: &mut String,
+        analysis: &AnalysisResult,
+    ) -> Result<()> {
+        writeln!(
+            output,
+            "Total Duration: {}",
+            self.duration(analysis.total_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Average Duration: {}",
+            self.duration(analysis.average_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Std Dev Duration: {}",
+            self.duration(analysis.stddev_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Min Duration: {}",
+            self.duration(analysis.min_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "P95 Duration: {}",
+            self.highlight_duration(self.duration(analysis.p95_duration), analysis.p95_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "P99 Duration: {}",
+            self.highlight_duration(self.duration(analysis.p99_duration), analysis.p99_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Max Duration: {}",
+            self.highlight_duration(self.duration(analysis.max_duration), analysis.max_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        Ok(())
+    }
+
+    /// Write the per-user and per-database tables
+    fn write_breakdowns(&self, output: &mut String, analysis: &AnalysisResult) -> Result<()> {
+        if !analysis.queries_by_user.is_empty() {
+            writeln!(
+                output,
+                "\n{}",
+                bold("Queries by User:", Some("blue"), self.enable_color)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            writeln!(
+                output,
+                "  {:<20}  {:>8}  {:>14}",
+                "User",
+                "Count",
+                self.duration_header("Duration")
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            for (user, count) in by_count_desc(&analysis.queries_by_user) {
+                let duration = match analysis.durations_logged() {
+                    true => {
+                        let duration = analysis.duration_by_user.get(user).copied();
+                        self.duration_cell(duration.unwrap_or(0.0))
+                    }
+                    false => "n/a".to_string(),
+                };
+                writeln!(
+                    output,
+                    "  {:<20}  {:>8}  {:>14}",
+                    user,
+                    self.count(count),
+                    duration
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+        }
+
+        if !analysis.queries_by_database.is_empty() || !analysis.errors_by_database.is_empty() {
+            writeln!(
+                output,
+                "\n{}",
+                bold("Queries by Database:", Some("blue"), self.enable_color)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            writeln!(
+                output,
+                "  {:<20}  {:>8}  {:>8}",
+                "Database", "Queries", "Errors"
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            let mut databases = analysis.queries_by_database.clone();
+            for database in analysis.errors_by_database.keys() {
+                databases.entry(database.clone()).or_insert(0);
+            }
+            for (database, count) in by_count_desc(&databases) {
+                let errors = analysis
+                    .errors_by_database
+                    .get(database)
+                    .copied()
+                    .unwrap_or(0);
+                writeln!(
+                    output,
+                    "  {:<20}  {:>8}  {:>8}",
+                    database,
+                    self.count(count),
+                    self.count(errors)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the per-application table, each row followed by its slowest
+    /// queries; without `durations_logged`, durations show as `n/a`
+    fn write_applications(
+        &self,
+        output: &mut String,
+        applications: &[ApplicationStats],
+        durations_logged: bool,
+    ) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Applications:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {:<20}  {:>8}  {:>14}  {:>10}  {:>6}",
+            "Application",
+            "Queries",
+            self.duration_header("Duration"),
+            self.duration_header("P95"),
+            "Errors"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for application in applications {
+            if !durations_logged {
+                writeln!(
+                    output,
+                    "  {:<20}  {:>8}  {:>14}  {:>10}  {:>6}",
+                    application.application_name,
+                    self.count(application.query_count),
+                    "n/a",
+                    "n/a",
+                    self.count(application.error_count)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+                continue;
+            }
+            writeln!(
+                output,
+                "  {:<20}  {:>8}  {:>14}  {}  {:>6}",
+                application.application_name,
+                self.count(application.query_count),
+                self.duration_cell(application.total_duration),
+                self.highlight_duration(
+                    format!("{:>10}", self.duration_cell(application.p95_duration)),
+                    application.p95_duration
+                ),
+                self.count(application.error_count)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            for (query, duration) in &application.slowest_queries {
+                writeln!(
+                    output,
+                    "      {}  {}  {}",
+                    self.highlight_duration(
+                        format!("{:>12}", self.duration_cell(*duration)),
+                        *duration
+                    ),
+                    short_query_id(query),
+                    self.display_query(query)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the per-client-host table, busiest host first
+    fn write_clients(&self, output: &mut String, clients: &[ClientHostStats]) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Clients:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {:<24}  {:>11}  {:>8}  {:>14}  {:>6}  {:>7}",
+            "Host",
+            "Connections",
+            "Queries",
+            self.duration_header("Duration"),
+            "Errors",
+            "Error %"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for client in clients {
+            writeln!(
+                output,
+                "  {:<24}  {:>11}  {:>8}  {:>14}  {:>6}  {:>6.1}%",
+                client.client_host,
+                self.count(client.connection_count),
+                self.count(client.query_count),
+                self.duration_cell(client.total_duration),
+                self.count(client.error_count),
+                client.error_rate * 100.0
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write `COPY` totals and the per-table table, longest total duration first
+    fn write_copy(&self, output: &mut String, copy: &CopyStats) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("COPY Operations:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {} operations, {} total, {} max, {} errors",
+            self.count(copy.operations),
+            self.duration(copy.total_duration),
+            self.duration(copy.max_duration),
+            self.count(copy.error_count)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {:<24}  {:<4}  {:>8}  {:>14}  {:>10}  {:>6}",
+            "Table",
+            "Dir",
+            "Count",
+            self.duration_header("Duration"),
+            self.duration_header("Max"),
+            "Errors"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for table in &copy.tables {
+            writeln!(
+                output,
+                "  {:<24}  {:<4}  {:>8}  {:>14}  {}  {:>6}",
+                table.table,
+                table.direction,
+                self.count(table.operations),
+                self.duration_cell(table.total_duration),
+                self.highlight_duration(
+                    format!("{:>10}", self.duration_cell(table.max_duration)),
+                    table.max_duration
+                ),
+                self.count(table.error_count)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the most frequent message templates with their severity and
+    /// when they were first and last seen
+    fn write_log_events(&self, output: &mut String, events: &[LogEventStats]) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Events:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "  {:<8}  {:>8}  {:<19}  {:<19}  Message",
+            "Severity", "Count", "First Seen", "Last Seen"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for event in events {
+            writeln!(
+                output,
+                "  {:<8}  {:>8}  {:<19}  {:<19}  {}",
+                event.severity,
+                self.count(event.count),
+                self.timestamp(event.first_seen, "%Y-%m-%d %H:%M:%S"),
+                self.timestamp(event.last_seen, "%Y-%m-%d %H:%M:%S"),
+                event.template
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write replication event counts by category, the longest stretch
+    /// without streaming, and the changes between streaming and not
+    /// Note on the statements left out of the query lists
+    fn write_unparsed_statements(
+        &self,
+        output: &mut String,
+        unparsed: &UnparsedStatements,
+    ) -> Result<()> {
+        writeln!(
+            output,
+            "\nUnparsed statements: {} ({}), counted by type only:",
+            self.count(unparsed.count),
+            self.duration(unparsed.total_duration)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for sample in &unparsed.samples {
+            writeln!(output, "  {}", self.display_query(sample)).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the temp file and checkpoint write volumes, per hour of the
+    /// log's span when it has one
+    fn write_io_volume(&self, output: &mut String, analysis: &AnalysisResult) -> Result<()> {
+        let io = &analysis.io;
+        let span_seconds = analysis
+            .time_range
+            .as_ref()
+            .map_or(0.0, |range| range.span_seconds);
+        let rate = |mb_per_hour: Option<f64>| {
+            mb_per_hour
+                .map(|mb| format!(", {:.2} MB/hour", mb))
+                .unwrap_or_default()
+        };
+        if io.temp_files > 0 {
+            writeln!(
+                output,
+                "Temp Files: {} ({:.2} MB{})",
+                self.count(io.temp_files),
+                bytes_to_mb(io.temp_bytes),
+                rate(per_hour(bytes_to_mb(io.temp_bytes), span_seconds))
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        if io.checkpoints > 0 {
+            writeln!(
+                output,
+                "Checkpoint Writes: {} buffers ({:.2} MB{})",
+                self.count(io.checkpoint_buffers),
+                io.checkpoint_mb(),
+                rate(io.checkpoint_mb_per_hour(span_seconds))
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write checkpoint and autovacuum counts, with the figures of the
+    /// messages in the shape of the log's PostgreSQL version
+    fn write_maintenance(&self, output: &mut String, maintenance: &MaintenanceStats) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Maintenance:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        let checkpoints = &maintenance.checkpoints;
+        if checkpoints.count > 0 {
+            writeln!(
+                output,
+                "  Checkpoints: {} ({} with details: {} buffers written, {} total, {} max, {} kB of WAL)",
+                self.count(checkpoints.count),
+                self.count(checkpoints.with_details),
+                self.count(checkpoints.buffers_written),
+                self.duration(checkpoints.total_ms),
+                self.duration(checkpoints.max_ms),
+                self.count(checkpoints.distance_kb)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        let autovacuums = &maintenance.autovacuums;
+        if autovacuums.count > 0 {
+            writeln!(
+                output,
+                "  Autovacuums: {} ({} with details: {} pages and {} tuples removed)",
+                self.count(autovacuums.count),
+                self.count(autovacuums.with_details),
+                self.count(autovacuums.pages_removed),
+                self.count(autovacuums.tuples_removed)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        for rule in HINT_RULES {
+            let Some(warnings) = maintenance.warnings.get(rule.id) else {
+                continue;
+            };
+            writeln!(
+                output,
+                "  Warnings, {}: {} (min {}, avg {})",
+                rule.label,
+                self.count(warnings.count),
+                self.duration(warnings.min_value * 1000.0),
+                self.duration(warnings.average_value() * 1000.0)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+
+        let hints = maintenance.hints();
+        if hints.is_empty() {
+            return Ok(());
+        }
+        writeln!(
+            output,
+            "\n{}",
+            bold("Tuning Hints:", Some("yellow"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for hint in hints {
+            let distance = hint
+                .avg_checkpoint_distance_kb
+                .map(|kb| format!(", {:.0} kB of WAL per checkpoint", kb))
+                .unwrap_or_default();
+            writeln!(
+                output,
+                "  {}: {} times, more than {} (min {}, avg {}){}\n    {}",
+                hint.label,
+                self.count(hint.occurrences),
+                hint.threshold,
+                self.duration(hint.min_value * 1000.0),
+                self.duration(hint.avg_value * 1000.0),
+                distance,
+                hint.message
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the time of each extended protocol phase and the most executed
+    /// prepared statements
+    fn write_prepared_statements(
+        &self,
+        output: &mut String,
+        prepared: &PreparedStatementStats,
+    ) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Prepared Statements:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        let total_ms = prepared.total_ms();
+        for phase in [
+            PreparePhase::Parse,
+            PreparePhase::Bind,
+            PreparePhase::Execute,
+        ] {
+            let stats = prepared.phase(phase);
+            let share = if total_ms > 0.0 {
+                stats.total_ms / total_ms * 100.0
+            } else {
+                0.0
+            };
+            writeln!(
+                output,
+                "  {:<8}  {:>8}  {:>10}  {:>5.1}%",
+                phase,
+                self.count(stats.count),
+                self.duration(stats.total_ms),
+                share
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        writeln!(
+            output,
+            "  Unnamed Executes: {}, Re-prepares: {}",
+            self.count(prepared.unnamed_executes),
+            self.count(prepared.reprepares)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        let queries = prepared.most_executed();
+        if !queries.is_empty() {
+            writeln!(output, "  Most executed:").map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            for (query, count) in queries {
+                writeln!(
+                    output,
+                    "  {:>8}  {}  {}",
+                    format!("{}x", count),
+                    short_query_id(query),
+                    self.display_query(query)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_replication(&self, output: &mut String, replication: &ReplicationStats) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Replication:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for (kind, count) in &replication.counts {
+            writeln!(output, "  {:<20}  {:>8}", kind, self.count(*count)).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+        if let Some(gap) = &replication.longest_gap {
+            writeln!(
+                output,
+                "  Longest gap without streaming: {} ({} to {}{})",
+                self.duration(gap.duration_seconds * 1000.0),
+                self.timestamp(gap.start, "%Y-%m-%d %H:%M:%S"),
+                self.timestamp(gap.end, "%Y-%m-%d %H:%M:%S"),
+                if gap.ongoing { ", not resumed" } else { "" }
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        for transition in &replication.transitions {
+            writeln!(
+                output,
+                "  {}  {:<13}  ({})",
+                self.timestamp(transition.timestamp, "%Y-%m-%d %H:%M:%S"),
+                if transition.streaming {
+                    "streaming"
+                } else {
+                    "not streaming"
+                },
+                transition.kind
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write cancellation counts by reason, the most canceled statements, and
+    /// the hours they happened in
+    fn write_cancellations(
+        &self,
+        output: &mut String,
+        cancellations: &CancellationStats,
+    ) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Cancellations:", Some("red"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for (reason, count) in by_count_desc(&cancellations.by_reason) {
+            writeln!(output, "  {:<28}  {:>8}", reason, self.count(count)).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+        if !cancellations.top_queries.is_empty() {
+            writeln!(output, "  Most canceled:").map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+            for query in &cancellations.top_queries {
+                writeln!(
+                    output,
+                    "  {:>8}  {}  {}",
+                    format!("{}x", query.count),
+                    short_query_id(&query.query),
+                    self.display_query(&query.query)
+                )
+                .map_err(|e| PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                })?;
+            }
+        }
+        let hours = cancellations
+            .hourly
+            .iter()
+            .map(|(hour, count)| format!("{:02}:00 x{}", hour, count))
+            .collect::<Vec<_>>();
+        writeln!(output, "  By hour: {}", hours.join(", ")).map_err(|e| {
+            PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Write the queries that matched a lint rule, then what each rule means
+    fn write_query_warnings(&self, output: &mut String, warnings: &[QueryWarning]) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Query Warnings:", Some("yellow"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        if warnings.is_empty() {
+            writeln!(output, "  No suspicious query patterns found").map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+            return Ok(());
+        }
+        writeln!(
+            output,
+            "  {:<22}  {:>8}  {:>12}  {:<8}  Query",
+            "Pattern",
+            "Count",
+            self.duration_header("P95"),
+            "ID"
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for warning in warnings {
+            writeln!(
+                output,
+                "  {:<22}  {:>8}  {:>12}  {}  {}",
+                warning.rule,
+                self.count(warning.count),
+                self.duration_cell(warning.p95_duration),
+                short_query_id(&warning.query),
+                self.display_query(&warning.query)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        let mut described = Vec::new();
+        for warning in warnings {
+            if described.contains(&warning.rule.as_str()) {
+                continue;
+            }
+            described.push(warning.rule.as_str());
+            writeln!(output, "  {}: {}", warning.rule, warning.description).map_err(|e| {
+                PgLogstatsError::Unexpected {
+                    message: e.to_string(),
+                    context: Some("text formatting".to_string()),
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// `timestamp` in the display timezone, formatted with `format`
+    fn timestamp(&self, timestamp: DateTime<Utc>, format: &str) -> String {
+        match self.display_timezone {
+            Some(timezone) => timestamp
+                .with_timezone(&timezone)
+                .format(format)
+                .to_string(),
+            None => timestamp.format(format).to_string(),
+        }
+    }
+
+    /// Heading of a table by hour of day, naming the timezone of its hours
+    /// once a display or hour timezone is set, since UTC hours of day cannot
+    /// be shifted into a timezone with daylight saving time
+    /// Hours of day from the start to the end of `time_range` in the hour
+    /// timezone, wrapping around midnight; every hour once it spans a day
+    fn hours_covered(&self, time_range: &TimeRange) -> Vec<u32> {
+        if time_range.end - time_range.start >= chrono::Duration::hours(23) {
+            return (0..24).collect();
+        }
+        let start = hour_in(time_range.start, self.hour_timezone);
+        let end = hour_in(time_range.end, self.hour_timezone);
+        let hours = (end + 24 - start) % 24 + 1;
+        (0..hours).map(|offset| (start + offset) % 24).collect()
+    }
+
+    fn hourly_title(&self, title: &str) -> String {
+        match (self.hour_timezone, self.display_timezone) {
+            (Some(timezone), _) => format!("{} ({}):", title, timezone.name()),
+            (None, Some(_)) => format!("{} (UTC):", title),
+            (None, None) => format!("{}:", title),
+        }
+    }
+
+    /// Write the analyzed time window, its activity, and any gaps in it
+    fn write_time_range(&self, output: &mut String, time_range: &TimeRange) -> Result<()> {
+        writeln!(
+            output,
+            "Time Range: {} to {} ({})",
+            self.timestamp(time_range.start, TIME_RANGE_FORMAT),
+            self.timestamp(time_range.end, TIME_RANGE_FORMAT),
+            format_span(time_range.span_seconds)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Active Days: {}, Active Hours: {}",
+            time_range.active_days, time_range.active_hours
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        writeln!(
+            output,
+            "Queries per Second: {:.2}",
+            time_range.queries_per_second
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        for gap in &time_range.gaps {
+            let warning = format!(
+                "Warning: no entries for {} between {} and {}; are rotated log files missing?",
+                format_span(gap.minutes * 60.0),
+                self.timestamp(gap.start, TIME_RANGE_FORMAT),
+                self.timestamp(gap.end, TIME_RANGE_FORMAT)
+            );
+            writeln!(
+                output,
+                "{}",
+                bold(&warning, Some("yellow"), self.enable_color)
+            )
+            .map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the duration histogram as a bar chart scaled to the chart width
+    fn write_histogram(&self, output: &mut String, buckets: &[(String, u64)]) -> Result<()> {
+        writeln!(
+            output,
+            "\n{}",
+            bold("Duration Histogram:", Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        let max_count = buckets.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        for (bucket, count) in buckets {
+            let row = format!(
+                "  {:<10}  {:>8}  {}",
+                bucket,
+                self.count(*count),
+                bar(*count, max_count, self.chart_width)
+            );
+            writeln!(output, "{}", row.trim_end()).map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Write the traffic histogram, one row per bucket of the time range with
+    /// its query count as a bar and its errors, if any, after the bar
+    fn write_traffic_histogram(
+        &self,
+        output: &mut String,
+        traffic: &TrafficHistogram,
+    ) -> Result<()> {
+        let title = if traffic.is_widened() {
+            format!(
+                "Traffic Histogram (per {}, widened from 1 {}):",
+                format_bucket_width(traffic.bucket_minutes),
+                traffic.granularity
+            )
+        } else {
+            format!("Traffic Histogram (per {}):", traffic.granularity)
+        };
+        writeln!(
+            output,
+            "\n{}",
+            bold(&title, Some("blue"), self.enable_color)
+        )
+        .map_err(|e| PgLogstatsError::Unexpected {
+            message: e.to_string(),
+            context: Some("text formatting".to_string()),
+        })?;
+        let max_count = traffic.max_query_count();
+        let bar_width = self.chart_width.saturating_sub(TRAFFIC_ROW_EXTRA).max(1);
+        for bucket in &traffic.buckets {
+            let errors = match bucket.error_count {
+                0 => String::new(),
+                1 => "  1 error".to_string(),
+                count => format!("  {} errors", self.count(count)),
+            };
+            let row = format!(
+                "  {}  {:>8}  {}{}",
+                self.timestamp(bucket.start, "%Y-%m-%d %H:%M"),
+                self.count(bucket.query_count),
+                bar(bucket.query_count, max_count, bar_width),
+                errors
+            );
+            writeln!(output, "{}", row.trim_end()).map_err(|e| PgLogstatsError::Unexpected {
+                message: e.to_string(),
+                context: Some("text formatting".to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Count for display: grouped digits with human units, plain otherwise
+    fn count(&self, count: u64) -> String {
+        if self.human_units {
+            format_count(count)
+        } else {
+            count.to_string()
+        }
+    }
+
+    /// Duration with its unit: scaled with human units, milliseconds otherwise
+    fn duration(&self, duration_ms: f64) -> String {
+        if self.human_units {
+            format_duration(duration_ms)
+        } else {
+            format!("{:.2} ms", duration_ms)
+        }
+    }
+
+    /// Duration in a table column; without human units the column header
+    /// names the unit, so the value is bare milliseconds
+    fn duration_cell(&self, duration_ms: f64) -> String {
+        if self.human_units {
+            format_duration(duration_ms)
+        } else {
+            format!("{:.2}", duration_ms)
+        }
+    }
+
+    /// Header of a duration column, e.g. `Duration (ms)` without human units
+    fn duration_header(&self, label: &str) -> String {
+        if self.human_units {
+            label.to_string()
+        } else {
+            format!("{} (ms)", label)
+        }
+    }
+
+    /// Color already-padded `text` red when `duration_ms` is above the slow-query threshold
+    fn highlight_duration(&self, text: String, duration_ms: f64) -> String {
+        match self.slow_query_threshold {
+            Some(threshold) if duration_ms > threshold => {
+                bold(&text, Some("red"), self.enable_color)
+            }
+            _ => text,
+        }
+    }
+
+    /// `output` after the redactor, if any
+    fn redacted(&self, output: String) -> String {
+        match &self.redactor {
+            Some(redactor) => match redactor.redact(&output) {
+                Cow::Owned(redacted) => redacted,
+                Cow::Borrowed(_) => output,
+            },
+            None => output,
+        }
+    }
+
+    fn display_query<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        match self.max_query_length {
+            Some(max_chars) => truncate_query(query, max_chars),
+            None => Cow::Borrowed(query),
+        }
     }
 }
 
@@ -0,0 +1,173 @@
+//! Client addresses shared by the log parsers
+//!
+//! The client shows up in two places: the `%r` prefix escape, written as
+//! `host(port)`, and the `connection received: host=... port=...` line that
+//! `log_connections` writes when a session starts. Prefixes without `%h` or
+//! `%r` only have the latter, so [`SessionClients`] copies its address to the
+//! rest of the session's entries.
+
+use crate::LogEntry;
+use std::collections::HashMap;
+
+/// Message of the line `log_connections` writes for a new connection
+const CONNECTION_RECEIVED: &str = "connection received:";
+
+/// Host and port of a `%r` or `%h` value such as `10.0.0.5(51234)`.
+///
+/// Empty, `[unknown]`, and `-` values have no host; `[local]` is kept.
+pub(crate) fn parse_remote_host(remote_host: &str) -> (Option<String>, Option<u16>) {
+    let remote_host = remote_host.trim();
+    if let Some((host, port)) = remote_host
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+    {
+        if !host.is_empty() {
+            return (known_host(host), port.parse().ok());
+        }
+    }
+    (known_host(remote_host), None)
+}
+
+/// Host and port of a `connection received: host=... port=...` message
+pub(crate) fn connection_received_address(message: &str) -> Option<(String, Option<u16>)> {
+    let fields = message.strip_prefix(CONNECTION_RECEIVED)?;
+    let mut host = None;
+    let mut port = None;
+    for field in fields.split_whitespace() {
+        match field.split_once('=') {
+            Some(("host", value)) => host = known_host(value),
+            Some(("port", value)) => port = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some((host?, port))
+}
+
+/// Give entries without a client address the address of their session's
+/// `connection received` line.
+///
+/// Entries are in log order, so a process id reused by a later connection
+/// picks up that connection's address.
+pub(crate) fn attach_session_clients(entries: &mut [LogEntry]) {
+    let mut clients = SessionClients::default();
+    for entry in entries {
+        clients.attach(entry);
+    }
+}
+
+/// Client addresses of the sessions whose `connection received` line was
+/// seen, for entries handed to it in log order
+#[derive(Debug, Default)]
+pub(crate) struct SessionClients {
+    sessions: HashMap<String, (String, Option<u16>)>,
+}
+
+impl SessionClients {
+    /// Remember the address of a `connection received` entry, or give any
+    /// other entry without an address the address of its session
+    pub(crate) fn attach(&mut self, entry: &mut LogEntry) {
+        if entry.message.starts_with(CONNECTION_RECEIVED) {
+            match &entry.client_host {
+                Some(host) => {
+                    self.sessions.insert(
+                        entry.session_key().to_string(),
+                        (host.clone(), entry.client_port),
+                    );
+                }
+                None => {
+                    self.sessions.remove(entry.session_key());
+                }
+            }
+        } else if entry.client_host.is_none() {
+            if let Some((host, port)) = self.sessions.get(entry.session_key()) {
+                entry.client_host = Some(host.clone());
+                entry.client_port = *port;
+            }
+        }
+    }
+}
+
+fn known_host(host: &str) -> Option<String> {
+    let host = host.trim();
+    if host.is_empty() || host == "[unknown]" || host == "-" {
+        None
+    } else {
+        Some(host.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogLevel;
+    use chrono::Utc;
+
+    fn entry(process_id: &str, message: &str) -> LogEntry {
+        LogEntry::builder(Utc::now(), process_id, LogLevel::Log, message)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn splits_the_port_from_remote_hosts() {
+        assert_eq!(
+            parse_remote_host("10.0.0.5(51234)"),
+            (Some("10.0.0.5".to_string()), Some(51234))
+        );
+        assert_eq!(
+            parse_remote_host("app.example.com"),
+            (Some("app.example.com".to_string()), None)
+        );
+        assert_eq!(
+            parse_remote_host("[local]"),
+            (Some("[local]".to_string()), None)
+        );
+        assert_eq!(parse_remote_host("[unknown]"), (None, None));
+        assert_eq!(parse_remote_host(""), (None, None));
+    }
+
+    #[test]
+    fn reads_connection_received_lines() {
+        assert_eq!(
+            connection_received_address("connection received: host=10.0.0.5 port=51234"),
+            Some(("10.0.0.5".to_string(), Some(51234)))
+        );
+        assert_eq!(
+            connection_received_address("connection received: host=[local]"),
+            Some(("[local]".to_string(), None))
+        );
+        assert_eq!(
+            connection_received_address("connection authorized: user=app"),
+            None
+        );
+    }
+
+    #[test]
+    fn attaches_the_session_address_to_later_entries() {
+        let mut received = entry("7", "connection received: host=10.0.0.5 port=51234");
+        received.client_host = Some("10.0.0.5".to_string());
+        received.client_port = Some(51234);
+        let mut entries = vec![
+            entry("7", "statement: SELECT 1"),
+            received,
+            entry("8", "statement: SELECT 2"),
+            entry("7", "statement: SELECT 3"),
+        ];
+
+        attach_session_clients(&mut entries);
+
+        let hosts: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.client_host.as_deref(), entry.client_port))
+            .collect();
+        assert_eq!(
+            hosts,
+            vec![
+                (None, None),
+                (Some("10.0.0.5"), Some(51234)),
+                (None, None),
+                (Some("10.0.0.5"), Some(51234)),
+            ]
+        );
+    }
+}
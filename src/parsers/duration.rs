@@ -0,0 +1,179 @@
+//! Duration parsing shared by the log parsers
+//!
+//! PostgreSQL reports durations in several shapes: `duration: 45.123 ms` for
+//! statements, `elapsed: 12.34 s` for autovacuum, `total=45.678 s` for
+//! checkpoints, and `session time: 0:03:22.456` for disconnections. All of
+//! them are converted to milliseconds.
+
+/// Labels that introduce a duration in a log message, in lookup order
+const DURATION_LABELS: [&str; 4] = ["duration:", "elapsed:", "total=", "session time:"];
+
+/// Parse a single duration value such as `45.123 ms`, `12.34 s`, `2 min`, or
+/// `0:03:22.456` into milliseconds.
+///
+/// Returns `None` for anything else, including negative or non-finite values.
+pub fn parse_duration_ms(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.contains(':') {
+        return parse_clock_duration_ms(value);
+    }
+
+    let number_len = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(number_len);
+    let number = parse_non_negative(number)?;
+    let scale = match unit.trim_start() {
+        "ms" => 1.0,
+        "s" => 1_000.0,
+        "min" => 60_000.0,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+/// Find the first labelled duration in a log message and return it in
+/// milliseconds.
+///
+/// Recognizes `duration:`, `elapsed:`, `total=`, and `session time:`.
+pub fn extract_duration_ms(message: &str) -> Option<f64> {
+    DURATION_LABELS.iter().find_map(|label| {
+        let start = message.find(label)? + label.len();
+        parse_duration_ms(duration_token(message[start..].trim_start()))
+    })
+}
+
+/// The duration value at the start of `text`: a number with its unit, or an
+/// `H:MM:SS` clock value
+fn duration_token(text: &str) -> &str {
+    let number_len = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ':'))
+        .unwrap_or(text.len());
+    if text[..number_len].contains(':') {
+        return &text[..number_len];
+    }
+
+    let rest = &text[number_len..];
+    let unit_start = number_len + (rest.len() - rest.trim_start().len());
+    let unit_len = text[unit_start..]
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len() - unit_start);
+    &text[..unit_start + unit_len]
+}
+
+/// Parse `H:MM:SS` or `H:MM:SS.fff` into milliseconds
+fn parse_clock_duration_ms(value: &str) -> Option<f64> {
+    let mut parts = value.split(':');
+    let (hours, minutes, seconds) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some()
+        || minutes.len() != 2
+        || !seconds.starts_with(|c: char| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let hours = parse_non_negative(hours)?;
+    let minutes = parse_non_negative(minutes)?;
+    let seconds = parse_non_negative(seconds)?;
+    if hours.fract() != 0.0 || minutes.fract() != 0.0 || minutes >= 60.0 || seconds >= 60.0 {
+        return None;
+    }
+    Some(((hours * 60.0 + minutes) * 60.0 + seconds) * 1_000.0)
+}
+
+/// Parse an unsigned decimal number made of digits and at most one `.`
+fn parse_non_negative(number: &str) -> Option<f64> {
+    if !number.starts_with(|c: char| c.is_ascii_digit())
+        || !number.chars().all(|c| c.is_ascii_digit() || c == '.')
+    {
+        return None;
+    }
+    number.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_unit() {
+        assert_eq!(parse_duration_ms("45.123 ms"), Some(45.123));
+        assert_eq!(parse_duration_ms("1000 ms"), Some(1000.0));
+        assert_eq!(parse_duration_ms("0.001ms"), Some(0.001));
+        assert_eq!(parse_duration_ms("12.5 s"), Some(12_500.0));
+        assert_eq!(parse_duration_ms("3s"), Some(3_000.0));
+        assert_eq!(parse_duration_ms("2 min"), Some(120_000.0));
+        assert_eq!(parse_duration_ms("0.5 min"), Some(30_000.0));
+    }
+
+    #[test]
+    fn parses_session_time_clock_values() {
+        assert_eq!(parse_duration_ms("0:03:22.456"), Some(202_456.0));
+        assert_eq!(parse_duration_ms("1:00:00"), Some(3_600_000.0));
+        assert_eq!(parse_duration_ms("27:15:00.5"), Some(98_100_500.0));
+        assert_eq!(parse_duration_ms("0:00:00.000"), Some(0.0));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for value in [
+            "",
+            "ms",
+            "invalid ms",
+            "12",
+            "12 hours",
+            "12 msec",
+            "-5 ms",
+            "1.2.3 ms",
+            ".5 ms",
+            "0:60:00",
+            "0:00:60",
+            "0:3:22",
+            "0:03",
+            "1:02:03:04",
+            "a:03:22",
+            "0:03:-1",
+            "1.5:00:00",
+        ] {
+            assert_eq!(parse_duration_ms(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn extracts_labelled_durations_from_messages() {
+        assert_eq!(extract_duration_ms("duration: 45.123 ms"), Some(45.123));
+        assert_eq!(
+            extract_duration_ms("duration: 12.345 ms  statement: SELECT 1"),
+            Some(12.345)
+        );
+        assert_eq!(
+            extract_duration_ms(
+                "automatic vacuum of table \"shop.public.orders\": index scans: 1, \
+                 system usage: CPU: user: 0.10 s, system: 0.02 s, elapsed: 12.34 s"
+            ),
+            Some(12_340.0)
+        );
+        assert_eq!(
+            extract_duration_ms(
+                "checkpoint complete: wrote 42 buffers (0.3%); write=4.101 s, \
+                 sync=0.010 s, total=45.678 s; sync files=7"
+            ),
+            Some(45_678.0)
+        );
+        assert_eq!(
+            extract_duration_ms(
+                "disconnection: session time: 0:03:22.456 user=app database=shop host=[local]"
+            ),
+            Some(202_456.0)
+        );
+    }
+
+    #[test]
+    fn extract_returns_none_without_a_valid_duration() {
+        assert_eq!(extract_duration_ms("no duration here"), None);
+        assert_eq!(extract_duration_ms("duration: invalid ms"), None);
+        assert_eq!(extract_duration_ms("duration: 12"), None);
+        assert_eq!(extract_duration_ms("session time: soon"), None);
+        assert_eq!(extract_duration_ms("total=lots"), None);
+    }
+}
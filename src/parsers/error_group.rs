@@ -0,0 +1,272 @@
+//! Assembly of multi-line PostgreSQL errors
+//!
+//! PostgreSQL writes an error as several lines from the same backend: the
+//! `ERROR:` line, then optional `DETAIL:`, `HINT:`, `CONTEXT:`, and
+//! `STATEMENT:` lines. [`ErrorGroups`] folds those follow-up lines into the
+//! error entry they belong to, and the `DETAIL:  parameters: $1 = '...'`
+//! line of a bound statement into that statement.
+
+use crate::{LogEntry, Query};
+use std::collections::HashMap;
+
+/// Line that PostgreSQL writes after an error message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FollowUp {
+    Detail,
+    Hint,
+    Context,
+    Statement,
+}
+
+impl FollowUp {
+    /// Follow-up kind for a level tag such as `DETAIL`
+    pub(crate) fn from_level(level: &str) -> Option<Self> {
+        match level {
+            "DETAIL" => Some(Self::Detail),
+            "HINT" => Some(Self::Hint),
+            "CONTEXT" => Some(Self::Context),
+            "STATEMENT" => Some(Self::Statement),
+            _ => None,
+        }
+    }
+
+    fn field(self, entry: &mut LogEntry) -> &mut Option<String> {
+        match self {
+            Self::Detail => &mut entry.detail,
+            Self::Hint => &mut entry.hint,
+            Self::Context => &mut entry.context,
+            Self::Statement => &mut entry.statement,
+        }
+    }
+}
+
+/// Prefix of the `DETAIL:` line listing the parameters of a bound statement
+pub(crate) const PARAMETERS_PREFIX: &str = "parameters: ";
+
+/// Open error groups, keyed by process id.
+///
+/// Errors are pushed to the entry list as soon as they are parsed, so entries
+/// stay in log order; a group remembers where its error is and fills in its
+/// fields as follow-up lines arrive. Any other line from the same process
+/// closes the group. Lines from other processes do not, so bursts of errors
+/// from several backends are assembled independently.
+#[derive(Debug, Default)]
+pub(crate) struct ErrorGroups {
+    open: HashMap<String, usize>,
+    // Statements whose parameters line may follow, keyed by process id
+    statements: HashMap<String, usize>,
+    // Field that indented continuation lines are appended to
+    continuing: Option<(usize, FollowUp)>,
+}
+
+impl ErrorGroups {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track the entry at `index`: an error opens a group for its process,
+    /// a statement waits for its parameters, anything else closes both
+    pub(crate) fn observe(&mut self, entries: &[LogEntry], index: usize) {
+        self.continuing = None;
+        let entry = &entries[index];
+        self.open.remove(&entry.process_id);
+        self.statements.remove(&entry.process_id);
+        if entry.is_error() {
+            self.open.insert(entry.process_id.clone(), index);
+        } else if entry.is_query() {
+            self.statements.insert(entry.process_id.clone(), index);
+        }
+    }
+
+    /// Close the group of `process_id` without tracking a new entry
+    pub(crate) fn close(&mut self, process_id: &str) {
+        self.continuing = None;
+        self.open.remove(process_id);
+        self.statements.remove(process_id);
+    }
+
+    /// Fold a follow-up line into the open error of `process_id`, or a
+    /// parameters line into its last statement.
+    ///
+    /// Returns `false` when that process has neither, so the caller can keep
+    /// the line as an entry of its own.
+    pub(crate) fn fold(
+        &mut self,
+        entries: &mut [LogEntry],
+        process_id: &str,
+        follow_up: FollowUp,
+        text: &str,
+    ) -> bool {
+        let parameters = follow_up == FollowUp::Detail && text.starts_with(PARAMETERS_PREFIX);
+        let index = match self.open.get(process_id) {
+            Some(&index) => index,
+            None => match self.statements.remove(process_id) {
+                Some(index) if parameters => index,
+                _ => {
+                    self.continuing = None;
+                    return false;
+                }
+            },
+        };
+
+        let field = follow_up.field(&mut entries[index]);
+        match field {
+            // A repeated field, e.g. nested CONTEXT lines, is kept in order
+            Some(existing) => {
+                existing.push('\n');
+                existing.push_str(text.trim());
+            }
+            None => *field = Some(text.trim().to_string()),
+        }
+        self.continuing = Some((index, follow_up));
+        true
+    }
+
+    /// Append an indented continuation line to the last folded field.
+    ///
+    /// Returns `false` when the previous line was not a folded follow-up.
+    pub(crate) fn continue_line(&mut self, entries: &mut [LogEntry], line: &str) -> bool {
+        let Some((index, follow_up)) = self.continuing else {
+            return false;
+        };
+        if let Some(text) = follow_up.field(&mut entries[index]) {
+            text.push(' ');
+            text.push_str(line.trim());
+        }
+        true
+    }
+
+    /// Append a later `[n-m]` chunk from `process_id` to the last folded
+    /// field, if that field belongs to an error of the same process.
+    pub(crate) fn continue_chunk(
+        &mut self,
+        entries: &mut [LogEntry],
+        process_id: &str,
+        text: &str,
+    ) -> bool {
+        match self.continuing {
+            Some((index, _)) if entries[index].process_id == process_id => {
+                self.continue_line(entries, text)
+            }
+            _ => false,
+        }
+    }
+
+    /// Index of the first entry that a later line may still change
+    pub(crate) fn first_held(&self) -> Option<usize> {
+        self.open
+            .values()
+            .chain(self.statements.values())
+            .copied()
+            .chain(self.continuing.map(|(index, _)| index))
+            .min()
+    }
+
+    /// Forget the first `count` entries, which must all come before
+    /// [`first_held`](Self::first_held); the indices of the others move down
+    pub(crate) fn release(&mut self, count: usize) {
+        for index in self.open.values_mut().chain(self.statements.values_mut()) {
+            *index -= count;
+        }
+        if let Some((index, _)) = &mut self.continuing {
+            *index -= count;
+        }
+    }
+
+    /// Parse the failing statements of assembled errors into `queries` with
+    /// `parse_sql`
+    pub(crate) fn finish(
+        self,
+        entries: &mut [LogEntry],
+        mut parse_sql: impl FnMut(&str) -> Option<Vec<Query>>,
+    ) {
+        for entry in entries {
+            Self::finish_entry(entry, &mut parse_sql);
+        }
+    }
+
+    /// Parse the failing statement of an assembled error into `queries`
+    pub(crate) fn finish_entry(
+        entry: &mut LogEntry,
+        parse_sql: impl FnOnce(&str) -> Option<Vec<Query>>,
+    ) {
+        if !entry.is_error() {
+            return;
+        }
+        if let (Some(statement), None) = (&entry.statement, &entry.queries) {
+            entry.queries = parse_sql(statement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogLevel;
+    use chrono::Utc;
+
+    fn entry(process_id: &str, level: LogLevel) -> LogEntry {
+        LogEntry::builder(Utc::now(), process_id, level, "message")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn folds_follow_ups_into_the_error_of_the_same_process() {
+        let mut entries = vec![entry("1", LogLevel::Error), entry("2", LogLevel::Error)];
+        let mut groups = ErrorGroups::new();
+        groups.observe(&entries, 0);
+        groups.observe(&entries, 1);
+
+        assert!(groups.fold(&mut entries, "1", FollowUp::Detail, "Key (id)=(1) exists."));
+        assert!(groups.fold(&mut entries, "2", FollowUp::Statement, "SELECT *"));
+        assert!(groups.continue_line(&mut entries, "\tFROM missing"));
+        assert!(groups.fold(
+            &mut entries,
+            "1",
+            FollowUp::Statement,
+            "INSERT INTO t VALUES (1)"
+        ));
+        assert!(!groups.fold(&mut entries, "3", FollowUp::Hint, "unrelated"));
+        assert!(!groups.continue_line(&mut entries, "orphan"));
+        groups.finish(&mut entries, |sql| Query::from_sql(sql).ok());
+
+        assert_eq!(entries[0].detail.as_deref(), Some("Key (id)=(1) exists."));
+        assert_eq!(
+            entries[0].statement.as_deref(),
+            Some("INSERT INTO t VALUES (1)")
+        );
+        assert!(entries[0].queries.is_some());
+        assert_eq!(
+            entries[1].statement.as_deref(),
+            Some("SELECT * FROM missing")
+        );
+    }
+
+    #[test]
+    fn folds_parameters_into_the_last_statement_of_the_process() {
+        let mut entries = vec![entry("1", LogLevel::Statement), entry("2", LogLevel::Log)];
+        let mut groups = ErrorGroups::new();
+        groups.observe(&entries, 0);
+        groups.observe(&entries, 1);
+
+        assert!(!groups.fold(&mut entries, "1", FollowUp::Hint, "not a parameter"));
+        assert!(!groups.fold(&mut entries, "1", FollowUp::Detail, "parameters: $1 = '7'"));
+
+        groups.observe(&entries, 0);
+        assert!(groups.fold(&mut entries, "1", FollowUp::Detail, "parameters: $1 = '7'"));
+        assert!(!groups.fold(&mut entries, "1", FollowUp::Detail, "parameters: $1 = '8'"));
+        assert_eq!(entries[0].detail.as_deref(), Some("parameters: $1 = '7'"));
+    }
+
+    #[test]
+    fn another_line_from_the_process_closes_its_group() {
+        let mut entries = vec![entry("1", LogLevel::Fatal), entry("1", LogLevel::Log)];
+        let mut groups = ErrorGroups::new();
+        groups.observe(&entries, 0);
+        groups.observe(&entries, 1);
+
+        assert!(!groups.fold(&mut entries, "1", FollowUp::Hint, "too late"));
+        assert_eq!(entries[0].hint, None);
+    }
+}
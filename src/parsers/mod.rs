@@ -1,5 +1,112 @@
 //! Log format parsers for different PostgreSQL log formats
 
+mod client;
+pub mod duration;
+mod error_group;
+pub mod pgbouncer;
+pub mod report;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod syslog;
 pub mod text;
 
+pub use duration::{extract_duration_ms, parse_duration_ms};
+pub use pgbouncer::{PgbouncerEvent, PgbouncerLine, PgbouncerParser, PoolerStats};
+pub use report::{
+    FileLines, FileOverlap, MemoryLimit, ParseReport, SkipCounts, SkipReason, SkippedLine,
+    Truncation, UnterminatedLine, BACKWARDS_JUMP_THRESHOLD_MS,
+};
+#[cfg(feature = "async")]
+pub use stream::TextEntryStream;
+pub use syslog::SyslogParser;
 pub use text::{TextLogFormat, TextLogParser};
+
+pub(crate) use error_group::PARAMETERS_PREFIX;
+
+use crate::input::file::{read_log_lines_with_charset, Charset};
+use crate::{LineContext, ParsedLog, Result};
+use std::io::BufRead;
+use std::path::Path;
+
+/// Common interface of the log parsers, so callers can pick one at runtime.
+///
+/// [`ParserKind::parser`](crate::ParserKind::parser) returns the parser for a
+/// format as a `Box<dyn LogParser>`.
+pub trait LogParser {
+    /// Parse the lines of the input `context` names into entries, pooler
+    /// stats, and skip diagnostics, locating the errors of skipped lines in
+    /// the input
+    fn parse_log_lines_with_context(
+        &mut self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> ParsedLog;
+
+    /// Parse log lines into entries, pooler stats, and skip diagnostics
+    fn parse_log_lines(&mut self, lines: &[String]) -> ParsedLog {
+        self.parse_log_lines_with_context(lines, &LineContext::default())
+    }
+
+    /// Read every line from `reader` and parse it
+    fn parse_reader(&mut self, reader: &mut dyn BufRead) -> Result<ParsedLog> {
+        self.parse_reader_with_context(reader, &LineContext::default())
+    }
+
+    /// [`parse_reader`](Self::parse_reader) for the input `context` names
+    fn parse_reader_with_context(
+        &mut self,
+        reader: &mut dyn BufRead,
+        context: &LineContext,
+    ) -> Result<ParsedLog> {
+        let lines = reader.lines().collect::<std::io::Result<Vec<_>>>()?;
+        Ok(self.parse_log_lines_with_context(&lines, context))
+    }
+
+    /// Read a UTF-8 log file and parse it; entries and skipped-line samples
+    /// name the file as their source
+    fn parse_file(&mut self, path: &Path) -> Result<ParsedLog> {
+        let decoded = read_log_lines_with_charset(path, None, Charset::Utf8)?;
+        let mut parsed = self.parse_log_lines_with_context(
+            &decoded.lines,
+            &LineContext::file(path.display().to_string()),
+        );
+        parsed.parse_report.replaced_lines += decoded.replaced_lines;
+        parsed.set_source_file(path);
+        Ok(parsed)
+    }
+}
+
+impl LogParser for TextLogParser {
+    fn parse_log_lines_with_context(
+        &mut self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> ParsedLog {
+        let (entries, parse_report) = self.parse_lines_with_context(lines, context);
+        ParsedLog::new(entries, parse_report)
+    }
+}
+
+impl LogParser for SyslogParser {
+    fn parse_log_lines_with_context(
+        &mut self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> ParsedLog {
+        let (entries, parse_report) = self.parse_lines_with_context(lines, context);
+        ParsedLog::new(entries, parse_report)
+    }
+}
+
+impl LogParser for PgbouncerParser {
+    fn parse_log_lines_with_context(
+        &mut self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> ParsedLog {
+        let (entries, pooler_stats, parse_report) = self.parse_lines_with_context(lines, context);
+        let mut parsed = ParsedLog::new(entries, parse_report);
+        parsed.pooler_stats = pooler_stats;
+        parsed
+    }
+}
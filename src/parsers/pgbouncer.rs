@@ -0,0 +1,431 @@
+//! pgbouncer log parser
+//!
+//! Handles pgbouncer log lines such as
+//! `2024-08-15 10:30:15.123 UTC [12345] LOG C-0x55d1a2b3c4d0: app/web@10.0.0.5:54321 login attempt: db=app user=web tls=no`
+//! and the periodic `stats:` lines pgbouncer writes every `stats_period`.
+
+use super::report::{ParseReport, SkipReason};
+use crate::{timestamp_error, LineContext, LogEntry, LogLevel, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Connection events that pgbouncer parsing turns into log entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PgbouncerEvent {
+    /// `login attempt: db=... user=...`
+    Login,
+    /// `closing because: ...`
+    Close,
+    /// `pooler error: ...`
+    PoolerError,
+}
+
+impl PgbouncerEvent {
+    /// Classify a pgbouncer event message, e.g. `login attempt: db=app user=web`
+    pub fn classify(message: &str) -> Option<Self> {
+        if message.starts_with("login attempt:") {
+            Some(Self::Login)
+        } else if message.starts_with("closing because:") {
+            Some(Self::Close)
+        } else if message.starts_with("pooler error:") {
+            Some(Self::PoolerError)
+        } else {
+            None
+        }
+    }
+}
+
+/// One periodic pgbouncer `stats:` sample.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolerStats {
+    pub timestamp: DateTime<Utc>,
+    pub process_id: String,
+    /// Transactions per second
+    pub xacts_per_sec: f64,
+    /// Queries per second
+    pub queries_per_sec: f64,
+    /// Bytes received from clients per second
+    pub in_bytes_per_sec: f64,
+    /// Bytes sent to clients per second
+    pub out_bytes_per_sec: f64,
+    /// Average transaction time in microseconds
+    pub xact_time_us: f64,
+    /// Average query time in microseconds
+    pub query_time_us: f64,
+    /// Average client wait time in microseconds
+    pub wait_time_us: f64,
+}
+
+/// A single recognized pgbouncer line.
+#[derive(Debug, Clone)]
+pub enum PgbouncerLine {
+    /// Login, close, or pooler-error event
+    Entry(Box<LogEntry>),
+    /// Periodic stats sample
+    Stats(PoolerStats),
+    /// Recognized line that carries nothing pg-logstats analyzes
+    Other,
+}
+
+/// Patterns shared by every `PgbouncerParser`, compiled on first use
+struct PgbouncerRegexes {
+    line: Regex,
+    connection: Regex,
+    stats: Regex,
+    login_db: Regex,
+    login_user: Regex,
+}
+
+fn pgbouncer_regexes() -> &'static PgbouncerRegexes {
+    static REGEXES: OnceLock<PgbouncerRegexes> = OnceLock::new();
+    REGEXES.get_or_init(|| PgbouncerRegexes {
+        line: Regex::new(
+            r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?)(?: ([A-Za-z][A-Za-z0-9_+\-/]*|[+-]\d{2}(?::?\d{2})?))? \[?(\d+)\]? (LOG|NOISE|DEBUG|NOTICE|WARNING|ERROR|FATAL|PANIC) (.+)$"
+        )
+        .unwrap(),
+        connection: Regex::new(r"^([CS])-0x[0-9A-Fa-f]+: ([^/\s]*)/([^@\s]*)@(\S+) (.+)$").unwrap(),
+        stats: Regex::new(r"^[Ss]tats: (\d+(?:\.\d+)?) xacts/s, (\d+(?:\.\d+)?) queries/s").unwrap(),
+        login_db: Regex::new(r"\bdb=(\S+)").unwrap(),
+        login_user: Regex::new(r"\buser=(\S+)").unwrap(),
+    })
+}
+
+/// Parser for pgbouncer log files.
+pub struct PgbouncerParser {
+    line_regex: &'static Regex,
+    connection_regex: &'static Regex,
+    stats_regex: &'static Regex,
+    login_db_regex: &'static Regex,
+    login_user_regex: &'static Regex,
+    max_entries: Option<usize>,
+}
+
+impl PgbouncerParser {
+    /// Create a new pgbouncer parser
+    pub fn new() -> Self {
+        let regexes = pgbouncer_regexes();
+        Self {
+            line_regex: &regexes.line,
+            connection_regex: &regexes.connection,
+            stats_regex: &regexes.stats,
+            login_db_regex: &regexes.login_db,
+            login_user_regex: &regexes.login_user,
+            max_entries: None,
+        }
+    }
+
+    /// Stop parsing once `max_entries` entries were produced; where parsing
+    /// stopped is recorded as the report's
+    /// [`truncation`](crate::ParseReport::truncation)
+    pub fn with_max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Parse a single pgbouncer line.
+    ///
+    /// Returns `Ok(None)` when the line does not look like pgbouncer output and
+    /// `Err` when the timestamp cannot be parsed.
+    pub fn parse_line(&self, line: &str) -> Result<Option<PgbouncerLine>> {
+        let line = line.trim();
+        let Some(captures) = self.line_regex.captures(line) else {
+            return Ok(None);
+        };
+
+        let timestamp = parse_timestamp(&captures[1])?;
+        let process_id = captures[3].to_string();
+        let level = LogLevel::from(&captures[4]);
+        let message = &captures[5];
+
+        if let Some(stats) = self.parse_stats(message, timestamp, &process_id) {
+            return Ok(Some(PgbouncerLine::Stats(stats)));
+        }
+
+        let Some(connection) = self.connection_regex.captures(message) else {
+            return Ok(Some(PgbouncerLine::Other));
+        };
+
+        let event_message = &connection[5];
+        let Some(event) = PgbouncerEvent::classify(event_message) else {
+            return Ok(Some(PgbouncerLine::Other));
+        };
+
+        let mut database = known_name(&connection[2]);
+        let mut user = known_name(&connection[3]);
+        if event == PgbouncerEvent::Login {
+            if let Some(db) = self.login_db_regex.captures(event_message) {
+                database = known_name(&db[1]);
+            }
+            if let Some(login_user) = self.login_user_regex.captures(event_message) {
+                user = known_name(&login_user[1]);
+            }
+        }
+
+        // Only client (C-) connections carry the client address; server (S-)
+        // connections report the PostgreSQL backend instead.
+        let (client_host, client_port) = if &connection[1] == "C" {
+            let (host, port) = split_port(&connection[4]);
+            (Some(host.to_string()), port)
+        } else {
+            (None, None)
+        };
+
+        let entry = LogEntry::builder(timestamp, process_id, level, event_message)
+            .with_database(database)
+            .with_user(user)
+            .with_client_host(client_host)
+            .with_client_port(client_port)
+            .build()?;
+
+        Ok(Some(PgbouncerLine::Entry(Box::new(entry))))
+    }
+
+    fn parse_stats(
+        &self,
+        message: &str,
+        timestamp: DateTime<Utc>,
+        process_id: &str,
+    ) -> Option<PoolerStats> {
+        let captures = self.stats_regex.captures(message)?;
+
+        Some(PoolerStats {
+            timestamp,
+            process_id: process_id.to_string(),
+            xacts_per_sec: captures[1].parse().ok()?,
+            queries_per_sec: captures[2].parse().ok()?,
+            in_bytes_per_sec: stats_field(message, "in ", " B/s"),
+            out_bytes_per_sec: stats_field(message, "out ", " B/s"),
+            xact_time_us: stats_field(message, "xact ", " us"),
+            query_time_us: stats_field(message, "query ", " us"),
+            wait_time_us: stats_field(message, "wait ", " us"),
+        })
+    }
+
+    /// Parse multiple lines, returning entries, stats samples, and diagnostics
+    pub fn parse_lines_with_report(
+        &self,
+        lines: &[String],
+    ) -> (Vec<LogEntry>, Vec<PoolerStats>, ParseReport) {
+        self.parse_lines_with_context(lines, &LineContext::default())
+    }
+
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report) for the
+    /// lines of the input `context` names, locating the errors of skipped
+    /// lines in it
+    pub fn parse_lines_with_context(
+        &self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> (Vec<LogEntry>, Vec<PoolerStats>, ParseReport) {
+        let mut entries = Vec::new();
+        let mut stats = Vec::new();
+        let mut report = ParseReport::new();
+        let mut byte_offset = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 1;
+            let line_context = context.at(line_number, byte_offset);
+            byte_offset += line.len() as u64 + 1;
+            report.total_lines += 1;
+
+            if line.trim().is_empty() {
+                report.record_skipped(line_number, SkipReason::Empty, line);
+                continue;
+            }
+
+            match self.parse_line(line) {
+                Ok(Some(PgbouncerLine::Entry(_)))
+                    if report.stop_at_limit(self.max_entries, entries.len(), line_number) =>
+                {
+                    report.total_lines -= 1;
+                    break;
+                }
+                Ok(Some(PgbouncerLine::Entry(mut entry))) => {
+                    entry.seq = entries.len() as u64;
+                    entries.push(*entry);
+                }
+                Ok(Some(PgbouncerLine::Stats(sample))) => {
+                    report.ignored_lines += 1;
+                    stats.push(sample);
+                }
+                Ok(Some(PgbouncerLine::Other)) => report.ignored_lines += 1,
+                Ok(None) => report.record_skipped(line_number, SkipReason::RegexNoMatch, line),
+                Err(err) => {
+                    report.record_failed(&line_context, SkipReason::TimestampError, line, err)
+                }
+            }
+        }
+
+        report.parsed_entries = entries.len();
+        (entries, stats, report)
+    }
+
+    /// Parse multiple lines into log entries, dropping stats samples
+    ///
+    /// Lines that cannot be parsed are skipped and never fail the batch; see
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report).
+    pub fn parse_lines(&self, lines: &[String]) -> Result<Vec<LogEntry>> {
+        let (entries, _stats, _report) = self.parse_lines_with_report(lines);
+        Ok(entries)
+    }
+}
+
+impl Default for PgbouncerParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_timestamp(timestamp_str: &str) -> Result<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(timestamp_str, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+        .map_err(|_| timestamp_error("Failed to parse timestamp", timestamp_str))
+}
+
+/// pgbouncer prints `(nodb)` / `(nouser)` before a client has logged in.
+fn known_name(name: &str) -> Option<String> {
+    if name.is_empty() || name.starts_with('(') {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Host and port of `10.0.0.5:51234` or `[::1]:51234`
+fn split_port(address: &str) -> (&str, Option<u16>) {
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => (host, port.parse().ok()),
+        _ => (address, None),
+    };
+    (host.trim_start_matches('[').trim_end_matches(']'), port)
+}
+
+fn stats_field(message: &str, prefix: &str, suffix: &str) -> f64 {
+    message
+        .split(", ")
+        .find_map(|part| part.strip_prefix(prefix)?.strip_suffix(suffix))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_login_attempt_into_entry() {
+        let parser = PgbouncerParser::new();
+        let line = "2024-08-15 10:30:15.123 UTC [12345] LOG C-0x55d1a2b3c4d0: app/web@10.0.0.5:54321 login attempt: db=app user=web tls=no";
+
+        let Some(PgbouncerLine::Entry(entry)) = parser.parse_line(line).unwrap() else {
+            panic!("expected a login entry");
+        };
+
+        assert_eq!(entry.process_id, "12345");
+        assert_eq!(entry.database.as_deref(), Some("app"));
+        assert_eq!(entry.user.as_deref(), Some("web"));
+        assert_eq!(entry.client_host.as_deref(), Some("10.0.0.5"));
+        assert_eq!(entry.message_type, LogLevel::Log);
+        assert_eq!(
+            PgbouncerEvent::classify(&entry.message),
+            Some(PgbouncerEvent::Login)
+        );
+    }
+
+    #[test]
+    fn login_attempt_overrides_unknown_pool_names() {
+        let parser = PgbouncerParser::new();
+        let line = "2024-08-15 10:30:15.123 UTC [12345] LOG C-0x1: (nodb)/(nouser)@[::1]:40000 login attempt: db=app user=web tls=no";
+
+        let Some(PgbouncerLine::Entry(entry)) = parser.parse_line(line).unwrap() else {
+            panic!("expected a login entry");
+        };
+
+        assert_eq!(entry.database.as_deref(), Some("app"));
+        assert_eq!(entry.user.as_deref(), Some("web"));
+        assert_eq!(entry.client_host.as_deref(), Some("::1"));
+    }
+
+    #[test]
+    fn parses_close_and_pooler_error_events() {
+        let parser = PgbouncerParser::new();
+        let close = "2024-08-15 10:30:16.000 UTC [12345] LOG C-0x1: app/web@10.0.0.5:54321 closing because: client close request (age=1s)";
+        let error = "2024-08-15 10:30:17.000 UTC [12345] WARNING C-0x2: app/web@10.0.0.6:40000 pooler error: no more connections allowed (max_client_conn)";
+
+        let Some(PgbouncerLine::Entry(close)) = parser.parse_line(close).unwrap() else {
+            panic!("expected a close entry");
+        };
+        let Some(PgbouncerLine::Entry(error)) = parser.parse_line(error).unwrap() else {
+            panic!("expected a pooler error entry");
+        };
+
+        assert_eq!(
+            PgbouncerEvent::classify(&close.message),
+            Some(PgbouncerEvent::Close)
+        );
+        assert_eq!(
+            PgbouncerEvent::classify(&error.message),
+            Some(PgbouncerEvent::PoolerError)
+        );
+        assert_eq!(error.message_type, LogLevel::Warning);
+    }
+
+    #[test]
+    fn parses_stats_lines() {
+        let parser = PgbouncerParser::new();
+        let line = "2024-08-15 10:31:00.000 UTC [12345] LOG stats: 120 xacts/s, 340 queries/s, 0 client parses/s, in 20480 B/s, out 81920 B/s, xact 1500 us, query 400 us, wait 12 us";
+
+        let Some(PgbouncerLine::Stats(stats)) = parser.parse_line(line).unwrap() else {
+            panic!("expected a stats sample");
+        };
+
+        assert_eq!(stats.xacts_per_sec, 120.0);
+        assert_eq!(stats.queries_per_sec, 340.0);
+        assert_eq!(stats.in_bytes_per_sec, 20480.0);
+        assert_eq!(stats.out_bytes_per_sec, 81920.0);
+        assert_eq!(stats.xact_time_us, 1500.0);
+        assert_eq!(stats.query_time_us, 400.0);
+        assert_eq!(stats.wait_time_us, 12.0);
+    }
+
+    #[test]
+    fn server_connections_do_not_set_client_host() {
+        let parser = PgbouncerParser::new();
+        let line = "2024-08-15 10:30:15.123 UTC [12345] LOG S-0x2: app/web@10.0.0.10:5432 closing because: server idle timeout (age=600s)";
+
+        let Some(PgbouncerLine::Entry(entry)) = parser.parse_line(line).unwrap() else {
+            panic!("expected a close entry");
+        };
+
+        assert_eq!(entry.client_host, None);
+    }
+
+    #[test]
+    fn report_counts_ignored_and_unmatched_lines() {
+        let parser = PgbouncerParser::new();
+        let lines: Vec<String> = [
+            "2024-08-15 10:30:15.123 UTC [12345] LOG kernel file descriptor limit: 1024 (hard: 4096)",
+            "2024-08-15 10:30:15.200 UTC [12345] LOG C-0x1: app/web@10.0.0.5:54321 login attempt: db=app user=web tls=no",
+            "2024-08-15 10:30:16.000 UTC [12345] LOG S-0x2: app/web@10.0.0.10:5432 new connection to server (from 10.0.0.2:40000)",
+            "2024-08-15 10:31:00.000 UTC [12345] LOG stats: 1 xacts/s, 2 queries/s, in 0 B/s, out 0 B/s, xact 0 us, query 0 us, wait 0 us",
+            "",
+            "2024-08-15 10:30:15.123 UTC [12345] postgres@db psql: LOG:  statement: SELECT 1",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let (entries, stats, report) = parser.parse_lines_with_report(&lines);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(report.total_lines, 6);
+        assert_eq!(report.parsed_entries, 1);
+        assert_eq!(report.ignored_lines, 3);
+        assert_eq!(report.skipped.empty, 1);
+        assert_eq!(report.skipped.regex_no_match, 1);
+    }
+}
@@ -0,0 +1,545 @@
+//! Per-line parse diagnostics
+//!
+//! Parsers skip lines they cannot turn into entries. `ParseReport` records how
+//! many lines were skipped and why, so callers can tell a prefix mismatch from
+//! a genuinely sparse log.
+
+use crate::{LineContext, PgLogstatsError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Default number of skipped lines retained as samples in a report.
+pub const DEFAULT_SKIPPED_SAMPLE_LIMIT: usize = 10;
+
+/// How far an entry's timestamp may fall behind the newest entry before it
+/// before the entry counts as out of order, in milliseconds
+pub const BACKWARDS_JUMP_THRESHOLD_MS: u64 = 1_000;
+
+/// Why a line did not produce a log entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Blank or whitespace-only line
+    Empty,
+    /// Continuation line with no statement to attach it to
+    ContinuationWithoutPending,
+    /// Line did not match any accepted log line prefix
+    RegexNoMatch,
+    /// Line matched a prefix but its timestamp could not be parsed
+    TimestampError,
+    /// Continuation line dropped by strict multi-line handling because it
+    /// could belong to more than one backend
+    AmbiguousContinuation,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::Empty => write!(f, "empty"),
+            SkipReason::ContinuationWithoutPending => write!(f, "continuation without statement"),
+            SkipReason::RegexNoMatch => write!(f, "prefix mismatch"),
+            SkipReason::TimestampError => write!(f, "timestamp error"),
+            SkipReason::AmbiguousContinuation => write!(f, "ambiguous continuation"),
+        }
+    }
+}
+
+/// A sample of a skipped line kept for diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedLine {
+    /// 1-based line number within the parsed input
+    pub line_number: usize,
+    pub reason: SkipReason,
+    pub content: String,
+    /// Log file the line was read from, when parsed from a file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Offset of the start of the line in the input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<u64>,
+    /// Error parsing the line, located like `file.log:12: ...`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Skipped line counts broken down by reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkipCounts {
+    pub empty: usize,
+    pub continuation_without_pending: usize,
+    pub regex_no_match: usize,
+    pub timestamp_error: usize,
+    #[serde(default)]
+    pub ambiguous_continuation: usize,
+}
+
+impl SkipCounts {
+    /// Total skipped lines across all reasons.
+    pub fn total(&self) -> usize {
+        self.empty
+            + self.continuation_without_pending
+            + self.regex_no_match
+            + self.timestamp_error
+            + self.ambiguous_continuation
+    }
+
+    fn increment(&mut self, reason: SkipReason) {
+        match reason {
+            SkipReason::Empty => self.empty += 1,
+            SkipReason::ContinuationWithoutPending => self.continuation_without_pending += 1,
+            SkipReason::RegexNoMatch => self.regex_no_match += 1,
+            SkipReason::TimestampError => self.timestamp_error += 1,
+            SkipReason::AmbiguousContinuation => self.ambiguous_continuation += 1,
+        }
+    }
+}
+
+/// Where parsing stopped because an entry limit was reached.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncation {
+    /// Entry limit that was reached
+    pub max_entries: usize,
+    /// File being parsed when parsing stopped; `None` for in-memory input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Last line of the input that was parsed, 1-based; 0 when the limit was
+    /// reached before the input's first entry
+    pub line_number: usize,
+}
+
+/// Retained entries outgrew a memory limit and the analysis degraded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryLimit {
+    /// Memory limit in megabytes
+    pub limit_mb: usize,
+    /// Entries parsed when the limit was passed
+    pub entries: usize,
+}
+
+/// A log file whose first entry is not newer than the last entry of the files
+/// before it, e.g. a copy made by rotation that repeats part of another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOverlap {
+    /// File holding the newest entry before `later` was read
+    pub earlier: String,
+    pub later: String,
+    /// Timestamp of the first entry of `later`
+    pub start: DateTime<Utc>,
+    /// Timestamp of the newest entry of `earlier`
+    pub end: DateTime<Utc>,
+}
+
+/// The last line of a file that did not end with a newline, so the writer may
+/// not have finished it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnterminatedLine {
+    /// File the line was read from; `None` for in-memory input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// 1-based line number
+    pub line_number: usize,
+    /// Left unparsed so the next read sees it whole, as in follow mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub held_back: bool,
+    /// Parsed as the end of the input's last statement, which may be cut short
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated_statement: bool,
+}
+
+/// Lines read from one file of a parse run and the entries they produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileLines {
+    pub file: String,
+    pub lines: usize,
+    pub entries: usize,
+}
+
+/// Summary of a parse run returned alongside the parsed entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseReport {
+    /// Lines read from the input
+    pub total_lines: usize,
+    /// Log entries produced
+    pub parsed_entries: usize,
+    /// Continuation lines folded into a preceding statement, and error
+    /// follow-up lines folded into a preceding error
+    pub continuation_lines: usize,
+    /// Recognized lines that do not produce entries, e.g. pgbouncer stats
+    pub ignored_lines: usize,
+    /// Skipped lines by reason
+    pub skipped: SkipCounts,
+    /// First skipped lines, up to `sample_limit`
+    pub skipped_samples: Vec<SkippedLine>,
+    /// Maximum number of samples retained
+    pub sample_limit: usize,
+    /// Lines with bytes that were invalid in the input charset and were
+    /// decoded with U+FFFD replacement characters
+    #[serde(default)]
+    pub replaced_lines: usize,
+    /// Statements that sqlparser rejected, e.g. `COPY ... FROM STDIN` or
+    /// `DO` blocks, and that were normalized as text instead
+    #[serde(default)]
+    pub fallback_statements: usize,
+    /// Where parsing stopped, when an entry limit cut the input short
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<Truncation>,
+    /// Set when retained entries passed a memory limit, so the rest of the
+    /// input was analyzed as a stream with estimated query counts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<MemoryLimit>,
+    /// Entries dropped because an overlapping file repeated them exactly
+    /// (same timestamp, process id, and message)
+    #[serde(default)]
+    pub duplicate_entries: usize,
+    /// Files whose time range overlapped the files read before them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlapping_files: Vec<FileOverlap>,
+    /// Entries of the file list that matched no file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_files: Vec<String>,
+    /// Entries timestamped more than [`BACKWARDS_JUMP_THRESHOLD_MS`] before
+    /// the newest entry read before them in the same input
+    #[serde(default)]
+    pub out_of_order_entries: usize,
+    /// Largest of those backwards jumps, in milliseconds
+    #[serde(default)]
+    pub max_backwards_jump_ms: u64,
+    /// Set when entries were sorted by timestamp before analysis
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sorted_entries: bool,
+    /// Files whose last line had no trailing newline
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unterminated_lines: Vec<UnterminatedLine>,
+    /// Set when a statement still open at the end of the input was left for
+    /// the next read, because its continuation lines may not be written yet
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub held_back_statement: bool,
+    /// Lines and entries of each file, in the order read; written with the
+    /// run stats rather than with the parse diagnostics
+    #[serde(skip)]
+    pub files: Vec<FileLines>,
+}
+
+impl ParseReport {
+    /// Create an empty report keeping the default number of samples.
+    pub fn new() -> Self {
+        Self::with_sample_limit(DEFAULT_SKIPPED_SAMPLE_LIMIT)
+    }
+
+    /// Create an empty report keeping at most `sample_limit` skipped lines.
+    pub fn with_sample_limit(sample_limit: usize) -> Self {
+        Self {
+            total_lines: 0,
+            parsed_entries: 0,
+            continuation_lines: 0,
+            ignored_lines: 0,
+            skipped: SkipCounts::default(),
+            skipped_samples: Vec::new(),
+            sample_limit,
+            replaced_lines: 0,
+            fallback_statements: 0,
+            truncation: None,
+            memory_limit: None,
+            duplicate_entries: 0,
+            overlapping_files: Vec::new(),
+            missing_files: Vec::new(),
+            out_of_order_entries: 0,
+            max_backwards_jump_ms: 0,
+            sorted_entries: false,
+            unterminated_lines: Vec::new(),
+            held_back_statement: false,
+            files: Vec::new(),
+        }
+    }
+
+    /// Record a skipped line.
+    pub fn record_skipped(&mut self, line_number: usize, reason: SkipReason, content: &str) {
+        self.skipped.increment(reason);
+        if self.skipped_samples.len() < self.sample_limit {
+            self.skipped_samples.push(SkippedLine {
+                line_number,
+                reason,
+                content: content.to_string(),
+                file: None,
+                byte_offset: None,
+                error: None,
+            });
+        }
+    }
+
+    /// Record a line at `context` skipped because parsing it failed with
+    /// `error`, keeping the error located at `context` with the sample
+    pub fn record_failed(
+        &mut self,
+        context: &LineContext,
+        reason: SkipReason,
+        content: &str,
+        error: PgLogstatsError,
+    ) {
+        self.skipped.increment(reason);
+        if self.skipped_samples.len() < self.sample_limit {
+            self.skipped_samples.push(SkippedLine {
+                line_number: context.line_number.unwrap_or_default(),
+                reason,
+                content: content.to_string(),
+                file: context.file.clone(),
+                byte_offset: context.byte_offset,
+                error: Some(error.with_context(context).to_string()),
+            });
+        }
+    }
+
+    /// Whether one more entry would pass `max_entries`; if so, record that
+    /// parsing stopped before `line_number`
+    pub fn stop_at_limit(
+        &mut self,
+        max_entries: Option<usize>,
+        entries: usize,
+        line_number: usize,
+    ) -> bool {
+        match max_entries {
+            Some(max_entries) if entries >= max_entries => {
+                self.truncation = Some(Truncation {
+                    max_entries,
+                    file: None,
+                    line_number: line_number - 1,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Count the timestamps, in log order, that fall more than
+    /// [`BACKWARDS_JUMP_THRESHOLD_MS`] behind the newest one before them
+    pub fn record_timestamp_order(&mut self, timestamps: impl IntoIterator<Item = DateTime<Utc>>) {
+        let mut newest: Option<DateTime<Utc>> = None;
+        for timestamp in timestamps {
+            match newest {
+                Some(newest) if newest > timestamp => {
+                    let jump_ms = (newest - timestamp).num_milliseconds().unsigned_abs();
+                    if jump_ms > BACKWARDS_JUMP_THRESHOLD_MS {
+                        self.out_of_order_entries += 1;
+                        self.max_backwards_jump_ms = self.max_backwards_jump_ms.max(jump_ms);
+                    }
+                }
+                _ => newest = Some(timestamp),
+            }
+        }
+    }
+
+    /// Name `file` as the source of the skipped-line samples, the
+    /// truncation, and the unterminated line, for a report of a single file
+    pub fn set_file(&mut self, file: &str) {
+        self.files = vec![FileLines {
+            file: file.to_string(),
+            lines: self.total_lines,
+            entries: self.parsed_entries,
+        }];
+        for sample in &mut self.skipped_samples {
+            sample.file = Some(file.to_string());
+        }
+        for line in &mut self.unterminated_lines {
+            line.file = Some(file.to_string());
+        }
+        if let Some(truncation) = &mut self.truncation {
+            truncation.file = Some(file.to_string());
+        }
+    }
+
+    /// Total skipped lines across all reasons.
+    pub fn skipped_lines(&self) -> usize {
+        self.skipped.total()
+    }
+
+    /// Skipped lines that indicate content was lost, ignoring blank lines.
+    pub fn unparsed_lines(&self) -> usize {
+        self.skipped.total() - self.skipped.empty
+    }
+
+    /// Whether lines were left for the next read, as in follow mode
+    pub fn has_held_back_lines(&self) -> bool {
+        self.held_back_statement || self.unterminated_lines.iter().any(|line| line.held_back)
+    }
+
+    /// Fold another report into this one, e.g. when parsing several files.
+    pub fn merge(&mut self, other: ParseReport) {
+        self.total_lines += other.total_lines;
+        self.parsed_entries += other.parsed_entries;
+        self.continuation_lines += other.continuation_lines;
+        self.ignored_lines += other.ignored_lines;
+        self.skipped.empty += other.skipped.empty;
+        self.skipped.continuation_without_pending += other.skipped.continuation_without_pending;
+        self.skipped.regex_no_match += other.skipped.regex_no_match;
+        self.skipped.timestamp_error += other.skipped.timestamp_error;
+        self.skipped.ambiguous_continuation += other.skipped.ambiguous_continuation;
+        self.replaced_lines += other.replaced_lines;
+        self.fallback_statements += other.fallback_statements;
+        self.truncation = self.truncation.take().or(other.truncation);
+        self.memory_limit = self.memory_limit.or(other.memory_limit);
+        self.duplicate_entries += other.duplicate_entries;
+        self.overlapping_files.extend(other.overlapping_files);
+        self.missing_files.extend(other.missing_files);
+        self.out_of_order_entries += other.out_of_order_entries;
+        self.max_backwards_jump_ms = self.max_backwards_jump_ms.max(other.max_backwards_jump_ms);
+        self.sorted_entries |= other.sorted_entries;
+        self.unterminated_lines.extend(other.unterminated_lines);
+        self.held_back_statement |= other.held_back_statement;
+        self.files.extend(other.files);
+
+        let remaining = self.sample_limit.saturating_sub(self.skipped_samples.len());
+        self.skipped_samples
+            .extend(other.skipped_samples.into_iter().take(remaining));
+    }
+
+    /// One-line human-readable summary, or `None` when no content was lost.
+    pub fn warning_summary(&self) -> Option<String> {
+        if self.unparsed_lines() == 0 {
+            return None;
+        }
+
+        let mut reasons = Vec::new();
+        for (reason, count) in [
+            (SkipReason::RegexNoMatch, self.skipped.regex_no_match),
+            (SkipReason::TimestampError, self.skipped.timestamp_error),
+            (
+                SkipReason::ContinuationWithoutPending,
+                self.skipped.continuation_without_pending,
+            ),
+            (
+                SkipReason::AmbiguousContinuation,
+                self.skipped.ambiguous_continuation,
+            ),
+        ] {
+            if count > 0 {
+                reasons.push(format!("{} {}", count, reason));
+            }
+        }
+
+        // Ambiguous continuations are dropped on purpose, not a prefix problem.
+        let hint = if self.unparsed_lines() > self.skipped.ambiguous_continuation {
+            " — check --input-format matches your log_line_prefix"
+        } else {
+            ""
+        };
+        Some(format!(
+            "parsed {} entries from {} lines; skipped {} lines ({}){}",
+            self.parsed_entries,
+            self.total_lines,
+            self.unparsed_lines(),
+            reasons.join(", "),
+            hint
+        ))
+    }
+
+    /// The first located parse error among the skipped-line samples, if any
+    pub fn first_error(&self) -> Option<&str> {
+        self.skipped_samples
+            .iter()
+            .find_map(|sample| sample.error.as_deref())
+    }
+
+    /// One-line note about lines decoded with replacement characters, if any
+    pub fn encoding_warning(&self) -> Option<String> {
+        (self.replaced_lines > 0).then(|| {
+            format!(
+                "{} lines contained invalid UTF-8 and were decoded with replacement characters — try --charset latin-1",
+                self.replaced_lines
+            )
+        })
+    }
+
+    /// One-line note about where an entry limit stopped parsing, if it did
+    pub fn truncation_warning(&self) -> Option<String> {
+        self.truncation.as_ref().map(|truncation| {
+            let location = match &truncation.file {
+                Some(file) => format!("line {} of {}", truncation.line_number, file),
+                None => format!("line {}", truncation.line_number),
+            };
+            format!(
+                "stopped after {} entries at {}; the report covers only the input up to there",
+                truncation.max_entries, location
+            )
+        })
+    }
+
+    /// One-line note about overlapping files and the duplicates dropped from
+    /// them, if any
+    pub fn overlap_warning(&self) -> Option<String> {
+        if self.overlapping_files.is_empty() {
+            return None;
+        }
+        let files: Vec<_> = self
+            .overlapping_files
+            .iter()
+            .map(|overlap| format!("{} starts before {} ends", overlap.later, overlap.earlier))
+            .collect();
+        Some(format!(
+            "{}; merged their entries by timestamp and dropped {} duplicate entries",
+            files.join(", "),
+            self.duplicate_entries
+        ))
+    }
+
+    /// One-line note about entries whose timestamps jump backwards, if any
+    pub fn clock_skew_warning(&self) -> Option<String> {
+        if self.out_of_order_entries == 0 {
+            return None;
+        }
+        let action = if self.sorted_entries {
+            "sorted entries by timestamp before analysis"
+        } else {
+            "time-based results may be off — try --sort-entries"
+        };
+        Some(format!(
+            "{} entries were out of order, up to {} ms behind earlier entries; {}",
+            self.out_of_order_entries, self.max_backwards_jump_ms, action
+        ))
+    }
+
+    /// One-line note about parsed last lines that had no trailing newline, if
+    /// any; lines held back for the next read are expected and not noted
+    pub fn unterminated_line_warning(&self) -> Option<String> {
+        let parsed: Vec<_> = self
+            .unterminated_lines
+            .iter()
+            .filter(|line| !line.held_back)
+            .collect();
+        if parsed.is_empty() {
+            return None;
+        }
+        let lines: Vec<_> = parsed
+            .iter()
+            .map(|line| match &line.file {
+                Some(file) => format!("line {} of {}", line.line_number, file),
+                None => format!("line {}", line.line_number),
+            })
+            .collect();
+        let statement = if parsed.iter().any(|line| line.truncated_statement) {
+            "; the statement it ends may be cut short"
+        } else {
+            ""
+        };
+        Some(format!(
+            "{} had no trailing newline and may be half-written{}",
+            lines.join(", "),
+            statement
+        ))
+    }
+
+    /// One-line note about the analysis degrading at a memory limit, if it did
+    pub fn memory_limit_warning(&self) -> Option<String> {
+        self.memory_limit.map(|limit| {
+            format!(
+                "parsed entries passed {} MB after {} entries; analyzed the rest as a stream with estimated query counts",
+                limit.limit_mb, limit.entries
+            )
+        })
+    }
+}
+
+impl Default for ParseReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
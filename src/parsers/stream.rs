@@ -0,0 +1,179 @@
+//! Async parsing of text logs, behind the `async` feature
+//!
+//! [`TextLogParser::parse_async_reader`] reads lines from a tokio reader and
+//! pushes them through the same line-at-a-time state as
+//! [`parse_lines_with_report`](TextLogParser::parse_lines_with_report), so
+//! both produce the same entries. Each entry is yielded as soon as no later
+//! line can change it, rather than when the input ends.
+
+use super::report::ParseReport;
+use super::text::{LineFeed, TextLogParser};
+use crate::{LineContext, LogEntry, Result};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, Lines};
+
+/// Entries of a text log read from an async reader; see
+/// [`TextLogParser::parse_async_reader`]
+pub struct TextEntryStream<R> {
+    lines: Lines<R>,
+    parser: TextLogParser,
+    // `None` once the input ended or failed
+    feed: Option<LineFeed>,
+    ready: VecDeque<LogEntry>,
+    parse_report: Option<ParseReport>,
+}
+
+impl<R> TextEntryStream<R> {
+    /// Report of the whole input, once every line was read
+    pub fn parse_report(&self) -> Option<&ParseReport> {
+        self.parse_report.as_ref()
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for TextEntryStream<R> {
+    type Item = Result<LogEntry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.ready.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            let Some(feed) = &mut this.feed else {
+                return Poll::Ready(None);
+            };
+            let line = match ready!(Pin::new(&mut this.lines).poll_next_line(cx)) {
+                Ok(line) => line,
+                Err(error) => {
+                    this.feed = None;
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+            };
+            match line {
+                Some(line) if feed.push_line(&mut this.parser, &line) => {
+                    this.ready.extend(feed.take_settled(&mut this.parser));
+                }
+                // End of input or the entry limit
+                _ => {
+                    if let Some(feed) = this.feed.take() {
+                        let (entries, parse_report) = feed.finish(&mut this.parser);
+                        this.ready.extend(entries);
+                        this.parse_report = Some(parse_report);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TextLogParser {
+    /// Parse the lines of `reader` as they arrive, yielding each entry once
+    /// no later line can change it.
+    ///
+    /// The entries are those [`parse_reader`](super::LogParser::parse_reader)
+    /// returns for the same input, in the same order. A statement is held
+    /// until the next timestamped line, and an error or statement until
+    /// another line of its process, since follow-up lines may still fold into
+    /// it. A read error, including invalid UTF-8, ends the stream after it is
+    /// yielded.
+    pub fn parse_async_reader<R: AsyncBufRead + Unpin>(mut self, reader: R) -> TextEntryStream<R> {
+        let feed = LineFeed::new(&mut self, LineContext::default());
+        TextEntryStream {
+            lines: reader.lines(),
+            parser: self,
+            feed: Some(feed),
+            ready: VecDeque::new(),
+            parse_report: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::LogParser;
+    use std::future::poll_fn;
+    use std::time::Duration;
+    use tokio::io::{AsyncWriteExt, BufReader};
+
+    /// Several fixtures, three times over, so that entries settle well
+    /// before the input ends
+    fn fixture_lines() -> Vec<String> {
+        let files = [
+            "comprehensive.log",
+            "error_groups.log",
+            "client_hosts.log",
+            "multiline_interleaved.log",
+            "prepared_statements.log",
+        ];
+        let mut lines = Vec::new();
+        for _ in 0..3 {
+            for file in files {
+                let path = format!("{}/tests/fixtures/cli/{}", env!("CARGO_MANIFEST_DIR"), file);
+                let content = std::fs::read_to_string(path).unwrap();
+                lines.extend(content.lines().map(str::to_string));
+            }
+        }
+        lines
+    }
+
+    fn json(entries: &[LogEntry]) -> serde_json::Value {
+        serde_json::to_value(entries).unwrap()
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn matches_the_sync_parser_on_the_same_input() {
+        let lines = fixture_lines();
+        let input = lines.join("\n");
+        let parsed = TextLogParser::new().parse_log_lines(&lines);
+
+        let mut stream = TextLogParser::new().parse_async_reader(input.as_bytes());
+        let mut entries = Vec::new();
+        while let Some(entry) = next(&mut stream).await {
+            entries.push(entry.unwrap());
+        }
+
+        assert!(parsed.entries.len() > 100);
+        assert_eq!(json(&entries), json(&parsed.entries));
+        let report = stream.parse_report().unwrap();
+        assert_eq!(report.total_lines, parsed.parse_report.total_lines);
+        assert_eq!(report.parsed_entries, parsed.parse_report.parsed_entries);
+        assert_eq!(
+            report.continuation_lines,
+            parsed.parse_report.continuation_lines
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelling_mid_stream_keeps_the_yielded_entries() {
+        let lines = fixture_lines();
+        let expected = TextLogParser::new().parse_log_lines(&lines).entries;
+
+        // Write the first half of the log and keep the writer open, as a
+        // server still logging would
+        let (reader, mut writer) = tokio::io::duplex(1 << 20);
+        let half = lines[..lines.len() / 2].join("\n") + "\n";
+        writer.write_all(half.as_bytes()).await.unwrap();
+
+        let mut stream = TextLogParser::new().parse_async_reader(BufReader::new(reader));
+        let mut yielded = Vec::new();
+        while let Ok(Some(entry)) =
+            tokio::time::timeout(Duration::from_millis(100), next(&mut stream)).await
+        {
+            yielded.push(entry.unwrap());
+        }
+        drop(stream);
+        drop(writer);
+
+        assert!(!yielded.is_empty());
+        assert!(yielded.len() < expected.len());
+        assert_eq!(json(&yielded), json(&expected[..yielded.len()]));
+    }
+}
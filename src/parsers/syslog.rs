@@ -0,0 +1,524 @@
+//! Syslog log parser
+//!
+//! Handles PostgreSQL logs written through syslog, such as
+//! `Aug 15 10:30:15 dbhost postgres[12345]: [3-1] user=app,db=prod LOG:  statement: SELECT 1`.
+//! PostgreSQL splits long and multi-line messages into `[seq-chunk]` pieces;
+//! pieces are reassembled per (pid, seq) before the message is parsed. Both the
+//! traditional `Mmm dd hh:mm:ss` header and RFC 3339 timestamps are accepted.
+
+use super::client::attach_session_clients;
+use super::error_group::{ErrorGroups, FollowUp};
+use super::report::{ParseReport, SkipReason};
+use super::text::{LogMetadata, TextLogParser, PAYLOAD_PATTERN};
+use crate::{timestamp_error, LineContext, LogEntry, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Patterns shared by every `SyslogParser`, compiled on first use
+struct SyslogRegexes {
+    bsd_header: Regex,
+    rfc3339_header: Regex,
+    payload: Regex,
+}
+
+fn syslog_regexes() -> &'static SyslogRegexes {
+    static REGEXES: OnceLock<SyslogRegexes> = OnceLock::new();
+    REGEXES.get_or_init(|| SyslogRegexes {
+        bsd_header: Regex::new(
+            r"^(?:<\d+>)?([A-Z][a-z]{2})\s+(\d{1,2}) (\d{2}:\d{2}:\d{2}) (\S+) ([^\[\s]+)\[(\d+)\]: (?:\[(\d+)-(\d+)\] ?)?(.*)$",
+        )
+        .unwrap(),
+        rfc3339_header: Regex::new(
+            r"^(?:<\d+>)?(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?) (\S+) ([^\[\s]+)\[(\d+)\]: (?:\[(\d+)-(\d+)\] ?)?(.*)$",
+        )
+        .unwrap(),
+        payload: Regex::new(PAYLOAD_PATTERN).unwrap(),
+    })
+}
+
+/// Parser for PostgreSQL logs delivered through syslog.
+pub struct SyslogParser {
+    bsd_header_regex: &'static Regex,
+    rfc3339_header_regex: &'static Regex,
+    payload_regex: &'static Regex,
+    year: Option<i32>,
+    max_entries: Option<usize>,
+    collapse_lists: bool,
+}
+
+/// Header timestamp before the year is known.
+enum HeaderTimestamp {
+    /// `Aug 15 10:30:15`; syslog omits the year
+    Partial {
+        month: u32,
+        day: u32,
+        time: NaiveTime,
+    },
+    /// RFC 3339 timestamp
+    Full(DateTime<Utc>),
+}
+
+struct SyslogHeader {
+    timestamp: HeaderTimestamp,
+    process_id: String,
+    /// `(seq, chunk)` when `syslog_sequence_numbers` is on
+    sequence: Option<(u64, u32)>,
+    text: String,
+}
+
+/// A message being reassembled from its chunks.
+struct PendingMessage {
+    line_number: usize,
+    /// Where the message's first line is in the input
+    context: LineContext,
+    line: String,
+    timestamp: DateTime<Utc>,
+    process_id: String,
+    /// Syslog sequence number, when `syslog_sequence_numbers` is on
+    seq: Option<u64>,
+    chunks: Vec<(u32, String)>,
+}
+
+/// Infers the year for headers that omit it.
+///
+/// Without an explicit year, a first line dated later in the year than today
+/// is assumed to come from last year. The year advances when the month jumps
+/// backwards by more than six months, e.g. from December to January.
+struct YearTracker {
+    year: i32,
+    explicit: bool,
+    last_month: Option<u32>,
+}
+
+impl YearTracker {
+    fn new(year: Option<i32>) -> Self {
+        Self {
+            year: year.unwrap_or_else(|| Utc::now().year()),
+            explicit: year.is_some(),
+            last_month: None,
+        }
+    }
+
+    fn resolve(&mut self, month: u32) -> i32 {
+        match self.last_month {
+            None if !self.explicit && month > Utc::now().month() => self.year -= 1,
+            Some(last_month) if last_month > month + 6 => self.year += 1,
+            _ => {}
+        }
+        self.last_month = Some(month);
+        self.year
+    }
+}
+
+impl SyslogParser {
+    /// Create a syslog parser that infers the year from the current date
+    pub fn new() -> Self {
+        let regexes = syslog_regexes();
+        Self {
+            bsd_header_regex: &regexes.bsd_header,
+            rfc3339_header_regex: &regexes.rfc3339_header,
+            payload_regex: &regexes.payload,
+            year: None,
+            max_entries: None,
+            collapse_lists: true,
+        }
+    }
+
+    /// Use a fixed starting year for headers that omit it
+    pub fn with_year(mut self, year: i32) -> Self {
+        self.year = Some(year);
+        self
+    }
+
+    /// Stop parsing once `max_entries` entries were produced; where parsing
+    /// stopped is recorded as the report's
+    /// [`truncation`](crate::ParseReport::truncation)
+    pub fn with_max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Collapse `IN` lists and multi-row `VALUES` of like literals when
+    /// normalizing statements; on by default
+    pub fn with_collapse_lists(mut self, collapse: bool) -> Self {
+        self.collapse_lists = collapse;
+        self
+    }
+
+    fn parse_header(&self, line: &str) -> Option<SyslogHeader> {
+        if let Some(captures) = self.bsd_header_regex.captures(line) {
+            let month = MONTHS.iter().position(|m| *m == &captures[1])? as u32 + 1;
+            let time = NaiveTime::parse_from_str(&captures[3], "%H:%M:%S").ok()?;
+            return Some(SyslogHeader {
+                timestamp: HeaderTimestamp::Partial {
+                    month,
+                    day: captures[2].parse().ok()?,
+                    time,
+                },
+                process_id: captures[6].to_string(),
+                sequence: sequence(captures.get(7), captures.get(8)),
+                text: unescape(&captures[9]),
+            });
+        }
+
+        let captures = self.rfc3339_header_regex.captures(line)?;
+        let timestamp = DateTime::parse_from_rfc3339(&captures[1])
+            .map(|dt| dt.with_timezone(&Utc))
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(&captures[1], "%Y-%m-%dT%H:%M:%S%.f")
+                    .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+            })
+            .ok()?;
+
+        Some(SyslogHeader {
+            timestamp: HeaderTimestamp::Full(timestamp),
+            process_id: captures[4].to_string(),
+            sequence: sequence(captures.get(5), captures.get(6)),
+            text: unescape(&captures[7]),
+        })
+    }
+
+    fn resolve_timestamp(
+        &self,
+        timestamp: &HeaderTimestamp,
+        years: &mut YearTracker,
+    ) -> Result<DateTime<Utc>> {
+        match timestamp {
+            HeaderTimestamp::Full(timestamp) => Ok(*timestamp),
+            HeaderTimestamp::Partial { month, day, time } => {
+                let year = years.resolve(*month);
+                NaiveDate::from_ymd_opt(year, *month, *day)
+                    .map(|date| DateTime::from_naive_utc_and_offset(date.and_time(*time), Utc))
+                    .ok_or_else(|| {
+                        timestamp_error(
+                            "Invalid syslog date",
+                            &format!("{} {} {}", MONTHS[*month as usize - 1], day, time),
+                        )
+                    })
+            }
+        }
+    }
+
+    /// Parse a reassembled message payload: log line prefix, `LEVEL:`, message
+    fn parse_payload(
+        &self,
+        parser: &mut TextLogParser,
+        message: &PendingMessage,
+        payload: &str,
+    ) -> Result<Option<LogEntry>> {
+        let Some(captures) = self.payload_regex.captures(payload) else {
+            return Ok(None);
+        };
+
+        let prefix = captures.get(1).map(|m| m.as_str()).unwrap_or("");
+        let chunk = message
+            .seq
+            .and(message.chunks.last().map(|(chunk, _)| *chunk));
+        let metadata = LogMetadata::from_prefix(&message.process_id, prefix)
+            .with_session_line(message.seq.and_then(|seq| u32::try_from(seq).ok()), chunk);
+        parser.parse_message(message.timestamp, metadata, &captures[2], &captures[3])
+    }
+
+    /// Kind and text of a `DETAIL:`, `HINT:`, `CONTEXT:`, or `STATEMENT:` payload
+    fn follow_up<'a>(&self, payload: &'a str) -> Option<(FollowUp, &'a str)> {
+        let captures = self.payload_regex.captures(payload)?;
+        let follow_up = FollowUp::from_level(captures.get(2)?.as_str())?;
+        Some((follow_up, captures.get(3)?.as_str()))
+    }
+
+    /// Parse syslog lines, reassembling split messages, and report skipped lines.
+    ///
+    /// Error follow-up lines are folded into the preceding error of the same
+    /// process, as in [`TextLogParser::parse_lines_with_report`].
+    pub fn parse_lines_with_report(&self, lines: &[String]) -> (Vec<LogEntry>, ParseReport) {
+        self.parse_lines_with_context(lines, &LineContext::default())
+    }
+
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report) for the
+    /// lines of the input `context` names, locating the errors of skipped
+    /// lines in it
+    pub fn parse_lines_with_context(
+        &self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> (Vec<LogEntry>, ParseReport) {
+        let mut report = ParseReport::new();
+        let mut years = YearTracker::new(self.year);
+        let mut messages: Vec<PendingMessage> = Vec::new();
+        let mut open_messages: HashMap<(String, u64), usize> = HashMap::new();
+        let mut byte_offset = 0;
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 1;
+            let line_context = context.at(line_number, byte_offset);
+            byte_offset += line.len() as u64 + 1;
+            report.total_lines += 1;
+
+            if line.trim().is_empty() {
+                report.record_skipped(line_number, SkipReason::Empty, line);
+                continue;
+            }
+
+            let Some(header) = self.parse_header(line.trim_end()) else {
+                report.record_skipped(line_number, SkipReason::RegexNoMatch, line);
+                continue;
+            };
+
+            if let Some((seq, chunk)) = header.sequence {
+                if chunk > 1 {
+                    match open_messages.get(&(header.process_id.clone(), seq)) {
+                        Some(&slot) => {
+                            messages[slot].chunks.push((chunk, header.text));
+                            report.continuation_lines += 1;
+                        }
+                        None => report.record_skipped(
+                            line_number,
+                            SkipReason::ContinuationWithoutPending,
+                            line,
+                        ),
+                    }
+                    continue;
+                }
+            }
+
+            let timestamp = match self.resolve_timestamp(&header.timestamp, &mut years) {
+                Ok(timestamp) => timestamp,
+                Err(err) => {
+                    report.record_failed(&line_context, SkipReason::TimestampError, line, err);
+                    continue;
+                }
+            };
+
+            if let Some((seq, _)) = header.sequence {
+                open_messages.insert((header.process_id.clone(), seq), messages.len());
+            }
+            messages.push(PendingMessage {
+                line_number,
+                context: line_context,
+                line: line.clone(),
+                timestamp,
+                process_id: header.process_id,
+                seq: header.sequence.map(|(seq, _)| seq),
+                chunks: vec![(1, header.text)],
+            });
+        }
+
+        let mut parser = TextLogParser::new().with_collapse_lists(self.collapse_lists);
+        let mut entries = Vec::new();
+        let mut error_groups = ErrorGroups::new();
+        for mut message in messages {
+            message.chunks.sort_by_key(|(chunk, _)| *chunk);
+            let payload = message
+                .chunks
+                .iter()
+                .map(|(_, text)| text.trim())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            if let Some((follow_up, text)) = self.follow_up(&payload) {
+                if error_groups.fold(&mut entries, &message.process_id, follow_up, text) {
+                    report.continuation_lines += 1;
+                    continue;
+                }
+            }
+
+            match self.parse_payload(&mut parser, &message, &payload) {
+                Ok(Some(_))
+                    if report.stop_at_limit(
+                        self.max_entries,
+                        entries.len(),
+                        message.line_number,
+                    ) =>
+                {
+                    break;
+                }
+                Ok(Some(mut entry)) => {
+                    entry.seq = entries.len() as u64;
+                    entries.push(entry);
+                    error_groups.observe(&entries, entries.len() - 1);
+                }
+                Ok(None) => report.record_skipped(
+                    message.line_number,
+                    SkipReason::RegexNoMatch,
+                    &message.line,
+                ),
+                Err(err) => report.record_failed(
+                    &message.context,
+                    SkipReason::TimestampError,
+                    &message.line,
+                    err,
+                ),
+            }
+        }
+
+        error_groups.finish(&mut entries, |sql| parser.parse_sql(sql));
+        attach_session_clients(&mut entries);
+
+        report.parsed_entries = entries.len();
+        report.fallback_statements = parser.fallback_statements();
+        (entries, report)
+    }
+
+    /// Parse syslog lines into log entries
+    ///
+    /// Lines that cannot be parsed are skipped and never fail the batch; see
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report).
+    pub fn parse_lines(&self, lines: &[String]) -> Result<Vec<LogEntry>> {
+        let (entries, _report) = self.parse_lines_with_report(lines);
+        Ok(entries)
+    }
+}
+
+impl Default for SyslogParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sequence(seq: Option<regex::Match>, chunk: Option<regex::Match>) -> Option<(u64, u32)> {
+    Some((seq?.as_str().parse().ok()?, chunk?.as_str().parse().ok()?))
+}
+
+/// rsyslog escapes control characters as `#ooo`; restore tabs.
+fn unescape(text: &str) -> String {
+    text.replace("#011", "\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogLevel;
+    use chrono::TimeZone;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_single_chunk_statement() {
+        let parser = SyslogParser::new().with_year(2024);
+        let (entries, report) = parser.parse_lines_with_report(&lines(&[
+            "Aug 15 10:30:15 dbhost postgres[12345]: [3-1] user=app,db=prod LOG:  statement: SELECT 1",
+        ]));
+
+        assert_eq!(report.parsed_entries, 1);
+        let entry = &entries[0];
+        assert_eq!(
+            entry.timestamp,
+            Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 15).unwrap()
+        );
+        assert_eq!(entry.process_id, "12345");
+        assert_eq!(entry.user.as_deref(), Some("app"));
+        assert_eq!(entry.database.as_deref(), Some("prod"));
+        assert_eq!(entry.message_type, LogLevel::Statement);
+        assert_eq!(entry.message, "statement: SELECT 1");
+    }
+
+    #[test]
+    fn reassembles_interleaved_chunks_in_order() {
+        let parser = SyslogParser::new().with_year(2024);
+        let (entries, report) = parser.parse_lines_with_report(&lines(&[
+            "Aug 15 10:30:15 dbhost postgres[100]: [7-1] user=app,db=prod LOG:  statement: SELECT id,",
+            "Aug 15 10:30:15 dbhost postgres[200]: [4-1] user=etl,db=prod LOG:  statement: SELECT 2",
+            "Aug 15 10:30:15 dbhost postgres[100]: [7-3] #011WHERE id = 1",
+            "Aug 15 10:30:15 dbhost postgres[100]: [7-2] #011name FROM users",
+        ]));
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(report.continuation_lines, 2);
+        assert_eq!(
+            entries[0].message,
+            "statement: SELECT id, name FROM users WHERE id = 1"
+        );
+        assert_eq!(
+            (entries[0].session_line, entries[0].chunk),
+            (Some(7), Some(3))
+        );
+        assert_eq!(entries[1].process_id, "200");
+    }
+
+    #[test]
+    fn infers_year_rollover() {
+        let parser = SyslogParser::new().with_year(2023);
+        let (entries, _) = parser.parse_lines_with_report(&lines(&[
+            "Dec 31 23:59:59 dbhost postgres[1]: [1-1] LOG:  checkpoint starting: time",
+            "Jan  1 00:00:01 dbhost postgres[1]: [2-1] LOG:  checkpoint complete",
+        ]));
+
+        assert_eq!(entries[0].timestamp.year(), 2023);
+        assert_eq!(entries[1].timestamp.year(), 2024);
+        assert_eq!(entries[1].timestamp.day(), 1);
+    }
+
+    #[test]
+    fn accepts_rfc3339_headers_and_user_at_db_prefix() {
+        let parser = SyslogParser::new();
+        let (entries, _) = parser.parse_lines_with_report(&lines(&[
+            "2024-08-15T10:30:15.123456+00:00 dbhost postgres[12345]: [3-1] app@prod LOG:  duration: 12.5 ms",
+        ]));
+
+        let entry = &entries[0];
+        assert_eq!(entry.timestamp.year(), 2024);
+        assert_eq!(entry.user.as_deref(), Some("app"));
+        assert_eq!(entry.database.as_deref(), Some("prod"));
+        assert_eq!(entry.duration, Some(12.5));
+    }
+
+    #[test]
+    fn reads_sqlstate_from_prefix() {
+        let parser = SyslogParser::new().with_year(2024);
+        let (entries, _) = parser.parse_lines_with_report(&lines(&[
+            "Aug 15 10:30:15 dbhost postgres[1]: [4-1] user=app,db=prod,sqlstate=40P01 ERROR:  deadlock detected",
+            "Aug 15 10:30:16 dbhost postgres[1]: [5-1] app@prod 57014 ERROR:  canceling statement due to user request",
+        ]));
+
+        assert_eq!(entries[0].sqlstate.as_deref(), Some("40P01"));
+        assert_eq!(entries[0].message, "deadlock detected");
+        assert_eq!(entries[1].sqlstate.as_deref(), Some("57014"));
+        assert_eq!(entries[1].database.as_deref(), Some("prod"));
+    }
+
+    #[test]
+    fn folds_error_follow_ups_into_the_error() {
+        let parser = SyslogParser::new().with_year(2024);
+        let (entries, report) = parser.parse_lines_with_report(&lines(&[
+            "Aug 15 10:30:15 dbhost postgres[1]: [4-1] app@prod 42P01 ERROR:  relation \"nope\" does not exist",
+            "Aug 15 10:30:15 dbhost postgres[2]: [7-1] app@prod LOG:  statement: SELECT 1",
+            "Aug 15 10:30:15 dbhost postgres[1]: [4-2] at character 15",
+            "Aug 15 10:30:15 dbhost postgres[1]: [5-1] app@prod 42P01 STATEMENT:  SELECT *",
+            "Aug 15 10:30:15 dbhost postgres[1]: [5-2] \tFROM nope",
+        ]));
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].message,
+            "relation \"nope\" does not exist at character 15"
+        );
+        assert_eq!(entries[0].statement.as_deref(), Some("SELECT * FROM nope"));
+        assert_eq!(
+            entries[0].queries.as_ref().unwrap()[0].normalized_query,
+            "SELECT * FROM nope"
+        );
+        assert_eq!(report.continuation_lines, 3);
+    }
+
+    #[test]
+    fn reports_orphan_chunks_and_unmatched_lines() {
+        let parser = SyslogParser::new().with_year(2024);
+        let (entries, report) = parser.parse_lines_with_report(&lines(&[
+            "Aug 15 10:30:15 dbhost postgres[1]: [9-2] FROM users",
+            "2024-08-15 10:30:15.123 UTC [1] app@prod psql: LOG:  statement: SELECT 1",
+            "Aug 15 10:30:15 dbhost postgres[1]: [10-1] no level marker here",
+        ]));
+
+        assert!(entries.is_empty());
+        assert_eq!(report.skipped.continuation_without_pending, 1);
+        assert_eq!(report.skipped.regex_no_match, 2);
+    }
+}
@@ -2,11 +2,34 @@
 //!
 //! Handles the default text log prefix `log_line_prefix =
 //! '%m [%p] %q%u@%d %a: '` and Amazon RDS logs with the documented RDS prefix
-//! shape `%t:%r:%u@%d:[%p]:`.
-
-use crate::{timestamp_error, LogEntry, LogLevel, PgLogstatsError, Result};
+//! shape `%t:%r:%u@%d:[%p]:`. Prefixes with a session line number, such as
+//! `'%m [%p]: [%l-1] user=%u,db=%d '`, are accepted too; records continuing
+//! the same `[n-m]` message are appended to it.
+
+use super::client::{connection_received_address, parse_remote_host, SessionClients};
+use super::duration::{extract_duration_ms, parse_duration_ms};
+use super::error_group::{ErrorGroups, FollowUp};
+use super::report::{ParseReport, SkipReason};
+use super::LogParser;
+use crate::prepared::{PhaseMessage, PreparePhase};
+use crate::sqlstate::is_sqlstate;
+use crate::{
+    timestamp_error, ConnectionEventKind, LineContext, LogEntry, LogLevel, Query, Result,
+    TextNormalizer,
+};
 use chrono::{DateTime, Utc};
 use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Level tags of the lines PostgreSQL writes after an error
+const FOLLOW_UP_TAGS: [&str; 4] = ["DETAIL:", "HINT:", "CONTEXT:", "STATEMENT:"];
+
+/// Free-form log line prefix, `LEVEL:`, and message
+pub(crate) const PAYLOAD_PATTERN: &str = r"^(?:(.*?)\s+)?(DEBUG[1-5]?|INFO|NOTICE|WARNING|ERROR|LOG|FATAL|PANIC|STATEMENT|DETAIL|HINT|CONTEXT|LOCATION):\s+(.*)$";
+
+/// Recent entries searched for the message a `[n-m]` chunk continues
+const CHUNK_LOOKBACK: usize = 64;
 
 /// Text log prefix variants supported by the parser.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,38 +52,358 @@ impl TextLogFormat {
     }
 }
 
+/// Patterns shared by every `TextLogParser`, compiled on first use
+struct TextRegexes {
+    log_line: Regex,
+    rds_log_line: Regex,
+    session_line: Regex,
+    payload: Regex,
+    duration: Regex,
+    duration_statement: Regex,
+    duration_phase: Regex,
+    execute_statement: Regex,
+    parameter: Regex,
+}
+
+fn text_regexes() -> &'static TextRegexes {
+    static REGEXES: OnceLock<TextRegexes> = OnceLock::new();
+    REGEXES.get_or_init(|| TextRegexes {
+        log_line: Regex::new(
+            r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?) ([A-Za-z0-9_+\-:/]+) \[(\d+)\] ([^@]+)@([^ ]+) ([^:]+): (\w+):\s*(.+)$"
+        )
+        .unwrap(),
+        rds_log_line: Regex::new(
+            r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?)(?: ([^:]+))?:(.*):([^@]+)@([^:]+):\[(\d+)\]:(\w+):\s*(.+)$"
+        )
+        .unwrap(),
+        session_line: Regex::new(
+            r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?) ([A-Za-z0-9_+\-:/]+) \[(\d+)\]:? \[(\d+)(?:-(\d+))?\] ?(.*)$"
+        )
+        .unwrap(),
+        payload: Regex::new(PAYLOAD_PATTERN).unwrap(),
+        duration: Regex::new(r"duration:\s*([\d.]+)\s*ms").unwrap(),
+        duration_statement: Regex::new(
+            r"^duration:\s*([\d.]+\s*(?:ms|s|min))\s+(?:statement|execute\s+[^:]+):\s*(.+)$"
+        )
+        .unwrap(),
+        duration_phase: Regex::new(
+            r"^duration:\s*[\d.]+\s*(?:ms|s|min)\s+(?:parse|bind)\s+[^:]+:"
+        )
+        .unwrap(),
+        execute_statement: Regex::new(r"^execute\s+[^:]+:\s*(.+)$").unwrap(),
+        parameter: Regex::new(r"\$(\d+)").unwrap(),
+    })
+}
+
 /// Parser for supported text log formats.
 pub struct TextLogParser {
-    pub log_line_regex: Regex,
-    pub rds_log_line_regex: Regex,
-    duration_regex: Regex,
-    duration_statement_regex: Regex,
-    execute_statement_regex: Regex,
-    parameter_regex: Regex,
+    pub log_line_regex: &'static Regex,
+    pub rds_log_line_regex: &'static Regex,
+    session_line_regex: &'static Regex,
+    payload_regex: &'static Regex,
+    duration_regex: &'static Regex,
+    duration_statement_regex: &'static Regex,
+    duration_phase_regex: &'static Regex,
+    execute_statement_regex: &'static Regex,
+    parameter_regex: &'static Regex,
     format: TextLogFormat,
     // State for handling multi-line statements
     pending_statement: Option<PendingStatement>,
+    // Defer query normalization until the statement is assembled
+    assemble_statements: bool,
+    // Drop continuation lines that could belong to more than one backend
+    strict_multiline: bool,
+    // Normalizes statements that sqlparser rejects
+    text_normalizer: TextNormalizer,
+    // Statements normalized by `text_normalizer`
+    fallback_statements: usize,
+    // Stop parsing before the entry past this many
+    max_entries: Option<usize>,
+    // Leave a statement still pending at the end of the input out
+    hold_back_pending_statement: bool,
 }
 
+/// Session fields taken from a log line prefix.
 #[derive(Debug, Clone)]
-struct LogMetadata {
+pub(crate) struct LogMetadata {
     process_id: String,
     user: Option<String>,
     database: Option<String>,
     client_host: Option<String>,
+    client_port: Option<u16>,
     application_name: Option<String>,
+    sqlstate: Option<String>,
+    session_line: Option<u32>,
+    chunk: Option<u32>,
+    session_id: Option<String>,
+    virtual_txid: Option<String>,
+    txid: Option<u64>,
+}
+
+/// A line whose prefix has a `[n-m]` session line number.
+struct SessionRecord<'a> {
+    timestamp: &'a str,
+    timezone: &'a str,
+    process_id: &'a str,
+    session_line: u32,
+    chunk: Option<u32>,
+    /// Everything after the `[n-m]` pair
+    text: &'a str,
+}
+
+impl SessionRecord<'_> {
+    /// Whether the record continues an earlier record of the same message
+    fn is_continuation(&self) -> bool {
+        self.chunk.is_some_and(|chunk| chunk > 1)
+    }
 }
 
 /// Represents a statement that spans multiple lines
 #[derive(Debug)]
 struct PendingStatement {
-    timestamp: DateTime<Utc>,
-    process_id: String,
-    user: String,
-    database: String,
-    application_name: String,
+    entry: LogEntry,
     query: String,
     line_count: usize,
+    // Started right after another backend's statement, so a following
+    // continuation line may belong to either
+    ambiguous: bool,
+}
+
+impl PendingStatement {
+    fn new(entry: LogEntry, ambiguous: bool) -> Self {
+        let query = entry
+            .message
+            .strip_prefix("statement: ")
+            .unwrap_or(&entry.message)
+            .to_string();
+        Self {
+            entry,
+            query,
+            line_count: 1,
+            ambiguous,
+        }
+    }
+
+    fn append(&mut self, line: &str) {
+        self.query.push(' ');
+        self.query.push_str(line);
+        self.line_count += 1;
+    }
+
+    /// Normalize the assembled statement and turn it into an entry
+    fn finish(self, parser: &mut TextLogParser) -> LogEntry {
+        let mut entry = self.entry;
+        entry.queries = parser.parse_sql(&self.query);
+        entry.message = format!("statement: {}", self.query);
+        entry
+    }
+}
+
+/// Parse state of one input, fed a line at a time without doing any I/O.
+///
+/// [`TextLogParser::parse_lines_with_report`], the batches a pipeline parses
+/// files in, and the async entry stream all drive it, so they assemble
+/// statements and errors the same way. An entry is
+/// settled once no later line can change it: it is further back than a
+/// `[n-m]` chunk is looked for, and it is not an error or statement that
+/// follow-up lines of its process may still fold into.
+pub(crate) struct LineFeed {
+    // Entries not handed out yet, in log order
+    entries: Vec<LogEntry>,
+    // Entries handed out before them
+    settled: usize,
+    report: ParseReport,
+    error_groups: ErrorGroups,
+    clients: SessionClients,
+    // Process id of the last timestamped line, if it was a statement
+    last_statement_pid: Option<String>,
+    // File of the input, and where the next line starts in it
+    context: LineContext,
+    byte_offset: u64,
+    // Parser state restored or reported by `finish`
+    assemble_statements: bool,
+    fallback_statements: usize,
+    stopped: bool,
+}
+
+impl LineFeed {
+    /// Feed for the lines of the input `context` names
+    pub(crate) fn new(parser: &mut TextLogParser, context: LineContext) -> Self {
+        Self {
+            entries: Vec::new(),
+            settled: 0,
+            report: ParseReport::new(),
+            error_groups: ErrorGroups::new(),
+            clients: SessionClients::default(),
+            last_statement_pid: None,
+            context,
+            byte_offset: 0,
+            assemble_statements: std::mem::replace(&mut parser.assemble_statements, true),
+            fallback_statements: parser.fallback_statements,
+            stopped: false,
+        }
+    }
+
+    /// Parse the next line of the input.
+    ///
+    /// Returns `false` once the parser's entry limit was reached; that line
+    /// and any later ones are ignored.
+    pub(crate) fn push_line(&mut self, parser: &mut TextLogParser, line: &str) -> bool {
+        if self.stopped {
+            return false;
+        }
+        self.report.total_lines += 1;
+        let line_number = self.report.total_lines;
+        let context = self.context.at(line_number, self.byte_offset);
+        self.byte_offset += line.len() as u64 + 1;
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            self.report
+                .record_skipped(line_number, SkipReason::Empty, line);
+            return true;
+        }
+
+        if !parser.is_log_line(trimmed) {
+            // Indented lines look like statement continuations, even when
+            // they start with a timestamp inside a string literal; anything
+            // else does not match the prefix at all.
+            let indented = line.starts_with(char::is_whitespace);
+            if indented && self.error_groups.continue_line(&mut self.entries, trimmed) {
+                self.report.continuation_lines += 1;
+                return true;
+            }
+            let strict = parser.strict_multiline;
+            match &mut parser.pending_statement {
+                Some(pending) if indented && strict && pending.ambiguous => self
+                    .report
+                    .record_skipped(line_number, SkipReason::AmbiguousContinuation, line),
+                Some(pending) if indented => {
+                    pending.append(trimmed);
+                    self.report.continuation_lines += 1;
+                }
+                None if indented => self.report.record_skipped(
+                    line_number,
+                    SkipReason::ContinuationWithoutPending,
+                    line,
+                ),
+                _ => self
+                    .report
+                    .record_skipped(line_number, SkipReason::RegexNoMatch, line),
+            }
+            return true;
+        }
+
+        if let Some(record) = parser
+            .session_record(trimmed)
+            .filter(SessionRecord::is_continuation)
+        {
+            if parser.append_chunk(&mut self.entries, &mut self.error_groups, &record) {
+                self.report.continuation_lines += 1;
+            } else {
+                self.report.record_skipped(
+                    line_number,
+                    SkipReason::ContinuationWithoutPending,
+                    line,
+                );
+            }
+            return true;
+        }
+
+        // Any timestamped line ends the pending statement.
+        if let Some(statement) = parser.finish_pending_statement() {
+            self.push_entry(statement);
+        }
+        if let Some((process_id, follow_up, text)) = parser.follow_up_line(line) {
+            if self
+                .error_groups
+                .fold(&mut self.entries, process_id, follow_up, text)
+            {
+                self.last_statement_pid = None;
+                self.report.continuation_lines += 1;
+                return true;
+            }
+        }
+
+        let parsed = parser.parse_line(line);
+        if matches!(parsed, Ok(Some(_)))
+            && self.report.stop_at_limit(
+                parser.max_entries,
+                self.settled + self.entries.len(),
+                line_number,
+            )
+        {
+            self.report.total_lines -= 1;
+            self.stopped = true;
+            return false;
+        }
+        match parsed {
+            Ok(Some(entry)) if entry.message_type == LogLevel::Statement => {
+                let ambiguous = self
+                    .last_statement_pid
+                    .as_ref()
+                    .is_some_and(|pid| *pid != entry.process_id);
+                self.last_statement_pid = Some(entry.process_id.clone());
+                self.error_groups.close(&entry.process_id);
+                parser.pending_statement = Some(PendingStatement::new(entry, ambiguous));
+            }
+            Ok(Some(entry)) => {
+                self.last_statement_pid = None;
+                self.push_entry(entry);
+            }
+            Ok(None) => self
+                .report
+                .record_skipped(line_number, SkipReason::RegexNoMatch, line),
+            Err(err) => self
+                .report
+                .record_failed(&context, SkipReason::TimestampError, line, err),
+        }
+        true
+    }
+
+    fn push_entry(&mut self, entry: LogEntry) {
+        self.entries.push(entry);
+        self.error_groups
+            .observe(&self.entries, self.entries.len() - 1);
+    }
+
+    /// Hand out the settled entries, in log order
+    pub(crate) fn take_settled(&mut self, parser: &mut TextLogParser) -> Vec<LogEntry> {
+        let mut count = self.entries.len().saturating_sub(CHUNK_LOOKBACK);
+        if let Some(held) = self.error_groups.first_held() {
+            count = count.min(held);
+        }
+        self.error_groups.release(count);
+        self.take(parser, count)
+    }
+
+    /// End the input: hand out the pending statement and every entry left,
+    /// with the report of the whole input
+    pub(crate) fn finish(mut self, parser: &mut TextLogParser) -> (Vec<LogEntry>, ParseReport) {
+        if parser.hold_back_pending_statement {
+            self.report.held_back_statement = parser.pending_statement.take().is_some();
+        }
+        self.entries.extend(parser.finish_pending_statement());
+        let entries = self.take(parser, self.entries.len());
+
+        self.report.parsed_entries = self.settled;
+        self.report.fallback_statements = parser.fallback_statements - self.fallback_statements;
+        parser.assemble_statements = self.assemble_statements;
+        (entries, self.report)
+    }
+
+    /// Hand out the first `count` entries once their errors' statements are
+    /// parsed and their sessions' client addresses filled in
+    fn take(&mut self, parser: &mut TextLogParser, count: usize) -> Vec<LogEntry> {
+        let mut entries: Vec<_> = self.entries.drain(..count).collect();
+        for (seq, entry) in (self.settled as u64..).zip(&mut entries) {
+            ErrorGroups::finish_entry(entry, |sql| parser.parse_sql(sql));
+            self.clients.attach(entry);
+            entry.seq = seq;
+        }
+        self.settled += count;
+        entries
+    }
 }
 
 impl TextLogParser {
@@ -71,29 +414,77 @@ impl TextLogParser {
 
     /// Create a parser restricted to one supported text log prefix.
     pub fn with_format(format: TextLogFormat) -> Self {
+        let regexes = text_regexes();
         Self {
-            log_line_regex: Regex::new(
-                r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?) ([A-Za-z0-9_+\-:/]+) \[(\d+)\] ([^@]+)@([^ ]+) ([^:]+): (\w+):\s*(.+)$"
-            ).unwrap(),
-            rds_log_line_regex: Regex::new(
-                r"^(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?)(?: ([^:]+))?:(.*):([^@]+)@([^:]+):\[(\d+)\]:(\w+):\s*(.+)$"
-            ).unwrap(),
-            duration_regex: Regex::new(r"duration:\s*([\d.]+)\s*ms").unwrap(),
-            duration_statement_regex: Regex::new(
-                r"^duration:\s*([\d.]+)\s*ms\s+(?:statement|execute\s+[^:]+):\s*(.+)$"
-            )
-            .unwrap(),
-            execute_statement_regex: Regex::new(r"^execute\s+[^:]+:\s*(.+)$").unwrap(),
-            parameter_regex: Regex::new(r"\$(\d+)").unwrap(),
+            log_line_regex: &regexes.log_line,
+            rds_log_line_regex: &regexes.rds_log_line,
+            session_line_regex: &regexes.session_line,
+            payload_regex: &regexes.payload,
+            duration_regex: &regexes.duration,
+            duration_statement_regex: &regexes.duration_statement,
+            duration_phase_regex: &regexes.duration_phase,
+            execute_statement_regex: &regexes.execute_statement,
+            parameter_regex: &regexes.parameter,
             format,
             pending_statement: None,
+            assemble_statements: false,
+            strict_multiline: false,
+            text_normalizer: TextNormalizer::new(),
+            fallback_statements: 0,
+            max_entries: None,
+            hold_back_pending_statement: false,
         }
     }
 
+    /// Drop continuation lines whose statement directly follows a statement
+    /// from another backend, instead of attaching them to the most recent one.
+    ///
+    /// PostgreSQL only continues the most recent message, but concurrent
+    /// backends writing to the same log can interleave their lines. Dropped
+    /// lines are counted as [`SkipReason::AmbiguousContinuation`].
+    pub fn with_strict_multiline(mut self, strict: bool) -> Self {
+        self.strict_multiline = strict;
+        self
+    }
+
+    /// Stop parsing once `max_entries` entries were produced; where parsing
+    /// stopped is recorded as the report's
+    /// [`truncation`](crate::ParseReport::truncation)
+    pub fn with_max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Leave a statement still pending at the end of the input out of the
+    /// entries, since its continuation lines may not be written yet; for
+    /// inputs that are read again, as in follow mode. The report's
+    /// [`held_back_statement`](crate::ParseReport::held_back_statement)
+    /// records it.
+    pub fn with_hold_back_pending_statement(mut self, hold_back: bool) -> Self {
+        self.hold_back_pending_statement = hold_back;
+        self
+    }
+
+    /// Collapse `IN` lists and multi-row `VALUES` of like literals when
+    /// normalizing statements; on by default
+    pub fn with_collapse_lists(mut self, collapse: bool) -> Self {
+        self.text_normalizer = self.text_normalizer.with_collapse_lists(collapse);
+        self
+    }
+
+    /// Read a UTF-8 log file and parse it, as
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report) does its
+    /// lines; each entry's `source_file` is the file
+    pub fn parse_file(&mut self, path: &Path) -> Result<(Vec<LogEntry>, ParseReport)> {
+        let parsed = LogParser::parse_file(self, path)?;
+        Ok((parsed.entries, parsed.parse_report))
+    }
+
     /// Parse a single log line
     /// Returns Ok(Some(LogEntry)) for valid log entries
     /// Returns Ok(None) for unparseable lines (continuation lines, empty lines, etc.)
-    /// Returns Err for critical parsing errors
+    /// Returns Err when the line matches a log line format but its timestamp
+    /// cannot be parsed; batch parsing records such a line and moves on
     pub fn parse_line(&mut self, line: &str) -> Result<Option<LogEntry>> {
         let line = line.trim();
 
@@ -102,11 +493,20 @@ impl TextLogParser {
             return Ok(None);
         }
 
-        // Check if this is a continuation line (no timestamp)
-        if !line.chars().next().unwrap_or(' ').is_ascii_digit() {
+        // Anything without a full log line prefix continues the pending
+        // statement, even when it starts with a timestamp-like literal
+        if !self.is_log_line(line) {
             return self.handle_continuation_line(line);
         }
 
+        // Lines with a session line number may continue an earlier record.
+        if let Some(record) = self.session_record(line) {
+            if record.is_continuation() {
+                return self.handle_continuation_line(record.text.trim());
+            }
+            return self.parse_session_record(&record);
+        }
+
         // Try to parse as the default local text log line.
         if self.format.accepts_default() {
             if let Some(captures) = self.log_line_regex.captures(line) {
@@ -126,52 +526,216 @@ impl TextLogParser {
     }
 
     /// Parse multiple log lines with state management
-    pub fn parse_lines(&self, lines: &[String]) -> Result<Vec<LogEntry>> {
-        let mut parser = TextLogParser::with_format(self.format);
+    ///
+    /// Lines that cannot be parsed, including lines with a bad timestamp, are
+    /// skipped and never fail the batch; use
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report) to find out
+    /// which lines were skipped and why.
+    pub fn parse_lines(&mut self, lines: &[String]) -> Result<Vec<LogEntry>> {
+        let (entries, _report) = self.parse_lines_with_report(lines);
+        Ok(entries)
+    }
+
+    /// Parse multiple log lines and report per-line skip diagnostics
+    ///
+    /// A statement line becomes the pending statement, and the continuation
+    /// lines that follow are appended to it. The statement is normalized and
+    /// emitted when the next timestamped line arrives or the input ends, so
+    /// entries stay in log order.
+    ///
+    /// `DETAIL:`, `HINT:`, `CONTEXT:`, and `STATEMENT:` lines are folded into
+    /// the open error of the same process id, until another line from that
+    /// process arrives. A `DETAIL:  parameters: ...` line right after a
+    /// statement goes to that statement's `detail`. Other follow-up lines are
+    /// kept as entries.
+    ///
+    /// A `[n-m]` record with `m` above 1 is appended to the message of the
+    /// same process id and session line `n`.
+    pub fn parse_lines_with_report(&mut self, lines: &[String]) -> (Vec<LogEntry>, ParseReport) {
+        self.parse_lines_with_context(lines, &LineContext::default())
+    }
+
+    /// [`parse_lines_with_report`](Self::parse_lines_with_report) for the
+    /// lines of the input `context` names, locating the errors of skipped
+    /// lines in it
+    pub fn parse_lines_with_context(
+        &mut self,
+        lines: &[String],
+        context: &LineContext,
+    ) -> (Vec<LogEntry>, ParseReport) {
         let mut entries = Vec::new();
-        let mut errors = Vec::new();
+        let report =
+            self.parse_lines_in_batches(lines, context, usize::MAX, |batch| entries.extend(batch));
+        (entries, report)
+    }
 
-        for (line_number, line) in lines.iter().enumerate() {
-            match parser.parse_line(line) {
-                Ok(Some(entry)) => entries.push(entry),
-                Ok(None) => {
-                    // Skip unparseable lines silently
+    /// [`parse_lines_with_context`](Self::parse_lines_with_context), handing
+    /// the entries to `sink` in log order as they settle, every `batch_lines`
+    /// lines and at the end, instead of collecting them
+    pub(crate) fn parse_lines_in_batches(
+        &mut self,
+        lines: &[String],
+        context: &LineContext,
+        batch_lines: usize,
+        mut sink: impl FnMut(Vec<LogEntry>),
+    ) -> ParseReport {
+        let mut feed = LineFeed::new(self, context.clone());
+        for (index, line) in lines.iter().enumerate() {
+            if !feed.push_line(self, line) {
+                break;
+            }
+            if (index + 1) % batch_lines == 0 {
+                let settled = feed.take_settled(self);
+                if !settled.is_empty() {
+                    sink(settled);
                 }
-                Err(e) => {
-                    errors.push(format!("Line {}: {}", line_number + 1, e));
+            }
+        }
+        let (entries, report) = feed.finish(self);
+        sink(entries);
+        report
+    }
+
+    /// Process id, kind, and text of a `DETAIL:`, `HINT:`, `CONTEXT:`, or
+    /// `STATEMENT:` line
+    fn follow_up_line<'a>(&self, line: &'a str) -> Option<(&'a str, FollowUp, &'a str)> {
+        if !FOLLOW_UP_TAGS.iter().any(|tag| line.contains(tag)) {
+            return None;
+        }
+
+        let line = line.trim();
+        if let Some(record) = self.session_record(line) {
+            let captures = self.payload_regex.captures(record.text)?;
+            let follow_up = FollowUp::from_level(captures.get(2)?.as_str())?;
+            return Some((record.process_id, follow_up, captures.get(3)?.as_str()));
+        }
+
+        let default_captures = self
+            .format
+            .accepts_default()
+            .then(|| self.log_line_regex.captures(line))
+            .flatten();
+        let (process_id, level, text) = if let Some(captures) = default_captures {
+            (captures.get(3)?, captures.get(7)?, captures.get(8)?)
+        } else {
+            let captures = self
+                .format
+                .accepts_rds()
+                .then(|| self.rds_log_line_regex.captures(line))
+                .flatten()?;
+            (captures.get(6)?, captures.get(7)?, captures.get(8)?)
+        };
+        let follow_up = FollowUp::from_level(level.as_str())?;
+        Some((process_id.as_str(), follow_up, text.as_str()))
+    }
+
+    /// Normalize and return the pending statement, if any
+    fn finish_pending_statement(&mut self) -> Option<LogEntry> {
+        let pending = self.pending_statement.take()?;
+        Some(pending.finish(self))
+    }
+
+    /// Whether `line` has the full prefix of an accepted log line format, so
+    /// it starts a new record rather than continuing the previous one
+    fn is_log_line(&self, line: &str) -> bool {
+        if !line.starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        (self.format.accepts_default()
+            && (self.session_line_regex.is_match(line) || self.log_line_regex.is_match(line)))
+            || (self.format.accepts_rds() && self.rds_log_line_regex.is_match(line))
+    }
+
+    /// Split a line whose prefix has a `[n-m]` session line number
+    fn session_record<'a>(&self, line: &'a str) -> Option<SessionRecord<'a>> {
+        if !self.format.accepts_default() {
+            return None;
+        }
+        let captures = self.session_line_regex.captures(line)?;
+        Some(SessionRecord {
+            timestamp: captures.get(1)?.as_str(),
+            timezone: captures.get(2)?.as_str(),
+            process_id: captures.get(3)?.as_str(),
+            session_line: captures.get(4)?.as_str().parse().ok()?,
+            chunk: captures
+                .get(5)
+                .and_then(|chunk| chunk.as_str().parse().ok()),
+            text: captures.get(6)?.as_str(),
+        })
+    }
+
+    /// Parse the first record of a message: free-form prefix, `LEVEL:`,
+    /// message
+    fn parse_session_record(&mut self, record: &SessionRecord) -> Result<Option<LogEntry>> {
+        let Some(captures) = self.payload_regex.captures(record.text) else {
+            return Ok(None);
+        };
+
+        let timestamp = self.parse_timestamp(record.timestamp, record.timezone)?;
+        let prefix = captures.get(1).map(|m| m.as_str()).unwrap_or("");
+        let metadata = LogMetadata::from_prefix(record.process_id, prefix)
+            .with_session_line(Some(record.session_line), record.chunk);
+        self.parse_message(timestamp, metadata, &captures[2], &captures[3])
+    }
+
+    /// Append a continuation record to the message it belongs to: the
+    /// pending statement, a recent entry with the same process id and session
+    /// line, or the last follow-up folded into an error of that process.
+    ///
+    /// Returns `false` when there is no such message.
+    fn append_chunk(
+        &mut self,
+        entries: &mut [LogEntry],
+        error_groups: &mut ErrorGroups,
+        record: &SessionRecord,
+    ) -> bool {
+        let text = record.text.trim();
+        let belongs = |entry: &LogEntry| {
+            entry.process_id == record.process_id && entry.session_line == Some(record.session_line)
+        };
+
+        if let Some(pending) = &mut self.pending_statement {
+            if belongs(&pending.entry) {
+                pending.append(text);
+                pending.entry.chunk = record.chunk;
+                return true;
+            }
+        }
+        let recent = entries.len().saturating_sub(CHUNK_LOOKBACK);
+        if let Some(entry) = entries[recent..]
+            .iter_mut()
+            .rev()
+            .find(|entry| belongs(entry))
+        {
+            entry.message.push(' ');
+            entry.message.push_str(text);
+            entry.chunk = record.chunk;
+            // A statement finished before its last chunk is normalized again;
+            // its fallback was counted when first parsed
+            if entry.message_type == LogLevel::Statement {
+                if let Some(sql) = entry.message.strip_prefix("statement: ") {
+                    entry.queries = Some(self.text_normalizer.parse(sql).0);
                 }
             }
+            return true;
         }
+        error_groups.continue_chunk(entries, record.process_id, text)
+    }
 
-        // If we have a pending statement, finalize it
-        if let Some(pending) = parser.pending_statement.take() {
-            entries.push(LogEntry {
-                timestamp: pending.timestamp,
-                process_id: pending.process_id,
-                user: Some(pending.user),
-                database: Some(pending.database),
-                client_host: None,
-                application_name: Some(pending.application_name),
-                message_type: LogLevel::Statement,
-                message: format!("statement: {}", pending.query),
-                queries: crate::Query::from_sql(&pending.query).ok(),
-                duration: None,
-            });
-        }
-
-        if !errors.is_empty() {
-            return Err(PgLogstatsError::Parse {
-                message: format!(
-                    "Failed to parse {} lines: {}",
-                    errors.len(),
-                    errors.join("; ")
-                ),
-                line_number: None,
-                line_content: None,
-            });
+    /// Parse SQL into queries, normalizing it as text when sqlparser rejects
+    /// it so the statement is still counted
+    pub(crate) fn parse_sql(&mut self, sql: &str) -> Option<Vec<Query>> {
+        if sql.trim().is_empty() {
+            return None;
         }
+        let (queries, fallback) = self.text_normalizer.parse(sql);
+        self.fallback_statements += usize::from(fallback);
+        Some(queries)
+    }
 
-        Ok(entries)
+    /// Statements normalized as text because sqlparser rejected them
+    pub(crate) fn fallback_statements(&self) -> usize {
+        self.fallback_statements
     }
 
     /// Parse the default text log format.
@@ -212,48 +776,95 @@ impl TextLogParser {
         let message = captures.get(8).unwrap().as_str();
 
         let timestamp = self.parse_timestamp(timestamp_str, timezone)?;
-        let metadata = LogMetadata::new(
-            process_id,
-            Some(user),
-            Some(database),
-            normalize_rds_client_host(remote_host),
-            None,
-        );
+        let (client_host, client_port) = parse_remote_host(remote_host);
+        let metadata = LogMetadata::new(process_id, Some(user), Some(database), client_host, None)
+            .with_client_port(client_port);
 
         self.parse_message(timestamp, metadata, log_level, message)
     }
 
-    fn parse_message(
+    /// Turn the message part of a line (after `LEVEL:`) into an entry.
+    ///
+    /// With `log_error_verbosity = verbose` the message starts with the
+    /// SQLSTATE code, e.g. `23505: duplicate key value`; the code is moved to
+    /// the entry's `sqlstate`.
+    pub(crate) fn parse_message(
         &mut self,
         timestamp: DateTime<Utc>,
-        metadata: LogMetadata,
+        mut metadata: LogMetadata,
         log_level: &str,
         message: &str,
     ) -> Result<Option<LogEntry>> {
+        let message = match message.split_once(": ") {
+            Some((code, rest)) if is_sqlstate(code) => {
+                metadata.sqlstate = Some(code.to_string());
+                rest.trim_start()
+            }
+            _ => message,
+        };
+
+        // Prefixes without %h or %r only name the client when it connects
+        if metadata.client_host.is_none() {
+            if let Some((host, port)) = connection_received_address(message) {
+                metadata.client_host = Some(host);
+                metadata.client_port = port;
+            }
+        }
+
+        // `execute` lines keep the prepared statement name, so executes can
+        // be attributed to the SQL of their parse
+        let prepared_statement = PhaseMessage::parse(message)
+            .filter(|phase| phase.phase == PreparePhase::Execute)
+            .map(|phase| phase.statement.to_string());
+
         if let Some((duration_ms, statement)) = self.extract_duration_statement(message) {
             return self.handle_statement_message(
                 timestamp,
                 metadata,
                 statement,
                 Some(duration_ms),
+                prepared_statement,
             );
         }
 
         if let Some(statement) = self.extract_statement(message) {
-            return self.handle_statement_message(timestamp, metadata, statement, None);
+            return self.handle_statement_message(
+                timestamp,
+                metadata,
+                statement,
+                None,
+                prepared_statement,
+            );
+        }
+
+        // Parse and bind times of the extended protocol are followed by the
+        // execute line for the same statement; keeping them out of the
+        // duration entries stops them from being paired with another statement
+        if self.duration_phase_regex.is_match(message) {
+            return Ok(Some(metadata.into_entry(
+                timestamp,
+                LogLevel::from(log_level),
+                message.to_string(),
+                None,
+                self.extract_duration(message),
+            )?));
         }
 
         if message.starts_with("duration: ") {
             return self.handle_duration_message(timestamp, metadata, message);
         }
 
-        Ok(Some(metadata.into_entry(
+        // Autovacuum, checkpoint, and disconnection messages carry their own
+        // elapsed times
+        let mut entry = metadata.into_entry(
             timestamp,
             LogLevel::from(log_level),
             message.to_string(),
             None,
-            None,
-        )))
+            extract_duration_ms(message),
+        )?;
+        entry.connection_event = ConnectionEventKind::from_message(message);
+        Ok(Some(entry))
     }
 
     /// Handle statement messages (may be multi-line)
@@ -263,19 +874,25 @@ impl TextLogParser {
         metadata: LogMetadata,
         query: &str,
         duration_ms: Option<f64>,
+        prepared_statement: Option<String>,
     ) -> Result<Option<LogEntry>> {
-        // For now, always create a statement entry
-        // Multi-line handling will be done by continuation lines
-        let queries = crate::Query::from_sql(query);
-        let normalized_queries = queries.ok();
+        // When assembling multi-line statements the first line may be an
+        // incomplete statement; it is normalized once the statement is finished.
+        let normalized_queries = if self.assemble_statements {
+            None
+        } else {
+            self.parse_sql(query)
+        };
 
-        Ok(Some(metadata.into_entry(
+        let mut entry = metadata.into_entry(
             timestamp,
             LogLevel::Statement,
             format!("statement: {}", query),
             normalized_queries,
             duration_ms,
-        )))
+        )?;
+        entry.prepared_statement = prepared_statement;
+        Ok(Some(entry))
     }
 
     /// Handle duration messages
@@ -295,7 +912,7 @@ impl TextLogParser {
                 message.to_string(),
                 None,
                 Some(duration),
-            )))
+            )?))
         } else {
             // Duration message without valid duration
             Ok(Some(metadata.into_entry(
@@ -304,7 +921,7 @@ impl TextLogParser {
                 message.to_string(),
                 None,
                 None,
-            )))
+            )?))
         }
     }
 
@@ -312,9 +929,7 @@ impl TextLogParser {
     fn handle_continuation_line(&mut self, line: &str) -> Result<Option<LogEntry>> {
         if let Some(pending) = &mut self.pending_statement {
             // Append to the pending statement
-            pending.query.push(' ');
-            pending.query.push_str(line);
-            pending.line_count += 1;
+            pending.append(line);
             Ok(None)
         } else {
             // No pending statement, skip this line
@@ -354,17 +969,14 @@ impl TextLogParser {
         Err(timestamp_error("Failed to parse timestamp", timestamp_str))
     }
 
-    /// Extract duration from duration message (public for testing)
+    /// Extract duration from duration message in milliseconds (public for testing)
     pub fn extract_duration(&self, message: &str) -> Option<f64> {
-        self.duration_regex
-            .captures(message)
-            .and_then(|captures| captures.get(1))
-            .and_then(|m| m.as_str().parse::<f64>().ok())
+        extract_duration_ms(message)
     }
 
     fn extract_duration_statement<'a>(&self, message: &'a str) -> Option<(f64, &'a str)> {
         let captures = self.duration_statement_regex.captures(message)?;
-        let duration = captures.get(1)?.as_str().parse::<f64>().ok()?;
+        let duration = parse_duration_ms(captures.get(1)?.as_str())?;
         let statement = captures.get(2)?.as_str();
         Some((duration, statement))
     }
@@ -382,17 +994,17 @@ impl TextLogParser {
 
     /// Get the duration regex for testing
     pub fn duration_regex(&self) -> &Regex {
-        &self.duration_regex
+        self.duration_regex
     }
 
     /// Get the parameter regex for testing
     pub fn parameter_regex(&self) -> &Regex {
-        &self.parameter_regex
+        self.parameter_regex
     }
 }
 
 impl LogMetadata {
-    fn new(
+    pub(crate) fn new(
         process_id: &str,
         user: Option<&str>,
         database: Option<&str>,
@@ -404,10 +1016,108 @@ impl LogMetadata {
             user: user.and_then(optional_metadata_value),
             database: database.and_then(optional_metadata_value),
             client_host,
+            client_port: None,
             application_name: application_name.and_then(optional_metadata_value),
+            sqlstate: None,
+            session_line: None,
+            chunk: None,
+            session_id: None,
+            virtual_txid: None,
+            txid: None,
         }
     }
 
+    /// Extract session fields from a free-form `log_line_prefix` such as
+    /// `user=app,db=prod,app=psql,client=10.0.0.5` or `app@prod`.
+    ///
+    /// The session id (`%c`) is read from `session=`, `sid=`, or `c=`, or
+    /// from a bare token shaped like one; the virtual transaction id (`%v`)
+    /// from `vxid=` or `v=`, and the transaction id (`%x`) from `txid=`,
+    /// `xid=`, or `x=`.
+    pub(crate) fn from_prefix(process_id: &str, prefix: &str) -> Self {
+        let mut user = None;
+        let mut database = None;
+        let mut application_name = None;
+        let mut client_host = None;
+        let mut client_port = None;
+        let mut sqlstate = None;
+        let mut session_id = None;
+        let mut virtual_txid = None;
+        let mut txid = None;
+
+        for token in prefix.split([',', ' ']).filter(|token| !token.is_empty()) {
+            match token.split_once('=') {
+                Some(("user" | "usr" | "u", value)) => user = Some(value),
+                Some(("db" | "database" | "d", value)) => database = Some(value),
+                Some(("app" | "application" | "application_name" | "a", value)) => {
+                    application_name = Some(value)
+                }
+                Some(("client" | "host" | "remote" | "h" | "r", value)) => {
+                    (client_host, client_port) = parse_remote_host(value)
+                }
+                Some(("sqlstate" | "e", value)) => sqlstate = Some(value),
+                Some(("session" | "session_id" | "sid" | "c", value)) => session_id = Some(value),
+                Some(("vxid" | "virtual_txid" | "v", value)) => virtual_txid = Some(value),
+                Some(("txid" | "xid" | "x", value)) => txid = value.parse().ok(),
+                Some(_) => {}
+                None => {
+                    if let Some((token_user, token_database)) = token.split_once('@') {
+                        user = Some(token_user);
+                        database = Some(token_database);
+                    } else if is_sqlstate(token) {
+                        sqlstate = Some(token);
+                    } else if is_session_id(token) {
+                        session_id = Some(token);
+                    }
+                }
+            }
+        }
+
+        Self::new(process_id, user, database, client_host, application_name)
+            .with_client_port(client_port)
+            .with_sqlstate(sqlstate)
+            .with_transaction(session_id, virtual_txid, txid)
+    }
+
+    /// Attach the session id (`%c`), virtual transaction id (`%v`), and
+    /// transaction id (`%x`) taken from the prefix
+    pub(crate) fn with_transaction(
+        mut self,
+        session_id: Option<&str>,
+        virtual_txid: Option<&str>,
+        txid: Option<u64>,
+    ) -> Self {
+        self.session_id = session_id.and_then(optional_metadata_value);
+        self.virtual_txid = virtual_txid.and_then(optional_metadata_value);
+        self.txid = txid.filter(|txid| *txid != 0);
+        self
+    }
+
+    /// Attach the `[n-m]` session line number and chunk
+    pub(crate) fn with_session_line(
+        mut self,
+        session_line: Option<u32>,
+        chunk: Option<u32>,
+    ) -> Self {
+        self.session_line = session_line;
+        self.chunk = chunk;
+        self
+    }
+
+    /// Attach the client port taken from the prefix (`%r`)
+    pub(crate) fn with_client_port(mut self, client_port: Option<u16>) -> Self {
+        self.client_port = client_port;
+        self
+    }
+
+    /// Attach a SQLSTATE code taken from the prefix (`%e`)
+    pub(crate) fn with_sqlstate(mut self, sqlstate: Option<&str>) -> Self {
+        self.sqlstate = sqlstate
+            .filter(|code| is_sqlstate(code))
+            .map(str::to_string);
+        self
+    }
+
     fn into_entry(
         self,
         timestamp: DateTime<Utc>,
@@ -415,22 +1125,38 @@ impl LogMetadata {
         message: String,
         queries: Option<Vec<crate::Query>>,
         duration: Option<f64>,
-    ) -> LogEntry {
-        LogEntry {
-            timestamp,
-            process_id: self.process_id,
-            user: self.user,
-            database: self.database,
-            client_host: self.client_host,
-            application_name: self.application_name,
-            message_type,
-            message,
-            queries,
-            duration,
-        }
+    ) -> Result<LogEntry> {
+        LogEntry::builder(timestamp, self.process_id, message_type, message)
+            .with_user(self.user)
+            .with_database(self.database)
+            .with_client_host(self.client_host)
+            .with_client_port(self.client_port)
+            .with_application_name(self.application_name)
+            .with_queries(queries)
+            .with_duration(duration)
+            .with_sqlstate(self.sqlstate)
+            .with_session_line(self.session_line, self.chunk)
+            .with_session_id(self.session_id)
+            .with_virtual_txid(self.virtual_txid)
+            .with_txid(self.txid)
+            .build()
     }
 }
 
+/// Whether `token` has the shape of a `%c` session id: the hex start time of
+/// the backend, a dot, and its hex PID, e.g. `65a5b1c0.fa0`
+fn is_session_id(token: &str) -> bool {
+    token.split_once('.').is_some_and(|(start, pid)| {
+        start.len() == 8
+            && !pid.is_empty()
+            && pid.len() <= 8
+            && start
+                .chars()
+                .chain(pid.chars())
+                .all(|c| c.is_ascii_hexdigit())
+    })
+}
+
 fn optional_metadata_value(value: &str) -> Option<String> {
     let value = value.trim();
     if value.is_empty() || value == "[unknown]" || value == "-" {
@@ -440,21 +1166,6 @@ fn optional_metadata_value(value: &str) -> Option<String> {
     }
 }
 
-fn normalize_rds_client_host(remote_host: &str) -> Option<String> {
-    let remote_host = remote_host.trim();
-    if remote_host.is_empty() || remote_host == "[unknown]" || remote_host == "-" {
-        return None;
-    }
-
-    if let Some(open_paren) = remote_host.rfind('(') {
-        if remote_host.ends_with(')') && open_paren > 0 {
-            return optional_metadata_value(&remote_host[..open_paren]);
-        }
-    }
-
-    optional_metadata_value(remote_host)
-}
-
 impl Default for TextLogParser {
     fn default() -> Self {
         Self::new()
@@ -548,7 +1259,7 @@ mod tests {
             "2024-08-14 10:30:18.123 UTC [12348] postgres@testdb psql: LOG:  duration: 12.345 ms",
         ];
 
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
         let result = parser.parse_lines(&lines.iter().map(|s| s.to_string()).collect::<Vec<_>>());
         assert!(result.is_ok());
 
@@ -590,7 +1301,7 @@ mod tests {
             "2024-08-14 10:30:15.456 UTC [12345] postgres@testdb psql: LOG:  duration: 45.123 ms",
         ];
 
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
         let result = parser.parse_lines(&lines.iter().map(|s| s.to_string()).collect::<Vec<_>>());
         assert!(result.is_ok());
 
@@ -598,6 +1309,90 @@ mod tests {
         assert_eq!(entries.len(), 2); // Should parse 2 valid lines, skip 1 invalid
     }
 
+    #[test]
+    fn test_timestamps_inside_literals_continue_the_statement() {
+        let lines: Vec<String> = [
+            "2024-08-15 10:30:15.000 UTC [12345] app@logs loader: LOG:  statement: INSERT INTO audit (line, severity) VALUES ('",
+            "    2024-08-15 10:30:15.123 UTC [999] ERROR:  disk full', 3),",
+            "    ('2024-08-15 10:30:16.000 UTC [999] LOG:  retrying",
+            "    2024-08-15 10:30:17 UTC [999] LOG:  done', 1)",
+            "2024-08-15 10:30:15.200 UTC [12345] app@logs loader: LOG:  duration: 2.500 ms",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let mut parser = TextLogParser::new();
+        let (entries, report) = parser.parse_lines_with_report(&lines);
+
+        assert_eq!(entries.len(), 2);
+        let message = &entries[0].message;
+        assert!(message.contains("disk full"), "{}", message);
+        assert!(message.ends_with("LOG:  done', 1)"), "{}", message);
+        assert_eq!(entries[1].duration, Some(2.5));
+        assert_eq!(report.continuation_lines, 3);
+        assert_eq!(report.skipped_lines(), 0);
+
+        // A line with a full prefix still starts a new entry
+        let mut parser = TextLogParser::new();
+        parser.parse_line(&lines[0]).unwrap();
+        assert!(parser.parse_line(&lines[1]).unwrap().is_none());
+        assert!(parser.parse_line(&lines[4]).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_bad_timestamp_does_not_fail_the_batch() {
+        let mut lines: Vec<String> = (0..1000)
+            .map(|i| {
+                format!(
+                    "2024-08-14 10:{:02}:{:02}.000 UTC [12345] postgres@testdb psql: LOG:  connection authorized: user=postgres",
+                    i / 60 % 60,
+                    i % 60
+                )
+            })
+            .collect();
+        lines.insert(
+            500,
+            "2024-13-45 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  connection authorized: user=postgres".to_string(),
+        );
+
+        let mut parser = TextLogParser::new();
+        let (entries, report) = parser.parse_lines_with_report(&lines);
+        assert_eq!(entries.len(), 1000);
+        assert_eq!(report.skipped.timestamp_error, 1);
+        assert_eq!(parser.parse_lines(&lines).unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn test_reused_parser_matches_a_fresh_one_per_call() {
+        let batches = [
+            vec![
+                "2024-08-14 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  statement: LISTEN jobs".to_string(),
+                "2024-08-14 10:30:15.200 UTC [12345] postgres@testdb psql: LOG:  duration: 1.000 ms".to_string(),
+            ],
+            vec![
+                "2024-08-14 10:30:16.123 UTC [12346] postgres@testdb psql: LOG:  statement: SELECT *".to_string(),
+                "    FROM users WHERE id = 7".to_string(),
+            ],
+        ];
+
+        let mut reused = TextLogParser::new();
+        for batch in &batches {
+            let (entries, report) = reused.parse_lines_with_report(batch);
+            let (fresh_entries, fresh_report) = TextLogParser::new().parse_lines_with_report(batch);
+            assert_eq!(format!("{:?}", entries), format!("{:?}", fresh_entries));
+            assert_eq!(report, fresh_report);
+        }
+        // The caller's parser keeps counting across calls
+        assert_eq!(reused.fallback_statements(), 1);
+        assert!(reused
+            .parse_line("2024-08-14 10:30:17.123 UTC [12347] postgres@testdb psql: LOG:  statement: SELECT 1")
+            .unwrap()
+            .unwrap()
+            .queries
+            .is_some());
+    }
+
     #[test]
     fn test_timestamp_parsing() {
         let parser = TextLogParser::new();
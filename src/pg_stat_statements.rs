@@ -0,0 +1,365 @@
+//! Cross-reference with a `pg_stat_statements` export
+//!
+//! An export of `pg_stat_statements`, as CSV with a header row or as JSON, is
+//! read with [`read_pg_stat_statements`]. Its query texts are normalized the
+//! way the parsers normalize logged SQL, so `$1` placeholders and literals
+//! both become `?`, and [`cross_reference`] matches them with the queries of
+//! a report. Where normalization differs, as for text the SQL parser rejects,
+//! a looser key that ignores case, whitespace, and trailing semicolons is
+//! tried too.
+//!
+//! Only the queries a report keeps, its slowest and most frequent, can be
+//! matched, so a busy `pg_stat_statements` query outside those lists is
+//! reported as missing from the logs.
+
+use crate::{query_id, AnalysisResult, PgLogstatsError, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Busiest unmatched `pg_stat_statements` queries listed by default
+pub const DEFAULT_MISSING_FROM_LOGS: usize = 10;
+
+/// One row of a `pg_stat_statements` export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PgStatStatement {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queryid: Option<String>,
+    pub query: String,
+    pub calls: u64,
+    /// Total execution time in milliseconds
+    pub total_exec_time: f64,
+    /// Mean execution time in milliseconds
+    pub mean_exec_time: f64,
+}
+
+/// Read a CSV or JSON export; the format is taken from a `.json`, `.ndjson`,
+/// or `.csv` extension, or else from the first character of the file
+pub fn read_pg_stat_statements(path: &Path) -> Result<Vec<PgStatStatement>> {
+    let text = std::fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let json = match extension.as_deref() {
+        Some("json" | "ndjson") => true,
+        Some("csv") => false,
+        _ => text.trim_start().starts_with(['[', '{']),
+    };
+    if json {
+        parse_json(&text)
+    } else {
+        parse_csv(&text)
+    }
+}
+
+/// Rows of a JSON array of objects, or of one object per line; numbers may
+/// be written as strings, as `row_to_json` does for `bigint` ids
+pub fn parse_json(text: &str) -> Result<Vec<PgStatStatement>> {
+    let trimmed = text.trim_start();
+    let rows: Vec<Value> = if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed).map_err(PgLogstatsError::Serialization)?
+    } else {
+        trimmed
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(PgLogstatsError::Serialization)?
+    };
+    rows.iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let field = |name: &str| match row.get(name) {
+                Some(Value::String(text)) => Some(text.clone()),
+                Some(Value::Null) | None => None,
+                Some(value) => Some(value.to_string()),
+            };
+            statement_from_fields(field, index + 1)
+        })
+        .collect()
+}
+
+/// Rows of CSV with a header row naming the columns, such as the output of
+/// `\copy (SELECT ...) TO 'pss.csv' CSV HEADER`
+pub fn parse_csv(text: &str) -> Result<Vec<PgStatStatement>> {
+    let mut records = csv_records(text).into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.trim().to_ascii_lowercase(), index))
+        .collect();
+    records
+        .filter(|record| record.iter().any(|value| !value.is_empty()))
+        .enumerate()
+        .map(|(index, record)| {
+            let field = |name: &str| {
+                columns
+                    .get(name)
+                    .and_then(|&column| record.get(column))
+                    .filter(|value| !value.is_empty())
+                    .cloned()
+            };
+            statement_from_fields(field, index + 2)
+        })
+        .collect()
+}
+
+/// Build a row from its named fields; `total_time` and `mean_time` are read
+/// for exports from PostgreSQL 12 and older, and a missing mean is computed
+fn statement_from_fields(
+    field: impl Fn(&str) -> Option<String>,
+    row: usize,
+) -> Result<PgStatStatement> {
+    let invalid = |message: String| PgLogstatsError::Configuration {
+        message: format!("pg_stat_statements row {}: {}", row, message),
+        field: Some("pg_stat_statements".to_string()),
+    };
+    let number = |names: &[&str]| -> Result<Option<f64>> {
+        let Some((name, value)) = names
+            .iter()
+            .find_map(|name| field(name).map(|value| (*name, value)))
+        else {
+            return Ok(None);
+        };
+        value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| invalid(format!("{} '{}' is not a number", name, value)))
+    };
+
+    let query = field("query").ok_or_else(|| invalid("no query".to_string()))?;
+    let calls = number(&["calls"])?.ok_or_else(|| invalid("no calls".to_string()))?;
+    let total_exec_time = number(&["total_exec_time", "total_time"])?
+        .ok_or_else(|| invalid("no total_exec_time".to_string()))?;
+    let mean_exec_time = number(&["mean_exec_time", "mean_time"])?.unwrap_or(if calls > 0.0 {
+        total_exec_time / calls
+    } else {
+        0.0
+    });
+    Ok(PgStatStatement {
+        queryid: field("queryid"),
+        query,
+        calls: calls as u64,
+        total_exec_time,
+        mean_exec_time,
+    })
+}
+
+/// Fields of each record of RFC 4180 CSV, where quoted fields may hold
+/// commas, newlines, and `""` for a quote
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Looser key of normalized SQL: lowercase, single-spaced, without trailing
+/// semicolons, and with `$n` placeholders as `?`
+pub fn loose_key(normalized_sql: &str) -> String {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\$[0-9]+").unwrap());
+    let text = normalized_sql.trim().trim_end_matches(';').to_lowercase();
+    let text = placeholder.replace_all(&text, "?");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A query of the report with the `pg_stat_statements` row it matched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryCrossReference {
+    /// Normalized query of the report
+    pub query: String,
+    pub query_id: String,
+    pub log_count: u64,
+    /// Average duration in the log, in milliseconds
+    pub log_avg_ms: f64,
+    /// 95th percentile duration in the log, in milliseconds
+    pub log_p95_ms: f64,
+    /// Matched row; `None` when the export has no row for the query, as when
+    /// its entry was deallocated or the export was taken elsewhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pg_stat_statements: Option<PgStatStatement>,
+}
+
+/// Queries of a report matched against a `pg_stat_statements` export
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PgStatStatementsComparison {
+    /// Rows in the export
+    pub rows: usize,
+    /// The report's slowest and most frequent queries, most frequent first
+    pub queries: Vec<QueryCrossReference>,
+    /// Busiest rows, by total execution time, that matched no query of the
+    /// report
+    pub missing_from_logs: Vec<PgStatStatement>,
+}
+
+impl PgStatStatementsComparison {
+    /// Queries of the report without a `pg_stat_statements` row
+    pub fn missing_from_pg_stat_statements(&self) -> impl Iterator<Item = &QueryCrossReference> {
+        self.queries
+            .iter()
+            .filter(|query| query.pg_stat_statements.is_none())
+    }
+}
+
+/// Match the slowest and most frequent queries of `analysis` with the rows
+/// of an export, listing up to `missing_limit` of the busiest rows that
+/// matched none of them.
+///
+/// A row matches by its normalized text, or else by [`loose_key`]; when
+/// several rows match, as for one query run by several users, their calls
+/// and times are added up.
+pub fn cross_reference(
+    analysis: &AnalysisResult,
+    statements: &[PgStatStatement],
+    missing_limit: usize,
+) -> PgStatStatementsComparison {
+    let keys: Vec<(String, String)> = statements
+        .iter()
+        .map(|statement| {
+            let normalized = crate::normalize(&statement.query).unwrap_or_default();
+            let loose = loose_key(&normalized);
+            (normalized, loose)
+        })
+        .collect();
+    let mut by_normalized: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut by_loose: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, (normalized, loose)) in keys.iter().enumerate() {
+        by_normalized.entry(normalized).or_default().push(index);
+        by_loose.entry(loose).or_default().push(index);
+    }
+
+    let mut seen = HashSet::new();
+    let report_queries = analysis
+        .most_frequent_queries
+        .iter()
+        .map(|(query, _)| query)
+        .chain(analysis.slowest_queries.iter().map(|(query, _)| query))
+        .filter(|query| seen.insert(query.as_str()));
+
+    let mut matched_rows = HashSet::new();
+    let mut queries = Vec::new();
+    for query in report_queries {
+        let rows = by_normalized
+            .get(query.as_str())
+            .or_else(|| by_loose.get(loose_key(query).as_str()));
+        let pg_stat_statements = rows.map(|rows| {
+            matched_rows.extend(rows.iter().copied());
+            combine(rows.iter().map(|&row| &statements[row]))
+        });
+        let stats = analysis.query_stats.get(query);
+        queries.push(QueryCrossReference {
+            query: query.clone(),
+            query_id: query_id(query),
+            log_count: stats.map_or(0, |stats| stats.count),
+            log_avg_ms: stats.map_or(0.0, |stats| stats.average_duration),
+            log_p95_ms: stats.map_or(0.0, |stats| stats.p95_duration),
+            pg_stat_statements,
+        });
+    }
+
+    let mut missing_from_logs: Vec<_> = statements
+        .iter()
+        .enumerate()
+        .filter(|(row, _)| !matched_rows.contains(row))
+        .map(|(_, statement)| statement.clone())
+        .collect();
+    missing_from_logs.sort_by(|a, b| {
+        b.total_exec_time
+            .total_cmp(&a.total_exec_time)
+            .then_with(|| a.query.cmp(&b.query))
+    });
+    missing_from_logs.truncate(missing_limit);
+
+    PgStatStatementsComparison {
+        rows: statements.len(),
+        queries,
+        missing_from_logs,
+    }
+}
+
+/// One row adding up the calls and times of `rows`, keeping the first id
+fn combine<'a>(rows: impl Iterator<Item = &'a PgStatStatement>) -> PgStatStatement {
+    let mut combined: Option<PgStatStatement> = None;
+    for row in rows {
+        match &mut combined {
+            None => combined = Some(row.clone()),
+            Some(combined) => {
+                combined.calls += row.calls;
+                combined.total_exec_time += row.total_exec_time;
+                // The mean of a single row is kept as exported
+                if combined.calls > 0 {
+                    combined.mean_exec_time = combined.total_exec_time / combined.calls as f64;
+                }
+            }
+        }
+    }
+    combined.unwrap_or_else(|| PgStatStatement {
+        queryid: None,
+        query: String::new(),
+        calls: 0,
+        total_exec_time: 0.0,
+        mean_exec_time: 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_quoted_csv_fields_and_old_column_names() {
+        let rows = parse_csv(
+            "queryid,query,calls,total_time\r\n\
+             42,\"SELECT a, b\nFROM t WHERE c = $1 AND d = 'x\"\"y'\",4,10.0\r\n",
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].queryid.as_deref(), Some("42"));
+        assert_eq!(
+            rows[0].query,
+            "SELECT a, b\nFROM t WHERE c = $1 AND d = 'x\"y'"
+        );
+        assert_eq!(rows[0].calls, 4);
+        assert_eq!(rows[0].mean_exec_time, 2.5);
+        assert!(parse_csv("query,calls\nSELECT 1,many\n").is_err());
+    }
+
+    #[test]
+    fn loose_key_ignores_case_spacing_and_placeholders() {
+        assert_eq!(
+            loose_key("select *\n  FROM t where id = $12;"),
+            loose_key("SELECT * FROM t WHERE id = ?")
+        );
+    }
+}
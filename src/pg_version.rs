@@ -0,0 +1,363 @@
+//! PostgreSQL major version of a log, and the message patterns of each
+//!
+//! Some messages changed shape across major versions: in 15, checkpoint
+//! completions gained the `lsn` and `redo lsn` of the checkpoint, and the
+//! autovacuum page counts traded the pages skipped for the pages scanned. A
+//! server names its version in the `starting PostgreSQL` line it logs at
+//! startup. Without one, the version is inferred from the first message whose
+//! shape only some versions log. [`LogPatterns::for_version`] picks the
+//! patterns to parse checkpoint and autovacuum messages with.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Where the major version of a log came from, least reliable first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PgVersionSource {
+    /// The shape of a checkpoint or autovacuum message; the earliest version
+    /// logging that shape
+    MessageShape,
+    /// A `starting PostgreSQL 16.3 on ...` line
+    Banner,
+    /// Given with `--assume-pg-version`
+    Assumed,
+}
+
+impl PgVersionSource {
+    /// Snake-case name as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PgVersionSource::MessageShape => "message_shape",
+            PgVersionSource::Banner => "banner",
+            PgVersionSource::Assumed => "assumed",
+        }
+    }
+}
+
+/// PostgreSQL major version a log was written by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PgVersion {
+    /// Major version, e.g. 16 for 16.3
+    pub major: u32,
+    pub source: PgVersionSource,
+}
+
+impl PgVersion {
+    /// Version given by the user
+    pub fn assumed(major: u32) -> Self {
+        Self {
+            major,
+            source: PgVersionSource::Assumed,
+        }
+    }
+
+    /// Version named by a `starting PostgreSQL 16.3 on ...` message
+    pub fn from_banner(message: &str) -> Option<Self> {
+        let major = message
+            .strip_prefix("starting PostgreSQL ")?
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()?;
+        Some(Self {
+            major,
+            source: PgVersionSource::Banner,
+        })
+    }
+
+    /// Earliest version logging a checkpoint or autovacuum `message` in its
+    /// shape; `None` for other messages and shapes no registered version logs
+    pub fn from_message_shape(message: &str) -> Option<Self> {
+        LogPatterns::registry()
+            .iter()
+            .find(|patterns| {
+                patterns.parse_checkpoint(message).is_some()
+                    || patterns.parse_vacuum(message).is_some()
+            })
+            .map(|patterns| Self {
+                major: patterns.since,
+                source: PgVersionSource::MessageShape,
+            })
+    }
+
+    /// Replace `current` with `seen` unless `current` came from a more
+    /// reliable source; a later banner wins over an earlier one, as after an
+    /// upgrade
+    pub fn update(current: &mut Option<Self>, seen: Self) {
+        if current.is_none_or(|current| current.source <= seen.source) {
+            *current = Some(seen);
+        }
+    }
+
+    /// Patterns of the messages of this version
+    pub fn patterns(&self) -> &'static LogPatterns {
+        LogPatterns::for_version(self.major)
+    }
+}
+
+impl std::fmt::Display for PgVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.major, self.source.as_str())
+    }
+}
+
+/// First major version of each pattern set, with its checkpoint completion
+/// and autovacuum patterns
+const PATTERN_SOURCES: [(u32, &str, &str); 2] = [
+    (
+        13,
+        concat!(
+            r"^(?:checkpoint|restartpoint) complete: wrote (?P<buffers>\d+) buffers \([\d.]+%\); ",
+            r"\d+ WAL file\(s\) added, \d+ removed, \d+ recycled; ",
+            r"write=[\d.]+ s, sync=[\d.]+ s, total=(?P<total>[\d.]+) s; ",
+            r"sync files=\d+, longest=[\d.]+ s, average=[\d.]+ s; ",
+            r"distance=(?P<distance>\d+) kB, estimate=\d+ kB$",
+        ),
+        concat!(
+            r"pages: (?P<pages>\d+) removed, \d+ remain, \d+ skipped due to pins, \d+ skipped frozen\s+",
+            r"tuples: (?P<tuples>\d+) removed, ",
+        ),
+    ),
+    (
+        15,
+        concat!(
+            r"^(?:checkpoint|restartpoint) complete: wrote (?P<buffers>\d+) buffers \([\d.]+%\); ",
+            r"\d+ WAL file\(s\) added, \d+ removed, \d+ recycled; ",
+            r"write=[\d.]+ s, sync=[\d.]+ s, total=(?P<total>[\d.]+) s; ",
+            r"sync files=\d+, longest=[\d.]+ s, average=[\d.]+ s; ",
+            r"distance=(?P<distance>\d+) kB, estimate=\d+ kB; ",
+            r"lsn=[0-9A-F]+/[0-9A-F]+, redo lsn=[0-9A-F]+/[0-9A-F]+$",
+        ),
+        concat!(
+            r"pages: (?P<pages>\d+) removed, \d+ remain, \d+ scanned \([\d.]+% of total\)\s+",
+            r"tuples: (?P<tuples>\d+) removed, ",
+        ),
+    ),
+];
+
+/// Checkpoint and autovacuum message patterns of a range of major versions
+#[derive(Debug)]
+pub struct LogPatterns {
+    since: u32,
+    checkpoint: Regex,
+    vacuum: Regex,
+}
+
+/// Figures of a `checkpoint complete` or `restartpoint complete` message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CheckpointDetails {
+    pub buffers_written: u64,
+    pub total_ms: f64,
+    /// WAL written since the previous checkpoint, in kB
+    pub distance_kb: u64,
+}
+
+/// Figures of an autovacuum message with its `pages:` and `tuples:` lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VacuumDetails {
+    pub pages_removed: u64,
+    pub tuples_removed: u64,
+}
+
+impl LogPatterns {
+    /// Every pattern set, oldest version first
+    pub fn registry() -> &'static [LogPatterns] {
+        static REGISTRY: OnceLock<Vec<LogPatterns>> = OnceLock::new();
+        REGISTRY.get_or_init(|| {
+            PATTERN_SOURCES
+                .iter()
+                .map(|(since, checkpoint, vacuum)| LogPatterns {
+                    since: *since,
+                    checkpoint: Regex::new(checkpoint).expect("valid checkpoint pattern"),
+                    vacuum: Regex::new(vacuum).expect("valid autovacuum pattern"),
+                })
+                .collect()
+        })
+    }
+
+    /// Patterns of major version `major`; versions older than the oldest
+    /// pattern set get that one
+    pub fn for_version(major: u32) -> &'static Self {
+        let registry = Self::registry();
+        registry
+            .iter()
+            .rev()
+            .find(|patterns| patterns.since <= major)
+            .unwrap_or(&registry[0])
+    }
+
+    /// First major version these patterns are for
+    pub fn since(&self) -> u32 {
+        self.since
+    }
+
+    /// Figures of a checkpoint completion in this version's shape
+    pub fn parse_checkpoint(&self, message: &str) -> Option<CheckpointDetails> {
+        let captures = self.checkpoint.captures(message)?;
+        Some(CheckpointDetails {
+            buffers_written: captures["buffers"].parse().ok()?,
+            total_ms: captures["total"].parse::<f64>().ok()? * 1_000.0,
+            distance_kb: captures["distance"].parse().ok()?,
+        })
+    }
+
+    /// Figures of an autovacuum message in this version's shape
+    pub fn parse_vacuum(&self, message: &str) -> Option<VacuumDetails> {
+        if !is_autovacuum_message(message) {
+            return None;
+        }
+        let captures = self.vacuum.captures(message)?;
+        Some(VacuumDetails {
+            pages_removed: captures["pages"].parse().ok()?,
+            tuples_removed: captures["tuples"].parse().ok()?,
+        })
+    }
+}
+
+/// Whether `message` reports a completed checkpoint or restart point
+pub fn is_checkpoint_message(message: &str) -> bool {
+    message.starts_with("checkpoint complete:") || message.starts_with("restartpoint complete:")
+}
+
+/// Whether `message` reports an automatic vacuum of a table, aggressive or to
+/// prevent wraparound included
+pub fn is_autovacuum_message(message: &str) -> bool {
+    message.starts_with("automatic ")
+        && message.contains("vacuum ")
+        && message.contains(" of table ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKPOINT_13: &str = "checkpoint complete: wrote 1520 buffers (9.3%); \
+        0 WAL file(s) added, 0 removed, 1 recycled; write=151.878 s, sync=0.012 s, \
+        total=151.921 s; sync files=41, longest=0.004 s, average=0.001 s; \
+        distance=16384 kB, estimate=16384 kB";
+    const CHECKPOINT_16: &str = "checkpoint complete: wrote 3 buffers (0.0%); \
+        0 WAL file(s) added, 0 removed, 0 recycled; write=0.302 s, sync=0.002 s, \
+        total=0.310 s; sync files=2, longest=0.001 s, average=0.001 s; \
+        distance=4 kB, estimate=4 kB; lsn=0/1A2B3C8, redo lsn=0/1A2B390";
+
+    #[test]
+    fn reads_the_major_version_from_the_banner() {
+        let banner = "starting PostgreSQL 16.3 (Debian 16.3-1.pgdg120+1) on \
+            x86_64-pc-linux-gnu, compiled by gcc (Debian 12.2.0-14) 12.2.0, 64-bit";
+        assert_eq!(
+            PgVersion::from_banner(banner),
+            Some(PgVersion {
+                major: 16,
+                source: PgVersionSource::Banner
+            })
+        );
+        assert_eq!(
+            PgVersion::from_banner("starting PostgreSQL 17beta1 on aarch64").map(|v| v.major),
+            Some(17)
+        );
+        assert_eq!(
+            PgVersion::from_banner("database system was shut down at 2024-08-15"),
+            None
+        );
+    }
+
+    #[test]
+    fn picks_the_checkpoint_pattern_of_each_version() {
+        let v13 = LogPatterns::for_version(13);
+        let v16 = LogPatterns::for_version(16);
+        assert_eq!((v13.since(), v16.since()), (13, 15));
+        assert_eq!(LogPatterns::for_version(14).since(), 13);
+        assert_eq!(LogPatterns::for_version(11).since(), 13);
+
+        assert_eq!(
+            v13.parse_checkpoint(CHECKPOINT_13),
+            Some(CheckpointDetails {
+                buffers_written: 1520,
+                total_ms: 151_921.0,
+                distance_kb: 16384,
+            })
+        );
+        assert_eq!(
+            v16.parse_checkpoint(CHECKPOINT_16),
+            Some(CheckpointDetails {
+                buffers_written: 3,
+                total_ms: 310.0,
+                distance_kb: 4,
+            })
+        );
+        assert_eq!(v13.parse_checkpoint(CHECKPOINT_16), None);
+        assert_eq!(v16.parse_checkpoint(CHECKPOINT_13), None);
+        assert!(v16
+            .parse_checkpoint(&CHECKPOINT_16.replace("checkpoint", "restartpoint"))
+            .is_some());
+
+        assert_eq!(
+            PgVersion::from_message_shape(CHECKPOINT_13).map(|v| v.major),
+            Some(13)
+        );
+        assert_eq!(
+            PgVersion::from_message_shape(CHECKPOINT_16).map(|v| v.major),
+            Some(15)
+        );
+        assert_eq!(PgVersion::from_message_shape("checkpoint complete"), None);
+    }
+
+    #[test]
+    fn picks_the_autovacuum_pattern_of_each_version() {
+        let v13 = "automatic vacuum of table \"shop.public.orders\": index scans: 1\n\
+            pages: 12 removed, 340 remain, 0 skipped due to pins, 0 skipped frozen\n\
+            tuples: 2500 removed, 41000 remain, 3 are dead but not yet removable, oldest xmin: 7321";
+        let v16 = "automatic aggressive vacuum of table \"shop.public.orders\": index scans: 0\n\
+            pages: 0 removed, 352 remain, 352 scanned (100.00% of total)\n\
+            tuples: 18 removed, 43000 remain, 0 are dead but not yet removable";
+
+        assert_eq!(
+            LogPatterns::for_version(13).parse_vacuum(v13),
+            Some(VacuumDetails {
+                pages_removed: 12,
+                tuples_removed: 2500,
+            })
+        );
+        assert_eq!(
+            LogPatterns::for_version(16).parse_vacuum(v16),
+            Some(VacuumDetails {
+                pages_removed: 0,
+                tuples_removed: 18,
+            })
+        );
+        assert_eq!(LogPatterns::for_version(16).parse_vacuum(v13), None);
+        assert_eq!(
+            PgVersion::from_message_shape(v16).map(|v| v.major),
+            Some(15)
+        );
+        assert!(!is_autovacuum_message("automatic analyze of table \"t\""));
+    }
+
+    #[test]
+    fn more_reliable_sources_win() {
+        let mut version = None;
+        PgVersion::update(
+            &mut version,
+            PgVersion::from_message_shape(CHECKPOINT_16).unwrap(),
+        );
+        PgVersion::update(
+            &mut version,
+            PgVersion::from_banner("starting PostgreSQL 16.3 on x86_64").unwrap(),
+        );
+        assert_eq!(version.unwrap().to_string(), "16 (banner)");
+        PgVersion::update(
+            &mut version,
+            PgVersion::from_message_shape(CHECKPOINT_13).unwrap(),
+        );
+        assert_eq!(version.unwrap().major, 16);
+
+        let mut assumed = Some(PgVersion::assumed(14));
+        PgVersion::update(
+            &mut assumed,
+            PgVersion::from_banner("starting PostgreSQL 16.3 on x86_64").unwrap(),
+        );
+        assert_eq!(assumed, Some(PgVersion::assumed(14)));
+    }
+}
@@ -0,0 +1,2495 @@
+//! High-level analysis pipeline.
+//!
+//! `Pipeline` wires the parser, entry filters, event normalization,
+//! correlation, and analyzers together so library users and the CLI share one
+//! code path. Each run returns a [`Report`] that can be rendered as JSON or
+//! text.
+//!
+//! ```
+//! use pg_logstats::{Analyzer, LogFilter, ParserKind, Pipeline};
+//!
+//! let lines: Vec<String> = [
+//!     "2024-01-15 10:00:00.123 UTC [1234] app@shop psql: LOG:  statement: SELECT * FROM users WHERE id = 1",
+//!     "2024-01-15 10:00:00.456 UTC [1234] app@shop psql: LOG:  duration: 12.5 ms",
+//!     "2024-01-15 10:00:01.000 UTC [1235] app@shop psql: ERROR:  relation \"missing\" does not exist",
+//! ]
+//! .iter()
+//! .map(|line| line.to_string())
+//! .collect();
+//!
+//! let report = Pipeline::new()
+//!     .with_parser(ParserKind::Stderr)
+//!     .with_filters(LogFilter::new().with_database("shop"))
+//!     .with_analyzers([Analyzer::Queries, Analyzer::Timing])
+//!     .run_lines(&lines)
+//!     .unwrap();
+//!
+//! let analysis = report.analysis.as_ref().unwrap();
+//! assert_eq!(analysis.total_queries, 1);
+//! assert_eq!(analysis.error_count, 1);
+//! assert_eq!(report.parse_report.parsed_entries, 3);
+//!
+//! let json = report.to_json().unwrap();
+//! assert!(json.contains("\"query_analysis\""));
+//! println!("{}", report.to_text().unwrap());
+//! ```
+
+use crate::input::{
+    discover_log_files_for_path, peek_log_lines, read_log_lines_with_charset, Charset,
+};
+#[cfg(feature = "async")]
+use crate::TextEntryStream;
+use crate::{
+    is_session_event, normalize_log_entries, parsers::LogParser, query_family_findings, query_id,
+    session_timeline, slow_query_diff_findings, AnalysisResult, Correlator, EventSourceKind,
+    FileOverlap, FindingSet, JsonFormatter, LineContext, LogEntry, MemoryLimit, NormalizedEvent,
+    ParseReport, PgLogstatsError, PgStatStatementsComparison, PgbouncerParser, PoolerAnalyzer,
+    PoolerStats, PoolerSummary, ProcessOrderCorrelator, QueryAnalyzer, QueryAnalyzerState,
+    QueryExecution, QuerySort, ReportComparison, Result, SampleEstimate, SessionEvent,
+    SlowQueryDiffOptions, SlowestRankChange, StatementSampler, StatementSampling,
+    StreamingCorrelator, SyslogParser, TextFormatter, TextLogFormat, TextLogParser, TimingAnalysis,
+    TimingAnalyzer, TimingAnalyzerState, Tz, UnterminatedLine,
+};
+use chrono::{DateTime, NaiveTime, Utc};
+use log::{info, warn};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+
+/// Entry batches queued between the parser thread and the analyzers
+const STREAM_CHANNEL_CAPACITY: usize = 4;
+/// Entries sent to the analyzers per batch
+const STREAM_BATCH_SIZE: usize = 4096;
+/// Lines read from each log file to find its first entry when ordering files
+const PEEK_LINES: usize = 200;
+
+/// Log source format the pipeline parses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParserKind {
+    /// Accept any supported text log prefix.
+    #[default]
+    Auto,
+    /// Local PostgreSQL stderr logs with the default text prefix.
+    Stderr,
+    /// Amazon RDS logs using `%t:%r:%u@%d:[%p]:`.
+    AwsRds,
+    /// pgbouncer logs; runs the pooler summary in addition to the analyzers.
+    Pgbouncer,
+    /// PostgreSQL logs delivered through syslog.
+    Syslog,
+}
+
+impl ParserKind {
+    /// Text prefix variant accepted by the parser, or `None` for formats
+    /// handled by a dedicated parser.
+    pub fn text_log_format(self) -> Option<TextLogFormat> {
+        match self {
+            Self::Auto => Some(TextLogFormat::Auto),
+            Self::Stderr => Some(TextLogFormat::Default),
+            Self::AwsRds => Some(TextLogFormat::AwsRds),
+            Self::Pgbouncer | Self::Syslog => None,
+        }
+    }
+
+    /// Names accepted by [`FromStr`](std::str::FromStr), one per format
+    pub const NAMES: [(&'static str, Self); 5] = [
+        ("auto", Self::Auto),
+        ("stderr", Self::Stderr),
+        ("rds", Self::AwsRds),
+        ("pgbouncer", Self::Pgbouncer),
+        ("syslog", Self::Syslog),
+    ];
+
+    /// Build the parser for this format with default settings
+    pub fn parser(self) -> Box<dyn LogParser> {
+        match self {
+            Self::Pgbouncer => Box::new(PgbouncerParser::new()),
+            Self::Syslog => Box::new(SyslogParser::new()),
+            kind => Box::new(TextLogParser::with_format(
+                kind.text_log_format().unwrap_or(TextLogFormat::Auto),
+            )),
+        }
+    }
+
+    /// Source kind recorded on normalized events.
+    pub fn event_source_kind(self) -> EventSourceKind {
+        match self {
+            Self::AwsRds => EventSourceKind::AwsRds,
+            Self::Pgbouncer => EventSourceKind::Pgbouncer,
+            Self::Syslog => EventSourceKind::Syslog,
+            Self::Auto | Self::Stderr => EventSourceKind::Stderr,
+        }
+    }
+}
+
+impl std::str::FromStr for ParserKind {
+    type Err = PgLogstatsError;
+
+    /// Parse a format name such as `stderr`; `default` and `aws-rds` are
+    /// accepted as aliases
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_ascii_lowercase();
+        let name = match name.as_str() {
+            "default" => "stderr",
+            "aws-rds" | "awsrds" => "rds",
+            name => name,
+        };
+        Self::NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, kind)| *kind)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::NAMES.iter().map(|(name, _)| *name).collect();
+                PgLogstatsError::Configuration {
+                    message: format!(
+                        "Unsupported log format '{}'; supported formats: {}",
+                        s.trim(),
+                        supported.join(", ")
+                    ),
+                    field: Some("format".to_string()),
+                }
+            })
+    }
+}
+
+/// Analysis stages a pipeline run can include.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Analyzer {
+    /// Aggregate query statistics (`AnalysisResult`)
+    Queries,
+    /// Timing and temporal patterns (`TimingAnalysis`)
+    Timing,
+    /// Query families ranked by total runtime (`FindingSet`)
+    QueryFamilies { limit: usize },
+    /// Chronological steps of each session (`SessionEvent`s), usually run
+    /// with a [`LogFilter::with_process_id`] or
+    /// [`LogFilter::with_session_id`] filter
+    SessionTimeline,
+}
+
+/// Entry filters applied after parsing and before analysis.
+///
+/// Empty lists match every value; all configured conditions must match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogFilter {
+    pub databases: Vec<String>,
+    pub users: Vec<String>,
+    pub applications: Vec<String>,
+    pub process_ids: Vec<String>,
+    /// Session ids of the `%c` prefix escape
+    pub session_ids: Vec<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Lowercase `query_id` values or prefixes to keep
+    pub query_ids: Vec<String>,
+    /// Timestamp patterns of which an entry must match one
+    pub include_times: Vec<TimePattern>,
+    /// Timestamp patterns dropping every entry they match
+    pub exclude_times: Vec<TimePattern>,
+    /// Daily windows of which an entry must fall in one
+    pub include_windows: Vec<TimeWindow>,
+    /// Daily windows dropping every entry inside them
+    pub exclude_windows: Vec<TimeWindow>,
+}
+
+impl LogFilter {
+    /// Create a filter that matches every entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keep entries for the given database
+    pub fn with_database(mut self, database: impl Into<String>) -> Self {
+        self.databases.push(database.into());
+        self
+    }
+
+    /// Only keep entries for the given user
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.users.push(user.into());
+        self
+    }
+
+    /// Only keep entries for the given application name
+    pub fn with_application(mut self, application: impl Into<String>) -> Self {
+        self.applications.push(application.into());
+        self
+    }
+
+    /// Only keep entries of the backend process with this PID
+    pub fn with_process_id(mut self, process_id: impl Into<String>) -> Self {
+        self.process_ids.push(process_id.into());
+        self
+    }
+
+    /// Only keep entries of the session with this `%c` session id
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_ids.push(session_id.into());
+        self
+    }
+
+    /// Only keep entries inside `[since, until)`
+    pub fn with_time_range(
+        mut self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.since = since;
+        self.until = until;
+        self
+    }
+
+    /// Only keep executions of the query with this `query_id`.
+    ///
+    /// A unique prefix such as the ID column of the text report also matches.
+    pub fn with_query_id(mut self, query_id: impl Into<String>) -> Self {
+        self.query_ids.push(query_id.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Only keep entries whose timestamp matches `pattern`; with several
+    /// include rules, matching any of them is enough
+    pub fn with_include_time(mut self, pattern: TimePattern) -> Self {
+        self.include_times.push(pattern);
+        self
+    }
+
+    /// Drop entries whose timestamp matches `pattern`, even when an include
+    /// rule matches too
+    pub fn with_exclude_time(mut self, pattern: TimePattern) -> Self {
+        self.exclude_times.push(pattern);
+        self
+    }
+
+    /// Only keep entries inside the daily `window`; with several include
+    /// rules, matching any of them is enough
+    pub fn with_include_window(mut self, window: TimeWindow) -> Self {
+        self.include_windows.push(window);
+        self
+    }
+
+    /// Drop entries inside the daily `window`, even when an include rule
+    /// matches too
+    pub fn with_exclude_window(mut self, window: TimeWindow) -> Self {
+        self.exclude_windows.push(window);
+        self
+    }
+
+    /// Whether the filter keeps every entry
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Check whether an entry passes the filter
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        matches_any(&self.databases, entry.database.as_deref())
+            && matches_any(&self.users, entry.user.as_deref())
+            && matches_any(&self.applications, entry.application_name.as_deref())
+            && matches_any(&self.process_ids, Some(&entry.process_id))
+            && matches_any(&self.session_ids, entry.session_id.as_deref())
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.matches_time_of_day(entry.timestamp)
+    }
+
+    /// Apply the include and exclude timestamp rules; exclusions win
+    fn matches_time_of_day(&self, timestamp: DateTime<Utc>) -> bool {
+        let has_includes = !self.include_times.is_empty() || !self.include_windows.is_empty();
+        let has_excludes = !self.exclude_times.is_empty() || !self.exclude_windows.is_empty();
+        if !has_includes && !has_excludes {
+            return true;
+        }
+
+        let formatted = timestamp.format(TimePattern::TIMESTAMP_FORMAT).to_string();
+        let time = timestamp.time();
+        let matches_rules = |patterns: &[TimePattern], windows: &[TimeWindow]| {
+            patterns.iter().any(|pattern| pattern.is_match(&formatted))
+                || windows.iter().any(|window| window.contains(time))
+        };
+
+        if matches_rules(&self.exclude_times, &self.exclude_windows) {
+            return false;
+        }
+        !has_includes || matches_rules(&self.include_times, &self.include_windows)
+    }
+
+    /// Check whether a statement entry has one of the filtered `query_id`s
+    pub fn matches_query_id(&self, entry: &LogEntry) -> bool {
+        if self.query_ids.is_empty() {
+            return true;
+        }
+        let normalized_sql = entry.normalized_query().unwrap_or_else(|| {
+            entry
+                .message
+                .strip_prefix("statement: ")
+                .unwrap_or(&entry.message)
+                .to_string()
+        });
+        let id = query_id(&normalized_sql);
+        self.query_ids.iter().any(|prefix| id.starts_with(prefix))
+    }
+
+    /// Drop entries that do not pass the filter
+    ///
+    /// With a `query_id` filter, statements must match it, and lines without
+    /// SQL (durations, errors) are kept only when the last statement from the
+    /// same process matched.
+    pub fn apply(&self, entries: Vec<LogEntry>) -> Vec<LogEntry> {
+        if self.is_empty() {
+            return entries;
+        }
+
+        let mut filter = EntryFilter::new(self);
+        entries
+            .into_iter()
+            .filter(|entry| filter.keep(entry))
+            .collect()
+    }
+}
+
+/// [`LogFilter`] applied one entry at a time, remembering per process whether
+/// the last statement matched the `query_id` filter
+struct EntryFilter<'a> {
+    filter: &'a LogFilter,
+    last_statement_matched: HashMap<String, bool>,
+}
+
+impl<'a> EntryFilter<'a> {
+    fn new(filter: &'a LogFilter) -> Self {
+        Self {
+            filter,
+            last_statement_matched: HashMap::new(),
+        }
+    }
+
+    fn keep(&mut self, entry: &LogEntry) -> bool {
+        if !self.filter.matches(entry) {
+            return false;
+        }
+        if self.filter.query_ids.is_empty() {
+            return true;
+        }
+        if entry.is_query() {
+            let matched = self.filter.matches_query_id(entry);
+            self.last_statement_matched
+                .insert(entry.session_key().to_string(), matched);
+            matched
+        } else {
+            self.last_statement_matched
+                .get(entry.session_key())
+                .copied()
+                .unwrap_or(false)
+        }
+    }
+}
+
+fn matches_any(allowed: &[String], value: Option<&str>) -> bool {
+    allowed.is_empty() || value.is_some_and(|value| allowed.iter().any(|a| a == value))
+}
+
+/// Regular expression matched against an entry's timestamp formatted as
+/// `YYYY-MM-DD HH:MM:SS` in UTC, as pgBadger's `--exclude-time` does
+#[derive(Debug, Clone)]
+pub struct TimePattern(Regex);
+
+impl TimePattern {
+    /// Format of the text the pattern is matched against
+    pub const TIMESTAMP_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
+
+    /// Whether the pattern matches a formatted timestamp
+    pub fn is_match(&self, timestamp: &str) -> bool {
+        self.0.is_match(timestamp)
+    }
+
+    /// The pattern as written
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for TimePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl std::str::FromStr for TimePattern {
+    type Err = PgLogstatsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Regex::new(s)
+            .map(Self)
+            .map_err(|e| PgLogstatsError::Configuration {
+                message: format!("Invalid time pattern '{}': {}", s, e),
+                field: Some("time".to_string()),
+            })
+    }
+}
+
+/// Time-of-day range repeated every day, such as `02:00-03:00`.
+///
+/// The start is inclusive and the end exclusive; a start after the end makes
+/// the window span midnight, so `23:00-01:00` covers two hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Whether a UTC time of day falls inside the window
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl std::str::FromStr for TimeWindow {
+    type Err = PgLogstatsError;
+
+    /// Parse `HH:MM-HH:MM`; seconds (`HH:MM:SS`) are accepted too
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: &str| PgLogstatsError::Configuration {
+            message: format!("Invalid time window '{}': {}", s, reason),
+            field: Some("window".to_string()),
+        };
+        let parse_time = |value: &str| {
+            let value = value.trim();
+            NaiveTime::parse_from_str(value, "%H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+                .map_err(|_| invalid("expected HH:MM-HH:MM"))
+        };
+
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| invalid("expected HH:MM-HH:MM"))?;
+        let window = Self {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        };
+        if window.start == window.end {
+            return Err(invalid("start and end are equal"));
+        }
+        Ok(window)
+    }
+}
+
+impl std::fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Parser output ready for analysis.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedLog {
+    pub entries: Vec<LogEntry>,
+    pub parse_report: ParseReport,
+    /// Periodic pgbouncer stats samples
+    pub pooler_stats: Vec<PoolerStats>,
+    /// Approximate memory held by `entries` when they were parsed
+    approximate_size: usize,
+    /// Newest entry timestamp of the files merged so far, and its file
+    latest: Option<(DateTime<Utc>, String)>,
+}
+
+impl ParsedLog {
+    /// Wrap entries produced by a text parser, counting the entries whose
+    /// timestamps jump backwards in the parse report
+    pub fn new(entries: Vec<LogEntry>, mut parse_report: ParseReport) -> Self {
+        parse_report.record_timestamp_order(entries.iter().map(|entry| entry.timestamp));
+        Self {
+            approximate_size: entries.iter().map(LogEntry::approximate_size).sum(),
+            entries,
+            parse_report,
+            pooler_stats: Vec::new(),
+            latest: None,
+        }
+    }
+
+    /// Mark `path` as the file the entries and skipped lines were read from
+    pub fn set_source_file(&mut self, path: &Path) {
+        let source_file = Arc::new(path.to_path_buf());
+        for entry in &mut self.entries {
+            entry.source_file = Some(source_file.clone());
+        }
+        self.parse_report.set_file(&path.display().to_string());
+    }
+
+    /// Append another parsed input, e.g. the next file
+    pub fn merge(&mut self, mut other: ParsedLog) {
+        self.entries.append(&mut other.entries);
+        self.parse_report.merge(other.parse_report);
+        self.pooler_stats.append(&mut other.pooler_stats);
+        self.approximate_size += other.approximate_size;
+    }
+
+    /// Append the entries parsed from `file`, a file that starts no earlier
+    /// than the ones merged before it.
+    ///
+    /// When the file starts before the previous files end, its entries are
+    /// merged in by timestamp, keeping each file's own order, and entries
+    /// repeated exactly from the earlier files (same timestamp, process id,
+    /// and message) are dropped and counted in `duplicate_entries`.
+    pub fn merge_file(&mut self, mut other: ParsedLog, file: &str) {
+        let newest = other.entries.iter().map(|entry| entry.timestamp).max();
+        let overlap = match (&self.latest, other.entries.first()) {
+            (Some((latest, earlier)), Some(first)) if first.timestamp <= *latest => {
+                Some((first.timestamp, *latest, earlier.clone()))
+            }
+            _ => None,
+        };
+
+        if let Some((start, end, earlier)) = overlap {
+            let split = self
+                .entries
+                .iter()
+                .rposition(|entry| entry.timestamp < start)
+                .map_or(0, |index| index + 1);
+            let tail = self.entries.split_off(split);
+            let duplicates = drop_duplicates(&tail, &mut other, end);
+            if start < end || duplicates > 0 {
+                self.parse_report.duplicate_entries += duplicates;
+                self.parse_report.overlapping_files.push(FileOverlap {
+                    earlier,
+                    later: file.to_string(),
+                    start,
+                    end,
+                });
+            }
+            let later = std::mem::take(&mut other.entries);
+            self.entries.extend(merge_by_timestamp(tail, later));
+        }
+
+        if let Some(newest) = newest {
+            if self
+                .latest
+                .as_ref()
+                .is_none_or(|(latest, _)| newest > *latest)
+            {
+                self.latest = Some((newest, file.to_string()));
+            }
+        }
+        self.merge(other);
+    }
+
+    /// Whether `first`, the first entry of a file, is not newer than the
+    /// files merged so far
+    fn overlaps(&self, first: &LogEntry) -> bool {
+        self.latest
+            .as_ref()
+            .is_some_and(|(latest, _)| first.timestamp <= *latest)
+    }
+
+    /// Append a batch of entries of the file being parsed, which does not
+    /// overlap the files before it
+    fn append_batch(&mut self, entries: Vec<LogEntry>) {
+        self.approximate_size += entries
+            .iter()
+            .map(LogEntry::approximate_size)
+            .sum::<usize>();
+        self.entries.extend(entries);
+    }
+
+    /// End `file`, whose entries were appended with
+    /// [`append_batch`](Self::append_batch): add its diagnostics, with
+    /// `newest` its newest entry timestamp
+    fn finish_file(&mut self, other: ParsedLog, newest: Option<DateTime<Utc>>, file: &str) {
+        if let Some(newest) = newest {
+            if self
+                .latest
+                .as_ref()
+                .is_none_or(|(latest, _)| newest > *latest)
+            {
+                self.latest = Some((newest, file.to_string()));
+            }
+        }
+        self.merge(other);
+    }
+
+    /// Sort the entries by timestamp, keeping the log order of entries with
+    /// equal timestamps; see [`LogEntry::log_order`]
+    pub fn sort_by_timestamp(&mut self) {
+        self.entries
+            .sort_by(|a, b| a.log_order().cmp(&b.log_order()));
+        self.parse_report.sorted_entries = true;
+    }
+
+    /// Approximate memory held by the parsed entries in bytes
+    pub fn approximate_size(&self) -> usize {
+        self.approximate_size
+    }
+
+    /// Move the entries out, keeping the diagnostics and pooler stats
+    fn take_entries(&mut self) -> Vec<LogEntry> {
+        self.approximate_size = 0;
+        std::mem::take(&mut self.entries)
+    }
+
+    /// Move out the entries older than `next_start`, the first timestamp of
+    /// the next file, keeping the rest so the next file can be merged with
+    /// them; all entries when there is no next file
+    fn take_entries_before(&mut self, next_start: Option<DateTime<Utc>>) -> Vec<LogEntry> {
+        let Some(next_start) = next_start else {
+            return self.take_entries();
+        };
+        let split = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.timestamp < next_start)
+            .map_or(0, |index| index + 1);
+        let kept = self.entries.split_off(split);
+        self.approximate_size = kept.iter().map(LogEntry::approximate_size).sum();
+        std::mem::replace(&mut self.entries, kept)
+    }
+}
+
+/// Remove the entries of `later` up to `end` that repeat an entry of
+/// `earlier`, matching each earlier entry at most once, and return how many
+/// were removed
+fn drop_duplicates(earlier: &[LogEntry], later: &mut ParsedLog, end: DateTime<Utc>) -> usize {
+    let key = |entry: &LogEntry| {
+        (
+            entry.timestamp,
+            entry.process_id.clone(),
+            entry.message.clone(),
+        )
+    };
+    let mut seen: HashMap<_, usize> = HashMap::new();
+    for entry in earlier {
+        *seen.entry(key(entry)).or_insert(0) += 1;
+    }
+
+    let before = later.entries.len();
+    let mut dropped_size = 0;
+    later
+        .entries
+        .retain(|entry| match seen.get_mut(&key(entry)) {
+            _ if entry.timestamp > end => true,
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                dropped_size += entry.approximate_size();
+                false
+            }
+            _ => true,
+        });
+    later.approximate_size = later.approximate_size.saturating_sub(dropped_size);
+    before - later.entries.len()
+}
+
+/// Merge two runs of entries in [`LogEntry::log_order`], keeping each run's
+/// order, so ties go by file name and then read order
+fn merge_by_timestamp(earlier: Vec<LogEntry>, later: Vec<LogEntry>) -> Vec<LogEntry> {
+    let mut merged = Vec::with_capacity(earlier.len() + later.len());
+    let mut earlier = earlier.into_iter().peekable();
+    let mut later = later.into_iter().peekable();
+    loop {
+        let take_earlier = match (earlier.peek(), later.peek()) {
+            (Some(a), Some(b)) => a.log_order() <= b.log_order(),
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let next = if take_earlier {
+            earlier.next()
+        } else {
+            later.next()
+        };
+        merged.extend(next);
+    }
+    merged
+}
+
+/// Builder that runs parsing, filtering, and analysis end to end.
+pub struct Pipeline {
+    parser_kind: ParserKind,
+    source_kind: Option<EventSourceKind>,
+    filter: LogFilter,
+    analyzers: Vec<Analyzer>,
+    sample_size: Option<usize>,
+    sampling: Option<StatementSampling>,
+    strict_multiline: bool,
+    collapse_lists: bool,
+    charset: Charset,
+    max_entries: Option<usize>,
+    max_memory_mb: Option<usize>,
+    sort_entries: bool,
+    hold_back_unterminated: bool,
+    query_analyzer: QueryAnalyzer,
+    timing_analyzer: TimingAnalyzer,
+}
+
+impl Pipeline {
+    /// Create a pipeline that auto-detects the log prefix and runs the query
+    /// and timing analyzers
+    pub fn new() -> Self {
+        Self {
+            parser_kind: ParserKind::default(),
+            source_kind: None,
+            filter: LogFilter::default(),
+            analyzers: vec![Analyzer::Queries, Analyzer::Timing],
+            sample_size: None,
+            sampling: None,
+            strict_multiline: false,
+            collapse_lists: true,
+            charset: Charset::default(),
+            max_entries: None,
+            max_memory_mb: None,
+            sort_entries: false,
+            hold_back_unterminated: false,
+            query_analyzer: QueryAnalyzer::new(),
+            timing_analyzer: TimingAnalyzer::new(),
+        }
+    }
+
+    /// Set the log format to parse
+    pub fn with_parser(mut self, parser_kind: ParserKind) -> Self {
+        self.parser_kind = parser_kind;
+        self
+    }
+
+    /// Override the source kind recorded on normalized events
+    pub fn with_source_kind(mut self, source_kind: EventSourceKind) -> Self {
+        self.source_kind = Some(source_kind);
+        self
+    }
+
+    /// Set the filters applied to parsed entries
+    pub fn with_filters(mut self, filter: LogFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Replace the analyzers run on each input
+    pub fn with_analyzers(mut self, analyzers: impl IntoIterator<Item = Analyzer>) -> Self {
+        self.analyzers = analyzers.into_iter().collect();
+        self
+    }
+
+    /// Only parse the first `sample_size` lines of each file
+    pub fn with_sample_size(mut self, sample_size: Option<usize>) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+
+    /// Analyze a random sample of the statements that pass the filters, with
+    /// their duration lines, and scale query counts and totals up by the
+    /// inverse of the rate; see [`StatementSampler`]
+    pub fn with_sampling(mut self, sampling: Option<StatementSampling>) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Drop ambiguous continuation lines from interleaved backends instead of
+    /// attaching them to the most recent statement
+    pub fn with_strict_multiline(mut self, strict: bool) -> Self {
+        self.strict_multiline = strict;
+        self
+    }
+
+    /// Collapse `IN` lists and multi-row `VALUES` of like literals when
+    /// normalizing queries, so their length does not split query counts; on
+    /// by default
+    pub fn with_collapse_lists(mut self, collapse: bool) -> Self {
+        self.collapse_lists = collapse;
+        self
+    }
+
+    /// Decode log files with `charset`; bytes invalid in UTF-8 are replaced
+    /// and counted in the parse report
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Stop parsing once `max_entries` entries were parsed across all inputs.
+    ///
+    /// Where parsing stopped is recorded as the parse report's
+    /// [`truncation`](ParseReport::truncation).
+    pub fn with_max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Bound the memory held by parsed entries in [`run_files`](Self::run_files).
+    ///
+    /// Once the entries parsed so far pass `max_memory_mb` megabytes, they are
+    /// analyzed and dropped, the remaining files are streamed as in
+    /// [`run_files_streaming`](Self::run_files_streaming), and query counts
+    /// are estimated as in low-memory mode.
+    pub fn with_max_memory_mb(mut self, max_memory_mb: Option<usize>) -> Self {
+        self.max_memory_mb = max_memory_mb;
+        self
+    }
+
+    /// Sort each input's entries by timestamp before analysis, for logs
+    /// gathered from hosts whose clocks disagree.
+    ///
+    /// Each file is sorted on its own after it is parsed, and overlapping
+    /// files are merged by timestamp as usual. The sort moves entries through
+    /// a scratch buffer of up to one `LogEntry` per entry of the file; their
+    /// strings are not copied, so peak memory grows by about
+    /// `size_of::<LogEntry>()` bytes per entry, also when streaming.
+    pub fn with_sort_entries(mut self, sort: bool) -> Self {
+        self.sort_entries = sort;
+        self
+    }
+
+    /// Leave a file's last line unparsed when it has no trailing newline,
+    /// because the server may still be writing it; for inputs that are read
+    /// again, as in follow mode. Otherwise the line is parsed and flagged in
+    /// `parse_report.unterminated_lines`.
+    ///
+    /// A statement still open at the end of the last file is left out too,
+    /// since more of its lines may follow, and is reported once the next
+    /// line of the log is written.
+    pub fn with_hold_back_unterminated(mut self, hold_back: bool) -> Self {
+        self.hold_back_unterminated = hold_back;
+        self
+    }
+
+    /// Use a custom query analyzer
+    pub fn with_query_analyzer(mut self, analyzer: QueryAnalyzer) -> Self {
+        self.query_analyzer = analyzer;
+        self
+    }
+
+    /// Use a custom timing analyzer
+    pub fn with_timing_analyzer(mut self, analyzer: TimingAnalyzer) -> Self {
+        self.timing_analyzer = analyzer;
+        self
+    }
+
+    /// Get the configured parser kind
+    pub fn parser_kind(&self) -> ParserKind {
+        self.parser_kind
+    }
+
+    /// Get the configured analyzers
+    pub fn analyzers(&self) -> &[Analyzer] {
+        &self.analyzers
+    }
+
+    /// Get the configured entry filter
+    pub fn filter(&self) -> &LogFilter {
+        &self.filter
+    }
+
+    /// Get the configured entry limit
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Whether entries parsed so far hold more memory than the configured limit
+    pub fn exceeds_memory_limit(&self, parsed: &ParsedLog) -> bool {
+        self.max_memory_mb
+            .is_some_and(|limit_mb| parsed.approximate_size() > limit_mb * 1024 * 1024)
+    }
+
+    fn source_kind(&self) -> EventSourceKind {
+        self.source_kind
+            .unwrap_or_else(|| self.parser_kind.event_source_kind())
+    }
+
+    /// Parse in-memory log lines with the configured parser
+    pub fn parse_lines(&self, lines: &[String]) -> ParsedLog {
+        let mut parsed = self.log_parser().parse_log_lines(lines);
+        if self.sort_entries {
+            parsed.sort_by_timestamp();
+        }
+        parsed
+    }
+
+    /// Build the parser for the configured format and parser settings
+    pub fn log_parser(&self) -> Box<dyn LogParser> {
+        self.parser_with_limit(self.max_entries)
+    }
+
+    fn parser_with_limit(&self, max_entries: Option<usize>) -> Box<dyn LogParser> {
+        match self.parser_kind {
+            ParserKind::Pgbouncer => Box::new(PgbouncerParser::new().with_max_entries(max_entries)),
+            ParserKind::Syslog => Box::new(
+                SyslogParser::new()
+                    .with_max_entries(max_entries)
+                    .with_collapse_lists(self.collapse_lists),
+            ),
+            kind => Box::new(self.text_parser(
+                kind.text_log_format().unwrap_or(TextLogFormat::Auto),
+                max_entries,
+            )),
+        }
+    }
+
+    fn text_parser(&self, format: TextLogFormat, max_entries: Option<usize>) -> TextLogParser {
+        TextLogParser::with_format(format)
+            .with_strict_multiline(self.strict_multiline)
+            .with_collapse_lists(self.collapse_lists)
+            .with_max_entries(max_entries)
+    }
+
+    /// Parse a text log read from `reader` into a stream of entries, with the
+    /// configured format and parser settings; see
+    /// [`TextLogParser::parse_async_reader`].
+    ///
+    /// pgbouncer and syslog logs are not supported.
+    #[cfg(feature = "async")]
+    pub fn parse_async_reader<R: tokio::io::AsyncBufRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<TextEntryStream<R>> {
+        let format =
+            self.parser_kind
+                .text_log_format()
+                .ok_or_else(|| PgLogstatsError::Configuration {
+                    message: "Async parsing supports the auto, stderr, and rds formats".to_string(),
+                    field: Some("parser".to_string()),
+                })?;
+        Ok(self
+            .text_parser(format, self.max_entries)
+            .parse_async_reader(reader))
+    }
+
+    /// Parse and analyze a text log read from `reader`, as
+    /// [`run_lines`](Self::run_lines) does its lines
+    #[cfg(feature = "async")]
+    pub async fn run_async_reader<R: tokio::io::AsyncBufRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<Report> {
+        use futures_core::Stream;
+        use std::pin::Pin;
+
+        let mut stream = self.parse_async_reader(reader)?;
+        let mut entries = Vec::new();
+        while let Some(entry) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            entries.push(entry?);
+        }
+        let parse_report = stream.parse_report().cloned().unwrap_or_default();
+        let mut parsed = ParsedLog::new(entries, parse_report);
+        if self.sort_entries {
+            parsed.sort_by_timestamp();
+        }
+        self.run_parsed(parsed)
+    }
+
+    /// Timestamp of the first entry among the first lines of a log file;
+    /// `None` when none of them parse or the file cannot be read
+    pub fn first_entry_timestamp(&self, path: &Path) -> Option<DateTime<Utc>> {
+        let lines = peek_log_lines(path, PEEK_LINES, self.charset).ok()?;
+        let parsed = self.parser_with_limit(Some(1)).parse_log_lines(&lines);
+        parsed.entries.first().map(|entry| entry.timestamp)
+    }
+
+    /// Log files ordered by the timestamp of their first entry rather than by
+    /// name, so a rotated `postgresql.log.1` comes before `postgresql.log`.
+    ///
+    /// Files without a parseable entry keep their relative order at the end.
+    pub fn order_log_files<P: AsRef<Path>>(&self, paths: &[P]) -> Vec<PathBuf> {
+        let mut files: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                let path = path.as_ref();
+                (self.first_entry_timestamp(path), path.to_path_buf())
+            })
+            .collect();
+        files.sort_by_key(|(first, _)| (first.is_none(), *first));
+        files.into_iter().map(|(_, path)| path).collect()
+    }
+
+    /// Parse one log file, honoring the configured sample size and entry limit
+    pub fn parse_file(&self, path: &Path) -> Result<ParsedLog> {
+        let mut parsed = ParsedLog::default();
+        self.parse_file_into(path, &mut parsed)?;
+        Ok(parsed)
+    }
+
+    /// Parse one more log file and append it to `parsed`, returning the
+    /// number of entries added.
+    ///
+    /// The entry limit counts everything already in `parsed`; once it is
+    /// reached, `parsed.parse_report.truncation` names the file and line
+    /// where parsing stopped.
+    pub fn parse_file_into(&self, path: &Path, parsed: &mut ParsedLog) -> Result<usize> {
+        self.parse_file_batches(path, true, parsed, |_| {})
+    }
+
+    /// [`parse_file_into`](Self::parse_file_into), calling `on_batch` each
+    /// time entries were appended to `parsed`, so it can take them out before
+    /// the rest of the file is parsed.
+    ///
+    /// Text logs are appended a batch at a time. A file that is sorted, that
+    /// starts before the files already in `parsed` end, or that is a syslog
+    /// or pgbouncer log is appended whole, once it is parsed. Only the
+    /// `last_file` of a run may still be written to.
+    fn parse_file_batches(
+        &self,
+        path: &Path,
+        last_file: bool,
+        parsed: &mut ParsedLog,
+        mut on_batch: impl FnMut(&mut ParsedLog),
+    ) -> Result<usize> {
+        let remaining = self
+            .max_entries
+            .map(|max_entries| max_entries.saturating_sub(parsed.parse_report.parsed_entries));
+        let mut decoded = read_log_lines_with_charset(path, self.sample_size, self.charset)?;
+        let held_back = decoded.unterminated && self.hold_back_unterminated;
+        if held_back {
+            decoded.lines.pop();
+        }
+        let file = path.display().to_string();
+        let context = LineContext::file(file.clone());
+
+        let mut batches = FileBatches::new(path, !self.sort_entries);
+        let (mut file_parsed, last_is_query) = match self.parser_kind.text_log_format() {
+            Some(format) => {
+                let report = self
+                    .text_parser(format, remaining)
+                    .with_hold_back_pending_statement(self.hold_back_unterminated && last_file)
+                    .parse_lines_in_batches(&decoded.lines, &context, STREAM_BATCH_SIZE, |batch| {
+                        batches.push(batch, parsed, &mut on_batch)
+                    });
+                batches.finish(report)
+            }
+            None => {
+                let file_parsed = self
+                    .parser_with_limit(remaining)
+                    .parse_log_lines_with_context(&decoded.lines, &context);
+                let last_is_query = file_parsed.entries.last().is_some_and(LogEntry::is_query);
+                (file_parsed, last_is_query)
+            }
+        };
+
+        file_parsed.parse_report.replaced_lines += decoded.replaced_lines;
+        if decoded.unterminated {
+            // The last line ends whatever entry was still being assembled
+            file_parsed
+                .parse_report
+                .unterminated_lines
+                .push(UnterminatedLine {
+                    file: None,
+                    line_number: decoded.lines.len() + usize::from(held_back),
+                    held_back,
+                    truncated_statement: !held_back && last_is_query,
+                });
+        }
+        file_parsed.set_source_file(path);
+        if self.sort_entries {
+            file_parsed.sort_by_timestamp();
+        }
+        if let Some(truncation) = &mut file_parsed.parse_report.truncation {
+            truncation.max_entries = self.max_entries.unwrap_or(truncation.max_entries);
+        }
+
+        let entries = batches.appended + file_parsed.entries.len();
+        let duplicates = parsed.parse_report.duplicate_entries;
+        if batches.appended > 0 {
+            parsed.finish_file(file_parsed, batches.newest, &file);
+        } else {
+            parsed.merge_file(file_parsed, &file);
+        }
+        on_batch(parsed);
+        Ok(entries - (parsed.parse_report.duplicate_entries - duplicates))
+    }
+
+    /// Parse a log file or every log file under a directory
+    pub fn parse_path(&self, path: &Path) -> Result<ParsedLog> {
+        let log_files = discover_log_files_for_path(path)?;
+        if log_files.is_empty() {
+            return Err(PgLogstatsError::Configuration {
+                message: format!("No log files found under {}", path.display()),
+                field: Some("path".to_string()),
+            });
+        }
+
+        let mut parsed = ParsedLog::default();
+        for log_file in self.order_log_files(&log_files) {
+            self.parse_file_into(&log_file, &mut parsed)?;
+            if parsed.parse_report.truncation.is_some() {
+                break;
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parse and analyze a list of log files, in the order of
+    /// [`order_log_files`](Self::order_log_files).
+    ///
+    /// Parsing stops at the entry limit. The memory limit is checked after
+    /// each batch of a text log is parsed; once it is passed, the entries are
+    /// analyzed and dropped as they are parsed, also in the middle of a file,
+    /// and query counts are estimated.
+    pub fn run_files<P: AsRef<Path> + Sync>(&self, paths: &[P]) -> Result<Report> {
+        self.run_files_with_progress(paths, |_| {})
+    }
+
+    /// [`run_files`](Self::run_files), telling `on_progress` when each file
+    /// is parsed and when the run moves on to analysis
+    pub fn run_files_with_progress<P: AsRef<Path> + Sync>(
+        &self,
+        paths: &[P],
+        mut on_progress: impl FnMut(RunProgress<'_>),
+    ) -> Result<Report> {
+        let paths = self.order_log_files(paths);
+        let mut parsed = ParsedLog::default();
+        let mut streaming: Option<StreamingAnalysis> = None;
+        for (index, path) in paths.iter().enumerate() {
+            on_progress(RunProgress::Parsing { index, path });
+            let mut next_start = None;
+            let last_file = index + 1 == paths.len();
+            let entries = self.parse_file_batches(path, last_file, &mut parsed, |parsed| {
+                if streaming.is_none() && self.exceeds_memory_limit(parsed) {
+                    on_progress(RunProgress::Streaming);
+                    streaming = Some(self.start_streaming(parsed));
+                }
+                if let Some(analysis) = &mut streaming {
+                    // Entries the next file may overlap stay until it is merged in
+                    let next_start = *next_start.get_or_insert_with(|| {
+                        paths
+                            .get(index + 1)
+                            .and_then(|next| self.first_entry_timestamp(next))
+                    });
+                    for entry in parsed.take_entries_before(next_start) {
+                        analysis.observe(entry);
+                    }
+                }
+            })?;
+            info!("Processed {} entries from {}", entries, path.display());
+            if parsed.parse_report.truncation.is_some() {
+                break;
+            }
+        }
+
+        match streaming {
+            Some(mut analysis) => {
+                for entry in parsed.take_entries() {
+                    analysis.observe(entry);
+                }
+                analysis.finalize(parsed)
+            }
+            None => {
+                on_progress(RunProgress::Analyzing);
+                self.run_parsed(parsed)
+            }
+        }
+    }
+
+    /// Record in `parsed` that its entries passed the memory limit
+    fn note_memory_limit(&self, parsed: &mut ParsedLog) {
+        let limit = MemoryLimit {
+            limit_mb: self.max_memory_mb.unwrap_or_default(),
+            entries: parsed.entries.len() + parsed.parse_report.duplicate_entries,
+        };
+        warn!(
+            "Parsed entries passed {} MB after {} entries; analyzing the rest as it is parsed",
+            limit.limit_mb, limit.entries
+        );
+        parsed.parse_report.memory_limit = Some(limit);
+    }
+
+    /// Analysis that takes over from a [`run_files`](Self::run_files) whose
+    /// entries in `parsed` passed the memory limit
+    fn start_streaming(&self, parsed: &mut ParsedLog) -> StreamingAnalysis<'_> {
+        self.note_memory_limit(parsed);
+        let mut analysis = StreamingAnalysis::new(self);
+        analysis.estimate_query_counts();
+        analysis
+    }
+
+    /// Parse and analyze in-memory log lines
+    pub fn run_lines(&self, lines: &[String]) -> Result<Report> {
+        self.run_parsed(self.parse_lines(lines))
+    }
+
+    /// Filter and analyze entries that were already parsed
+    pub fn run_entries(&self, entries: Vec<LogEntry>, parse_report: ParseReport) -> Result<Report> {
+        self.run_parsed(ParsedLog::new(entries, parse_report))
+    }
+
+    /// Filter and analyze parser output
+    pub fn run_parsed(&self, parsed: ParsedLog) -> Result<Report> {
+        let mut entries = self.filter.apply(parsed.entries);
+        let mut sampler = self.sampling.map(StatementSampler::new);
+        if let Some(sampler) = &mut sampler {
+            entries = sampler.apply(entries);
+        }
+        info!("Analyzing {} entries", entries.len());
+
+        let events = normalize_log_entries(&entries, self.source_kind());
+        let mut report = Report::new(entries.len(), parsed.parse_report);
+
+        if self.parser_kind == ParserKind::Pgbouncer {
+            report.pooler = Some(PoolerAnalyzer::new().analyze(&entries, &parsed.pooler_stats)?);
+        }
+
+        for analyzer in &self.analyzers {
+            match analyzer {
+                Analyzer::Queries => {
+                    report.analysis = Some(self.query_analyzer.analyze_events(&events)?);
+                    report.slow_query_threshold = Some(self.query_analyzer.slow_query_threshold());
+                    report.query_sort = Some(self.query_analyzer.query_sort());
+                    report.hour_timezone = self.query_analyzer.bucket_timezone();
+                }
+                Analyzer::Timing => {
+                    report.timing = Some(self.timing_analyzer.analyze_timing_events(&events)?);
+                    report.hour_timezone = self.timing_analyzer.config().bucket_timezone;
+                }
+                Analyzer::QueryFamilies { limit } => {
+                    let executions = ProcessOrderCorrelator.correlate(&events);
+                    report.findings = Some(query_family_findings(&executions, *limit));
+                }
+                Analyzer::SessionTimeline => {
+                    report.session_timeline = Some(session_timeline(&events));
+                }
+            }
+        }
+        if let Some(sampler) = sampler {
+            report.scale_to_sample(sampler.estimate());
+        }
+
+        Ok(report)
+    }
+
+    /// Parse and analyze log files without collecting every entry first.
+    ///
+    /// A parser thread sends each file's entries in batches over a bounded
+    /// channel, and the analyzers update as the batches arrive, so only the
+    /// file being parsed and a few queued batches are held in memory. Files
+    /// are ordered and merged as in [`run_files`](Self::run_files), whose
+    /// report this matches.
+    pub fn run_files_streaming<P: AsRef<Path> + Sync>(&self, paths: &[P]) -> Result<Report> {
+        self.stream_files(ParsedLog::default(), &self.order_log_files(paths), false)
+    }
+
+    /// Analyze entries that a batch run parsed before passing the memory
+    /// limit, then stream the remaining `paths`.
+    ///
+    /// The entries are dropped as they are analyzed, query counts are
+    /// estimated from then on, and the parse report's
+    /// [`memory_limit`](ParseReport::memory_limit) records the switch.
+    pub fn resume_streaming<P: AsRef<Path> + Sync>(
+        &self,
+        mut parsed: ParsedLog,
+        paths: &[P],
+    ) -> Result<Report> {
+        self.note_memory_limit(&mut parsed);
+        self.stream_files(parsed, paths, true)
+    }
+
+    /// Stream `paths` after the entries already in `parsed`
+    fn stream_files<P: AsRef<Path> + Sync>(
+        &self,
+        mut parsed: ParsedLog,
+        paths: &[P],
+        estimate_query_counts: bool,
+    ) -> Result<Report> {
+        // Entries that the next file may overlap are held back until it is
+        // merged in, so entries reach the analyzers in timestamp order
+        let next_start = |index: usize| {
+            paths
+                .get(index)
+                .and_then(|path| self.first_entry_timestamp(path.as_ref()))
+        };
+        let retained = parsed.take_entries_before(next_start(0));
+        let (sender, receiver) = mpsc::sync_channel::<Vec<LogEntry>>(STREAM_CHANNEL_CAPACITY);
+
+        std::thread::scope(|scope| {
+            let parser = scope.spawn(move || -> Result<ParsedLog> {
+                let mut totals = parsed;
+                for (index, path) in paths.iter().enumerate() {
+                    if totals.parse_report.truncation.is_some() {
+                        break;
+                    }
+                    let next_start = next_start(index + 1);
+                    let mut listening = true;
+                    let last_file = index + 1 == paths.len();
+                    self.parse_file_batches(path.as_ref(), last_file, &mut totals, |totals| {
+                        // Past the entry limit no later file is merged in
+                        let next_start =
+                            next_start.filter(|_| totals.parse_report.truncation.is_none());
+                        listening = listening
+                            && send_batches(&sender, totals.take_entries_before(next_start));
+                    })?;
+                    if !listening {
+                        return Ok(totals);
+                    }
+                }
+                send_batches(&sender, totals.take_entries());
+                Ok(totals)
+            });
+
+            let mut analysis = StreamingAnalysis::new(self);
+            if estimate_query_counts {
+                analysis.estimate_query_counts();
+            }
+            for entry in retained {
+                analysis.observe(entry);
+            }
+            for batch in receiver {
+                for entry in batch {
+                    analysis.observe(entry);
+                }
+            }
+
+            let parsed = parser.join().map_err(|_| PgLogstatsError::Unexpected {
+                message: "Log parser thread panicked".to_string(),
+                context: None,
+            })??;
+            analysis.finalize(parsed)
+        })
+    }
+
+    /// Compare slow queries in a target window against a baseline window.
+    ///
+    /// Both paths may be a single file or a directory of logs. The returned
+    /// report carries only findings; the configured analyzers are not run.
+    pub fn run_diff(
+        &self,
+        baseline: &Path,
+        target: &Path,
+        options: SlowQueryDiffOptions,
+    ) -> Result<Report> {
+        info!(
+            "Building slow-query diff findings from baseline {} and target {}",
+            baseline.display(),
+            target.display()
+        );
+
+        let baseline = self.parse_path(baseline)?;
+        let target = self.parse_path(target)?;
+        let mut parse_report = baseline.parse_report;
+        parse_report.merge(target.parse_report);
+
+        let baseline_entries = self.filter.apply(baseline.entries);
+        let target_entries = self.filter.apply(target.entries);
+
+        let baseline_events = normalize_log_entries(&baseline_entries, self.source_kind());
+        let target_events = normalize_log_entries(&target_entries, self.source_kind());
+        let baseline_executions = ProcessOrderCorrelator.correlate(&baseline_events);
+        let target_executions = ProcessOrderCorrelator.correlate(&target_events);
+
+        let mut report = Report::new(baseline_entries.len() + target_entries.len(), parse_report);
+        report.findings = Some(slow_query_diff_findings(
+            &baseline_executions,
+            &target_executions,
+            options,
+        ));
+
+        Ok(report)
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Entries of the file being parsed, appended to the parsed log a batch at a
+/// time, or kept until the whole file is parsed when it has to be merged in
+struct FileBatches {
+    source_file: Arc<PathBuf>,
+    /// Entries kept to merge whole; `None` while batches are appended
+    whole: Option<Vec<LogEntry>>,
+    started: bool,
+    /// Entries appended to the parsed log, and the newest of them
+    appended: usize,
+    newest: Option<DateTime<Utc>>,
+    /// Backwards timestamp jumps among the appended entries
+    order: ParseReport,
+    last_is_query: bool,
+}
+
+impl FileBatches {
+    /// Batches of the file at `path`; with `append`, they are appended as
+    /// they come unless the file overlaps the files before it
+    fn new(path: &Path, append: bool) -> Self {
+        Self {
+            source_file: Arc::new(path.to_path_buf()),
+            whole: (!append).then(Vec::new),
+            started: false,
+            appended: 0,
+            newest: None,
+            order: ParseReport::new(),
+            last_is_query: false,
+        }
+    }
+
+    /// Take the next entries of the file, in log order
+    fn push(
+        &mut self,
+        mut batch: Vec<LogEntry>,
+        parsed: &mut ParsedLog,
+        on_batch: &mut impl FnMut(&mut ParsedLog),
+    ) {
+        let Some(last) = batch.last() else {
+            return;
+        };
+        self.last_is_query = last.is_query();
+        if !self.started {
+            self.started = true;
+            if parsed.overlaps(&batch[0]) {
+                self.whole = Some(Vec::new());
+            }
+        }
+        if let Some(whole) = &mut self.whole {
+            whole.extend(batch);
+            return;
+        }
+
+        for entry in &mut batch {
+            entry.source_file = Some(self.source_file.clone());
+        }
+        let timestamps = || batch.iter().map(|entry| entry.timestamp);
+        self.order
+            .record_timestamp_order(self.newest.into_iter().chain(timestamps()));
+        self.newest = self.newest.max(timestamps().max());
+        self.appended += batch.len();
+        parsed.append_batch(batch);
+        on_batch(parsed);
+    }
+
+    /// The file's parse with `report`, holding the entries that were not
+    /// appended, and whether its last entry is a query
+    fn finish(&mut self, mut report: ParseReport) -> (ParsedLog, bool) {
+        let parsed = match self.whole.take() {
+            Some(entries) => ParsedLog::new(entries, report),
+            None => {
+                report.merge(std::mem::take(&mut self.order));
+                ParsedLog::new(Vec::new(), report)
+            }
+        };
+        (parsed, self.last_is_query)
+    }
+}
+
+/// Send `entries` to the analyzers in batches; `false` once they stopped
+/// listening
+fn send_batches(sender: &mpsc::SyncSender<Vec<LogEntry>>, entries: Vec<LogEntry>) -> bool {
+    let mut entries = entries.into_iter();
+    loop {
+        let batch: Vec<_> = entries.by_ref().take(STREAM_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            return true;
+        }
+        if sender.send(batch).is_err() {
+            return false;
+        }
+    }
+}
+
+/// Analyzer state for [`Pipeline::run_files_streaming`]
+struct StreamingAnalysis<'a> {
+    pipeline: &'a Pipeline,
+    filter: EntryFilter<'a>,
+    sampler: Option<StatementSampler>,
+    source_kind: EventSourceKind,
+    total_entries: usize,
+    queries: Option<QueryAnalyzerState>,
+    timing: Option<TimingAnalyzerState>,
+    /// Correlator and executions for `Analyzer::QueryFamilies`, with its limit
+    families: Option<(usize, StreamingCorrelator, Vec<QueryExecution>)>,
+    /// Events kept for `Analyzer::SessionTimeline`
+    session_events: Option<Vec<NormalizedEvent>>,
+    /// Entries kept for the pooler summary of pgbouncer input
+    pooler_entries: Vec<LogEntry>,
+}
+
+impl<'a> StreamingAnalysis<'a> {
+    fn new(pipeline: &'a Pipeline) -> Self {
+        let mut analysis = Self {
+            pipeline,
+            filter: EntryFilter::new(&pipeline.filter),
+            sampler: pipeline.sampling.map(StatementSampler::new),
+            source_kind: pipeline.source_kind(),
+            total_entries: 0,
+            queries: None,
+            timing: None,
+            families: None,
+            session_events: None,
+            pooler_entries: Vec::new(),
+        };
+        for analyzer in &pipeline.analyzers {
+            match analyzer {
+                Analyzer::Queries => {
+                    analysis.queries = Some(pipeline.query_analyzer.state());
+                }
+                Analyzer::Timing => {
+                    analysis.timing = Some(pipeline.timing_analyzer.state());
+                }
+                Analyzer::QueryFamilies { limit } => {
+                    analysis.families = Some((*limit, StreamingCorrelator::new(), Vec::new()));
+                }
+                Analyzer::SessionTimeline => {
+                    analysis.session_events = Some(Vec::new());
+                }
+            }
+        }
+        analysis
+    }
+
+    /// Estimate query counts from here on, as in low-memory mode
+    fn estimate_query_counts(&mut self) {
+        if let Some(queries) = &mut self.queries {
+            queries.estimate_query_counts();
+        }
+    }
+
+    fn observe(&mut self, entry: LogEntry) {
+        if !self.filter.keep(&entry) {
+            return;
+        }
+        if let Some(sampler) = &mut self.sampler {
+            if !sampler.keep(&entry) {
+                return;
+            }
+        }
+
+        let event = NormalizedEvent::from_log_entry(&entry, self.source_kind, self.total_entries);
+        self.total_entries += 1;
+        if let Some(queries) = &mut self.queries {
+            queries.observe_event(&event);
+        }
+        if let Some(timing) = &mut self.timing {
+            timing.observe_event(&event);
+        }
+        if let Some((_, correlator, executions)) = &mut self.families {
+            executions.extend(correlator.push(&event));
+        }
+        if let Some(session_events) = &mut self.session_events {
+            if is_session_event(&event) {
+                session_events.push(event);
+            }
+        }
+        if self.pipeline.parser_kind == ParserKind::Pgbouncer {
+            self.pooler_entries.push(entry);
+        }
+    }
+
+    fn finalize(self, parsed: ParsedLog) -> Result<Report> {
+        info!("Analyzed {} entries", self.total_entries);
+        let mut report = Report::new(self.total_entries, parsed.parse_report);
+
+        if self.pipeline.parser_kind == ParserKind::Pgbouncer {
+            report.pooler =
+                Some(PoolerAnalyzer::new().analyze(&self.pooler_entries, &parsed.pooler_stats)?);
+        }
+        if let Some(queries) = self.queries {
+            report.analysis = Some(queries.into_result());
+            report.slow_query_threshold = Some(self.pipeline.query_analyzer.slow_query_threshold());
+            report.query_sort = Some(self.pipeline.query_analyzer.query_sort());
+            report.hour_timezone = self.pipeline.query_analyzer.bucket_timezone();
+        }
+        if let Some(timing) = self.timing {
+            report.timing = Some(timing.into_analysis());
+            report.hour_timezone = self.pipeline.timing_analyzer.config().bucket_timezone;
+        }
+        if let Some((limit, correlator, mut executions)) = self.families {
+            executions.extend(correlator.finish());
+            report.findings = Some(query_family_findings(&executions, limit));
+        }
+        if let Some(session_events) = self.session_events {
+            report.session_timeline = Some(session_timeline(&session_events));
+        }
+        if let Some(sampler) = &self.sampler {
+            report.scale_to_sample(sampler.estimate());
+        }
+
+        Ok(report)
+    }
+}
+
+/// Stage of a [`Pipeline::run_files_with_progress`] run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunProgress<'a> {
+    /// The file at `index` of the ordered files is being parsed
+    Parsing { index: usize, path: &'a Path },
+    /// Parsed entries passed the memory limit; the rest is analyzed as it is
+    /// parsed
+    Streaming,
+    /// Every file is parsed and the entries are being analyzed
+    Analyzing,
+}
+
+/// Output of a pipeline run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Log entries analyzed after filtering
+    pub total_entries: usize,
+    /// Parse diagnostics for the input
+    pub parse_report: ParseReport,
+    /// Query statistics, when `Analyzer::Queries` ran
+    pub analysis: Option<AnalysisResult>,
+    /// Timing statistics, when `Analyzer::Timing` ran
+    pub timing: Option<TimingAnalysis>,
+    /// Investigation findings, when a findings analyzer ran
+    pub findings: Option<FindingSet>,
+    /// Connection pooler summary, for pgbouncer input
+    pub pooler: Option<PoolerSummary>,
+    /// Slow-query threshold in milliseconds, when `Analyzer::Queries` ran
+    pub slow_query_threshold: Option<f64>,
+    /// Ranking of the most frequent queries, when `Analyzer::Queries` ran
+    pub query_sort: Option<QuerySort>,
+    /// Timezone the hour-of-day stats were bucketed in; `None` for UTC
+    pub hour_timezone: Option<Tz>,
+    /// Changes against a baseline report, when one was given
+    pub comparison: Option<ReportComparison>,
+    /// Queries matched with a `pg_stat_statements` export, when one was given
+    pub pg_stat_statements: Option<PgStatStatementsComparison>,
+    /// Session steps, when `Analyzer::SessionTimeline` ran
+    pub session_timeline: Option<Vec<SessionEvent>>,
+}
+
+impl Report {
+    /// Create an empty report for the given input
+    pub fn new(total_entries: usize, parse_report: ParseReport) -> Self {
+        Self {
+            total_entries,
+            parse_report,
+            analysis: None,
+            timing: None,
+            findings: None,
+            pooler: None,
+            slow_query_threshold: None,
+            query_sort: None,
+            hour_timezone: None,
+            comparison: None,
+            pg_stat_statements: None,
+            session_timeline: None,
+        }
+    }
+
+    /// Scale the query counts and totals of the analyses up from a sample
+    pub fn scale_to_sample(&mut self, estimate: SampleEstimate) {
+        if let Some(analysis) = &mut self.analysis {
+            estimate.scale_analysis(analysis);
+        }
+        if let Some(timing) = &mut self.timing {
+            estimate.scale_timing(timing);
+        }
+    }
+
+    /// Build the JSON formatter used by `to_json`
+    pub fn json_formatter(&self) -> JsonFormatter {
+        JsonFormatter::new()
+            .with_pretty(true)
+            .with_metadata(
+                env!("CARGO_PKG_VERSION"),
+                self.parse_report
+                    .files
+                    .iter()
+                    .map(|file| file.file.clone())
+                    .collect(),
+                self.total_entries,
+            )
+            .with_parse_report(self.parse_report.clone())
+            .with_query_sort(self.query_sort.unwrap_or_default())
+            .with_hour_timezone(self.hour_timezone)
+            .with_slowest_changes(self.slowest_changes())
+    }
+
+    /// Rank changes of the slowest queries against the baseline, if any
+    fn slowest_changes(&self) -> Vec<SlowestRankChange> {
+        self.comparison
+            .as_ref()
+            .map_or_else(Vec::new, |comparison| comparison.slowest.clone())
+    }
+
+    /// Render the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        self.format_json(&self.json_formatter())
+    }
+
+    /// Render the report as JSON with a custom formatter; a session timeline
+    /// renders as an array of its events
+    pub fn format_json(&self, formatter: &JsonFormatter) -> Result<String> {
+        if let Some(timeline) = &self.session_timeline {
+            return formatter.format_session_timeline(timeline);
+        }
+        let mut sections = serde_json::Map::new();
+
+        let base = if self.analysis.is_none() && self.timing.is_none() {
+            let empty = FindingSet::new(Vec::new());
+            formatter.format_findings(self.findings.as_ref().unwrap_or(&empty))?
+        } else {
+            if let Some(findings) = &self.findings {
+                sections.insert("schema_version".to_string(), json!(findings.schema_version));
+                sections.insert("findings".to_string(), json!(findings.findings));
+            }
+
+            let analysis = self.analysis.clone().unwrap_or_default();
+            match &self.timing {
+                Some(timing) => formatter.format_with_timing(&analysis, timing)?,
+                None => formatter.format(&analysis)?,
+            }
+        };
+
+        if let Some(pooler) = &self.pooler {
+            sections.insert("pooler_summary".to_string(), json!(pooler));
+        }
+        if let Some(comparison) = &self.comparison {
+            sections.insert("comparison".to_string(), json!(comparison));
+        }
+        if let Some(pg_stat_statements) = &self.pg_stat_statements {
+            sections.insert("pg_stat_statements".to_string(), json!(pg_stat_statements));
+        }
+
+        if sections.is_empty() {
+            return Ok(base);
+        }
+
+        let mut root: serde_json::Value =
+            serde_json::from_str(&base).map_err(PgLogstatsError::Serialization)?;
+        if let Some(obj) = root.as_object_mut() {
+            obj.extend(sections);
+        }
+        formatter.render(&root)
+    }
+
+    /// Build the text formatter used by `to_text`
+    pub fn text_formatter(&self) -> TextFormatter {
+        let formatter = TextFormatter::new()
+            .with_query_sort(self.query_sort.unwrap_or_default())
+            .with_hour_timezone(self.hour_timezone)
+            .with_slowest_changes(self.slowest_changes());
+        match self.slow_query_threshold {
+            Some(threshold) => formatter.with_slow_query_threshold(threshold),
+            None => formatter,
+        }
+    }
+
+    /// Render the report as plain text
+    pub fn to_text(&self) -> Result<String> {
+        self.format_text(&self.text_formatter())
+    }
+
+    /// Render the report as text with a custom formatter; a session timeline
+    /// renders as the timeline alone
+    pub fn format_text(&self, formatter: &TextFormatter) -> Result<String> {
+        if let Some(timeline) = &self.session_timeline {
+            return formatter.format_session_timeline(timeline);
+        }
+        let mut sections = Vec::new();
+
+        if let Some(analysis) = &self.analysis {
+            sections.push(formatter.format_query_analysis(analysis)?);
+        }
+        if let Some(timing) = &self.timing {
+            let mut timing_text = formatter.format_timing_analysis(timing)?;
+            if let Some(analysis) = &self.analysis {
+                timing_text.push_str(&formatter.format_hourly_report(analysis, timing)?);
+            }
+            sections.push(timing_text);
+        }
+        if let Some(findings) = &self.findings {
+            sections.push(formatter.format_findings(findings)?);
+        }
+        if let Some(pooler) = &self.pooler {
+            sections.push(formatter.format_pooler_summary(pooler)?);
+        }
+        if let Some(comparison) = &self.comparison {
+            sections.push(formatter.format_comparison(comparison)?);
+        }
+        if let Some(pg_stat_statements) = &self.pg_stat_statements {
+            sections.push(formatter.format_pg_stat_statements(pg_stat_statements)?);
+        }
+        if let Some(run_stats) = formatter.run_stats() {
+            sections.push(formatter.format_run_stats(run_stats)?);
+        }
+
+        Ok(sections.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogLevel;
+
+    fn sample_lines() -> Vec<String> {
+        [
+            "2024-08-15 10:30:15.123 UTC [12345] alice@orders psql: LOG:  statement: SELECT * FROM orders WHERE id = 1",
+            "2024-08-15 10:30:15.200 UTC [12345] alice@orders psql: LOG:  duration: 20.000 ms",
+            "2024-08-15 10:31:15.123 UTC [12346] bob@billing app: LOG:  statement: SELECT * FROM invoices WHERE id = 2",
+            "2024-08-15 10:31:15.200 UTC [12346] bob@billing app: LOG:  duration: 40.000 ms",
+            "not a log line",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_query_id_filter_keeps_matching_statements_and_their_durations() {
+        let id = query_id("SELECT * FROM invoices WHERE id = ?");
+        let report = Pipeline::new()
+            .with_filters(LogFilter::new().with_query_id(id[..8].to_ascii_uppercase()))
+            .run_lines(&sample_lines())
+            .unwrap();
+
+        assert_eq!(report.total_entries, 2);
+        let analysis = report.analysis.unwrap();
+        assert_eq!(analysis.total_queries, 1);
+        assert_eq!(analysis.total_duration, 40.0);
+        assert_eq!(
+            analysis.most_frequent_queries,
+            vec![("SELECT * FROM invoices WHERE id = ?".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_parser_kind_from_str_selects_a_boxed_parser() {
+        let kind: ParserKind = "stderr".parse().unwrap();
+        assert_eq!(kind, ParserKind::Stderr);
+        assert_eq!("default".parse::<ParserKind>().unwrap(), ParserKind::Stderr);
+        assert_eq!("AWS-RDS".parse::<ParserKind>().unwrap(), ParserKind::AwsRds);
+
+        let input = sample_lines().join("\n");
+        let parsed = kind
+            .parser()
+            .parse_reader(&mut std::io::Cursor::new(input))
+            .unwrap();
+        assert_eq!(parsed.entries.len(), 4);
+        assert_eq!(parsed.parse_report.total_lines, 5);
+    }
+
+    #[test]
+    fn test_unknown_parser_kind_lists_supported_formats() {
+        let err = "csvlog".parse::<ParserKind>().unwrap_err();
+
+        match err {
+            PgLogstatsError::Configuration { message, field } => {
+                assert_eq!(field.as_deref(), Some("format"));
+                assert!(message.contains("'csvlog'"));
+                assert!(message.contains("auto, stderr, rds, pgbouncer, syslog"));
+            }
+            other => panic!("expected a configuration error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_file_decodes_lines_with_the_configured_charset() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let mut bytes = sample_lines()[..2].join("\r\n").into_bytes();
+        let literal = bytes.windows(3).position(|w| w == b"= 1").unwrap() + 2;
+        bytes.splice(literal..literal + 1, *b"'\xe9'");
+        std::fs::write(&path, bytes).unwrap();
+
+        let parsed = Pipeline::new().parse_file(&path).unwrap();
+        assert_eq!(parsed.parse_report.replaced_lines, 1);
+        assert_eq!(parsed.entries.len(), 2);
+        assert!(parsed.entries[0].message.ends_with("id = '\u{FFFD}'"));
+        assert_eq!(parsed.entries[1].duration, Some(20.0));
+
+        let parsed = Pipeline::new()
+            .with_charset(Charset::Latin1)
+            .with_sample_size(Some(1))
+            .parse_file(&path)
+            .unwrap();
+        assert_eq!(parsed.parse_report.replaced_lines, 0);
+        assert_eq!(parsed.parse_report.total_lines, 1);
+        assert!(parsed.entries[0].message.ends_with("id = '\u{e9}'"));
+    }
+
+    #[test]
+    fn test_unterminated_last_line_is_flagged_or_held_back_until_written() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let lines = sample_lines();
+        let (written, rest) = lines[2].split_at(lines[2].len() - 1);
+        std::fs::write(&path, format!("{}\n{}\n{}", lines[0], lines[1], written)).unwrap();
+
+        let parsed = Pipeline::new().parse_file(&path).unwrap();
+        assert_eq!(parsed.entries.len(), 3);
+        assert!(parsed.entries[2].message.ends_with("WHERE id ="));
+        assert_eq!(
+            parsed.parse_report.unterminated_lines,
+            [UnterminatedLine {
+                file: Some(path.display().to_string()),
+                line_number: 3,
+                held_back: false,
+                truncated_statement: true,
+            }]
+        );
+        assert!(parsed
+            .parse_report
+            .unterminated_line_warning()
+            .unwrap()
+            .ends_with("may be cut short"));
+
+        let following = Pipeline::new().with_hold_back_unterminated(true);
+        let parsed = following.parse_file(&path).unwrap();
+        assert_eq!(parsed.entries.len(), 2);
+        assert_eq!(parsed.parse_report.total_lines, 2);
+        assert!(parsed.parse_report.unterminated_lines[0].held_back);
+        assert_eq!(parsed.parse_report.unterminated_line_warning(), None);
+
+        // The writer finishes the line
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, format!("{}\n", rest).as_bytes()).unwrap();
+        let parsed = following.parse_file(&path).unwrap();
+        assert!(parsed.parse_report.unterminated_lines.is_empty());
+        // The statement may still get continuation lines, so it waits too
+        assert_eq!(parsed.entries.len(), 2);
+        assert!(parsed.parse_report.held_back_statement);
+        let parsed = Pipeline::new().parse_file(&path).unwrap();
+        assert_eq!(parsed.entries.len(), 3);
+        assert!(!parsed.parse_report.held_back_statement);
+
+        std::io::Write::write_all(
+            &mut file,
+            format!("    AND total > 0\n{}\n", lines[3]).as_bytes(),
+        )
+        .unwrap();
+        let parsed = following.parse_file(&path).unwrap();
+        assert_eq!(parsed.entries.len(), 4);
+        assert!(parsed.entries[2]
+            .message
+            .ends_with("WHERE id = 2 AND total > 0"));
+        assert!(!parsed.parse_report.held_back_statement);
+    }
+
+    #[test]
+    fn test_entries_name_the_file_they_were_parsed_from() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = dir.path().join("postgresql-1.log");
+        let second = dir.path().join("postgresql-2.log");
+        std::fs::write(&first, sample_lines().join("\n")).unwrap();
+        std::fs::write(
+            &second,
+            format!("not a log line\n{}", sample_lines()[0]).replace(" 10:", " 11:"),
+        )
+        .unwrap();
+
+        let parsed = Pipeline::new().parse_path(dir.path()).unwrap();
+        let files: Vec<_> = parsed
+            .entries
+            .iter()
+            .map(|entry| entry.source_file.as_deref().unwrap())
+            .collect();
+        assert_eq!(files, [&first, &first, &first, &first, &second]);
+        assert!(Arc::ptr_eq(
+            parsed.entries[0].source_file.as_ref().unwrap(),
+            parsed.entries[1].source_file.as_ref().unwrap()
+        ));
+        let sample = parsed.parse_report.skipped_samples.last().unwrap();
+        assert_eq!(sample.file, Some(second.display().to_string()));
+
+        let (entries, report) = TextLogParser::new().parse_file(&second).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(report.skipped_samples[0].file, sample.file);
+        let json = serde_json::to_value(&entries[0]).unwrap();
+        assert_eq!(json["source_file"], second.display().to_string());
+    }
+
+    #[test]
+    fn test_default_pipeline_runs_query_and_timing_analyzers() {
+        let report = Pipeline::new().run_lines(&sample_lines()).unwrap();
+
+        assert_eq!(report.total_entries, 4);
+        assert_eq!(report.parse_report.total_lines, 5);
+        assert_eq!(report.parse_report.unparsed_lines(), 1);
+        assert_eq!(report.analysis.as_ref().unwrap().total_queries, 2);
+        assert_eq!(report.timing.as_ref().unwrap().total_queries, 2);
+        assert!(report.findings.is_none());
+    }
+
+    #[test]
+    fn test_pipeline_filters_entries_before_analysis() {
+        let report = Pipeline::new()
+            .with_filters(LogFilter::new().with_user("bob"))
+            .with_analyzers([Analyzer::QueryFamilies { limit: 10 }])
+            .run_lines(&sample_lines())
+            .unwrap();
+
+        assert_eq!(report.total_entries, 2);
+        let findings = report.findings.unwrap();
+        assert_eq!(findings.findings.len(), 1);
+        assert_eq!(
+            findings.findings[0]
+                .query_family
+                .as_ref()
+                .unwrap()
+                .normalized_sql,
+            "SELECT * FROM invoices WHERE id = ?"
+        );
+    }
+
+    #[test]
+    fn test_log_filter_time_range_is_half_open() {
+        let entries = Pipeline::new().parse_lines(&sample_lines()).entries;
+        let until = entries[2].timestamp;
+        let filter = LogFilter::new().with_time_range(None, Some(until));
+
+        assert!(filter.matches(&entries[0]));
+        assert!(!filter.matches(&entries[2]));
+        assert!(LogFilter::new().is_empty());
+        assert!(!filter.is_empty());
+    }
+
+    fn entry_at(timestamp: &str) -> LogEntry {
+        let timestamp = DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc);
+        LogEntry::builder(timestamp, "1234", LogLevel::Log, "statement: SELECT 1")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_time_window_spanning_midnight() {
+        let window: TimeWindow = "23:00-01:00".parse().unwrap();
+        let at = |time: &str| NaiveTime::parse_from_str(time, "%H:%M:%S").unwrap();
+
+        assert!(window.contains(at("23:00:00")));
+        assert!(window.contains(at("23:59:59")));
+        assert!(window.contains(at("00:00:00")));
+        assert!(window.contains(at("00:59:59")));
+        assert!(!window.contains(at("01:00:00")));
+        assert!(!window.contains(at("22:59:59")));
+        assert!(!window.contains(at("12:00:00")));
+        assert_eq!(window.to_string(), "23:00-01:00");
+    }
+
+    #[test]
+    fn test_time_window_rejects_malformed_ranges() {
+        assert!("02:00".parse::<TimeWindow>().is_err());
+        assert!("02:00-25:00".parse::<TimeWindow>().is_err());
+        assert!("02:00-02:00".parse::<TimeWindow>().is_err());
+        assert!("02:00:30-03:00".parse::<TimeWindow>().is_ok());
+    }
+
+    #[test]
+    fn test_log_filter_exclude_window_spanning_midnight() {
+        let filter = LogFilter::new().with_exclude_window("23:30-00:30".parse().unwrap());
+
+        assert!(!filter.is_empty());
+        assert!(!filter.matches(&entry_at("2024-01-15T23:45:00Z")));
+        assert!(!filter.matches(&entry_at("2024-01-16T00:15:00Z")));
+        assert!(filter.matches(&entry_at("2024-01-16T00:30:00Z")));
+        assert!(filter.matches(&entry_at("2024-01-15T12:00:00Z")));
+    }
+
+    #[test]
+    fn test_log_filter_include_windows_match_any() {
+        let filter = LogFilter::new()
+            .with_include_window("22:00-02:00".parse().unwrap())
+            .with_include_window("09:00-10:00".parse().unwrap());
+
+        assert!(filter.matches(&entry_at("2024-01-15T01:00:00Z")));
+        assert!(filter.matches(&entry_at("2024-01-15T09:30:00Z")));
+        assert!(!filter.matches(&entry_at("2024-01-15T12:00:00Z")));
+    }
+
+    #[test]
+    fn test_log_filter_exclude_wins_over_include() {
+        let filter = LogFilter::new()
+            .with_include_window("00:00-06:00".parse().unwrap())
+            .with_exclude_time("^2024-01-15 02:".parse().unwrap());
+
+        assert!(filter.matches(&entry_at("2024-01-15T01:59:59Z")));
+        assert!(!filter.matches(&entry_at("2024-01-15T02:10:00Z")));
+        assert!(filter.matches(&entry_at("2024-01-16T02:10:00Z")));
+    }
+
+    #[test]
+    fn test_log_filter_time_patterns_compose_with_user_filter() {
+        let filter = LogFilter::new()
+            .with_user("bob")
+            .with_include_time(r"2024-01-15 10:00:0\d".parse().unwrap());
+        let mut entry = entry_at("2024-01-15T10:00:05Z");
+
+        assert!(!filter.matches(&entry));
+        entry.user = Some("bob".to_string());
+        assert!(filter.matches(&entry));
+        entry.timestamp = entry_at("2024-01-15T10:00:15Z").timestamp;
+        assert!(!filter.matches(&entry));
+        assert!("[".parse::<TimePattern>().is_err());
+    }
+
+    #[test]
+    fn test_streaming_run_matches_batch_run() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = dir.path().join("postgresql-1.log");
+        let second = dir.path().join("postgresql-2.log");
+        std::fs::write(
+            &first,
+            [
+                "2024-08-15 10:30:15.123 UTC [12345] alice@orders psql: LOG:  statement: SELECT * FROM orders WHERE id = 1",
+                "2024-08-15 10:30:15.150 UTC [12346] bob@billing app: LOG:  statement: SELECT * FROM invoices WHERE id = 2",
+                "2024-08-15 10:30:15.180 UTC [12346] bob@billing app: LOG:  duration: 1500.000 ms",
+                "2024-08-15 10:30:15.200 UTC [12345] alice@orders psql: LOG:  duration: 20.000 ms",
+                "2024-08-15 10:30:16.000 UTC [12347] bob@billing app: ERROR:  canceling statement due to statement timeout",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            &second,
+            [
+                "2024-08-15 11:00:00.000 UTC [12348] [unknown]@[unknown] [unknown]: LOG:  connection received: host=10.0.0.1 port=5432",
+                "2024-08-15 11:00:01.000 UTC [12348] alice@orders psql: LOG:  statement: SELECT * FROM orders WHERE id = 3",
+                "2024-08-15 11:00:01.100 UTC [12349] alice@orders psql: LOG:  statement: UPDATE orders SET paid = true",
+                "2024-08-15 11:00:01.300 UTC [12348] alice@orders psql: LOG:  duration: 30.000 ms",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let pipeline = Pipeline::new().with_analyzers([
+            Analyzer::Queries,
+            Analyzer::Timing,
+            Analyzer::QueryFamilies { limit: 5 },
+        ]);
+        let paths = [first, second];
+        let batch = pipeline.run_files(&paths).unwrap();
+        let streamed = pipeline.run_files_streaming(&paths).unwrap();
+
+        assert_eq!(streamed.total_entries, 9);
+        assert_eq!(streamed.analysis.as_ref().unwrap().total_queries, 4);
+        assert_eq!(
+            serde_json::to_value(&streamed).unwrap(),
+            serde_json::to_value(&batch).unwrap()
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_reader_run_matches_lines_run() {
+        let lines = sample_lines();
+        let input = lines.join("\n");
+        let pipeline = Pipeline::new().with_analyzers([
+            Analyzer::Queries,
+            Analyzer::Timing,
+            Analyzer::SessionTimeline,
+        ]);
+
+        let from_lines = pipeline.run_lines(&lines).unwrap();
+        let from_reader = pipeline.run_async_reader(input.as_bytes()).await.unwrap();
+
+        assert_eq!(from_reader.analysis.as_ref().unwrap().total_queries, 2);
+        assert_eq!(
+            serde_json::to_value(&from_reader).unwrap(),
+            serde_json::to_value(&from_lines).unwrap()
+        );
+        assert!(Pipeline::new()
+            .with_parser(ParserKind::Syslog)
+            .parse_async_reader(input.as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn test_streaming_run_applies_query_id_filter() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        std::fs::write(&path, sample_lines().join("\n")).unwrap();
+        let id = query_id("SELECT * FROM orders WHERE id = ?");
+
+        let report = Pipeline::new()
+            .with_filters(LogFilter::new().with_query_id(&id[..8]))
+            .run_files_streaming(&[&path])
+            .unwrap();
+
+        assert_eq!(report.total_entries, 2);
+        assert_eq!(report.parse_report.total_lines, 5);
+        assert_eq!(report.analysis.unwrap().total_duration, 20.0);
+    }
+
+    #[test]
+    fn test_max_entries_counts_entries_across_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = dir.path().join("postgresql-1.log");
+        let second = dir.path().join("postgresql-2.log");
+        std::fs::write(&first, sample_lines().join("\n")).unwrap();
+        std::fs::write(&second, sample_lines().join("\n").replace(" 10:", " 11:")).unwrap();
+
+        let pipeline = Pipeline::new().with_max_entries(Some(6));
+        let report = pipeline.run_files(&[&first, &second]).unwrap();
+
+        assert_eq!(report.total_entries, 6);
+        let truncation = report.parse_report.truncation.as_ref().unwrap();
+        assert_eq!(truncation.file, Some(second.display().to_string()));
+        assert_eq!(truncation.line_number, 2);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["metadata"]["truncated"], true);
+
+        // A limit reached exactly at the end of the input truncates nothing.
+        let report = Pipeline::new()
+            .with_max_entries(Some(8))
+            .run_files(&[&first, &second])
+            .unwrap();
+        assert!(report.parse_report.truncation.is_none());
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert!(json["metadata"].get("truncated").is_none());
+    }
+
+    #[test]
+    fn test_memory_limit_streams_the_remaining_files_with_estimated_counts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths: Vec<_> = (0..3)
+            .map(|file| {
+                let path = dir.path().join(format!("postgresql-{file}.log"));
+                let lines: Vec<_> = (0..2000)
+                    .flat_map(|i| {
+                        let pid = file * 10_000 + i;
+                        [
+                            format!("2024-08-15 10:{:02}:{:02}.000 UTC [{pid}] alice@orders psql: LOG:  statement: SELECT * FROM orders WHERE id = {i}", file * 10 + i / 600, i % 60),
+                            format!("2024-08-15 10:{:02}:{:02}.500 UTC [{pid}] alice@orders psql: LOG:  duration: 1.000 ms", file * 10 + i / 600, i % 60),
+                        ]
+                    })
+                    .collect();
+                std::fs::write(&path, lines.join("\n")).unwrap();
+                path
+            })
+            .collect();
+
+        let pipeline = Pipeline::new();
+        let first_file = pipeline.parse_file(&paths[0]).unwrap();
+        assert!(first_file.approximate_size() > 1024 * 1024);
+
+        let limited = pipeline.with_max_memory_mb(Some(1));
+        assert!(limited.exceeds_memory_limit(&first_file));
+        let report = limited.run_files(&paths).unwrap();
+        let memory_limit = report.parse_report.memory_limit.unwrap();
+        assert_eq!(memory_limit.limit_mb, 1);
+        assert_eq!(memory_limit.entries, 4000);
+
+        let unlimited = Pipeline::new().run_files(&paths).unwrap();
+        let analysis = report.analysis.as_ref().unwrap();
+        assert_eq!(analysis.total_queries, 6000);
+        assert_eq!(report.total_entries, unlimited.total_entries);
+        assert!(analysis.frequency_estimate.is_some());
+        assert!(unlimited.analysis.unwrap().frequency_estimate.is_none());
+    }
+
+    #[test]
+    fn test_memory_limit_switches_to_streaming_inside_a_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let lines: Vec<_> = (0..10_000)
+            .flat_map(|i| {
+                let (minute, second) = (i / 600, i / 10 % 60);
+                [
+                    format!("2024-08-15 10:{minute:02}:{second:02}.000 UTC [{i}] alice@orders psql: LOG:  statement: SELECT * FROM orders WHERE id = {i}"),
+                    format!("2024-08-15 10:{minute:02}:{second:02}.500 UTC [{i}] alice@orders psql: LOG:  duration: 1.000 ms"),
+                ]
+            })
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let mut stages = Vec::new();
+        let report = Pipeline::new()
+            .with_max_memory_mb(Some(1))
+            .run_files_with_progress(&[&path], |progress| {
+                stages.push(match progress {
+                    RunProgress::Parsing { index, .. } => format!("parsing {index}"),
+                    stage => format!("{stage:?}"),
+                })
+            })
+            .unwrap();
+        assert_eq!(stages, ["parsing 0", "Streaming"]);
+
+        // The limit was passed a few batches into the file, not at its end
+        let memory_limit = report.parse_report.memory_limit.unwrap();
+        assert!(memory_limit.entries < 20_000, "{}", memory_limit.entries);
+        assert!(memory_limit.entries >= STREAM_BATCH_SIZE / 2);
+
+        let unlimited = Pipeline::new().run_files(&[&path]).unwrap();
+        assert_eq!(report.total_entries, unlimited.total_entries);
+        assert_eq!(report.parse_report.parsed_entries, 20_000);
+        assert_eq!(report.analysis.as_ref().unwrap().total_queries, 10_000);
+        assert!(report.analysis.unwrap().frequency_estimate.is_some());
+
+        // Streaming sends the file's batches as they are parsed, in order
+        let streamed = Pipeline::new().run_files_streaming(&[&path]).unwrap();
+        assert_eq!(
+            serde_json::to_value(&streamed).unwrap(),
+            serde_json::to_value(&unlimited).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sampling_keeps_statements_with_their_durations_and_scales_counts() {
+        // Two sessions interleave their statement and duration lines
+        let mut lines: Vec<_> = (0..200)
+            .flat_map(|i| {
+                let (minute, second) = (i / 60, i % 60);
+                let pid = 100 + i % 2;
+                [
+                    format!("2024-08-15 10:{minute:02}:{second:02}.000 UTC [{pid}] alice@orders psql: LOG:  statement: SELECT * FROM orders WHERE id = {i}"),
+                    format!("2024-08-15 10:{minute:02}:{second:02}.500 UTC [{pid}] alice@orders psql: LOG:  duration: 10.000 ms"),
+                ]
+            })
+            .collect();
+        for i in (0..lines.len()).step_by(4) {
+            lines.swap(i + 1, i + 2);
+        }
+        lines.push("2024-08-15 10:05:00.000 UTC [102] alice@orders psql: ERROR:  relation \"missing\" does not exist".to_string());
+
+        let sampling = StatementSampling::new(0.25, 42).unwrap();
+        let pipeline = Pipeline::new().with_sampling(Some(sampling));
+        let report = pipeline.run_lines(&lines).unwrap();
+        let analysis = report.analysis.unwrap();
+        let estimate = analysis.sample_estimate.unwrap();
+        assert_eq!(estimate.statements_seen, 200);
+        assert!((25..=75).contains(&estimate.statements_kept));
+        assert_eq!(
+            report.total_entries as u64,
+            estimate.statements_kept * 2 + 1
+        );
+
+        // A kept statement without its duration would pull the average down
+        assert_eq!(analysis.average_duration, 10.0);
+        assert_eq!(analysis.total_queries, estimate.statements_kept * 4);
+        assert_eq!(
+            analysis.total_duration,
+            estimate.statements_kept as f64 * 10.0 * 4.0
+        );
+        assert_eq!(analysis.error_count, 1);
+        let timing = report.timing.unwrap();
+        assert_eq!(timing.total_queries, analysis.total_queries);
+
+        // The seed picks the same statements again, also when streaming
+        let again = pipeline.run_lines(&lines).unwrap().analysis.unwrap();
+        assert_eq!(again.sample_estimate, Some(estimate));
+        assert_eq!(again.most_frequent_queries, analysis.most_frequent_queries);
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let streamed = pipeline.run_files_streaming(&[path]).unwrap();
+        assert_eq!(streamed.analysis.unwrap().sample_estimate, Some(estimate));
+
+        let entries = pipeline.parse_lines(&lines).entries;
+        let kept = |seed| {
+            let mut sampler = StatementSampler::new(StatementSampling::new(0.25, seed).unwrap());
+            sampler
+                .apply(entries.clone())
+                .into_iter()
+                .map(|entry| entry.timestamp)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kept(42), kept(42));
+        assert_ne!(kept(42), kept(43));
+    }
+
+    #[test]
+    fn test_merge_file_interleaves_overlapping_entries_and_drops_duplicates() {
+        let mut parser = Pipeline::new().parser_with_limit(None);
+        let older = parser.parse_log_lines(&sample_lines());
+        let newer = parser.parse_log_lines(
+            &[
+                "2024-08-15 10:31:00.000 UTC [12347] carol@orders psql: LOG:  statement: SELECT 1",
+                "2024-08-15 10:31:15.123 UTC [12346] bob@billing app: LOG:  statement: SELECT * FROM invoices WHERE id = 2",
+                "2024-08-15 10:32:00.000 UTC [12347] carol@orders psql: LOG:  statement: SELECT 2",
+            ]
+            .map(String::from),
+        );
+
+        let mut parsed = ParsedLog::default();
+        parsed.merge_file(older, "postgresql.log.1");
+        parsed.merge_file(newer, "postgresql.log");
+
+        assert_eq!(parsed.parse_report.duplicate_entries, 1);
+        assert_eq!(parsed.parse_report.overlapping_files.len(), 1);
+        let overlap = &parsed.parse_report.overlapping_files[0];
+        assert_eq!(overlap.earlier, "postgresql.log.1");
+        assert_eq!(overlap.later, "postgresql.log");
+        let pids: Vec<_> = parsed
+            .entries
+            .iter()
+            .map(|entry| entry.process_id.as_str())
+            .collect();
+        assert_eq!(pids, ["12345", "12345", "12347", "12346", "12346", "12347"]);
+        assert!(parsed
+            .entries
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+    }
+
+    #[test]
+    fn test_entries_sharing_a_timestamp_keep_read_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let lines: Vec<_> = (0..500)
+            .flat_map(|i| {
+                let prefix = format!(
+                    "2024-08-15 10:30:15.123 UTC [{}] app@orders psql: LOG:  ",
+                    4000 + i % 7
+                );
+                [
+                    format!("{}statement: SELECT {}", prefix, i),
+                    format!("{}duration: {}.000 ms", prefix, i),
+                ]
+            })
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let pipeline = Pipeline::new().with_sort_entries(true);
+        let mut parsed = pipeline.parse_path(&path).unwrap();
+        assert_eq!(parsed.entries.len(), 1000);
+        assert!(parsed
+            .entries
+            .iter()
+            .enumerate()
+            .all(|(index, entry)| entry.seq == index as u64));
+
+        let read_order: Vec<_> = parsed.entries.iter().map(|entry| entry.seq).collect();
+        parsed.entries.reverse();
+        parsed.sort_by_timestamp();
+        let sorted: Vec<_> = parsed.entries.iter().map(|entry| entry.seq).collect();
+        assert_eq!(sorted, read_order);
+
+        let events = crate::normalize_log_entries(&parsed.entries, EventSourceKind::Stderr);
+        let expected: Vec<_> = (0..500)
+            .map(|i| (format!("SELECT {}", i), Some(i as f64)))
+            .collect();
+        let timeline: Vec<_> = crate::session_timeline(&events)
+            .into_iter()
+            .map(|step| (step.message, step.duration_ms))
+            .collect();
+        assert_eq!(timeline, expected);
+        let executions: Vec<_> = crate::correlate_query_executions(&events)
+            .into_iter()
+            .map(|execution| (execution.statement, execution.duration_ms))
+            .collect();
+        assert_eq!(executions, expected);
+
+        let report = pipeline.run_parsed(parsed).unwrap();
+        let analysis = report.analysis.as_ref().unwrap();
+        assert_eq!(analysis.total_queries, 500);
+        assert_eq!(analysis.total_duration, (0..500).sum::<u32>() as f64);
+        assert_eq!(report.timing.as_ref().unwrap().total_queries, 500);
+    }
+
+    #[test]
+    fn test_report_json_includes_findings_alongside_analysis() {
+        let report = Pipeline::new()
+            .with_analyzers([Analyzer::Queries, Analyzer::QueryFamilies { limit: 1 }])
+            .run_lines(&sample_lines())
+            .unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["summary"]["total_queries"], 2);
+        assert_eq!(json["findings"].as_array().unwrap().len(), 1);
+        assert_eq!(json["metadata"]["parse_report"]["total_lines"], 5);
+
+        let text = report.to_text().unwrap();
+        assert!(text.contains("Findings"));
+    }
+}
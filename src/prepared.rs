@@ -0,0 +1,282 @@
+//! Prepared statements of the extended query protocol
+//!
+//! With durations logged, each phase of the extended protocol gets its own
+//! line: `duration: 0.05 ms  parse S_1: SELECT ...`, then `bind S_1: ...` and
+//! `execute S_1: ...`. Statement names belong to a session, so the SQL given
+//! to each name at parse time is kept per session, or per backend PID
+//! without a `%c` session id in the prefix, until the session
+//! disconnects, and executes are counted under that SQL.
+
+use crate::events::{EventKind, NormalizedEvent};
+use crate::parsers::parse_duration_ms;
+use crate::ConnectionEventKind;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Name PostgreSQL logs for the unnamed prepared statement
+pub const UNNAMED_STATEMENT: &str = "<unnamed>";
+
+/// Phase of the extended query protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreparePhase {
+    Parse,
+    Bind,
+    Execute,
+}
+
+impl fmt::Display for PreparePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Parse => "parse",
+            Self::Bind => "bind",
+            Self::Execute => "execute",
+        })
+    }
+}
+
+/// A `parse`, `bind`, or `execute` message
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseMessage<'a> {
+    pub phase: PreparePhase,
+    /// Prepared statement name, without the portal of `bind` and `execute`
+    pub statement: &'a str,
+    pub duration_ms: Option<f64>,
+    pub sql: &'a str,
+}
+
+impl<'a> PhaseMessage<'a> {
+    /// Read a message such as `duration: 0.12 ms  bind S_1/C_1: SELECT 1`
+    /// or `execute <unnamed>: SELECT 1`
+    pub fn parse(message: &'a str) -> Option<Self> {
+        static REGEX: OnceLock<Regex> = OnceLock::new();
+        let regex = REGEX.get_or_init(|| {
+            Regex::new(
+                r"^(?:duration:\s*([\d.]+\s*(?:ms|s|min))\s+)?(parse|bind|execute)\s+([^:]+):\s*(.*)$",
+            )
+            .unwrap()
+        });
+        let captures = regex.captures(message)?;
+        let phase = match &captures[2] {
+            "parse" => PreparePhase::Parse,
+            "bind" => PreparePhase::Bind,
+            _ => PreparePhase::Execute,
+        };
+        Some(Self {
+            phase,
+            statement: statement_name(captures.get(3)?.as_str()),
+            duration_ms: captures
+                .get(1)
+                .and_then(|duration| parse_duration_ms(duration.as_str())),
+            sql: captures.get(4)?.as_str(),
+        })
+    }
+}
+
+/// Prepared statement name of the `S_1/C_1` or `fetch from S_1/C_1` that
+/// follows a phase
+pub fn statement_name(target: &str) -> &str {
+    let target = target.trim();
+    let target = target.strip_prefix("fetch from ").unwrap_or(target);
+    target.split('/').next().unwrap_or(target)
+}
+
+/// Count and time of one phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseStats {
+    pub count: u64,
+    pub total_ms: f64,
+}
+
+impl PhaseStats {
+    fn add(&mut self, duration_ms: Option<f64>) {
+        self.count += 1;
+        self.total_ms += duration_ms.unwrap_or(0.0);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.count += other.count;
+        self.total_ms += other.total_ms;
+    }
+}
+
+/// Extended protocol activity of a log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreparedStatementStats {
+    pub parse: PhaseStats,
+    pub bind: PhaseStats,
+    pub execute: PhaseStats,
+    /// Executes of the unnamed statement
+    pub unnamed_executes: u64,
+    /// Parses of a name the session had already prepared
+    pub reprepares: u64,
+    /// Executes per normalized query, taken from the parse of the statement
+    /// or, when the parse was not logged, from the execute line
+    pub executes_by_query: BTreeMap<String, u64>,
+}
+
+impl PreparedStatementStats {
+    /// Add the activity of `other`
+    pub fn merge(&mut self, other: Self) {
+        self.parse.merge(other.parse);
+        self.bind.merge(other.bind);
+        self.execute.merge(other.execute);
+        self.unnamed_executes += other.unnamed_executes;
+        self.reprepares += other.reprepares;
+        for (query, count) in other.executes_by_query {
+            *self.executes_by_query.entry(query).or_insert(0) += count;
+        }
+    }
+
+    /// Time of the three phases together
+    pub fn total_ms(&self) -> f64 {
+        self.parse.total_ms + self.bind.total_ms + self.execute.total_ms
+    }
+
+    /// Stats of `phase`
+    pub fn phase(&self, phase: PreparePhase) -> &PhaseStats {
+        match phase {
+            PreparePhase::Parse => &self.parse,
+            PreparePhase::Bind => &self.bind,
+            PreparePhase::Execute => &self.execute,
+        }
+    }
+
+    /// Executed queries with their execute counts, most executed first
+    pub fn most_executed(&self) -> Vec<(&str, u64)> {
+        let mut queries: Vec<_> = self
+            .executes_by_query
+            .iter()
+            .map(|(query, count)| (query.as_str(), *count))
+            .collect();
+        queries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        queries
+    }
+
+    /// Keep only the `limit` most executed queries in `executes_by_query`
+    pub fn keep_most_executed(&mut self, limit: usize) {
+        let kept: BTreeMap<_, _> = self
+            .most_executed()
+            .into_iter()
+            .take(limit)
+            .map(|(query, count)| (query.to_string(), count))
+            .collect();
+        self.executes_by_query = kept;
+    }
+
+    /// Whether the log had no extended protocol messages
+    pub fn is_empty(&self) -> bool {
+        self.parse.count == 0 && self.bind.count == 0 && self.execute.count == 0
+    }
+}
+
+/// Normalized SQL of each prepared statement name, per session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreparedStatementTracker {
+    sessions: HashMap<String, HashMap<String, String>>,
+}
+
+impl PreparedStatementTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count `event` in `stats` when it is a phase of the extended protocol,
+    /// and forget the statements of a session when it disconnects
+    pub fn record(&mut self, event: &NormalizedEvent, stats: &mut PreparedStatementStats) {
+        let session_key = event.session.key();
+        match &event.kind {
+            EventKind::Statement(statement) => {
+                let Some(name) = &statement.prepared_statement else {
+                    return;
+                };
+                stats.execute.add(statement.duration_ms);
+                if name == UNNAMED_STATEMENT {
+                    stats.unnamed_executes += 1;
+                }
+                let query = self
+                    .sessions
+                    .get(session_key)
+                    .and_then(|statements| statements.get(name))
+                    .cloned()
+                    .or_else(|| event.normalized_query())
+                    .unwrap_or_else(|| crate::normalize(&statement.statement).unwrap_or_default());
+                *stats.executes_by_query.entry(query).or_insert(0) += 1;
+            }
+            EventKind::Log {
+                message,
+                connection,
+                ..
+            } => {
+                if *connection == Some(ConnectionEventKind::Disconnection) {
+                    self.sessions.remove(session_key);
+                    return;
+                }
+                let Some(phase) = PhaseMessage::parse(message) else {
+                    return;
+                };
+                match phase.phase {
+                    PreparePhase::Parse => {
+                        stats.parse.add(phase.duration_ms);
+                        let previous = self
+                            .sessions
+                            .entry(session_key.to_string())
+                            .or_default()
+                            .insert(
+                                phase.statement.to_string(),
+                                crate::normalize(phase.sql).unwrap_or_default(),
+                            );
+                        if previous.is_some() && phase.statement != UNNAMED_STATEMENT {
+                            stats.reprepares += 1;
+                        }
+                    }
+                    PreparePhase::Bind => stats.bind.add(phase.duration_ms),
+                    // Executes are statement entries
+                    PreparePhase::Execute => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Take the sessions of `other`, which saw the entries following the
+    /// ones seen here
+    pub fn merge(&mut self, other: Self) {
+        for (process_id, statements) in other.sessions {
+            self.sessions
+                .entry(process_id)
+                .or_default()
+                .extend(statements);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_three_phases() {
+        let parse = PhaseMessage::parse("duration: 0.080 ms  parse S_1: SELECT 1").unwrap();
+        assert_eq!(parse.phase, PreparePhase::Parse);
+        assert_eq!(parse.statement, "S_1");
+        assert_eq!(parse.duration_ms, Some(0.08));
+        assert_eq!(parse.sql, "SELECT 1");
+
+        let bind = PhaseMessage::parse("duration: 1.5 s  bind <unnamed>/C_2: SELECT 2").unwrap();
+        assert_eq!(bind.phase, PreparePhase::Bind);
+        assert_eq!(bind.statement, UNNAMED_STATEMENT);
+        assert_eq!(bind.duration_ms, Some(1500.0));
+
+        let execute = PhaseMessage::parse("execute fetch from S_3/C_1: SELECT 3").unwrap();
+        assert_eq!(execute.phase, PreparePhase::Execute);
+        assert_eq!(execute.statement, "S_3");
+        assert_eq!(execute.duration_ms, None);
+
+        assert!(PhaseMessage::parse("statement: SELECT 1").is_none());
+        assert!(PhaseMessage::parse("duration: 1.0 ms").is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! Streaming replication and recovery events of a standby
+//!
+//! A standby logs fixed messages when its WAL receiver starts or stops
+//! streaming, when it cannot reach the primary, when it cancels queries that
+//! conflict with WAL replay, and at each restart point.
+//! [`ReplicationEventKind::from_message`] recognizes them so the analyzer can
+//! count them, list the changes between streaming and not streaming, and find
+//! the longest stretch without streaming.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Category of a replication or recovery message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicationEventKind {
+    /// `started streaming WAL from primary`
+    StreamingStarted,
+    /// The WAL receiver lost or ended its connection to the primary
+    StreamingStopped,
+    /// `could not connect to the primary server`
+    ConnectionFailure,
+    /// A query canceled or a session terminated to replay WAL
+    RecoveryConflict,
+    /// `recovery restart point at ...`
+    RestartPoint,
+}
+
+/// Message fragments of each category
+const EVENT_MESSAGES: &[(&str, ReplicationEventKind)] = &[
+    (
+        "started streaming WAL from primary",
+        ReplicationEventKind::StreamingStarted,
+    ),
+    (
+        "replication terminated by primary server",
+        ReplicationEventKind::StreamingStopped,
+    ),
+    (
+        "could not receive data from WAL stream",
+        ReplicationEventKind::StreamingStopped,
+    ),
+    (
+        "terminating walreceiver",
+        ReplicationEventKind::StreamingStopped,
+    ),
+    (
+        "could not connect to the primary server",
+        ReplicationEventKind::ConnectionFailure,
+    ),
+    (
+        "due to conflict with recovery",
+        ReplicationEventKind::RecoveryConflict,
+    ),
+    (
+        "recovery restart point at",
+        ReplicationEventKind::RestartPoint,
+    ),
+];
+
+impl ReplicationEventKind {
+    /// Every category, in report order
+    pub const ALL: [ReplicationEventKind; 5] = [
+        ReplicationEventKind::StreamingStarted,
+        ReplicationEventKind::StreamingStopped,
+        ReplicationEventKind::ConnectionFailure,
+        ReplicationEventKind::RecoveryConflict,
+        ReplicationEventKind::RestartPoint,
+    ];
+
+    /// Category of a log or error message; `None` for other messages
+    pub fn from_message(message: &str) -> Option<Self> {
+        EVENT_MESSAGES
+            .iter()
+            .find(|(fragment, _)| message.contains(fragment))
+            .map(|(_, kind)| *kind)
+    }
+
+    /// Snake-case name as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplicationEventKind::StreamingStarted => "streaming_started",
+            ReplicationEventKind::StreamingStopped => "streaming_stopped",
+            ReplicationEventKind::ConnectionFailure => "connection_failure",
+            ReplicationEventKind::RecoveryConflict => "recovery_conflict",
+            ReplicationEventKind::RestartPoint => "restart_point",
+        }
+    }
+
+    /// Whether streaming was running after an event of this category;
+    /// `None` when the category says nothing about it
+    fn streaming(&self) -> Option<bool> {
+        match self {
+            ReplicationEventKind::StreamingStarted => Some(true),
+            ReplicationEventKind::StreamingStopped | ReplicationEventKind::ConnectionFailure => {
+                Some(false)
+            }
+            ReplicationEventKind::RecoveryConflict | ReplicationEventKind::RestartPoint => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ReplicationEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// A change between streaming and not streaming
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationTransition {
+    pub timestamp: DateTime<Utc>,
+    /// The event that caused the change
+    pub kind: ReplicationEventKind,
+    /// Whether the standby was streaming afterwards
+    pub streaming: bool,
+}
+
+/// A stretch without streaming
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationGap {
+    pub start: DateTime<Utc>,
+    /// When streaming resumed, or the last replication event when it had not
+    pub end: DateTime<Utc>,
+    pub duration_seconds: f64,
+    /// Whether streaming had not resumed by the end of the log
+    pub ongoing: bool,
+}
+
+/// Replication and recovery events of a log
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationStats {
+    /// Events per category; categories without events are left out
+    pub counts: BTreeMap<ReplicationEventKind, u64>,
+    /// Changes between streaming and not streaming, oldest first
+    pub transitions: Vec<ReplicationTransition>,
+    /// Longest stretch without streaming
+    pub longest_gap: Option<ReplicationGap>,
+}
+
+impl ReplicationStats {
+    /// Events and their categories, in any order, as a report
+    pub fn from_events(mut events: Vec<(DateTime<Utc>, ReplicationEventKind)>) -> Self {
+        events.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let mut counts = BTreeMap::new();
+        let mut transitions = Vec::new();
+        let mut streaming = None;
+        let mut gap_start = None;
+        let mut longest_gap: Option<ReplicationGap> = None;
+        let mut keep_longest = |gap: ReplicationGap| {
+            if longest_gap
+                .as_ref()
+                .is_none_or(|longest| gap.duration_seconds > longest.duration_seconds)
+            {
+                longest_gap = Some(gap);
+            }
+        };
+
+        for &(timestamp, kind) in &events {
+            *counts.entry(kind).or_insert(0) += 1;
+            let Some(now_streaming) = kind.streaming() else {
+                continue;
+            };
+            if streaming == Some(now_streaming) {
+                continue;
+            }
+            streaming = Some(now_streaming);
+            transitions.push(ReplicationTransition {
+                timestamp,
+                kind,
+                streaming: now_streaming,
+            });
+            match (now_streaming, gap_start.take()) {
+                (true, Some(start)) => keep_longest(gap(start, timestamp, false)),
+                (false, _) => gap_start = Some(timestamp),
+                (true, None) => {}
+            }
+        }
+        if let (Some(start), Some((end, _))) = (gap_start, events.last()) {
+            keep_longest(gap(start, *end, true));
+        }
+
+        Self {
+            counts,
+            transitions,
+            longest_gap,
+        }
+    }
+
+    /// Total number of replication events
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Whether the log had no replication events
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+fn gap(start: DateTime<Utc>, end: DateTime<Utc>, ongoing: bool) -> ReplicationGap {
+    ReplicationGap {
+        start,
+        end,
+        duration_seconds: (end - start).num_milliseconds() as f64 / 1000.0,
+        ongoing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn recognizes_each_category() {
+        let cases = [
+            (
+                "started streaming WAL from primary at 0/3000000 on timeline 1",
+                ReplicationEventKind::StreamingStarted,
+            ),
+            (
+                "replication terminated by primary server",
+                ReplicationEventKind::StreamingStopped,
+            ),
+            (
+                "could not receive data from WAL stream: server closed the connection unexpectedly",
+                ReplicationEventKind::StreamingStopped,
+            ),
+            (
+                "terminating walreceiver process due to administrator command",
+                ReplicationEventKind::StreamingStopped,
+            ),
+            (
+                "could not connect to the primary server: connection to server at \"10.0.0.1\", port 5432 failed",
+                ReplicationEventKind::ConnectionFailure,
+            ),
+            (
+                "canceling statement due to conflict with recovery",
+                ReplicationEventKind::RecoveryConflict,
+            ),
+            (
+                "terminating connection due to conflict with recovery",
+                ReplicationEventKind::RecoveryConflict,
+            ),
+            (
+                "recovery restart point at 0/5000028",
+                ReplicationEventKind::RestartPoint,
+            ),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(
+                ReplicationEventKind::from_message(message),
+                Some(kind),
+                "{}",
+                message
+            );
+        }
+        assert_eq!(
+            ReplicationEventKind::from_message("checkpoint starting: time"),
+            None
+        );
+    }
+
+    #[test]
+    fn tracks_transitions_and_the_longest_gap() {
+        let at = |second| Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, second).unwrap();
+        let stats = ReplicationStats::from_events(vec![
+            (at(40), ReplicationEventKind::StreamingStarted),
+            (at(0), ReplicationEventKind::StreamingStarted),
+            (at(5), ReplicationEventKind::StreamingStopped),
+            (at(10), ReplicationEventKind::ConnectionFailure),
+            (at(15), ReplicationEventKind::ConnectionFailure),
+            (at(20), ReplicationEventKind::StreamingStarted),
+            (at(30), ReplicationEventKind::RestartPoint),
+            (at(35), ReplicationEventKind::ConnectionFailure),
+        ]);
+
+        assert_eq!(stats.total(), 8);
+        assert_eq!(stats.counts[&ReplicationEventKind::ConnectionFailure], 3);
+        let transitions: Vec<_> = stats
+            .transitions
+            .iter()
+            .map(|transition| (transition.timestamp, transition.streaming))
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                (at(0), true),
+                (at(5), false),
+                (at(20), true),
+                (at(35), false),
+                (at(40), true)
+            ]
+        );
+        assert_eq!(
+            stats.longest_gap,
+            Some(ReplicationGap {
+                start: at(5),
+                end: at(20),
+                duration_seconds: 15.0,
+                ongoing: false,
+            })
+        );
+
+        let stats = ReplicationStats::from_events(vec![
+            (at(0), ReplicationEventKind::ConnectionFailure),
+            (at(30), ReplicationEventKind::ConnectionFailure),
+        ]);
+        let gap = stats.longest_gap.unwrap();
+        assert_eq!((gap.duration_seconds, gap.ongoing), (30.0, true));
+    }
+}
@@ -0,0 +1,108 @@
+//! Performance of a run of the tool, written with its report
+//!
+//! Lets a pipeline that runs pg-logstats on a schedule see how long each
+//! stage took and how much input it read, from the report alone.
+
+use crate::ParseReport;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Instant;
+
+/// Lines and entries read from one log file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRunStats {
+    pub file: String,
+    /// File size, when it could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    pub lines: usize,
+    pub entries: usize,
+}
+
+/// Wall-clock time of each stage of a run and the input it processed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Time spent reading and parsing the input, in milliseconds
+    pub parse_ms: f64,
+    /// Time spent analyzing the parsed entries, in milliseconds
+    pub analysis_ms: f64,
+    /// Time from the end of the analysis until this output was rendered,
+    /// including any baseline comparison and outputs written before it
+    pub output_ms: f64,
+    /// Set when entries were analyzed while they were parsed; the time of
+    /// both is then in `parse_ms`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub streamed: bool,
+    /// Size of the log files, or of the CloudWatch messages
+    pub input_bytes: u64,
+    pub lines_processed: usize,
+    pub entries_parsed: usize,
+    pub lines_skipped: usize,
+    /// Log files in the order they were read; empty for CloudWatch input
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileRunStats>,
+    /// Largest resident set size of the process so far, in kB; only read on
+    /// Linux
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_kb: Option<u64>,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the line, entry, and file counts of the parse
+    pub fn record_parse_report(&mut self, parse_report: &ParseReport) {
+        self.lines_processed = parse_report.total_lines;
+        self.entries_parsed = parse_report.parsed_entries;
+        self.lines_skipped = parse_report.skipped_lines();
+        self.files = parse_report
+            .files
+            .iter()
+            .map(|file| FileRunStats {
+                file: file.file.clone(),
+                bytes: fs::metadata(&file.file).ok().map(|metadata| metadata.len()),
+                lines: file.lines,
+                entries: file.entries,
+            })
+            .collect();
+        if !self.files.is_empty() {
+            self.input_bytes = self.files.iter().filter_map(|file| file.bytes).sum();
+        }
+    }
+
+    /// Read the peak resident set size of the process
+    pub fn sample_peak_rss(&mut self) {
+        self.peak_rss_kb = fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| peak_rss_kb(&status));
+    }
+}
+
+/// Milliseconds elapsed since `start`
+pub fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// `VmHWM` of a `/proc/<pid>/status` file, in kB
+fn peak_rss_kb(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_peak_rss_from_proc_status() {
+        let status =
+            "Name:\tpg-logstats\nVmPeak:\t  20000 kB\nVmHWM:\t   12345 kB\nVmRSS:\t 1000 kB\n";
+        assert_eq!(peak_rss_kb(status), Some(12345));
+        assert_eq!(peak_rss_kb("Name:\tpg-logstats\n"), None);
+    }
+}
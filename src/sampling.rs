@@ -0,0 +1,273 @@
+//! Statistical sampling of statements for very large logs
+//!
+//! `--sample-size` reads the first lines of each file, which only shows what
+//! ran right after the log rotated. [`StatementSampler`] instead keeps each
+//! statement with probability `rate`, together with the duration line that
+//! follows it in the same session. Errors, connection events, and other
+//! messages are always kept. Query counts and totals of the report are then
+//! scaled by `1 / rate`; durations, averages, and percentiles come from the
+//! sample as they are.
+//!
+//! The random numbers come from a seeded SplitMix64 generator rather than an
+//! external crate, so a seed selects the same statements on every platform
+//! and release.
+
+use crate::{AnalysisResult, LogEntry, PgLogstatsError, Result, TimingAnalysis};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Rate and seed of statement sampling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatementSampling {
+    /// Share of statements kept, above 0 and at most 1
+    pub rate: f64,
+    pub seed: u64,
+}
+
+impl StatementSampling {
+    /// Keep statements with probability `rate`, drawn from `seed`
+    pub fn new(rate: f64, seed: u64) -> Result<Self> {
+        if !(rate > 0.0 && rate <= 1.0) {
+            return Err(PgLogstatsError::Configuration {
+                message: format!("Sample rate must be above 0 and at most 1, got {}", rate),
+                field: Some("sample_rate".to_string()),
+            });
+        }
+        Ok(Self { rate, seed })
+    }
+}
+
+/// Statements a sampled run saw and kept, with the rate their counts were
+/// scaled by
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SampleEstimate {
+    pub rate: f64,
+    pub seed: u64,
+    /// Statements, and durations without a statement, that passed the filters
+    pub statements_seen: u64,
+    /// Those of them kept in the sample
+    pub statements_kept: u64,
+}
+
+impl SampleEstimate {
+    /// Factor the sampled counts are multiplied by
+    pub fn scale_factor(&self) -> f64 {
+        1.0 / self.rate
+    }
+
+    /// One-line note that the report is estimated from a sample
+    pub fn note(&self) -> String {
+        format!(
+            "query counts and totals are estimated from a {}% sample of statements ({} of {}, seed {}); durations and percentiles are from the sample",
+            // Rounded so rates like 0.07 do not print as 7.000000000000001
+            (self.rate * 1e6).round() / 1e4,
+            self.statements_kept,
+            self.statements_seen,
+            self.seed
+        )
+    }
+
+    /// Scale the query counts and total durations of `analysis` up to the
+    /// whole log and mark it as estimated.
+    ///
+    /// Errors, connections, and maintenance were not sampled and are left
+    /// as counted.
+    pub fn scale_analysis(&self, analysis: &mut AnalysisResult) {
+        let factor = self.scale_factor();
+        let count = |count: &mut u64| *count = scale_count(*count, factor);
+
+        count(&mut analysis.total_queries);
+        analysis.total_duration *= factor;
+        count(&mut analysis.slow_query_count);
+        count(&mut analysis.durations_without_statement);
+        count(&mut analysis.queries_without_duration);
+        count(&mut analysis.pooler_maintenance_count);
+        for stats in analysis.query_types.values_mut() {
+            count(&mut stats.count);
+            stats.total_duration *= factor;
+        }
+        for (_, frequency) in &mut analysis.most_frequent_queries {
+            count(frequency);
+        }
+        for stats in analysis
+            .query_stats
+            .values_mut()
+            .chain(analysis.all_query_stats.values_mut())
+        {
+            count(&mut stats.count);
+            stats.total_duration *= factor;
+        }
+        analysis.queries_by_user.values_mut().for_each(count);
+        analysis.queries_by_database.values_mut().for_each(count);
+        for duration in analysis.duration_by_user.values_mut() {
+            *duration *= factor;
+        }
+        for group in analysis.query_groups.values_mut() {
+            count(&mut group.total_queries);
+            group.total_duration *= factor;
+            for (_, frequency) in &mut group.most_frequent_queries {
+                count(frequency);
+            }
+        }
+        for application in &mut analysis.applications {
+            count(&mut application.query_count);
+            application.total_duration *= factor;
+        }
+        for host in &mut analysis.client_hosts {
+            count(&mut host.query_count);
+            host.total_duration *= factor;
+        }
+        for (_, bucket) in &mut analysis.duration_histogram {
+            count(bucket);
+        }
+        for hour in &mut analysis.hourly_stats {
+            count(&mut hour.query_count);
+            hour.queries_per_second *= factor;
+            hour.total_duration *= factor;
+        }
+        if let Some(time_range) = &mut analysis.time_range {
+            time_range.queries_per_second *= factor;
+        }
+        analysis.sample_estimate = Some(*self);
+    }
+
+    /// Scale the query counts and total durations of `timing` up to the
+    /// whole log
+    pub fn scale_timing(&self, timing: &mut TimingAnalysis) {
+        let factor = self.scale_factor();
+        timing.total_queries = scale_count(timing.total_queries, factor);
+        timing.timed_queries = scale_count(timing.timed_queries, factor);
+        timing.total_duration *= factor;
+        for counts in [
+            &mut timing.hourly_query_counts,
+            &mut timing.daily_query_counts,
+        ] {
+            for count in counts.values_mut() {
+                *count = scale_count(*count, factor);
+            }
+        }
+        for durations in [&mut timing.hourly_patterns, &mut timing.daily_patterns] {
+            for duration in durations.values_mut() {
+                *duration *= factor;
+            }
+        }
+        for stats in timing.by_hour.values_mut() {
+            stats.query_count = scale_count(stats.query_count, factor);
+            stats.total_duration *= factor;
+        }
+    }
+}
+
+fn scale_count(count: u64, factor: f64) -> u64 {
+    (count as f64 * factor).round() as u64
+}
+
+/// Picks the statements of a sample one entry at a time, remembering per
+/// session whether the last statement was kept so its duration line goes
+/// with it
+#[derive(Debug, Clone)]
+pub struct StatementSampler {
+    sampling: StatementSampling,
+    rng: SplitMix64,
+    /// Decision for the statement whose duration line is still to come
+    pending: HashMap<String, bool>,
+    seen: u64,
+    kept: u64,
+}
+
+impl StatementSampler {
+    pub fn new(sampling: StatementSampling) -> Self {
+        Self {
+            sampling,
+            rng: SplitMix64(sampling.seed),
+            pending: HashMap::new(),
+            seen: 0,
+            kept: 0,
+        }
+    }
+
+    /// Whether `entry` belongs to the sample
+    pub fn keep(&mut self, entry: &LogEntry) -> bool {
+        if entry.is_query() {
+            let keep = self.draw();
+            if entry.duration.is_none() {
+                self.pending.insert(entry.session_key().to_string(), keep);
+            } else {
+                self.pending.remove(entry.session_key());
+            }
+            keep
+        } else if entry.is_duration() {
+            match self.pending.remove(entry.session_key()) {
+                Some(keep) => keep,
+                // Only the duration was logged
+                None => self.draw(),
+            }
+        } else {
+            true
+        }
+    }
+
+    /// Entries of `entries` that belong to the sample
+    pub fn apply(&mut self, entries: Vec<LogEntry>) -> Vec<LogEntry> {
+        entries
+            .into_iter()
+            .filter(|entry| self.keep(entry))
+            .collect()
+    }
+
+    /// Statements seen and kept so far
+    pub fn estimate(&self) -> SampleEstimate {
+        SampleEstimate {
+            rate: self.sampling.rate,
+            seed: self.sampling.seed,
+            statements_seen: self.seen,
+            statements_kept: self.kept,
+        }
+    }
+
+    fn draw(&mut self) -> bool {
+        let keep = self.rng.next_f64() < self.sampling.rate;
+        self.seen += 1;
+        self.kept += u64::from(keep);
+        keep
+    }
+}
+
+/// SplitMix64 generator of Steele, Lea, and Flood
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitmix_matches_the_reference_sequence() {
+        let mut rng = SplitMix64(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+    }
+
+    #[test]
+    fn rejects_rates_outside_zero_to_one() {
+        assert!(StatementSampling::new(0.0, 1).is_err());
+        assert!(StatementSampling::new(1.5, 1).is_err());
+        assert!(StatementSampling::new(f64::NAN, 1).is_err());
+        assert!(StatementSampling::new(1.0, 1).is_ok());
+    }
+}
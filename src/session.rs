@@ -0,0 +1,208 @@
+//! Chronological timeline of individual sessions
+//!
+//! [`session_timeline`] turns the events of one or more backend processes
+//! into connection, statement, error, and disconnection steps. Statements are
+//! paired with their durations by [`correlate_query_executions`], so a
+//! `duration:` line shows up as the duration of the statement before it, and
+//! one without a logged statement as `(statement not logged)`.
+
+use crate::{correlate_query_executions, ConnectionEventKind, EventKind, NormalizedEvent};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What happened at one step of a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// `connection received` or `connection authorized`
+    Connection,
+    /// A statement, with its duration when one was logged
+    Statement,
+    /// An `ERROR`, `FATAL`, or `PANIC` entry
+    Error,
+    /// `disconnection: session time: ...`
+    Disconnection,
+}
+
+impl SessionEventKind {
+    /// Lowercase name as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionEventKind::Connection => "connection",
+            SessionEventKind::Statement => "statement",
+            SessionEventKind::Error => "error",
+            SessionEventKind::Disconnection => "disconnection",
+        }
+    }
+}
+
+impl std::fmt::Display for SessionEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// One step of a session timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionEvent {
+    pub timestamp: DateTime<Utc>,
+    pub process_id: String,
+    /// Session id of the `%c` prefix escape
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub kind: SessionEventKind,
+    /// Statement text, error message, or connection line
+    pub message: String,
+    /// Statement duration in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+    /// SQLSTATE of an error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlstate: Option<String>,
+}
+
+/// Connection or disconnection step of a `LOG` message, if it is one
+fn connection_kind(event: &NormalizedEvent) -> Option<SessionEventKind> {
+    match event.connection_event()? {
+        ConnectionEventKind::Received | ConnectionEventKind::Authorized => {
+            Some(SessionEventKind::Connection)
+        }
+        ConnectionEventKind::Disconnection => Some(SessionEventKind::Disconnection),
+        ConnectionEventKind::Reset => None,
+    }
+}
+
+/// Whether `event` can appear in a session timeline; other events, such as
+/// checkpoints, can be dropped before [`session_timeline`]
+pub fn is_session_event(event: &NormalizedEvent) -> bool {
+    match &event.kind {
+        EventKind::Log { .. } => connection_kind(event).is_some(),
+        _ => true,
+    }
+}
+
+/// Steps of the sessions in `events`, ordered by timestamp; steps with the
+/// same timestamp keep the order of their log lines
+pub fn session_timeline(events: &[NormalizedEvent]) -> Vec<SessionEvent> {
+    let mut steps: Vec<(usize, SessionEvent)> = Vec::new();
+    for event in events {
+        let (kind, message, sqlstate) = match &event.kind {
+            EventKind::Log { message, .. } => match connection_kind(event) {
+                Some(kind) => (kind, message.clone(), None),
+                None => continue,
+            },
+            EventKind::Error(error) => (
+                SessionEventKind::Error,
+                error.message.clone(),
+                error.sqlstate.clone(),
+            ),
+            EventKind::Statement(_) | EventKind::Duration(_) => continue,
+        };
+        steps.push((
+            event.source.record_index,
+            SessionEvent {
+                timestamp: event.timestamp,
+                process_id: event.session.process_id.clone(),
+                session_id: event.session.session_id.clone(),
+                kind,
+                message,
+                duration_ms: None,
+                sqlstate,
+            },
+        ));
+    }
+
+    for execution in correlate_query_executions(events) {
+        let Some(first) = execution.evidence.first() else {
+            continue;
+        };
+        steps.push((
+            first.record_index,
+            SessionEvent {
+                timestamp: execution.timestamp,
+                process_id: execution.session.process_id,
+                session_id: execution.session.session_id,
+                kind: SessionEventKind::Statement,
+                message: execution.statement,
+                duration_ms: execution.duration_ms,
+                sqlstate: None,
+            },
+        ));
+    }
+
+    steps.sort_by_key(|(record_index, step)| (step.timestamp, *record_index));
+    steps.into_iter().map(|(_, step)| step).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normalize_log_entries, EventSourceKind, LogEntry, LogLevel, STATEMENT_NOT_LOGGED};
+    use chrono::TimeZone;
+
+    fn entry(second: u32, pid: &str, level: LogLevel, message: &str) -> LogEntry {
+        let mut entry = LogEntry::builder(
+            Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, second).unwrap(),
+            pid,
+            level,
+            message,
+        )
+        .with_connection_event(ConnectionEventKind::from_message(message))
+        .build()
+        .unwrap();
+        if let Some(statement) = message.strip_prefix("statement: ") {
+            entry.message_type = LogLevel::Statement;
+            entry.statement = Some(statement.to_string());
+        }
+        if let Some(duration) = message
+            .strip_prefix("duration: ")
+            .and_then(|rest| rest.strip_suffix(" ms"))
+        {
+            entry.message_type = LogLevel::Duration;
+            entry.duration = duration.parse().ok();
+        }
+        entry
+    }
+
+    #[test]
+    fn orders_steps_and_pairs_durations_with_statements() {
+        let entries = vec![
+            entry(0, "7", LogLevel::Log, "connection authorized: user=app"),
+            entry(1, "7", LogLevel::Log, "statement: SELECT 1"),
+            entry(1, "7", LogLevel::Log, "duration: 2.5 ms"),
+            entry(1, "7", LogLevel::Error, "division by zero"),
+            entry(2, "7", LogLevel::Log, "duration: 4 ms"),
+            entry(2, "7", LogLevel::Log, "checkpoint starting: time"),
+            entry(
+                3,
+                "7",
+                LogLevel::Log,
+                "disconnection: session time: 0:00:03.000",
+            ),
+        ];
+        let timeline = session_timeline(&normalize_log_entries(&entries, EventSourceKind::Stderr));
+
+        let steps: Vec<_> = timeline
+            .iter()
+            .map(|step| (step.kind, step.message.as_str(), step.duration_ms))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    SessionEventKind::Connection,
+                    "connection authorized: user=app",
+                    None
+                ),
+                (SessionEventKind::Statement, "SELECT 1", Some(2.5)),
+                (SessionEventKind::Error, "division by zero", None),
+                (SessionEventKind::Statement, STATEMENT_NOT_LOGGED, Some(4.0)),
+                (
+                    SessionEventKind::Disconnection,
+                    "disconnection: session time: 0:00:03.000",
+                    None
+                ),
+            ]
+        );
+    }
+}
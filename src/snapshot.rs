@@ -0,0 +1,334 @@
+//! Deltas between successive analyses of a growing log
+//!
+//! In follow mode the log is analyzed again at each interval, and
+//! [`Snapshot::between`] turns the previous and current results into the
+//! queries, durations, and errors added since the previous snapshot. Queries
+//! are matched by normalized SQL across the `all_query_stats` of both
+//! results, so every counted query shows up, not only the listed slowest or
+//! most frequent ones.
+
+use crate::{query_id, AnalysisResult, PgLogstatsError, Redact, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// Log time covered by a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotWindow {
+    /// Latest entry of the previous snapshot, or the earliest entry of the
+    /// first one
+    pub start: DateTime<Utc>,
+    /// Latest entry of this snapshot
+    pub end: DateTime<Utc>,
+}
+
+/// Change of the overall totals since the previous snapshot
+///
+/// Counts are signed so a truncated or rotated log shows up as a drop rather
+/// than as zero. Durations are `None` when the log has no query durations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SummaryDelta {
+    pub queries: i64,
+    pub total_duration_ms: Option<f64>,
+    pub errors: i64,
+    pub connections: i64,
+    pub slow_queries: i64,
+}
+
+/// Change of one query since the previous snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryDelta {
+    pub query_id: String,
+    pub query: String,
+    pub count: i64,
+    /// `None` when the log has no query durations
+    pub total_duration_ms: Option<f64>,
+}
+
+/// One NDJSON record of follow mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Wall-clock time the snapshot was taken
+    pub snapshot_at: DateTime<Utc>,
+    /// `None` when the log has no entries yet
+    pub window: Option<SnapshotWindow>,
+    pub summary: SummaryDelta,
+    /// Queries that ran since the previous snapshot, most added time first
+    pub queries: Vec<QueryDelta>,
+}
+
+impl Snapshot {
+    /// Changes from `previous` to `current`; everything in `current` is new
+    /// for the first snapshot
+    pub fn between(
+        previous: Option<&AnalysisResult>,
+        current: &AnalysisResult,
+        snapshot_at: DateTime<Utc>,
+    ) -> Self {
+        let empty = AnalysisResult::new();
+        let previous = previous.unwrap_or(&empty);
+
+        let window = current.time_range.as_ref().map(|range| SnapshotWindow {
+            start: previous
+                .time_range
+                .as_ref()
+                .map_or(range.start, |previous| previous.end.min(range.end)),
+            end: range.end,
+        });
+        let durations_logged = current.durations_logged();
+        let summary = SummaryDelta {
+            queries: count_delta(current.total_queries, previous.total_queries),
+            total_duration_ms: durations_logged
+                .then_some(current.total_duration - previous.total_duration),
+            errors: count_delta(current.error_count, previous.error_count),
+            connections: count_delta(current.connection_count, previous.connection_count),
+            slow_queries: count_delta(current.slow_query_count, previous.slow_query_count),
+        };
+
+        // Queries gone from the current result, as after a rotation, count
+        // as drops so the deltas add up to the summary
+        let totals = |result: &AnalysisResult, sql: &str| {
+            result
+                .all_query_stats
+                .get(sql)
+                .map_or((0, 0.0), |stats| (stats.count, stats.total_duration))
+        };
+        let sqls: BTreeSet<&String> = current
+            .all_query_stats
+            .keys()
+            .chain(previous.all_query_stats.keys())
+            .collect();
+        let mut queries: Vec<QueryDelta> = sqls
+            .into_iter()
+            .filter_map(|sql| {
+                let (count, total_duration) = totals(current, sql);
+                let (previous_count, previous_duration) = totals(previous, sql);
+                let count = count_delta(count, previous_count);
+                if count == 0 {
+                    return None;
+                }
+                Some(QueryDelta {
+                    query_id: query_id(sql),
+                    query: sql.clone(),
+                    count,
+                    total_duration_ms: durations_logged
+                        .then_some(total_duration - previous_duration),
+                })
+            })
+            .collect();
+        queries.sort_by(|a, b| {
+            let duration = |delta: &QueryDelta| delta.total_duration_ms.unwrap_or_default();
+            duration(b)
+                .total_cmp(&duration(a))
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.query.cmp(&b.query))
+        });
+
+        Self {
+            snapshot_at,
+            window,
+            summary,
+            queries,
+        }
+    }
+
+    /// The snapshot as one JSON line, newline included
+    pub fn to_ndjson_line(&self) -> Result<String> {
+        self.to_ndjson_line_with(None)
+    }
+
+    /// [`to_ndjson_line`](Self::to_ndjson_line) with every string redacted
+    /// by `redactor`
+    pub fn to_ndjson_line_with(&self, redactor: Option<&dyn Redact>) -> Result<String> {
+        let mut line = match redactor {
+            Some(redactor) => {
+                let mut value =
+                    serde_json::to_value(self).map_err(PgLogstatsError::Serialization)?;
+                redactor.redact_json(&mut value);
+                value.to_string()
+            }
+            None => serde_json::to_string(self).map_err(PgLogstatsError::Serialization)?,
+        };
+        line.push('\n');
+        Ok(line)
+    }
+
+    /// Append the snapshot to `path` in a single write to a file opened for
+    /// appending, so earlier records stay intact if the process dies
+    pub fn append_to(&self, path: &Path) -> Result<()> {
+        self.append_to_with(path, None)
+    }
+
+    /// [`append_to`](Self::append_to) with every string redacted by
+    /// `redactor`
+    pub fn append_to_with(&self, path: &Path, redactor: Option<&dyn Redact>) -> Result<()> {
+        let line = self.to_ndjson_line_with(redactor)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn count_delta(current: u64, previous: u64) -> i64 {
+    current as i64 - previous as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Analyzer, Pipeline, QueryAnalyzer};
+    use chrono::TimeZone;
+    use std::fs;
+
+    fn statement(second: u32, sql: &str, duration_ms: f64) -> String {
+        format!(
+            "2024-08-15 10:00:{second:02}.000 UTC [4242] app@shop psql: LOG:  statement: {sql}\n\
+             2024-08-15 10:00:{second:02}.100 UTC [4242] app@shop psql: LOG:  duration: {duration_ms:.3} ms\n"
+        )
+    }
+
+    #[test]
+    fn deltas_of_two_cycles_over_a_growing_log() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let pipeline = Pipeline::new().with_analyzers([Analyzer::Queries]);
+        let analyze = || pipeline.run_files(&[&path]).unwrap().analysis.unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 8, 15, 11, 0, 0).unwrap();
+
+        fs::write(
+            &path,
+            statement(0, "SELECT * FROM orders WHERE id = 1", 10.0)
+                + &statement(1, "SELECT * FROM orders WHERE id = 2", 20.0)
+                + &statement(2, "UPDATE stock SET qty = 0", 5.0),
+        )
+        .unwrap();
+        let first_result = analyze();
+        let first = Snapshot::between(None, &first_result, at);
+        assert_eq!(first.summary.queries, 3);
+        assert_eq!(first.summary.total_duration_ms, Some(35.0));
+        assert_eq!(first.queries.len(), 2);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(
+            (statement(3, "SELECT * FROM orders WHERE id = 3", 30.0)
+                + &statement(4, "DELETE FROM carts", 1.5))
+                .as_bytes(),
+        )
+        .unwrap();
+        let second_result = analyze();
+        let second = Snapshot::between(Some(&first_result), &second_result, at);
+
+        assert_eq!(second.summary.queries, 2);
+        assert_eq!(second.summary.total_duration_ms, Some(31.5));
+        let window = second.window.unwrap();
+        assert_eq!(
+            (window.start.timestamp() % 60, window.end.timestamp() % 60),
+            (2, 4)
+        );
+        let deltas: Vec<_> = second
+            .queries
+            .iter()
+            .map(|delta| (delta.query.as_str(), delta.count, delta.total_duration_ms))
+            .collect();
+        assert_eq!(
+            deltas,
+            [
+                ("SELECT * FROM orders WHERE id = ?", 1, Some(30.0)),
+                ("DELETE FROM carts", 1, Some(1.5))
+            ]
+        );
+    }
+
+    #[test]
+    fn deltas_of_a_query_crossing_the_top_n_cutoff() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let pipeline = Pipeline::new()
+            .with_analyzers([Analyzer::Queries])
+            .with_query_analyzer(QueryAnalyzer::with_settings(1000.0, 1, 1));
+        let analyze = || pipeline.run_files(&[&path]).unwrap().analysis.unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 8, 15, 11, 0, 0).unwrap();
+
+        fs::write(
+            &path,
+            statement(0, "SELECT * FROM orders", 50.0)
+                + &statement(1, "SELECT * FROM orders", 50.0)
+                + &statement(2, "DELETE FROM carts", 1.0),
+        )
+        .unwrap();
+        let first_result = analyze();
+        assert!(!first_result.query_stats.contains_key("DELETE FROM carts"));
+        let first = Snapshot::between(None, &first_result, at);
+        assert!(first
+            .queries
+            .iter()
+            .any(|delta| delta.query == "DELETE FROM carts" && delta.count == 1));
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(
+            (statement(3, "DELETE FROM carts", 2.0) + &statement(4, "DELETE FROM carts", 3.0))
+                .as_bytes(),
+        )
+        .unwrap();
+        let second_result = analyze();
+        assert!(second_result.query_stats.contains_key("DELETE FROM carts"));
+        let second = Snapshot::between(Some(&first_result), &second_result, at);
+
+        let deltas: Vec<_> = second
+            .queries
+            .iter()
+            .map(|delta| (delta.query.as_str(), delta.count, delta.total_duration_ms))
+            .collect();
+        assert_eq!(deltas, [("DELETE FROM carts", 2, Some(5.0))]);
+    }
+
+    #[test]
+    fn queries_gone_after_a_rotation_count_as_drops() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("postgresql.log");
+        let pipeline = Pipeline::new().with_analyzers([Analyzer::Queries]);
+        let analyze = || pipeline.run_files(&[&path]).unwrap().analysis.unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 8, 15, 11, 0, 0).unwrap();
+
+        fs::write(
+            &path,
+            statement(0, "SELECT * FROM orders", 10.0)
+                + &statement(1, "DELETE FROM carts", 2.0)
+                + &statement(2, "DELETE FROM carts", 3.0),
+        )
+        .unwrap();
+        let first_result = analyze();
+        // The log was rotated and started over
+        fs::write(&path, statement(3, "SELECT * FROM orders", 10.0)).unwrap();
+        let second = Snapshot::between(Some(&first_result), &analyze(), at);
+
+        let deltas: Vec<_> = second
+            .queries
+            .iter()
+            .map(|delta| (delta.query.as_str(), delta.count, delta.total_duration_ms))
+            .collect();
+        assert_eq!(deltas, [("DELETE FROM carts", -2, Some(-5.0))]);
+        let counted: i64 = second.queries.iter().map(|delta| delta.count).sum();
+        assert_eq!(counted, second.summary.queries);
+    }
+
+    #[test]
+    fn appends_one_line_per_snapshot() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("snapshots.ndjson");
+        let at = Utc.with_ymd_and_hms(2024, 8, 15, 11, 0, 0).unwrap();
+        let snapshot = Snapshot::between(None, &AnalysisResult::new(), at);
+
+        snapshot.append_to(&path).unwrap();
+        snapshot.append_to(&path).unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = written.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: Snapshot = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(parsed, snapshot);
+    }
+}
@@ -0,0 +1,239 @@
+//! Text-based normalization for SQL that sqlparser cannot parse
+//!
+//! PostgreSQL accepts plenty of syntax the parser does not know, such as
+//! `COPY ... FROM STDIN`, `LISTEN`/`NOTIFY`, and `DO $$ ... $$` blocks. Those
+//! statements are normalized with regexes instead, so they are still counted
+//! and classified. Literals become `?`, the placeholder the syntax-tree
+//! normalizer uses, so both produce the same kind of query family.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::query::{Query, QueryType};
+
+/// Quoted identifiers, parameters, numbers, and string literals, compiled on
+/// first use and shared by every `TextNormalizer`
+///
+/// Quoted identifiers are matched so their digits are kept; everything else
+/// the regex matches becomes `?`.
+fn literal_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#""(?:[^"]|"")*"|\$\d+|\b\d+(?:\.\d+)?\b|'(?:[^']|'')*'"#).unwrap()
+    })
+}
+
+/// Regex normalizer replacing parameters and literals in raw SQL text
+#[derive(Debug, Clone)]
+pub struct TextNormalizer {
+    literal_regex: &'static Regex,
+    collapse_lists: bool,
+}
+
+impl TextNormalizer {
+    pub fn new() -> Self {
+        Self {
+            literal_regex: literal_regex(),
+            collapse_lists: true,
+        }
+    }
+
+    /// Whether parsed queries collapse `IN` lists and `VALUES` rows, as in
+    /// [`Query::from_sql_with`]; on by default
+    pub fn with_collapse_lists(mut self, collapse: bool) -> Self {
+        self.collapse_lists = collapse;
+        self
+    }
+
+    /// Replace parameters, numbers, and string literals with `?`, and
+    /// collapse whitespace
+    ///
+    /// Identifiers keep their digits, so normalizing normalized text changes
+    /// nothing.
+    pub fn normalize(&self, sql: &str) -> String {
+        let normalized = self
+            .literal_regex
+            .replace_all(sql.trim(), |caps: &regex::Captures| {
+                let matched = &caps[0];
+                if matched.starts_with('"') {
+                    matched.to_string()
+                } else {
+                    "?".to_string()
+                }
+            });
+        normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Query for SQL text that could not be parsed, classified by its leading
+    /// keyword
+    pub fn query(&self, sql: &str) -> Query {
+        Query {
+            sql: sql.trim().to_string(),
+            query_type: classify_sql(sql),
+            normalized_query: self.normalize(sql),
+            unparsed: true,
+        }
+    }
+
+    /// Parse SQL into queries, falling back to a single text-normalized query
+    /// when the parser rejects it.
+    ///
+    /// The flag is `true` when the fallback was used.
+    pub fn parse(&self, sql: &str) -> (Vec<Query>, bool) {
+        match Query::from_sql_with(sql, self.collapse_lists) {
+            Ok(queries) => (queries, false),
+            Err(_) => (vec![self.query(sql)], true),
+        }
+    }
+}
+
+impl Default for TextNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Classify SQL text by its leading keywords
+pub fn classify_sql(sql: &str) -> QueryType {
+    let sql_upper = sql.to_uppercase();
+    let mut words = sql_upper
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty());
+    let first = words.next().unwrap_or("");
+    let second = words.next().unwrap_or("");
+
+    match (first, second) {
+        ("SELECT" | "WITH" | "VALUES" | "TABLE", _) => QueryType::Select,
+        ("INSERT", _) => QueryType::Insert,
+        ("UPDATE", _) => QueryType::Update,
+        ("DELETE", _) => QueryType::Delete,
+        ("CREATE" | "DROP" | "ALTER" | "TRUNCATE" | "GRANT" | "REVOKE", _) => QueryType::DDL,
+        ("COPY", _) => QueryType::Copy,
+        // Checked before SET and PREPARE, which otherwise mean something else
+        ("SET" | "PREPARE", "TRANSACTION")
+        | (
+            "BEGIN" | "START" | "COMMIT" | "END" | "ROLLBACK" | "ABORT" | "SAVEPOINT" | "RELEASE",
+            _,
+        ) => QueryType::Transaction,
+        ("SET" | "RESET", _) => QueryType::Set,
+        ("SHOW", _) => QueryType::Show,
+        ("EXPLAIN", _) => QueryType::Explain,
+        ("VACUUM" | "ANALYZE" | "ANALYSE" | "REINDEX" | "CLUSTER", _) => QueryType::Maintenance,
+        _ => QueryType::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_for_statements_the_parser_rejects() {
+        let normalizer = TextNormalizer::new();
+
+        for (sql, query_type, normalized) in [
+            (
+                "COPY orders FROM STDIN",
+                QueryType::Copy,
+                "COPY orders FROM STDIN",
+            ),
+            ("LISTEN jobs", QueryType::Other, "LISTEN jobs"),
+            (
+                "NOTIFY jobs, 'order 42'",
+                QueryType::Other,
+                "NOTIFY jobs, ?",
+            ),
+            (
+                "DO $$ BEGIN PERFORM pg_sleep(1); END $$",
+                QueryType::Other,
+                "DO $$ BEGIN PERFORM pg_sleep(?); END $$",
+            ),
+            (
+                "COPY \"sales 2024\".q1_orders FROM STDIN",
+                QueryType::Copy,
+                "COPY \"sales 2024\".q1_orders FROM STDIN",
+            ),
+            (
+                "NOTIFY \"jobs_2\", 'it''s 42'",
+                QueryType::Other,
+                "NOTIFY \"jobs_2\", ?",
+            ),
+        ] {
+            assert!(Query::from_sql(sql).is_err(), "{sql} parses");
+            let (queries, fallback) = normalizer.parse(sql);
+            assert!(fallback, "{sql}");
+            assert_eq!(queries.len(), 1);
+            assert_eq!(queries[0].query_type, query_type);
+            assert_eq!(queries[0].normalized_query, normalized);
+            assert_eq!(normalizer.normalize(normalized), normalized, "{sql}");
+        }
+    }
+
+    #[test]
+    fn parses_supported_sql_without_falling_back() {
+        let (queries, fallback) = TextNormalizer::new().parse("SELECT * FROM t WHERE id = 1");
+
+        assert!(!fallback);
+        assert_eq!(queries[0].normalized_query, "SELECT * FROM t WHERE id = ?");
+    }
+
+    #[test]
+    fn classifies_by_leading_keyword() {
+        for (sql, query_type) in [
+            ("  select 1", QueryType::Select),
+            ("WITH t AS (SELECT 1) SELECT * FROM t", QueryType::Select),
+            ("GRANT ALL ON t TO app", QueryType::DDL),
+            ("COPY t TO STDOUT", QueryType::Copy),
+            ("SET search_path TO app", QueryType::Set),
+            ("RESET ALL", QueryType::Set),
+            (
+                "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE",
+                QueryType::Transaction,
+            ),
+            ("SHOW work_mem", QueryType::Show),
+            ("EXPLAIN SELECT 1", QueryType::Explain),
+            ("VACUUM (ANALYZE) t", QueryType::Maintenance),
+            ("REINDEX TABLE t", QueryType::Maintenance),
+            ("CLUSTER t USING t_pkey", QueryType::Maintenance),
+            ("begin", QueryType::Transaction),
+            ("PREPARE TRANSACTION 'tx1'", QueryType::Transaction),
+            ("COMMIT PREPARED 'tx1'", QueryType::Transaction),
+            ("PREPARE q AS SELECT 1", QueryType::Other),
+            ("LISTEN jobs", QueryType::Other),
+            ("", QueryType::Other),
+        ] {
+            assert_eq!(classify_sql(sql), query_type, "{sql}");
+        }
+    }
+
+    #[test]
+    fn keyword_classification_agrees_with_parsed_statements() {
+        for sql in [
+            "SELECT * FROM t",
+            "WITH t AS (SELECT 1) SELECT * FROM t",
+            "INSERT INTO t VALUES (1)",
+            "UPDATE t SET a = 1",
+            "DELETE FROM t",
+            "CREATE TABLE t (id INT)",
+            "CREATE FUNCTION f() RETURNS INT AS 'SELECT 1' LANGUAGE SQL",
+            "ALTER TABLE t ADD COLUMN b INT",
+            "DROP INDEX t_idx",
+            "GRANT SELECT ON t TO app",
+            "TRUNCATE t",
+            "COPY t (id) TO STDOUT",
+            "SET search_path TO app",
+            "SET TIME ZONE 'UTC'",
+            "SHOW work_mem",
+            "EXPLAIN ANALYZE SELECT 1",
+            "BEGIN",
+            "START TRANSACTION",
+            "COMMIT",
+            "ROLLBACK",
+            "SAVEPOINT s1",
+            "RELEASE SAVEPOINT s1",
+        ] {
+            let parsed = Query::from_sql(sql).unwrap_or_else(|e| panic!("{sql}: {e}"));
+            assert_eq!(parsed[0].query_type, classify_sql(sql), "{sql}");
+        }
+    }
+}
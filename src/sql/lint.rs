@@ -0,0 +1,471 @@
+//! Heuristic warnings for suspicious query patterns
+//!
+//! A [`QueryLinter`] parses each distinct query once and runs its
+//! [`QueryLintRule`]s over the statements. The built-in rules flag `SELECT *`
+//! without a `LIMIT`, `LIKE '%...'` patterns, `UPDATE` and `DELETE` without a
+//! `WHERE`, long `IN` lists, and many joins. Library users can register their
+//! own rules with [`QueryLinter::with_rule`].
+//!
+//! Rules see the SQL as logged, before normalization, so a leading wildcard in
+//! a literal pattern is still visible; bound parameters are not.
+
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    visit_expressions, Expr, Query as AstQuery, SelectItem, SetExpr, Statement, TableFactor,
+    TableWithJoins, Value, Visit, Visitor,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+
+/// Default number of `IN` list items above which a query is flagged
+pub const DEFAULT_MAX_IN_LIST: usize = 50;
+
+/// Default number of joins above which a query is flagged
+pub const DEFAULT_MAX_JOINS: usize = 5;
+
+/// A suspicious pattern to look for in parsed statements
+pub trait QueryLintRule: Send + Sync {
+    /// Short kebab-case name shown in reports, such as `select-star`
+    fn name(&self) -> &str;
+
+    /// One-line explanation of why the pattern is a problem
+    fn description(&self) -> String;
+
+    /// Whether `statement` shows the pattern
+    fn matches(&self, statement: &Statement) -> bool;
+}
+
+/// A query that matched a lint rule, with its workload so warnings can be
+/// prioritized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryWarning {
+    /// Name of the rule that matched
+    pub rule: String,
+    /// The rule's explanation
+    pub description: String,
+    /// Normalized SQL of the query
+    pub query: String,
+    /// Number of executions of the query
+    pub count: u64,
+    /// 95th percentile duration of the query in milliseconds
+    pub p95_duration: f64,
+}
+
+/// `SELECT *` without `LIMIT` or `FETCH`, which returns every column of every
+/// matching row
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelectStar;
+
+impl QueryLintRule for SelectStar {
+    fn name(&self) -> &str {
+        "select-star"
+    }
+
+    fn description(&self) -> String {
+        "SELECT * without LIMIT returns every column of every matching row".to_string()
+    }
+
+    fn matches(&self, statement: &Statement) -> bool {
+        let Statement::Query(query) = statement else {
+            return false;
+        };
+        query.limit.is_none()
+            && query.fetch.is_none()
+            && selects(&query.body).into_iter().any(|select| {
+                select.projection.iter().any(|item| {
+                    matches!(
+                        item,
+                        SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+                    )
+                })
+            })
+    }
+}
+
+/// `LIKE` or `ILIKE` with a pattern starting with `%`, which cannot use a
+/// b-tree index
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeadingWildcardLike;
+
+impl QueryLintRule for LeadingWildcardLike {
+    fn name(&self) -> &str {
+        "leading-wildcard-like"
+    }
+
+    fn description(&self) -> String {
+        "LIKE pattern starting with % cannot use a b-tree index".to_string()
+    }
+
+    fn matches(&self, statement: &Statement) -> bool {
+        any_expression(statement, |expr| match expr {
+            Expr::Like { pattern, .. } | Expr::ILike { pattern, .. } => matches!(
+                pattern.as_ref(),
+                Expr::Value(Value::SingleQuotedString(pattern)) if pattern.starts_with('%')
+            ),
+            _ => false,
+        })
+    }
+}
+
+/// `UPDATE` or `DELETE` without `WHERE`, which touches every row
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MissingWhere;
+
+impl QueryLintRule for MissingWhere {
+    fn name(&self) -> &str {
+        "missing-where"
+    }
+
+    fn description(&self) -> String {
+        "UPDATE or DELETE without WHERE changes every row of the table".to_string()
+    }
+
+    fn matches(&self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Update { selection, .. } => selection.is_none(),
+            Statement::Delete(delete) => delete.selection.is_none(),
+            _ => false,
+        }
+    }
+}
+
+/// `IN` list with more than `max` items
+#[derive(Debug, Clone, Copy)]
+pub struct LongInList {
+    pub max: usize,
+}
+
+impl Default for LongInList {
+    fn default() -> Self {
+        Self {
+            max: DEFAULT_MAX_IN_LIST,
+        }
+    }
+}
+
+impl QueryLintRule for LongInList {
+    fn name(&self) -> &str {
+        "long-in-list"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "IN list with more than {} items; consider = ANY($1) or a join",
+            self.max
+        )
+    }
+
+    fn matches(&self, statement: &Statement) -> bool {
+        any_expression(
+            statement,
+            |expr| matches!(expr, Expr::InList { list, .. } if list.len() > self.max),
+        )
+    }
+}
+
+/// More than `max` joins in one statement, counting comma-separated `FROM`
+/// items and joins in subqueries
+#[derive(Debug, Clone, Copy)]
+pub struct ManyJoins {
+    pub max: usize,
+}
+
+impl Default for ManyJoins {
+    fn default() -> Self {
+        Self {
+            max: DEFAULT_MAX_JOINS,
+        }
+    }
+}
+
+impl QueryLintRule for ManyJoins {
+    fn name(&self) -> &str {
+        "many-joins"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "more than {} joins make plans expensive to find and fragile",
+            self.max
+        )
+    }
+
+    fn matches(&self, statement: &Statement) -> bool {
+        let mut counter = JoinCounter(0);
+        let _ = statement.visit(&mut counter);
+        counter.0 > self.max
+    }
+}
+
+/// Runs lint rules over SQL text
+pub struct QueryLinter {
+    rules: Vec<Box<dyn QueryLintRule>>,
+}
+
+impl QueryLinter {
+    /// A linter with the built-in rules and their default thresholds
+    pub fn new() -> Self {
+        Self::with_limits(DEFAULT_MAX_IN_LIST, DEFAULT_MAX_JOINS)
+    }
+
+    /// A linter with the built-in rules, flagging `IN` lists longer than
+    /// `max_in_list` and statements with more than `max_joins` joins
+    pub fn with_limits(max_in_list: usize, max_joins: usize) -> Self {
+        Self::empty()
+            .with_rule(SelectStar)
+            .with_rule(LeadingWildcardLike)
+            .with_rule(MissingWhere)
+            .with_rule(LongInList { max: max_in_list })
+            .with_rule(ManyJoins { max: max_joins })
+    }
+
+    /// A linter without rules
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Add a rule
+    pub fn with_rule(mut self, rule: impl QueryLintRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Get the registered rules
+    pub fn rules(&self) -> &[Box<dyn QueryLintRule>] {
+        &self.rules
+    }
+
+    /// Rules matched by any statement of `sql`, in registration order. SQL
+    /// that does not parse matches nothing.
+    pub fn lint(&self, sql: &str) -> Vec<&dyn QueryLintRule> {
+        let Ok(statements) = Parser::parse_sql(&PostgreSqlDialect {}, sql) else {
+            return Vec::new();
+        };
+        self.rules
+            .iter()
+            .filter(|rule| statements.iter().any(|statement| rule.matches(statement)))
+            .map(|rule| rule.as_ref())
+            .collect()
+    }
+}
+
+impl Default for QueryLinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for QueryLinter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.rules.iter().map(|rule| rule.name()))
+            .finish()
+    }
+}
+
+/// Whether any expression in `statement`, including subqueries, satisfies
+/// `predicate`
+fn any_expression(statement: &Statement, predicate: impl Fn(&Expr) -> bool) -> bool {
+    visit_expressions(statement, |expr| {
+        if predicate(expr) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .is_break()
+}
+
+/// The `SELECT`s of a query body, through set operations but not subqueries
+fn selects(body: &SetExpr) -> Vec<&sqlparser::ast::Select> {
+    match body {
+        SetExpr::Select(select) => vec![select],
+        SetExpr::Query(query) => selects(&query.body),
+        SetExpr::SetOperation { left, right, .. } => {
+            let mut found = selects(left);
+            found.extend(selects(right));
+            found
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Counts joins in every query of a statement
+struct JoinCounter(usize);
+
+impl Visitor for JoinCounter {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &AstQuery) -> ControlFlow<Self::Break> {
+        self.0 += body_joins(&query.body);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Joins of a query body; nested queries are visited on their own
+fn body_joins(body: &SetExpr) -> usize {
+    match body {
+        SetExpr::Select(select) => {
+            select.from.len().saturating_sub(1) + select.from.iter().map(joins).sum::<usize>()
+        }
+        SetExpr::SetOperation { left, right, .. } => body_joins(left) + body_joins(right),
+        _ => 0,
+    }
+}
+
+/// Explicit joins of a `FROM` item, including parenthesized ones
+fn joins(table: &TableWithJoins) -> usize {
+    let nested = |factor: &TableFactor| match factor {
+        TableFactor::NestedJoin {
+            table_with_joins, ..
+        } => joins(table_with_joins),
+        _ => 0,
+    };
+    table.joins.len()
+        + nested(&table.relation)
+        + table
+            .joins
+            .iter()
+            .map(|join| nested(&join.relation))
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(rule: &dyn QueryLintRule, sql: &str) -> bool {
+        Parser::parse_sql(&PostgreSqlDialect {}, sql)
+            .unwrap()
+            .iter()
+            .any(|statement| rule.matches(statement))
+    }
+
+    #[test]
+    fn select_star_without_limit() {
+        assert!(matches(&SelectStar, "SELECT * FROM orders"));
+        assert!(matches(
+            &SelectStar,
+            "SELECT o.* FROM orders o WHERE id > 1"
+        ));
+        assert!(!matches(&SelectStar, "SELECT * FROM orders LIMIT 10"));
+        assert!(!matches(&SelectStar, "SELECT id, total FROM orders"));
+        assert!(!matches(&SelectStar, "SELECT count(*) FROM orders"));
+    }
+
+    #[test]
+    fn leading_wildcard_like() {
+        assert!(matches(
+            &LeadingWildcardLike,
+            "SELECT id FROM users WHERE name LIKE '%smith%'"
+        ));
+        assert!(matches(
+            &LeadingWildcardLike,
+            "SELECT id FROM users WHERE id IN (SELECT user_id FROM notes WHERE body ILIKE '%x')"
+        ));
+        assert!(!matches(
+            &LeadingWildcardLike,
+            "SELECT id FROM users WHERE name LIKE 'smith%'"
+        ));
+        assert!(!matches(
+            &LeadingWildcardLike,
+            "SELECT id FROM users WHERE name LIKE $1"
+        ));
+    }
+
+    #[test]
+    fn missing_where() {
+        assert!(matches(&MissingWhere, "UPDATE users SET active = false"));
+        assert!(matches(&MissingWhere, "DELETE FROM sessions"));
+        assert!(!matches(
+            &MissingWhere,
+            "UPDATE users SET active = false WHERE id = 1"
+        ));
+        assert!(!matches(
+            &MissingWhere,
+            "DELETE FROM sessions WHERE expires_at < now()"
+        ));
+        assert!(!matches(&MissingWhere, "SELECT * FROM users"));
+    }
+
+    #[test]
+    fn long_in_list() {
+        let rule = LongInList { max: 3 };
+        assert!(matches(
+            &rule,
+            "SELECT id FROM users WHERE id IN (1, 2, 3, 4)"
+        ));
+        assert!(matches(
+            &rule,
+            "DELETE FROM users WHERE id IN ($1, $2, $3, $4, $5)"
+        ));
+        assert!(!matches(
+            &rule,
+            "SELECT id FROM users WHERE id IN (1, 2, 3)"
+        ));
+        assert!(!matches(
+            &rule,
+            "SELECT id FROM users WHERE id IN (SELECT user_id FROM orders)"
+        ));
+    }
+
+    #[test]
+    fn many_joins() {
+        let rule = ManyJoins { max: 2 };
+        assert!(matches(
+            &rule,
+            "SELECT 1 FROM a JOIN b ON a.id = b.a_id JOIN c ON b.id = c.b_id \
+             LEFT JOIN d ON c.id = d.c_id"
+        ));
+        assert!(matches(
+            &rule,
+            "SELECT 1 FROM a, b, (SELECT 1 FROM c JOIN d ON c.id = d.id) AS e"
+        ));
+        assert!(!matches(
+            &rule,
+            "SELECT 1 FROM a JOIN b ON a.id = b.a_id JOIN c ON b.id = c.b_id"
+        ));
+        assert!(!matches(&rule, "SELECT 1 FROM a"));
+    }
+
+    #[test]
+    fn linter_reports_matching_rules_in_order() {
+        let linter = QueryLinter::new();
+        let names = |sql: &str| -> Vec<String> {
+            linter
+                .lint(sql)
+                .iter()
+                .map(|rule| rule.name().to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            names("SELECT * FROM users WHERE email LIKE '%@example.com'"),
+            vec!["select-star", "leading-wildcard-like"]
+        );
+        assert!(names("SELECT id FROM users WHERE id = 1").is_empty());
+        assert!(names("not sql at all").is_empty());
+    }
+
+    #[test]
+    fn custom_rules_can_be_registered() {
+        struct NoTruncate;
+        impl QueryLintRule for NoTruncate {
+            fn name(&self) -> &str {
+                "truncate"
+            }
+            fn description(&self) -> String {
+                "TRUNCATE in application traffic".to_string()
+            }
+            fn matches(&self, statement: &Statement) -> bool {
+                matches!(statement, Statement::Truncate { .. })
+            }
+        }
+
+        let linter = QueryLinter::empty().with_rule(NoTruncate);
+        assert_eq!(linter.rules().len(), 1);
+        assert_eq!(linter.lint("TRUNCATE audit_log")[0].name(), "truncate");
+        assert!(linter.lint("DELETE FROM audit_log").is_empty());
+    }
+}
@@ -1,3 +1,16 @@
+pub mod fallback;
+pub mod lint;
+pub mod normalize;
+pub mod pooler_noise;
 pub mod query;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-pub use query::{Query, QueryType};
+pub use fallback::{classify_sql, TextNormalizer};
+pub use lint::{QueryLintRule, QueryLinter, QueryWarning};
+pub use normalize::{classify, fingerprint, normalize, normalize_many};
+pub use pooler_noise::{PoolerNoise, POOLER_MAINTENANCE};
+pub use query::{
+    fingerprint_sql, format_query_id, join_normalized_queries, query_id, Query, QueryType,
+    STATEMENT_SEPARATOR,
+};
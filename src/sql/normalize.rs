@@ -0,0 +1,175 @@
+//! Stable entry points for SQL normalization and classification
+//!
+//! These are the functions the log parsers and analyzers use, so tools that
+//! only need the SQL handling get the same query families, types, and ids as
+//! the reports.
+
+use super::{classify_sql, fingerprint_sql, join_normalized_queries, QueryType, TextNormalizer};
+use crate::{PgLogstatsError, Result};
+
+/// Normalize `sql` the way a statement's query family is normalized: literals
+/// and parameters become `?`, and the statements are joined with `; `.
+///
+/// SQL the parser rejects is normalized as text instead. Fails when `sql`
+/// holds no statement.
+pub fn normalize(sql: &str) -> Result<String> {
+    normalize_with(&TextNormalizer::new(), sql)
+}
+
+/// [`normalize`] each of `sqls`, sharing one normalizer across them
+pub fn normalize_many(sqls: &[&str]) -> Vec<Result<String>> {
+    let normalizer = TextNormalizer::new();
+    sqls.iter()
+        .map(|sql| normalize_with(&normalizer, sql))
+        .collect()
+}
+
+fn normalize_with(normalizer: &TextNormalizer, sql: &str) -> Result<String> {
+    if sql.trim().is_empty() {
+        return Err(no_statement(sql));
+    }
+    let (queries, _) = normalizer.parse(sql);
+    if queries.is_empty() {
+        return Err(no_statement(sql));
+    }
+    Ok(join_normalized_queries(&queries))
+}
+
+fn no_statement(sql: &str) -> PgLogstatsError {
+    PgLogstatsError::Parse {
+        message: "No SQL statement to normalize".to_string(),
+        context: Default::default(),
+        line_content: Some(sql.to_string()),
+    }
+}
+
+/// Type of the first statement in `sql`, from its syntax tree, or from its
+/// leading keywords when the parser rejects it
+pub fn classify(sql: &str) -> QueryType {
+    let (queries, _) = TextNormalizer::new().parse(sql);
+    queries
+        .into_iter()
+        .next()
+        .map_or_else(|| classify_sql(sql), |query| query.query_type)
+}
+
+/// Fingerprint of the normalized `sql`, the number behind a report's
+/// `query_id`; SQL without a statement fingerprints as empty text
+pub fn fingerprint(sql: &str) -> u64 {
+    fingerprint_sql(&normalize(sql).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::format_query_id;
+    use crate::{query_id, Query, TextLogParser};
+
+    const STATEMENTS: [&str; 6] = [
+        "SELECT * FROM users WHERE age > 25 AND name = 'John' AND id = $1",
+        "UPDATE   users\n  SET name='Jane'  WHERE id IN (1, 2, 3)",
+        "INSERT INTO t VALUES (1, 'a'); DELETE FROM t WHERE id = 2",
+        "NOTIFY jobs, 'order 42'",
+        "DO $$ BEGIN PERFORM pg_sleep(1.5); END $$",
+        "COPY orders FROM STDIN",
+    ];
+
+    #[test]
+    fn normalize_matches_the_query_families_of_parsed_logs() {
+        let lines: Vec<_> = STATEMENTS
+            .iter()
+            .map(|sql| {
+                format!(
+                    "2024-08-15 10:30:15.123 UTC [1] app@shop psql: LOG:  statement: {}",
+                    sql.replace('\n', " ")
+                )
+            })
+            .collect();
+        let (entries, _) = TextLogParser::new().parse_lines_with_report(&lines);
+
+        let batch = normalize_many(&STATEMENTS);
+        for ((sql, entry), batched) in STATEMENTS.iter().zip(&entries).zip(batch) {
+            let normalized = normalize(sql).unwrap();
+            assert_eq!(Some(&normalized), entry.normalized_query().as_ref());
+            assert_eq!(batched.unwrap(), normalized);
+            assert_eq!(fingerprint(sql), fingerprint_sql(&normalized));
+        }
+        assert_eq!(
+            normalize(STATEMENTS[0]).unwrap(),
+            "SELECT * FROM users WHERE age > ? AND name = ? AND id = ?"
+        );
+        assert_eq!(
+            normalize(STATEMENTS[1]).unwrap(),
+            "UPDATE users SET name = ? WHERE id IN (?)"
+        );
+        assert_eq!(
+            normalize(STATEMENTS[2]).unwrap(),
+            "INSERT INTO t VALUES (?, ?); DELETE FROM t WHERE id = ?"
+        );
+        // The text fallback uses the same placeholder as the syntax tree
+        assert_eq!(normalize(STATEMENTS[3]).unwrap(), "NOTIFY jobs, ?");
+        assert_eq!(
+            normalize(STATEMENTS[4]).unwrap(),
+            "DO $$ BEGIN PERFORM pg_sleep(?); END $$"
+        );
+        assert_eq!(
+            query_id(&normalize("SELECT 1").unwrap()),
+            query_id(&normalize("select   2").unwrap())
+        );
+
+        assert!(normalize("  ").is_err());
+        assert!(normalize_many(&["", "SELECT 1"])[0].is_err());
+        assert_eq!(fingerprint(""), fingerprint_sql(""));
+    }
+
+    #[test]
+    fn fingerprint_matches_the_report_query_id_of_a_batch() {
+        let sql = "SELECT 1; SELECT 2";
+        let lines = [
+            format!(
+                "2024-08-15 10:30:15.123 UTC [1] app@shop psql: LOG:  statement: {}",
+                sql
+            ),
+            "2024-08-15 10:30:15.200 UTC [1] app@shop psql: LOG:  duration: 2000.000 ms"
+                .to_string(),
+        ];
+        let report = crate::Pipeline::new().run_lines(&lines).unwrap();
+        let json: crate::output::schema::Report =
+            serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        let slowest = json.query_analysis.unwrap().slowest_queries.unwrap();
+
+        assert_eq!(slowest[0].query, normalize(sql).unwrap());
+        assert_eq!(slowest[0].query_id, format_query_id(fingerprint(sql)));
+    }
+
+    #[test]
+    fn classify_agrees_with_the_parser_and_the_keyword_fallback() {
+        for sql in [
+            "SELECT * FROM users",
+            "WITH t AS (SELECT 1) SELECT * FROM t",
+            "INSERT INTO users VALUES (1, 'John')",
+            "UPDATE users SET name = 'Jane'",
+            "DELETE FROM users WHERE id = 1",
+            "CREATE TABLE users (id INT)",
+            "DROP TABLE users",
+            "BEGIN",
+            "COMMIT",
+            "SET work_mem = '64MB'",
+            "SHOW work_mem",
+            "EXPLAIN SELECT 1",
+        ] {
+            let parsed = &Query::from_sql(sql).unwrap()[0];
+            assert_eq!(classify(sql), parsed.query_type, "{sql}");
+            assert_eq!(classify(sql), classify_sql(sql), "{sql}");
+        }
+        for (sql, query_type) in [
+            ("LISTEN jobs", QueryType::Other),
+            ("COPY orders FROM STDIN", QueryType::Copy),
+            ("VACUUM (VERBOSE) users", QueryType::Maintenance),
+            ("ANALYZE users", QueryType::Maintenance),
+        ] {
+            assert_eq!(classify(sql), query_type, "{sql}");
+            assert_eq!(classify(sql), classify_sql(sql), "{sql}");
+        }
+    }
+}
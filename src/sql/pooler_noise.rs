@@ -0,0 +1,147 @@
+//! Statements connection poolers send between clients
+//!
+//! PgBouncer and Odyssey reset server connections with statements such as
+//! `DISCARD ALL` and `RESET ALL`, and replay client parameters with `SET`.
+//! On a busy pool these dominate the most frequent queries. They are matched
+//! on normalized statement text, so `SET SESSION x = 1`, normalized to
+//! `SET x = ?`, matches like `SET x = 1`.
+
+use crate::{PgLogstatsError, Result};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+/// Name of the category of statements matched by [`PoolerNoise`]
+pub const POOLER_MAINTENANCE: &str = "pooler-maintenance";
+
+/// Built-in patterns, matched case-insensitively against normalized text
+const BUILTIN_PATTERNS: [&str; 9] = [
+    r"^DISCARD (ALL|PLANS|SEQUENCES|TEMP|TEMPORARY)$",
+    r"^RESET ALL$",
+    r"^RESET SESSION AUTHORIZATION$",
+    r"^SET SESSION (AUTHORIZATION|CHARACTERISTICS) ",
+    r"^SET (client_encoding|datestyle|timezone|standard_conforming_strings|application_name) = \?$",
+    r"^DEALLOCATE ALL$",
+    r"^CLOSE ALL$",
+    r"^UNLISTEN \*$",
+    r"^SELECT pg_advisory_unlock_all\(\)$",
+];
+
+/// Matcher for pooler maintenance statements: the built-in patterns and any
+/// added with [`with_pattern`](Self::with_pattern).
+///
+/// Serializes as the list of added patterns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct PoolerNoise {
+    set: RegexSet,
+    /// Patterns added to the built-in ones
+    extra: Vec<String>,
+}
+
+impl PoolerNoise {
+    /// A matcher with the built-in patterns
+    pub fn new() -> Self {
+        Self::with_patterns(Vec::new()).expect("built-in pooler noise patterns are valid")
+    }
+
+    /// Also match normalized statements matching the regex `pattern`.
+    /// Unlike the built-in patterns, it is case-sensitive and unanchored.
+    pub fn with_pattern(self, pattern: &str) -> Result<Self> {
+        let mut extra = self.extra;
+        extra.push(pattern.to_string());
+        Self::with_patterns(extra)
+    }
+
+    fn with_patterns(extra: Vec<String>) -> Result<Self> {
+        let builtin = BUILTIN_PATTERNS
+            .iter()
+            .map(|pattern| format!("(?i){}", pattern));
+        let set = RegexSet::new(builtin.chain(extra.iter().cloned())).map_err(|e| {
+            PgLogstatsError::Configuration {
+                message: format!("Invalid pooler noise pattern: {}", e),
+                field: Some("pooler_noise_pattern".to_string()),
+            }
+        })?;
+        Ok(Self { set, extra })
+    }
+
+    /// Patterns added to the built-in ones
+    pub fn extra_patterns(&self) -> &[String] {
+        &self.extra
+    }
+
+    /// Whether `normalized_sql` is a pooler maintenance statement
+    pub fn is_match(&self, normalized_sql: &str) -> bool {
+        self.set.is_match(normalized_sql.trim())
+    }
+}
+
+impl Default for PoolerNoise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<Vec<String>> for PoolerNoise {
+    type Error = PgLogstatsError;
+
+    fn try_from(extra: Vec<String>) -> Result<Self> {
+        Self::with_patterns(extra)
+    }
+}
+
+impl From<PoolerNoise> for Vec<String> {
+    fn from(noise: PoolerNoise) -> Self {
+        noise.extra
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize;
+
+    #[test]
+    fn matches_pooler_resets_after_normalization() {
+        let noise = PoolerNoise::new();
+        for sql in [
+            "DISCARD ALL",
+            "discard all",
+            "RESET ALL",
+            "SET SESSION AUTHORIZATION DEFAULT",
+            "RESET SESSION AUTHORIZATION",
+            "SET SESSION timezone TO 'UTC'",
+            "SET client_encoding = 'UTF8'",
+            "DEALLOCATE ALL",
+            "UNLISTEN *",
+            "SELECT pg_advisory_unlock_all()",
+        ] {
+            assert!(noise.is_match(&normalize(sql).unwrap()), "{sql}");
+        }
+        for sql in [
+            "SELECT * FROM discard_all",
+            "SET work_mem = '64MB'",
+            "DISCARD ALL; SELECT 1",
+            "RESET work_mem",
+        ] {
+            assert!(!noise.is_match(&normalize(sql).unwrap()), "{sql}");
+        }
+    }
+
+    #[test]
+    fn added_patterns_survive_serialization() {
+        let noise = PoolerNoise::new().with_pattern(r"^SELECT 1$").unwrap();
+        assert!(noise.is_match("SELECT 1"));
+        assert!(noise.is_match("DISCARD ALL"));
+        assert!(!noise.is_match("select 1"));
+
+        let json = serde_json::to_string(&noise).unwrap();
+        assert_eq!(json, r#"["^SELECT 1$"]"#);
+        let restored: PoolerNoise = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.extra_patterns(), ["^SELECT 1$"]);
+        assert!(restored.is_match("SELECT 1"));
+
+        assert!(PoolerNoise::new().with_pattern("(").is_err());
+        assert!(serde_json::from_str::<PoolerNoise>(r#"["("]"#).is_err());
+    }
+}
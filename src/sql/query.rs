@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use sqlparser::{
-    ast::{Expr, Value, VisitMut, VisitorMut},
+    ast::{
+        Expr, Ident, ObjectName, SelectItem, SetExpr, Statement, TableFactor, Value, VisitMut,
+        VisitorMut,
+    },
     dialect::PostgreSqlDialect,
     parser::Parser,
 };
@@ -20,10 +23,49 @@ pub enum QueryType {
     Delete,
     /// Data Definition Language (CREATE, DROP, ALTER, etc.)
     DDL,
-    /// Other queries (BEGIN, COMMIT, ROLLBACK, etc.)
+    /// COPY to or from a file or the client
+    Copy,
+    /// SET and RESET of run-time parameters
+    Set,
+    /// SHOW of run-time parameters
+    Show,
+    /// EXPLAIN of another statement
+    Explain,
+    /// Maintenance commands (VACUUM, ANALYZE, REINDEX, CLUSTER)
+    Maintenance,
+    /// Transaction control (BEGIN, COMMIT, ROLLBACK, SAVEPOINT, etc.)
+    Transaction,
+    /// Other queries (LISTEN, NOTIFY, DO, etc.)
     Other,
 }
 
+impl QueryType {
+    /// Every query type, in declaration order
+    pub const ALL: [QueryType; 12] = [
+        QueryType::Select,
+        QueryType::Insert,
+        QueryType::Update,
+        QueryType::Delete,
+        QueryType::DDL,
+        QueryType::Copy,
+        QueryType::Set,
+        QueryType::Show,
+        QueryType::Explain,
+        QueryType::Maintenance,
+        QueryType::Transaction,
+        QueryType::Other,
+    ];
+
+    /// Position of the type named `name` in [`QueryType::ALL`], or past the
+    /// end for a name that is not a query type, for ordering report rows
+    pub fn canonical_rank(name: &str) -> usize {
+        Self::ALL
+            .iter()
+            .position(|query_type| query_type.to_string() == name)
+            .unwrap_or(Self::ALL.len())
+    }
+}
+
 impl std::fmt::Display for QueryType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -32,42 +74,104 @@ impl std::fmt::Display for QueryType {
             QueryType::Update => write!(f, "UPDATE"),
             QueryType::Delete => write!(f, "DELETE"),
             QueryType::DDL => write!(f, "DDL"),
+            QueryType::Copy => write!(f, "COPY"),
+            QueryType::Set => write!(f, "SET"),
+            QueryType::Show => write!(f, "SHOW"),
+            QueryType::Explain => write!(f, "EXPLAIN"),
+            QueryType::Maintenance => write!(f, "MAINTENANCE"),
+            QueryType::Transaction => write!(f, "TRANSACTION"),
             QueryType::Other => write!(f, "OTHER"),
         }
     }
 }
 
+/// Stable 64-bit fingerprint of normalized SQL text.
+///
+/// Uses FNV-1a so ids stay the same across runs, platforms, and Rust
+/// versions. Literal values and whitespace are already removed by
+/// normalization, so executions of one query shape share a fingerprint.
+pub fn fingerprint_sql(normalized_sql: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    normalized_sql.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Format a fingerprint as the 16-digit hex `query_id` used in reports
+pub fn format_query_id(fingerprint: u64) -> String {
+    format!("{:016x}", fingerprint)
+}
+
+/// `query_id` of normalized SQL text
+pub fn query_id(normalized_sql: &str) -> String {
+    format_query_id(fingerprint_sql(normalized_sql))
+}
+
+/// Separator between the normalized statements of a multi-statement query
+pub const STATEMENT_SEPARATOR: &str = "; ";
+
+/// Normalized SQL of the statements of one log line, as a single query
+/// family; every family and fingerprint of a batch is built with it
+pub fn join_normalized_queries(queries: &[Query]) -> String {
+    queries
+        .iter()
+        .map(|query| query.normalized_query.as_str())
+        .collect::<Vec<_>>()
+        .join(STATEMENT_SEPARATOR)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Query {
     pub sql: String,
     pub query_type: QueryType,
     pub normalized_query: String,
+    /// Normalized as text because the SQL parser rejected the statement
+    #[serde(default)]
+    pub unparsed: bool,
 }
 
 impl Query {
+    /// Stable fingerprint of the normalized query, see [`fingerprint_sql`]
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint_sql(&self.normalized_query)
+    }
+
     /// Parse SQL and return a vector of Query, one for each statement
     pub fn from_sql(sql: &str) -> Result<Vec<Query>, PgLogstatsError> {
+        Self::from_sql_with(sql, true)
+    }
+
+    /// Parse SQL like [`from_sql`](Self::from_sql); with `collapse_lists`,
+    /// `IN` lists and multi-row `VALUES` of like literals normalize to a
+    /// single item, so their length does not split a query into several
+    pub fn from_sql_with(sql: &str, collapse_lists: bool) -> Result<Vec<Query>, PgLogstatsError> {
         let dialect = PostgreSqlDialect {};
         let ast = Parser::parse_sql(&dialect, sql).map_err(|e| PgLogstatsError::Parse {
             message: format!("Failed to parse SQL: {}", e),
-            line_number: None,
+            context: Default::default(),
             line_content: Some(sql.to_string()),
         })?;
 
         let mut queries = Vec::new();
         for stmt in &ast {
             let query_type = Query::query_type_from_statement(stmt);
-            let normalized_query = Query::normalize_query(std::slice::from_ref(stmt))
-                .unwrap_or_else(|_| stmt.to_string());
+            let normalized_query =
+                Query::normalize_query(std::slice::from_ref(stmt), collapse_lists)
+                    .unwrap_or_else(|_| stmt.to_string());
             queries.push(Query {
                 sql: stmt.to_string(),
                 query_type,
                 normalized_query,
+                unparsed: false,
             });
         }
         Ok(queries)
     }
 
+    /// Query type of a parsed statement; agrees with the keyword-based
+    /// [`classify_sql`](super::classify_sql)
     fn query_type_from_statement(stmt: &sqlparser::ast::Statement) -> QueryType {
         use sqlparser::ast::Statement::*;
         match stmt {
@@ -77,18 +181,60 @@ impl Query {
             Delete { .. } => QueryType::Delete,
             CreateTable { .. }
             | CreateView { .. }
+            | CreateVirtualTable { .. }
             | CreateIndex { .. }
             | CreateSchema { .. }
             | CreateDatabase { .. }
+            | CreateExtension { .. }
+            | CreateFunction { .. }
+            | CreateProcedure { .. }
+            | CreateMacro { .. }
+            | CreateStage { .. }
+            | CreateRole { .. }
+            | CreateSecret { .. }
+            | CreateSequence { .. }
+            | CreateType { .. }
             | Drop { .. }
+            | DropFunction { .. }
+            | DropSecret { .. }
             | AlterTable { .. }
-            | Truncate { .. } => QueryType::DDL,
+            | AlterIndex { .. }
+            | AlterView { .. }
+            | AlterRole { .. }
+            | Truncate { .. }
+            | Grant { .. }
+            | Revoke { .. } => QueryType::DDL,
+            Copy { .. } => QueryType::Copy,
+            SetVariable { .. }
+            | SetTimeZone { .. }
+            | SetNames { .. }
+            | SetNamesDefault { .. }
+            | SetRole { .. } => QueryType::Set,
+            ShowFunctions { .. }
+            | ShowVariable { .. }
+            | ShowStatus { .. }
+            | ShowVariables { .. }
+            | ShowCreate { .. }
+            | ShowColumns { .. }
+            | ShowTables { .. }
+            | ShowCollation { .. } => QueryType::Show,
+            Explain { .. } | ExplainTable { .. } => QueryType::Explain,
+            Analyze { .. } => QueryType::Maintenance,
+            StartTransaction { .. }
+            | SetTransaction { .. }
+            | Commit { .. }
+            | Rollback { .. }
+            | Savepoint { .. }
+            | ReleaseSavepoint { .. } => QueryType::Transaction,
             _ => QueryType::Other,
         }
     }
 
     /// Normalize SQL query using an existing AST
-    fn normalize_query(ast: &[sqlparser::ast::Statement]) -> Result<String, PgLogstatsError> {
+    fn normalize_query(
+        ast: &[sqlparser::ast::Statement],
+        collapse_lists: bool,
+    ) -> Result<String, PgLogstatsError> {
         if ast.is_empty() {
             return Ok("".to_string());
         }
@@ -96,28 +242,80 @@ impl Query {
         // Clone AST to mutate
         let mut ast = ast.to_owned();
 
-        let mut normalizer = LiteralNormalizer;
+        let mut normalizer = LiteralNormalizer { collapse_lists };
         for stmt in &mut ast {
             let _ = stmt.visit(&mut normalizer);
+            let _ = stmt.visit(&mut IdentifierNormalizer);
         }
 
         let normalized_sql = ast
             .iter()
             .map(|stmt| stmt.to_string())
             .collect::<Vec<_>>()
-            .join("; ");
+            .join(STATEMENT_SEPARATOR);
 
         Ok(normalized_sql)
     }
 }
 
 /// Visitor that replaces literal values with placeholders
-struct LiteralNormalizer;
+struct LiteralNormalizer {
+    /// Keep one item of `IN` lists and `VALUES` rows made only of like literals
+    collapse_lists: bool,
+}
+
+/// Kind of literal or parameter an expression is, if it is one; a list of a
+/// single kind can be collapsed
+fn literal_kind(expr: &Expr) -> Option<std::mem::Discriminant<Value>> {
+    match expr {
+        Expr::Value(
+            value @ (Value::Number(_, _)
+            | Value::SingleQuotedString(_)
+            | Value::DoubleQuotedString(_)
+            | Value::Boolean(_)
+            | Value::Placeholder(_)),
+        ) => Some(std::mem::discriminant(value)),
+        _ => None,
+    }
+}
+
+/// Whether every expression is a literal of the same kind
+fn is_homogeneous<'a>(exprs: impl IntoIterator<Item = &'a Expr>) -> bool {
+    let mut kinds = exprs.into_iter().map(literal_kind);
+    match kinds.next() {
+        Some(Some(first)) => kinds.all(|kind| kind == Some(first)),
+        _ => false,
+    }
+}
 
 impl VisitorMut for LiteralNormalizer {
     type Break = ();
 
-    fn pre_visit_expr(&mut self, _expr: &mut Expr) -> std::ops::ControlFlow<Self::Break> {
+    fn pre_visit_query(
+        &mut self,
+        query: &mut sqlparser::ast::Query,
+    ) -> std::ops::ControlFlow<Self::Break> {
+        // Collapse before literals become placeholders and look alike
+        if let SetExpr::Values(values) = query.body.as_mut() {
+            let width = values.rows.first().map_or(0, Vec::len);
+            let collapsible = self.collapse_lists
+                && values.rows.len() > 1
+                && values.rows.iter().all(|row| row.len() == width)
+                && (0..width)
+                    .all(|column| is_homogeneous(values.rows.iter().map(|row| &row[column])));
+            if collapsible {
+                values.rows.truncate(1);
+            }
+        }
+        std::ops::ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> std::ops::ControlFlow<Self::Break> {
+        if let Expr::InList { list, .. } = expr {
+            if self.collapse_lists && is_homogeneous(list.iter()) {
+                list.truncate(1);
+            }
+        }
         // Always continue traversal to visit nested expressions
         std::ops::ControlFlow::Continue(())
     }
@@ -146,6 +344,226 @@ impl VisitorMut for LiteralNormalizer {
     }
 }
 
+/// Keywords PostgreSQL reserves, which must stay quoted when used as
+/// identifiers; sorted for binary search
+const RESERVED_KEYWORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "binary",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "false",
+    "fetch",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "intersect",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "natural",
+    "not",
+    "notnull",
+    "null",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "outer",
+    "overlaps",
+    "placing",
+    "primary",
+    "references",
+    "returning",
+    "right",
+    "select",
+    "session_user",
+    "similar",
+    "some",
+    "symmetric",
+    "system_user",
+    "table",
+    "tablesample",
+    "then",
+    "to",
+    "trailing",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+/// Whether `value` reads the same as an unquoted identifier: lowercase
+/// ASCII letters, digits, `_`, and `$`, not starting with a digit or `$`,
+/// and not a reserved keyword
+fn is_plain_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_lowercase() || first == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '$')
+        && RESERVED_KEYWORDS.binary_search(&value).is_err()
+}
+
+/// Canonical spelling of an identifier, as PostgreSQL resolves it: unquoted
+/// names fold to lowercase, and quotes are dropped where the name would
+/// fold to itself without them
+fn normalize_ident(ident: &mut Ident) {
+    match ident.quote_style {
+        None => ident.value.make_ascii_lowercase(),
+        Some('"') if is_plain_identifier(&ident.value) => ident.quote_style = None,
+        Some(_) => {}
+    }
+}
+
+fn normalize_object_name(name: &mut ObjectName) {
+    name.0.iter_mut().for_each(normalize_ident);
+}
+
+/// Visitor that spells table, column, alias, and function names the way
+/// PostgreSQL resolves them, so `Users`, `users`, and `"users"` normalize
+/// alike while `"MyTable"` stays distinct from `mytable`
+struct IdentifierNormalizer;
+
+impl VisitorMut for IdentifierNormalizer {
+    type Break = ();
+
+    fn pre_visit_query(
+        &mut self,
+        query: &mut sqlparser::ast::Query,
+    ) -> std::ops::ControlFlow<Self::Break> {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                normalize_ident(&mut cte.alias.name);
+                cte.alias.columns.iter_mut().for_each(normalize_ident);
+            }
+        }
+        if let SetExpr::Select(select) = query.body.as_mut() {
+            for item in &mut select.projection {
+                match item {
+                    SelectItem::ExprWithAlias { alias, .. } => normalize_ident(alias),
+                    SelectItem::QualifiedWildcard(name, _) => normalize_object_name(name),
+                    SelectItem::UnnamedExpr(_) | SelectItem::Wildcard(_) => {}
+                }
+            }
+        }
+        std::ops::ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(
+        &mut self,
+        relation: &mut ObjectName,
+    ) -> std::ops::ControlFlow<Self::Break> {
+        normalize_object_name(relation);
+        std::ops::ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> std::ops::ControlFlow<Self::Break> {
+        if let TableFactor::Table {
+            alias: Some(alias), ..
+        }
+        | TableFactor::Derived {
+            alias: Some(alias), ..
+        } = table_factor
+        {
+            normalize_ident(&mut alias.name);
+            alias.columns.iter_mut().for_each(normalize_ident);
+        }
+        std::ops::ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> std::ops::ControlFlow<Self::Break> {
+        match expr {
+            Expr::Identifier(ident) => normalize_ident(ident),
+            Expr::CompoundIdentifier(idents) => idents.iter_mut().for_each(normalize_ident),
+            Expr::Function(function) => normalize_object_name(&mut function.name),
+            _ => {}
+        }
+        std::ops::ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(
+        &mut self,
+        statement: &mut Statement,
+    ) -> std::ops::ControlFlow<Self::Break> {
+        match statement {
+            Statement::Insert(insert) => {
+                insert.columns.iter_mut().for_each(normalize_ident);
+                if let Some(alias) = &mut insert.table_alias {
+                    normalize_ident(alias);
+                }
+            }
+            Statement::Update { assignments, .. } => {
+                for assignment in assignments {
+                    assignment.id.iter_mut().for_each(normalize_ident);
+                }
+            }
+            _ => {}
+        }
+        std::ops::ControlFlow::Continue(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,6 +581,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fingerprint_ignores_literals_and_whitespace() {
+        let fingerprint = |sql: &str| Query::from_sql(sql).unwrap()[0].fingerprint();
+
+        let id = fingerprint("SELECT * FROM users WHERE id = 1");
+        assert_eq!(id, fingerprint("SELECT   *  FROM users\n  WHERE id=42"));
+        assert_eq!(id, fingerprint("SELECT * FROM users WHERE id = $1"));
+        assert_ne!(id, fingerprint("SELECT * FROM users WHERE name = 'x'"));
+        assert_eq!(
+            format_query_id(id),
+            query_id("SELECT * FROM users WHERE id = ?")
+        );
+        assert_eq!(format_query_id(id).len(), 16);
+        // FNV-1a reference value, so ids stay stable between releases
+        assert_eq!(format_query_id(fingerprint_sql("")), "cbf29ce484222325");
+    }
+
     #[test]
     fn test_parameterized_normalization() {
         let cases = vec![
@@ -184,7 +619,7 @@ mod tests {
             ),
             (
                 "SELECT * FROM users WHERE (age > 25 AND name = 'John') OR id IN (1, 2, 3)",
-                "SELECT * FROM users WHERE (age > ? AND name = ?) OR id IN (?, ?, ?)",
+                "SELECT * FROM users WHERE (age > ? AND name = ?) OR id IN (?)",
             ),
             (
                 "INSERT INTO users (name, age) VALUES ('Alice', 30)",
@@ -204,7 +639,7 @@ mod tests {
             ),
             (
                 "SELECT * FROM products WHERE id IN ($1, $2, $3)",
-                "SELECT * FROM products WHERE id IN (?, ?, ?)",
+                "SELECT * FROM products WHERE id IN (?)",
             ),
             (
                 "SELECT   *   FROM    users   WHERE   id=1",
@@ -228,4 +663,86 @@ mod tests {
             run_normalization_test(original, expected);
         }
     }
+
+    #[test]
+    fn test_in_lists_of_any_length_normalize_identically() {
+        let in_list = |len: usize| {
+            let ids: Vec<String> = (1..=len).map(|id| id.to_string()).collect();
+            format!("SELECT * FROM users WHERE id IN ({})", ids.join(", "))
+        };
+        let normalized: Vec<Query> = [1, 3, 500]
+            .into_iter()
+            .map(|len| Query::from_sql(&in_list(len)).unwrap().remove(0))
+            .collect();
+
+        for query in &normalized {
+            assert_eq!(
+                query.normalized_query,
+                "SELECT * FROM users WHERE id IN (?)"
+            );
+            assert_eq!(query.fingerprint(), normalized[0].fingerprint());
+        }
+
+        let kept = Query::from_sql_with(&in_list(3), false).unwrap();
+        assert_eq!(
+            kept[0].normalized_query,
+            "SELECT * FROM users WHERE id IN (?, ?, ?)"
+        );
+    }
+
+    #[test]
+    fn test_mixed_in_lists_are_left_alone() {
+        run_normalization_test(
+            "SELECT * FROM users WHERE id IN (1, 'two', 3)",
+            "SELECT * FROM users WHERE id IN (?, ?, ?)",
+        );
+        run_normalization_test(
+            "SELECT * FROM users WHERE id IN (1, $1, owner_id)",
+            "SELECT * FROM users WHERE id IN (?, ?, owner_id)",
+        );
+    }
+
+    #[test]
+    fn test_values_rows_collapse_when_columns_match() {
+        run_normalization_test(
+            "INSERT INTO users (name, age) VALUES ('Alice', 30), ('Bob', 41), ('Carol', 25)",
+            "INSERT INTO users (name, age) VALUES (?, ?)",
+        );
+        run_normalization_test(
+            "INSERT INTO users (name, age) VALUES ('Alice', 30), ('Bob', NULL)",
+            "INSERT INTO users (name, age) VALUES (?, ?), (?, ?)",
+        );
+    }
+
+    #[test]
+    fn test_identifier_case_and_quoting_share_a_fingerprint() {
+        let fingerprint = |sql: &str| Query::from_sql(sql).unwrap()[0].fingerprint();
+
+        let id = fingerprint("SELECT * FROM users");
+        assert_eq!(id, fingerprint("SELECT * FROM Users"));
+        assert_eq!(id, fingerprint("select * from users"));
+        assert_eq!(id, fingerprint("SELECT * FROM \"users\""));
+        run_normalization_test(
+            "select U.\"Id\", Count(*) AS Total FROM \"public\".\"users\" u GROUP BY \"u\".ID",
+            "SELECT u.\"Id\", count(*) AS total FROM public.users AS u GROUP BY u.id",
+        );
+    }
+
+    #[test]
+    fn test_case_sensitive_identifiers_stay_quoted() {
+        let fingerprint = |sql: &str| Query::from_sql(sql).unwrap()[0].fingerprint();
+
+        assert_ne!(
+            fingerprint("SELECT * FROM \"MyTable\""),
+            fingerprint("SELECT * FROM mytable")
+        );
+        assert_eq!(
+            fingerprint("SELECT * FROM MyTable"),
+            fingerprint("SELECT * FROM mytable")
+        );
+        run_normalization_test(
+            "SELECT \"order\", \"first name\" FROM \"MyTable\"",
+            "SELECT \"order\", \"first name\" FROM \"MyTable\"",
+        );
+    }
 }
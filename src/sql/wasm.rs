@@ -0,0 +1,75 @@
+//! WebAssembly bindings of the SQL entry points, behind the `wasm` feature
+//!
+//! A log viewer running in the browser calls these to normalize, classify,
+//! and fingerprint SQL exactly as the reports do, so its numbers match.
+//! Nothing here touches the filesystem or timestamps. `make wasm-check`
+//! checks the build for `wasm32-unknown-unknown`.
+
+use wasm_bindgen::prelude::*;
+
+/// [`normalize`](super::normalize) for JavaScript; throws when `sql` holds
+/// no statement
+#[wasm_bindgen]
+pub fn normalize(sql: &str) -> Result<String, JsError> {
+    super::normalize(sql).map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// [`classify`](super::classify) for JavaScript, as the type's name, e.g.
+/// `SELECT`
+#[wasm_bindgen]
+pub fn classify(sql: &str) -> String {
+    super::classify(sql).to_string()
+}
+
+/// [`fingerprint`](super::fingerprint) for JavaScript, where it is a
+/// `BigInt`
+#[wasm_bindgen]
+pub fn fingerprint(sql: &str) -> u64 {
+    super::fingerprint(sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQL: [&str; 5] = [
+        "SELECT * FROM users WHERE id = 42",
+        "select * from users where id = 7",
+        "INSERT INTO events (kind) VALUES ('login'), ('logout')",
+        "BEGIN; UPDATE accounts SET balance = 0 WHERE id = 1; COMMIT",
+        "VACUUM (VERBOSE) orders",
+    ];
+
+    #[test]
+    fn test_bindings_match_the_native_functions() {
+        for sql in SQL {
+            assert_eq!(
+                normalize(sql).ok(),
+                crate::normalize(sql).ok(),
+                "normalize {}",
+                sql
+            );
+            assert_eq!(
+                classify(sql),
+                crate::classify(sql).to_string(),
+                "classify {}",
+                sql
+            );
+            assert_eq!(
+                fingerprint(sql),
+                crate::fingerprint(sql),
+                "fingerprint {}",
+                sql
+            );
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_literals() {
+        assert_eq!(fingerprint(SQL[0]), fingerprint(SQL[1]));
+        assert_eq!(
+            crate::sql::format_query_id(fingerprint(SQL[0])),
+            crate::query_id(&crate::normalize(SQL[0]).unwrap())
+        );
+    }
+}
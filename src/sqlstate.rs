@@ -0,0 +1,102 @@
+//! SQLSTATE error code classes
+//!
+//! The first two characters of a SQLSTATE code name its class, e.g. `23505`
+//! (unique_violation) belongs to class `23`, Integrity Constraint Violation.
+//! Class names follow Appendix A of the PostgreSQL documentation.
+
+/// Grouping key for errors logged without a SQLSTATE code
+pub const UNKNOWN_SQLSTATE: &str = "unknown";
+
+const SQLSTATE_CLASSES: &[(&str, &str)] = &[
+    ("00", "Successful Completion"),
+    ("01", "Warning"),
+    ("02", "No Data"),
+    ("03", "SQL Statement Not Yet Complete"),
+    ("08", "Connection Exception"),
+    ("09", "Triggered Action Exception"),
+    ("0A", "Feature Not Supported"),
+    ("0B", "Invalid Transaction Initiation"),
+    ("0F", "Locator Exception"),
+    ("0L", "Invalid Grantor"),
+    ("0P", "Invalid Role Specification"),
+    ("0Z", "Diagnostics Exception"),
+    ("20", "Case Not Found"),
+    ("21", "Cardinality Violation"),
+    ("22", "Data Exception"),
+    ("23", "Integrity Constraint Violation"),
+    ("24", "Invalid Cursor State"),
+    ("25", "Invalid Transaction State"),
+    ("26", "Invalid SQL Statement Name"),
+    ("27", "Triggered Data Change Violation"),
+    ("28", "Invalid Authorization Specification"),
+    ("2B", "Dependent Privilege Descriptors Still Exist"),
+    ("2D", "Invalid Transaction Termination"),
+    ("2F", "SQL Routine Exception"),
+    ("34", "Invalid Cursor Name"),
+    ("38", "External Routine Exception"),
+    ("39", "External Routine Invocation Exception"),
+    ("3B", "Savepoint Exception"),
+    ("3D", "Invalid Catalog Name"),
+    ("3F", "Invalid Schema Name"),
+    ("40", "Transaction Rollback"),
+    ("42", "Syntax Error or Access Rule Violation"),
+    ("44", "WITH CHECK OPTION Violation"),
+    ("53", "Insufficient Resources"),
+    ("54", "Program Limit Exceeded"),
+    ("55", "Object Not In Prerequisite State"),
+    ("57", "Operator Intervention"),
+    ("58", "System Error"),
+    ("72", "Snapshot Failure"),
+    ("F0", "Configuration File Error"),
+    ("HV", "Foreign Data Wrapper Error"),
+    ("P0", "PL/pgSQL Error"),
+    ("XX", "Internal Error"),
+];
+
+/// Human-readable name for a SQLSTATE class such as `23` or a full code such
+/// as `23505`. Unrecognized classes are reported as "Unknown".
+pub fn sqlstate_class_name(code: &str) -> &'static str {
+    let class = code.get(..2).unwrap_or(code);
+    SQLSTATE_CLASSES
+        .iter()
+        .find(|(known, _)| *known == class)
+        .map(|(_, name)| *name)
+        .unwrap_or("Unknown")
+}
+
+/// Whether `code` looks like a SQLSTATE code: five uppercase alphanumerics
+/// with at least one digit, which keeps words like `ERROR` out.
+pub(crate) fn is_sqlstate(code: &str) -> bool {
+    code.len() == 5
+        && code
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+        && code.chars().any(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_known_classes() {
+        assert_eq!(
+            sqlstate_class_name("23505"),
+            "Integrity Constraint Violation"
+        );
+        assert_eq!(sqlstate_class_name("40P01"), "Transaction Rollback");
+        assert_eq!(sqlstate_class_name("57"), "Operator Intervention");
+        assert_eq!(sqlstate_class_name("ZZ000"), "Unknown");
+        assert_eq!(sqlstate_class_name(UNKNOWN_SQLSTATE), "Unknown");
+    }
+
+    #[test]
+    fn recognizes_sqlstate_codes() {
+        assert!(is_sqlstate("23505"));
+        assert!(is_sqlstate("40P01"));
+        assert!(is_sqlstate("HV000"));
+        assert!(!is_sqlstate("ERROR"));
+        assert!(!is_sqlstate("2350"));
+        assert!(!is_sqlstate("23505x"));
+    }
+}
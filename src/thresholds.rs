@@ -0,0 +1,184 @@
+//! Pass/fail conditions for CI runs
+//!
+//! [`Thresholds`] checks an [`AnalysisResult`] against limits on error count,
+//! slow-query count, and p95 duration. The CLI exits with
+//! [`THRESHOLD_EXIT_CODE`] when any limit is exceeded, after writing its
+//! reports, so a CI job can fail the build and still upload them.
+
+use crate::AnalysisResult;
+use std::fmt;
+
+/// Process exit code used when a threshold is exceeded
+pub const THRESHOLD_EXIT_CODE: i32 = 3;
+
+/// Limits a report must stay within; a limit that is `None` is not checked
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Thresholds {
+    max_errors: Option<u64>,
+    max_slow_queries: Option<u64>,
+    max_p95_ms: Option<f64>,
+}
+
+/// One exceeded limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdBreach {
+    /// `errors`, `slow_queries`, or `p95_ms`
+    pub metric: &'static str,
+    /// Value in the analysis
+    pub actual: f64,
+    /// Largest value allowed
+    pub limit: f64,
+}
+
+impl fmt::Display for ThresholdBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}>{}", self.metric, self.actual, self.limit)
+    }
+}
+
+impl Thresholds {
+    /// Thresholds that check nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail when there are more than `max` error entries
+    pub fn with_max_errors(mut self, max: Option<u64>) -> Self {
+        self.max_errors = max;
+        self
+    }
+
+    /// Fail when more than `max` queries are slower than the query
+    /// analyzer's slow-query threshold
+    pub fn with_max_slow_queries(mut self, max: Option<u64>) -> Self {
+        self.max_slow_queries = max;
+        self
+    }
+
+    /// Fail when the p95 query duration is above `max_ms` milliseconds
+    pub fn with_max_p95_ms(mut self, max_ms: Option<f64>) -> Self {
+        self.max_p95_ms = max_ms;
+        self
+    }
+
+    /// Whether any limit is set
+    pub fn is_empty(&self) -> bool {
+        self.max_errors.is_none() && self.max_slow_queries.is_none() && self.max_p95_ms.is_none()
+    }
+
+    /// Limits the analysis exceeds, in the order errors, slow queries, p95
+    pub fn check(&self, analysis: &AnalysisResult) -> Vec<ThresholdBreach> {
+        [
+            (
+                "errors",
+                analysis.error_count as f64,
+                self.max_errors.map(|max| max as f64),
+            ),
+            (
+                "slow_queries",
+                analysis.slow_query_count as f64,
+                self.max_slow_queries.map(|max| max as f64),
+            ),
+            ("p95_ms", analysis.p95_duration, self.max_p95_ms),
+        ]
+        .into_iter()
+        .filter_map(|(metric, actual, limit)| {
+            let limit = limit?;
+            (actual > limit).then_some(ThresholdBreach {
+                metric,
+                actual,
+                limit,
+            })
+        })
+        .collect()
+    }
+
+    /// `0` when the analysis is within every limit, otherwise
+    /// [`THRESHOLD_EXIT_CODE`]
+    pub fn exit_code(&self, analysis: &AnalysisResult) -> i32 {
+        if self.check(analysis).is_empty() {
+            0
+        } else {
+            THRESHOLD_EXIT_CODE
+        }
+    }
+}
+
+/// One line naming every exceeded limit, e.g.
+/// `threshold exceeded: errors=5>3 p95_ms=1250.5>1000`
+pub fn breach_summary(breaches: &[ThresholdBreach]) -> String {
+    let breaches: Vec<String> = breaches.iter().map(ToString::to_string).collect();
+    format!("threshold exceeded: {}", breaches.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analysis() -> AnalysisResult {
+        AnalysisResult {
+            error_count: 4,
+            slow_query_count: 2,
+            p95_duration: 850.0,
+            ..AnalysisResult::new()
+        }
+    }
+
+    #[test]
+    fn passes_when_within_every_limit() {
+        let thresholds = Thresholds::new()
+            .with_max_errors(Some(4))
+            .with_max_slow_queries(Some(2))
+            .with_max_p95_ms(Some(850.0));
+
+        assert!(thresholds.check(&analysis()).is_empty());
+        assert_eq!(thresholds.exit_code(&analysis()), 0);
+        assert_eq!(Thresholds::new().exit_code(&analysis()), 0);
+    }
+
+    #[test]
+    fn trips_on_errors() {
+        let thresholds = Thresholds::new().with_max_errors(Some(3));
+
+        assert_eq!(
+            thresholds.check(&analysis()),
+            vec![ThresholdBreach {
+                metric: "errors",
+                actual: 4.0,
+                limit: 3.0,
+            }]
+        );
+        assert_eq!(thresholds.exit_code(&analysis()), THRESHOLD_EXIT_CODE);
+    }
+
+    #[test]
+    fn trips_on_slow_queries() {
+        let thresholds = Thresholds::new().with_max_slow_queries(Some(0));
+
+        let breaches = thresholds.check(&analysis());
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].metric, "slow_queries");
+        assert_eq!(thresholds.exit_code(&analysis()), THRESHOLD_EXIT_CODE);
+    }
+
+    #[test]
+    fn trips_on_p95() {
+        let thresholds = Thresholds::new().with_max_p95_ms(Some(500.0));
+
+        let breaches = thresholds.check(&analysis());
+        assert_eq!(breaches[0].to_string(), "p95_ms=850>500");
+        assert_eq!(thresholds.exit_code(&analysis()), THRESHOLD_EXIT_CODE);
+    }
+
+    #[test]
+    fn summary_lists_every_breach_on_one_line() {
+        let thresholds = Thresholds::new()
+            .with_max_errors(Some(1))
+            .with_max_p95_ms(Some(500.5));
+
+        assert_eq!(
+            breach_summary(&thresholds.check(&analysis())),
+            "threshold exceeded: errors=4>1 p95_ms=850>500.5"
+        );
+    }
+}
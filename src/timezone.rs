@@ -0,0 +1,75 @@
+//! Timezones reports are shown and bucketed in
+//!
+//! Timestamps are kept in UTC throughout. A [`Tz`] given with
+//! `--display-timezone` only changes how text reports print them, and, with
+//! `--bucket-in-display-tz`, which hour of day and weekday the hourly and
+//! daily analytics put each entry in. [`parse_timezone`] accepts IANA names
+//! such as `Europe/Berlin`, so daylight saving time is applied per entry.
+
+use crate::{PgLogstatsError, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+pub use chrono_tz::Tz;
+
+/// Names suggested when a timezone is not recognized
+const EXAMPLE_TIMEZONES: [&str; 4] = ["UTC", "Europe/Berlin", "America/New_York", "Asia/Kolkata"];
+
+/// Parse an IANA timezone name such as `Europe/Berlin`
+pub fn parse_timezone(s: &str) -> Result<Tz> {
+    s.trim()
+        .parse::<Tz>()
+        .map_err(|_| PgLogstatsError::Configuration {
+            message: format!(
+                "Unknown timezone '{}': expected an IANA name such as {}",
+                s.trim(),
+                EXAMPLE_TIMEZONES.join(", ")
+            ),
+            field: Some("timezone".to_string()),
+        })
+}
+
+/// Hour of day of `timestamp` in `timezone`, or in UTC without one
+pub fn hour_in(timestamp: DateTime<Utc>, timezone: Option<Tz>) -> u32 {
+    match timezone {
+        Some(timezone) => timestamp.with_timezone(&timezone).hour(),
+        None => timestamp.hour(),
+    }
+}
+
+/// Weekday of `timestamp` in `timezone`, counted from Monday as 0, or in UTC
+/// without one
+pub fn weekday_in(timestamp: DateTime<Utc>, timezone: Option<Tz>) -> u32 {
+    match timezone {
+        Some(timezone) => timestamp.with_timezone(&timezone).weekday(),
+        None => timestamp.weekday(),
+    }
+    .num_days_from_monday()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn buckets_follow_daylight_saving_time() {
+        let berlin = parse_timezone("Europe/Berlin").unwrap();
+        let winter = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2024, 7, 15, 10, 0, 0).unwrap();
+
+        assert_eq!(hour_in(winter, None), 10);
+        assert_eq!(hour_in(winter, Some(berlin)), 11);
+        assert_eq!(hour_in(summer, Some(berlin)), 12);
+
+        let sunday_night = Utc.with_ymd_and_hms(2024, 7, 14, 23, 0, 0).unwrap();
+        assert_eq!(weekday_in(sunday_night, None), 6);
+        assert_eq!(weekday_in(sunday_night, Some(berlin)), 0);
+    }
+
+    #[test]
+    fn unknown_names_list_examples() {
+        let error = parse_timezone("Berlin").unwrap_err().to_string();
+        assert!(error.contains("Unknown timezone 'Berlin'"), "{error}");
+        assert!(error.contains("Europe/Berlin"), "{error}");
+        assert!(parse_timezone("+02:00").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Interactive terminal view of a summary, in the manner of `top`
+//!
+//! [`QueryTable`] turns an [`AnalysisResult`] into what the view shows: a
+//! header of summary numbers, one row per normalized query sorted by a
+//! [`QuerySort`], and the examples of the selected row. Drawing it and
+//! reading keys needs the optional `tui` feature; without it, [`run`]
+//! returns an error.
+
+#[cfg(feature = "tui")]
+mod render;
+
+use crate::{AnalysisResult, QueryExample, QuerySort, Redact, Result};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Summary numbers shown above the query table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SummaryHeader {
+    pub total_queries: u64,
+    /// Rows of the query table
+    pub distinct_queries: usize,
+    /// Total duration in milliseconds
+    pub total_duration: f64,
+    /// 95th percentile duration in milliseconds
+    pub p95_duration: f64,
+    pub slow_query_count: u64,
+    pub error_count: u64,
+    pub connection_count: u64,
+    /// First and last entry analyzed
+    pub time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+/// A normalized query with its statistics and slowest executions
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRow {
+    pub query: String,
+    pub count: u64,
+    /// Total duration in milliseconds
+    pub total_duration: f64,
+    /// Average duration in milliseconds
+    pub average_duration: f64,
+    /// 95th percentile duration in milliseconds
+    pub p95_duration: f64,
+    /// Slowest executions, slowest first
+    pub examples: Vec<QueryExample>,
+}
+
+/// Rows, order, and selection of the interactive query table
+#[derive(Debug, Clone)]
+pub struct QueryTable {
+    header: SummaryHeader,
+    rows: Vec<QueryRow>,
+    sort: QuerySort,
+    selected: usize,
+    redactor: Option<Arc<dyn Redact>>,
+}
+
+impl QueryTable {
+    /// Table of the queries of `analysis`, ordered by `sort`, with the first
+    /// row selected
+    pub fn new(analysis: &AnalysisResult, sort: QuerySort) -> Self {
+        Self::with_redactor(analysis, sort, None)
+    }
+
+    /// Like [`new`](Self::new), with the query text and examples redacted by
+    /// `redactor`
+    pub fn with_redactor(
+        analysis: &AnalysisResult,
+        sort: QuerySort,
+        redactor: Option<Arc<dyn Redact>>,
+    ) -> Self {
+        let mut table = Self {
+            header: SummaryHeader::default(),
+            rows: Vec::new(),
+            sort,
+            selected: 0,
+            redactor,
+        };
+        table.update(analysis);
+        table
+    }
+
+    /// Replace the rows with those of a newer analysis, keeping the order
+    /// and, when it is still listed, the selected query
+    pub fn update(&mut self, analysis: &AnalysisResult) {
+        let selected = self.selected().map(|row| row.query.clone());
+        let redact = |text: &str| match &self.redactor {
+            Some(redactor) => redactor.redact(text).into_owned(),
+            None => text.to_string(),
+        };
+        self.rows = analysis
+            .query_stats
+            .iter()
+            .map(|(query, stats)| QueryRow {
+                query: redact(query),
+                count: stats.count,
+                total_duration: stats.total_duration,
+                average_duration: stats.average_duration,
+                p95_duration: stats.p95_duration,
+                examples: stats
+                    .examples
+                    .iter()
+                    .map(|example| QueryExample {
+                        sql: redact(&example.sql),
+                        parameters: example.parameters.as_deref().map(redact),
+                        ..example.clone()
+                    })
+                    .collect(),
+            })
+            .collect();
+        self.header = SummaryHeader {
+            total_queries: analysis.total_queries,
+            distinct_queries: self.rows.len(),
+            total_duration: analysis.total_duration,
+            p95_duration: analysis.p95_duration,
+            slow_query_count: analysis.slow_query_count,
+            error_count: analysis.error_count,
+            connection_count: analysis.connection_count,
+            time_range: analysis
+                .time_range
+                .as_ref()
+                .map(|range| (range.start, range.end)),
+        };
+        self.sort_rows(selected);
+    }
+
+    /// Order the rows by `sort`, keeping the selected query selected
+    pub fn sort_by(&mut self, sort: QuerySort) {
+        let selected = self.selected().map(|row| row.query.clone());
+        self.sort = sort;
+        self.sort_rows(selected);
+    }
+
+    fn sort_rows(&mut self, selected: Option<String>) {
+        let sort = self.sort;
+        self.rows.sort_by(|a, b| {
+            sort_key(sort, b)
+                .total_cmp(&sort_key(sort, a))
+                .then_with(|| a.query.cmp(&b.query))
+        });
+        self.selected = selected
+            .and_then(|query| self.rows.iter().position(|row| row.query == query))
+            .unwrap_or(0);
+    }
+
+    /// Select the row below the selected one, if any
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.rows.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Select the row above the selected one, if any
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn header(&self) -> &SummaryHeader {
+        &self.header
+    }
+
+    pub fn rows(&self) -> &[QueryRow] {
+        &self.rows
+    }
+
+    pub fn sort(&self) -> QuerySort {
+        self.sort
+    }
+
+    /// Index of the selected row
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// The selected row; `None` when there are no queries
+    pub fn selected(&self) -> Option<&QueryRow> {
+        self.rows.get(self.selected)
+    }
+}
+
+/// The metric of `row` that `sort` ranks by, as [`QuerySort::key`] does for
+/// query stats
+fn sort_key(sort: QuerySort, row: &QueryRow) -> f64 {
+    match sort {
+        QuerySort::Count => row.count as f64,
+        QuerySort::TotalTime => row.total_duration,
+        QuerySort::AvgTime => row.average_duration,
+        QuerySort::P95 => row.p95_duration,
+    }
+}
+
+/// Show the [`QueryTable`] of `analyze` in the terminal until the user
+/// quits; with a `refresh` interval, `analyze` is called again that often.
+///
+/// Fails before analyzing anything when stdout is not a terminal, or without
+/// the `tui` feature.
+pub fn run(
+    sort: QuerySort,
+    redactor: Option<Arc<dyn Redact>>,
+    refresh: Option<Duration>,
+    analyze: impl FnMut() -> Result<AnalysisResult>,
+) -> Result<()> {
+    #[cfg(feature = "tui")]
+    return render::run(sort, redactor, refresh, analyze);
+
+    #[cfg(not(feature = "tui"))]
+    {
+        let _ = (sort, redactor, refresh, analyze);
+        Err(crate::PgLogstatsError::Configuration {
+            message: "--tui requires building pg-logstats with `--features tui`".to_string(),
+            field: Some("tui".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{QueryStats, Redactor};
+    use chrono::TimeZone;
+
+    fn stats(count: u64, total_duration: f64, p95_duration: f64) -> QueryStats {
+        QueryStats {
+            count,
+            total_duration,
+            average_duration: total_duration / count as f64,
+            p95_duration,
+            examples: Vec::new(),
+        }
+    }
+
+    fn analysis() -> AnalysisResult {
+        let mut analysis = AnalysisResult {
+            total_queries: 111,
+            error_count: 2,
+            ..AnalysisResult::default()
+        };
+        let mut slow = stats(1, 900.0, 900.0);
+        slow.examples.push(QueryExample {
+            sql: "SELECT * FROM users WHERE email = 'a@example.com'".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap(),
+            duration: 900.0,
+            user: Some("app".to_string()),
+            database: Some("shop".to_string()),
+            parameters: None,
+        });
+        analysis.query_stats.extend([
+            ("SELECT * FROM users WHERE email = ?".to_string(), slow),
+            ("SELECT 1".to_string(), stats(100, 50.0, 1.0)),
+            (
+                "UPDATE orders SET paid = ?".to_string(),
+                stats(10, 200.0, 40.0),
+            ),
+        ]);
+        analysis
+    }
+
+    fn queries(table: &QueryTable) -> Vec<&str> {
+        table.rows().iter().map(|row| row.query.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_rows_and_keeps_the_selected_query() {
+        let mut table = QueryTable::new(&analysis(), QuerySort::Count);
+        assert_eq!(
+            queries(&table),
+            [
+                "SELECT 1",
+                "UPDATE orders SET paid = ?",
+                "SELECT * FROM users WHERE email = ?"
+            ]
+        );
+        assert_eq!(table.header().total_queries, 111);
+        assert_eq!(table.header().distinct_queries, 3);
+
+        table.select_next();
+        table.sort_by(QuerySort::TotalTime);
+        assert_eq!(queries(&table)[0], "SELECT * FROM users WHERE email = ?");
+        assert_eq!(
+            table.selected().unwrap().query,
+            "UPDATE orders SET paid = ?"
+        );
+
+        table.sort_by(QuerySort::P95);
+        assert_eq!(queries(&table)[2], "SELECT 1");
+        table.select_next();
+        table.select_next();
+        assert_eq!(table.selected_index(), 2);
+    }
+
+    #[test]
+    fn refresh_keeps_the_order_and_selection() {
+        let mut table = QueryTable::new(&analysis(), QuerySort::Count);
+        table.select_next();
+
+        let mut newer = analysis();
+        newer.query_stats.remove("SELECT 1");
+        newer.total_queries = 11;
+        table.update(&newer);
+
+        assert_eq!(table.rows().len(), 2);
+        assert_eq!(table.sort(), QuerySort::Count);
+        assert_eq!(
+            table.selected().unwrap().query,
+            "UPDATE orders SET paid = ?"
+        );
+        assert_eq!(table.header().total_queries, 11);
+
+        table.update(&AnalysisResult::default());
+        assert!(table.selected().is_none());
+    }
+
+    #[test]
+    fn redacts_queries_and_examples() {
+        let redactor = Redactor::sensitive();
+        let table =
+            QueryTable::with_redactor(&analysis(), QuerySort::TotalTime, Some(Arc::new(redactor)));
+        let example = &table.selected().unwrap().examples[0];
+        assert!(!example.sql.contains("a@example.com"), "{}", example.sql);
+    }
+}
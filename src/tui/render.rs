@@ -0,0 +1,256 @@
+//! Drawing of [`QueryTable`] with ratatui and its key bindings
+
+use super::QueryTable;
+use crate::{AnalysisResult, PgLogstatsError, QuerySort, Redact, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::Frame;
+use std::io::IsTerminal;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Keys listed at the bottom of the view
+const HELP: &str = "c count  t total  a average  p p95  \u{2191}/\u{2193} or j/k select  q quit";
+
+/// Longest wait for a key before the refresh deadline is checked again
+const KEY_POLL: Duration = Duration::from_millis(250);
+
+pub(super) fn run(
+    sort: QuerySort,
+    redactor: Option<Arc<dyn Redact>>,
+    refresh: Option<Duration>,
+    mut analyze: impl FnMut() -> Result<AnalysisResult>,
+) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return Err(PgLogstatsError::Configuration {
+            message: "--tui needs a terminal on stdout; drop --tui to write a report".to_string(),
+            field: Some("tui".to_string()),
+        });
+    }
+
+    let mut table = QueryTable::with_redactor(&analyze()?, sort, redactor);
+    let mut terminal = ratatui::try_init()?;
+    let mut next_refresh = refresh.map(|interval| Instant::now() + interval);
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| draw(frame, &table, refresh)) {
+            break Err(e.into());
+        }
+        if let (Some(interval), Some(deadline)) = (refresh, next_refresh) {
+            if Instant::now() >= deadline {
+                match analyze() {
+                    Ok(analysis) => table.update(&analysis),
+                    Err(e) => break Err(e),
+                }
+                next_refresh = Some(Instant::now() + interval);
+                continue;
+            }
+        }
+        match event::poll(KEY_POLL).and_then(|ready| ready.then(event::read).transpose()) {
+            Ok(Some(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                let interrupt =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if interrupt || !handle_key(&mut table, key.code) {
+                    break Ok(());
+                }
+            }
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    ratatui::try_restore()?;
+    result
+}
+
+/// Apply a key to `table`; returns `false` for the keys that quit
+fn handle_key(table: &mut QueryTable, code: KeyCode) -> bool {
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => return false,
+        KeyCode::Char('c') => table.sort_by(QuerySort::Count),
+        KeyCode::Char('t') => table.sort_by(QuerySort::TotalTime),
+        KeyCode::Char('a') => table.sort_by(QuerySort::AvgTime),
+        KeyCode::Char('p') => table.sort_by(QuerySort::P95),
+        KeyCode::Down | KeyCode::Char('j') => table.select_next(),
+        KeyCode::Up | KeyCode::Char('k') => table.select_previous(),
+        _ => {}
+    }
+    true
+}
+
+/// Header, query table, examples of the selected query, and key help
+fn draw(frame: &mut Frame, table: &QueryTable, refresh: Option<Duration>) {
+    let [header_area, table_area, detail_area, help_area] = Layout::vertical([
+        Constraint::Length(4),
+        Constraint::Min(6),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let header = table.header();
+    let mut lines = vec![
+        Line::from(format!(
+            "Queries: {} ({} normalized)  Total: {:.3} ms  p95: {:.3} ms  Slow: {}  Errors: {}  Connections: {}",
+            header.total_queries,
+            header.distinct_queries,
+            header.total_duration,
+            header.p95_duration,
+            header.slow_query_count,
+            header.error_count,
+            header.connection_count
+        )),
+        Line::from(match header.time_range {
+            Some((start, end)) => format!(
+                "From {} to {}",
+                start.format("%Y-%m-%d %H:%M:%S"),
+                end.format("%Y-%m-%d %H:%M:%S")
+            ),
+            None => "No entries".to_string(),
+        }),
+    ];
+    if let Some(interval) = refresh {
+        lines.push(Line::from(format!(
+            "Refreshing every {}s",
+            interval.as_secs_f64()
+        )));
+    }
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::BOTTOM)
+                .title("pg-logstats"),
+        ),
+        header_area,
+    );
+
+    let columns = [
+        ("Count", Some(QuerySort::Count)),
+        ("Total ms", Some(QuerySort::TotalTime)),
+        ("Avg ms", Some(QuerySort::AvgTime)),
+        ("P95 ms", Some(QuerySort::P95)),
+        ("Query", None),
+    ];
+    let header_row = Row::new(columns.map(|(title, sort)| {
+        if sort == Some(table.sort()) {
+            Cell::from(format!("{} \u{25bc}", title))
+        } else {
+            Cell::from(title)
+        }
+    }))
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = table.rows().iter().map(|row| {
+        Row::new([
+            Cell::from(row.count.to_string()),
+            Cell::from(format!("{:.3}", row.total_duration)),
+            Cell::from(format!("{:.3}", row.average_duration)),
+            Cell::from(format!("{:.3}", row.p95_duration)),
+            Cell::from(one_line(&row.query)),
+        ])
+    });
+    let widths = [
+        Constraint::Length(10),
+        Constraint::Length(14),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Fill(1),
+    ];
+    let mut state =
+        TableState::default().with_selected(table.selected().map(|_| table.selected_index()));
+    frame.render_stateful_widget(
+        Table::new(rows, widths)
+            .header(header_row)
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        table_area,
+        &mut state,
+    );
+
+    let detail: Vec<Line> = match table.selected() {
+        Some(row) if !row.examples.is_empty() => row
+            .examples
+            .iter()
+            .flat_map(|example| {
+                let session = match (&example.user, &example.database) {
+                    (Some(user), Some(database)) => format!("{}@{}", user, database),
+                    (Some(name), None) | (None, Some(name)) => name.clone(),
+                    (None, None) => "-".to_string(),
+                };
+                let mut lines = vec![
+                    Line::from(format!(
+                        "{} {:.3} ms {}",
+                        example.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+                        example.duration,
+                        session
+                    )),
+                    Line::from(format!("  {}", one_line(&example.sql))),
+                ];
+                if let Some(parameters) = &example.parameters {
+                    lines.push(Line::from(format!("  parameters: {}", parameters)));
+                }
+                lines
+            })
+            .collect(),
+        Some(row) => vec![Line::from(one_line(&row.query))],
+        None => vec![Line::from("No queries")],
+    };
+    frame.render_widget(
+        Paragraph::new(detail)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::TOP).title("Examples")),
+        detail_area,
+    );
+
+    frame.render_widget(Paragraph::new(HELP), help_area);
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryStats;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn draws_the_table_and_switches_sort_keys() {
+        let mut analysis = AnalysisResult {
+            total_queries: 3,
+            ..AnalysisResult::default()
+        };
+        for (query, count, total_duration) in
+            [("SELECT 1", 2, 2.0), ("SELECT pg_sleep(?)", 1, 500.0)]
+        {
+            analysis.query_stats.insert(
+                query.to_string(),
+                QueryStats {
+                    count,
+                    total_duration,
+                    average_duration: total_duration / count as f64,
+                    p95_duration: total_duration,
+                    examples: Vec::new(),
+                },
+            );
+        }
+        let mut table = QueryTable::new(&analysis, QuerySort::Count);
+        assert!(handle_key(&mut table, KeyCode::Char('t')));
+        assert_eq!(table.sort(), QuerySort::TotalTime);
+        assert!(!handle_key(&mut table, KeyCode::Char('q')));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &table, None)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Queries: 3 (2 normalized)"));
+        assert!(screen.contains("Total ms \u{25bc}"));
+        assert!(screen.find("SELECT pg_sleep(?)") < screen.find("SELECT 1"));
+    }
+}
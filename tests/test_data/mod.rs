@@ -124,6 +124,11 @@ pub fn generate_expected_json_output() -> serde_json::Value {
             "total_queries": 11,
             "total_duration_ms": 3689.456,
             "avg_duration_ms": 335.405,
+            "stddev_duration_ms": 958.503,
+            "min_duration_ms": 0.001,
+            "p95_duration_ms": 2800.0,
+            "p99_duration_ms": 3000.0,
+            "max_duration_ms": 3111.111,
             "error_count": 3,
             "connection_count": 2
         },
@@ -176,8 +181,11 @@ pub fn generate_expected_text_output() -> String {
 Total Queries: 11
 Total Duration: 3689.46 ms
 Average Duration: 335.41 ms
+Std Dev Duration: 958.50 ms
+Min Duration: 0.00 ms
 P95 Duration: 2800.00 ms
 P99 Duration: 3000.00 ms
+Max Duration: 3111.11 ms
 Error Count: 3
 Connection Count: 2
 
@@ -199,7 +207,7 @@ Most Frequent Queries:
      #     Count  Query
      1         1  SELECT * FROM users WHERE active = ?
      2         1  UPDATE products SET price = ? WHERE id = ?
-     3         1  INSERT INTO users (name, email) VALUES (S, S)
+     3         1  INSERT INTO users (name, email) VALUES (?, ?)
 "#.to_string()
 }
 
@@ -4,8 +4,12 @@
 
 use chrono::{DateTime, TimeZone, Utc};
 use pg_logstats::analytics::queries::{QueryAnalyzer, QueryMetrics};
+use pg_logstats::analytics::TimingAnalyzerConfig;
 use pg_logstats::sql::{Query, QueryType};
-use pg_logstats::{LogEntry, LogLevel};
+use pg_logstats::{
+    classify, normalize, BatchDuration, ConnectionEventKind, GroupBy, LogEntry, LogLevel,
+    QueryAnalyzerState, QueryLinter, QuerySort, TextLogParser, TimingAnalyzer,
+};
 use std::collections::HashMap;
 
 /// Helper function to create test log entries
@@ -18,20 +22,22 @@ fn create_test_entry(
     user: Option<&str>,
     database: Option<&str>,
 ) -> LogEntry {
-    LogEntry {
+    let message = query
+        .as_ref()
+        .map_or("test message".to_string(), |q| format!("statement: {}", q));
+    LogEntry::builder(
         timestamp,
-        process_id: process_id.unwrap_or("12345").to_string(),
-        user: user.map(|u| u.to_string()),
-        database: database.map(|d| d.to_string()),
-        client_host: None,
-        application_name: Some("psql".to_string()),
+        process_id.unwrap_or("12345"),
         message_type,
-        message: query
-            .as_ref()
-            .map_or("test message".to_string(), |q| format!("statement: {}", q)),
-        queries: Query::from_sql(query.as_deref().unwrap_or("")).ok(),
-        duration,
-    }
+        message,
+    )
+    .with_user(user)
+    .with_database(database)
+    .with_application_name(Some("psql"))
+    .with_queries(Query::from_sql(query.as_deref().unwrap_or("")).ok())
+    .with_duration(duration)
+    .build()
+    .unwrap()
 }
 
 /// Helper function to create a set of diverse test entries
@@ -223,8 +229,6 @@ mod analytics_unit_tests {
 
     #[test]
     fn test_classify_query_select() {
-        let analyzer = QueryAnalyzer::new();
-
         let select_queries = vec![
             "SELECT * FROM users",
             "select id, name from products",
@@ -233,14 +237,12 @@ mod analytics_unit_tests {
         ];
 
         for query in select_queries {
-            assert_eq!(analyzer.classify_query(query), QueryType::Select);
+            assert_eq!(classify(query), QueryType::Select);
         }
     }
 
     #[test]
     fn test_classify_query_insert() {
-        let analyzer = QueryAnalyzer::new();
-
         let insert_queries = vec![
             "INSERT INTO users (name) VALUES ('John')",
             "insert into products values (1, 'Product')",
@@ -248,14 +250,12 @@ mod analytics_unit_tests {
         ];
 
         for query in insert_queries {
-            assert_eq!(analyzer.classify_query(query), QueryType::Insert);
+            assert_eq!(classify(query), QueryType::Insert);
         }
     }
 
     #[test]
     fn test_classify_query_update() {
-        let analyzer = QueryAnalyzer::new();
-
         let update_queries = vec![
             "UPDATE users SET name = 'Jane'",
             "update products set price = 99.99",
@@ -263,14 +263,12 @@ mod analytics_unit_tests {
         ];
 
         for query in update_queries {
-            assert_eq!(analyzer.classify_query(query), QueryType::Update);
+            assert_eq!(classify(query), QueryType::Update);
         }
     }
 
     #[test]
     fn test_classify_query_delete() {
-        let analyzer = QueryAnalyzer::new();
-
         let delete_queries = vec![
             "DELETE FROM users WHERE id = 1",
             "delete from temp_data",
@@ -278,14 +276,12 @@ mod analytics_unit_tests {
         ];
 
         for query in delete_queries {
-            assert_eq!(analyzer.classify_query(query), QueryType::Delete);
+            assert_eq!(classify(query), QueryType::Delete);
         }
     }
 
     #[test]
     fn test_classify_query_ddl() {
-        let analyzer = QueryAnalyzer::new();
-
         let ddl_queries = vec![
             "CREATE TABLE users (id INT)",
             "DROP TABLE temp_data",
@@ -297,32 +293,47 @@ mod analytics_unit_tests {
         ];
 
         for query in ddl_queries {
-            assert_eq!(analyzer.classify_query(query), QueryType::DDL);
+            assert_eq!(classify(query), QueryType::DDL);
         }
     }
 
     #[test]
     fn test_classify_query_other() {
-        let analyzer = QueryAnalyzer::new();
-
         let other_queries = vec![
-            "BEGIN",
-            "COMMIT",
-            "ROLLBACK",
-            "EXPLAIN SELECT * FROM users",
-            "ANALYZE TABLE users",
-            "VACUUM users",
+            "LISTEN jobs",
+            "NOTIFY jobs",
+            "DO $$ BEGIN NULL; END $$",
+            "PREPARE q AS SELECT 1",
+            "DISCARD ALL",
         ];
 
         for query in other_queries {
-            assert_eq!(analyzer.classify_query(query), QueryType::Other);
+            assert_eq!(classify(query), QueryType::Other);
         }
     }
 
     #[test]
-    fn test_normalize_query_parameters() {
-        let analyzer = QueryAnalyzer::new();
+    fn test_classify_query_utility_categories() {
+        for (query, query_type) in [
+            ("BEGIN", QueryType::Transaction),
+            ("COMMIT", QueryType::Transaction),
+            ("ROLLBACK", QueryType::Transaction),
+            ("SAVEPOINT before_update", QueryType::Transaction),
+            ("COMMIT PREPARED 'tx1'", QueryType::Transaction),
+            ("EXPLAIN SELECT * FROM users", QueryType::Explain),
+            ("ANALYZE users", QueryType::Maintenance),
+            ("VACUUM users", QueryType::Maintenance),
+            ("REINDEX INDEX users_pkey", QueryType::Maintenance),
+            ("COPY users FROM STDIN", QueryType::Copy),
+            ("SET statement_timeout = 0", QueryType::Set),
+            ("SHOW server_version", QueryType::Show),
+        ] {
+            assert_eq!(classify(query), query_type, "{query}");
+        }
+    }
 
+    #[test]
+    fn test_normalize_query_parameters() {
         let test_cases = vec![
             (
                 "SELECT * FROM users WHERE id = $1",
@@ -339,95 +350,146 @@ mod analytics_unit_tests {
         ];
 
         for (input, expected) in test_cases {
-            let result = analyzer.normalize_query(input);
+            let result = normalize(input).unwrap();
             assert_eq!(result, expected);
         }
     }
 
     #[test]
     fn test_normalize_query_numeric_literals() {
-        let analyzer = QueryAnalyzer::new();
-
         let test_cases = vec![
             (
                 "SELECT * FROM users WHERE age > 25",
-                "SELECT * FROM users WHERE age > N",
+                "SELECT * FROM users WHERE age > ?",
             ),
             (
                 "UPDATE products SET price = 99.99",
-                "UPDATE products SET price = N",
+                "UPDATE products SET price = ?",
             ),
             (
                 "SELECT * FROM orders WHERE total BETWEEN 10.5 AND 100",
-                "SELECT * FROM orders WHERE total BETWEEN N AND N",
+                "SELECT * FROM orders WHERE total BETWEEN ? AND ?",
             ),
         ];
 
         for (input, expected) in test_cases {
-            let result = analyzer.normalize_query(input);
+            let result = normalize(input).unwrap();
             assert_eq!(result, expected);
         }
     }
 
     #[test]
     fn test_normalize_query_string_literals() {
-        let analyzer = QueryAnalyzer::new();
-
         let test_cases = vec![
             (
                 "SELECT * FROM users WHERE name = 'John'",
-                "SELECT * FROM users WHERE name = S",
+                "SELECT * FROM users WHERE name = ?",
             ),
             (
                 "INSERT INTO users VALUES ('John', 'john@example.com')",
-                "INSERT INTO users VALUES (S, S)",
+                "INSERT INTO users VALUES (?, ?)",
             ),
             (
                 "UPDATE users SET status = 'active' WHERE name LIKE '%admin%'",
-                "UPDATE users SET status = S WHERE name LIKE S",
+                "UPDATE users SET status = ? WHERE name LIKE ?",
             ),
         ];
 
         for (input, expected) in test_cases {
-            let result = analyzer.normalize_query(input);
+            let result = normalize(input).unwrap();
             assert_eq!(result, expected);
         }
     }
 
     #[test]
     fn test_normalize_query_whitespace() {
-        let analyzer = QueryAnalyzer::new();
-
         let test_cases = vec![
             ("SELECT   *   FROM    users", "SELECT * FROM users"),
             (
                 "  UPDATE  users  SET  name='John'  ",
-                "UPDATE users SET name=S",
+                "UPDATE users SET name = ?",
             ),
             (
                 "SELECT\n*\nFROM\nusers\nWHERE\nid=1",
-                "SELECT * FROM users WHERE id=N",
+                "SELECT * FROM users WHERE id = ?",
             ),
         ];
 
         for (input, expected) in test_cases {
-            let result = analyzer.normalize_query(input);
+            let result = normalize(input).unwrap();
             assert_eq!(result, expected);
         }
     }
 
     #[test]
     fn test_normalize_query_complex() {
-        let analyzer = QueryAnalyzer::new();
-
         let input = "SELECT u.name, u.email FROM users u WHERE u.age > 25 AND u.status = 'active' AND u.id = $1";
         let expected =
-            "SELECT u.name, u.email FROM users u WHERE u.age > N AND u.status = S AND u.id = ?";
+            "SELECT u.name, u.email FROM users AS u WHERE u.age > ? AND u.status = ? AND u.id = ?";
 
-        let result = analyzer.normalize_query(input);
+        let result = normalize(input).unwrap();
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_normalize_keeps_digits_in_identifiers() {
+        let test_cases = vec![
+            (
+                "SELECT * FROM table_1 WHERE id = 1",
+                "SELECT * FROM table_1 WHERE id = ?",
+            ),
+            (
+                "SELECT t1.col2 FROM s3.orders_2024 t1 WHERE t1.id IN (1, 2)",
+                "SELECT t1.col2 FROM s3.orders_2024 AS t1 WHERE t1.id IN (?)",
+            ),
+            (
+                "SELECT * FROM \"sales 2024\" WHERE region = 'q1'",
+                "SELECT * FROM \"sales 2024\" WHERE region = ?",
+            ),
+            (
+                "COPY \"sales 2024\".q1_orders FROM STDIN",
+                "COPY \"sales 2024\".q1_orders FROM STDIN",
+            ),
+        ];
+
+        for (input, expected) in test_cases {
+            let result = normalize(input).unwrap();
+            assert_eq!(result, expected);
+            // Already-normalized text comes back unchanged
+            assert_eq!(normalize(&result).unwrap(), expected);
+        }
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
+        let entries: Vec<_> = [
+            "SELECT * FROM table_1 WHERE id = 1",
+            "SELECT * FROM table_1 WHERE id = 2",
+            "SELECT * FROM table_2 WHERE id = 3",
+        ]
+        .iter()
+        .map(|sql| {
+            create_test_entry(
+                timestamp,
+                LogLevel::Statement,
+                Some(sql.to_string()),
+                Some(1.0),
+                None,
+                None,
+                None,
+            )
+        })
+        .collect();
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+        assert_eq!(result.query_stats.len(), 2);
+        assert_eq!(
+            result.query_stats["SELECT * FROM table_1 WHERE id = ?"].count,
+            2
+        );
+        assert_eq!(
+            result.query_stats["SELECT * FROM table_2 WHERE id = ?"].count,
+            1
+        );
+    }
+
     #[test]
     fn test_analyze_empty_entries() {
         let analyzer = QueryAnalyzer::new();
@@ -441,6 +503,80 @@ mod analytics_unit_tests {
         assert!(result.slowest_queries.is_empty());
         assert!(result.most_frequent_queries.is_empty());
         assert!(result.query_types.is_empty());
+        assert!(result.time_range.is_none());
+    }
+
+    #[test]
+    fn test_time_range_of_single_entry() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
+        let entries = vec![create_test_entry(
+            timestamp,
+            LogLevel::Statement,
+            Some("SELECT 1".to_string()),
+            Some(1.0),
+            None,
+            None,
+            None,
+        )];
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+
+        let time_range = result.time_range.unwrap();
+        assert_eq!(time_range.start, timestamp);
+        assert_eq!(time_range.end, timestamp);
+        assert_eq!(time_range.span_seconds, 0.0);
+        assert_eq!(time_range.queries_per_second, 0.0);
+        assert_eq!(time_range.active_days, 1);
+        assert_eq!(time_range.active_hours, 1);
+        assert!(time_range.gaps.is_empty());
+    }
+
+    #[test]
+    fn test_time_range_counts_activity_and_gaps() {
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 23, 50, 0).unwrap();
+        let entry = |minutes: i64, level: LogLevel| {
+            create_test_entry(
+                base_time + chrono::Duration::minutes(minutes),
+                level,
+                Some("SELECT 1".to_string()),
+                Some(1.0),
+                None,
+                None,
+                None,
+            )
+        };
+        // Out of order, across midnight, with a 90-minute hole
+        let entries = vec![
+            entry(100, LogLevel::Statement),
+            entry(0, LogLevel::Statement),
+            entry(5, LogLevel::Log),
+            entry(10, LogLevel::Statement),
+        ];
+
+        let result = QueryAnalyzer::new()
+            .with_gap_threshold_minutes(30.0)
+            .analyze(&entries)
+            .unwrap();
+
+        let time_range = result.time_range.unwrap();
+        assert_eq!(time_range.start, base_time);
+        assert_eq!(time_range.end, base_time + chrono::Duration::minutes(100));
+        assert_eq!(time_range.span_seconds, 6_000.0);
+        assert_eq!(time_range.queries_per_second, 3.0 / 6_000.0);
+        assert_eq!(time_range.active_days, 2);
+        assert_eq!(time_range.active_hours, 3);
+        assert_eq!(time_range.gaps.len(), 1);
+        assert_eq!(
+            time_range.gaps[0].start,
+            base_time + chrono::Duration::minutes(10)
+        );
+        assert_eq!(time_range.gaps[0].minutes, 90.0);
+
+        let result = QueryAnalyzer::new()
+            .with_gap_threshold_minutes(120.0)
+            .analyze(&entries)
+            .unwrap();
+        assert!(result.time_range.unwrap().gaps.is_empty());
     }
 
     #[test]
@@ -463,7 +599,10 @@ mod analytics_unit_tests {
         assert_eq!(result.average_duration, 100.0);
         assert_eq!(result.error_count, 0);
         assert_eq!(result.connection_count, 0);
-        assert_eq!(result.query_types.get("SELECT"), Some(&1));
+        assert_eq!(
+            result.query_types.get("SELECT").map(|stats| stats.count),
+            Some(1)
+        );
         assert_eq!(result.most_frequent_queries.len(), 1);
         assert_eq!(result.most_frequent_queries[0].0, "SELECT * FROM users");
         assert_eq!(result.most_frequent_queries[0].1, 1);
@@ -502,12 +641,202 @@ mod analytics_unit_tests {
         assert_eq!(result.error_count, 2);
 
         // Query type distribution
-        assert_eq!(result.query_types.get("SELECT"), Some(&5)); // 3 SELECT queries (including duplicates)
-        assert_eq!(result.query_types.get("INSERT"), Some(&2));
-        assert_eq!(result.query_types.get("UPDATE"), Some(&1));
-        assert_eq!(result.query_types.get("DELETE"), Some(&1));
-        assert_eq!(result.query_types.get("DDL"), Some(&2));
-        assert_eq!(result.query_types.get("OTHER"), Some(&2));
+        assert_eq!(
+            result.query_types.get("SELECT").map(|stats| stats.count),
+            Some(5)
+        ); // 3 SELECT queries (including duplicates)
+        assert_eq!(
+            result.query_types.get("INSERT").map(|stats| stats.count),
+            Some(2)
+        );
+        assert_eq!(
+            result.query_types.get("UPDATE").map(|stats| stats.count),
+            Some(1)
+        );
+        assert_eq!(
+            result.query_types.get("DELETE").map(|stats| stats.count),
+            Some(1)
+        );
+        assert_eq!(
+            result.query_types.get("DDL").map(|stats| stats.count),
+            Some(2)
+        );
+        assert_eq!(
+            result
+                .query_types
+                .get("TRANSACTION")
+                .map(|stats| stats.count),
+            Some(2)
+        );
+        assert_eq!(result.query_types.get("OTHER"), None);
+    }
+
+    #[test]
+    fn test_query_types_carry_latency_per_type() {
+        let statements = [
+            ("SELECT * FROM users WHERE id = 1", 10.0),
+            ("SELECT * FROM users WHERE id = 2", 20.0),
+            ("SELECT * FROM orders", 30.0),
+            ("CREATE INDEX idx_users_email ON users(email)", 2000.0),
+            ("DROP TABLE old_users", 100.0),
+        ];
+        let entries: Vec<_> = statements
+            .iter()
+            .map(|(sql, duration)| {
+                create_test_entry(
+                    Utc::now(),
+                    LogLevel::Statement,
+                    Some(sql.to_string()),
+                    Some(*duration),
+                    None,
+                    None,
+                    None,
+                )
+            })
+            .collect();
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+
+        let select = &result.query_types["SELECT"];
+        assert_eq!(select.count, 3);
+        assert_eq!(select.total_duration, 60.0);
+        assert_eq!(select.average_duration, 20.0);
+        assert_eq!(select.max_duration, 30.0);
+        assert!(select.p95_duration > 20.0 && select.p95_duration <= 30.0);
+
+        // Rare slow DDL no longer hides in the overall average
+        let ddl = &result.query_types["DDL"];
+        assert_eq!(ddl.count, 2);
+        assert_eq!(ddl.average_duration, 1050.0);
+        assert_eq!(ddl.max_duration, 2000.0);
+        assert!(ddl.average_duration > result.average_duration);
+    }
+
+    #[test]
+    fn test_multi_statement_lines_are_one_query_or_split() {
+        let entries: Vec<_> = [
+            (
+                "BEGIN; UPDATE accounts SET balance = 0 WHERE id = 1; COMMIT",
+                12.0,
+            ),
+            ("SELECT 1", 3.0),
+        ]
+        .iter()
+        .map(|(sql, duration)| {
+            create_test_entry(
+                Utc::now(),
+                LogLevel::Statement,
+                Some(sql.to_string()),
+                Some(*duration),
+                None,
+                None,
+                None,
+            )
+        })
+        .collect();
+        let batch_sql = "BEGIN TRANSACTION; UPDATE accounts SET balance = ? WHERE id = ?; COMMIT";
+        let update_sql = "UPDATE accounts SET balance = ? WHERE id = ?";
+
+        let batch = QueryAnalyzer::with_settings(10.0, 5, 10)
+            .analyze(&entries)
+            .unwrap();
+        assert_eq!(batch.total_queries, 2);
+        assert_eq!(batch.total_duration, 15.0);
+        assert!(!batch.query_types.contains_key("TRANSACTION"));
+        assert_eq!(batch.query_types["UPDATE"].count, 1);
+        assert_eq!(batch.query_types["UPDATE"].total_duration, 12.0);
+        assert_eq!(batch.query_types["SELECT"].total_duration, 3.0);
+        assert_eq!(batch.most_frequent_queries.len(), 2);
+        assert!(!batch.query_stats.contains_key(update_sql));
+        assert_eq!(batch.query_stats[batch_sql].count, 1);
+        assert_eq!(batch.query_stats[batch_sql].total_duration, 12.0);
+        assert_eq!(batch.slowest_queries[0], (batch_sql.to_string(), 12.0));
+
+        let split = QueryAnalyzer::with_settings(10.0, 5, 10)
+            .with_batch_duration(BatchDuration::Split)
+            .analyze(&entries)
+            .unwrap();
+        assert_eq!(split.total_queries, 4);
+        assert_eq!(split.total_duration, 15.0);
+        assert_eq!(split.query_types["TRANSACTION"].total_duration, 8.0);
+        assert_eq!(split.query_stats[update_sql].total_duration, 4.0);
+        assert!(split
+            .slowest_queries
+            .iter()
+            .all(|(sql, _)| sql != batch_sql));
+    }
+
+    #[test]
+    fn test_batch_durations_add_up_to_the_total_in_both_modes() {
+        let entries: Vec<_> = [
+            (
+                "BEGIN; UPDATE accounts SET balance = 0 WHERE id = 1; COMMIT",
+                2000.0,
+            ),
+            ("SELECT 1; SELECT 2", 500.0),
+            ("SELECT 3", 100.0),
+        ]
+        .iter()
+        .map(|(sql, duration)| {
+            create_test_entry(
+                Utc::now(),
+                LogLevel::Statement,
+                Some(sql.to_string()),
+                Some(*duration),
+                None,
+                None,
+                None,
+            )
+        })
+        .collect();
+
+        // Shares of a split batch may not add up to the exact duration
+        let adds_up = |total: f64| (total - 2600.0).abs() < 1e-9;
+        for (mode, queries) in [(BatchDuration::Batch, 3), (BatchDuration::Split, 6)] {
+            let result = QueryAnalyzer::with_settings(10.0, 5, 10)
+                .with_batch_duration(mode)
+                .analyze(&entries)
+                .unwrap();
+            assert_eq!(result.total_queries, queries, "{:?}", mode);
+            assert_eq!(result.total_duration, 2600.0);
+            let (count, total) = result
+                .query_types
+                .values()
+                .fold((0, 0.0), |(count, total), stats| {
+                    (count + stats.count, total + stats.total_duration)
+                });
+            assert_eq!(count, queries, "{:?}", mode);
+            assert!(adds_up(total), "{:?}: {}", mode, total);
+            let (count, total) = result
+                .query_stats
+                .values()
+                .fold((0, 0.0), |(count, total), stats| {
+                    (count + stats.count, total + stats.total_duration)
+                });
+            assert_eq!(count, queries, "{:?}", mode);
+            assert!(adds_up(total), "{:?}: {}", mode, total);
+            let frequent: f64 = result
+                .most_frequent_queries
+                .iter()
+                .map(|(sql, _)| result.query_stats[sql].total_duration)
+                .sum();
+            assert!(adds_up(frequent), "{:?}: {}", mode, frequent);
+            let slowest = result.slowest_queries[0].1;
+            assert_eq!(slowest, result.max_duration, "{:?}", mode);
+
+            let timing = TimingAnalyzer::with_config(
+                TimingAnalyzerConfig::default().with_batch_duration(mode),
+            )
+            .analyze_timing(&entries)
+            .unwrap();
+            assert_eq!(timing.total_queries, queries, "{:?}", mode);
+            assert!(adds_up(timing.total_duration), "{:?}", mode);
+            assert_eq!(
+                timing.p95_response_time_ms, result.max_duration,
+                "{:?}",
+                mode
+            );
+        }
     }
 
     #[test]
@@ -560,6 +889,145 @@ mod analytics_unit_tests {
             .contains("SELECT * FROM users WHERE active = ?"));
     }
 
+    #[test]
+    fn test_query_stats_of_listed_queries() {
+        let analyzer = QueryAnalyzer::new();
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
+        let entries: Vec<_> = [
+            ("SELECT * FROM t WHERE id = 1", 10.0),
+            ("SELECT * FROM t WHERE id = 2", 60.0),
+            ("SELECT * FROM t WHERE id = 3", 20.0),
+            ("DELETE FROM t", 5.0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (sql, duration))| {
+            create_test_entry(
+                base_time + chrono::Duration::seconds(i as i64),
+                LogLevel::Statement,
+                Some(sql.to_string()),
+                Some(*duration),
+                Some(&i.to_string()),
+                None,
+                None,
+            )
+        })
+        .collect();
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        let stats = &result.query_stats["SELECT * FROM t WHERE id = ?"];
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.total_duration, 90.0);
+        assert_eq!(stats.average_duration, 30.0);
+        assert_eq!(stats.p95_duration, 60.0);
+        assert_eq!(result.query_stats["DELETE FROM t"].average_duration, 5.0);
+        assert_eq!(result.query_stats.len(), result.most_frequent_queries.len());
+    }
+
+    #[test]
+    fn test_query_examples_keep_the_slowest_executions_with_literals() {
+        let analyzer = QueryAnalyzer::new().with_max_query_examples(2);
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
+        let entries: Vec<_> = [
+            (1, 10.0),
+            (2, 60.0),
+            (3, 20.0),
+            (4, 60.0),
+            (5, 60.0),
+            (6, 5.0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (id, duration))| {
+            create_test_entry(
+                base_time + chrono::Duration::seconds(i as i64),
+                LogLevel::Statement,
+                Some(format!("SELECT * FROM t WHERE id = {}", id)),
+                Some(*duration),
+                Some(&i.to_string()),
+                Some("alice"),
+                Some("shop"),
+            )
+        })
+        .collect();
+
+        let result = analyzer.analyze(&entries).unwrap();
+
+        let examples = &result.query_stats["SELECT * FROM t WHERE id = ?"].examples;
+        // Bounded at two, and the last execution of equal duration displaces
+        // neither of the two kept before it
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[0].sql, "SELECT * FROM t WHERE id = 2");
+        assert_eq!(examples[0].duration, 60.0);
+        assert_eq!(
+            examples[0].timestamp,
+            base_time + chrono::Duration::seconds(1)
+        );
+        assert_eq!(examples[0].user.as_deref(), Some("alice"));
+        assert_eq!(examples[0].database.as_deref(), Some("shop"));
+        assert_eq!(examples[1].sql, "SELECT * FROM t WHERE id = 4");
+
+        let none = QueryAnalyzer::new()
+            .with_max_query_examples(0)
+            .analyze(&entries)
+            .unwrap();
+        assert!(none.query_stats["SELECT * FROM t WHERE id = ?"]
+            .examples
+            .is_empty());
+    }
+
+    fn create_sortable_entries() -> Vec<LogEntry> {
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
+        [
+            ("SELECT a FROM t", vec![1.0, 1.0, 1.0, 1.0]),
+            ("SELECT b FROM t", vec![10.0, 10.0]),
+            ("SELECT c FROM t", vec![1.0, 1.0, 15.0]),
+            ("SELECT d FROM t", vec![12.0]),
+        ]
+        .iter()
+        .flat_map(|(sql, durations)| durations.iter().map(move |duration| (*sql, *duration)))
+        .enumerate()
+        .map(|(i, (sql, duration))| {
+            create_test_entry(
+                base_time + chrono::Duration::seconds(i as i64),
+                LogLevel::Statement,
+                Some(sql.to_string()),
+                Some(duration),
+                Some(&i.to_string()),
+                None,
+                None,
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_most_frequent_queries_follow_each_sort_key() {
+        let entries = create_sortable_entries();
+        let order = |sort: QuerySort| -> Vec<String> {
+            QueryAnalyzer::new()
+                .with_query_sort(sort)
+                .analyze(&entries)
+                .unwrap()
+                .most_frequent_queries
+                .into_iter()
+                .map(|(query, _)| query)
+                .collect()
+        };
+
+        let names = |names: [&str; 4]| -> Vec<String> {
+            names
+                .iter()
+                .map(|n| format!("SELECT {} FROM t", n))
+                .collect()
+        };
+        assert_eq!(order(QuerySort::Count), names(["a", "c", "b", "d"]));
+        assert_eq!(order(QuerySort::TotalTime), names(["b", "c", "d", "a"]));
+        assert_eq!(order(QuerySort::AvgTime), names(["d", "b", "c", "a"]));
+        assert_eq!(order(QuerySort::P95), names(["c", "d", "b", "a"]));
+    }
+
     #[test]
     fn test_calculate_metrics() {
         let analyzer = QueryAnalyzer::new();
@@ -626,7 +1094,8 @@ mod analytics_unit_tests {
         assert_eq!(distribution.get(&QueryType::Update), Some(&1));
         assert_eq!(distribution.get(&QueryType::Delete), Some(&1));
         assert_eq!(distribution.get(&QueryType::DDL), Some(&2));
-        assert_eq!(distribution.get(&QueryType::Other), Some(&2));
+        assert_eq!(distribution.get(&QueryType::Transaction), Some(&2));
+        assert_eq!(distribution.get(&QueryType::Other), None);
     }
 
     #[test]
@@ -771,6 +1240,597 @@ mod analytics_unit_tests {
         assert_eq!(result.total_queries, 100);
         assert_eq!(result.most_frequent_queries.len(), 1); // All unique
     }
+
+    /// Statements over a skewed distribution: 20 heavy hitters with 1000/k + 50
+    /// executions and 2000 rare queries run one to three times each, in a
+    /// fixed shuffled order
+    fn create_skewed_entries() -> (Vec<LogEntry>, HashMap<String, u64>) {
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        let mut templates = Vec::new();
+        let mut runs = Vec::new();
+        for table in 0..2020u64 {
+            let count = if table < 20 {
+                1000 / (table + 1) + 50
+            } else {
+                table % 3 + 1
+            };
+            templates.push(create_test_entry(
+                base_time,
+                LogLevel::Statement,
+                Some(format!("SELECT * FROM table_{} WHERE id = 1", table)),
+                Some(5.0),
+                None,
+                Some("postgres"),
+                Some("testdb"),
+            ));
+            runs.extend(std::iter::repeat_n(table as usize, count as usize));
+        }
+
+        // Fisher-Yates with a fixed linear congruential generator
+        let mut state: u64 = 42;
+        for i in (1..runs.len()).rev() {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            runs.swap(i, (state >> 33) as usize % (i + 1));
+        }
+
+        let mut exact_counts = HashMap::new();
+        let entries = runs
+            .into_iter()
+            .enumerate()
+            .map(|(i, table)| {
+                let mut entry = templates[table].clone();
+                entry.timestamp = base_time + chrono::Duration::milliseconds(i as i64);
+                *exact_counts
+                    .entry(entry.normalized_query().unwrap())
+                    .or_insert(0) += 1;
+                entry
+            })
+            .collect();
+        (entries, exact_counts)
+    }
+
+    #[test]
+    fn test_low_memory_top_queries_match_exact_heavy_hitters() {
+        let (entries, exact_counts) = create_skewed_entries();
+        let exact = QueryAnalyzer::new().analyze(&entries).unwrap();
+        let approximate = QueryAnalyzer::new()
+            .with_low_memory(true)
+            .with_top_k_capacity(100)
+            .analyze(&entries)
+            .unwrap();
+
+        assert_eq!(exact.frequency_estimate, None);
+        let estimate = approximate.frequency_estimate.unwrap();
+        assert_eq!(estimate.capacity, 100);
+        assert!(estimate.max_overcount > 0);
+
+        let top = |result: &pg_logstats::AnalysisResult| -> Vec<String> {
+            let mut queries: Vec<_> = result
+                .most_frequent_queries
+                .iter()
+                .map(|(sql, _)| sql.clone())
+                .collect();
+            queries.sort();
+            queries
+        };
+        assert_eq!(exact.most_frequent_queries.len(), 20);
+        assert_eq!(top(&approximate), top(&exact));
+        for (sql, count) in &approximate.most_frequent_queries {
+            let true_count = exact_counts[sql];
+            assert!(*count >= true_count, "{} undercounted", sql);
+            assert!(*count <= true_count + estimate.max_overcount, "{}", sql);
+        }
+        assert_eq!(approximate.total_queries, exact.total_queries);
+    }
+
+    #[test]
+    fn test_exact_query_limit_switches_to_estimated_counts() {
+        let (entries, _) = create_skewed_entries();
+        let result = QueryAnalyzer::new()
+            .with_exact_query_limit(500)
+            .with_top_k_capacity(200)
+            .analyze(&entries)
+            .unwrap();
+
+        let estimate = result.frequency_estimate.unwrap();
+        assert_eq!(estimate.capacity, 200);
+        assert_eq!(
+            result.most_frequent_queries[0],
+            ("SELECT * FROM table_0 WHERE id = ?".to_string(), 1050)
+        );
+    }
+
+    #[test]
+    fn test_lint_warnings_carry_query_workload() {
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        let statements = [
+            ("UPDATE users SET active = false", 80.0),
+            ("SELECT * FROM users WHERE id = 1", 5.0),
+            ("SELECT * FROM users WHERE id = 2", 9.0),
+            ("SELECT id FROM users WHERE id = 3", 1.0),
+        ];
+        let entries: Vec<_> = statements
+            .iter()
+            .enumerate()
+            .map(|(i, (sql, duration))| {
+                create_test_entry(
+                    base_time + chrono::Duration::seconds(i as i64),
+                    LogLevel::Statement,
+                    Some(sql.to_string()),
+                    Some(*duration),
+                    None,
+                    Some("postgres"),
+                    Some("testdb"),
+                )
+            })
+            .collect();
+
+        let unlinted = QueryAnalyzer::new().analyze(&entries).unwrap();
+        assert_eq!(unlinted.query_warnings, None);
+
+        let result = QueryAnalyzer::new()
+            .with_linter(Some(QueryLinter::new()))
+            .analyze(&entries)
+            .unwrap();
+        let warnings = result.query_warnings.unwrap();
+        let summary: Vec<_> = warnings
+            .iter()
+            .map(|w| (w.rule.as_str(), w.query.as_str(), w.count, w.p95_duration))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("select-star", "SELECT * FROM users WHERE id = ?", 2, 9.0),
+                ("missing-where", "UPDATE users SET active = ?", 1, 80.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_breakdowns_by_user_and_database() {
+        let mut entries = create_diverse_test_entries();
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 11, 0, 0).unwrap();
+        entries.push(create_test_entry(
+            base_time,
+            LogLevel::Statement,
+            Some("SELECT 1".to_string()),
+            Some(5.0),
+            Some("22222"),
+            None,
+            None,
+        ));
+        entries.push(create_test_entry(
+            base_time,
+            LogLevel::Error,
+            None,
+            None,
+            Some("12345"),
+            Some("postgres"),
+            Some("testdb"),
+        ));
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+
+        assert_eq!(
+            result.queries_by_user.values().sum::<u64>(),
+            result.total_queries
+        );
+        assert_eq!(result.queries_by_user["unknown"], 1);
+        assert_eq!(result.duration_by_user["unknown"], 5.0);
+        assert_eq!(result.queries_by_database["unknown"], 1);
+        assert!(result.errors_by_database["testdb"] >= 1);
+        assert_eq!(
+            result.errors_by_database.values().sum::<u64>(),
+            result.error_count
+        );
+        assert!(result.group_by.is_none());
+        assert!(result.query_groups.is_empty());
+    }
+
+    #[test]
+    fn test_application_stats_from_diverse_entries() {
+        let mut entries = create_diverse_test_entries();
+        let application_names = [Some("checkout"), Some("billing"), None];
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.application_name =
+                application_names[i % application_names.len()].map(str::to_string);
+        }
+
+        let analyzer = QueryAnalyzer::new().with_max_application_queries(1);
+        let result = analyzer.analyze(&entries).unwrap();
+
+        let names: Vec<_> = result
+            .applications
+            .iter()
+            .map(|application| application.application_name.as_str())
+            .collect();
+        assert!(names.contains(&"(none)"));
+        assert!(names.contains(&"checkout"));
+        assert!(result
+            .applications
+            .windows(2)
+            .all(|pair| pair[0].total_duration >= pair[1].total_duration));
+        assert_eq!(
+            result
+                .applications
+                .iter()
+                .map(|application| application.query_count)
+                .sum::<u64>(),
+            result.total_queries
+        );
+        assert_eq!(
+            result
+                .applications
+                .iter()
+                .map(|application| application.error_count)
+                .sum::<u64>(),
+            result.error_count
+        );
+        for application in &result.applications {
+            assert!(application.slowest_queries.len() <= 1);
+            if let Some((_, slowest)) = application.slowest_queries.first() {
+                assert!(*slowest >= application.p95_duration);
+            }
+        }
+    }
+
+    #[test]
+    fn test_client_host_stats_aggregate_ports() {
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        let mut entries = Vec::new();
+        for (pid, port, duration) in [("1", 51234, 10.0), ("2", 51240, 30.0)] {
+            let received = LogEntry::builder(
+                base_time,
+                pid,
+                LogLevel::Log,
+                format!("connection received: host=10.0.0.5 port={}", port),
+            )
+            .with_client_host(Some("10.0.0.5"))
+            .with_client_port(Some(port))
+            .with_connection_event(Some(ConnectionEventKind::Received))
+            .build()
+            .unwrap();
+            let mut statement = create_test_entry(
+                base_time,
+                LogLevel::Statement,
+                Some("SELECT * FROM users".to_string()),
+                Some(duration),
+                Some(pid),
+                None,
+                None,
+            );
+            statement.client_host = received.client_host.clone();
+            statement.client_port = received.client_port;
+            entries.push(received);
+            entries.push(statement);
+        }
+        let mut error = create_test_entry(
+            base_time,
+            LogLevel::Error,
+            None,
+            None,
+            Some("2"),
+            None,
+            None,
+        );
+        error.client_host = Some("10.0.0.5".to_string());
+        error.client_port = Some(51240);
+        entries.push(error);
+        entries.push(create_test_entry(
+            base_time,
+            LogLevel::Statement,
+            Some("SELECT 1".to_string()),
+            Some(5.0),
+            Some("3"),
+            None,
+            None,
+        ));
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+
+        assert_eq!(result.client_hosts.len(), 1);
+        let client = &result.client_hosts[0];
+        assert_eq!(client.client_host, "10.0.0.5");
+        assert_eq!(client.connection_count, 2);
+        assert_eq!(client.query_count, 2);
+        assert_eq!(client.total_duration, 40.0);
+        assert_eq!(client.error_count, 1);
+        assert!((client.error_rate - 1.0 / 3.0).abs() < 1e-9);
+
+        let result = QueryAnalyzer::new()
+            .with_client_ports(true)
+            .analyze(&entries)
+            .unwrap();
+        let hosts: Vec<_> = result
+            .client_hosts
+            .iter()
+            .map(|client| (client.client_host.as_str(), client.error_count))
+            .collect();
+        assert_eq!(hosts, vec![("10.0.0.5(51240)", 1), ("10.0.0.5(51234)", 0)]);
+
+        let result = QueryAnalyzer::new()
+            .with_client_ports(true)
+            .with_max_client_hosts(1)
+            .analyze(&entries)
+            .unwrap();
+        assert_eq!(result.client_hosts.len(), 1);
+    }
+
+    #[test]
+    fn test_cancellations_by_reason_statement_and_hour() {
+        let lines: Vec<String> = include_str!("../fixtures/cli/cancellations.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+
+        let cancellations = &result.cancellations;
+        assert_eq!(cancellations.total, 8);
+        for (reason, count) in [
+            ("statement_timeout", 2),
+            ("lock_timeout", 1),
+            ("idle_in_transaction_timeout", 1),
+            ("administrator_command", 1),
+            ("recovery_conflict", 2),
+            ("user_request", 1),
+        ] {
+            assert_eq!(
+                cancellations.by_reason.get(reason),
+                Some(&count),
+                "{}",
+                reason
+            );
+        }
+        assert_eq!(
+            cancellations.hourly.iter().collect::<Vec<_>>(),
+            vec![(&10, &3), (&11, &5)]
+        );
+        let top = &cancellations.top_queries[0];
+        assert_eq!(
+            top.query,
+            "SELECT * FROM events WHERE created_at > ? ORDER BY id"
+        );
+        assert_eq!(top.count, 3);
+        assert_eq!(top.by_reason.get("statement_timeout"), Some(&2));
+        assert_eq!(top.by_reason.get("user_request"), Some(&1));
+        assert_eq!(cancellations.top_queries.len(), 3);
+        assert_eq!(result.error_count, 9);
+
+        let result = QueryAnalyzer::new()
+            .with_max_canceled_queries(1)
+            .analyze(&entries)
+            .unwrap();
+        assert_eq!(result.cancellations.top_queries.len(), 1);
+    }
+
+    #[test]
+    fn test_connection_count_uses_parsed_connection_events() {
+        let lines: Vec<String> = [
+            "2024-01-15 10:00:00.000 UTC [6001] [unknown]@[unknown] [unknown]: LOG:  connection received: host=10.0.0.5 port=51234",
+            "2024-01-15 10:00:00.010 UTC [6001] app@appdb api: LOG:  connection authorized: user=app database=appdb",
+            "2024-01-15 10:00:01.000 UTC [6001] app@appdb api: LOG:  statement: SELECT * FROM connection_log WHERE note = 'connection reset'",
+            "2024-01-15 10:00:02.000 UTC [6001] app@appdb api: LOG:  Connection limit of pool is close to the connection slot count",
+            "2024-01-15 10:00:03.000 UTC [6001] app@appdb api: ERROR:  could not serialize access due to concurrent update on connection slot",
+            "2024-01-15 10:00:04.000 UTC [6001] app@appdb api: LOG:  could not receive data from client: Connection reset by peer",
+            "2024-01-15 10:00:05.000 UTC [6001] app@appdb api: LOG:  disconnection: session time: 0:00:05.000 user=app database=appdb host=10.0.0.5 port=51234",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        let kinds: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| entry.connection_event)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ConnectionEventKind::Received,
+                ConnectionEventKind::Authorized,
+                ConnectionEventKind::Reset,
+                ConnectionEventKind::Disconnection,
+            ]
+        );
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+        assert_eq!(result.connection_count, 4);
+        assert_eq!(result.error_count, 1);
+
+        let connections = TimingAnalyzer::new()
+            .analyze_connection_patterns(&entries)
+            .unwrap();
+        assert_eq!(connections.total_connections, 4);
+        assert_eq!(connections.connection_errors, 1);
+    }
+
+    #[test]
+    fn test_group_by_user_tracks_queries_per_user() {
+        let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        let entries: Vec<_> = [
+            ("alice", "SELECT * FROM orders WHERE id = 1", 1500.0),
+            ("alice", "SELECT * FROM orders WHERE id = 2", 10.0),
+            ("bob", "DELETE FROM sessions WHERE id = 3", 2500.0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (user, sql, duration))| {
+            create_test_entry(
+                base_time + chrono::Duration::seconds(i as i64),
+                LogLevel::Statement,
+                Some(sql.to_string()),
+                Some(*duration),
+                Some(&(100 + i).to_string()),
+                Some(user),
+                Some("shop"),
+            )
+        })
+        .collect();
+
+        let analyzer = QueryAnalyzer::new().with_group_by(Some(GroupBy::User));
+        let result = analyzer.analyze(&entries).unwrap();
+
+        assert_eq!(result.group_by, Some(GroupBy::User));
+        let alice = &result.query_groups["alice"];
+        assert_eq!(alice.total_queries, 2);
+        assert_eq!(alice.total_duration, 1510.0);
+        assert_eq!(
+            alice.most_frequent_queries,
+            vec![("SELECT * FROM orders WHERE id = ?".to_string(), 2)]
+        );
+        assert_eq!(alice.slowest_queries.len(), 1);
+        let bob = &result.query_groups["bob"];
+        assert_eq!(
+            bob.slowest_queries,
+            vec![("DELETE FROM sessions WHERE id = ?".to_string(), 2500.0)]
+        );
+        let order: Vec<_> = result
+            .query_groups_by_duration()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(order, vec!["bob", "alice"]);
+    }
+
+    #[test]
+    fn test_duration_histogram_counts_edge_values_in_upper_bucket() {
+        let analyzer = QueryAnalyzer::new();
+        let histogram = analyzer.duration_histogram(&[
+            0.0, 0.999, 1.0, 9.999, 10.0, 100.0, 999.9, 1_000.0, 10_000.0, 60_000.0,
+        ]);
+
+        assert_eq!(
+            histogram,
+            vec![
+                ("<1ms".to_string(), 2),
+                ("1-10ms".to_string(), 2),
+                ("10-100ms".to_string(), 1),
+                ("100ms-1s".to_string(), 2),
+                ("1-10s".to_string(), 1),
+                (">=10s".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duration_histogram_with_custom_buckets() {
+        let analyzer =
+            QueryAnalyzer::new().with_histogram_buckets([500.0, 50.0, 50.0, -1.0, 5_000.0]);
+        assert_eq!(analyzer.histogram_bounds(), &[50.0, 500.0, 5_000.0]);
+
+        let histogram = analyzer.duration_histogram(&[49.0, 50.0, 500.0, 5_000.0]);
+        assert_eq!(
+            histogram,
+            vec![
+                ("<50ms".to_string(), 1),
+                ("50-500ms".to_string(), 1),
+                ("500ms-5s".to_string(), 1),
+                (">=5s".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analysis_fills_duration_histogram() {
+        let result = QueryAnalyzer::new()
+            .analyze(&create_diverse_test_entries())
+            .unwrap();
+
+        assert_eq!(result.duration_histogram.len(), 6);
+        assert_eq!(
+            result
+                .duration_histogram
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<u64>(),
+            result.total_queries
+        );
+    }
+
+    fn incremental_analyzer() -> QueryAnalyzer {
+        QueryAnalyzer::with_settings(100.0, 5, 10)
+            .with_group_by(Some(GroupBy::User))
+            .with_linter(Some(QueryLinter::new()))
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn test_observing_entries_one_at_a_time_matches_slice_analysis() {
+        let entries = create_diverse_test_entries();
+        let analyzer = incremental_analyzer();
+
+        let mut state = analyzer.state();
+        assert_send(&state);
+        for entry in &entries {
+            state.observe(entry);
+        }
+
+        assert_eq!(
+            serde_json::to_value(state.finish()).unwrap(),
+            serde_json::to_value(analyzer.analyze(&entries).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merging_states_of_disjoint_halves_matches_whole() {
+        let entries = create_diverse_test_entries();
+        let analyzer = incremental_analyzer();
+        let (first, second) = entries.split_at(entries.len() / 2);
+
+        let mut state = analyzer.state();
+        let mut rest = analyzer.state();
+        first.iter().for_each(|entry| state.observe(entry));
+        second.iter().for_each(|entry| rest.observe(entry));
+        state.merge(rest);
+
+        assert_eq!(
+            serde_json::to_value(state.finish()).unwrap(),
+            serde_json::to_value(analyzer.analyze(&entries).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_checkpointed_state_resumes_analysis() {
+        let entries = create_diverse_test_entries();
+        let analyzer = QueryAnalyzer::with_settings(100.0, 5, 10);
+        let (first, second) = entries.split_at(entries.len() / 2);
+
+        let mut state = analyzer.state();
+        first.iter().for_each(|entry| state.observe(entry));
+        let checkpoint = serde_json::to_string(&state).unwrap();
+        let mut state: QueryAnalyzerState = serde_json::from_str(&checkpoint).unwrap();
+        second.iter().for_each(|entry| state.observe(entry));
+
+        assert_eq!(
+            serde_json::to_value(state.finish()).unwrap(),
+            serde_json::to_value(analyzer.analyze(&entries).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_timing_state_observe_and_merge_match_slice_analysis() {
+        let entries = create_diverse_test_entries();
+        let analyzer = TimingAnalyzer::new();
+        let expected = serde_json::to_value(analyzer.analyze_timing(&entries).unwrap()).unwrap();
+
+        let mut whole = analyzer.state();
+        assert_send(&whole);
+        entries.iter().for_each(|entry| whole.observe(entry));
+        assert_eq!(serde_json::to_value(whole.finish()).unwrap(), expected);
+
+        let (first, second) = entries.split_at(entries.len() / 2);
+        let mut state = analyzer.state();
+        let mut rest = analyzer.state();
+        first.iter().for_each(|entry| state.observe(entry));
+        second.iter().for_each(|entry| rest.observe(entry));
+        state.merge(rest);
+        assert_eq!(serde_json::to_value(state.finish()).unwrap(), expected);
+    }
 }
 
 #[cfg(test)]
@@ -784,6 +1844,12 @@ mod query_type_tests {
         assert_eq!(QueryType::Update.to_string(), "UPDATE");
         assert_eq!(QueryType::Delete.to_string(), "DELETE");
         assert_eq!(QueryType::DDL.to_string(), "DDL");
+        assert_eq!(QueryType::Copy.to_string(), "COPY");
+        assert_eq!(QueryType::Set.to_string(), "SET");
+        assert_eq!(QueryType::Show.to_string(), "SHOW");
+        assert_eq!(QueryType::Explain.to_string(), "EXPLAIN");
+        assert_eq!(QueryType::Maintenance.to_string(), "MAINTENANCE");
+        assert_eq!(QueryType::Transaction.to_string(), "TRANSACTION");
         assert_eq!(QueryType::Other.to_string(), "OTHER");
     }
 
@@ -803,6 +1869,52 @@ mod query_type_tests {
         assert_eq!(map.get(&QueryType::Insert), Some(&5));
         assert_eq!(map.get(&QueryType::Update), None);
     }
+
+    #[test]
+    fn test_statements_sqlparser_rejects_are_still_classified() {
+        let lines: Vec<String> = include_str!("../fixtures/cli/postgres_specific.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        let result = QueryAnalyzer::new().analyze(&entries).unwrap();
+
+        assert_eq!(result.total_queries, 7);
+        for (query_type, count) in [
+            ("SELECT", 1),
+            ("SET", 1),
+            ("COPY", 1),
+            ("EXPLAIN", 1),
+            ("OTHER", 3),
+        ] {
+            assert_eq!(
+                result.query_types.get(query_type).map(|stats| stats.count),
+                Some(count),
+                "{query_type}"
+            );
+        }
+        let frequent: Vec<_> = result
+            .most_frequent_queries
+            .iter()
+            .map(|(query, _)| query.as_str())
+            .collect();
+        for query in [
+            "SET search_path = staging, public",
+            "EXPLAIN ANALYZE SELECT * FROM staging.orders WHERE id = ?",
+        ] {
+            assert!(
+                frequent.contains(&query),
+                "{query} missing from {frequent:?}"
+            );
+        }
+        assert!(!frequent.iter().any(|query| query.starts_with("DO ")));
+        assert_eq!(result.unparsed_statements.count, 4);
+        assert!(result
+            .unparsed_statements
+            .samples
+            .contains(&"DO $$ BEGIN PERFORM pg_sleep(0.5); END $$".to_string()));
+    }
 }
 
 #[cfg(test)]
@@ -874,8 +1986,6 @@ mod property_based_analytics_tests {
     /// Property: Query normalization should reduce the number of unique queries
     #[test]
     fn property_normalization_reduces_uniqueness() {
-        let analyzer = QueryAnalyzer::new();
-
         let similar_queries = vec![
             "SELECT * FROM users WHERE id = 1",
             "SELECT * FROM users WHERE id = 2",
@@ -889,14 +1999,14 @@ mod property_based_analytics_tests {
 
         for query in similar_queries {
             unique_original.insert(query.to_string());
-            unique_normalized.insert(analyzer.normalize_query(query));
+            unique_normalized.insert(normalize(query).unwrap());
         }
 
         // Normalization should reduce uniqueness
         assert!(unique_normalized.len() < unique_original.len());
 
         // All normalized queries should be the same
-        assert_eq!(unique_normalized.len(), 2);
+        assert_eq!(unique_normalized.len(), 1);
         assert!(unique_normalized.contains("SELECT * FROM users WHERE id = ?"));
     }
 }
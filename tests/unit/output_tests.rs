@@ -4,20 +4,93 @@
 
 use chrono::{Duration, TimeZone, Utc};
 use pg_logstats::output::json::JsonFormatter;
-use pg_logstats::output::text::TextFormatter;
+use pg_logstats::output::schema::Report;
+use pg_logstats::output::text::{
+    format_count, format_duration, truncate_query, EntryLevelFilter, EntryLimit, TextFormatter,
+    DEFAULT_MAX_QUERY_LENGTH, HOURLY_BAR_WIDTH,
+};
 use pg_logstats::{
-    AnalysisResult, Finding, FindingConfidence, FindingKind, FindingMetrics, FindingSet, LogEntry,
-    LogLevel, Query, QueryFamilyFinding, ReasonCode, SourceReference, TimingAnalysis,
+    query_id, AnalysisResult, ApplicationStats, CanceledQuery, ChangeKind, ClientHostStats,
+    CopyDirection, CopyStats, CopyTableStats, Finding, FindingConfidence, FindingKind,
+    FindingMetrics, FindingSet, FrequencyEstimate, GroupBy, HourStats, HourlyStats, LogEntry,
+    LogEntryBuilder, LogLevel, QueryChange, QueryFamilyFinding, QueryGroup, QuerySnapshot,
+    QuerySort, QueryStats, QueryTypeStats, QueryWarning, ReasonCode, ReportComparison,
+    ReportSections, SlowestRankChange, SourceReference, TimeGap, TimeRange, TimingAnalysis,
+    TrafficBucket, TrafficGranularity, TrafficHistogram,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Three slowest queries out of 1000 ms of query time, the listed ones taking
+/// 500, 250, and 50 ms of it
+fn create_slowest_share_analysis() -> AnalysisResult {
+    let mut analysis = AnalysisResult::new();
+    analysis.total_queries = 20;
+    analysis.total_duration = 1000.0;
+    for (query, count, total, max) in [
+        ("SELECT a", 1, 500.0, 500.0),
+        ("SELECT b", 5, 250.0, 100.0),
+        ("SELECT c", 2, 50.0, 40.0),
+    ] {
+        analysis.slowest_queries.push((query.to_string(), max));
+        analysis.query_stats.insert(
+            query.to_string(),
+            QueryStats {
+                count,
+                total_duration: total,
+                average_duration: total / count as f64,
+                p95_duration: max,
+                examples: Vec::new(),
+            },
+        );
+    }
+    analysis
+}
+
+/// Rank changes of [`create_slowest_share_analysis`] against a baseline that
+/// listed `SELECT b` first and did not list `SELECT c`
+fn create_slowest_changes() -> Vec<SlowestRankChange> {
+    vec![
+        SlowestRankChange {
+            query_id: query_id("SELECT a"),
+            rank: 1,
+            rank_delta: Some(1),
+            p95_delta_ms: Some(300.0),
+        },
+        SlowestRankChange {
+            query_id: query_id("SELECT b"),
+            rank: 2,
+            rank_delta: Some(-1),
+            p95_delta_ms: Some(-20.0),
+        },
+        SlowestRankChange {
+            query_id: query_id("SELECT c"),
+            rank: 3,
+            rank_delta: None,
+            p95_delta_ms: None,
+        },
+    ]
+}
 
 /// Helper function to create a test AnalysisResult
 fn create_test_analysis_result() -> AnalysisResult {
-    let mut query_types = HashMap::new();
-    query_types.insert("SELECT".to_string(), 5);
-    query_types.insert("INSERT".to_string(), 3);
-    query_types.insert("UPDATE".to_string(), 2);
-    query_types.insert("DELETE".to_string(), 1);
+    let query_types: HashMap<_, _> = [
+        ("SELECT", 5, 3000.0, 2500.0),
+        ("INSERT", 3, 1500.0, 900.0),
+        ("UPDATE", 2, 800.0, 600.0),
+        ("DELETE", 1, 200.0, 200.0),
+    ]
+    .into_iter()
+    .map(|(query_type, count, total_duration, max_duration)| {
+        let stats = QueryTypeStats {
+            count,
+            total_duration,
+            average_duration: total_duration / count as f64,
+            p95_duration: max_duration,
+            max_duration,
+        };
+        (query_type.to_string(), stats)
+    })
+    .collect();
 
     let slowest_queries = vec![
         (
@@ -51,16 +124,50 @@ fn create_test_analysis_result() -> AnalysisResult {
         total_queries: 11,
         total_duration: 5500.0,
         average_duration: 500.0,
+        stddev_duration: 700.0,
+        min_duration: 20.0,
         p95_duration: 2000.0,
         p99_duration: 2400.0,
+        max_duration: 2500.0,
         error_count: 2,
+        errors_by_sqlstate: BTreeMap::new(),
         connection_count: 3,
         query_types,
         slowest_queries,
         most_frequent_queries,
+        ..AnalysisResult::new()
     }
 }
 
+/// Helper function to create an analysis whose frequent queries rank
+/// differently under each sort key
+fn create_sortable_analysis() -> AnalysisResult {
+    let mut analysis = create_test_analysis_result();
+    analysis.total_duration = 200.0;
+    analysis.most_frequent_queries.clear();
+    for (query, count, total_duration, average_duration, p95_duration) in [
+        ("SELECT a FROM t", 4, 4.0, 1.0, 1.0),
+        ("SELECT b FROM t", 2, 20.0, 10.0, 10.0),
+        ("SELECT c FROM t", 3, 17.0, 17.0 / 3.0, 15.0),
+        ("SELECT d FROM t", 1, 12.0, 12.0, 12.0),
+    ] {
+        analysis
+            .most_frequent_queries
+            .push((query.to_string(), count));
+        analysis.query_stats.insert(
+            query.to_string(),
+            QueryStats {
+                count,
+                total_duration,
+                average_duration,
+                p95_duration,
+                examples: Vec::new(),
+            },
+        );
+    }
+    analysis
+}
+
 /// Helper function to create a test TimingAnalysis
 fn create_test_timing_analysis() -> TimingAnalysis {
     let mut hourly_patterns = HashMap::new();
@@ -70,10 +177,13 @@ fn create_test_timing_analysis() -> TimingAnalysis {
     hourly_patterns.insert(14, 3200.0);
     hourly_patterns.insert(15, 2100.0);
 
+    let hourly_query_counts = HashMap::from([(9, 3), (10, 5), (11, 4), (14, 8), (15, 6)]);
+
     let mut daily_patterns = HashMap::new();
     daily_patterns.insert(0, 5000.0); // Monday
     daily_patterns.insert(1, 4500.0); // Tuesday
     daily_patterns.insert(2, 4800.0); // Wednesday
+    let daily_query_counts = HashMap::from([(0, 10), (1, 9), (2, 7)]);
 
     let mut connection_patterns = HashMap::new();
     connection_patterns.insert(9, 10);
@@ -81,15 +191,37 @@ fn create_test_timing_analysis() -> TimingAnalysis {
     connection_patterns.insert(11, 18);
 
     TimingAnalysis {
-        average_response_time: Duration::milliseconds(450),
-        p95_response_time: Duration::milliseconds(1800),
-        p99_response_time: Duration::milliseconds(2300),
+        average_response_time_ms: 450.0,
+        p95_response_time_ms: 1800.0,
+        p99_response_time_ms: 2300.0,
         hourly_patterns,
+        hourly_query_counts,
         daily_patterns,
+        daily_query_counts,
+        by_hour: BTreeMap::from([
+            (
+                Utc.with_ymd_and_hms(2024, 8, 12, 9, 0, 0).unwrap(),
+                HourStats {
+                    query_count: 3,
+                    total_duration: 1200.0,
+                    connection_count: 10,
+                },
+            ),
+            (
+                Utc.with_ymd_and_hms(2024, 8, 13, 10, 0, 0).unwrap(),
+                HourStats {
+                    query_count: 5,
+                    total_duration: 2500.0,
+                    connection_count: 25,
+                },
+            ),
+        ]),
         connection_patterns,
         peak_hours: vec![10, 14, 15],
         total_queries: 100,
+        timed_queries: 100,
         total_duration: 45000.0,
+        business_hours: Default::default(),
     }
 }
 
@@ -98,42 +230,35 @@ fn create_test_log_entries() -> Vec<LogEntry> {
     let base_time = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
 
     vec![
-        LogEntry {
-            timestamp: base_time,
-            process_id: "12345".to_string(),
-            user: Some("postgres".to_string()),
-            database: Some("testdb".to_string()),
-            client_host: None,
-            application_name: Some("psql".to_string()),
-            message_type: LogLevel::Statement,
-            message: "statement: SELECT * FROM users WHERE active = true".to_string(),
-            queries: Query::from_sql("SELECT * FROM users WHERE active = true").ok(),
-            duration: Some(150.0),
-        },
-        LogEntry {
-            timestamp: base_time + Duration::seconds(1),
-            process_id: "12346".to_string(),
-            user: Some("admin".to_string()),
-            database: Some("analytics".to_string()),
-            client_host: Some("192.168.1.100".to_string()),
-            application_name: Some("pgbench".to_string()),
-            message_type: LogLevel::Error,
-            message: "relation \"missing_table\" does not exist".to_string(),
-            queries: None,
-            duration: None,
-        },
-        LogEntry {
-            timestamp: base_time + Duration::seconds(2),
-            process_id: "12347".to_string(),
-            user: Some("app_user".to_string()),
-            database: Some("app_db".to_string()),
-            client_host: None,
-            application_name: Some("web_app".to_string()),
-            message_type: LogLevel::Duration,
-            message: "duration: 45.123 ms".to_string(),
-            queries: None,
-            duration: Some(45.123),
-        },
+        LogEntryBuilder::statement(
+            base_time,
+            "12345",
+            "SELECT * FROM users WHERE active = true",
+        )
+        .with_user(Some("postgres"))
+        .with_database(Some("testdb"))
+        .with_application_name(Some("psql"))
+        .with_duration(Some(150.0))
+        .build()
+        .unwrap(),
+        LogEntry::builder(
+            base_time + Duration::seconds(1),
+            "12346",
+            LogLevel::Error,
+            "relation \"missing_table\" does not exist",
+        )
+        .with_user(Some("admin"))
+        .with_database(Some("analytics"))
+        .with_client_host(Some("192.168.1.100"))
+        .with_application_name(Some("pgbench"))
+        .build()
+        .unwrap(),
+        LogEntryBuilder::duration(base_time + Duration::seconds(2), "12347", 45.123)
+            .with_user(Some("app_user"))
+            .with_database(Some("app_db"))
+            .with_application_name(Some("web_app"))
+            .build()
+            .unwrap(),
     ]
 }
 
@@ -199,6 +324,121 @@ mod text_formatter_tests {
         assert!(!formatter.is_color_enabled());
     }
 
+    #[test]
+    fn test_slowest_queries_show_calls_share_and_baseline_rank() {
+        let analysis = create_slowest_share_analysis();
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("Duration (ms)     Calls  % Total  ID"));
+        assert!(output.contains(&format!(
+            "       500.00         1   50.00%  {}  SELECT a",
+            &query_id("SELECT a")[..8]
+        )));
+        assert!(output.contains("         5   25.00%"));
+        assert!(output.contains("         2    5.00%"));
+        assert!(!output.contains("vs Baseline"));
+
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .with_slowest_changes(create_slowest_changes())
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("% Total  vs Baseline         ID"));
+        assert!(output.contains("50.00%  \u{2191}1 p95 +300.00      "));
+        assert!(output.contains("25.00%  \u{2193}1 p95 -20.00       "));
+        assert!(output.contains(" 5.00%  new                 "));
+    }
+
+    #[test]
+    fn test_color_highlights_slow_durations_and_errors() {
+        let mut analysis = AnalysisResult::new();
+        analysis.total_queries = 2;
+        analysis.p95_duration = 1500.0;
+        analysis.p99_duration = 1500.0;
+        analysis.error_count = 3;
+        analysis.slowest_queries = vec![
+            ("SELECT pg_sleep(2)".to_string(), 1500.0),
+            ("SELECT 1".to_string(), 12.5),
+        ];
+
+        let output = TextFormatter::new()
+            .with_color(true)
+            .with_human_units(false)
+            .with_slow_query_threshold(1000.0)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("P95 Duration: \x1b[31;1m1500.00 ms\x1b[0m"));
+        assert!(output.contains("Error Count: \x1b[31;1m3\x1b[0m"));
+        assert!(output.contains(&format!(
+            "\x1b[31;1m     1500.00\x1b[0m         -        -  {}  SELECT pg_sleep(2)",
+            &query_id("SELECT pg_sleep(2)")[..8]
+        )));
+        assert!(output.contains(&format!(
+            "        12.50         -        -  {}  SELECT 1",
+            &query_id("SELECT 1")[..8]
+        )));
+
+        analysis.error_count = 0;
+        let output = TextFormatter::new()
+            .with_color(true)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("Error Count: 0\n"));
+        assert!(output.contains("P95 Duration: 1.50 s\n"));
+
+        let output = TextFormatter::new()
+            .with_slow_query_threshold(1000.0)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(!output.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_truncate_query_is_utf8_safe() {
+        assert_eq!(truncate_query("SELECT 1", 8), "SELECT 1");
+        assert_eq!(truncate_query("SELECT 1", 5), "SELE…");
+
+        let query = "SELECT * FROM users WHERE name = '测试用户'";
+        let truncated = truncate_query(query, 37);
+        assert_eq!(truncated, "SELECT * FROM users WHERE name = '测试…");
+        assert_eq!(truncated.chars().count(), 37);
+    }
+
+    #[test]
+    fn test_format_query_analysis_truncates_long_queries() {
+        let long_query = format!(
+            "SELECT {} FROM 测试用户",
+            (0..60)
+                .map(|i| format!("column_{i}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let mut analysis = create_test_analysis_result();
+        analysis.slowest_queries = vec![(long_query.clone(), 5000.0)];
+        analysis.most_frequent_queries = vec![(long_query.clone(), 9)];
+
+        let formatter = TextFormatter::new();
+        assert_eq!(formatter.max_query_length(), Some(DEFAULT_MAX_QUERY_LENGTH));
+        let output = formatter.format_query_analysis(&analysis).unwrap();
+        assert!(!output.contains(&long_query));
+        let shortened = truncate_query(&long_query, DEFAULT_MAX_QUERY_LENGTH);
+        assert_eq!(output.matches(shortened.as_ref()).count(), 2);
+
+        let output = TextFormatter::new()
+            .with_max_query_length(20)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("  SELECT column_0, co…\n"));
+
+        let output = TextFormatter::new()
+            .with_wide(true)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert_eq!(output.matches(long_query.as_str()).count(), 2);
+    }
+
     #[test]
     fn test_format_query_analysis_basic() {
         let formatter = TextFormatter::new();
@@ -211,14 +451,117 @@ mod text_formatter_tests {
 
         // Check that basic statistics are included
         assert!(output.contains("Total Queries: 11"));
-        assert!(output.contains("Total Duration: 5500.00 ms"));
+        assert!(output.contains("Total Duration: 5.50 s"));
         assert!(output.contains("Average Duration: 500.00 ms"));
-        assert!(output.contains("P95 Duration: 2000.00 ms"));
-        assert!(output.contains("P99 Duration: 2400.00 ms"));
+        assert!(output.contains("P95 Duration: 2.00 s"));
+        assert!(output.contains("P99 Duration: 2.40 s"));
+        assert!(output.contains("Std Dev Duration: 700.00 ms"));
+        assert!(output.contains("Min Duration: 20.00 ms"));
+        assert!(output.contains("Max Duration: 2.50 s"));
         assert!(output.contains("Error Count: 2"));
         assert!(output.contains("Connection Count: 3"));
     }
 
+    #[test]
+    fn test_format_query_analysis_errors_by_sqlstate() {
+        let formatter = TextFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        analysis.add_error_with_sqlstate(Some("23505"));
+        analysis.add_error_with_sqlstate(Some("23503"));
+        analysis.add_error_with_sqlstate(Some("40P01"));
+        analysis.add_error_with_sqlstate(None);
+
+        let output = formatter.format_query_analysis(&analysis).unwrap();
+
+        assert!(output.contains("Errors by SQLSTATE:"));
+        assert!(output
+            .contains("        23         2  Integrity Constraint Violation (23503 x1, 23505 x1)"));
+        assert!(output.contains("        40         1  Transaction Rollback (40P01 x1)"));
+        assert!(output.contains("   unknown         1  Unknown"));
+    }
+
+    #[test]
+    fn test_format_query_analysis_failing_statements_per_class() {
+        let formatter = TextFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        for statement in [
+            "INSERT INTO t VALUES (?)",
+            "INSERT INTO t VALUES (?)",
+            "DELETE FROM t",
+        ] {
+            analysis.add_error_with_statement(Some("23505"), Some(statement));
+        }
+
+        let output = formatter.format_query_analysis(&analysis).unwrap();
+
+        let class = output.find("        23         3").unwrap();
+        let insert = output
+            .find("                  2x  INSERT INTO t VALUES (?)")
+            .unwrap();
+        let delete = output.find("                  1x  DELETE FROM t").unwrap();
+        assert!(class < insert && insert < delete);
+    }
+
+    #[test]
+    fn test_format_query_analysis_errors_by_severity() {
+        let formatter = TextFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        analysis.errors_by_severity =
+            BTreeMap::from([("ERROR".to_string(), 3), ("FATAL".to_string(), 1)]);
+
+        let output = formatter.format_query_analysis(&analysis).unwrap();
+
+        assert!(output.contains("Errors by Severity:"));
+        assert!(output.contains("  ERROR            3\n"));
+        assert!(output.contains("  FATAL            1\n"));
+    }
+
+    #[test]
+    fn test_format_query_analysis_time_range() {
+        let formatter = TextFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        let start = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        analysis.time_range = Some(TimeRange {
+            start,
+            end: start + Duration::minutes(150),
+            span_seconds: 9_000.0,
+            queries_per_second: 0.5,
+            active_days: 1,
+            active_hours: 2,
+            gaps: vec![TimeGap {
+                start: start + Duration::minutes(30),
+                end: start + Duration::minutes(125),
+                minutes: 95.0,
+            }],
+        });
+
+        let output = formatter.format_query_analysis(&analysis).unwrap();
+
+        let header = output.find("Time Range:").unwrap();
+        assert!(header < output.find("Total Queries:").unwrap());
+        assert!(output.contains(
+            "Time Range: 2024-08-15 10:00:00 UTC to 2024-08-15 12:30:00 UTC (2h 30m 0s)\n"
+        ));
+        assert!(output.contains("Active Days: 1, Active Hours: 2\n"));
+        assert!(output.contains("Queries per Second: 0.50\n"));
+        assert!(output.contains(
+            "Warning: no entries for 1h 35m 0s between 2024-08-15 10:30:00 UTC and 2024-08-15 12:05:00 UTC"
+        ));
+
+        let empty = formatter
+            .format_query_analysis(&AnalysisResult::new())
+            .unwrap();
+        assert!(!empty.contains("Time Range:"));
+
+        let berlin = TextFormatter::new()
+            .with_display_timezone(Some(pg_logstats::parse_timezone("Europe/Berlin").unwrap()))
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(berlin.contains(
+            "Time Range: 2024-08-15 12:00:00 CEST to 2024-08-15 14:30:00 CEST (2h 30m 0s)\n"
+        ));
+    }
+
     #[test]
     fn test_format_query_analysis_query_types() {
         let formatter = TextFormatter::new();
@@ -231,15 +574,25 @@ mod text_formatter_tests {
 
         // Check query types section
         assert!(output.contains("Query Types:"));
-        assert!(output.contains("SELECT: 5"));
-        assert!(output.contains("INSERT: 3"));
-        assert!(output.contains("UPDATE: 2"));
-        assert!(output.contains("DELETE: 1"));
+        assert!(output.contains(
+            "  Type             Count           Total         Avg         P95         Max\n"
+        ));
+        assert!(output.contains(
+            "  SELECT               5          3.00 s   600.00 ms      2.50 s      2.50 s\n"
+        ));
+        assert!(output.contains(
+            "  DELETE               1       200.00 ms   200.00 ms   200.00 ms   200.00 ms\n"
+        ));
+        // Canonical type order, whatever the counts
+        let position = |query_type: &str| output.find(&format!("  {query_type} ")).unwrap();
+        assert!(position("SELECT") < position("INSERT"));
+        assert!(position("INSERT") < position("UPDATE"));
+        assert!(position("UPDATE") < position("DELETE"));
     }
 
     #[test]
     fn test_format_query_analysis_slowest_queries() {
-        let formatter = TextFormatter::new();
+        let formatter = TextFormatter::new().with_human_units(false);
         let analysis = create_test_analysis_result();
 
         let result = formatter.format_query_analysis(&analysis);
@@ -255,6 +608,11 @@ mod text_formatter_tests {
         assert!(output.contains("800.00"));
         assert!(output.contains("SELECT * FROM large_table"));
         assert!(output.contains("UPDATE users SET last_login"));
+
+        // Rows carry the short query id ahead of the SQL
+        assert!(output.contains("  ID        Query"));
+        let (query, _) = &analysis.slowest_queries[0];
+        assert!(output.contains(&format!("  {}  {}", &query_id(query)[..8], query)));
     }
 
     #[test]
@@ -278,6 +636,39 @@ mod text_formatter_tests {
         assert!(output.contains("SELECT COUNT(*) FROM orders"));
     }
 
+    #[test]
+    fn test_frequent_queries_follow_query_sort() {
+        let analysis = create_sortable_analysis();
+        let order = |sort: QuerySort| -> String {
+            let output = TextFormatter::new()
+                .with_query_sort(sort)
+                .format_query_analysis(&analysis)
+                .unwrap();
+            let section = output.split("Most Frequent Queries").nth(1).unwrap();
+            ["a", "b", "c", "d"]
+                .iter()
+                .map(|name| {
+                    let position = section.find(&format!("SELECT {} FROM t", name)).unwrap();
+                    (position, *name)
+                })
+                .collect::<BTreeMap<_, _>>()
+                .into_values()
+                .collect()
+        };
+
+        assert_eq!(order(QuerySort::Count), "acbd");
+        assert_eq!(order(QuerySort::TotalTime), "bcda");
+        assert_eq!(order(QuerySort::AvgTime), "dbca");
+        assert_eq!(order(QuerySort::P95), "cdba");
+
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("Total (ms)  % Total    Avg (ms)"));
+        assert!(output.contains("10.00%"));
+    }
+
     #[test]
     fn test_format_query_analysis_with_color() {
         let formatter = TextFormatter::new().with_color(true);
@@ -328,9 +719,68 @@ mod text_formatter_tests {
 
         // Check timing analysis content
         assert!(output.contains("Timing Analysis Report"));
-        assert!(output.contains("Average Response Time: 450ms"));
-        assert!(output.contains("95th Percentile: 1800ms"));
-        assert!(output.contains("99th Percentile: 2300ms"));
+        assert!(output.contains("Average Response Time: 450.00 ms"));
+        assert!(output.contains("95th Percentile: 1.80 s"));
+        assert!(output.contains("99th Percentile: 2.30 s"));
+        assert!(output.contains(
+            "Peak Usage:\n  Busiest Hour: 14:00 (8 queries)\n  Peak Hours: 10:00, 14:00, 15:00\n"
+        ));
+
+        let without_hourly = TextFormatter::new()
+            .with_sections(ReportSections::all().without(ReportSections::HOURLY))
+            .format_timing_analysis(&timing)
+            .unwrap();
+        assert!(!without_hourly.contains("Peak Usage:"));
+        let idle = formatter
+            .format_timing_analysis(&TimingAnalysis::default())
+            .unwrap();
+        assert!(!idle.contains("Peak Usage:"));
+    }
+
+    #[test]
+    fn test_response_times_keep_fractional_milliseconds() {
+        let mut timing = create_test_timing_analysis();
+        timing.average_response_time_ms = 408.875;
+        timing.p95_response_time_ms = 912.5;
+
+        let text = TextFormatter::new()
+            .with_human_units(false)
+            .format_timing_analysis(&timing)
+            .unwrap();
+        assert!(text.contains("Average Response Time: 408.88 ms"));
+        assert!(text.contains("95th Percentile: 912.50 ms"));
+
+        let json_str = JsonFormatter::new()
+            .format_with_timing(&create_test_analysis_result(), &timing)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(
+            json["temporal_analysis"]["average_response_time_ms"],
+            408.875
+        );
+        assert_eq!(json["temporal_analysis"]["p95_response_time_ms"], 912.5);
+    }
+
+    #[test]
+    fn test_format_timing_analysis_lists_each_date() {
+        let mut timing = create_test_timing_analysis();
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_timing_analysis(&timing)
+            .unwrap();
+
+        assert!(output.contains("Daily Activity:"));
+        assert!(output.contains(
+            "  2024-08-12 Mon         3         1200.00           10\n  \
+             2024-08-13 Tue         5         2500.00           25\n"
+        ));
+
+        // A log within one date has nothing to add to the hourly table
+        timing.by_hour.pop_last();
+        let single_date = TextFormatter::new()
+            .format_timing_analysis(&timing)
+            .unwrap();
+        assert!(!single_date.contains("Daily Activity:"));
     }
 
     #[test]
@@ -348,6 +798,55 @@ mod text_formatter_tests {
         assert!(output.contains("SQL: SELECT ?"));
     }
 
+    #[test]
+    fn test_format_comparison() {
+        let snapshot = |count, avg_duration_ms: f64| QuerySnapshot {
+            count,
+            avg_duration_ms,
+            p95_duration_ms: Some(avg_duration_ms * 2.0),
+            total_duration_ms: avg_duration_ms * count as f64,
+        };
+        let comparison = ReportComparison {
+            threshold_percent: 20.0,
+            changes: vec![
+                QueryChange {
+                    kind: ChangeKind::Regressed,
+                    query: "SELECT * FROM users WHERE id = ?".to_string(),
+                    query_id: query_id("SELECT * FROM users WHERE id = ?"),
+                    baseline: Some(snapshot(10, 5.0)),
+                    current: Some(snapshot(12, 10.0)),
+                    count_change_percent: Some(20.0),
+                    avg_change_percent: Some(100.0),
+                    p95_change_percent: Some(100.0),
+                    time_delta_ms: 70.0,
+                },
+                QueryChange {
+                    kind: ChangeKind::Disappeared,
+                    query: "DELETE FROM sessions".to_string(),
+                    query_id: query_id("DELETE FROM sessions"),
+                    baseline: Some(snapshot(2, 3.0)),
+                    current: None,
+                    count_change_percent: None,
+                    avg_change_percent: None,
+                    p95_change_percent: None,
+                    time_delta_ms: -6.0,
+                },
+            ],
+            slowest: Vec::new(),
+        };
+
+        let output = TextFormatter::new().format_comparison(&comparison).unwrap();
+
+        assert!(output.contains("Changes Since Baseline (over 20%)"));
+        assert!(output.contains("Regressed: 1  Improved: 0  New: 0  Disappeared: 1"));
+        assert!(output.contains("regressed"));
+        assert!(output.contains("+70.00"));
+        assert!(output.contains(
+            "count 10 -> 12 (+20%), avg 5.00 -> 10.00 ms (+100%), p95 10.00 -> 20.00 ms (+100%)"
+        ));
+        assert!(output.contains("count 2, avg 3.00 ms in baseline"));
+    }
+
     #[test]
     fn test_format_log_entries() {
         let formatter = TextFormatter::new();
@@ -368,6 +867,29 @@ mod text_formatter_tests {
         assert!(output.contains("duration: 45.123 ms"));
     }
 
+    #[test]
+    fn test_format_log_entries_lists_batch_statements() {
+        let formatter = TextFormatter::new();
+        let timestamp = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 0).unwrap();
+        let entries = vec![LogEntryBuilder::statement(
+            timestamp,
+            "12345",
+            "BEGIN; UPDATE accounts SET balance = 0 WHERE id = 1; COMMIT",
+        )
+        .build()
+        .unwrap()];
+
+        let output = formatter.format_log_entries(&entries).unwrap();
+        assert!(output.contains("    1/3 TRANSACTION: BEGIN"));
+        assert!(output.contains("    2/3 UPDATE: UPDATE accounts SET balance = 0 WHERE id = 1"));
+        assert!(output.contains("    3/3 TRANSACTION: COMMIT"));
+
+        let single = formatter
+            .format_log_entries(&create_test_log_entries())
+            .unwrap();
+        assert!(!single.contains("1/1"));
+    }
+
     #[test]
     fn test_format_log_entries_empty() {
         let formatter = TextFormatter::new();
@@ -380,6 +902,64 @@ mod text_formatter_tests {
         assert!(output.contains("Log Entries (0 total)"));
     }
 
+    #[test]
+    fn test_format_log_entries_limit_and_tail() {
+        let entries: Vec<_> = (0..30).flat_map(|_| create_test_log_entries()).collect();
+        let listed = |output: &str| output.lines().filter(|line| line.starts_with('[')).count();
+
+        let head = TextFormatter::new()
+            .with_limit(Some(EntryLimit::Head(5)))
+            .format_log_entries(&entries)
+            .unwrap();
+        assert!(head.contains("Log Entries (90 total)"));
+        assert_eq!(listed(&head), 5);
+        assert!(head.contains("[5] "));
+        assert!(head.trim_end().ends_with("... 85 later entries omitted"));
+
+        let tail = TextFormatter::new()
+            .with_limit(Some(EntryLimit::Tail(4)))
+            .format_log_entries(&entries)
+            .unwrap();
+        assert_eq!(listed(&tail), 4);
+        assert!(tail.contains("  ... 86 earlier entries omitted\n[87] "));
+        assert!(tail.contains("[90] "));
+
+        let all = TextFormatter::new()
+            .with_limit(Some(EntryLimit::Tail(90)))
+            .format_log_entries(&entries)
+            .unwrap();
+        assert_eq!(listed(&all), 90);
+        assert!(!all.contains("omitted"));
+    }
+
+    #[test]
+    fn test_format_log_entries_level_filter() {
+        let entries: Vec<_> = (0..10).flat_map(|_| create_test_log_entries()).collect();
+        let format = |level, limit| {
+            TextFormatter::new()
+                .with_level_filter(Some(level))
+                .with_limit(limit)
+                .format_log_entries(&entries)
+                .unwrap()
+        };
+
+        let errors = format(EntryLevelFilter::Error, None);
+        assert!(errors.contains("Log Entries (30 total, 10 errors)"));
+        assert_eq!(errors.matches(" ERROR: ").count(), 10);
+        assert!(!errors.contains("STATEMENT:"));
+        assert!(errors.contains("[2] ") && errors.contains("[29] "));
+
+        let statements = format(EntryLevelFilter::Statement, Some(EntryLimit::Head(3)));
+        assert!(statements.contains("Log Entries (30 total, 10 statements)"));
+        assert_eq!(statements.matches(" STATEMENT: ").count(), 3);
+        assert!(statements.contains("[7] 2024-08-15 10:30:00 STATEMENT:"));
+        assert!(statements.contains("... 7 later entries omitted"));
+
+        let warnings = format(EntryLevelFilter::Warning, Some(EntryLimit::Head(3)));
+        assert!(warnings.contains("Log Entries (30 total, 0 warnings)"));
+        assert!(!warnings.contains("omitted"));
+    }
+
     #[test]
     fn test_bold_function_no_color() {
         let result = pg_logstats::output::text::bold("test", Some("red"), false);
@@ -471,10 +1051,275 @@ mod json_formatter_tests {
         assert_eq!(json["summary"]["connection_count"], 3);
 
         // Check query analysis
-        assert_eq!(json["query_analysis"]["by_type"]["SELECT"], 5);
-        assert_eq!(json["query_analysis"]["by_type"]["INSERT"], 3);
-        assert_eq!(json["query_analysis"]["by_type"]["UPDATE"], 2);
-        assert_eq!(json["query_analysis"]["by_type"]["DELETE"], 1);
+        assert_eq!(
+            json["query_analysis"]["by_type"]["SELECT"],
+            serde_json::json!({
+                "count": 5,
+                "total_ms": 3000.0,
+                "avg_ms": 600.0,
+                "p95_ms": 2500.0,
+                "max_ms": 2500.0
+            })
+        );
+        assert_eq!(json["query_analysis"]["by_type"]["INSERT"]["count"], 3);
+        assert_eq!(json["query_analysis"]["by_type"]["UPDATE"]["avg_ms"], 400.0);
+        assert_eq!(json["query_analysis"]["by_type"]["DELETE"]["max_ms"], 200.0);
+        assert_eq!(
+            json["query_analysis"]["by_type_counts"],
+            serde_json::json!({"DELETE": 1, "INSERT": 3, "SELECT": 5, "UPDATE": 2})
+        );
+        assert_eq!(json["metadata"]["schema_version"], 3);
+    }
+
+    #[test]
+    fn test_reports_with_flat_type_counts_still_parse() {
+        let formatter = JsonFormatter::new();
+        let mut json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&create_test_analysis_result()).unwrap())
+                .unwrap();
+        json["metadata"]
+            .as_object_mut()
+            .unwrap()
+            .remove("schema_version");
+        json["query_analysis"]["by_type"] = serde_json::json!({"SELECT": 5, "INSERT": 3});
+        json["query_analysis"]
+            .as_object_mut()
+            .unwrap()
+            .remove("by_type_counts");
+
+        let report: Report = serde_json::from_value(json).unwrap();
+        assert_eq!(report.metadata.schema_version, 1);
+        let query_analysis = report.query_analysis.unwrap();
+        let by_type = query_analysis.by_type.unwrap();
+        assert_eq!(by_type["SELECT"].count, 5);
+        assert_eq!(by_type["INSERT"].count, 3);
+        assert_eq!(by_type["INSERT"].avg_ms, None);
+        assert_eq!(query_analysis.by_type_counts, None);
+    }
+
+    #[test]
+    fn test_reports_with_null_durations_parse() {
+        let formatter = JsonFormatter::new();
+        let mut json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&create_test_analysis_result()).unwrap())
+                .unwrap();
+        for key in [
+            "total_duration_ms",
+            "avg_duration_ms",
+            "stddev_duration_ms",
+            "min_duration_ms",
+            "p95_duration_ms",
+            "p99_duration_ms",
+            "max_duration_ms",
+        ] {
+            json["summary"][key] = serde_json::Value::Null;
+        }
+        json["query_analysis"]["by_type"] = serde_json::json!({
+            "SELECT": {"count": 5, "total_ms": null, "avg_ms": null, "p95_ms": null, "max_ms": null}
+        });
+
+        let report: Report = serde_json::from_value(json).unwrap();
+        assert_eq!(report.metadata.schema_version, 3);
+        let summary = report.summary.unwrap();
+        assert_eq!(summary.total_duration_ms, Some(None));
+        assert_eq!(summary.avg_duration_ms, Some(None));
+        assert_eq!(summary.p99_duration_ms, Some(None));
+        assert_eq!(summary.max_duration_ms, Some(None));
+        let by_type = report.query_analysis.unwrap().by_type.unwrap();
+        assert_eq!(by_type["SELECT"].count, 5);
+        assert_eq!(by_type["SELECT"].total_ms, None);
+        assert_eq!(by_type["SELECT"].avg_ms, None);
+    }
+
+    #[test]
+    fn test_stable_schema_writes_every_query_type() {
+        let mut analysis = AnalysisResult::new();
+        analysis.query_types.insert(
+            "SELECT".to_string(),
+            QueryTypeStats {
+                count: 4,
+                total_duration: 40.0,
+                average_duration: 10.0,
+                p95_duration: 20.0,
+                max_duration: 20.0,
+            },
+        );
+        let render = |formatter: JsonFormatter| -> serde_json::Value {
+            serde_json::from_str(&formatter.format(&analysis).unwrap()).unwrap()
+        };
+
+        // Without the flag only the types seen are written
+        let json = render(JsonFormatter::new());
+        assert_eq!(
+            json["query_analysis"]["by_type_counts"],
+            serde_json::json!({"SELECT": 4})
+        );
+        assert!(json["query_analysis"].get("other_detail").is_none());
+
+        let json = render(JsonFormatter::new().with_stable_schema(true));
+        let counts = json["query_analysis"]["by_type_counts"]
+            .as_object()
+            .unwrap();
+        let keys: Vec<&str> = counts.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            [
+                "COPY",
+                "DDL",
+                "DELETE",
+                "EXPLAIN",
+                "INSERT",
+                "MAINTENANCE",
+                "OTHER",
+                "SELECT",
+                "SET",
+                "SHOW",
+                "TRANSACTION",
+                "UPDATE"
+            ]
+        );
+        assert_eq!(counts["SELECT"], 4);
+        assert_eq!(counts["DDL"], 0);
+        assert_eq!(
+            json["query_analysis"]["by_type"]["UPDATE"],
+            serde_json::json!({
+                "count": 0,
+                "total_ms": 0.0,
+                "avg_ms": 0.0,
+                "p95_ms": 0.0,
+                "max_ms": 0.0
+            })
+        );
+        assert_eq!(
+            json["query_analysis"]["other_detail"],
+            serde_json::json!({})
+        );
+    }
+
+    #[test]
+    fn test_stable_schema_nests_unknown_types_under_other_detail() {
+        let mut analysis = AnalysisResult::new();
+        for (query_type, count, total_duration) in [("OTHER", 1, 4.0), ("MERGE", 3, 30.0)] {
+            analysis.query_types.insert(
+                query_type.to_string(),
+                QueryTypeStats {
+                    count,
+                    total_duration,
+                    average_duration: total_duration / count as f64,
+                    p95_duration: total_duration,
+                    max_duration: total_duration,
+                },
+            );
+        }
+
+        let formatter = JsonFormatter::new().with_stable_schema(true);
+        let json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&analysis).unwrap()).unwrap();
+        let query_analysis = &json["query_analysis"];
+        assert!(query_analysis["by_type"].get("MERGE").is_none());
+        assert_eq!(query_analysis["other_detail"]["MERGE"]["count"], 3);
+        assert_eq!(query_analysis["by_type"]["OTHER"]["count"], 4);
+        assert_eq!(query_analysis["by_type"]["OTHER"]["total_ms"], 34.0);
+        assert_eq!(query_analysis["by_type"]["OTHER"]["avg_ms"], 8.5);
+        assert_eq!(query_analysis["by_type"]["OTHER"]["max_ms"], 30.0);
+        assert_eq!(query_analysis["by_type_counts"]["OTHER"], 4);
+
+        let report: Report = serde_json::from_value(json).unwrap();
+        let other_detail = report.query_analysis.unwrap().other_detail.unwrap();
+        assert_eq!(other_detail["MERGE"].count, 3);
+    }
+
+    #[test]
+    fn test_format_errors_by_sqlstate() {
+        let formatter = JsonFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        analysis.add_error_with_sqlstate(Some("57014"));
+        analysis.add_error_with_sqlstate(Some("57014"));
+        analysis.add_error_with_sqlstate(None);
+        // Five bytes, but not a SQLSTATE
+        analysis.add_error_with_sqlstate(Some("1é23"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&analysis).unwrap()).unwrap();
+
+        assert_eq!(json["summary"]["error_count"], 4);
+        let errors = &json["errors_by_sqlstate"];
+        assert_eq!(errors["57"]["class_name"], "Operator Intervention");
+        assert_eq!(errors["57"]["count"], 2);
+        assert_eq!(errors["57"]["codes"]["57014"], 2);
+        assert_eq!(errors["unknown"]["count"], 2);
+    }
+
+    #[test]
+    fn test_format_errors_by_sqlstate_lists_failing_statements() {
+        let mut analysis = AnalysisResult::new();
+        analysis.add_error_with_statement(Some("42P01"), Some("SELECT * FROM nope"));
+        analysis.add_error_with_statement(Some("42P01"), None);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&JsonFormatter::new().format(&analysis).unwrap()).unwrap();
+
+        assert_eq!(json["errors_by_sqlstate"]["42"]["count"], 2);
+        assert_eq!(
+            json["errors_by_sqlstate"]["42"]["statements"],
+            serde_json::json!({"SELECT * FROM nope": 1})
+        );
+    }
+
+    #[test]
+    fn test_format_errors_by_severity() {
+        let formatter = JsonFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        analysis.errors_by_severity =
+            BTreeMap::from([("FATAL".to_string(), 2), ("PANIC".to_string(), 1)]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&analysis).unwrap()).unwrap();
+
+        assert_eq!(
+            json["errors_by_severity"],
+            serde_json::json!({"FATAL": 2, "PANIC": 1})
+        );
+    }
+
+    #[test]
+    fn test_format_time_range() {
+        let formatter = JsonFormatter::new();
+        let mut analysis = AnalysisResult::new();
+        let start = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        analysis.time_range = Some(TimeRange {
+            start,
+            end: start + Duration::hours(2),
+            span_seconds: 7_200.0,
+            queries_per_second: 0.25,
+            active_days: 1,
+            active_hours: 3,
+            gaps: vec![TimeGap {
+                start,
+                end: start + Duration::hours(2),
+                minutes: 120.0,
+            }],
+        });
+
+        let json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&analysis).unwrap()).unwrap();
+
+        assert_eq!(
+            json["summary"]["time_range"],
+            serde_json::json!({
+                "start": "2024-08-15T10:00:00Z",
+                "end": "2024-08-15T12:00:00Z",
+                "span_seconds": 7200.0,
+                "queries_per_second": 0.25,
+                "active_days": 1,
+                "active_hours": 3,
+                "gaps": [{
+                    "start": "2024-08-15T10:00:00Z",
+                    "end": "2024-08-15T12:00:00Z",
+                    "minutes": 120.0
+                }]
+            })
+        );
     }
 
     #[test]
@@ -500,6 +1345,11 @@ mod json_formatter_tests {
             .contains("SELECT * FROM large_table"));
         assert_eq!(first["duration_ms"], 2500.0);
         assert_eq!(first["count"], 1); // Default count
+        assert_eq!(
+            first["query_id"],
+            query_id(first["query"].as_str().unwrap()).as_str()
+        );
+        assert_eq!(first["query_id"].as_str().unwrap().len(), 16);
     }
 
     #[test]
@@ -527,6 +1377,114 @@ mod json_formatter_tests {
         assert_eq!(first["avg_duration_ms"], 500.0); // Overall average
     }
 
+    #[test]
+    fn test_format_most_frequent_queries_with_query_stats() {
+        let formatter = JsonFormatter::new();
+        let mut analysis = create_test_analysis_result();
+        let (query, count) = analysis.most_frequent_queries[0].clone();
+        analysis.query_stats.insert(
+            query,
+            QueryStats {
+                count,
+                total_duration: 300.0,
+                average_duration: 20.0,
+                p95_duration: 45.0,
+                examples: Vec::new(),
+            },
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&formatter.format(&analysis).unwrap()).unwrap();
+
+        let frequent = &json["query_analysis"]["most_frequent"];
+        assert_eq!(frequent[0]["avg_duration_ms"], 20.0);
+        assert_eq!(frequent[0]["p95_duration_ms"], 45.0);
+        assert_eq!(frequent[0]["total_duration_ms"], 300.0);
+        // Queries without stats keep the overall average
+        assert_eq!(frequent[1]["avg_duration_ms"], 500.0);
+        assert!(frequent[1].get("p95_duration_ms").is_none());
+    }
+
+    #[test]
+    fn test_slowest_queries_share_of_total_time() {
+        let analysis = create_slowest_share_analysis();
+        let json: serde_json::Value =
+            serde_json::from_str(&JsonFormatter::new().format(&analysis).unwrap()).unwrap();
+
+        let slowest = json["query_analysis"]["slowest_queries"]
+            .as_array()
+            .unwrap();
+        let shares: Vec<_> = slowest
+            .iter()
+            .map(|entry| {
+                (
+                    entry["count"].as_u64().unwrap(),
+                    entry["pct_of_total_time"].as_f64().unwrap(),
+                )
+            })
+            .collect();
+        // The top three take 80% of the time; the rest went to unlisted queries
+        assert_eq!(shares, vec![(1, 50.0), (5, 25.0), (2, 5.0)]);
+        assert_eq!(slowest[1]["total_duration_ms"], 250.0);
+        assert_eq!(slowest[1]["p95_duration_ms"], 100.0);
+        assert!(slowest[0].get("rank_delta").is_none());
+
+        let json: serde_json::Value = serde_json::from_str(
+            &JsonFormatter::new()
+                .with_slowest_changes(create_slowest_changes())
+                .format(&analysis)
+                .unwrap(),
+        )
+        .unwrap();
+        let slowest = &json["query_analysis"]["slowest_queries"];
+        assert_eq!(slowest[0]["rank_delta"], 1);
+        assert_eq!(slowest[0]["p95_delta_ms"], 300.0);
+        assert_eq!(slowest[1]["rank_delta"], -1);
+        assert_eq!(slowest[1]["p95_delta_ms"], -20.0);
+        assert!(slowest[2].get("rank_delta").is_none());
+    }
+
+    #[test]
+    fn test_most_frequent_queries_follow_query_sort() {
+        let analysis = create_sortable_analysis();
+        let order = |sort: QuerySort| -> Vec<String> {
+            let json: serde_json::Value = serde_json::from_str(
+                &JsonFormatter::new()
+                    .with_query_sort(sort)
+                    .format(&analysis)
+                    .unwrap(),
+            )
+            .unwrap();
+            json["query_analysis"]["most_frequent"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["query"].as_str().unwrap()[7..8].to_string())
+                .collect()
+        };
+
+        assert_eq!(order(QuerySort::Count), ["a", "c", "b", "d"]);
+        assert_eq!(order(QuerySort::TotalTime), ["b", "c", "d", "a"]);
+        assert_eq!(order(QuerySort::AvgTime), ["d", "b", "c", "a"]);
+        assert_eq!(order(QuerySort::P95), ["c", "d", "b", "a"]);
+
+        let json: serde_json::Value = serde_json::from_str(
+            &JsonFormatter::new()
+                .with_query_sort(QuerySort::TotalTime)
+                .format(&analysis)
+                .unwrap(),
+        )
+        .unwrap();
+        let first = &json["query_analysis"]["most_frequent"][0];
+        assert_eq!(first["count"], 2);
+        assert_eq!(first["total_duration_ms"], 20.0);
+        assert_eq!(first["avg_duration_ms"], 10.0);
+        assert_eq!(first["p95_duration_ms"], 10.0);
+        assert_eq!(first["percent_of_total_duration"], 10.0);
+        let third = &json["query_analysis"]["most_frequent"][2];
+        assert_eq!(third["percent_of_total_duration"], 6.0);
+    }
+
     #[test]
     fn test_format_with_timing() {
         let formatter = JsonFormatter::new();
@@ -541,14 +1499,38 @@ mod json_formatter_tests {
 
         // Should have temporal analysis section
         assert!(json["temporal_analysis"].is_object());
-        assert_eq!(json["temporal_analysis"]["average_response_time_ms"], 450);
-        assert_eq!(json["temporal_analysis"]["p95_response_time_ms"], 1800);
-        assert_eq!(json["temporal_analysis"]["p99_response_time_ms"], 2300);
+        assert_eq!(json["temporal_analysis"]["average_response_time_ms"], 450.0);
+        assert_eq!(json["temporal_analysis"]["p95_response_time_ms"], 1800.0);
+        assert_eq!(json["temporal_analysis"]["p99_response_time_ms"], 2300.0);
 
         // Check hourly stats
         let hourly = &json["temporal_analysis"]["hourly_stats"];
         assert!(hourly.is_array());
         assert_eq!(hourly.as_array().unwrap().len(), 5);
+        assert_eq!(
+            hourly[1],
+            serde_json::json!({
+                "hour": 10,
+                "query_count": 5,
+                "total_duration_ms": 2500.0,
+                "avg_duration_ms": 500.0,
+                "connection_count": 25,
+            })
+        );
+        assert_eq!(hourly[3]["connection_count"], 0);
+
+        let temporal = &json["temporal_analysis"];
+        assert_eq!(temporal["peak_hours"], serde_json::json!([10, 14, 15]));
+        assert_eq!(temporal["busiest_hour"], 14);
+        let daily = temporal["daily_patterns"].as_array().unwrap();
+        let days: Vec<_> = daily
+            .iter()
+            .map(|day| day["day"].as_str().unwrap())
+            .collect();
+        assert_eq!(days, ["Monday", "Tuesday", "Wednesday"]);
+        assert_eq!(daily[0]["query_count"], 10);
+        assert_eq!(daily[0]["total_duration_ms"], 5000.0);
+        assert_eq!(daily[0]["avg_duration_ms"], 500.0);
     }
 
     #[test]
@@ -625,6 +1607,7 @@ mod json_formatter_tests {
         assert_eq!(json["summary"]["avg_duration_ms"], 0.0);
         assert_eq!(json["summary"]["error_count"], 0);
         assert_eq!(json["summary"]["connection_count"], 0);
+        assert!(json["summary"]["time_range"].is_null());
 
         // Empty arrays for queries
         assert!(json["query_analysis"]["slowest_queries"]
@@ -654,6 +1637,76 @@ mod json_formatter_tests {
         assert_eq!(metadata["total_log_entries"], 500);
     }
 
+    #[test]
+    fn test_approximate_query_counts_are_labeled() {
+        let mut analysis = create_test_analysis_result();
+        let exact = JsonFormatter::new().format(&analysis).unwrap();
+        assert!(!exact.contains("approximate_query_counts"));
+        assert!(!TextFormatter::new()
+            .format_query_analysis(&analysis)
+            .unwrap()
+            .contains("Approximate counts"));
+
+        analysis.frequency_estimate = Some(FrequencyEstimate {
+            capacity: 100,
+            max_overcount: 7,
+        });
+        let json: serde_json::Value =
+            serde_json::from_str(&JsonFormatter::new().format(&analysis).unwrap()).unwrap();
+        assert_eq!(
+            json["metadata"]["approximate_query_counts"],
+            serde_json::json!({"capacity": 100, "max_overcount": 7})
+        );
+        let text = TextFormatter::new()
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(text.contains(
+            "Approximate counts: the 100 most frequent distinct queries were tracked; \
+             each count may be up to 7 too high"
+        ));
+    }
+
+    #[test]
+    fn test_query_warnings_output() {
+        let mut analysis = create_test_analysis_result();
+        analysis.query_warnings = Some(vec![QueryWarning {
+            rule: "missing-where".to_string(),
+            description: "UPDATE or DELETE without WHERE changes every row of the table"
+                .to_string(),
+            query: "DELETE FROM sessions".to_string(),
+            count: 4,
+            p95_duration: 120.5,
+        }]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&JsonFormatter::new().format(&analysis).unwrap()).unwrap();
+        assert_eq!(
+            json["query_warnings"][0],
+            serde_json::json!({
+                "rule": "missing-where",
+                "description": "UPDATE or DELETE without WHERE changes every row of the table",
+                "query": "DELETE FROM sessions",
+                "query_id": query_id("DELETE FROM sessions"),
+                "count": 4,
+                "p95_duration_ms": 120.5,
+            })
+        );
+
+        let text = TextFormatter::new()
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(text.contains("Query Warnings:"));
+        assert!(text.contains("missing-where"));
+        assert!(text.contains("120.50"));
+        assert!(text.contains("missing-where: UPDATE or DELETE without WHERE"));
+
+        analysis.query_warnings = Some(Vec::new());
+        let text = TextFormatter::new()
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(text.contains("No suspicious query patterns found"));
+    }
+
     #[test]
     fn test_json_serialization_roundtrip() {
         let formatter = JsonFormatter::new();
@@ -694,6 +1747,666 @@ mod json_formatter_tests {
         assert!(qa["slowest_queries"].is_array());
         assert!(qa["most_frequent"].is_array());
     }
+
+    /// Analysis touching every report section, for the snapshot
+    fn create_snapshot_analysis() -> AnalysisResult {
+        let mut analysis = create_test_analysis_result();
+        let start = Utc.with_ymd_and_hms(2024, 8, 15, 9, 0, 0).unwrap();
+        analysis.time_range = Some(TimeRange {
+            start,
+            end: start + Duration::hours(7),
+            span_seconds: 25_200.0,
+            queries_per_second: 11.0 / 25_200.0,
+            active_days: 1,
+            active_hours: 5,
+            gaps: Vec::new(),
+        });
+        let (query, count) = analysis.most_frequent_queries[0].clone();
+        analysis.query_stats.insert(
+            query,
+            QueryStats {
+                count,
+                total_duration: 300.0,
+                average_duration: 20.0,
+                p95_duration: 45.0,
+                examples: Vec::new(),
+            },
+        );
+        analysis.errors_by_severity.insert("ERROR".to_string(), 2);
+        analysis.applications.push(ApplicationStats {
+            application_name: "checkout".to_string(),
+            query_count: 11,
+            total_duration: 5500.0,
+            p95_duration: 2000.0,
+            error_count: 2,
+            slowest_queries: analysis.slowest_queries[..1].to_vec(),
+        });
+        analysis.client_hosts.push(ClientHostStats {
+            client_host: "10.0.0.5".to_string(),
+            connection_count: 3,
+            query_count: 7,
+            total_duration: 3500.0,
+            error_count: 1,
+            error_rate: 0.125,
+        });
+        analysis.queries_by_user.insert("app".to_string(), 11);
+        analysis.duration_by_user.insert("app".to_string(), 5500.0);
+        analysis.queries_by_database.insert("appdb".to_string(), 11);
+        analysis.errors_by_database.insert("appdb".to_string(), 2);
+        analysis.duration_histogram = vec![
+            ("<1ms".to_string(), 0),
+            ("100ms-1s".to_string(), 9),
+            ("1-10s".to_string(), 2),
+        ];
+        analysis
+    }
+
+    #[test]
+    fn test_report_matches_snapshot() {
+        let formatter = JsonFormatter::new()
+            .with_pretty(true)
+            .with_metadata("0.1.0", vec!["postgresql.log".to_string()], 42)
+            .with_analysis_timestamp(Utc.with_ymd_and_hms(2024, 8, 16, 0, 0, 0).unwrap());
+
+        let json = formatter
+            .format_with_timing(&create_snapshot_analysis(), &create_test_timing_analysis())
+            .unwrap();
+
+        assert_eq!(
+            json,
+            include_str!("../golden/summary_report.json").trim_end()
+        );
+    }
+
+    #[test]
+    fn test_report_schema_roundtrip() {
+        let json = JsonFormatter::new()
+            .with_pretty(true)
+            .format_with_timing(&create_snapshot_analysis(), &create_test_timing_analysis())
+            .unwrap();
+
+        let report = Report::from_json(&json).unwrap();
+
+        assert_eq!(report.to_json(true).unwrap(), json);
+        let summary = report.summary.unwrap();
+        assert_eq!(summary.total_queries, Some(11));
+        let most_frequent = report.query_analysis.unwrap().most_frequent.unwrap();
+        assert_eq!(most_frequent[0].p95_duration_ms, Some(45.0));
+        assert_eq!(most_frequent[1].p95_duration_ms, None);
+    }
+}
+
+#[cfg(test)]
+mod report_sections_tests {
+    use super::*;
+
+    /// Text marker for each section
+    const TEXT_MARKERS: [(ReportSections, &str); 11] = [
+        (ReportSections::SUMMARY, "Total Queries:"),
+        (ReportSections::QUERY_TYPES, "Query Types:"),
+        (ReportSections::SLOWEST, "Slowest Queries:"),
+        (ReportSections::MOST_FREQUENT, "Most Frequent Queries:"),
+        (ReportSections::ERRORS, "Errors by SQLSTATE:"),
+        (ReportSections::CONNECTIONS, "Connection Count:"),
+        (ReportSections::HOURLY, "Hourly Activity:"),
+        (ReportSections::BREAKDOWNS, "Queries by User:"),
+        (ReportSections::APPLICATIONS, "Applications:"),
+        (ReportSections::COPY, "COPY Operations:"),
+        (ReportSections::HISTOGRAM, "Duration Histogram:"),
+    ];
+
+    /// JSON pointer for each section
+    const JSON_POINTERS: [(ReportSections, &str); 11] = [
+        (ReportSections::SUMMARY, "/summary/total_queries"),
+        (ReportSections::QUERY_TYPES, "/query_analysis/by_type"),
+        (ReportSections::SLOWEST, "/query_analysis/slowest_queries"),
+        (
+            ReportSections::MOST_FREQUENT,
+            "/query_analysis/most_frequent",
+        ),
+        (ReportSections::ERRORS, "/errors_by_sqlstate"),
+        (ReportSections::CONNECTIONS, "/summary/connection_count"),
+        (ReportSections::HOURLY, "/temporal_analysis/hourly_stats"),
+        (ReportSections::BREAKDOWNS, "/breakdowns/queries_by_user"),
+        (ReportSections::APPLICATIONS, "/applications"),
+        (ReportSections::COPY, "/copy/tables"),
+        (
+            ReportSections::HISTOGRAM,
+            "/query_analysis/duration_histogram",
+        ),
+    ];
+
+    fn analysis_with_errors() -> AnalysisResult {
+        let mut analysis = create_test_analysis_result();
+        analysis.add_error_with_sqlstate(Some("40P01"));
+        analysis.queries_by_user.insert("app".to_string(), 11);
+        analysis.applications.push(ApplicationStats {
+            application_name: "checkout".to_string(),
+            query_count: 11,
+            ..ApplicationStats::default()
+        });
+        analysis.duration_histogram = vec![("<1ms".to_string(), 11)];
+        analysis.copy_stats = CopyStats {
+            operations: 1,
+            total_duration: 800.0,
+            max_duration: 800.0,
+            error_count: 0,
+            tables: vec![CopyTableStats {
+                operations: 1,
+                total_duration: 800.0,
+                max_duration: 800.0,
+                ..CopyTableStats::new("orders", CopyDirection::From)
+            }],
+        };
+        analysis
+    }
+
+    fn text_report(sections: ReportSections) -> String {
+        let formatter = TextFormatter::new().with_sections(sections);
+        let mut output = formatter
+            .format_query_analysis(&analysis_with_errors())
+            .unwrap();
+        let timing = create_test_timing_analysis();
+        output.push_str(&formatter.format_timing_analysis(&timing).unwrap());
+        output.push_str(
+            &formatter
+                .format_hourly_report(&analysis_with_errors(), &timing)
+                .unwrap(),
+        );
+        output
+    }
+
+    fn json_report(sections: ReportSections) -> serde_json::Value {
+        let formatter = JsonFormatter::new().with_sections(sections);
+        let json = formatter
+            .format_with_timing(&analysis_with_errors(), &create_test_timing_analysis())
+            .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_all_sections_are_rendered_by_default() {
+        let text = text_report(ReportSections::default());
+        for (_, marker) in TEXT_MARKERS {
+            assert!(text.contains(marker), "missing {marker}");
+        }
+
+        let json = json_report(ReportSections::default());
+        for (_, pointer) in JSON_POINTERS {
+            assert!(json.pointer(pointer).is_some(), "missing {pointer}");
+        }
+    }
+
+    #[test]
+    fn test_each_text_section_can_be_disabled() {
+        for (disabled, marker) in TEXT_MARKERS {
+            let text = text_report(ReportSections::all().without(disabled));
+
+            assert!(!text.contains(marker), "{marker} should be omitted");
+            for (other, other_marker) in TEXT_MARKERS {
+                if other != disabled {
+                    assert!(text.contains(other_marker), "missing {other_marker}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_each_json_section_can_be_disabled() {
+        for (disabled, pointer) in JSON_POINTERS {
+            let json = json_report(ReportSections::all().without(disabled));
+
+            assert!(
+                json.pointer(pointer).is_none(),
+                "{pointer} should be omitted"
+            );
+            for (other, other_pointer) in JSON_POINTERS {
+                if other != disabled {
+                    assert!(
+                        json.pointer(other_pointer).is_some(),
+                        "missing {other_pointer}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_omits_emptied_objects() {
+        let json = json_report("summary".parse().unwrap());
+
+        assert!(json.get("query_analysis").is_none());
+        assert!(json.get("errors_by_sqlstate").is_none());
+        assert!(json.get("errors_by_severity").is_none());
+        assert!(json["summary"].get("error_count").is_none());
+        assert!(json["temporal_analysis"].get("hourly_stats").is_none());
+
+        let json = json_report("slowest".parse().unwrap());
+        assert!(json.get("summary").is_none());
+        assert!(json.get("temporal_analysis").is_none());
+        assert!(json["query_analysis"]["slowest_queries"].is_array());
+    }
+}
+
+#[cfg(test)]
+mod breakdown_tests {
+    use super::*;
+
+    fn grouped_analysis() -> AnalysisResult {
+        let mut analysis = create_test_analysis_result();
+        analysis.queries_by_user =
+            HashMap::from([("app".to_string(), 9), ("reporting".to_string(), 2)]);
+        analysis.duration_by_user = HashMap::from([
+            ("app".to_string(), 1500.0),
+            ("reporting".to_string(), 4000.0),
+        ]);
+        analysis.queries_by_database = HashMap::from([("shop".to_string(), 11)]);
+        analysis.errors_by_database =
+            HashMap::from([("shop".to_string(), 1), ("unknown".to_string(), 1)]);
+        analysis.group_by = Some(GroupBy::User);
+        analysis.query_groups = BTreeMap::from([
+            (
+                "app".to_string(),
+                QueryGroup {
+                    total_queries: 9,
+                    total_duration: 1500.0,
+                    slowest_queries: vec![],
+                    most_frequent_queries: vec![("SELECT COUNT(*) FROM orders".to_string(), 9)],
+                },
+            ),
+            (
+                "reporting".to_string(),
+                QueryGroup {
+                    total_queries: 2,
+                    total_duration: 4000.0,
+                    slowest_queries: vec![("SELECT * FROM large_table".to_string(), 2500.0)],
+                    most_frequent_queries: vec![("SELECT * FROM large_table".to_string(), 2)],
+                },
+            ),
+        ]);
+        analysis
+    }
+
+    #[test]
+    fn test_text_breakdown_tables() {
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_query_analysis(&grouped_analysis())
+            .unwrap();
+
+        assert!(output.contains("Queries by User:"));
+        let app = output.find("  app    ").unwrap();
+        assert!(output[app..].starts_with(&format!("  {:<20}  {:>8}  {:>14.2}", "app", 9, 1500.0)));
+        assert!(output.find("  reporting    ").unwrap() > app);
+        assert!(output.contains(&format!("  {:<20}  {:>8}  {:>8}", "shop", 11, 1)));
+        assert!(output.contains(&format!("  {:<20}  {:>8}  {:>8}", "unknown", 0, 1)));
+    }
+
+    #[test]
+    fn test_text_groups_queries_by_dimension() {
+        let output = TextFormatter::new()
+            .format_query_analysis(&grouped_analysis())
+            .unwrap();
+
+        assert!(output.contains("Slowest Queries by user:"));
+        assert!(output.contains("Most Frequent Queries by user:"));
+        assert!(!output.contains("\nSlowest Queries:"));
+        // Only reporting has slow queries; groups are ordered by total duration
+        let slowest = &output[output.find("Slowest Queries by user:").unwrap()..];
+        let slowest = &slowest[..slowest.find("Most Frequent").unwrap()];
+        assert!(slowest.contains("user reporting (2 queries, 4.00 s total)"));
+        assert!(!slowest.contains("user app"));
+        let frequent = &output[output.find("Most Frequent Queries by user:").unwrap()..];
+        assert!(frequent.find("user reporting").unwrap() < frequent.find("user app").unwrap());
+    }
+
+    #[test]
+    fn test_json_breakdowns_and_groups() {
+        let json = JsonFormatter::new().format(&grouped_analysis()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(json["breakdowns"]["queries_by_user"]["app"], 9);
+        assert_eq!(json["breakdowns"]["duration_by_user"]["reporting"], 4000.0);
+        assert_eq!(json["breakdowns"]["queries_by_database"]["shop"], 11);
+        assert_eq!(json["breakdowns"]["errors_by_database"]["unknown"], 1);
+
+        let query_analysis = &json["query_analysis"];
+        assert_eq!(query_analysis["group_by"], "user");
+        let groups = query_analysis["groups"].as_array().unwrap();
+        assert_eq!(groups[0]["key"], "reporting");
+        assert_eq!(groups[0]["total_queries"], 2);
+        assert_eq!(
+            groups[0]["slowest_queries"][0]["query_id"],
+            query_id("SELECT * FROM large_table")
+        );
+        assert_eq!(groups[0]["slowest_queries"][0]["count"], 2);
+        assert_eq!(groups[1]["most_frequent"][0]["count"], 9);
+        assert_eq!(
+            groups[1]["most_frequent"][0]["avg_duration_ms"],
+            1500.0 / 9.0
+        );
+        // The ungrouped lists are kept for existing consumers
+        assert!(query_analysis["slowest_queries"].is_array());
+    }
+
+    fn analysis_with_applications() -> AnalysisResult {
+        let mut analysis = create_test_analysis_result();
+        analysis.applications = vec![
+            ApplicationStats {
+                application_name: "checkout".to_string(),
+                query_count: 8,
+                total_duration: 4800.0,
+                p95_duration: 2500.0,
+                error_count: 2,
+                slowest_queries: vec![
+                    ("SELECT * FROM large_table".to_string(), 2500.0),
+                    ("SELECT COUNT(*) FROM orders".to_string(), 1200.0),
+                ],
+            },
+            ApplicationStats {
+                application_name: "(none)".to_string(),
+                query_count: 3,
+                total_duration: 700.0,
+                p95_duration: 400.0,
+                error_count: 0,
+                slowest_queries: vec![],
+            },
+        ];
+        analysis
+    }
+
+    #[test]
+    fn test_text_application_table() {
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_query_analysis(&analysis_with_applications())
+            .unwrap();
+
+        let table = &output[output.find("Applications:").unwrap()..];
+        let checkout = format!(
+            "  {:<20}  {:>8}  {:>14.2}  {:>10.2}  {:>6}",
+            "checkout", 8, 4800.0, 2500.0, 2
+        );
+        let none = format!(
+            "  {:<20}  {:>8}  {:>14.2}  {:>10.2}  {:>6}",
+            "(none)", 3, 700.0, 400.0, 0
+        );
+        assert!(table.find(&checkout).unwrap() < table.find(&none).unwrap());
+        assert!(table.contains(&format!(
+            "      {:>12.2}  {}  SELECT * FROM large_table",
+            2500.0,
+            &query_id("SELECT * FROM large_table")[..8]
+        )));
+    }
+
+    #[test]
+    fn test_json_applications() {
+        let json = JsonFormatter::new()
+            .format(&analysis_with_applications())
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let applications = json["applications"].as_array().unwrap();
+        assert_eq!(applications.len(), 2);
+        assert_eq!(applications[0]["application_name"], "checkout");
+        assert_eq!(applications[0]["query_count"], 8);
+        assert_eq!(applications[0]["p95_duration_ms"], 2500.0);
+        assert_eq!(applications[0]["error_count"], 2);
+        assert_eq!(
+            applications[0]["slowest_queries"][1]["query"],
+            "SELECT COUNT(*) FROM orders"
+        );
+        assert_eq!(applications[1]["application_name"], "(none)");
+    }
+
+    fn analysis_with_clients() -> AnalysisResult {
+        let mut analysis = create_test_analysis_result();
+        analysis.client_hosts = vec![
+            ClientHostStats {
+                client_host: "192.168.7.20".to_string(),
+                connection_count: 1,
+                query_count: 3,
+                total_duration: 1500.0,
+                error_count: 0,
+                error_rate: 0.0,
+            },
+            ClientHostStats {
+                client_host: "10.0.0.5".to_string(),
+                connection_count: 2,
+                query_count: 3,
+                total_duration: 50.0,
+                error_count: 1,
+                error_rate: 0.25,
+            },
+        ];
+        analysis
+    }
+
+    #[test]
+    fn test_text_client_table() {
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_query_analysis(&analysis_with_clients())
+            .unwrap();
+
+        let table = &output[output.find("Clients:").unwrap()..];
+        let batch = format!(
+            "  {:<24}  {:>11}  {:>8}  {:>14.2}  {:>6}  {:>6.1}%",
+            "192.168.7.20", 1, 3, 1500.0, 0, 0.0
+        );
+        let app = format!(
+            "  {:<24}  {:>11}  {:>8}  {:>14.2}  {:>6}  {:>6.1}%",
+            "10.0.0.5", 2, 3, 50.0, 1, 25.0
+        );
+        assert!(table.find(&batch).unwrap() < table.find(&app).unwrap());
+
+        let output = TextFormatter::new()
+            .format_query_analysis(&create_test_analysis_result())
+            .unwrap();
+        assert!(!output.contains("Clients:"));
+    }
+
+    #[test]
+    fn test_json_clients() {
+        let json = JsonFormatter::new()
+            .format(&analysis_with_clients())
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let clients = json["clients"].as_array().unwrap();
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[0]["client_host"], "192.168.7.20");
+        assert_eq!(clients[0]["total_duration_ms"], 1500.0);
+        assert_eq!(clients[1]["connection_count"], 2);
+        assert_eq!(clients[1]["error_rate"], 0.25);
+    }
+}
+
+#[cfg(test)]
+mod hourly_report_tests {
+    use super::*;
+
+    fn hourly(hour: u32, query_count: u64, total_duration: f64, error_count: u64) -> HourlyStats {
+        HourlyStats {
+            hour,
+            query_count,
+            queries_per_second: 0.0,
+            total_duration,
+            average_duration: total_duration / query_count.max(1) as f64,
+            p95_duration: total_duration,
+            error_count,
+        }
+    }
+
+    #[test]
+    fn test_hourly_report_fills_quiet_hours() {
+        let mut analysis = create_test_analysis_result();
+        analysis.hourly_stats = vec![hourly(9, 4, 100.0, 1), hourly(12, 2, 30.0, 0)];
+        let timing = TimingAnalysis {
+            connection_patterns: HashMap::from([(9, 3), (13, 1)]),
+            ..TimingAnalysis::default()
+        };
+
+        let output = TextFormatter::new()
+            .with_human_units(false)
+            .format_hourly_report(&analysis, &timing)
+            .unwrap();
+
+        let rows: Vec<_> = output
+            .lines()
+            .skip_while(|line| !line.contains("Hourly Activity:"))
+            .skip(2)
+            .collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(
+            rows[0],
+            format!(
+                "  {:>4}  {:>8}  {:>10.2}  {:>10.2}  {:>6}  {:>11}  {}",
+                "09",
+                4,
+                25.0,
+                100.0,
+                1,
+                3,
+                "#".repeat(HOURLY_BAR_WIDTH)
+            )
+        );
+        assert_eq!(
+            rows[1],
+            format!(
+                "  {:>4}  {:>8}  {:>10.2}  {:>10.2}  {:>6}  {:>11}",
+                "10", 0, 0.0, 0.0, 0, 0
+            )
+        );
+        assert!(rows[3].ends_with(&format!("  {}", "#".repeat(HOURLY_BAR_WIDTH / 2))));
+        assert!(rows[4].starts_with("    13"));
+    }
+
+    #[test]
+    fn test_hourly_report_wraps_around_midnight() {
+        let mut analysis = create_test_analysis_result();
+        analysis.hourly_stats = vec![
+            hourly(9, 4, 100.0, 0),
+            hourly(12, 2, 30.0, 0),
+            hourly(1, 1, 10.0, 0),
+        ];
+        let start = Utc.with_ymd_and_hms(2024, 8, 15, 9, 0, 0).unwrap();
+        analysis.time_range = Some(TimeRange {
+            start,
+            end: start + Duration::minutes(16 * 60 + 30),
+            span_seconds: 59_400.0,
+            queries_per_second: 0.0,
+            active_days: 2,
+            active_hours: 3,
+            gaps: Vec::new(),
+        });
+
+        let output = TextFormatter::new()
+            .format_hourly_report(&analysis, &TimingAnalysis::default())
+            .unwrap();
+
+        let hours: Vec<_> = output
+            .lines()
+            .skip_while(|line| !line.contains("Hourly Activity:"))
+            .skip(2)
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        let expected: Vec<_> = (9..24)
+            .chain(0..2)
+            .map(|hour| format!("{:02}", hour))
+            .collect();
+        assert_eq!(hours, expected);
+    }
+
+    #[test]
+    fn test_hourly_report_respects_sections() {
+        let mut analysis = create_test_analysis_result();
+        analysis.hourly_stats = vec![hourly(9, 1, 10.0, 0)];
+        let timing = TimingAnalysis::default();
+
+        let output = TextFormatter::new()
+            .with_sections(ReportSections::all().without(ReportSections::HOURLY))
+            .format_hourly_report(&analysis, &timing)
+            .unwrap();
+        assert!(output.is_empty());
+
+        let output = TextFormatter::new()
+            .format_hourly_report(&create_test_analysis_result(), &timing)
+            .unwrap();
+        assert!(output.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod cancellation_output_tests {
+    use super::*;
+
+    fn analysis_with_cancellations() -> AnalysisResult {
+        let mut analysis = create_test_analysis_result();
+        let cancellations = &mut analysis.cancellations;
+        cancellations.total = 3;
+        cancellations
+            .by_reason
+            .insert("statement_timeout".to_string(), 2);
+        cancellations
+            .by_reason
+            .insert("lock_timeout".to_string(), 1);
+        cancellations.top_queries.push(CanceledQuery {
+            query: "SELECT * FROM events WHERE id > ?".to_string(),
+            count: 2,
+            by_reason: BTreeMap::from([("statement_timeout".to_string(), 2)]),
+        });
+        cancellations.hourly.insert(9, 1);
+        cancellations.hourly.insert(14, 2);
+        analysis
+    }
+
+    #[test]
+    fn test_text_cancellations() {
+        let output = TextFormatter::new()
+            .format_query_analysis(&analysis_with_cancellations())
+            .unwrap();
+
+        let section = &output[output.find("Cancellations:").unwrap()..];
+        assert!(section.find("statement_timeout").unwrap() < section.find("lock_timeout").unwrap());
+        assert!(section.contains("2x  "));
+        assert!(section.contains("SELECT * FROM events WHERE id > ?"));
+        assert!(section.contains("By hour: 09:00 x1, 14:00 x2"));
+
+        let output = TextFormatter::new()
+            .format_query_analysis(&create_test_analysis_result())
+            .unwrap();
+        assert!(!output.contains("Cancellations:"));
+    }
+
+    #[test]
+    fn test_json_cancellations() {
+        let json = JsonFormatter::new()
+            .format(&analysis_with_cancellations())
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let cancellations = &json["cancellations"];
+        assert_eq!(cancellations["total"], 3);
+        assert_eq!(cancellations["by_reason"]["statement_timeout"], 2);
+        let query = &cancellations["top_queries"][0];
+        assert_eq!(
+            query["query_id"],
+            query_id("SELECT * FROM events WHERE id > ?")
+        );
+        assert_eq!(query["by_reason"]["statement_timeout"], 2);
+        assert_eq!(
+            cancellations["hourly"],
+            serde_json::json!([{"hour": 9, "count": 1}, {"hour": 14, "count": 2}])
+        );
+
+        let json = JsonFormatter::new()
+            .with_sections(ReportSections::all().without(ReportSections::ERRORS))
+            .format(&analysis_with_cancellations())
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(json.get("cancellations").is_none());
+    }
 }
 
 #[cfg(test)]
@@ -751,7 +2464,7 @@ mod output_edge_cases_tests {
 
     #[test]
     fn test_text_formatter_with_very_long_queries() {
-        let formatter = TextFormatter::new();
+        let formatter = TextFormatter::new().with_wide(true);
 
         let long_query = format!("SELECT {} FROM users", "column_name, ".repeat(100));
         let mut analysis = AnalysisResult::new();
@@ -875,11 +2588,47 @@ mod output_edge_cases_tests {
         assert!(result.is_ok());
 
         let output = result.unwrap();
+        assert!(output.contains("Total Queries: 1,000,000"));
+        assert!(output.contains("Total Duration: 16.67 min"));
+        assert!(output.contains("Average Duration: 1.00 s"));
+
+        let output = formatter
+            .with_human_units(false)
+            .format_query_analysis(&analysis)
+            .unwrap();
         assert!(output.contains("Total Queries: 1000000"));
         assert!(output.contains("Total Duration: 999999.99 ms"));
         assert!(output.contains("Average Duration: 1000.00 ms"));
     }
 
+    #[test]
+    fn test_text_formatter_duration_unit_boundaries() {
+        assert_eq!(format_duration(999.99), "999.99 ms");
+        assert_eq!(format_duration(999.996), "1.00 s");
+        assert_eq!(format_duration(1000.0), "1.00 s");
+        assert_eq!(format_duration(59_999.0), "1.00 min");
+        assert_eq!(format_duration(90_000.0), "1.50 min");
+
+        let mut analysis = AnalysisResult::new();
+        analysis.total_duration = 90_000.0;
+        analysis.average_duration = 999.99;
+        analysis.p95_duration = 1000.0;
+        let output = TextFormatter::new()
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(output.contains("Total Duration: 1.50 min"));
+        assert!(output.contains("Average Duration: 999.99 ms"));
+        assert!(output.contains("P95 Duration: 1.00 s"));
+    }
+
+    #[test]
+    fn test_text_formatter_count_separators() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1000), "1,000");
+        assert_eq!(format_count(12_345_678), "12,345,678");
+    }
+
     #[test]
     fn test_json_formatter_with_large_numbers() {
         let formatter = JsonFormatter::new();
@@ -984,7 +2733,7 @@ mod output_performance_tests {
 
     #[test]
     fn test_text_formatter_memory_usage() {
-        let formatter = TextFormatter::new();
+        let formatter = TextFormatter::new().with_wide(true);
 
         // Create analysis with very long query strings
         let mut analysis = AnalysisResult::new();
@@ -1044,3 +2793,184 @@ mod output_performance_tests {
         assert!(first_query.contains("very_long_column_name"));
     }
 }
+
+#[cfg(test)]
+mod histogram_tests {
+    use super::*;
+
+    fn histogram_analysis() -> AnalysisResult {
+        let mut analysis = AnalysisResult::new();
+        analysis.total_queries = 13;
+        analysis.duration_histogram = vec![
+            ("<1ms".to_string(), 8),
+            ("1-10ms".to_string(), 4),
+            ("10-100ms".to_string(), 0),
+            (">=10s".to_string(), 1),
+        ];
+        analysis
+    }
+
+    #[test]
+    fn test_text_histogram_bars_scale_to_chart_width() {
+        let output = TextFormatter::new()
+            .with_chart_width(16)
+            .format_query_analysis(&histogram_analysis())
+            .unwrap();
+
+        assert!(output.contains("Duration Histogram:"));
+        assert!(output.contains(&format!("  {:<10}  {:>8}  {}\n", "<1ms", 8, "#".repeat(16))));
+        assert!(output.contains(&format!(
+            "  {:<10}  {:>8}  {}\n",
+            "1-10ms",
+            4,
+            "#".repeat(8)
+        )));
+        assert!(output.contains(&format!("  {:<10}  {:>8}\n", "10-100ms", 0)));
+        assert!(output.contains(&format!("  {:<10}  {:>8}  ##\n", ">=10s", 1)));
+
+        let narrow = TextFormatter::new()
+            .with_chart_width(1)
+            .format_query_analysis(&histogram_analysis())
+            .unwrap();
+        assert!(narrow.contains(&format!("  {:<10}  {:>8}  #\n", ">=10s", 1)));
+    }
+
+    #[test]
+    fn test_json_histogram_is_bucket_count_array() {
+        let json = JsonFormatter::new().format(&histogram_analysis()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let histogram = &json["query_analysis"]["duration_histogram"];
+        assert_eq!(histogram.as_array().unwrap().len(), 4);
+        assert_eq!(
+            histogram[0],
+            serde_json::json!({"bucket": "<1ms", "count": 8})
+        );
+        assert_eq!(
+            histogram[3],
+            serde_json::json!({"bucket": ">=10s", "count": 1})
+        );
+    }
+
+    fn traffic_analysis(bucket_minutes: u64) -> AnalysisResult {
+        let start = Utc.with_ymd_and_hms(2024, 8, 15, 10, 0, 0).unwrap();
+        let buckets = [(40, 0), (10, 2), (0, 1), (1, 0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (query_count, error_count))| TrafficBucket {
+                start: start + Duration::minutes(i as i64 * bucket_minutes as i64),
+                query_count,
+                error_count,
+            })
+            .collect();
+        AnalysisResult {
+            traffic_histogram: Some(TrafficHistogram {
+                granularity: TrafficGranularity::Minute,
+                bucket_minutes,
+                buckets,
+            }),
+            ..AnalysisResult::new()
+        }
+    }
+
+    #[test]
+    fn test_text_traffic_histogram_scales_bars_and_notes_errors() {
+        let output = TextFormatter::new()
+            .with_chart_width(36)
+            .format_query_analysis(&traffic_analysis(1))
+            .unwrap();
+
+        assert!(output.contains("Traffic Histogram (per minute):\n"));
+        // Bars are 16 characters shorter than the chart width for the longer
+        // rows. 40 queries fill them; 10 of 40 is a quarter, and a single
+        // query still gets one mark
+        assert!(output.contains(&format!(
+            "  2024-08-15 10:00        40  {}\n",
+            "#".repeat(20)
+        )));
+        assert!(output.contains("  2024-08-15 10:01        10  #####  2 errors\n"));
+        assert!(output.contains("  2024-08-15 10:02         0    1 error\n"));
+        assert!(output.contains("  2024-08-15 10:03         1  #\n"));
+
+        let widened = TextFormatter::new()
+            .format_query_analysis(&traffic_analysis(10))
+            .unwrap();
+        assert!(widened.contains("Traffic Histogram (per 10 minutes, widened from 1 minute):"));
+        assert!(widened.contains("  2024-08-15 10:30         1  #\n"));
+        assert!(!TextFormatter::new()
+            .format_query_analysis(&AnalysisResult::new())
+            .unwrap()
+            .contains("Traffic Histogram"));
+    }
+
+    #[test]
+    fn test_json_traffic_histogram_lists_bucket_bounds_and_counts() {
+        let json = JsonFormatter::new().format(&traffic_analysis(10)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let histogram = json["traffic_histogram"].as_array().unwrap();
+        assert_eq!(histogram.len(), 4);
+        assert_eq!(
+            histogram[1],
+            serde_json::json!({
+                "start": "2024-08-15T10:10:00Z",
+                "end": "2024-08-15T10:20:00Z",
+                "query_count": 10,
+                "error_count": 2,
+            })
+        );
+        assert!(json["metadata"]["notes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|note| note
+                .as_str()
+                .unwrap()
+                .contains("widened from 1 minute to 10 minutes")));
+
+        let json = JsonFormatter::new().format(&AnalysisResult::new()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(json.get("traffic_histogram").is_none());
+    }
+}
+
+#[cfg(test)]
+mod redaction_tests {
+    use super::*;
+    use pg_logstats::{Redact, Redactor};
+    use std::sync::Arc;
+
+    fn redactor() -> Option<Arc<dyn Redact>> {
+        Some(Arc::new(
+            Redactor::new().with_pattern("large_table").unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_formatters_apply_the_redactor_to_every_format() {
+        let analysis = create_test_analysis_result();
+
+        let text = TextFormatter::new()
+            .with_redactor(redactor())
+            .format_query_analysis(&analysis)
+            .unwrap();
+        assert!(!text.contains("large_table"));
+        assert!(text.contains("SELECT * FROM [REDACTED] WHERE"));
+
+        let json = JsonFormatter::new()
+            .with_redactor(redactor())
+            .format(&analysis)
+            .unwrap();
+        assert!(!json.contains("large_table"));
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            json["query_analysis"]["slowest_queries"][0]["query"],
+            "SELECT * FROM [REDACTED] WHERE complex_condition = ?"
+        );
+        // Numbers are left exact
+        assert_eq!(json["summary"]["total_queries"], analysis.total_queries);
+
+        let unredacted = JsonFormatter::new().format(&analysis).unwrap();
+        assert!(unredacted.contains("large_table"));
+    }
+}
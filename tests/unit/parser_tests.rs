@@ -2,9 +2,10 @@
 //!
 //! Tests various log line formats, edge cases, and parser functionality in isolation
 
-use chrono::DateTime;
+use chrono::{DateTime, TimeZone, Utc};
 use pg_logstats::parsers::text::{TextLogFormat, TextLogParser};
-use pg_logstats::LogLevel;
+use pg_logstats::parsers::SkipReason;
+use pg_logstats::{LogEntry, LogEntryBuilder, LogLevel, PgLogstatsError, QueryType};
 
 /// Helper function to create test log lines with various formats
 fn create_test_lines() -> Vec<String> {
@@ -128,6 +129,24 @@ mod parser_unit_tests {
         assert!(entry.duration.is_none());
     }
 
+    #[test]
+    fn test_parse_verbose_error_sqlstate() {
+        let mut parser = TextLogParser::new();
+        let line = "2024-08-15 10:30:16.789 UTC [12346] admin@analytics pgbench: ERROR:  23505: duplicate key value violates unique constraint \"users_pkey\"";
+
+        let entry = parser.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.message_type, LogLevel::Error);
+        assert_eq!(entry.sqlstate.as_deref(), Some("23505"));
+        assert_eq!(
+            entry.message,
+            "duplicate key value violates unique constraint \"users_pkey\""
+        );
+
+        let line = "2024-08-15 10:30:16.789 UTC [12346] admin@analytics pgbench: ERROR:  relation \"missing_table\" does not exist";
+        let entry = parser.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.sqlstate, None);
+    }
+
     #[test]
     fn test_parse_warning_log() {
         let mut parser = TextLogParser::new();
@@ -174,7 +193,7 @@ mod parser_unit_tests {
             "2024-08-15 10:30:19.890 UTC [12349] postgres@testdb psql: LOG:  duration: 12.345 ms",
         ];
 
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
         let result = parser.parse_lines(&lines.iter().map(|s| s.to_string()).collect::<Vec<_>>());
         assert!(result.is_ok());
 
@@ -192,9 +211,237 @@ mod parser_unit_tests {
         assert!(statement_entry.queries.is_some());
         let queries = statement_entry.queries.as_ref().unwrap();
         assert_eq!(queries.len(), 1);
-        assert!(queries[0]
-            .normalized_query
-            .contains("SELECT u.name, p.title"));
+        assert_eq!(
+            queries[0].normalized_query,
+            "SELECT u.name, p.title FROM users AS u JOIN posts AS p ON u.id = p.user_id WHERE u.active = ? ORDER BY p.created_at DESC"
+        );
+        assert_eq!(
+            statement_entry.message,
+            "statement: SELECT u.name, p.title FROM users u JOIN posts p ON u.id = p.user_id WHERE u.active = true ORDER BY p.created_at DESC;"
+        );
+    }
+
+    #[test]
+    fn test_parse_interleaved_multi_line_statements() {
+        let lines: Vec<String> = [
+            "2024-08-15 10:30:19.678 UTC [100] postgres@testdb psql: LOG:  statement: SELECT a",
+            "    FROM t1",
+            "2024-08-15 10:30:19.679 UTC [200] postgres@testdb psql: LOG:  statement: SELECT b",
+            "    FROM t2",
+            "    WHERE x = 1",
+            "2024-08-15 10:30:19.680 UTC [100] postgres@testdb psql: LOG:  duration: 1.000 ms",
+            "2024-08-15 10:30:19.681 UTC [200] postgres@testdb psql: LOG:  statement: SELECT c",
+            "    FROM t3",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let mut parser = TextLogParser::new();
+        let (entries, report) = parser.parse_lines_with_report(&lines);
+
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.process_id.as_str(), entry.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("100", "statement: SELECT a FROM t1"),
+                ("200", "statement: SELECT b FROM t2 WHERE x = 1"),
+                ("100", "duration: 1.000 ms"),
+                ("200", "statement: SELECT c FROM t3"),
+            ]
+        );
+        let normalized: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| entry.queries.as_ref())
+            .map(|queries| queries[0].normalized_query.as_str())
+            .collect();
+        assert_eq!(
+            normalized,
+            vec![
+                "SELECT a FROM t1",
+                "SELECT b FROM t2 WHERE x = ?",
+                "SELECT c FROM t3"
+            ]
+        );
+        assert_eq!(report.continuation_lines, 4);
+        assert_eq!(report.parsed_entries, 4);
+    }
+
+    #[test]
+    fn test_parse_session_line_number_prefix() {
+        let mut parser = TextLogParser::new();
+        let entry = parser
+            .parse_line("2024-08-15 10:30:15.123 UTC [12345]: [7-1] user=postgres,db=testdb LOG:  statement: SELECT 1")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(entry.process_id, "12345");
+        assert_eq!(entry.user.as_deref(), Some("postgres"));
+        assert_eq!(entry.database.as_deref(), Some("testdb"));
+        assert_eq!(entry.message, "statement: SELECT 1");
+        assert_eq!(entry.session_line, Some(7));
+        assert_eq!(entry.chunk, Some(1));
+    }
+
+    #[test]
+    fn test_parse_session_and_transaction_ids_from_prefix() {
+        let mut parser = TextLogParser::new();
+        let entry = parser
+            .parse_line("2024-08-15 10:30:15.123 UTC [12345]: [7-1] session=65a5b1c0.3039 vxid=3/42 txid=731 user=postgres,db=testdb LOG:  statement: UPDATE t SET x = 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.session_id.as_deref(), Some("65a5b1c0.3039"));
+        assert_eq!(entry.virtual_txid.as_deref(), Some("3/42"));
+        assert_eq!(entry.txid, Some(731));
+        assert_eq!(entry.session_key(), "65a5b1c0.3039");
+
+        // A bare %c token is recognized by its shape; %x is 0 outside a
+        // transaction
+        let entry = parser
+            .parse_line("2024-08-15 10:30:16.000 UTC [12345]: [8-1] 65a5b1c0.3039 x=0 postgres@testdb LOG:  statement: SELECT 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.session_id.as_deref(), Some("65a5b1c0.3039"));
+        assert_eq!(entry.txid, None);
+        assert_eq!(entry.user.as_deref(), Some("postgres"));
+
+        let entry = parser
+            .parse_line("2024-08-15 10:30:17.000 UTC [12345]: [9-1] user=postgres,db=testdb LOG:  statement: SELECT 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.session_id, None);
+        assert_eq!(entry.session_key(), "12345");
+    }
+
+    #[test]
+    fn test_reassemble_statement_split_into_three_chunks() {
+        let lines: Vec<String> = [
+            "2024-08-15 10:30:15.123 UTC [100]: [7-1] user=app,db=shop LOG:  statement: SELECT id,",
+            "2024-08-15 10:30:15.123 UTC [200]: [3-1] user=app,db=shop LOG:  duration: 2.000 ms",
+            "2024-08-15 10:30:15.123 UTC [100]: [7-2] name FROM users",
+            "2024-08-15 10:30:15.124 UTC [100]: [7-3] WHERE id = 42",
+            "2024-08-15 10:30:15.130 UTC [100]: [8-1] user=app,db=shop LOG:  duration: 6.500 ms",
+            "2024-08-15 10:30:15.131 UTC [300]: [2-2] FROM orphans",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let (entries, report) = TextLogParser::new().parse_lines_with_report(&lines);
+
+        assert_eq!(entries.len(), 3);
+        let statement = &entries[0];
+        assert_eq!(statement.process_id, "100");
+        assert_eq!(
+            statement.message,
+            "statement: SELECT id, name FROM users WHERE id = 42"
+        );
+        assert_eq!(
+            statement.queries.as_ref().unwrap()[0].normalized_query,
+            "SELECT id, name FROM users WHERE id = ?"
+        );
+        assert_eq!(
+            (statement.session_line, statement.chunk),
+            (Some(7), Some(3))
+        );
+        assert_eq!(entries[2].duration, Some(6.5));
+        assert_eq!(report.continuation_lines, 2);
+        assert_eq!(report.skipped.continuation_without_pending, 1);
+    }
+
+    #[test]
+    fn test_strict_multiline_drops_ambiguous_continuations() {
+        let lines: Vec<String> = [
+            "2024-08-15 10:30:19.678 UTC [100] postgres@testdb psql: LOG:  statement: SELECT a",
+            "2024-08-15 10:30:19.679 UTC [200] postgres@testdb psql: LOG:  statement: SELECT b",
+            "    FROM t1",
+            "2024-08-15 10:30:19.680 UTC [200] postgres@testdb psql: LOG:  duration: 1.000 ms",
+            "2024-08-15 10:30:19.681 UTC [100] postgres@testdb psql: LOG:  statement: SELECT c",
+            "    FROM t3",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let messages = |entries: &[pg_logstats::LogEntry]| -> Vec<String> {
+            entries
+                .iter()
+                .filter(|entry| entry.message_type == LogLevel::Statement)
+                .map(|entry| entry.message.clone())
+                .collect()
+        };
+
+        // Default: the continuation belongs to the most recent message.
+        let (entries, report) = TextLogParser::new().parse_lines_with_report(&lines);
+        assert_eq!(
+            messages(&entries),
+            vec![
+                "statement: SELECT a",
+                "statement: SELECT b FROM t1",
+                "statement: SELECT c FROM t3"
+            ]
+        );
+        assert_eq!(report.continuation_lines, 2);
+        assert_eq!(report.skipped.ambiguous_continuation, 0);
+
+        // Strict: a statement that follows another backend's statement does
+        // not take continuations; one that follows a duration line still does.
+        let (entries, report) = TextLogParser::new()
+            .with_strict_multiline(true)
+            .parse_lines_with_report(&lines);
+        assert_eq!(
+            messages(&entries),
+            vec![
+                "statement: SELECT a",
+                "statement: SELECT b",
+                "statement: SELECT c FROM t3"
+            ]
+        );
+        assert_eq!(report.continuation_lines, 1);
+        assert_eq!(report.skipped.ambiguous_continuation, 1);
+        assert_eq!(
+            report.skipped_samples[0].reason,
+            SkipReason::AmbiguousContinuation
+        );
+        assert_eq!(report.skipped_samples[0].line_number, 3);
+        assert!(!report
+            .warning_summary()
+            .unwrap()
+            .contains("check --input-format"));
+    }
+
+    #[test]
+    fn test_max_entries_stops_before_the_next_entry() {
+        let lines: Vec<String> = [
+            "2024-08-15 10:30:19.678 UTC [100] postgres@testdb psql: LOG:  statement: SELECT a",
+            "    FROM t1",
+            "2024-08-15 10:30:19.680 UTC [100] postgres@testdb psql: LOG:  duration: 1.000 ms",
+            "not a log line",
+            "2024-08-15 10:30:19.681 UTC [200] postgres@testdb psql: LOG:  statement: SELECT c",
+            "2024-08-15 10:30:19.682 UTC [200] postgres@testdb psql: LOG:  duration: 2.000 ms",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let (entries, report) = TextLogParser::new()
+            .with_max_entries(Some(2))
+            .parse_lines_with_report(&lines);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "statement: SELECT a FROM t1");
+        assert_eq!(report.total_lines, 4);
+        let truncation = report.truncation.as_ref().unwrap();
+        assert_eq!((truncation.max_entries, truncation.line_number), (2, 4));
+        assert_eq!(truncation.file, None);
+
+        // Reaching the limit with nothing left to parse is not a truncation.
+        let (entries, report) = TextLogParser::new()
+            .with_max_entries(Some(4))
+            .parse_lines_with_report(&lines);
+        assert_eq!(entries.len(), 4);
+        assert!(report.truncation.is_none());
     }
 
     #[test]
@@ -247,6 +494,9 @@ mod parser_unit_tests {
             ("ERROR", LogLevel::Error),
             ("FATAL", LogLevel::Fatal),
             ("PANIC", LogLevel::Panic),
+            ("DETAIL", LogLevel::Detail),
+            ("HINT", LogLevel::Hint),
+            ("CONTEXT", LogLevel::Context),
             ("LOG", LogLevel::Log),
         ];
 
@@ -263,6 +513,87 @@ mod parser_unit_tests {
         }
     }
 
+    #[test]
+    fn test_fatal_and_panic_entries_are_errors() {
+        let mut parser = TextLogParser::new();
+
+        for (level, is_error) in [
+            ("ERROR", true),
+            ("FATAL", true),
+            ("PANIC", true),
+            ("WARNING", false),
+            ("DETAIL", false),
+        ] {
+            let line = format!(
+                "2024-08-15 10:30:15.123 UTC [12345] postgres@testdb psql: {}:  test message",
+                level
+            );
+            let entry = parser.parse_line(&line).unwrap().unwrap();
+            assert_eq!(entry.is_error(), is_error, "{level}");
+            assert_eq!(entry.message_type.to_string(), level);
+        }
+    }
+
+    #[test]
+    fn test_log_entry_builder_fills_fields_and_rejects_empty_process_id() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 8, 15, 10, 30, 15).unwrap();
+
+        let statement = LogEntryBuilder::statement(timestamp, "12345", "SELECT * FROM users")
+            .with_user(Some("postgres"))
+            .with_session_line(Some(3), Some(1))
+            .build()
+            .unwrap();
+        assert!(statement.is_query());
+        assert_eq!(statement.message, "statement: SELECT * FROM users");
+        assert_eq!(statement.user.as_deref(), Some("postgres"));
+        assert_eq!(statement.session_line, Some(3));
+        assert_eq!(
+            statement.normalized_query().as_deref(),
+            Some("SELECT * FROM users")
+        );
+
+        let duration = LogEntryBuilder::duration(timestamp, "12345", 45.123)
+            .build()
+            .unwrap();
+        assert!(duration.is_duration());
+        assert_eq!(duration.message, "duration: 45.123 ms");
+        assert_eq!(duration.duration, Some(45.123));
+
+        let error = LogEntry::builder(timestamp, "", LogLevel::Error, "division by zero")
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("empty process id"));
+        assert!(matches!(
+            error,
+            PgLogstatsError::Configuration { field: Some(ref field), .. } if field == "process_id"
+        ));
+    }
+
+    #[test]
+    fn test_log_level_deserializes_levels_stored_as_unknown() {
+        for (json, expected) in [
+            (r#"{"Unknown":"FATAL"}"#, LogLevel::Fatal),
+            (r#"{"Unknown":"PANIC"}"#, LogLevel::Panic),
+            (r#"{"Unknown":"DETAIL"}"#, LogLevel::Detail),
+            (
+                r#"{"Unknown":"LOCATION"}"#,
+                LogLevel::Unknown("LOCATION".to_string()),
+            ),
+            (r#""Fatal""#, LogLevel::Fatal),
+            (r#""Hint""#, LogLevel::Hint),
+        ] {
+            let level: LogLevel = serde_json::from_str(json).unwrap();
+            assert_eq!(level, expected, "{json}");
+        }
+
+        let round_trip = serde_json::to_string(&LogLevel::Context).unwrap();
+        assert_eq!(round_trip, r#""Context""#);
+        assert_eq!(
+            serde_json::from_str::<LogLevel>(&round_trip).unwrap(),
+            LogLevel::Context
+        );
+    }
+
     #[test]
     fn test_parse_complex_query_with_special_characters() {
         let mut parser = TextLogParser::new();
@@ -293,11 +624,18 @@ mod parser_unit_tests {
             entry.message,
             "statement: SELECT * FROM users WHERE id = $1"
         );
+        assert_eq!(entry.prepared_statement.as_deref(), Some("<unnamed>"));
         let queries = entry.queries.unwrap();
         assert_eq!(
             queries[0].normalized_query,
             "SELECT * FROM users WHERE id = ?"
         );
+
+        // The portal is not part of the statement name
+        let line = "2024-08-15 10:30:25.555 UTC [12355] postgres@testdb psql: LOG:  duration: 1.250 ms  execute S_3/C_4: SELECT 1";
+        let entry = parser.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.duration, Some(1.25));
+        assert_eq!(entry.prepared_statement.as_deref(), Some("S_3"));
     }
 
     #[test]
@@ -322,6 +660,46 @@ mod parser_unit_tests {
         );
     }
 
+    #[test]
+    fn test_parse_aws_rds_keeps_client_port() {
+        let mut parser = TextLogParser::with_format(TextLogFormat::AwsRds);
+        let line = "2019-09-24 17:19:25 UTC:172.31.10.173(53224):username@database:[12829]:LOG:  statement: SELECT 1";
+
+        let entry = parser.parse_line(line).unwrap().unwrap();
+
+        assert_eq!(entry.client_host.as_deref(), Some("172.31.10.173"));
+        assert_eq!(entry.client_port, Some(53224));
+    }
+
+    #[test]
+    fn test_connection_received_names_the_session_client() {
+        let lines: Vec<String> = [
+            "2024-01-15 10:00:00.000 UTC [3001] [unknown]@[unknown] [unknown]: LOG:  connection received: host=10.0.0.5 port=51234",
+            "2024-01-15 10:00:01.000 UTC [3001] app@appdb api: LOG:  statement: SELECT 1;",
+            "2024-01-15 10:00:01.012 UTC [3001] app@appdb api: LOG:  duration: 12.500 ms",
+            "2024-01-15 10:00:02.000 UTC [3002] app@appdb api: LOG:  statement: SELECT 2;",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        let clients: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.client_host.as_deref(), entry.client_port))
+            .collect();
+        assert_eq!(
+            clients,
+            vec![
+                (Some("10.0.0.5"), Some(51234)),
+                (Some("10.0.0.5"), Some(51234)),
+                (Some("10.0.0.5"), Some(51234)),
+                (None, None),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_aws_rds_combined_duration_statement() {
         let mut parser = TextLogParser::with_format(TextLogFormat::AwsRds);
@@ -379,7 +757,7 @@ mod parser_unit_tests {
             "    continuation line without pending statement",
         ];
 
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
         let result = parser.parse_lines(&lines.iter().map(|s| s.to_string()).collect::<Vec<_>>());
         assert!(result.is_ok());
 
@@ -387,6 +765,159 @@ mod parser_unit_tests {
         assert_eq!(entries.len(), 2); // Should parse 2 valid lines, skip invalid ones
     }
 
+    #[test]
+    fn test_parse_lines_with_report_counts_skip_reasons() {
+        let lines = [
+            "2024-08-15 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  statement: SELECT * FROM users;",
+            "This is not a valid log line",
+            "",
+            "2024-13-45 10:30:15.456 UTC [12345] postgres@testdb psql: LOG:  duration: 45.123 ms",
+            "    continuation line without pending statement",
+            "2024-08-15 10:30:15.456 UTC [12345] postgres@testdb psql: LOG:  duration: 45.123 ms",
+        ];
+
+        let mut parser = TextLogParser::new();
+        let (entries, report) = parser
+            .parse_lines_with_report(&lines.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(report.total_lines, 6);
+        assert_eq!(report.parsed_entries, 2);
+        assert_eq!(report.skipped.empty, 1);
+        assert_eq!(report.skipped.regex_no_match, 1);
+        assert_eq!(report.skipped.timestamp_error, 1);
+        assert_eq!(report.skipped.continuation_without_pending, 1);
+        assert_eq!(report.skipped_lines(), 4);
+        assert_eq!(report.unparsed_lines(), 3);
+
+        assert_eq!(report.skipped_samples.len(), 4);
+        assert_eq!(report.skipped_samples[0].line_number, 2);
+        assert_eq!(report.skipped_samples[0].reason, SkipReason::RegexNoMatch);
+        assert_eq!(report.skipped_samples[2].line_number, 4);
+        assert_eq!(report.skipped_samples[2].reason, SkipReason::TimestampError);
+        // Lines 1-3 take 96, 29, and 1 bytes with their line ends
+        assert_eq!(report.skipped_samples[2].byte_offset, Some(126));
+        assert_eq!(
+            report.first_error(),
+            Some("line 4: Timestamp parse error: Failed to parse timestamp")
+        );
+        assert_eq!(report.skipped_samples[0].error, None);
+    }
+
+    #[test]
+    fn test_timestamp_errors_name_the_file_and_line() {
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+        writeln!(
+            file,
+            "2024-08-15 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  statement: SELECT 1"
+        )
+        .unwrap();
+        writeln!(
+            file,
+            "2024-13-45 10:30:15.456 UTC [12345] postgres@testdb psql: LOG:  duration: 45.123 ms"
+        )
+        .unwrap();
+        let path = file.path().display().to_string();
+
+        let (_, report) = TextLogParser::new().parse_file(file.path()).unwrap();
+
+        let sample = &report.skipped_samples[0];
+        assert_eq!(sample.file.as_deref(), Some(path.as_str()));
+        assert_eq!(sample.line_number, 2);
+        assert_eq!(sample.byte_offset, Some(84));
+        assert_eq!(
+            report.first_error(),
+            Some(
+                format!(
+                    "{}:2: Timestamp parse error: Failed to parse timestamp",
+                    path
+                )
+                .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_with_report_limits_samples() {
+        let lines: Vec<String> = (0..25).map(|i| format!("garbage line {}", i)).collect();
+
+        let mut parser = TextLogParser::new();
+        let (entries, report) = parser.parse_lines_with_report(&lines);
+
+        assert!(entries.is_empty());
+        assert_eq!(report.skipped.regex_no_match, 25);
+        assert_eq!(report.skipped_samples.len(), report.sample_limit);
+        assert!(report
+            .warning_summary()
+            .unwrap()
+            .contains("parsed 0 entries from 25 lines"));
+    }
+
+    #[test]
+    fn test_parse_report_merge() {
+        let mut parser = TextLogParser::new();
+        let (_, mut report) = parser.parse_lines_with_report(&[
+            "2024-08-15 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  statement: SELECT 1;"
+                .to_string(),
+            "not a log line".to_string(),
+        ]);
+        let (_, other) = parser.parse_lines_with_report(&["".to_string()]);
+
+        report.merge(other);
+
+        assert_eq!(report.total_lines, 3);
+        assert_eq!(report.parsed_entries, 1);
+        assert_eq!(report.skipped.regex_no_match, 1);
+        assert_eq!(report.skipped.empty, 1);
+        assert_eq!(report.skipped_samples.len(), 2);
+    }
+
+    #[test]
+    fn test_out_of_order_entries_are_counted_and_sorted() {
+        use pg_logstats::LogParser;
+
+        let lines: Vec<String> = include_str!("../fixtures/cli/skewed_hosts.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let mut parsed = TextLogParser::new().parse_log_lines(&lines);
+
+        // Jumps of under a second, as between backends, are not counted
+        assert_eq!(parsed.parse_report.out_of_order_entries, 6);
+        assert_eq!(parsed.parse_report.max_backwards_jump_ms, 180_040);
+        assert!(parsed
+            .parse_report
+            .clock_skew_warning()
+            .unwrap()
+            .contains("--sort-entries"));
+
+        parsed.sort_by_timestamp();
+        assert!(parsed
+            .entries
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        assert!(parsed
+            .parse_report
+            .clock_skew_warning()
+            .unwrap()
+            .contains("sorted entries by timestamp"));
+    }
+
+    #[test]
+    fn test_parse_report_no_warning_for_blank_lines() {
+        let mut parser = TextLogParser::new();
+        let (_, report) = parser.parse_lines_with_report(&[
+            "".to_string(),
+            "2024-08-15 10:30:15.123 UTC [12345] postgres@testdb psql: LOG:  statement: SELECT 1;"
+                .to_string(),
+        ]);
+
+        assert_eq!(report.skipped.empty, 1);
+        assert!(report.warning_summary().is_none());
+    }
+
     #[test]
     fn test_extract_duration() {
         let parser = TextLogParser::new();
@@ -396,6 +927,222 @@ mod parser_unit_tests {
         assert_eq!(parser.extract_duration("duration: 0.001 ms"), Some(0.001));
         assert_eq!(parser.extract_duration("no duration here"), None);
         assert_eq!(parser.extract_duration("duration: invalid ms"), None);
+        assert_eq!(parser.extract_duration("duration: 1.5 s"), Some(1500.0));
+        assert_eq!(
+            parser.extract_duration("session time: 0:00:01.250"),
+            Some(1250.0)
+        );
+    }
+
+    #[test]
+    fn test_maintenance_messages_carry_durations_in_milliseconds() {
+        let lines: Vec<String> = include_str!("../fixtures/cli/maintenance_durations.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        let durations: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.message_type.clone(), entry.duration))
+            .collect();
+        assert_eq!(
+            durations,
+            vec![
+                (LogLevel::Statement, None),
+                (LogLevel::Duration, Some(20.0)),
+                (LogLevel::Log, Some(12_340.0)),
+                (LogLevel::Log, Some(45_678.0)),
+                (LogLevel::Log, Some(202_456.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stderr_combined_duration_statement() {
+        let mut parser = TextLogParser::new();
+        let line = "2024-08-15 10:00:00.100 UTC [4101] app@shop checkout: LOG:  duration: 123.456 ms  statement: SELECT * FROM orders WHERE id = 42";
+
+        let entry = parser.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.message_type, LogLevel::Statement);
+        assert_eq!(entry.duration, Some(123.456));
+        assert_eq!(
+            entry.message,
+            "statement: SELECT * FROM orders WHERE id = 42"
+        );
+        assert_eq!(
+            entry.queries.unwrap()[0].normalized_query,
+            "SELECT * FROM orders WHERE id = ?"
+        );
+    }
+
+    #[test]
+    fn test_log_min_duration_lines_carry_statement_and_duration() {
+        let lines: Vec<String> = include_str!("../fixtures/cli/log_min_duration.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        let statements: Vec<_> = entries
+            .iter()
+            .filter(|entry| entry.is_query())
+            .map(|entry| {
+                (
+                    entry.queries.as_ref().unwrap()[0].normalized_query.as_str(),
+                    entry.duration,
+                )
+            })
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                (
+                    "SELECT * FROM orders WHERE customer_id = ? ORDER BY created_at DESC",
+                    Some(1523.456)
+                ),
+                ("UPDATE orders SET status = ? WHERE id = ?", Some(2500.0)),
+                (
+                    "SELECT c.name, count(*) FROM customers AS c JOIN orders AS o ON o.customer_id = c.id GROUP BY c.name",
+                    Some(1204.812)
+                ),
+                (
+                    "SELECT * FROM orders WHERE customer_id = ? ORDER BY created_at DESC",
+                    Some(15.0)
+                ),
+            ]
+        );
+        // Parse and bind times are not standalone durations
+        assert!(!entries.iter().any(|entry| entry.is_duration()));
+    }
+
+    #[test]
+    fn test_statements_sqlparser_rejects_fall_back_to_text_normalization() {
+        let lines: Vec<String> = include_str!("../fixtures/cli/postgres_specific.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let (entries, report) = TextLogParser::new().parse_lines_with_report(&lines);
+
+        let statements: Vec<_> = entries
+            .iter()
+            .filter(|entry| entry.is_query())
+            .map(|entry| {
+                let queries = entry.queries.as_ref().unwrap();
+                assert_eq!(queries.len(), 1);
+                (
+                    queries[0].query_type.clone(),
+                    queries[0].normalized_query.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                (QueryType::Set, "SET search_path = staging, public"),
+                (
+                    QueryType::Copy,
+                    "COPY staging.orders (id, total) FROM STDIN WITH (FORMAT csv)"
+                ),
+                (QueryType::Other, "DO $$ BEGIN PERFORM pg_sleep(?); END $$"),
+                (
+                    QueryType::Explain,
+                    "EXPLAIN ANALYZE SELECT * FROM staging.orders WHERE id = ?"
+                ),
+                (QueryType::Other, "LISTEN order_events"),
+                (QueryType::Other, "NOTIFY order_events, ?"),
+                (QueryType::Select, "SELECT count(*) FROM staging.orders"),
+            ]
+        );
+        // COPY, DO, LISTEN, and NOTIFY are not understood by sqlparser
+        assert_eq!(report.fallback_statements, 4);
+        assert_eq!(report.continuation_lines, 4);
+    }
+
+    #[test]
+    fn test_error_follow_up_lines_are_folded_per_process() {
+        let lines: Vec<String> = include_str!("../fixtures/cli/error_groups.log")
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let (entries, report) = TextLogParser::new().parse_lines_with_report(&lines);
+
+        let errors: Vec<_> = entries.iter().filter(|entry| entry.is_error()).collect();
+        assert_eq!(errors.len(), 6);
+
+        // Interleaved errors from 5101 and 5102 keep their own follow-ups
+        assert_eq!(errors[0].process_id, "5101");
+        assert_eq!(errors[0].sqlstate.as_deref(), Some("23505"));
+        assert_eq!(
+            errors[0].detail.as_deref(),
+            Some("Key (id)=(42) already exists.")
+        );
+        assert_eq!(
+            errors[0].statement.as_deref(),
+            Some("INSERT INTO orders (id, total) VALUES (42, 10.5)")
+        );
+        assert_eq!(
+            errors[0].queries.as_ref().unwrap()[0].normalized_query,
+            "INSERT INTO orders (id, total) VALUES (?, ?)"
+        );
+        assert_eq!(errors[1].process_id, "5102");
+        assert_eq!(errors[1].detail, None);
+        assert_eq!(
+            errors[1].statement.as_deref(),
+            Some("SELECT * FROM invoices_old WHERE customer_id = 7")
+        );
+
+        // A statement that does not parse is normalized as text
+        assert_eq!(errors[2].statement.as_deref(), Some("SELECT 1 FORM dual"));
+        assert_eq!(
+            errors[2].queries.as_ref().unwrap()[0].normalized_query,
+            "SELECT ? FORM dual"
+        );
+
+        assert_eq!(
+            errors[4].detail.as_deref(),
+            Some(
+                "Process 5104 waits for ShareLock on transaction 1001; blocked by process 5105. \
+                 Process 5105 waits for ShareLock on transaction 1000; blocked by process 5104."
+            )
+        );
+        assert_eq!(
+            errors[4].hint.as_deref(),
+            Some("See server log for query details.")
+        );
+        assert_eq!(
+            errors[4].context.as_deref(),
+            Some("while updating tuple (0,1) in relation \"orders\"")
+        );
+        assert_eq!(errors[5].message_type, LogLevel::Fatal);
+
+        // STATEMENT lines are not executed queries, and the parameters line
+        // after a statement from the same process goes to that statement
+        let queries: Vec<_> = entries.iter().filter(|entry| entry.is_query()).collect();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[1].detail.as_deref(), Some("parameters: $1 = '7'"));
+        assert!(!entries
+            .iter()
+            .any(|entry| entry.message_type == LogLevel::Detail));
+        assert_eq!(report.continuation_lines, 13);
+        assert_eq!(report.unparsed_lines(), 0);
+    }
+
+    #[test]
+    fn test_follow_up_line_after_another_entry_from_the_process_is_not_folded() {
+        let lines: Vec<String> = [
+            "2024-08-15 10:00:00.000 UTC [7001] app@shop psql: ERROR:  division by zero",
+            "2024-08-15 10:00:00.001 UTC [7001] app@shop psql: LOG:  checkpoint starting: time",
+            "2024-08-15 10:00:00.002 UTC [7001] app@shop psql: HINT:  Too late for the error.",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        let entries = TextLogParser::new().parse_lines(&lines).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].hint, None);
+        assert_eq!(entries[2].message_type, LogLevel::Hint);
     }
 
     #[test]
@@ -494,7 +1241,7 @@ mod parser_unit_tests {
 
     #[test]
     fn test_performance_with_large_input() {
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
 
         // Create a large number of log lines
         let mut lines = Vec::new();
@@ -552,7 +1299,7 @@ mod property_based_tests {
     /// Property: All valid log entries should have required fields
     #[test]
     fn property_valid_entries_have_required_fields() {
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
         let test_lines = create_test_lines();
 
         let result = parser.parse_lines(&test_lines);
@@ -591,7 +1338,7 @@ mod property_based_tests {
 
         for perm in permutations {
             let lines: Vec<String> = perm.iter().map(|&i| base_lines[i].to_string()).collect();
-            let parser = TextLogParser::new();
+            let mut parser = TextLogParser::new();
             let result = parser.parse_lines(&lines);
 
             assert!(result.is_ok());
@@ -603,7 +1350,7 @@ mod property_based_tests {
     /// Property: Unique process IDs should be preserved
     #[test]
     fn property_process_ids_preserved() {
-        let parser = TextLogParser::new();
+        let mut parser = TextLogParser::new();
         let test_lines = create_test_lines();
 
         let result = parser.parse_lines(&test_lines);